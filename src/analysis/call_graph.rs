@@ -0,0 +1,103 @@
+use crate::parser::expression::Expression;
+
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    /// line of the definition, or of the first prototype of a function that isn't defined
+    pub line: Option<u16>,
+    /// `false` for functions that are only declared, like the ones of libc
+    pub defined: bool,
+}
+
+/// Calls from one function to another, by their indices in `CallGraph::functions`
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub caller: usize,
+    pub callee: usize,
+    /// lines of every call, in source order
+    pub lines: Vec<u16>,
+}
+
+/// Which function definitions call which functions. Only calls of a name are seen, calls
+/// through pointers can't be followed without running the program.
+#[derive(Debug, Clone, Default)]
+pub struct CallGraph {
+    /// definitions in source order, then functions that are called without being defined
+    pub functions: Vec<Function>,
+    pub calls: Vec<Call>,
+}
+
+impl CallGraph {
+    pub fn build(expressions: &[Expression]) -> Self {
+        let mut graph = CallGraph::default();
+        for expression in expressions {
+            if let Expression::Function {
+                name, debug_data, ..
+            } = expression
+            {
+                graph.functions.push(Function {
+                    name: name.to_owned(),
+                    line: Some(debug_data.line),
+                    defined: true,
+                });
+            }
+        }
+        let prototypes: Vec<_> = expressions
+            .iter()
+            .filter_map(|expression| match expression {
+                Expression::FunctionDeclaration {
+                    name, debug_data, ..
+                } => Some((name, debug_data.line)),
+                _ => None,
+            })
+            .collect();
+
+        for expression in expressions {
+            let Expression::Function { name, inside, .. } = expression else {
+                continue;
+            };
+            let caller = graph.index(name).expect("definitions were added above");
+            let mut work: Vec<&Expression> = inside.iter().rev().collect();
+            while let Some(expression) = work.pop() {
+                if let Expression::FunctionCall {
+                    left, debug_data, ..
+                } = expression
+                    && let Expression::Identifier(callee, _) = left.as_ref()
+                {
+                    let callee = graph.index(callee).unwrap_or_else(|| {
+                        let line = prototypes
+                            .iter()
+                            .find(|(name, _)| *name == callee)
+                            .map(|(_, line)| *line);
+                        graph.functions.push(Function {
+                            name: callee.to_owned(),
+                            line,
+                            defined: false,
+                        });
+                        graph.functions.len() - 1
+                    });
+                    match graph
+                        .calls
+                        .iter_mut()
+                        .find(|call| call.caller == caller && call.callee == callee)
+                    {
+                        Some(call) => call.lines.push(debug_data.line),
+                        None => graph.calls.push(Call {
+                            caller,
+                            callee,
+                            lines: vec![debug_data.line],
+                        }),
+                    }
+                }
+                work.extend(expression.children().into_iter().rev());
+            }
+        }
+        graph
+    }
+
+    fn index(&self, name: &str) -> Option<usize> {
+        self.functions
+            .iter()
+            .position(|function| function.name == name)
+    }
+}
@@ -0,0 +1,348 @@
+use crate::parser::expression::Expression;
+
+pub type BlockId = usize;
+
+/// where every function starts, it holds the statements before the first branch
+pub const ENTRY: BlockId = 0;
+/// where `return` and falling off the end of the function go, it has no statements
+pub const EXIT: BlockId = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    Always,
+    /// taken when the condition of the block isn't zero
+    True,
+    False,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub target: BlockId,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Block<'a> {
+    /// statements that run one after another, `if`, `while` and `for` are split into blocks and
+    /// their conditions
+    pub statements: Vec<&'a Expression>,
+    /// evaluated after the statements, it picks between the `True` and `False` edges
+    pub condition: Option<&'a Expression>,
+    pub successors: Vec<Edge>,
+}
+
+/// Control-flow graph of one function, built from the syntax tree. Conditions that are
+/// constants only get the edge that is taken, so `while (1)` without a `break` never reaches
+/// what is after it.
+#[derive(Debug, Clone)]
+pub struct Cfg<'a> {
+    pub function: &'a str,
+    /// line of the function definition
+    pub line: u16,
+    pub blocks: Vec<Block<'a>>,
+}
+
+impl<'a> Cfg<'a> {
+    /// Graphs of every function definition in the program, in source order
+    pub fn functions(expressions: &'a [Expression]) -> Vec<Cfg<'a>> {
+        expressions.iter().filter_map(Cfg::build).collect()
+    }
+
+    /// `None` when the expression isn't a function definition
+    pub fn build(function: &'a Expression) -> Option<Self> {
+        let Expression::Function {
+            name,
+            inside,
+            debug_data,
+            ..
+        } = function
+        else {
+            return None;
+        };
+        let mut builder = Builder {
+            blocks: vec![Block::default(), Block::default()],
+            current: ENTRY,
+            loops: Vec::new(),
+        };
+        builder.statements(inside);
+        builder.edge(builder.current, EXIT, EdgeKind::Always);
+        let mut cfg = Cfg {
+            function: name,
+            line: debug_data.line,
+            blocks: builder.blocks,
+        };
+        cfg.simplify();
+        Some(cfg)
+    }
+
+    /// Blocks that jump to each block
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut predecessors = vec![Vec::new(); self.blocks.len()];
+        for (id, block) in self.blocks.iter().enumerate() {
+            for edge in &block.successors {
+                predecessors[edge.target].push(id);
+            }
+        }
+        predecessors
+    }
+
+    /// Blocks that can run, starting from the entry
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut work = vec![ENTRY];
+        while let Some(id) = work.pop() {
+            if std::mem::replace(&mut reachable[id], true) {
+                continue;
+            }
+            work.extend(self.blocks[id].successors.iter().map(|edge| edge.target));
+        }
+        reachable
+    }
+
+    /// Skips empty blocks that only jump somewhere else, drops the ones that nothing jumps to
+    /// and numbers the rest in the order a depth-first walk from the entry finds them, blocks
+    /// that can't run come last
+    fn simplify(&mut self) {
+        for id in EXIT + 1..self.blocks.len() {
+            let block = &self.blocks[id];
+            let [edge] = block.successors[..] else {
+                continue;
+            };
+            if !block.statements.is_empty() || block.condition.is_some() || edge.target == id {
+                continue;
+            }
+            for block in &mut self.blocks {
+                for successor in &mut block.successors {
+                    if successor.target == id {
+                        successor.target = edge.target;
+                    }
+                }
+            }
+            self.blocks[id].successors.clear();
+        }
+
+        let predecessors = self.predecessors();
+        let kept = |id: BlockId| {
+            !predecessors[id].is_empty()
+                || !self.blocks[id].statements.is_empty()
+                || self.blocks[id].condition.is_some()
+        };
+        let mut order = vec![ENTRY, EXIT];
+        let mut seen = vec![false; self.blocks.len()];
+        seen[ENTRY] = true;
+        seen[EXIT] = true;
+        let mut work = vec![ENTRY];
+        while let Some(id) = work.pop() {
+            if id != ENTRY {
+                order.push(id);
+            }
+            for edge in self.blocks[id].successors.iter().rev() {
+                if !std::mem::replace(&mut seen[edge.target], true) {
+                    work.push(edge.target);
+                }
+            }
+        }
+        order.extend((EXIT + 1..self.blocks.len()).filter(|&id| !seen[id] && kept(id)));
+
+        let mut renumbered = vec![0; self.blocks.len()];
+        for (new, &old) in order.iter().enumerate() {
+            renumbered[old] = new;
+        }
+        let mut blocks: Vec<Option<Block>> = std::mem::take(&mut self.blocks)
+            .into_iter()
+            .map(Some)
+            .collect();
+        self.blocks = order
+            .iter()
+            .map(|&id| {
+                let mut block = blocks[id].take().expect("every block is in the order once");
+                for edge in &mut block.successors {
+                    edge.target = renumbered[edge.target];
+                }
+                block
+            })
+            .collect();
+    }
+}
+
+/// Value of a condition that is a constant: `1`, `true`, `(0)`
+pub fn constant_condition(condition: &Expression) -> Option<bool> {
+    match condition {
+        Expression::Number(value, ..) => Some(*value != 0),
+        Expression::Boolean(value, _) => Some(*value),
+        Expression::Grouping { value, .. } => constant_condition(value),
+        _ => None,
+    }
+}
+
+struct Builder<'a> {
+    blocks: Vec<Block<'a>>,
+    /// block that the next statement goes into
+    current: BlockId,
+    /// where `break` goes in each loop around the current statement
+    loops: Vec<BlockId>,
+}
+
+impl<'a> Builder<'a> {
+    fn block(&mut self) -> BlockId {
+        self.blocks.push(Block::default());
+        self.blocks.len() - 1
+    }
+
+    fn edge(&mut self, from: BlockId, target: BlockId, kind: EdgeKind) {
+        self.blocks[from].successors.push(Edge { target, kind });
+    }
+
+    /// Ends the current block with the condition, the result is the block of the `True` edge
+    /// and the one that needs the `False` edge, if it can be taken
+    fn branch(&mut self, condition: &'a Expression) -> (BlockId, Option<BlockId>) {
+        let from = self.current;
+        let then = self.block();
+        match constant_condition(condition) {
+            Some(true) => {
+                self.edge(from, then, EdgeKind::Always);
+                (then, None)
+            }
+            Some(false) => (then, Some(from)),
+            None => {
+                self.blocks[from].condition = Some(condition);
+                self.edge(from, then, EdgeKind::True);
+                (then, Some(from))
+            }
+        }
+    }
+
+    /// Adds the `False` edge of a block that `branch` returned
+    fn otherwise(&mut self, from: BlockId, target: BlockId) {
+        let kind = match self.blocks[from].condition {
+            Some(_) => EdgeKind::False,
+            None => EdgeKind::Always,
+        };
+        self.edge(from, target, kind);
+    }
+
+    fn statements(&mut self, statements: &'a [Expression]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &'a Expression) {
+        match statement {
+            Expression::Skip => {}
+            Expression::NewCodeBlock { inside, .. } => self.statements(inside),
+            Expression::If {
+                condition,
+                inside,
+                chained_elses,
+                ..
+            } => {
+                let join = self.block();
+                let (then, mut otherwise) = self.branch(condition);
+                self.current = then;
+                self.statements(inside);
+                self.edge(self.current, join, EdgeKind::Always);
+                for chained in chained_elses {
+                    let Expression::Else {
+                        condition, inside, ..
+                    } = chained
+                    else {
+                        continue;
+                    };
+                    let Some(from) = otherwise else {
+                        break;
+                    };
+                    let next = self.block();
+                    self.otherwise(from, next);
+                    self.current = next;
+                    match condition {
+                        Some(condition) => {
+                            let (then, next_otherwise) = self.branch(condition);
+                            self.current = then;
+                            otherwise = next_otherwise;
+                        }
+                        None => otherwise = None,
+                    }
+                    self.statements(inside);
+                    self.edge(self.current, join, EdgeKind::Always);
+                }
+                if let Some(from) = otherwise {
+                    self.otherwise(from, join);
+                }
+                self.current = join;
+            }
+            Expression::While {
+                condition, inside, ..
+            } => {
+                let header = self.block();
+                self.edge(self.current, header, EdgeKind::Always);
+                self.current = header;
+                let (body, otherwise) = self.branch(condition);
+                self.looped(body, header, otherwise, inside, None);
+            }
+            Expression::For {
+                iterator_init,
+                condition,
+                incr,
+                inside,
+                ..
+            } => {
+                self.statement(iterator_init);
+                let header = self.block();
+                self.edge(self.current, header, EdgeKind::Always);
+                self.current = header;
+                let (body, otherwise) = match condition.as_ref() {
+                    // `for (;;)`
+                    Expression::Skip => {
+                        let body = self.block();
+                        self.edge(header, body, EdgeKind::Always);
+                        (body, None)
+                    }
+                    condition => self.branch(condition),
+                };
+                self.looped(body, header, otherwise, inside, Some(incr));
+            }
+            Expression::Return { .. } => {
+                self.blocks[self.current].statements.push(statement);
+                self.edge(self.current, EXIT, EdgeKind::Always);
+                self.current = self.block();
+            }
+            Expression::Break { .. } => {
+                self.blocks[self.current].statements.push(statement);
+                // outside of a loop it does nothing that the graph could show
+                if let Some(&after) = self.loops.last() {
+                    self.edge(self.current, after, EdgeKind::Always);
+                    self.current = self.block();
+                }
+            }
+            statement => self.blocks[self.current].statements.push(statement),
+        }
+    }
+
+    /// Body of a loop whose condition is at the end of `header`, `step` runs after the body
+    fn looped(
+        &mut self,
+        body: BlockId,
+        header: BlockId,
+        otherwise: Option<BlockId>,
+        inside: &'a [Expression],
+        step: Option<&'a Expression>,
+    ) {
+        let after = self.block();
+        if let Some(from) = otherwise {
+            self.otherwise(from, after);
+        }
+        self.loops.push(after);
+        self.current = body;
+        self.statements(inside);
+        if let Some(step) = step {
+            let block = self.block();
+            self.edge(self.current, block, EdgeKind::Always);
+            self.current = block;
+            self.statement(step);
+        }
+        self.edge(self.current, header, EdgeKind::Always);
+        self.loops.pop();
+        self.current = after;
+    }
+}
@@ -0,0 +1,448 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{
+    analysis::{
+        self,
+        cfg::{BlockId, Cfg, ENTRY},
+    },
+    diagnostics::Diagnostic,
+    lexer::token::TokenKind,
+    parser::expression::{DebugData, Expression},
+    semantic::{
+        scope::{BUILTIN_FUNCTIONS, ScopeKind, SymbolId, SymbolKind, SymbolTable},
+        type_check,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Store {
+    /// `int x = 1;`
+    Initializer,
+    /// `x = 1;`, `x += 1;`
+    Assignment,
+    /// `x++`, `x--`
+    Step,
+}
+
+/// What a statement does with a tracked variable, in the order it happens
+#[derive(Debug, Clone, Copy)]
+enum Event<'a> {
+    /// `int x;`, the variable has no value again, which matters in loops
+    Declare(SymbolId),
+    Read(SymbolId, &'a DebugData),
+    Write(SymbolId, Store, &'a DebugData),
+}
+
+/// Definite assignment and liveness of the local variables of every function definition:
+/// reads of variables that may not have a value yet, stores that nothing reads and variables
+/// that are never used. Variables whose address is taken, `static` ones, arrays and structs
+/// are only checked for being used at all.
+pub fn check(expressions: &[Expression], symbols: &SymbolTable) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for function in expressions {
+        let Some(cfg) = Cfg::build(function) else {
+            continue;
+        };
+        let mut function_diagnostics = Function::new(function, cfg, symbols).check();
+        function_diagnostics.sort_by_key(|diagnostic| diagnostic.debug_data.line);
+        diagnostics.extend(function_diagnostics);
+    }
+    diagnostics
+}
+
+struct Function<'a> {
+    cfg: Cfg<'a>,
+    symbols: &'a SymbolTable,
+    parameters: Vec<SymbolId>,
+    /// local variables declared in the function, in source order
+    locals: Vec<SymbolId>,
+    /// scalars that the analysis follows, parameters included
+    tracked: HashSet<SymbolId>,
+    events: Vec<Vec<Event<'a>>>,
+    reachable: Vec<bool>,
+    predecessors: Vec<Vec<BlockId>>,
+}
+
+impl<'a> Function<'a> {
+    fn new(function: &'a Expression, cfg: Cfg<'a>, symbols: &'a SymbolTable) -> Self {
+        let parameters = symbols
+            .parameters
+            .get(&function.id())
+            .cloned()
+            .unwrap_or_default();
+        let mut locals = Vec::new();
+        let mut statics: HashSet<SymbolId> = HashSet::new();
+        let mut taken = HashSet::new();
+        let mut work = vec![function];
+        while let Some(expression) = work.pop() {
+            match expression {
+                Expression::VariableDeclaration { .. } => {
+                    locals.extend(symbols.declarations.get(&expression.id()))
+                }
+                Expression::Static { value, .. } => {
+                    if let Expression::Assignment { target, .. } = value.as_ref() {
+                        statics.extend(symbols.declarations.get(&target.id()));
+                    }
+                    statics.extend(symbols.declarations.get(&value.id()));
+                }
+                Expression::AccessReference { value, .. } => {
+                    taken.extend(analysis::root(value, symbols));
+                }
+                _ => {}
+            }
+            work.extend(expression.children().into_iter().rev());
+        }
+        locals.retain(|&id| {
+            let symbol = symbols.symbol(id);
+            symbol.kind == SymbolKind::Variable
+                && symbols.scopes[symbol.scope].kind != ScopeKind::File
+        });
+        let tracked = locals
+            .iter()
+            .chain(&parameters)
+            .copied()
+            .filter(|id| !statics.contains(id) && !taken.contains(id))
+            .filter(|&id| {
+                let data_type = type_check::resolve(symbols, &symbols.symbol(id).data_type);
+                type_check::is_scalar(&data_type)
+            })
+            .collect();
+
+        let mut function = Function {
+            reachable: cfg.reachable(),
+            predecessors: cfg.predecessors(),
+            cfg,
+            symbols,
+            parameters,
+            locals,
+            tracked,
+            events: Vec::new(),
+        };
+        function.events = function
+            .cfg
+            .blocks
+            .iter()
+            .map(|block| {
+                let mut events = Vec::new();
+                for expression in block.statements.iter().chain(&block.condition) {
+                    function.collect(expression, &mut events);
+                }
+                events
+            })
+            .collect();
+        function
+    }
+
+    fn tracked(&self, expression: &Expression) -> Option<SymbolId> {
+        let id = match expression {
+            // `DataTypeAccess` when a variable has the name of a type
+            Expression::Identifier(..) | Expression::DataTypeAccess { .. } => {
+                *self.symbols.resolutions.get(&expression.id())?
+            }
+            Expression::VariableDeclaration { .. } => {
+                *self.symbols.declarations.get(&expression.id())?
+            }
+            _ => return None,
+        };
+        self.tracked.contains(&id).then_some(id)
+    }
+
+    /// Events of the expression in the order it evaluates
+    fn collect(&self, expression: &'a Expression, events: &mut Vec<Event<'a>>) {
+        match expression {
+            Expression::Identifier(_, debug_data)
+            | Expression::DataTypeAccess { debug_data, .. } => {
+                if let Some(id) = self.tracked(expression) {
+                    events.push(Event::Read(id, debug_data));
+                }
+            }
+            Expression::VariableDeclaration { .. } => {
+                if let Some(id) = self.tracked(expression) {
+                    events.push(Event::Declare(id));
+                }
+            }
+            Expression::Assignment {
+                target,
+                operator,
+                value,
+                debug_data,
+            } => match (target.as_ref(), self.tracked(target)) {
+                (Expression::VariableDeclaration { .. }, Some(id)) => {
+                    self.collect(value, events);
+                    events.push(Event::Write(id, Store::Initializer, debug_data));
+                }
+                (Expression::Identifier(_, target_debug_data), Some(id)) => {
+                    if operator.kind != TokenKind::Assignment {
+                        events.push(Event::Read(id, target_debug_data));
+                    }
+                    self.collect(value, events);
+                    events.push(Event::Write(id, Store::Assignment, debug_data));
+                }
+                _ => {
+                    self.collect(target, events);
+                    self.collect(value, events);
+                }
+            },
+            Expression::Increment { target, debug_data }
+            | Expression::Decrement { target, debug_data } => match self.tracked(target) {
+                Some(id) => {
+                    events.push(Event::Read(id, debug_data));
+                    events.push(Event::Write(id, Store::Step, debug_data));
+                }
+                None => self.collect(target, events),
+            },
+            // only the left side of `a.b` and `a->b` names a variable
+            Expression::MemberExpr { left, .. } | Expression::Arrow { left, .. } => {
+                self.collect(left, events)
+            }
+            // `static` variables keep their value between calls, they aren't tracked
+            Expression::Static { .. } => {}
+            // `sizeof(x)` doesn't read `x`
+            Expression::FunctionCall { left, .. } if builtin(left) => {}
+            expression => {
+                for child in expression.children() {
+                    self.collect(child, events);
+                }
+            }
+        }
+    }
+
+    fn name(&self, id: SymbolId) -> &str {
+        &self.symbols.symbol(id).name
+    }
+
+    fn check(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let unused = self.unused(&mut diagnostics);
+        self.uninitialized(&mut diagnostics);
+        self.dead_stores(&unused, &mut diagnostics);
+        diagnostics
+    }
+
+    /// Locals that nothing refers to, and tracked ones that are only written to. The result
+    /// are the variables that got a warning.
+    fn unused(&self, diagnostics: &mut Vec<Diagnostic>) -> HashSet<SymbolId> {
+        let read: HashSet<_> = self
+            .events
+            .iter()
+            .flatten()
+            .filter_map(|event| match event {
+                Event::Read(id, _) => Some(*id),
+                _ => None,
+            })
+            .collect();
+        let mut unused = HashSet::new();
+        for &id in &self.locals {
+            let symbol = self.symbols.symbol(id);
+            let (message, suggestion) = if self.symbols.references_to(id).next().is_none() {
+                (
+                    format!("'{}' is declared but never used", symbol.name),
+                    format!("remove the declaration of '{}'", symbol.name),
+                )
+            } else if self.tracked.contains(&id) && !read.contains(&id) {
+                (
+                    format!("'{}' is assigned but its value is never read", symbol.name),
+                    format!("remove '{}' and the assignments to it", symbol.name),
+                )
+            } else {
+                continue;
+            };
+            unused.insert(id);
+            diagnostics.push(
+                Diagnostic::warning(message, &symbol.debug_data)
+                    .with_span(&symbol.name)
+                    .with_suggestion(suggestion),
+            );
+        }
+        unused
+    }
+
+    /// Forward analysis of which variables have a value on every path, and on some path,
+    /// when each block starts
+    fn assigned(&self, every_path: bool) -> Vec<HashSet<SymbolId>> {
+        let mut assigned: Vec<Option<HashSet<SymbolId>>> = vec![None; self.cfg.blocks.len()];
+        assigned[ENTRY] = Some(self.parameters.iter().copied().collect());
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for id in 0..self.cfg.blocks.len() {
+                if id == ENTRY || !self.reachable[id] {
+                    continue;
+                }
+                let mut incoming = self.predecessors[id]
+                    .iter()
+                    .filter(|&&from| self.reachable[from])
+                    .filter_map(|&from| assigned[from].as_ref().map(|set| self.after(from, set)));
+                let Some(first) = incoming.next() else {
+                    continue;
+                };
+                let set = incoming.fold(first, |set, other| match every_path {
+                    true => set.intersection(&other).copied().collect(),
+                    false => set.union(&other).copied().collect(),
+                });
+                if assigned[id].as_ref() != Some(&set) {
+                    assigned[id] = Some(set);
+                    changed = true;
+                }
+            }
+        }
+        assigned
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect()
+    }
+
+    /// Variables with a value after the block, given the ones when it starts
+    fn after(&self, block: BlockId, assigned: &HashSet<SymbolId>) -> HashSet<SymbolId> {
+        let mut assigned = assigned.clone();
+        for event in &self.events[block] {
+            match *event {
+                Event::Declare(id) => {
+                    assigned.remove(&id);
+                }
+                Event::Write(id, ..) => {
+                    assigned.insert(id);
+                }
+                Event::Read(..) => {}
+            }
+        }
+        assigned
+    }
+
+    fn uninitialized(&self, diagnostics: &mut Vec<Diagnostic>) {
+        let every_path = self.assigned(true);
+        let some_path = self.assigned(false);
+        let mut reported = HashSet::new();
+        for id in 0..self.cfg.blocks.len() {
+            if !self.reachable[id] {
+                continue;
+            }
+            let mut every_path = every_path[id].clone();
+            let mut some_path = some_path[id].clone();
+            for event in &self.events[id] {
+                match *event {
+                    Event::Declare(symbol) => {
+                        every_path.remove(&symbol);
+                        some_path.remove(&symbol);
+                    }
+                    Event::Write(symbol, ..) => {
+                        every_path.insert(symbol);
+                        some_path.insert(symbol);
+                    }
+                    Event::Read(symbol, debug_data) => {
+                        if every_path.contains(&symbol)
+                            || !reported.insert((symbol, debug_data.line))
+                        {
+                            continue;
+                        }
+                        let name = self.name(symbol);
+                        let message = match some_path.contains(&symbol) {
+                            true => format!("'{name}' may be used before it is assigned"),
+                            false => format!("'{name}' is used before it is assigned"),
+                        };
+                        let declaration = self.symbols.symbol(symbol).data_type.declaration(name);
+                        diagnostics.push(
+                            Diagnostic::warning(message, debug_data)
+                                .with_span(name)
+                                .with_suggestion(format!(
+                                    "give it a value where it is declared: `{} = 0;`",
+                                    declaration.trim_end()
+                                )),
+                        );
+                        // one warning is enough for the reads after it
+                        every_path.insert(symbol);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Backward analysis of which variables are read again before they are written, when each
+    /// block ends
+    fn live(&self) -> Vec<HashSet<SymbolId>> {
+        let mut live_out = vec![HashSet::new(); self.cfg.blocks.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for id in (0..self.cfg.blocks.len()).rev() {
+                let set: HashSet<_> = self.cfg.blocks[id]
+                    .successors
+                    .iter()
+                    .flat_map(|edge| self.before(edge.target, &live_out[edge.target]))
+                    .collect();
+                if set != live_out[id] {
+                    live_out[id] = set;
+                    changed = true;
+                }
+            }
+        }
+        live_out
+    }
+
+    /// Variables read again before the block, given the ones after it
+    fn before(&self, block: BlockId, live: &HashSet<SymbolId>) -> HashSet<SymbolId> {
+        let mut live = live.clone();
+        for event in self.events[block].iter().rev() {
+            match *event {
+                Event::Declare(id) | Event::Write(id, ..) => {
+                    live.remove(&id);
+                }
+                Event::Read(id, _) => {
+                    live.insert(id);
+                }
+            }
+        }
+        live
+    }
+
+    fn dead_stores(&self, unused: &HashSet<SymbolId>, diagnostics: &mut Vec<Diagnostic>) {
+        let live_out = self.live();
+        let mut dead = BTreeMap::new();
+        for (id, mut live) in live_out.into_iter().enumerate() {
+            if !self.reachable[id] {
+                continue;
+            }
+            for event in self.events[id].iter().rev() {
+                match *event {
+                    Event::Declare(symbol) => {
+                        live.remove(&symbol);
+                    }
+                    Event::Write(symbol, store, debug_data) => {
+                        if !live.remove(&symbol) && !unused.contains(&symbol) {
+                            dead.insert((debug_data.line, symbol), (store, debug_data));
+                        }
+                    }
+                    Event::Read(symbol, _) => {
+                        live.insert(symbol);
+                    }
+                }
+            }
+        }
+        for ((_, symbol), (store, debug_data)) in dead {
+            let name = self.name(symbol);
+            let (message, suggestion) = match store {
+                Store::Initializer => (
+                    format!("the value '{name}' is initialized with is never read"),
+                    format!("declare '{name}' without a value"),
+                ),
+                Store::Assignment => (
+                    format!("the value assigned to '{name}' is never read"),
+                    "remove the assignment, keep the value only if it has side effects".to_owned(),
+                ),
+                Store::Step => (
+                    format!("'{name}' is changed but the new value is never read"),
+                    format!("remove the change of '{name}'"),
+                ),
+            };
+            diagnostics.push(
+                Diagnostic::warning(message, debug_data)
+                    .with_span(name)
+                    .with_suggestion(suggestion),
+            );
+        }
+    }
+}
+
+fn builtin(function: &Expression) -> bool {
+    matches!(function, Expression::Identifier(name, _) if BUILTIN_FUNCTIONS.contains(&name.as_str()))
+}
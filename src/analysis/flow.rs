@@ -0,0 +1,269 @@
+use std::collections::HashSet;
+
+use crate::{
+    analysis::{
+        self,
+        cfg::{Cfg, EXIT},
+    },
+    diagnostics::Diagnostic,
+    parser::expression::{DebugData, Expression},
+    semantic::{
+        scope::{ScopeKind, SymbolId, SymbolKind, SymbolTable},
+        type_check,
+    },
+};
+
+/// functions that never come back, a call to them leaves a loop like `break` does
+const NO_RETURN_FUNCTIONS: [&str; 5] = ["exit", "_Exit", "abort", "longjmp", "quick_exit"];
+
+/// Checks on the control flow of every function definition: code that can't run, non-void
+/// functions that fall off their end and loops whose condition never changes
+pub fn check(expressions: &[Expression], symbols: &SymbolTable) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for function in expressions {
+        let Some(cfg) = Cfg::build(function) else {
+            continue;
+        };
+        unreachable_code(&cfg, &mut diagnostics);
+        missing_return(function, &cfg, symbols, &mut diagnostics);
+        let mut work: Vec<_> = function.children().into_iter().rev().collect();
+        while let Some(expression) = work.pop() {
+            infinite_loop(function, expression, symbols, &mut diagnostics);
+            work.extend(expression.children().into_iter().rev());
+        }
+    }
+    diagnostics
+}
+
+/// One warning for every group of connected blocks that can't run, on its first line
+fn unreachable_code(cfg: &Cfg, diagnostics: &mut Vec<Diagnostic>) {
+    let reachable = cfg.reachable();
+    let predecessors = cfg.predecessors();
+    let mut seen = reachable.clone();
+    let mut regions = Vec::new();
+    for start in 0..cfg.blocks.len() {
+        if std::mem::replace(&mut seen[start], true) {
+            continue;
+        }
+        let mut first: Option<&DebugData> = None;
+        let mut work = vec![start];
+        while let Some(id) = work.pop() {
+            let block = &cfg.blocks[id];
+            for expression in block.statements.iter().chain(&block.condition) {
+                if let Some(debug_data) = expression.debug_data()
+                    && first.is_none_or(|first| debug_data.line < first.line)
+                {
+                    first = Some(debug_data);
+                }
+            }
+            let neighbours = block
+                .successors
+                .iter()
+                .map(|edge| edge.target)
+                .chain(predecessors[id].iter().copied());
+            for neighbour in neighbours {
+                if !std::mem::replace(&mut seen[neighbour], true) {
+                    work.push(neighbour);
+                }
+            }
+        }
+        regions.extend(first);
+    }
+    regions.sort_by_key(|debug_data| debug_data.line);
+    for debug_data in regions {
+        diagnostics.push(Diagnostic::warning(
+            format!("unreachable code in '{}'", cfg.function),
+            debug_data,
+        ));
+    }
+}
+
+/// Falling off the end of a function is fine for `void` and for `main`, which returns 0
+fn missing_return(
+    function: &Expression,
+    cfg: &Cfg,
+    symbols: &SymbolTable,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let Expression::Function {
+        name,
+        output,
+        debug_data,
+        ..
+    } = function
+    else {
+        return;
+    };
+    if name == "main" || type_check::is_void(&type_check::resolve(symbols, output)) {
+        return;
+    }
+    let reachable = cfg.reachable();
+    let falls_off = cfg.predecessors()[EXIT].iter().any(|&id| {
+        reachable[id]
+            && !matches!(
+                cfg.blocks[id].statements.last(),
+                Some(Expression::Return { .. })
+            )
+    });
+    if falls_off {
+        diagnostics.push(Diagnostic::warning(
+            format!("not all paths of '{name}' return a value"),
+            debug_data,
+        ));
+    }
+}
+
+/// Hint for `while` and `for` loops that can't be left: the condition is a constant or only
+/// reads local variables that nothing in the body changes, and there is no `break` or `return`
+fn infinite_loop(
+    function: &Expression,
+    expression: &Expression,
+    symbols: &SymbolTable,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let (condition, body, debug_data): (_, Vec<&Expression>, _) = match expression {
+        Expression::While {
+            condition,
+            inside,
+            debug_data,
+        } => (condition.as_ref(), inside.iter().collect(), debug_data),
+        Expression::For {
+            condition,
+            incr,
+            inside,
+            debug_data,
+            ..
+        } => (
+            condition.as_ref(),
+            inside.iter().chain([incr.as_ref()]).collect(),
+            debug_data,
+        ),
+        _ => return,
+    };
+    // the line of a loop is where its body ends
+    let debug_data = condition.debug_data().unwrap_or(debug_data);
+    if body.iter().any(|statement| leaves(statement, true)) {
+        return;
+    }
+    let forever = matches!(condition, Expression::Skip)
+        || crate::analysis::cfg::constant_condition(condition) == Some(true);
+    if forever {
+        diagnostics.push(Diagnostic::note(
+            "this loop never ends, nothing in it breaks out of it or returns".to_owned(),
+            debug_data,
+        ));
+        return;
+    }
+    let Some(variables) = condition_variables(condition, symbols) else {
+        return;
+    };
+    if variables.is_empty() {
+        return;
+    }
+    let mut taken = HashSet::new();
+    address_taken(function, symbols, &mut taken);
+    if variables.iter().any(|variable| taken.contains(variable)) {
+        return;
+    }
+    let mut changed = HashSet::new();
+    for statement in &body {
+        changes(statement, symbols, &mut changed);
+    }
+    if variables.iter().any(|variable| changed.contains(variable)) {
+        return;
+    }
+    let mut names: Vec<_> = variables
+        .iter()
+        .map(|&id| format!("'{}'", symbols.symbol(id).name))
+        .collect();
+    names.sort();
+    let (names, verb) = match names.split_last() {
+        Some((last, [])) => (last.to_owned(), "doesn't"),
+        Some((last, rest)) => (format!("{} and {last}", rest.join(", ")), "don't"),
+        None => return,
+    };
+    diagnostics.push(Diagnostic::note(
+        format!("{names} {verb} change in the body of this loop, it never ends once it starts"),
+        debug_data,
+    ));
+}
+
+/// Whether running the statement can leave the loop around it. `break` only counts outside of
+/// nested loops.
+fn leaves(expression: &Expression, outermost: bool) -> bool {
+    match expression {
+        Expression::Break { .. } => outermost,
+        Expression::Return { .. } => true,
+        Expression::FunctionCall { left, .. } if never_returns(left) => true,
+        Expression::While { .. } | Expression::For { .. } => expression
+            .children()
+            .into_iter()
+            .any(|child| leaves(child, false)),
+        _ => expression
+            .children()
+            .into_iter()
+            .any(|child| leaves(child, outermost)),
+    }
+}
+
+fn never_returns(function: &Expression) -> bool {
+    matches!(function, Expression::Identifier(name, _) if NO_RETURN_FUNCTIONS.contains(&name.as_str()))
+}
+
+/// Local variables and parameters that the condition reads, `None` when it depends on
+/// anything else: globals, calls or memory behind a pointer
+fn condition_variables(condition: &Expression, symbols: &SymbolTable) -> Option<HashSet<SymbolId>> {
+    let mut variables = HashSet::new();
+    let mut work = vec![condition];
+    while let Some(expression) = work.pop() {
+        match expression {
+            Expression::Identifier(..) | Expression::DataTypeAccess { .. } => {
+                let id = *symbols.resolutions.get(&expression.id())?;
+                let symbol = symbols.symbol(id);
+                match symbol.kind {
+                    SymbolKind::EnumConstant => {}
+                    SymbolKind::Parameter => {
+                        variables.insert(id);
+                    }
+                    SymbolKind::Variable
+                        if symbols.scopes[symbol.scope].kind != ScopeKind::File =>
+                    {
+                        variables.insert(id);
+                    }
+                    _ => return None,
+                }
+            }
+            Expression::FunctionCall { .. }
+            | Expression::Dereference { .. }
+            | Expression::Arrow { .. }
+            | Expression::AccessArray { .. } => return None,
+            // only the left side of `a.b` names a variable
+            Expression::MemberExpr { left, .. } => work.push(left),
+            expression => work.extend(expression.children()),
+        }
+    }
+    Some(variables)
+}
+
+/// Variables that are assigned, incremented or decremented anywhere in the expression
+fn changes(expression: &Expression, symbols: &SymbolTable, changed: &mut HashSet<SymbolId>) {
+    match expression {
+        Expression::Assignment { target, .. }
+        | Expression::Increment { target, .. }
+        | Expression::Decrement { target, .. } => changed.extend(analysis::root(target, symbols)),
+        _ => {}
+    }
+    for child in expression.children() {
+        changes(child, symbols, changed);
+    }
+}
+
+/// Variables whose address is taken, they can change through the pointer
+fn address_taken(expression: &Expression, symbols: &SymbolTable, taken: &mut HashSet<SymbolId>) {
+    if let Expression::AccessReference { value, .. } = expression {
+        taken.extend(analysis::root(value, symbols));
+    }
+    for child in expression.children() {
+        address_taken(child, symbols, taken);
+    }
+}
@@ -0,0 +1,426 @@
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+
+use crate::{
+    analysis::call_graph::CallGraph,
+    diagnostics::Diagnostic,
+    lexer::token::TokenKind,
+    parser::expression::{DebugData, Expression},
+};
+
+/// Number that `--threshold` can put a limit on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    Complexity,
+    Nesting,
+    Parameters,
+    Statements,
+    FanIn,
+    FanOut,
+}
+
+const METRICS: &[Metric] = &[
+    Metric::Complexity,
+    Metric::Nesting,
+    Metric::Parameters,
+    Metric::Statements,
+    Metric::FanIn,
+    Metric::FanOut,
+];
+
+impl Metric {
+    pub fn name(self) -> &'static str {
+        match self {
+            Metric::Complexity => "complexity",
+            Metric::Nesting => "nesting",
+            Metric::Parameters => "parameters",
+            Metric::Statements => "statements",
+            Metric::FanIn => "fan-in",
+            Metric::FanOut => "fan-out",
+        }
+    }
+
+    pub fn by_name(name: &str) -> Result<Self> {
+        match METRICS.iter().find(|metric| metric.name() == name) {
+            Some(metric) => Ok(*metric),
+            None => {
+                let names = METRICS
+                    .iter()
+                    .map(|metric| metric.name())
+                    .collect::<Vec<_>>();
+                bail!(
+                    "unknown metric: '{name}', expected one of: {}",
+                    names.join(", ")
+                )
+            }
+        }
+    }
+
+    fn of(self, function: &FunctionMetrics) -> usize {
+        match self {
+            Metric::Complexity => function.complexity,
+            Metric::Nesting => function.nesting,
+            Metric::Parameters => function.parameters,
+            Metric::Statements => function.statements,
+            Metric::FanIn => function.fan_in,
+            Metric::FanOut => function.fan_out,
+        }
+    }
+}
+
+/// `METRIC=N`, functions over `N` fail the run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Threshold {
+    pub metric: Metric,
+    pub limit: usize,
+}
+
+impl Threshold {
+    pub fn parse(text: &str) -> Result<Self> {
+        let Some((name, limit)) = text.split_once('=') else {
+            bail!("expected METRIC=N, found: '{text}'");
+        };
+        Ok(Threshold {
+            metric: Metric::by_name(name)?,
+            limit: limit
+                .parse()
+                .with_context(|| format!("invalid limit of '{name}': '{limit}'"))?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FunctionMetrics {
+    pub name: String,
+    pub line: u16,
+    /// 1 and one more for every `if`, `else if`, loop, `&&` and `||`
+    pub complexity: usize,
+    /// deepest `if`, `while` and `for` inside of each other
+    pub nesting: usize,
+    pub parameters: usize,
+    /// statements in the body and in the blocks inside of it
+    pub statements: usize,
+    /// function definitions that call this one
+    pub fan_in: usize,
+    /// functions that this one calls, defined or not
+    pub fan_out: usize,
+}
+
+/// Sums of the functions of a file, except `nesting` which is the deepest one
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Totals {
+    pub functions: usize,
+    pub complexity: usize,
+    pub max_complexity: usize,
+    pub nesting: usize,
+    pub parameters: usize,
+    pub statements: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileMetrics {
+    pub file: String,
+    pub functions: Vec<FunctionMetrics>,
+    pub totals: Totals,
+}
+
+impl FileMetrics {
+    /// Metrics of the function definitions that are in `file` itself, not in what it includes.
+    /// Calls from every definition in the program count for fan-in.
+    pub fn new(file: &str, expressions: &[Expression]) -> Self {
+        let graph = CallGraph::build(expressions);
+        let mut functions = Vec::new();
+        for expression in expressions {
+            let Expression::Function {
+                name,
+                properties,
+                inside,
+                debug_data,
+                ..
+            } = expression
+            else {
+                continue;
+            };
+            if debug_data.file != file {
+                continue;
+            }
+            let index = graph
+                .functions
+                .iter()
+                .position(|function| function.name == *name);
+            // calls of a function to itself don't count
+            let fan_in = graph
+                .calls
+                .iter()
+                .filter(|call| Some(call.callee) == index && Some(call.caller) != index)
+                .count();
+            let fan_out = graph
+                .calls
+                .iter()
+                .filter(|call| Some(call.caller) == index && Some(call.callee) != index)
+                .count();
+            let mut counter = Counter::default();
+            counter.statements(inside, 0);
+            functions.push(FunctionMetrics {
+                name: name.to_owned(),
+                line: debug_data.line,
+                complexity: 1 + counter.decisions,
+                nesting: counter.nesting,
+                parameters: properties.len(),
+                statements: counter.statements,
+                fan_in,
+                fan_out,
+            });
+        }
+        let totals = Totals {
+            functions: functions.len(),
+            complexity: functions.iter().map(|function| function.complexity).sum(),
+            max_complexity: functions
+                .iter()
+                .map(|function| function.complexity)
+                .max()
+                .unwrap_or(0),
+            nesting: functions
+                .iter()
+                .map(|function| function.nesting)
+                .max()
+                .unwrap_or(0),
+            parameters: functions.iter().map(|function| function.parameters).sum(),
+            statements: functions.iter().map(|function| function.statements).sum(),
+        };
+        FileMetrics {
+            file: file.to_owned(),
+            functions,
+            totals,
+        }
+    }
+
+    /// An error for every function over one of the thresholds
+    pub fn check(&self, thresholds: &[Threshold]) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for function in &self.functions {
+            for threshold in thresholds {
+                let value = threshold.metric.of(function);
+                if value > threshold.limit {
+                    diagnostics.push(Diagnostic::error(
+                        format!(
+                            "'{}' has {} {value}, over the limit of {}",
+                            function.name,
+                            threshold.metric.name(),
+                            threshold.limit
+                        ),
+                        &DebugData {
+                            line: function.line,
+                            file: self.file.to_owned(),
+                            expansion: None,
+                        },
+                    ));
+                }
+            }
+        }
+        diagnostics
+    }
+}
+
+#[derive(Default)]
+struct Counter {
+    decisions: usize,
+    nesting: usize,
+    statements: usize,
+}
+
+impl Counter {
+    fn statements(&mut self, statements: &[Expression], depth: usize) {
+        for statement in statements {
+            self.statement(statement, depth);
+        }
+    }
+
+    fn statement(&mut self, statement: &Expression, depth: usize) {
+        match statement {
+            Expression::Skip => return,
+            Expression::NewCodeBlock { inside, .. } => return self.statements(inside, depth),
+            _ => self.statements += 1,
+        }
+        match statement {
+            Expression::If {
+                condition,
+                inside,
+                chained_elses,
+                ..
+            } => {
+                self.nested(depth);
+                self.decisions += 1;
+                self.expression(condition);
+                self.statements(inside, depth + 1);
+                for chained in chained_elses {
+                    let Expression::Else {
+                        condition, inside, ..
+                    } = chained
+                    else {
+                        continue;
+                    };
+                    if let Some(condition) = condition {
+                        self.decisions += 1;
+                        self.expression(condition);
+                    }
+                    self.statements(inside, depth + 1);
+                }
+            }
+            Expression::While {
+                condition, inside, ..
+            } => {
+                self.nested(depth);
+                self.decisions += 1;
+                self.expression(condition);
+                self.statements(inside, depth + 1);
+            }
+            Expression::For {
+                iterator_init,
+                condition,
+                incr,
+                inside,
+                ..
+            } => {
+                self.nested(depth);
+                self.decisions += 1;
+                for expression in [iterator_init, condition, incr] {
+                    self.expression(expression);
+                }
+                self.statements(inside, depth + 1);
+            }
+            statement => self.expression(statement),
+        }
+    }
+
+    fn nested(&mut self, depth: usize) {
+        self.nesting = self.nesting.max(depth + 1);
+    }
+
+    /// `&&` and `||` are decisions too
+    fn expression(&mut self, expression: &Expression) {
+        if let Expression::Binary { operator, .. } = expression
+            && matches!(operator.kind, TokenKind::And | TokenKind::Or)
+        {
+            self.decisions += 1;
+        }
+        for child in expression.children() {
+            self.expression(child);
+        }
+    }
+}
+
+const COLUMNS: [&str; 8] = [
+    "function",
+    "line",
+    "complexity",
+    "nesting",
+    "parameters",
+    "statements",
+    "fan-in",
+    "fan-out",
+];
+
+/// A table for every file, with a line of totals under it
+pub fn table(files: &[FileMetrics]) -> String {
+    let mut output = String::new();
+    for file in files {
+        let mut rows: Vec<[String; 8]> = file
+            .functions
+            .iter()
+            .map(|function| {
+                [
+                    function.name.to_owned(),
+                    function.line.to_string(),
+                    function.complexity.to_string(),
+                    function.nesting.to_string(),
+                    function.parameters.to_string(),
+                    function.statements.to_string(),
+                    function.fan_in.to_string(),
+                    function.fan_out.to_string(),
+                ]
+            })
+            .collect();
+        let totals = &file.totals;
+        rows.push([
+            format!("total ({} functions)", totals.functions),
+            String::new(),
+            totals.complexity.to_string(),
+            totals.nesting.to_string(),
+            totals.parameters.to_string(),
+            totals.statements.to_string(),
+            String::new(),
+            String::new(),
+        ]);
+        let widths: Vec<usize> = (0..COLUMNS.len())
+            .map(|column| {
+                rows.iter()
+                    .map(|row| row[column].len())
+                    .chain([COLUMNS[column].len()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        output += &format!("{}\n", file.file);
+        let header = COLUMNS.map(str::to_owned);
+        for row in std::iter::once(&header).chain(&rows) {
+            // names on the left, numbers on the right
+            let mut line = format!("{:<width$}", row[0], width = widths[0]);
+            for (cell, width) in row.iter().zip(&widths).skip(1) {
+                line += &format!("  {cell:>width$}");
+            }
+            output += line.trim_end();
+            output += "\n";
+        }
+        output += &format!("max complexity: {}\n\n", totals.max_complexity);
+    }
+    output
+}
+
+/// `{"files": [...]}` with the functions and totals of every file
+pub fn to_json(files: &[FileMetrics]) -> String {
+    #[derive(Serialize)]
+    struct Report<'a> {
+        files: &'a [FileMetrics],
+    }
+    let mut output =
+        serde_json::to_string_pretty(&Report { files }).expect("metrics are always valid JSON");
+    output.push('\n');
+    output
+}
+
+/// A row for every function, and one with an empty function and line for the totals of each
+/// file
+pub fn csv(files: &[FileMetrics]) -> String {
+    let mut output = format!("file,{}\n", COLUMNS.join(",").replace('-', "_"));
+    for file in files {
+        let name = csv_field(&file.file);
+        for function in &file.functions {
+            output += &format!(
+                "{name},{},{},{},{},{},{},{},{}\n",
+                csv_field(&function.name),
+                function.line,
+                function.complexity,
+                function.nesting,
+                function.parameters,
+                function.statements,
+                function.fan_in,
+                function.fan_out
+            );
+        }
+        let totals = &file.totals;
+        output += &format!(
+            "{name},,,{},{},{},{},,\n",
+            totals.complexity, totals.nesting, totals.parameters, totals.statements
+        );
+    }
+    output
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_owned()
+    }
+}
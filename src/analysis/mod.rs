@@ -0,0 +1,24 @@
+use crate::{
+    parser::expression::Expression,
+    semantic::scope::{SymbolId, SymbolTable},
+};
+
+pub mod call_graph;
+pub mod cfg;
+pub mod dataflow;
+pub mod flow;
+pub mod metrics;
+
+/// Variable that an assignment to the expression changes, or that `&` points into: `x`, `x.y`,
+/// `x[i]`, `(x)`
+pub fn root(expression: &Expression, symbols: &SymbolTable) -> Option<SymbolId> {
+    match expression {
+        Expression::Identifier(..) | Expression::DataTypeAccess { .. } => {
+            symbols.resolutions.get(&expression.id()).copied()
+        }
+        Expression::MemberExpr { left, .. }
+        | Expression::AccessArray { left, .. }
+        | Expression::Grouping { value: left, .. } => root(left, symbols),
+        _ => None,
+    }
+}
@@ -0,0 +1,11 @@
+//! Language server for the C that RIP understands, talks JSON-RPC over stdin and stdout
+
+use log::error;
+
+fn main() {
+    colog::init();
+    let stdin = std::io::stdin();
+    if let Err(err) = rip::lsp::serve(stdin.lock(), std::io::stdout().lock()) {
+        error!("{err:?}");
+    }
+}
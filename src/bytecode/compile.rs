@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result, bail};
+
+use crate::bytecode::{Function, GLOBAL_BASE, Global, Native, Op, Program};
+use crate::ir::{self, BlockId, Instruction, Module, Terminator, Value};
+
+/// Compiles the three-address IR of the program to bytecode. Registers become locals, stack
+/// slots live in the stack memory of the frame, and globals get their addresses here. The value
+/// of a phi is written to a local of its own at the end of every predecessor and copied into
+/// the phi's register at the start of its block, so phis that swap values work.
+pub fn compile(module: &Module) -> Result<Program> {
+    let mut program = Program::default();
+    let mut addresses = HashMap::new();
+    let mut end = GLOBAL_BASE;
+    for global in &module.globals {
+        end = end.next_multiple_of(global.alignment.max(1));
+        addresses.insert(global.name.to_owned(), end);
+        end += global.bytes.len() as u64;
+    }
+    for global in &module.globals {
+        let mut bytes = global.bytes.to_owned();
+        for (offset, name) in &global.pointers {
+            let address = addresses
+                .get(name)
+                .with_context(|| format!("'{}' points to the function '{name}'", global.name))?;
+            let offset = *offset as usize;
+            bytes[offset..offset + 8].copy_from_slice(&address.to_le_bytes());
+        }
+        program.globals.push(Global {
+            name: global.name.to_owned(),
+            address: addresses[&global.name],
+            bytes,
+        });
+    }
+
+    let mut compiler = Compiler {
+        module,
+        addresses: &addresses,
+        constants: HashMap::new(),
+        program,
+    };
+    for function in &module.functions {
+        let compiled = compiler
+            .function(function)
+            .with_context(|| format!("compiling '{}' to bytecode", function.name))?;
+        compiler.program.functions.push(compiled);
+    }
+    Ok(compiler.program)
+}
+
+struct Compiler<'a> {
+    module: &'a Module,
+    addresses: &'a HashMap<String, u64>,
+    /// index of every value in the constant pool
+    constants: HashMap<i64, u32>,
+    program: Program,
+}
+
+/// Code of one function
+struct Emitter {
+    code: Vec<Op>,
+    /// jumps whose target is the start of a block
+    patches: Vec<(usize, BlockId)>,
+    /// local that the predecessors write the value of each phi to
+    incoming: HashMap<ir::Register, u32>,
+    slots: Vec<u32>,
+}
+
+impl Compiler<'_> {
+    fn constant(&mut self, value: i64) -> Op {
+        let constants = &mut self.program.constants;
+        let index = *self.constants.entry(value).or_insert_with(|| {
+            constants.push(value);
+            constants.len() as u32 - 1
+        });
+        Op::Constant(index)
+    }
+
+    fn push(&mut self, emitter: &mut Emitter, value: Value) {
+        let op = match value {
+            Value::Register(register) => Op::LoadLocal(register.0),
+            Value::Constant(constant, _) => self.constant(constant),
+        };
+        emitter.code.push(op);
+    }
+
+    fn function(&mut self, function: &ir::Function) -> Result<Function> {
+        let mut locals = function.registers.len() as u32;
+        let mut incoming = HashMap::new();
+        for instruction in function.blocks.iter().flat_map(|block| &block.instructions) {
+            if let Instruction::Phi { destination, .. } = instruction {
+                incoming.insert(*destination, locals);
+                locals += 1;
+            }
+        }
+        let mut size = 0;
+        let mut slots = Vec::new();
+        for slot in &function.slots {
+            size = u64::next_multiple_of(size, slot.alignment.max(1));
+            slots.push(size as u32);
+            size += slot.size;
+        }
+
+        let mut emitter = Emitter {
+            code: Vec::new(),
+            patches: Vec::new(),
+            incoming,
+            slots,
+        };
+        let mut starts = Vec::new();
+        for id in function.block_ids() {
+            starts.push(emitter.code.len() as u32);
+            let block = function.block(id);
+            for instruction in &block.instructions {
+                self.instruction(function, &mut emitter, instruction)?;
+            }
+            let next = BlockId(id.0 + 1);
+            match &block.terminator {
+                Terminator::Jump(target) => {
+                    self.jump(function, &mut emitter, id, *target, Some(next));
+                }
+                Terminator::Branch {
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    self.push(&mut emitter, *condition);
+                    let branch = emitter.code.len();
+                    emitter.code.push(Op::JumpIfZero(0));
+                    if has_phis(function, *otherwise) {
+                        self.jump(function, &mut emitter, id, *then, None);
+                        emitter.code[branch] = Op::JumpIfZero(emitter.code.len() as u32);
+                        self.jump(function, &mut emitter, id, *otherwise, Some(next));
+                    } else {
+                        emitter.patches.push((branch, *otherwise));
+                        self.jump(function, &mut emitter, id, *then, Some(next));
+                    }
+                }
+                Terminator::Return(value) => {
+                    if let Some(value) = value {
+                        self.push(&mut emitter, *value);
+                    }
+                    emitter.code.push(Op::Return);
+                }
+                Terminator::Unreachable => emitter.code.push(Op::Trap),
+            }
+        }
+        for (position, block) in &emitter.patches {
+            let start = starts[block.0 as usize];
+            emitter.code[*position] = match emitter.code[*position] {
+                Op::JumpIfZero(_) => Op::JumpIfZero(start),
+                _ => Op::Jump(start),
+            };
+        }
+
+        Ok(Function {
+            name: function.name.to_owned(),
+            parameters: function
+                .parameters
+                .iter()
+                .map(|parameter| parameter.0)
+                .collect(),
+            locals,
+            frame_size: size.next_multiple_of(16) as u32,
+            returns: function.output.is_some(),
+            code: emitter.code,
+        })
+    }
+
+    /// Writes the values of the phis of the target and jumps there, unless it is the block that
+    /// comes next
+    fn jump(
+        &mut self,
+        function: &ir::Function,
+        emitter: &mut Emitter,
+        from: BlockId,
+        to: BlockId,
+        next: Option<BlockId>,
+    ) {
+        for instruction in &function.block(to).instructions {
+            let Instruction::Phi {
+                destination,
+                incoming,
+            } = instruction
+            else {
+                break;
+            };
+            if let Some((_, value)) = incoming.iter().find(|(block, _)| *block == from) {
+                self.push(emitter, *value);
+                emitter
+                    .code
+                    .push(Op::StoreLocal(emitter.incoming[destination]));
+            }
+        }
+        if Some(to) != next {
+            emitter.patches.push((emitter.code.len(), to));
+            emitter.code.push(Op::Jump(0));
+        }
+    }
+
+    fn instruction(
+        &mut self,
+        function: &ir::Function,
+        emitter: &mut Emitter,
+        instruction: &Instruction,
+    ) -> Result<()> {
+        match instruction {
+            Instruction::Phi { destination, .. } => {
+                emitter
+                    .code
+                    .push(Op::LoadLocal(emitter.incoming[destination]));
+            }
+            Instruction::Copy { value, .. } => self.push(emitter, *value),
+            Instruction::Unary {
+                destination,
+                operator,
+                value,
+            } => {
+                self.push(emitter, *value);
+                let data_type = function.register_type(*destination);
+                emitter.code.push(Op::Unary(*operator, data_type));
+            }
+            Instruction::Binary {
+                destination,
+                operator,
+                left,
+                right,
+            } => {
+                self.push(emitter, *left);
+                self.push(emitter, *right);
+                let data_type = function.register_type(*destination);
+                emitter.code.push(Op::Binary(*operator, data_type));
+            }
+            Instruction::Compare {
+                condition,
+                left,
+                right,
+                ..
+            } => {
+                self.push(emitter, *left);
+                self.push(emitter, *right);
+                emitter
+                    .code
+                    .push(Op::Compare(*condition, function.type_of(*left)));
+            }
+            Instruction::Convert {
+                destination,
+                conversion,
+                value,
+            } => {
+                self.push(emitter, *value);
+                let (from, to) = (
+                    function.type_of(*value),
+                    function.register_type(*destination),
+                );
+                emitter.code.push(Op::Convert(*conversion, from, to));
+            }
+            Instruction::Load {
+                destination,
+                address,
+            } => {
+                self.push(emitter, *address);
+                emitter
+                    .code
+                    .push(Op::Load(function.register_type(*destination)));
+            }
+            Instruction::Store { address, value } => {
+                self.push(emitter, *address);
+                self.push(emitter, *value);
+                emitter.code.push(Op::Store(function.type_of(*value)));
+            }
+            Instruction::StackAddress { slot, .. } => {
+                emitter
+                    .code
+                    .push(Op::FrameAddress(emitter.slots[slot.0 as usize]));
+            }
+            Instruction::GlobalAddress { name, .. } => {
+                let address = self.addresses.get(name).with_context(|| {
+                    format!("functions can't be used as pointers, found '{name}'")
+                })?;
+                let op = self.constant(*address as i64);
+                emitter.code.push(op);
+            }
+            Instruction::Call {
+                destination,
+                function: name,
+                arguments,
+            } => {
+                for argument in arguments {
+                    self.push(emitter, *argument);
+                }
+                let returns = if let Some(index) = self.program_function(name) {
+                    emitter.code.push(Op::Call(index));
+                    self.module
+                        .function(name)
+                        .is_some_and(|callee| callee.output.is_some())
+                } else if let Some(native) = Native::by_name(name) {
+                    emitter
+                        .code
+                        .push(Op::CallNative(native, arguments.len() as u8));
+                    native.returns()
+                } else {
+                    let natives = Native::ALL.map(Native::name).join(", ");
+                    bail!("call to '{name}', which has no body, the VM only provides: {natives}");
+                };
+                if destination.is_none() && returns {
+                    emitter.code.push(Op::Pop);
+                }
+            }
+            Instruction::CopyMemory {
+                destination,
+                source,
+                size,
+            } => {
+                self.push(emitter, *destination);
+                self.push(emitter, *source);
+                emitter.code.push(Op::CopyMemory(*size as u32));
+            }
+            Instruction::ZeroMemory { destination, size } => {
+                self.push(emitter, *destination);
+                emitter.code.push(Op::ZeroMemory(*size as u32));
+            }
+        }
+        if let Some(destination) = instruction.destination() {
+            emitter.code.push(Op::StoreLocal(destination.0));
+        }
+        Ok(())
+    }
+
+    /// Index that the function will have in the program, functions are compiled in order
+    fn program_function(&self, name: &str) -> Option<u32> {
+        self.module
+            .functions
+            .iter()
+            .position(|function| function.name == name)
+            .map(|index| index as u32)
+    }
+}
+
+fn has_phis(function: &ir::Function, block: BlockId) -> bool {
+    matches!(
+        function.block(block).instructions.first(),
+        Some(Instruction::Phi { .. })
+    )
+}
@@ -0,0 +1,358 @@
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    bytecode::{Function, GLOBAL_BASE, Global, Native, Op, Program},
+    ir::{BinaryOperator, Condition, Conversion, Type, UnaryOperator},
+};
+
+/// Start of every file of bytecode
+const MAGIC: &[u8; 5] = b"RIPBC";
+/// changes whenever the encoding does, older files have to be compiled again
+const VERSION: u16 = 1;
+/// a function with more locals or stack memory than this comes from a damaged file
+const MAX_LOCALS: u32 = 1 << 20;
+const MAX_FRAME_SIZE: u32 = 1 << 30;
+
+// the encoding of each enum is its index in these
+const TYPES: [Type; 6] = [
+    Type::I1,
+    Type::I8,
+    Type::I16,
+    Type::I32,
+    Type::I64,
+    Type::Pointer,
+];
+const UNARY_OPERATORS: [UnaryOperator; 2] = [UnaryOperator::Negate, UnaryOperator::Not];
+const BINARY_OPERATORS: [BinaryOperator; 13] = [
+    BinaryOperator::Add,
+    BinaryOperator::Subtract,
+    BinaryOperator::Multiply,
+    BinaryOperator::SignedDivide,
+    BinaryOperator::UnsignedDivide,
+    BinaryOperator::SignedRemainder,
+    BinaryOperator::UnsignedRemainder,
+    BinaryOperator::And,
+    BinaryOperator::Or,
+    BinaryOperator::Xor,
+    BinaryOperator::ShiftLeft,
+    BinaryOperator::LogicalShiftRight,
+    BinaryOperator::ArithmeticShiftRight,
+];
+const CONDITIONS: [Condition; 10] = [
+    Condition::Equal,
+    Condition::NotEqual,
+    Condition::SignedLess,
+    Condition::SignedLessEqual,
+    Condition::SignedGreater,
+    Condition::SignedGreaterEqual,
+    Condition::UnsignedLess,
+    Condition::UnsignedLessEqual,
+    Condition::UnsignedGreater,
+    Condition::UnsignedGreaterEqual,
+];
+const CONVERSIONS: [Conversion; 5] = [
+    Conversion::Truncate,
+    Conversion::ZeroExtend,
+    Conversion::SignExtend,
+    Conversion::PointerToInteger,
+    Conversion::IntegerToPointer,
+];
+
+fn encode<T: PartialEq>(values: &[T], value: T) -> u8 {
+    values
+        .iter()
+        .position(|other| *other == value)
+        .unwrap_or_default() as u8
+}
+
+/// Binary format: the magic and version, then the constant pool, the globals and the
+/// functions. Numbers are little endian, lengths and strings are prefixed with a `u32`.
+impl Program {
+    pub fn is_bytecode(bytes: &[u8]) -> bool {
+        bytes.starts_with(MAGIC)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer(MAGIC.to_vec());
+        writer.0.extend(VERSION.to_le_bytes());
+        writer.u32(self.constants.len() as u32);
+        for constant in &self.constants {
+            writer.0.extend(constant.to_le_bytes());
+        }
+        writer.u32(self.globals.len() as u32);
+        for global in &self.globals {
+            writer.bytes(global.name.as_bytes());
+            writer.0.extend(global.address.to_le_bytes());
+            writer.bytes(&global.bytes);
+        }
+        writer.u32(self.functions.len() as u32);
+        for function in &self.functions {
+            writer.bytes(function.name.as_bytes());
+            writer.u32(function.parameters.len() as u32);
+            for parameter in &function.parameters {
+                writer.u32(*parameter);
+            }
+            writer.u32(function.locals);
+            writer.u32(function.frame_size);
+            writer.0.push(function.returns as u8);
+            writer.u32(function.code.len() as u32);
+            for op in &function.code {
+                writer.op(*op);
+            }
+        }
+        writer.0
+    }
+
+    /// Reads a program that `to_bytes` wrote, anything that the VM could trip over is an error
+    pub fn from_bytes(bytes: &[u8]) -> Result<Program> {
+        if !Program::is_bytecode(bytes) {
+            bail!("not a file of bytecode, it doesn't start with {MAGIC:?}");
+        }
+        let mut reader = Reader {
+            bytes,
+            position: MAGIC.len(),
+        };
+        let version = u16::from_le_bytes(reader.array()?);
+        if version != VERSION {
+            bail!("bytecode of version {version}, expected {VERSION}, it has to be compiled again");
+        }
+
+        let mut program = Program::default();
+        for _ in 0..reader.u32()? {
+            program.constants.push(i64::from_le_bytes(reader.array()?));
+        }
+        let mut end = GLOBAL_BASE;
+        for _ in 0..reader.u32()? {
+            let name = reader.string()?;
+            let address = u64::from_le_bytes(reader.array()?);
+            let bytes = reader.bytes()?.to_vec();
+            if address < end {
+                bail!("global '{name}' at 0x{address:x} overlaps the one before it");
+            }
+            end = address
+                .checked_add(bytes.len() as u64)
+                .with_context(|| format!("global '{name}' at 0x{address:x} is too big"))?;
+            program.globals.push(Global {
+                name,
+                address,
+                bytes,
+            });
+        }
+        let count = reader.u32()?;
+        for _ in 0..count {
+            let name = reader.string()?;
+            let mut parameters = Vec::new();
+            for _ in 0..reader.u32()? {
+                parameters.push(reader.u32()?);
+            }
+            let locals = reader.u32()?;
+            let frame_size = reader.u32()?;
+            let returns = reader.u8()? != 0;
+            let mut code = Vec::new();
+            for _ in 0..reader.u32()? {
+                code.push(reader.op()?);
+            }
+            let function = Function {
+                name,
+                parameters,
+                locals,
+                frame_size,
+                returns,
+                code,
+            };
+            validate(&function, &program, count)
+                .with_context(|| format!("invalid bytecode in '{}'", function.name))?;
+            program.functions.push(function);
+        }
+        if reader.position != bytes.len() {
+            bail!(
+                "{} bytes after the end of the bytecode",
+                bytes.len() - reader.position
+            );
+        }
+        Ok(program)
+    }
+}
+
+/// Checks every index in the function, the VM relies on them being in range
+fn validate(function: &Function, program: &Program, functions: u32) -> Result<()> {
+    if function.locals > MAX_LOCALS {
+        bail!(
+            "{} locals, at most {MAX_LOCALS} are allowed",
+            function.locals
+        );
+    }
+    if function.frame_size > MAX_FRAME_SIZE {
+        bail!(
+            "{} bytes of stack memory, at most {MAX_FRAME_SIZE} are allowed",
+            function.frame_size
+        );
+    }
+    if let Some(parameter) = function
+        .parameters
+        .iter()
+        .find(|parameter| **parameter >= function.locals)
+    {
+        bail!("parameter in local {parameter} of {}", function.locals);
+    }
+    for (position, op) in function.code.iter().enumerate() {
+        let valid = match *op {
+            Op::Constant(index) => (index as usize) < program.constants.len(),
+            Op::LoadLocal(local) | Op::StoreLocal(local) => local < function.locals,
+            Op::Call(index) => index < functions,
+            Op::Jump(target) | Op::JumpIfZero(target) => (target as usize) < function.code.len(),
+            _ => true,
+        };
+        if !valid {
+            bail!("instruction {position} is out of range: {op:?}");
+        }
+    }
+    Ok(())
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u32(&mut self, value: u32) {
+        self.0.extend(value.to_le_bytes());
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.u32(bytes.len() as u32);
+        self.0.extend(bytes);
+    }
+
+    fn op(&mut self, op: Op) {
+        let (opcode, operands): (u8, Vec<u8>) = match op {
+            Op::Constant(index) => (0, index.to_le_bytes().to_vec()),
+            Op::LoadLocal(local) => (1, local.to_le_bytes().to_vec()),
+            Op::StoreLocal(local) => (2, local.to_le_bytes().to_vec()),
+            Op::Unary(operator, data_type) => (
+                3,
+                vec![
+                    encode(&UNARY_OPERATORS, operator),
+                    encode(&TYPES, data_type),
+                ],
+            ),
+            Op::Binary(operator, data_type) => (
+                4,
+                vec![
+                    encode(&BINARY_OPERATORS, operator),
+                    encode(&TYPES, data_type),
+                ],
+            ),
+            Op::Compare(condition, data_type) => (
+                5,
+                vec![encode(&CONDITIONS, condition), encode(&TYPES, data_type)],
+            ),
+            Op::Convert(conversion, from, to) => (
+                6,
+                vec![
+                    encode(&CONVERSIONS, conversion),
+                    encode(&TYPES, from),
+                    encode(&TYPES, to),
+                ],
+            ),
+            Op::Load(data_type) => (7, vec![encode(&TYPES, data_type)]),
+            Op::Store(data_type) => (8, vec![encode(&TYPES, data_type)]),
+            Op::FrameAddress(offset) => (9, offset.to_le_bytes().to_vec()),
+            Op::CopyMemory(size) => (10, size.to_le_bytes().to_vec()),
+            Op::ZeroMemory(size) => (11, size.to_le_bytes().to_vec()),
+            Op::Call(index) => (12, index.to_le_bytes().to_vec()),
+            Op::CallNative(native, arguments) => {
+                (13, vec![encode(&Native::ALL, native), arguments])
+            }
+            Op::Pop => (14, Vec::new()),
+            Op::Jump(target) => (15, target.to_le_bytes().to_vec()),
+            Op::JumpIfZero(target) => (16, target.to_le_bytes().to_vec()),
+            Op::Return => (17, Vec::new()),
+            Op::Trap => (18, Vec::new()),
+        };
+        self.0.push(opcode);
+        self.0.extend(operands);
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + N)
+            .context("the bytecode ends too early")?;
+        self.position += N;
+        Ok(bytes.try_into()?)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn bytes(&mut self) -> Result<&[u8]> {
+        let length = self.u32()? as usize;
+        let bytes = self
+            .bytes
+            .get(self.position..self.position.saturating_add(length))
+            .context("the bytecode ends too early")?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        Ok(String::from_utf8(self.bytes()?.to_vec())?)
+    }
+
+    fn decode<T: Copy>(&mut self, values: &[T], what: &str) -> Result<T> {
+        let index = self.u8()?;
+        values
+            .get(index as usize)
+            .copied()
+            .with_context(|| format!("unknown {what}: {index}"))
+    }
+
+    fn op(&mut self) -> Result<Op> {
+        Ok(match self.u8()? {
+            0 => Op::Constant(self.u32()?),
+            1 => Op::LoadLocal(self.u32()?),
+            2 => Op::StoreLocal(self.u32()?),
+            3 => Op::Unary(
+                self.decode(&UNARY_OPERATORS, "operator")?,
+                self.decode(&TYPES, "type")?,
+            ),
+            4 => Op::Binary(
+                self.decode(&BINARY_OPERATORS, "operator")?,
+                self.decode(&TYPES, "type")?,
+            ),
+            5 => Op::Compare(
+                self.decode(&CONDITIONS, "condition")?,
+                self.decode(&TYPES, "type")?,
+            ),
+            6 => Op::Convert(
+                self.decode(&CONVERSIONS, "conversion")?,
+                self.decode(&TYPES, "type")?,
+                self.decode(&TYPES, "type")?,
+            ),
+            7 => Op::Load(self.decode(&TYPES, "type")?),
+            8 => Op::Store(self.decode(&TYPES, "type")?),
+            9 => Op::FrameAddress(self.u32()?),
+            10 => Op::CopyMemory(self.u32()?),
+            11 => Op::ZeroMemory(self.u32()?),
+            12 => Op::Call(self.u32()?),
+            13 => Op::CallNative(self.decode(&Native::ALL, "libc function")?, self.u8()?),
+            14 => Op::Pop,
+            15 => Op::Jump(self.u32()?),
+            16 => Op::JumpIfZero(self.u32()?),
+            17 => Op::Return,
+            18 => Op::Trap,
+            opcode => bail!("unknown opcode: {opcode}"),
+        })
+    }
+}
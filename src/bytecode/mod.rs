@@ -0,0 +1,190 @@
+pub mod compile;
+mod format;
+pub mod vm;
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::ir::{BinaryOperator, Condition, Conversion, Type, UnaryOperator};
+
+/// Address of the first global, no object is at the null pointer or right after it
+pub const GLOBAL_BASE: u64 = 16;
+
+/// Functions of libc that the VM provides
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Native {
+    Printf,
+    Malloc,
+    Free,
+    Strlen,
+}
+
+impl Native {
+    pub const ALL: [Native; 4] = [Native::Printf, Native::Malloc, Native::Free, Native::Strlen];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Native::Printf => "printf",
+            Native::Malloc => "malloc",
+            Native::Free => "free",
+            Native::Strlen => "strlen",
+        }
+    }
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        Native::ALL.into_iter().find(|native| native.name() == name)
+    }
+
+    pub fn returns(self) -> bool {
+        self != Native::Free
+    }
+}
+
+/// Instruction of the stack machine. Values on the stack are `i64`s holding a value of the type
+/// that the instructions give, narrow integers sign extended and `i1` as 0 or 1. Pointers are
+/// addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// pushes an entry of the constant pool
+    Constant(u32),
+    LoadLocal(u32),
+    /// pops into the local
+    StoreLocal(u32),
+    /// pops the operand and pushes the result
+    Unary(UnaryOperator, Type),
+    /// pops the right operand, then the left one, and pushes the result
+    Binary(BinaryOperator, Type),
+    /// like `Binary` with operands of the type, the result is 0 or 1
+    Compare(Condition, Type),
+    /// from the first type to the second one
+    Convert(Conversion, Type, Type),
+    /// pops an address and pushes the value there
+    Load(Type),
+    /// pops a value, then the address to write it to
+    Store(Type),
+    /// pushes the address of a byte of the stack memory of the frame
+    FrameAddress(u32),
+    /// pops the source, then the destination, and copies this many bytes
+    CopyMemory(u32),
+    /// pops the destination and zeroes this many bytes
+    ZeroMemory(u32),
+    /// pops the arguments, the last one first, and pushes the result if there is one
+    Call(u32),
+    /// call of a libc function with this many arguments
+    CallNative(Native, u8),
+    Pop,
+    Jump(u32),
+    /// pops a value and jumps if it is zero
+    JumpIfZero(u32),
+    /// pops the result if the function has one
+    Return,
+    /// the end of a function that should have returned
+    Trap,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Global {
+    pub name: String,
+    pub address: u64,
+    /// pointers in it already hold the addresses of what they point to
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    /// locals that the arguments go to
+    pub parameters: Vec<u32>,
+    pub locals: u32,
+    /// bytes of stack memory for the variables whose address is taken
+    pub frame_size: u32,
+    pub returns: bool,
+    pub code: Vec<Op>,
+}
+
+/// Compiled program, `to_bytes` and `from_bytes` store it on disk
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Program {
+    pub constants: Vec<i64>,
+    pub globals: Vec<Global>,
+    pub functions: Vec<Function>,
+}
+
+impl Program {
+    pub fn function(&self, name: &str) -> Option<usize> {
+        self.functions
+            .iter()
+            .position(|function| function.name == name)
+    }
+
+    fn instruction(&self, op: Op) -> String {
+        match op {
+            Op::Constant(index) => match self.constants.get(index as usize) {
+                Some(value) => format!("const #{index} ; {value}"),
+                None => format!("const #{index}"),
+            },
+            Op::LoadLocal(local) => format!("local.get {local}"),
+            Op::StoreLocal(local) => format!("local.set {local}"),
+            Op::Unary(UnaryOperator::Negate, data_type) => format!("neg {data_type}"),
+            Op::Unary(UnaryOperator::Not, data_type) => format!("not {data_type}"),
+            Op::Binary(operator, data_type) => format!("{} {data_type}", operator.name()),
+            Op::Compare(condition, data_type) => format!("icmp {} {data_type}", condition.name()),
+            Op::Convert(conversion, from, to) => format!("{} {from} -> {to}", conversion.name()),
+            Op::Load(data_type) => format!("load {data_type}"),
+            Op::Store(data_type) => format!("store {data_type}"),
+            Op::FrameAddress(offset) => format!("frame +{offset}"),
+            Op::CopyMemory(size) => format!("memcpy {size}"),
+            Op::ZeroMemory(size) => format!("memzero {size}"),
+            Op::Call(index) => match self.functions.get(index as usize) {
+                Some(function) => format!("call {}", function.name),
+                None => format!("call #{index}"),
+            },
+            Op::CallNative(native, arguments) => {
+                format!("call.native {} {arguments}", native.name())
+            }
+            Op::Pop => "pop".to_owned(),
+            Op::Jump(target) => format!("jump {target}"),
+            Op::JumpIfZero(target) => format!("jump.zero {target}"),
+            Op::Return => "ret".to_owned(),
+            Op::Trap => "trap".to_owned(),
+        }
+    }
+}
+
+/// Disassembly
+impl Display for Program {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "constants:")?;
+        for (index, value) in self.constants.iter().enumerate() {
+            writeln!(f, "  #{index} = {value}")?;
+        }
+        for global in &self.globals {
+            writeln!(
+                f,
+                "global {} at 0x{:x}, {} bytes",
+                global.name,
+                global.address,
+                global.bytes.len()
+            )?;
+        }
+        for function in &self.functions {
+            let parameters = function
+                .parameters
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(
+                f,
+                "\nfunction {}({parameters}): {} locals, {} bytes of frame{}",
+                function.name,
+                function.locals,
+                function.frame_size,
+                if function.returns { ", returns" } else { "" }
+            )?;
+            for (position, op) in function.code.iter().enumerate() {
+                writeln!(f, "  {position:>4}  {}", self.instruction(*op))?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,465 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    bytecode::{GLOBAL_BASE, Native, Op, Program},
+    interpreter::builtins::Specification,
+    ir::Type,
+};
+
+pub const DEFAULT_MEMORY_LIMIT: u64 = 64 * 1024 * 1024;
+/// Stack memory has addresses from here on, so they never collide with globals and the heap
+const STACK_BASE: u64 = 1 << 40;
+/// a function that pushes without popping can't take all of the memory
+const MAX_VALUES: usize = 1 << 20;
+/// rough cost of a call frame, it counts against the memory limit
+const FRAME_COST: u64 = 64;
+
+/// The VM stops with an error after this many instructions or when it needs more memory than
+/// this, `malloc` returns NULL instead
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub instructions: u64,
+    pub memory: u64,
+}
+
+/// What happened when the program ran
+#[derive(Debug)]
+pub struct Execution {
+    /// everything the program printed
+    pub output: String,
+    /// value returned from `main`, or the error that stopped the program
+    pub result: Result<i64>,
+    pub instructions: u64,
+}
+
+struct Frame {
+    function: usize,
+    /// next instruction
+    position: usize,
+    /// first local of the frame
+    locals: usize,
+    /// size of the stack memory before the frame
+    stack: usize,
+}
+
+/// Runs `main` of the program. Unlike the interpreter the VM doesn't know where objects start
+/// and end, it only stops the program when it reads outside of the memory that exists.
+pub fn run(program: &Program, limits: Limits) -> Execution {
+    let mut machine = Machine {
+        program,
+        limits,
+        data: Vec::new(),
+        stack: Vec::new(),
+        allocations: HashMap::new(),
+        free: Vec::new(),
+        values: Vec::new(),
+        locals: Vec::new(),
+        frames: Vec::new(),
+        output: Vec::new(),
+        instructions: 0,
+    };
+    let result = machine.execute();
+    Execution {
+        output: String::from_utf8_lossy(&machine.output).into_owned(),
+        result,
+        instructions: machine.instructions,
+    }
+}
+
+struct Machine<'a> {
+    program: &'a Program,
+    limits: Limits,
+    /// globals followed by the heap, addresses are indices
+    data: Vec<u8>,
+    stack: Vec<u8>,
+    /// size of every block that `malloc` returned
+    allocations: HashMap<u64, u64>,
+    /// freed blocks with their sizes, `malloc` reuses the first one that is big enough
+    free: Vec<(u64, u64)>,
+    values: Vec<i64>,
+    locals: Vec<i64>,
+    frames: Vec<Frame>,
+    output: Vec<u8>,
+    instructions: u64,
+}
+
+impl Machine<'_> {
+    fn execute(&mut self) -> Result<i64> {
+        for global in &self.program.globals {
+            let end = global.address as usize + global.bytes.len();
+            if end as u64 > self.limits.memory {
+                bail!("the globals don't fit in {} bytes", self.limits.memory);
+            }
+            if self.data.len() < end {
+                self.data.resize(end, 0);
+            }
+            self.data[global.address as usize..end].copy_from_slice(&global.bytes);
+        }
+        let main = self
+            .program
+            .function("main")
+            .context("the program has no 'main' function")?;
+        for _ in &self.program.functions[main].parameters {
+            self.push(0)?;
+        }
+        self.call(main)?;
+
+        loop {
+            let frame = self.frames.last().context("no function is running")?;
+            let function = &self.program.functions[frame.function];
+            let position = frame.position;
+            let result = self
+                .step(position)
+                .with_context(|| format!("in '{}' at instruction {position}", function.name))?;
+            if let Some(result) = result {
+                return Ok(result);
+            }
+        }
+    }
+
+    /// Executes one instruction, the result is what `main` returned once it did
+    fn step(&mut self, position: usize) -> Result<Option<i64>> {
+        let frame = self.frames.last_mut().context("no function is running")?;
+        let function = &self.program.functions[frame.function];
+        let op = *function
+            .code
+            .get(position)
+            .context("ran past the end of the function")?;
+        frame.position += 1;
+        let locals = frame.locals;
+        self.instructions += 1;
+        if self.instructions > self.limits.instructions {
+            bail!(
+                "the instruction budget of {} was used up",
+                self.limits.instructions
+            );
+        }
+
+        match op {
+            Op::Constant(index) => {
+                let value = *self
+                    .program
+                    .constants
+                    .get(index as usize)
+                    .context("constant out of range")?;
+                self.push(value)?;
+            }
+            Op::LoadLocal(local) => {
+                let value = self.locals[locals + local as usize];
+                self.push(value)?;
+            }
+            Op::StoreLocal(local) => {
+                let value = self.pop()?;
+                self.locals[locals + local as usize] = value;
+            }
+            Op::Unary(operator, data_type) => {
+                let value = self.pop()?;
+                self.push(operator.evaluate(value, data_type))?;
+            }
+            Op::Binary(operator, data_type) => {
+                let right = self.pop()?;
+                let left = self.pop()?;
+                let Some(result) = operator.evaluate(left, right, data_type) else {
+                    bail!("{} of {left} by {right}", operator.name());
+                };
+                self.push(result)?;
+            }
+            Op::Compare(condition, _) => {
+                let right = self.pop()?;
+                let left = self.pop()?;
+                self.push(condition.evaluate(left, right) as i64)?;
+            }
+            Op::Convert(conversion, from, to) => {
+                let value = self.pop()?;
+                self.push(conversion.evaluate(value, from, to))?;
+            }
+            Op::Load(data_type) => {
+                let address = self.pop()? as u64;
+                let mut bytes = [0; 8];
+                let size = data_type.size() as usize;
+                bytes[..size].copy_from_slice(self.memory(address, size as u64)?);
+                self.push(data_type.normalize(i64::from_le_bytes(bytes)))?;
+            }
+            Op::Store(data_type) => {
+                let value = self.pop()?;
+                let address = self.pop()? as u64;
+                let size = data_type.size();
+                self.memory(address, size)?
+                    .copy_from_slice(&value.to_le_bytes()[..size as usize]);
+            }
+            Op::FrameAddress(offset) => {
+                let base = self.stack_base()?;
+                self.push((STACK_BASE + base as u64 + offset as u64) as i64)?;
+            }
+            Op::CopyMemory(size) => {
+                let source = self.pop()? as u64;
+                let destination = self.pop()? as u64;
+                let bytes = self.memory(source, size as u64)?.to_vec();
+                self.memory(destination, size as u64)?
+                    .copy_from_slice(&bytes);
+            }
+            Op::ZeroMemory(size) => {
+                let destination = self.pop()? as u64;
+                self.memory(destination, size as u64)?.fill(0);
+            }
+            Op::Call(index) => {
+                if index as usize >= self.program.functions.len() {
+                    bail!("call of function #{index}, which doesn't exist");
+                }
+                self.call(index as usize)?;
+            }
+            Op::CallNative(native, count) => {
+                let start = self
+                    .values
+                    .len()
+                    .checked_sub(count as usize)
+                    .context("the stack has fewer values than the arguments")?;
+                let arguments = self.values.split_off(start);
+                let result = self
+                    .native(native, &arguments)
+                    .with_context(|| format!("in '{}'", native.name()))?;
+                if native.returns() {
+                    self.push(result)?;
+                }
+            }
+            Op::Pop => {
+                self.pop()?;
+            }
+            Op::Jump(target) => self.jump(target)?,
+            Op::JumpIfZero(target) => {
+                if self.pop()? == 0 {
+                    self.jump(target)?;
+                }
+            }
+            Op::Return => {
+                let function = &self.program.functions[self.frames[self.frames.len() - 1].function];
+                let result = if function.returns {
+                    Some(self.pop()?)
+                } else {
+                    None
+                };
+                let frame = self.frames.pop().context("no function is running")?;
+                self.locals.truncate(frame.locals);
+                self.stack.truncate(frame.stack);
+                if self.frames.is_empty() {
+                    return Ok(Some(result.unwrap_or(0)));
+                }
+                if let Some(result) = result {
+                    self.push(result)?;
+                }
+            }
+            Op::Trap => bail!("reached the end of a function that has to return a value"),
+        }
+        Ok(None)
+    }
+
+    fn push(&mut self, value: i64) -> Result<()> {
+        if self.values.len() >= MAX_VALUES {
+            bail!("too many values on the stack");
+        }
+        self.values.push(value);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<i64> {
+        self.values.pop().context("pop of an empty stack")
+    }
+
+    fn jump(&mut self, target: u32) -> Result<()> {
+        let frame = self.frames.last_mut().context("no function is running")?;
+        if target as usize >= self.program.functions[frame.function].code.len() {
+            bail!("jump to {target}, past the end of the function");
+        }
+        frame.position = target as usize;
+        Ok(())
+    }
+
+    fn stack_base(&self) -> Result<usize> {
+        let frame = self.frames.last().context("no function is running")?;
+        Ok(frame.stack.next_multiple_of(16))
+    }
+
+    /// Opens a frame, the arguments are on top of the stack
+    fn call(&mut self, index: usize) -> Result<()> {
+        let function = &self.program.functions[index];
+        let start = self
+            .values
+            .len()
+            .checked_sub(function.parameters.len())
+            .context("the stack has fewer values than the arguments")?;
+        // the sizes come from the file, they are checked before anything is allocated
+        let locals = self.locals.len();
+        let stack = self.stack.len();
+        let new_stack = stack.next_multiple_of(16) + function.frame_size as usize;
+        let needed = self.memory_used()
+            + function.locals as u64 * 8
+            + (new_stack - stack) as u64
+            + FRAME_COST;
+        if needed > self.limits.memory {
+            bail!(
+                "stack overflow, '{}' needs more than {} bytes of memory",
+                function.name,
+                self.limits.memory
+            );
+        }
+        let arguments = self.values.split_off(start);
+        self.locals.resize(locals + function.locals as usize, 0);
+        for (parameter, argument) in function.parameters.iter().zip(arguments) {
+            let local = self
+                .locals
+                .get_mut(locals + *parameter as usize)
+                .context("parameter out of range")?;
+            *local = argument;
+        }
+        self.stack.resize(new_stack, 0);
+        self.frames.push(Frame {
+            function: index,
+            position: 0,
+            locals,
+            stack,
+        });
+        Ok(())
+    }
+
+    fn memory_used(&self) -> u64 {
+        (self.data.len() + self.stack.len() + self.locals.len() * 8) as u64
+            + self.frames.len() as u64 * FRAME_COST
+    }
+
+    fn memory(&mut self, address: u64, size: u64) -> Result<&mut [u8]> {
+        let (memory, start) = if address >= STACK_BASE {
+            (&mut self.stack, address - STACK_BASE)
+        } else if address >= GLOBAL_BASE {
+            (&mut self.data, address)
+        } else {
+            bail!("access of {size} bytes at 0x{address:x}, near the null pointer");
+        };
+        match start.checked_add(size) {
+            Some(end) if end <= memory.len() as u64 => {
+                Ok(&mut memory[start as usize..end as usize])
+            }
+            _ => bail!("access of {size} bytes at 0x{address:x}, outside of the memory"),
+        }
+    }
+
+    fn string(&mut self, address: u64) -> Result<Vec<u8>> {
+        let mut string = Vec::new();
+        loop {
+            let byte = self.memory(address + string.len() as u64, 1)?[0];
+            if byte == 0 {
+                return Ok(string);
+            }
+            string.push(byte);
+        }
+    }
+
+    fn native(&mut self, native: Native, arguments: &[i64]) -> Result<i64> {
+        let expected = match native {
+            Native::Printf => 1..=usize::MAX,
+            _ => 1..=1,
+        };
+        if !expected.contains(&arguments.len()) {
+            bail!("wrong number of arguments: {}", arguments.len());
+        }
+        let argument = arguments[0];
+        match native {
+            Native::Printf => self.printf(arguments),
+            Native::Malloc => Ok(self.malloc(argument as u64)? as i64),
+            Native::Free => {
+                let address = argument as u64;
+                if address != 0 {
+                    let size = self.allocations.remove(&address).with_context(|| {
+                        format!("free of 0x{address:x}, which malloc didn't return")
+                    })?;
+                    self.free.push((address, size));
+                }
+                Ok(0)
+            }
+            Native::Strlen => Ok(self.string(argument as u64)?.len() as i64),
+        }
+    }
+
+    fn malloc(&mut self, size: u64) -> Result<u64> {
+        let size = size.max(1).next_multiple_of(16);
+        if let Some(index) = self.free.iter().position(|(_, free)| *free >= size) {
+            let (address, free) = self.free.swap_remove(index);
+            if free > size {
+                self.free.push((address + size, free - size));
+            }
+            self.memory(address, size)?.fill(0);
+            self.allocations.insert(address, size);
+            return Ok(address);
+        }
+        let address = (self.data.len() as u64).next_multiple_of(16);
+        if self
+            .memory_used()
+            .saturating_add(address + size - self.data.len() as u64)
+            > self.limits.memory
+        {
+            return Ok(0);
+        }
+        self.data.resize((address + size) as usize, 0);
+        self.allocations.insert(address, size);
+        Ok(address)
+    }
+
+    /// `%d %i %u %x %X %o %c %s %p %%` with flags, width, precision and length modifiers
+    fn printf(&mut self, arguments: &[i64]) -> Result<i64> {
+        let format = self.string(arguments[0] as u64)?;
+        let mut arguments = arguments[1..].iter().copied();
+        let mut output: Vec<u8> = Vec::new();
+        let mut bytes = format.iter().copied().peekable();
+
+        while let Some(byte) = bytes.next() {
+            if byte != b'%' {
+                output.push(byte);
+                continue;
+            }
+            let (specification, length) = Specification::parse(&mut bytes);
+            let Some(conversion) = bytes.next() else {
+                bail!("the format string ends in the middle of a conversion");
+            };
+            if conversion == b'%' {
+                output.push(b'%');
+                continue;
+            }
+            let Some(argument) = arguments.next() else {
+                bail!(
+                    "'%{}' in the format string has no argument",
+                    conversion as char
+                );
+            };
+            match conversion {
+                b'd' | b'i' | b'u' | b'x' | b'X' | b'o' => output.extend(
+                    specification
+                        .integer(conversion, argument as i128, &length)
+                        .into_bytes(),
+                ),
+                b'c' => output.extend(specification.bytes(&[argument as u8])),
+                b's' if argument == 0 => {
+                    output.extend(specification.pad("", "(null)").into_bytes())
+                }
+                b's' => {
+                    let string = self.string(argument as u64)?;
+                    output.extend(specification.string(string));
+                }
+                b'p' if argument == 0 => output.extend(specification.pad("", "(nil)").into_bytes()),
+                b'p' => output.extend(
+                    specification
+                        .pad("0x", &format!("{:x}", argument as u64))
+                        .into_bytes(),
+                ),
+                _ => bail!(
+                    "unsupported conversion '%{}' in the format string",
+                    conversion as char
+                ),
+            }
+        }
+
+        let written = output.len();
+        self.output.extend(output);
+        Ok(Type::I32.wrap(written as i64))
+    }
+}
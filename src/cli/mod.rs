@@ -0,0 +1,417 @@
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    analysis::metrics::Threshold,
+    bytecode::vm::DEFAULT_MEMORY_LIMIT,
+    format::{self, BraceStyle},
+    interpreter::DEFAULT_MAX_STEPS,
+    ir::optimize::{DEFAULT_PASSES, Pass},
+    layout::target::Target,
+};
+
+const DEFAULT_FILE_PATH: &str = "test_files/test.c";
+
+/// `-D` and `-U` are applied in the same order as they were given, like in cc
+#[derive(Debug, Clone)]
+pub enum CommandLineMacro {
+    /// `NAME` or `NAME=VALUE`
+    Define(String),
+    Undefine(String),
+}
+
+/// What `--emit=KIND` writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emit {
+    /// textual dump of the three-address IR
+    Ir,
+    /// x86-64 assembly for the GNU assembler
+    Asm,
+    /// WebAssembly text format
+    Wat,
+    /// textual LLVM IR, generated from the syntax tree instead of the three-address IR
+    Llvm,
+    /// binary bytecode for `--vm`, only to a file
+    Bytecode,
+}
+
+impl Emit {
+    fn by_name(name: &str) -> Result<Self> {
+        Ok(match name {
+            "ir" => Emit::Ir,
+            "asm" => Emit::Asm,
+            "wat" => Emit::Wat,
+            "llvm" => Emit::Llvm,
+            "bytecode" => Emit::Bytecode,
+            name => bail!(
+                "unknown kind of output: '{name}', expected one of: ir, asm, wat, llvm, bytecode"
+            ),
+        })
+    }
+}
+
+/// What `--dot=KIND` draws
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dot {
+    /// the expression tree
+    Ast,
+    /// calls between functions
+    CallGraph,
+    /// control-flow graph of every function
+    Cfg,
+}
+
+impl Dot {
+    fn by_name(name: &str) -> Result<Self> {
+        Ok(match name {
+            "ast" => Dot::Ast,
+            "callgraph" => Dot::CallGraph,
+            "cfg" => Dot::Cfg,
+            name => bail!("unknown kind of graph: '{name}', expected one of: ast, callgraph, cfg"),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub file: String,
+    pub macros: Vec<CommandLineMacro>,
+    /// `-E`: only preprocess and print the result
+    pub preprocess_only: bool,
+    /// `-C`: keep comments in the output of `-E`
+    pub keep_comments: bool,
+    /// `--symbols`: print declarations and what every name resolved to
+    pub print_symbols: bool,
+    /// `--target NAME` with `--type-size NAME=SIZE[:ALIGNMENT]` applied to it
+    pub target: Target,
+    /// `--layout`: print the layout of every typedef
+    pub print_layout: bool,
+    /// `--run`: execute the program with the interpreter
+    pub run: bool,
+    /// `--max-steps N`: the interpreter gives up after this many steps, and the VM after this
+    /// many instructions
+    pub max_steps: u64,
+    /// `--vm`: compile the program to bytecode and execute it, the input can be bytecode that
+    /// `--emit=bytecode` wrote
+    pub vm: bool,
+    /// `--max-memory BYTES`: memory that the VM can use
+    pub max_memory: u64,
+    /// `--disassemble`: print the bytecode of the program
+    pub disassemble: bool,
+    /// `--zig FILE`: write the program translated to Zig
+    pub zig_output: Option<String>,
+    /// `--rust FILE`: write Rust FFI bindings for the program
+    pub rust_output: Option<String>,
+    /// `--bindings-allow PATTERN`: only generate bindings for these names, can be repeated
+    pub bindings_allow: Vec<String>,
+    /// `--bindings-deny PATTERN`: never generate bindings for these names, can be repeated
+    pub bindings_deny: Vec<String>,
+    /// `--emit=KIND`: compile the program and write the result to `-o FILE` or stdout
+    pub emit: Option<Emit>,
+    /// `--dot=KIND`: draw the program as a Graphviz graph to `-o FILE` or stdout
+    pub dot: Option<Dot>,
+    /// `-o FILE`
+    pub output: Option<String>,
+    /// `--passes=LIST`: optimization passes to run on the IR in order, separated by commas, or
+    /// the default ones with `-O`
+    pub passes: Vec<Pass>,
+    /// `--print-c`: print the program written back out as C
+    pub print_c: bool,
+    /// `--ast-json`: print the parsed program as JSON
+    pub ast_json: bool,
+    /// `--lint`: run the lint rules after the semantic passes
+    pub lint: bool,
+    /// `--lint-config FILE`: TOML file with the severity of every rule, implies `--lint`
+    pub lint_config: Option<String>,
+    /// `--round-trip`: check that printing the program as C and parsing it again gives the
+    /// same tree, the input can be a directory of `.c` files
+    pub round_trip: bool,
+}
+
+/// What the binary was asked to do, `rip fmt ...` formats files instead of compiling one
+#[derive(Debug, Clone)]
+pub enum Command {
+    Compile(Box<Options>),
+    Format(FormatOptions),
+    Metrics(Box<MetricsOptions>),
+}
+
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// files and directories of `.c` files
+    pub files: Vec<String>,
+    /// `--check`: don't write anything, fail when a file isn't formatted
+    pub check: bool,
+    /// `--stdout`: print the formatted files instead of writing them
+    pub stdout: bool,
+    /// `--indent N`, `--braces STYLE` and `--max-width N`
+    pub config: format::Config,
+}
+
+/// How `rip metrics` prints the report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricsFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+impl MetricsFormat {
+    fn by_name(name: &str) -> Result<Self> {
+        Ok(match name {
+            "table" => MetricsFormat::Table,
+            "json" => MetricsFormat::Json,
+            "csv" => MetricsFormat::Csv,
+            name => bail!("unknown format of metrics: '{name}', expected one of: table, json, csv"),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MetricsOptions {
+    /// `--format table|json|csv`
+    pub format: MetricsFormat,
+    /// `--threshold METRIC=N`, can be repeated
+    pub thresholds: Vec<Threshold>,
+    /// the other flags are the ones of compiling, the input can be a directory of `.c` files
+    /// and the report goes to `-o FILE` or stdout
+    pub options: Options,
+}
+
+pub fn parse_command(args: impl Iterator<Item = String>) -> Result<Command> {
+    let mut args = args.peekable();
+    if args.next_if(|arg| arg == "fmt").is_some() {
+        return parse_format_args(args).map(Command::Format);
+    }
+    if args.next_if(|arg| arg == "metrics").is_some() {
+        return parse_metrics_args(args).map(|options| Command::Metrics(Box::new(options)));
+    }
+    parse_args(args).map(|options| Command::Compile(Box::new(options)))
+}
+
+/// Value of `flag` if `arg` is that flag. The value is the next argument (`--emit llvm`) or
+/// follows a `=` (`--emit=llvm`), short flags also take it glued to them (`-DNAME`).
+fn flag_value(
+    arg: &str,
+    flag: &str,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<Option<String>> {
+    let Some(rest) = arg.strip_prefix(flag) else {
+        return Ok(None);
+    };
+    if rest.is_empty() {
+        let value = args
+            .next()
+            .with_context(|| format!("expected to find a value after: '{flag}'"))?;
+        return Ok(Some(value));
+    }
+    if let Some(value) = rest.strip_prefix('=') {
+        return Ok(Some(value.to_owned()));
+    }
+    match flag.starts_with("--") {
+        true => Ok(None),
+        false => Ok(Some(rest.to_owned())),
+    }
+}
+
+fn parse_format_args(args: impl Iterator<Item = String>) -> Result<FormatOptions> {
+    let mut args = args.peekable();
+    let mut files = Vec::new();
+    let mut check = false;
+    let mut stdout = false;
+    let mut config = format::Config::default();
+
+    while let Some(arg) = args.next() {
+        if arg == "--check" {
+            check = true;
+        } else if arg == "--stdout" {
+            stdout = true;
+        } else if let Some(value) = flag_value(&arg, "--indent", &mut args)? {
+            config.indent_width = value
+                .parse()
+                .with_context(|| format!("invalid indent width: '{value}'"))?;
+        } else if let Some(value) = flag_value(&arg, "--braces", &mut args)? {
+            config.brace_style = BraceStyle::by_name(&value)?;
+        } else if let Some(value) = flag_value(&arg, "--max-width", &mut args)? {
+            config.max_width = value
+                .parse()
+                .with_context(|| format!("invalid line width: '{value}'"))?;
+        } else if arg.starts_with('-') {
+            bail!("unknown flag of fmt: '{arg}'")
+        } else {
+            files.push(arg);
+        }
+    }
+    if files.is_empty() {
+        bail!("expected files or directories to format after: 'fmt'");
+    }
+
+    Ok(FormatOptions {
+        files,
+        check,
+        stdout,
+        config,
+    })
+}
+
+fn parse_metrics_args(args: impl Iterator<Item = String>) -> Result<MetricsOptions> {
+    let mut args = args.peekable();
+    let mut format = MetricsFormat::Table;
+    let mut thresholds = Vec::new();
+    let mut rest = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if let Some(value) = flag_value(&arg, "--format", &mut args)? {
+            format = MetricsFormat::by_name(&value)?;
+        } else if let Some(value) = flag_value(&arg, "--threshold", &mut args)? {
+            thresholds.push(Threshold::parse(&value)?);
+        } else {
+            rest.push(arg);
+        }
+    }
+
+    Ok(MetricsOptions {
+        format,
+        thresholds,
+        options: parse_args(rest.into_iter())?,
+    })
+}
+
+pub fn parse_args(args: impl Iterator<Item = String>) -> Result<Options> {
+    let mut args = args.peekable();
+    let mut file = None;
+    let mut macros = Vec::new();
+    let mut preprocess_only = false;
+    let mut keep_comments = false;
+    let mut print_symbols = false;
+    let mut target = None;
+    let mut type_sizes = Vec::new();
+    let mut print_layout = false;
+    let mut run = false;
+    let mut max_steps = DEFAULT_MAX_STEPS;
+    let mut vm = false;
+    let mut max_memory = DEFAULT_MEMORY_LIMIT;
+    let mut disassemble = false;
+    let mut zig_output = None;
+    let mut rust_output = None;
+    let mut bindings_allow = Vec::new();
+    let mut bindings_deny = Vec::new();
+    let mut emit = None;
+    let mut dot = None;
+    let mut output = None;
+    let mut passes = Vec::new();
+    let mut print_c = false;
+    let mut ast_json = false;
+    let mut round_trip = false;
+    let mut lint = false;
+    let mut lint_config = None;
+
+    while let Some(arg) = args.next() {
+        if arg == "-E" {
+            preprocess_only = true;
+        } else if arg == "-C" {
+            keep_comments = true;
+        } else if arg == "--symbols" {
+            print_symbols = true;
+        } else if arg == "--layout" {
+            print_layout = true;
+        } else if arg == "--print-c" {
+            print_c = true;
+        } else if arg == "--ast-json" {
+            ast_json = true;
+        } else if arg == "--round-trip" {
+            round_trip = true;
+        } else if arg == "--lint" {
+            lint = true;
+        } else if let Some(value) = flag_value(&arg, "--lint-config", &mut args)? {
+            lint_config = Some(value);
+        } else if arg == "--run" {
+            run = true;
+        } else if let Some(value) = flag_value(&arg, "--max-steps", &mut args)? {
+            max_steps = value
+                .parse()
+                .with_context(|| format!("invalid amount of steps: '{value}'"))?;
+        } else if arg == "--vm" {
+            vm = true;
+        } else if let Some(value) = flag_value(&arg, "--max-memory", &mut args)? {
+            max_memory = value
+                .parse()
+                .with_context(|| format!("invalid amount of memory: '{value}'"))?;
+        } else if arg == "--disassemble" {
+            disassemble = true;
+        } else if let Some(value) = flag_value(&arg, "--zig", &mut args)? {
+            zig_output = Some(value);
+        } else if let Some(value) = flag_value(&arg, "--rust", &mut args)? {
+            rust_output = Some(value);
+        } else if let Some(value) = flag_value(&arg, "--bindings-allow", &mut args)? {
+            bindings_allow.push(value);
+        } else if let Some(value) = flag_value(&arg, "--bindings-deny", &mut args)? {
+            bindings_deny.push(value);
+        } else if let Some(value) = flag_value(&arg, "--emit", &mut args)? {
+            emit = Some(Emit::by_name(&value)?);
+        } else if let Some(value) = flag_value(&arg, "--dot", &mut args)? {
+            dot = Some(Dot::by_name(&value)?);
+        } else if arg == "-O" {
+            passes = DEFAULT_PASSES.to_vec();
+        } else if let Some(value) = flag_value(&arg, "--passes", &mut args)? {
+            passes = value
+                .split(',')
+                .filter(|name| !name.is_empty())
+                .map(Pass::by_name)
+                .collect::<Result<_>>()?;
+        } else if let Some(value) = flag_value(&arg, "-o", &mut args)? {
+            output = Some(value);
+        } else if let Some(value) = flag_value(&arg, "--target", &mut args)? {
+            target = Some(Target::by_name(&value)?);
+        } else if let Some(value) = flag_value(&arg, "--type-size", &mut args)? {
+            type_sizes.push(value);
+        } else if let Some(value) = flag_value(&arg, "-D", &mut args)? {
+            macros.push(CommandLineMacro::Define(value));
+        } else if let Some(value) = flag_value(&arg, "-U", &mut args)? {
+            macros.push(CommandLineMacro::Undefine(value));
+        } else if arg.starts_with('-') {
+            bail!("unknown flag: '{arg}'")
+        } else if file.is_none() {
+            file = Some(arg);
+        } else {
+            bail!("only one input file is supported, found: '{arg}'")
+        }
+    }
+    if emit == Some(Emit::Bytecode) && output.is_none() {
+        bail!("--emit=bytecode writes a binary file, it needs -o FILE");
+    }
+
+    let mut target = target.unwrap_or_default();
+    for type_size in type_sizes {
+        target
+            .override_size(&type_size)
+            .with_context(|| format!("--type-size {type_size}"))?;
+    }
+
+    Ok(Options {
+        file: file.unwrap_or(DEFAULT_FILE_PATH.to_owned()),
+        macros,
+        preprocess_only,
+        keep_comments,
+        print_symbols,
+        target,
+        print_layout,
+        run,
+        max_steps,
+        vm,
+        max_memory,
+        disassemble,
+        zig_output,
+        rust_output,
+        bindings_allow,
+        bindings_deny,
+        emit,
+        dot,
+        output,
+        passes,
+        print_c,
+        ast_json,
+        lint: lint || lint_config.is_some(),
+        lint_config,
+        round_trip,
+    })
+}
@@ -0,0 +1,598 @@
+use std::collections::HashMap;
+
+use crate::{
+    lexer::token::TokenKind,
+    parser::{
+        PREFIX_BINDING_POWER, binding_powers,
+        expression::{AlignAs, Expression, Property},
+        types::DataType,
+    },
+};
+
+const INDENT: &str = "  ";
+
+/// binding power of the postfix operators: calls, `[i]`, `.`, `->`, `++` and `--`
+const POSTFIX_BINDING_POWER: i8 = PREFIX_BINDING_POWER + 1;
+/// literals, names and parenthesized expressions
+const ATOM_BINDING_POWER: i8 = POSTFIX_BINDING_POWER + 1;
+
+/// Writes the program back out as C. Parentheses from the source are kept, and the ones the
+/// tree needs but doesn't have (after a rewrite) are added based on precedence.
+pub fn print(expressions: &[Expression]) -> String {
+    let mut printer = Printer {
+        binding_powers: binding_powers(),
+        level: 0,
+    };
+    let mut output = String::new();
+    let mut previous_was_function = false;
+    for expression in expressions {
+        let is_function = matches!(expression, Expression::Function { .. });
+        if (is_function || previous_was_function) && !output.is_empty() {
+            output.push('\n');
+        }
+        output += &printer.statement(expression, 0, false);
+        previous_was_function = is_function;
+    }
+    output
+}
+
+/// One expression as C on a single line, statements without their semicolon
+pub fn expression(expression: &Expression) -> String {
+    let mut printer = Printer {
+        binding_powers: binding_powers(),
+        level: 0,
+    };
+    printer.expression(expression)
+}
+
+/// First place where the two programs differ, ignoring lines and parentheses
+pub fn difference(expected: &[Expression], found: &[Expression]) -> Option<String> {
+    let expected = expected.iter().collect::<Vec<_>>();
+    let found = found.iter().collect::<Vec<_>>();
+    list_difference(None, &expected, &found)
+}
+
+fn list_difference(
+    parent: Option<&Expression>,
+    expected: &[&Expression],
+    found: &[&Expression],
+) -> Option<String> {
+    for (expected, found) in expected.iter().zip(found) {
+        if let Some(difference) = node_difference(expected, found) {
+            return Some(difference);
+        }
+    }
+    if expected.len() == found.len() {
+        return None;
+    }
+    let place = match parent {
+        Some(parent) => format!("{} inside of {}", line(parent), label(parent)),
+        None => "the top level".to_owned(),
+    };
+    Some(format!(
+        "{place}: expected {} expressions, found {}",
+        expected.len(),
+        found.len()
+    ))
+}
+
+fn node_difference(expected: &Expression, found: &Expression) -> Option<String> {
+    let (expected, found) = (ungroup(expected), ungroup(found));
+    if label(expected) != label(found) {
+        return Some(format!(
+            "{}: expected {}, found {}",
+            line(expected),
+            label(expected),
+            label(found)
+        ));
+    }
+    list_difference(Some(expected), &children(expected), &children(found))
+}
+
+fn children(expression: &Expression) -> Vec<&Expression> {
+    expression.children().into_iter().map(ungroup).collect()
+}
+
+fn ungroup(expression: &Expression) -> &Expression {
+    match expression {
+        Expression::Grouping { value, .. } => ungroup(value),
+        expression => expression,
+    }
+}
+
+fn line(expression: &Expression) -> String {
+    match expression.debug_data() {
+        Some(debug_data) => format!("line {}", debug_data.line),
+        None => "unknown line".to_owned(),
+    }
+}
+
+/// Kind of the node and what it holds besides other expressions
+fn label(expression: &Expression) -> String {
+    match expression {
+        Expression::Skip => "nothing".to_owned(),
+        Expression::Increment { .. } => "'++'".to_owned(),
+        Expression::Decrement { .. } => "'--'".to_owned(),
+        Expression::DataStructureInitialization { .. } => "initializer list".to_owned(),
+        Expression::TypeConversion { data_type, .. } => format!("cast to {data_type:?}"),
+        Expression::Typedef {
+            data_type, name, ..
+        } => format!("typedef '{name}' of {data_type:?}"),
+        Expression::Arrow { .. } => "'->'".to_owned(),
+        Expression::Dereference { .. } => "dereference".to_owned(),
+        Expression::Boolean(value, _) => format!("boolean {value}"),
+        Expression::Number(value, literal, _) => format!("number {}", literal.text(*value)),
+        Expression::CompilerData(value, _) => format!("'#{}'", value.trim()),
+        Expression::String(value, _) => format!("string \"{value}\""),
+        Expression::Character(value, _) => format!("character '{value}'"),
+        Expression::Identifier(name, _) => format!("identifier '{name}'"),
+        Expression::Prefix { prefix, .. } => format!("prefix '{}'", prefix.kind.to_str()),
+        Expression::Assignment { operator, .. } => {
+            format!("assignment '{}'", operator.kind.to_str())
+        }
+        Expression::DataTypeAccess { data_type, .. } => format!("type {data_type:?}"),
+        Expression::VariableDeclaration { var_type, name, .. } => {
+            format!("declaration of '{name}' as {var_type:?}")
+        }
+        Expression::Grouping { .. } => "parentheses".to_owned(),
+        Expression::Struct {
+            public,
+            name,
+            properties,
+            ..
+        } => format!("struct '{name}' (public: {public}) with {properties:?}"),
+        Expression::NewCodeBlock { .. } => "block".to_owned(),
+        Expression::Binary { operator, .. } => format!("binary '{}'", operator.kind.to_str()),
+        Expression::Function {
+            name,
+            properties,
+            output,
+            ..
+        } => format!("function '{name}' with {properties:?} returning {output:?}"),
+        Expression::FunctionDeclaration {
+            name,
+            properties,
+            output,
+            variadic,
+            ..
+        } => format!(
+            "prototype of '{name}' with {properties:?} returning {output:?} (variadic: {variadic})"
+        ),
+        Expression::MemberExpr { .. } => "'.'".to_owned(),
+        Expression::AccessReference { .. } => "address of".to_owned(),
+        Expression::Break { .. } => "break".to_owned(),
+        Expression::Return { .. } => "return".to_owned(),
+        Expression::If { .. } => "if".to_owned(),
+        Expression::Else { condition, .. } => match condition {
+            Some(_) => "else if".to_owned(),
+            None => "else".to_owned(),
+        },
+        Expression::AccessArray { .. } => "array access".to_owned(),
+        Expression::While { .. } => "while".to_owned(),
+        Expression::Static { .. } => "static".to_owned(),
+        Expression::For { .. } => "for".to_owned(),
+        Expression::FunctionCall { .. } => "call".to_owned(),
+    }
+}
+
+fn indent(level: usize) -> String {
+    INDENT.repeat(level)
+}
+
+struct Printer {
+    binding_powers: HashMap<TokenKind, i8>,
+    /// indentation of the statement being printed, records inside of it are indented deeper
+    level: usize,
+}
+
+impl Printer {
+    /// Splits a type into its base type and a declarator around `name`, like `int` and
+    /// `*values[4]`
+    fn declarator<'t>(&mut self, data_type: &'t DataType, name: String) -> (&'t DataType, String) {
+        match data_type {
+            DataType::Pointer(inside) => {
+                let name = match inside.as_ref() {
+                    DataType::Array { .. } | DataType::Function { .. } => format!("(*{name})"),
+                    _ => format!("*{name}"),
+                };
+                self.declarator(inside, name)
+            }
+            DataType::Array { length, inside } => {
+                self.declarator(inside, format!("{name}[{length}]"))
+            }
+            DataType::Function {
+                output,
+                parameters,
+                variadic,
+            } => {
+                let mut parameters = parameters
+                    .iter()
+                    .map(|parameter| self.declaration(parameter, ""))
+                    .collect::<Vec<_>>();
+                if *variadic {
+                    parameters.push("...".to_owned());
+                }
+                self.declarator(output, format!("{name}({})", parameters.join(", ")))
+            }
+            data_type => (data_type, name),
+        }
+    }
+
+    /// `int *values[4]`, or only the type when the name is empty
+    fn declaration(&mut self, data_type: &DataType, name: &str) -> String {
+        let (base, declarator) = self.declarator(data_type, name.to_owned());
+        let base = self.base_type(base);
+        if declarator.is_empty() {
+            base
+        } else {
+            format!("{base} {declarator}")
+        }
+    }
+
+    fn base_type(&mut self, data_type: &DataType) -> String {
+        match data_type {
+            DataType::Data {
+                name,
+                unsigned,
+                constant,
+            } => {
+                let mut output = String::new();
+                if *constant {
+                    output += "const ";
+                }
+                if *unsigned {
+                    output += "unsigned ";
+                }
+                output + name
+            }
+            DataType::Struct { properties, .. } => self.record("struct", properties),
+            DataType::Union { properties, .. } => self.record("union", properties),
+            // values are only written when they don't follow from the previous one
+            DataType::Enum { fields } => {
+                let mut next = 0;
+                let fields = fields
+                    .iter()
+                    .map(|field| {
+                        let text = match field.value == next {
+                            true => field.name.to_owned(),
+                            false => format!("{} = {}", field.name, field.value),
+                        };
+                        next = field.value + 1;
+                        text
+                    })
+                    .collect::<Vec<_>>();
+                format!("enum {{ {} }}", fields.join(", "))
+            }
+            data_type => self.declaration(data_type, ""),
+        }
+    }
+
+    fn record(&mut self, keyword: &str, properties: &[Property]) -> String {
+        if properties.is_empty() {
+            return format!("{keyword} {{}}");
+        }
+        let level = self.level;
+        self.level += 1;
+        let mut output = format!("{keyword} {{\n");
+        for property in properties {
+            let alignas = match &property.alignas {
+                Some(AlignAs::Bytes(bytes)) => format!("_Alignas({bytes}) "),
+                Some(AlignAs::Type(data_type)) => {
+                    format!("_Alignas({}) ", self.declaration(data_type, ""))
+                }
+                None => String::new(),
+            };
+            output += &format!(
+                "{}{alignas}{};\n",
+                indent(level + 1),
+                self.declaration(&property.var_type, &property.var_name)
+            );
+        }
+        self.level = level;
+        output + &indent(level) + "}"
+    }
+
+    // -- statements --
+
+    /// Statement on its own lines. Bodies of `if`, `else`, `while` and `for` need a semicolon
+    /// after every statement, even after a `}`, which is what `terminated` is for.
+    fn statement(&mut self, expression: &Expression, level: usize, terminated: bool) -> String {
+        self.level = level;
+        let tab = indent(level);
+        match expression {
+            Expression::Skip => String::new(),
+            Expression::CompilerData(value, _) => format!("#{}\n", value.trim_end()),
+            Expression::Function { .. }
+            | Expression::If { .. }
+            | Expression::While { .. }
+            | Expression::For { .. }
+            | Expression::NewCodeBlock { .. } => {
+                let terminator = if terminated { ";" } else { "" };
+                format!("{tab}{}{terminator}\n", self.compound(expression, level))
+            }
+            Expression::Struct {
+                public,
+                name,
+                properties,
+                functions,
+                ..
+            } => {
+                let public = if *public { "pub " } else { "" };
+                let mut output = format!(
+                    "{tab}{public}{name} {};\n",
+                    self.record("struct", properties)
+                );
+                for function in functions {
+                    output += &self.statement(function, level, false);
+                }
+                output
+            }
+            expression => format!("{tab}{};\n", self.expression(expression)),
+        }
+    }
+
+    /// Statement that ends with a body, without its indentation
+    fn compound(&mut self, expression: &Expression, level: usize) -> String {
+        match expression {
+            Expression::Function {
+                name,
+                properties,
+                output,
+                inside,
+                ..
+            } => {
+                let signature = self.signature(name, properties, output, false);
+                format!("{signature} {}", self.body(inside, level, false))
+            }
+            Expression::If {
+                condition,
+                inside,
+                chained_elses,
+                ..
+            } => {
+                let mut output = format!(
+                    "if ({}) {}",
+                    self.expression(condition),
+                    self.body(inside, level, true)
+                );
+                for chained in chained_elses {
+                    output += " ";
+                    output += &self.compound(chained, level);
+                }
+                output
+            }
+            Expression::Else {
+                condition, inside, ..
+            } => match condition {
+                Some(condition) => format!(
+                    "else if ({}) {}",
+                    self.expression(condition),
+                    self.body(inside, level, true)
+                ),
+                None => format!("else {}", self.body(inside, level, true)),
+            },
+            Expression::While {
+                condition, inside, ..
+            } => format!(
+                "while ({}) {}",
+                self.expression(condition),
+                self.body(inside, level, true)
+            ),
+            Expression::For {
+                iterator_init,
+                condition,
+                incr,
+                inside,
+                ..
+            } => {
+                let header = [iterator_init, condition, incr]
+                    .map(|part| self.expression(part))
+                    .join("; ");
+                format!(
+                    "for ({}) {}",
+                    header.trim_end(),
+                    self.body(inside, level, true)
+                )
+            }
+            Expression::NewCodeBlock { inside, .. } => self.body(inside, level, false),
+            expression => self.expression(expression),
+        }
+    }
+
+    /// `int *name(int a, ...)`
+    fn signature(
+        &mut self,
+        name: &str,
+        properties: &[Property],
+        output: &DataType,
+        variadic: bool,
+    ) -> String {
+        let mut parameters = properties
+            .iter()
+            .map(|property| self.declaration(&property.var_type, &property.var_name))
+            .collect::<Vec<_>>();
+        if variadic {
+            parameters.push("...".to_owned());
+        }
+        self.declaration(output, &format!("{name}({})", parameters.join(", ")))
+    }
+
+    fn body(&mut self, inside: &[Expression], level: usize, terminated: bool) -> String {
+        if inside.is_empty() {
+            return "{}".to_owned();
+        }
+        let mut output = String::from("{\n");
+        for expression in inside {
+            output += &self.statement(expression, level + 1, terminated);
+        }
+        self.level = level;
+        output + &indent(level) + "}"
+    }
+
+    // -- expressions --
+
+    /// How tightly the expression holds together, anything binding looser than its context
+    /// needs parentheses
+    fn binding_power(&self, expression: &Expression) -> i8 {
+        match expression {
+            Expression::Assignment { .. } => 1,
+            Expression::Binary { operator, .. } => self
+                .binding_powers
+                .get(&operator.kind)
+                .copied()
+                .unwrap_or_default(),
+            Expression::Prefix { .. }
+            | Expression::Dereference { .. }
+            | Expression::AccessReference { .. }
+            | Expression::TypeConversion { .. } => PREFIX_BINDING_POWER,
+            Expression::Increment { .. }
+            | Expression::Decrement { .. }
+            | Expression::AccessArray { .. }
+            | Expression::FunctionCall { .. }
+            | Expression::MemberExpr { .. }
+            | Expression::Arrow { .. } => POSTFIX_BINDING_POWER,
+            _ => ATOM_BINDING_POWER,
+        }
+    }
+
+    /// Operand that has to bind at least as tightly as `minimum`
+    fn operand(&mut self, expression: &Expression, minimum: i8) -> String {
+        let text = self.expression(expression);
+        if self.binding_power(expression) < minimum {
+            format!("({text})")
+        } else {
+            text
+        }
+    }
+
+    /// `-` in front of `-x` would turn into `--`
+    fn prefix(&mut self, operator: &str, value: &Expression) -> String {
+        let value = self.operand(value, PREFIX_BINDING_POWER);
+        let glued = matches!(
+            (operator.chars().last(), value.chars().next()),
+            (Some(last), Some(first)) if last == first && "+-&".contains(first)
+        );
+        if glued {
+            format!("{operator} {value}")
+        } else {
+            format!("{operator}{value}")
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) -> String {
+        match expression {
+            Expression::Skip => String::new(),
+            Expression::Boolean(value, _) => value.to_string(),
+            Expression::Number(value, literal, _) => literal.text(*value),
+            Expression::String(value, _) => format!("\"{value}\""),
+            Expression::Character(value, _) => format!("'{value}'"),
+            Expression::Identifier(name, _) => name.to_owned(),
+            Expression::CompilerData(value, _) => format!("#{}", value.trim_end()),
+            Expression::Break { .. } => "break".to_owned(),
+            Expression::Return { value, .. } => match value.as_ref() {
+                Expression::Skip => "return".to_owned(),
+                value => format!("return {}", self.expression(value)),
+            },
+            Expression::Static { value, .. } => format!("static {}", self.expression(value)),
+            Expression::Typedef {
+                data_type, name, ..
+            } => format!("typedef {}", self.declaration(data_type, name)),
+            Expression::VariableDeclaration { var_type, name, .. } => {
+                self.declaration(var_type, name)
+            }
+            Expression::DataTypeAccess { data_type, .. } => self.declaration(data_type, ""),
+            Expression::FunctionDeclaration {
+                name,
+                properties,
+                output,
+                variadic,
+                ..
+            } => self.signature(name, properties, output, *variadic),
+            Expression::Grouping { value, .. } => format!("({})", self.expression(value)),
+            Expression::DataStructureInitialization { values, .. } => {
+                let values = values
+                    .iter()
+                    .map(|value| self.expression(value))
+                    .collect::<Vec<_>>();
+                format!("{{{}}}", values.join(", "))
+            }
+            Expression::Assignment {
+                target,
+                operator,
+                value,
+                ..
+            } => format!(
+                "{} {} {}",
+                self.operand(target, 2),
+                operator.kind.to_str(),
+                self.operand(value, 1)
+            ),
+            // left associative, so an operand on the right with the same power needs parentheses
+            Expression::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => {
+                let power = self.binding_power(expression);
+                format!(
+                    "{} {} {}",
+                    self.operand(left, power),
+                    operator.kind.to_str(),
+                    self.operand(right, power + 1)
+                )
+            }
+            Expression::Prefix { prefix, value, .. } => self.prefix(prefix.kind.to_str(), value),
+            Expression::Dereference { value, .. } => self.prefix("*", value),
+            Expression::AccessReference { value, .. } => self.prefix("&", value),
+            Expression::TypeConversion {
+                value, data_type, ..
+            } => {
+                let data_type = self.declaration(data_type, "");
+                self.prefix(&format!("({data_type})"), value)
+            }
+            Expression::Increment { target, .. } => {
+                format!("{}++", self.operand(target, POSTFIX_BINDING_POWER))
+            }
+            Expression::Decrement { target, .. } => {
+                format!("{}--", self.operand(target, POSTFIX_BINDING_POWER))
+            }
+            Expression::AccessArray { left, index, .. } => format!(
+                "{}[{}]",
+                self.operand(left, POSTFIX_BINDING_POWER),
+                self.expression(index)
+            ),
+            Expression::FunctionCall { left, values, .. } => {
+                let callee = self.operand(left, POSTFIX_BINDING_POWER);
+                let values = values
+                    .iter()
+                    .map(|value| self.expression(value))
+                    .collect::<Vec<_>>();
+                format!("{callee}({})", values.join(", "))
+            }
+            Expression::MemberExpr { left, right, .. } => format!(
+                "{}.{}",
+                self.operand(left, POSTFIX_BINDING_POWER),
+                self.expression(right)
+            ),
+            Expression::Arrow { left, right, .. } => format!(
+                "{}->{}",
+                self.operand(left, POSTFIX_BINDING_POWER),
+                self.expression(right)
+            ),
+            Expression::Function { .. }
+            | Expression::If { .. }
+            | Expression::Else { .. }
+            | Expression::While { .. }
+            | Expression::For { .. }
+            | Expression::NewCodeBlock { .. } => {
+                let level = self.level;
+                self.compound(expression, level)
+            }
+            Expression::Struct { .. } => {
+                let level = self.level;
+                self.statement(expression, level, false).trim().to_owned()
+            }
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use crate::{
+    analysis::{
+        call_graph::CallGraph,
+        cfg::{Cfg, ENTRY, EXIT, EdgeKind},
+    },
+    code_gen::c,
+    parser::{
+        expression::{Expression, Property},
+        types::DataType,
+    },
+};
+
+const HEADER: &str = "    node [shape=box, fontname=\"monospace\"];\n";
+
+/// `--dot=ast`: every expression is a node labelled with its kind, its name or value and its
+/// line, with edges to its children in source order
+pub fn ast(expressions: &[Expression]) -> String {
+    let mut output = format!("digraph ast {{\n{HEADER}");
+    let mut next = 0;
+    for expression in expressions {
+        ast_node(expression, &mut next, &mut output);
+    }
+    output += "}\n";
+    output
+}
+
+/// Writes the node and its children, the result is its number
+fn ast_node(expression: &Expression, next: &mut usize, output: &mut String) -> usize {
+    let id = *next;
+    *next += 1;
+    let mut label = kind(expression).to_owned();
+    if let Some(detail) = detail(expression) {
+        label += &format!("\n{detail}");
+    }
+    if let Some(debug_data) = expression.debug_data() {
+        label += &format!("\nline {}", debug_data.line);
+    }
+    *output += &format!("    n{id} [label=\"{}\"];\n", escape(&label));
+    for child in expression.children() {
+        let child = ast_node(child, next, output);
+        *output += &format!("    n{id} -> n{child};\n");
+    }
+    id
+}
+
+/// `--dot=callgraph`: a node for every function definition and for every function that they
+/// call without it being defined, drawn dashed. Edges are labelled with the lines of the calls.
+pub fn call_graph(graph: &CallGraph) -> String {
+    let mut output = format!("digraph callgraph {{\n{HEADER}");
+    for (id, function) in graph.functions.iter().enumerate() {
+        let (place, style) = match (function.defined, function.line) {
+            (true, Some(line)) => (format!("line {line}"), ""),
+            (false, Some(line)) => (format!("declared on line {line}"), ", style=dashed"),
+            _ => ("not declared".to_owned(), ", style=dashed"),
+        };
+        let label = escape(&format!("{}\n{place}", function.name));
+        output += &format!("    f{id} [label=\"{label}\"{style}];\n");
+    }
+    for call in &graph.calls {
+        let lines = call
+            .lines
+            .iter()
+            .map(u16::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let plural = if call.lines.len() == 1 { "" } else { "s" };
+        output += &format!(
+            "    f{} -> f{} [label=\"line{plural} {lines}\"];\n",
+            call.caller, call.callee
+        );
+    }
+    output += "}\n";
+    output
+}
+
+/// `--dot=cfg`: a cluster for every function with its basic blocks. Blocks list their
+/// statements with lines and end with their condition, blocks that can't run are dashed.
+pub fn cfg(graphs: &[Cfg]) -> String {
+    let mut output = format!("digraph cfg {{\n{HEADER}");
+    for (function, graph) in graphs.iter().enumerate() {
+        output += &format!("    subgraph cluster_{function} {{\n");
+        output += &format!(
+            "        label=\"{}, line {}\";\n",
+            escape(graph.function),
+            graph.line
+        );
+        let reachable = graph.reachable();
+        for (id, block) in graph.blocks.iter().enumerate() {
+            let name = format!("f{function}_{id}");
+            if id == EXIT {
+                output += &format!("        {name} [label=\"exit\", shape=oval];\n");
+                continue;
+            }
+            let mut label = match id {
+                ENTRY => "entry\\l".to_owned(),
+                id => format!("B{id}\\l"),
+            };
+            for statement in &block.statements {
+                label += &escape(&format!(
+                    "{}: {}",
+                    line(statement),
+                    c::expression(statement)
+                ));
+                label += "\\l";
+            }
+            if let Some(condition) = block.condition {
+                label += &escape(&format!(
+                    "{}: {} ?",
+                    line(condition),
+                    c::expression(condition)
+                ));
+                label += "\\l";
+            }
+            let style = if reachable[id] { "" } else { ", style=dashed" };
+            output += &format!("        {name} [label=\"{label}\"{style}];\n");
+        }
+        for (id, block) in graph.blocks.iter().enumerate() {
+            for edge in &block.successors {
+                let label = match edge.kind {
+                    EdgeKind::Always => "",
+                    EdgeKind::True => " [label=\"true\"]",
+                    EdgeKind::False => " [label=\"false\"]",
+                };
+                output += &format!(
+                    "        f{function}_{id} -> f{function}_{}{label};\n",
+                    edge.target
+                );
+            }
+        }
+        output += "    }\n";
+    }
+    output += "}\n";
+    output
+}
+
+fn line(expression: &Expression) -> String {
+    match expression.debug_data() {
+        Some(debug_data) => format!("line {}", debug_data.line),
+        None => "line ?".to_owned(),
+    }
+}
+
+/// Text inside of a quoted DOT string, `\n` stays a line break
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn signature(name: &str, properties: &[Property], output: &DataType, variadic: bool) -> String {
+    let mut parameters = properties
+        .iter()
+        .map(|property| property.var_type.declaration(&property.var_name))
+        .map(|parameter| parameter.trim_end().to_owned())
+        .collect::<Vec<_>>();
+    if variadic {
+        parameters.push("...".to_owned());
+    }
+    output.declaration(&format!("{name}({})", parameters.join(", ")))
+}
+
+/// Name or value that tells nodes of the same kind apart
+fn detail(expression: &Expression) -> Option<String> {
+    Some(match expression {
+        Expression::Boolean(value, _) => value.to_string(),
+        Expression::Number(value, literal, _) => literal.text(*value),
+        Expression::CompilerData(value, _) => format!("#{}", value.trim()),
+        Expression::String(value, _) => format!("\"{value}\""),
+        Expression::Character(value, _) => format!("'{value}'"),
+        Expression::Identifier(name, _) => name.to_owned(),
+        Expression::Prefix { prefix: token, .. }
+        | Expression::Assignment {
+            operator: token, ..
+        }
+        | Expression::Binary {
+            operator: token, ..
+        } => token.kind.to_str().to_owned(),
+        Expression::TypeConversion { data_type, .. }
+        | Expression::DataTypeAccess { data_type, .. } => data_type.to_string(),
+        Expression::Typedef { name, .. } | Expression::Struct { name, .. } => name.to_owned(),
+        Expression::VariableDeclaration { var_type, name, .. } => var_type.declaration(name),
+        Expression::Function {
+            name,
+            properties,
+            output,
+            ..
+        } => signature(name, properties, output, false),
+        Expression::FunctionDeclaration {
+            name,
+            properties,
+            output,
+            variadic,
+            ..
+        } => signature(name, properties, output, *variadic),
+        _ => return None,
+    })
+}
+
+fn kind(expression: &Expression) -> &'static str {
+    match expression {
+        Expression::Skip => "Skip",
+        Expression::Increment { .. } => "Increment",
+        Expression::Decrement { .. } => "Decrement",
+        Expression::DataStructureInitialization { .. } => "DataStructureInitialization",
+        Expression::TypeConversion { .. } => "TypeConversion",
+        Expression::Typedef { .. } => "Typedef",
+        Expression::Arrow { .. } => "Arrow",
+        Expression::Dereference { .. } => "Dereference",
+        Expression::Boolean(..) => "Boolean",
+        Expression::Number(..) => "Number",
+        Expression::CompilerData(..) => "CompilerData",
+        Expression::String(..) => "String",
+        Expression::Character(..) => "Character",
+        Expression::Identifier(..) => "Identifier",
+        Expression::Prefix { .. } => "Prefix",
+        Expression::Assignment { .. } => "Assignment",
+        Expression::DataTypeAccess { .. } => "DataTypeAccess",
+        Expression::VariableDeclaration { .. } => "VariableDeclaration",
+        Expression::Grouping { .. } => "Grouping",
+        Expression::Struct { .. } => "Struct",
+        Expression::NewCodeBlock { .. } => "NewCodeBlock",
+        Expression::Binary { .. } => "Binary",
+        Expression::Function { .. } => "Function",
+        Expression::FunctionDeclaration { .. } => "FunctionDeclaration",
+        Expression::MemberExpr { .. } => "MemberExpr",
+        Expression::AccessReference { .. } => "AccessReference",
+        Expression::Break { .. } => "Break",
+        Expression::Return { .. } => "Return",
+        Expression::If { .. } => "If",
+        Expression::Else { .. } => "Else",
+        Expression::AccessArray { .. } => "AccessArray",
+        Expression::While { .. } => "While",
+        Expression::Static { .. } => "Static",
+        Expression::For { .. } => "For",
+        Expression::FunctionCall { .. } => "FunctionCall",
+    }
+}
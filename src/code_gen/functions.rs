@@ -1,63 +1,213 @@
 use crate::{
-    code_gen::{expr_to_string, vec_of_expr_to_string},
-    lexer::token::Token,
-    parser::expression::Expression,
+    parser::{expression::AlignAs, types::DataType},
+    semantic::{scope::SymbolTable, type_check::resolve},
 };
-use anyhow::Result;
-use log::info;
 
-pub fn assignment(target: Expression, operator: Token, value: Expression) -> Result<String> {
-    Ok(format!(
-        "{} {} {}",
-        expr_to_string(target)?,
-        operator.kind.to_str(),
-        expr_to_string(value)?
-    ))
-}
+/// words that Zig doesn't allow as plain identifiers
+const ZIG_RESERVED: [&str; 72] = [
+    "addrspace",
+    "align",
+    "allowzero",
+    "and",
+    "anyframe",
+    "anytype",
+    "asm",
+    "async",
+    "await",
+    "break",
+    "callconv",
+    "catch",
+    "comptime",
+    "const",
+    "continue",
+    "defer",
+    "else",
+    "enum",
+    "errdefer",
+    "error",
+    "export",
+    "extern",
+    "fn",
+    "for",
+    "if",
+    "inline",
+    "linksection",
+    "noalias",
+    "noinline",
+    "nosuspend",
+    "opaque",
+    "or",
+    "orelse",
+    "packed",
+    "pub",
+    "resume",
+    "return",
+    "struct",
+    "suspend",
+    "switch",
+    "test",
+    "threadlocal",
+    "try",
+    "union",
+    "unreachable",
+    "usingnamespace",
+    "var",
+    "volatile",
+    "while",
+    "bool",
+    "void",
+    "type",
+    "anyerror",
+    "anyopaque",
+    "comptime_int",
+    "comptime_float",
+    "noreturn",
+    "isize",
+    "usize",
+    "c_char",
+    "c_short",
+    "c_ushort",
+    "c_int",
+    "c_uint",
+    "c_long",
+    "c_ulong",
+    "true",
+    "false",
+    "null",
+    "undefined",
+    "std",
+    "_",
+];
 
-pub fn new_code_block(inside: Vec<Expression>) -> Result<String> {
-    Ok(format!("{{\n{}}}", vec_of_expr_to_string(inside)?))
+pub fn boolean(value: bool) -> String {
+    value.to_string()
 }
 
-pub fn for_loop(
-    iterator_init: Expression,
-    condition: Expression,
-    incr: Expression,
-    inside: Vec<Expression>,
-) -> Result<String> {
-    Ok(format!(
-        r"
-// for
-{} // iterator init
-while ({}) {{ // condition
-//inside
-{}  
-{} // increment
-}}
-",
-        expr_to_string(iterator_init)?,
-        expr_to_string(condition)?,
-        vec_of_expr_to_string(inside)?,
-        expr_to_string(incr)?,
-    ))
+/// C names that Zig reserves, like `i32` or `type`, are written as `@"i32"`
+pub fn identifier(value: &str) -> String {
+    let is_primitive = |prefix: char| {
+        value
+            .strip_prefix(prefix)
+            .is_some_and(|bits| !bits.is_empty() && bits.chars().all(|char| char.is_ascii_digit()))
+    };
+    let is_float = matches!(value, "f16" | "f32" | "f64" | "f80" | "f128");
+    if ZIG_RESERVED.contains(&value) || is_primitive('i') || is_primitive('u') || is_float {
+        format!("@\"{value}\"")
+    } else {
+        value.to_owned()
+    }
 }
-pub fn while_loop(condition: Expression, inside: Vec<Expression>) -> Result<String> {
-    Ok(format!(
-        "while ({}){{\n{}}}",
-        expr_to_string(condition)?,
-        vec_of_expr_to_string(inside)?
-    ))
+
+/// Zig string literal with the same bytes as the decoded C one, Zig has no octal escapes
+pub fn string(bytes: &[u8]) -> String {
+    let mut output = String::from("\"");
+    for byte in bytes {
+        match byte {
+            b'\n' => output += "\\n",
+            b'\t' => output += "\\t",
+            b'\r' => output += "\\r",
+            b'"' => output += "\\\"",
+            b'\\' => output += "\\\\",
+            b' '..=b'~' => output.push(*byte as char),
+            byte => output += &format!("\\x{byte:02x}"),
+        }
+    }
+    output.push('"');
+    output
 }
-pub fn boolean(value: bool) -> String {
-    value.to_string()
+
+/// Character constants stay readable when they are printable, anything else becomes a number
+pub fn character(value: i128) -> String {
+    match u8::try_from(value) {
+        Ok(byte @ b' '..=b'~') if byte != b'\'' && byte != b'\\' => format!("'{}'", byte as char),
+        _ => value.to_string(),
+    }
 }
 
-pub fn identifier(value: String) -> String {
-    // replace some know c identifiers names to zing ones
-    // or do some other operations
-    value
+fn primitive(name: &str, unsigned: bool) -> Option<&'static str> {
+    Some(match (name, unsigned) {
+        ("void", _) => "void",
+        ("bool", _) => "bool",
+        ("char", _) => "u8",
+        ("short", false) => "c_short",
+        ("short", true) => "c_ushort",
+        ("int", false) => "c_int",
+        ("int", true) => "c_uint",
+        ("long", false) => "c_long",
+        ("long", true) => "c_ulong",
+        ("float", _) => "f32",
+        ("double", _) => "f64",
+        _ => return None,
+    })
 }
 
-pub fn string(value: String) -> String {
-    format!("\"{}\"", value)
+/// C type written as the Zig type with the same layout: `int` is `c_int`, `T *` is `[*c]T`,
+/// `T[N]` is `[N]T` and records are `extern struct`s. Enums are `c_int`s with constants.
+pub fn zig_type(symbols: &SymbolTable, data_type: &DataType) -> String {
+    match data_type {
+        DataType::Data { name, unsigned, .. } => match primitive(name, *unsigned) {
+            Some(primitive) => primitive.to_owned(),
+            None => identifier(name),
+        },
+        DataType::Pointer(inside) => match resolve(symbols, inside) {
+            DataType::Data { name, .. } if name == "void" => "?*anyopaque".to_owned(),
+            function @ DataType::Function { .. } => {
+                format!("?*const {}", zig_type(symbols, &function))
+            }
+            DataType::Data { constant: true, .. } => {
+                format!("[*c]const {}", zig_type(symbols, inside))
+            }
+            _ => format!("[*c]{}", zig_type(symbols, inside)),
+        },
+        DataType::Array { length, inside } => format!("[{length}]{}", zig_type(symbols, inside)),
+        DataType::Struct { properties, .. } | DataType::Union { properties, .. } => {
+            let keyword = match data_type {
+                DataType::Union { .. } => "union",
+                _ => "struct",
+            };
+            let fields = properties
+                .iter()
+                .map(|property| {
+                    let alignment = match &property.alignas {
+                        Some(AlignAs::Bytes(bytes)) => {
+                            format!(" align({bytes})")
+                        }
+                        Some(AlignAs::Type(data_type)) => {
+                            format!(" align(@alignOf({}))", zig_type(symbols, data_type))
+                        }
+                        None => String::new(),
+                    };
+                    format!(
+                        "{}: {}{alignment}",
+                        identifier(&property.var_name),
+                        zig_type(symbols, &property.var_type)
+                    )
+                })
+                .collect::<Vec<_>>();
+            if fields.is_empty() {
+                format!("extern {keyword} {{}}")
+            } else {
+                format!("extern {keyword} {{ {} }}", fields.join(", "))
+            }
+        }
+        DataType::Enum { .. } => "c_int".to_owned(),
+        DataType::Function {
+            output,
+            parameters,
+            variadic,
+        } => {
+            let mut parameters = parameters
+                .iter()
+                .map(|parameter| zig_type(symbols, parameter))
+                .collect::<Vec<_>>();
+            if *variadic {
+                parameters.push("...".to_owned());
+            }
+            format!(
+                "fn ({}) callconv(.C) {}",
+                parameters.join(", "),
+                zig_type(symbols, output)
+            )
+        }
+    }
 }
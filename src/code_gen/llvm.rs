@@ -0,0 +1,1081 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    code_gen::libc_type,
+    ir::{
+        BinaryOperator, Condition, Conversion, Type, UnaryOperator,
+        walk::{Program, Walker, defined_functions, is_aggregate, line},
+    },
+    layout::target::Target,
+    lexer::string_bytes,
+    parser::{expression::Expression, types::DataType},
+    semantic::{
+        constant::Evaluator,
+        scope::{SymbolId, SymbolKind, SymbolTable},
+        type_check::{TypeTable, integer_rank, is_floating, is_integer, is_void, promote, resolve},
+    },
+};
+
+/// Operand of an instruction
+#[derive(Debug, Clone)]
+enum Operand {
+    /// register, argument or global, with its sigil
+    Named(String, Type),
+    /// already wrapped to the range of the type
+    Constant(i64, Type),
+}
+
+impl Operand {
+    fn data_type(&self) -> Type {
+        match self {
+            Operand::Named(_, data_type) | Operand::Constant(_, data_type) => *data_type,
+        }
+    }
+
+    fn text(&self) -> String {
+        match self {
+            Operand::Named(name, _) => name.to_owned(),
+            Operand::Constant(0, Type::Pointer) => "null".to_owned(),
+            Operand::Constant(value, Type::Pointer) => format!("inttoptr (i64 {value} to ptr)"),
+            Operand::Constant(0, Type::I1) => "false".to_owned(),
+            Operand::Constant(_, Type::I1) => "true".to_owned(),
+            Operand::Constant(value, _) => value.to_string(),
+        }
+    }
+
+    /// Type and operand, like `i32 %.3`
+    fn typed(&self) -> String {
+        format!("{} {}", self.data_type(), self.text())
+    }
+}
+
+/// Named LLVM type of a struct or union
+#[derive(Debug, Clone)]
+struct Record {
+    name: String,
+    /// index of every member in the LLVM struct, empty for unions since all of their members
+    /// start at the address of the union
+    members: HashMap<String, u32>,
+    /// `<{ ... }>` with the padding written out
+    packed: bool,
+}
+
+struct Block {
+    label: String,
+    lines: Vec<String>,
+    terminator: Option<String>,
+}
+
+/// State of the function that is being generated
+struct FunctionState {
+    name: String,
+    /// `alloca`s of every local, they go at the start of the entry block
+    allocas: Vec<String>,
+    blocks: Vec<Block>,
+    /// block that instructions are added to, `None` after a jump or a return until the next
+    /// block starts
+    current: Option<usize>,
+    /// address of every local and parameter
+    locals: HashMap<SymbolId, String>,
+    /// where `break` jumps to, innermost loop last
+    breaks: Vec<usize>,
+    /// C type of the result
+    output: DataType,
+    /// `sret` pointer to write a returned struct to
+    result: Option<String>,
+    temporaries: usize,
+    /// how many locals with each name there are, the later ones get a number
+    names: HashMap<String, usize>,
+}
+
+/// Translates the program to a textual LLVM IR module that `llc` compiles. Locals live in
+/// `alloca`s that LLVM's mem2reg promotes, structs and unions become named struct types, and
+/// members and elements are reached with `getelementptr`. Structs are passed `byval` and
+/// returned through an `sret` pointer. Pointers are opaque (`ptr`), LLVM 14 needs
+/// `-opaque-pointers` for them. The program should have no errors from the earlier passes.
+pub fn generate(
+    expressions: &[Expression],
+    symbols: &SymbolTable,
+    types: &TypeTable,
+    target: &Target,
+) -> Result<String> {
+    let mut generator = Generator {
+        program: Program::new(symbols, types, target, "the LLVM backend"),
+        records: HashMap::new(),
+        type_definitions: Vec::new(),
+        globals: HashMap::new(),
+        global_definitions: Vec::new(),
+        declarations: Vec::new(),
+        functions: Vec::new(),
+        defined: defined_functions(expressions),
+        declared: Vec::new(),
+        strings: 0,
+        records_named: 0,
+        function: None,
+    };
+    // structs and unions are named after their typedef
+    for symbol in &symbols.symbols {
+        let data_type = resolve(symbols, &symbol.data_type);
+        if symbol.kind == SymbolKind::Typedef && is_aggregate(&data_type) {
+            generator.record(&data_type, Some(&symbol.name))?;
+        }
+    }
+    for expression in expressions {
+        generator
+            .top_level(expression, true)
+            .with_context(|| format!("generating LLVM IR at line: {}", line(expression)))?;
+    }
+
+    let mut output = String::new();
+    if let Some(triple) = triple(target) {
+        writeln!(output, "target triple = \"{triple}\"\n").unwrap();
+    }
+    for section in [
+        &generator.type_definitions,
+        &generator.global_definitions,
+        &generator.declarations,
+    ] {
+        if !section.is_empty() {
+            writeln!(output, "{}\n", section.join("\n")).unwrap();
+        }
+    }
+    output.push_str(&generator.functions.join("\n"));
+    Ok(output)
+}
+
+fn triple(target: &Target) -> Option<&'static str> {
+    Some(match target.name.as_str() {
+        "x86_64-sysv" => "x86_64-pc-linux-gnu",
+        "ilp32" => "i386-pc-linux-gnu",
+        "llp64" => "x86_64-pc-windows-msvc",
+        "aarch64" => "aarch64-unknown-linux-gnu",
+        _ => return None,
+    })
+}
+
+/// `c"..."` constant with the bytes
+fn byte_string(bytes: &[u8]) -> String {
+    let mut output = String::from("c\"");
+    for byte in bytes {
+        match byte {
+            b' '..=b'~' if *byte != b'"' && *byte != b'\\' => output.push(*byte as char),
+            byte => write!(output, "\\{byte:02X}").unwrap(),
+        }
+    }
+    output.push('"');
+    output
+}
+
+struct Generator<'a> {
+    program: Program<'a>,
+    /// named types by the debug output of the struct or union, which has all of its members
+    records: HashMap<String, Record>,
+    type_definitions: Vec<String>,
+    /// names of globals and static locals, without the `@`
+    globals: HashMap<SymbolId, String>,
+    global_definitions: Vec<String>,
+    declarations: Vec<String>,
+    functions: Vec<String>,
+    /// functions with a body somewhere in the program
+    defined: Vec<String>,
+    /// functions without one that have a declaration
+    declared: Vec<String>,
+    strings: usize,
+    records_named: usize,
+    function: Option<FunctionState>,
+}
+
+impl Generator<'_> {
+    // -- types --
+
+    /// Type of the object in memory
+    fn memory_type(&mut self, data_type: &DataType) -> Result<String> {
+        let data_type = self.program.resolve(data_type);
+        Ok(match &data_type {
+            DataType::Array { length, inside } => {
+                format!("[{length} x {}]", self.memory_type(inside)?)
+            }
+            data_type if is_aggregate(data_type) => self.record(data_type, None)?.name,
+            // floating point members can be laid out even though their values aren't supported
+            data_type if is_floating(data_type) => match self.program.size_of(data_type)? {
+                4 => "float".to_owned(),
+                8 => "double".to_owned(),
+                size => format!("[{size} x i8]"),
+            },
+            data_type => self.program.scalar_type(data_type)?.to_string(),
+        })
+    }
+
+    /// Type that `getelementptr` steps over for pointer arithmetic, `void *` counts bytes
+    fn pointee_type(&mut self, pointee: &DataType) -> Result<String> {
+        match self.program.resolve(pointee) {
+            pointee if is_void(&pointee) => Ok("i8".to_owned()),
+            DataType::Function { .. } => Ok("i8".to_owned()),
+            pointee => self.memory_type(&pointee),
+        }
+    }
+
+    /// Named type of a struct or union, defined the first time it is needed. Members that
+    /// `#pragma pack` or `_Alignas` moved make it a packed struct with explicit padding, and a
+    /// union is its most aligned member followed by enough bytes for the largest one.
+    fn record(&mut self, data_type: &DataType, name: Option<&str>) -> Result<Record> {
+        let key = format!("{data_type:?}");
+        if let Some(record) = self.records.get(&key) {
+            return Ok(record.to_owned());
+        }
+        let name = match name {
+            Some(name) => format!("%{name}"),
+            None => {
+                self.records_named += 1;
+                format!("%anon.{}", self.records_named)
+            }
+        };
+        let mut record = Record {
+            name: name.to_owned(),
+            members: HashMap::new(),
+            packed: false,
+        };
+        // members that point to the record itself are only `ptr`, so it can be inserted early
+        self.records.insert(key, record.to_owned());
+
+        let layout = self.program.layout.layout(data_type)?;
+        let mut natural = true;
+        for field in &layout.fields {
+            natural &= field.alignment == self.program.layout.align_of(&field.data_type)?;
+        }
+        let mut members = Vec::new();
+        if let DataType::Union { .. } = data_type {
+            if let Some(field) = layout
+                .fields
+                .iter()
+                .filter(|field| field.alignment == layout.alignment)
+                .max_by_key(|field| (field.alignment, std::cmp::Reverse(field.offset)))
+            {
+                members.push(self.memory_type(&field.data_type)?);
+                if layout.size > field.size {
+                    members.push(format!("[{} x i8]", layout.size - field.size));
+                }
+            } else if layout.size > 0 {
+                natural = false;
+                members.push(format!("[{} x i8]", layout.size));
+            }
+        } else {
+            let mut end = 0;
+            for field in &layout.fields {
+                if !natural && field.offset > end {
+                    members.push(format!("[{} x i8]", field.offset - end));
+                }
+                record
+                    .members
+                    .insert(field.name.to_owned(), members.len() as u32);
+                members.push(self.memory_type(&field.data_type)?);
+                end = field.offset + field.size;
+            }
+            if !natural && layout.size > end {
+                members.push(format!("[{} x i8]", layout.size - end));
+            }
+        }
+        record.packed = !natural;
+        let body = if natural {
+            format!("{{ {} }}", members.join(", "))
+        } else {
+            format!("<{{ {} }}>", members.join(", "))
+        };
+        self.type_definitions.push(format!("{name} = type {body}"));
+        self.records
+            .insert(format!("{data_type:?}"), record.to_owned());
+        Ok(record)
+    }
+
+    // -- building --
+
+    fn state(&mut self) -> &mut FunctionState {
+        self.function
+            .as_mut()
+            .expect("instructions are only added inside of a function")
+    }
+
+    fn label(&mut self, block: usize) -> String {
+        self.state().blocks[block].label.to_owned()
+    }
+
+    fn current(&mut self) -> usize {
+        match self.state().current {
+            Some(block) => block,
+            // code after a jump or a return, it is unreachable
+            None => {
+                let block = self.new_block("dead");
+                self.state().current = Some(block);
+                block
+            }
+        }
+    }
+
+    fn terminate(&mut self, terminator: String) {
+        let block = self.current();
+        self.state().blocks[block].terminator = Some(terminator);
+        self.state().current = None;
+    }
+
+    fn emit(&mut self, line: String) {
+        let block = self.current();
+        self.state().blocks[block].lines.push(line);
+    }
+
+    /// Adds an instruction that has a result
+    fn instruction(&mut self, data_type: Type, instruction: String) -> Operand {
+        let state = self.state();
+        state.temporaries += 1;
+        let name = format!("%.{}", state.temporaries);
+        self.emit(format!("{name} = {instruction}"));
+        Operand::Named(name, data_type)
+    }
+
+    /// `alloca` in the entry block
+    fn allocate(&mut self, name: &str, data_type: &DataType) -> Result<String> {
+        let memory_type = self.memory_type(data_type)?;
+        let alignment = self.program.layout.align_of(data_type)?;
+        let state = self.state();
+        let count = state.names.entry(name.to_owned()).or_default();
+        let address = match *count {
+            0 => format!("%{name}.addr"),
+            count => format!("%{name}.addr{count}"),
+        };
+        *count += 1;
+        state.allocas.push(format!(
+            "{address} = alloca {memory_type}, align {alignment}"
+        ));
+        Ok(address)
+    }
+
+    fn zero(&mut self, address: Operand, size: u64) {
+        self.emit(format!(
+            "call void @llvm.memset.p0.i64({}, i8 0, i64 {size}, i1 false)",
+            address.typed()
+        ));
+        self.intrinsic("declare void @llvm.memset.p0.i64(ptr, i8, i64, i1)");
+    }
+
+    fn intrinsic(&mut self, declaration: &str) {
+        if !self
+            .declarations
+            .iter()
+            .any(|existing| existing == declaration)
+        {
+            self.declarations.push(declaration.to_owned());
+        }
+    }
+
+    /// Address of a member of the struct or union at `base`
+    fn member(&mut self, base: Operand, structure: &DataType, name: &str) -> Result<Operand> {
+        if self.program.layout.layout(structure)?.field(name).is_none() {
+            bail!("no member named '{name}' in '{structure}'");
+        }
+        let record = self.record(structure, None)?;
+        let Some(index) = record.members.get(name) else {
+            // members of unions start where the union does
+            return Ok(base);
+        };
+        Ok(self.instruction(
+            Type::Pointer,
+            format!(
+                "getelementptr inbounds {}, {}, i32 0, i32 {index}",
+                record.name,
+                base.typed()
+            ),
+        ))
+    }
+
+    // -- globals --
+
+    /// Constant of the type, what an initializer list leaves out is zero
+    fn constant_initializer(
+        &mut self,
+        data_type: &DataType,
+        initializer: Option<&Expression>,
+    ) -> Result<String> {
+        let resolved = self.program.resolve(data_type);
+        let Some(initializer) = initializer else {
+            return Ok(match resolved {
+                DataType::Pointer(_) => "null".to_owned(),
+                data_type if is_integer(&data_type) => "0".to_owned(),
+                _ => "zeroinitializer".to_owned(),
+            });
+        };
+        if let Expression::Grouping { value, .. } = initializer {
+            return self.constant_initializer(data_type, Some(value));
+        }
+        match (&resolved, initializer) {
+            (DataType::Array { length, .. }, Expression::String(value, _)) => {
+                let mut bytes = string_bytes(value)?;
+                bytes.resize(*length as usize, 0);
+                return Ok(byte_string(&bytes));
+            }
+            (
+                DataType::Array { length, inside },
+                Expression::DataStructureInitialization { values, .. },
+            ) => {
+                let element_type = self.memory_type(inside)?;
+                let mut elements = Vec::new();
+                for index in 0..*length as usize {
+                    let value = self.constant_initializer(inside, values.get(index))?;
+                    elements.push(format!("{element_type} {value}"));
+                }
+                return Ok(format!("[{}]", elements.join(", ")));
+            }
+            (DataType::Struct { .. }, Expression::DataStructureInitialization { values, .. }) => {
+                let layout = self.program.layout.layout(&resolved)?;
+                let packed = self.record(&resolved, None)?.packed;
+                let mut members = Vec::new();
+                let mut end = 0;
+                for (index, field) in layout.fields.iter().enumerate() {
+                    if packed && field.offset > end {
+                        members.push(format!("[{} x i8] zeroinitializer", field.offset - end));
+                    }
+                    let member_type = self.memory_type(&field.data_type)?;
+                    let value = self.constant_initializer(&field.data_type, values.get(index))?;
+                    members.push(format!("{member_type} {value}"));
+                    end = field.offset + field.size;
+                }
+                if packed && layout.size > end {
+                    members.push(format!("[{} x i8] zeroinitializer", layout.size - end));
+                }
+                return Ok(if packed {
+                    format!("<{{ {} }}>", members.join(", "))
+                } else {
+                    format!("{{ {} }}", members.join(", "))
+                });
+            }
+            (DataType::Union { .. }, Expression::DataStructureInitialization { values, .. })
+                if values.is_empty() =>
+            {
+                return Ok("zeroinitializer".to_owned());
+            }
+            (DataType::Union { .. }, _) => {
+                bail!("initializing unions in globals is not supported by the LLVM backend")
+            }
+            (DataType::Pointer(_), _) => {
+                let target = match initializer {
+                    Expression::String(value, _) => Some(self.string(value)?),
+                    Expression::AccessReference { value, .. } => Some(self.global_name(value)?),
+                    Expression::Identifier(..)
+                        if matches!(
+                            self.program.object_type(initializer)?,
+                            DataType::Array { .. }
+                        ) =>
+                    {
+                        Some(self.global_name(initializer)?)
+                    }
+                    _ => None,
+                };
+                if let Some(target) = target {
+                    return Ok(format!("@{target}"));
+                }
+            }
+            _ => {}
+        }
+        let constant = Evaluator::new(self.program.symbols, self.program.target)
+            .evaluate(initializer)
+            .map_err(|diagnostic| anyhow::anyhow!("{diagnostic}"))?;
+        let scalar = self.program.scalar_type(&resolved)?;
+        let value = scalar.wrap(constant.value as i64);
+        Ok(Operand::Constant(value, scalar).text())
+    }
+
+    /// Name of the global that the expression refers to
+    fn global_name(&self, expression: &Expression) -> Result<String> {
+        let symbols = self.program.symbols;
+        let id = symbols
+            .resolutions
+            .get(&expression.id())
+            .with_context(|| format!("'{expression:?}' is not a global variable"))?;
+        self.globals
+            .get(id)
+            .cloned()
+            .with_context(|| format!("'{}' is not a global variable", symbols.symbol(*id).name))
+    }
+
+    /// Constant global with the bytes of a string literal and its terminating zero
+    fn string(&mut self, value: &str) -> Result<String> {
+        let mut bytes = string_bytes(value)?;
+        bytes.push(0);
+        let name = format!(".str.{}", self.strings);
+        self.strings += 1;
+        self.global_definitions.push(format!(
+            "@{name} = private unnamed_addr constant [{} x i8] {}, align 1",
+            bytes.len(),
+            byte_string(&bytes)
+        ));
+        Ok(name)
+    }
+
+    // -- functions --
+
+    /// Adds a declaration for a function without a body the first time it is called
+    fn declare_function(&mut self, name: &str, data_type: &DataType) -> Result<()> {
+        if self.defined.iter().any(|defined| defined == name)
+            || self.declared.iter().any(|declared| declared == name)
+        {
+            return Ok(());
+        }
+        self.declared.push(name.to_owned());
+        let DataType::Function {
+            output,
+            parameters,
+            variadic,
+        } = self.program.resolve(data_type)
+        else {
+            bail!("'{name}' is not a function");
+        };
+        let (output, mut types) = self.signature(&output)?;
+        for parameter in &parameters {
+            types.push(self.parameter_type(parameter)?);
+        }
+        if variadic {
+            types.push("...".to_owned());
+        }
+        self.declarations
+            .push(format!("declare {output} @{name}({})", types.join(", ")));
+        Ok(())
+    }
+
+    /// LLVM result type, and the `sret` parameter that structs are returned through
+    fn signature(&mut self, output: &DataType) -> Result<(String, Vec<String>)> {
+        let output = self.program.resolve(output);
+        Ok(if is_void(&output) {
+            ("void".to_owned(), Vec::new())
+        } else if is_aggregate(&output) {
+            let record = self.record(&output, None)?;
+            (
+                "void".to_owned(),
+                vec![format!("ptr sret({})", record.name)],
+            )
+        } else {
+            (self.program.scalar_type(&output)?.to_string(), Vec::new())
+        })
+    }
+
+    /// Structs are passed as a pointer to a copy that LLVM makes
+    fn parameter_type(&mut self, data_type: &DataType) -> Result<String> {
+        let data_type = self.program.resolve(data_type);
+        if is_aggregate(&data_type) {
+            let record = self.record(&data_type, None)?;
+            Ok(format!("ptr byval({})", record.name))
+        } else {
+            Ok(self.program.scalar_type(&data_type)?.to_string())
+        }
+    }
+}
+
+impl<'a> Walker<'a> for Generator<'a> {
+    type Value = Operand;
+    type Block = usize;
+
+    fn program(&self) -> &Program<'a> {
+        &self.program
+    }
+
+    // -- building --
+
+    fn new_block(&mut self, name: &str) -> usize {
+        let blocks = &mut self.state().blocks;
+        blocks.push(Block {
+            label: format!("{name}{}", blocks.len()),
+            lines: Vec::new(),
+            terminator: None,
+        });
+        blocks.len() - 1
+    }
+
+    fn start(&mut self, block: usize) {
+        if self.state().current.is_some() {
+            self.jump(block);
+        }
+        self.state().current = Some(block);
+    }
+
+    fn jump(&mut self, target: usize) {
+        if self.state().current.is_some() {
+            let label = self.label(target);
+            self.terminate(format!("br label %{label}"));
+        }
+    }
+
+    fn branch(&mut self, condition: Operand, then: usize, otherwise: usize) {
+        let (then, otherwise) = (self.label(then), self.label(otherwise));
+        self.terminate(format!(
+            "br i1 {}, label %{then}, label %{otherwise}",
+            condition.text()
+        ));
+    }
+
+    fn return_value(&mut self, value: Option<Operand>) {
+        match value {
+            Some(value) => self.terminate(format!("ret {}", value.typed())),
+            None => self.terminate("ret void".to_owned()),
+        }
+    }
+
+    fn breaks(&mut self) -> &mut Vec<usize> {
+        &mut self.state().breaks
+    }
+
+    fn constant(&self, value: i64, data_type: Type) -> Operand {
+        Operand::Constant(value, data_type)
+    }
+
+    fn as_constant(&self, value: &Operand) -> Option<i64> {
+        match value {
+            Operand::Constant(constant, _) => Some(*constant),
+            Operand::Named(..) => None,
+        }
+    }
+
+    fn type_of(&mut self, value: &Operand) -> Type {
+        value.data_type()
+    }
+
+    fn binary_instruction(
+        &mut self,
+        operator: BinaryOperator,
+        left: Operand,
+        right: Operand,
+        data_type: Type,
+    ) -> Operand {
+        self.instruction(
+            data_type,
+            format!("{} {}, {}", operator.name(), left.typed(), right.text()),
+        )
+    }
+
+    fn unary_instruction(&mut self, operator: UnaryOperator, value: Operand) -> Operand {
+        let data_type = value.data_type();
+        match operator {
+            UnaryOperator::Negate => self.binary_instruction(
+                BinaryOperator::Subtract,
+                Operand::Constant(0, data_type),
+                value,
+                data_type,
+            ),
+            UnaryOperator::Not => self.binary_instruction(
+                BinaryOperator::Xor,
+                value,
+                Operand::Constant(-1, data_type),
+                data_type,
+            ),
+        }
+    }
+
+    fn compare(&mut self, condition: Condition, left: Operand, right: Operand) -> Operand {
+        self.instruction(
+            Type::I1,
+            format!(
+                "icmp {} {}, {}",
+                condition.name(),
+                left.typed(),
+                right.text()
+            ),
+        )
+    }
+
+    fn convert_instruction(&mut self, conversion: Conversion, value: Operand, to: Type) -> Operand {
+        self.instruction(
+            to,
+            format!("{} {} to {to}", conversion.name(), value.typed()),
+        )
+    }
+
+    fn load(&mut self, address: Operand, data_type: &DataType) -> Result<Operand> {
+        let data_type = self.program.resolve(data_type);
+        // arrays decay to their address and structs are passed around as it
+        if matches!(data_type, DataType::Array { .. }) || is_aggregate(&data_type) {
+            return Ok(address);
+        }
+        let scalar = self.program.scalar_type(&data_type)?;
+        Ok(self.instruction(scalar, format!("load {scalar}, {}", address.typed())))
+    }
+
+    fn store(&mut self, address: Operand, value: Operand, data_type: &DataType) -> Result<()> {
+        let data_type = self.program.resolve(data_type);
+        if is_aggregate(&data_type) {
+            let size = self.program.size_of(&data_type)?;
+            self.emit(format!(
+                "call void @llvm.memcpy.p0.p0.i64({}, {}, i64 {size}, i1 false)",
+                address.typed(),
+                value.typed()
+            ));
+            self.intrinsic("declare void @llvm.memcpy.p0.p0.i64(ptr, ptr, i64, i1)");
+        } else {
+            self.emit(format!("store {}, {}", value.typed(), address.typed()));
+        }
+        Ok(())
+    }
+
+    fn element_count(&mut self, bytes: Operand, size: i64) -> Operand {
+        self.instruction(Type::I64, format!("sdiv exact {}, {size}", bytes.typed()))
+    }
+
+    // -- storage --
+
+    fn output(&mut self) -> (DataType, Option<Operand>) {
+        let state = self.state();
+        let result = state
+            .result
+            .to_owned()
+            .map(|result| Operand::Named(result, Type::Pointer));
+        (state.output.to_owned(), result)
+    }
+
+    fn function_name(&mut self) -> String {
+        self.state().name.to_owned()
+    }
+
+    fn has_local(&mut self, id: SymbolId) -> bool {
+        self.state().locals.contains_key(&id)
+    }
+
+    /// `alloca` of a local variable
+    fn allocate_local(&mut self, id: SymbolId, name: &str, data_type: &DataType) -> Result<()> {
+        let address = self.allocate(name, data_type)?;
+        self.state().locals.insert(id, address);
+        Ok(())
+    }
+
+    fn variable_address(&mut self, id: SymbolId) -> Option<Operand> {
+        let address = match self.state().locals.get(&id) {
+            Some(local) => local.to_owned(),
+            None => format!("@{}", self.globals.get(&id)?),
+        };
+        Some(Operand::Named(address, Type::Pointer))
+    }
+
+    fn string_address(&mut self, value: &str) -> Result<Operand> {
+        let name = self.string(value)?;
+        Ok(Operand::Named(format!("@{name}"), Type::Pointer))
+    }
+
+    fn member_address(
+        &mut self,
+        base: Operand,
+        structure: &DataType,
+        member: &Expression,
+    ) -> Result<Operand> {
+        let Expression::Identifier(name, _) = member else {
+            bail!("expected a member name, found: {member:?}");
+        };
+        self.member(base, structure, name)
+    }
+
+    fn pointer_offset(
+        &mut self,
+        pointer: Operand,
+        index: Operand,
+        index_type: &DataType,
+        pointee: &DataType,
+        negate: bool,
+    ) -> Result<Operand> {
+        let index = self.resize(index, Type::I64, self.program.is_signed(index_type));
+        let index = match (negate, index) {
+            (false, index) => index,
+            (true, Operand::Constant(index, _)) => {
+                Operand::Constant(index.wrapping_neg(), Type::I64)
+            }
+            (true, index) => self.unary_instruction(UnaryOperator::Negate, index),
+        };
+        let element_type = self.pointee_type(pointee)?;
+        Ok(self.instruction(
+            Type::Pointer,
+            format!(
+                "getelementptr inbounds {element_type}, {}, {}",
+                pointer.typed(),
+                index.typed()
+            ),
+        ))
+    }
+
+    fn global(
+        &mut self,
+        declaration: &Expression,
+        initializer: Option<&Expression>,
+        name: String,
+        exported: bool,
+    ) -> Result<()> {
+        let Expression::VariableDeclaration { var_type, .. } = declaration else {
+            bail!("expected a variable declaration, found: {declaration:?}");
+        };
+        let id = self.program.declared(declaration, &name)?;
+        // pointers in the initializer can point to the global itself
+        self.globals.insert(id, name.to_owned());
+        let memory_type = self.memory_type(var_type)?;
+        let alignment = self.program.layout.align_of(var_type)?;
+        let value = self
+            .constant_initializer(var_type, initializer)
+            .with_context(|| format!("initializer of '{name}'"))?;
+        let linkage = if exported { "" } else { "internal " };
+        self.global_definitions.push(format!(
+            "@{name} = {linkage}global {memory_type} {value}, align {alignment}"
+        ));
+        Ok(())
+    }
+
+    fn function_definition(&mut self, expression: &'a Expression, exported: bool) -> Result<()> {
+        let Expression::Function {
+            name,
+            output,
+            inside,
+            ..
+        } = expression
+        else {
+            bail!("expected a function, found: {expression:?}");
+        };
+        let (llvm_output, hidden) = self.signature(output)?;
+        self.function = Some(FunctionState {
+            name: name.to_owned(),
+            allocas: Vec::new(),
+            blocks: Vec::new(),
+            current: None,
+            locals: HashMap::new(),
+            breaks: Vec::new(),
+            output: self.program.resolve(output),
+            result: None,
+            temporaries: 0,
+            names: HashMap::new(),
+        });
+        let entry = self.new_block("entry");
+        self.state().blocks[entry].label = "entry".to_owned();
+        self.start(entry);
+
+        let mut parameters = Vec::new();
+        if let Some(sret) = hidden.first() {
+            parameters.push(format!("{sret} %agg.result"));
+            self.state().result = Some("%agg.result".to_owned());
+        }
+        let symbols = self.program.symbols;
+        let ids = symbols
+            .parameters
+            .get(&expression.id())
+            .cloned()
+            .unwrap_or_default();
+        for id in ids {
+            let symbol = symbols.symbol(id);
+            let data_type = self.program.resolve(&symbol.data_type);
+            let argument = format!("%{}", symbol.name);
+            parameters.push(format!("{} {argument}", self.parameter_type(&data_type)?));
+            if is_aggregate(&data_type) {
+                self.state().locals.insert(id, argument);
+                continue;
+            }
+            let address = self.allocate(&symbol.name, &data_type)?;
+            self.state().locals.insert(id, address.to_owned());
+            let scalar = self.program.scalar_type(&data_type)?;
+            self.emit(format!("store {scalar} {argument}, ptr {address}"));
+        }
+
+        self.statements(inside)?;
+        if self.state().current.is_some() {
+            let terminator = if llvm_output == "void" {
+                "ret void".to_owned()
+            } else if name == "main" {
+                // reaching the end of main returns 0
+                format!("ret {llvm_output} 0")
+            } else {
+                "unreachable".to_owned()
+            };
+            self.terminate(terminator);
+        }
+
+        let state = self
+            .function
+            .take()
+            .expect("the function was started above");
+        let linkage = if exported { "" } else { "internal " };
+        let mut text = format!(
+            "define {linkage}{llvm_output} @{name}({}) {{\n",
+            parameters.join(", ")
+        );
+        for (index, block) in state.blocks.into_iter().enumerate() {
+            if index > 0 {
+                text.push('\n');
+            }
+            writeln!(text, "{}:", block.label).unwrap();
+            if index == 0 {
+                for alloca in &state.allocas {
+                    writeln!(text, "  {alloca}").unwrap();
+                }
+            }
+            for line in block.lines {
+                writeln!(text, "  {line}").unwrap();
+            }
+            let terminator = block.terminator.as_deref().unwrap_or("unreachable");
+            writeln!(text, "  {terminator}").unwrap();
+        }
+        text.push_str("}\n");
+        self.functions.push(text);
+        Ok(())
+    }
+
+    fn initialize(
+        &mut self,
+        address: Operand,
+        data_type: &DataType,
+        initializer: &'a Expression,
+    ) -> Result<()> {
+        let resolved = self.program.resolve(data_type);
+        if let (DataType::Array { length, inside }, Expression::String(value, _)) =
+            (&resolved, initializer)
+            && integer_rank(&self.program.resolve(inside)) == Some(2)
+        {
+            let length = *length as u64;
+            let string_length = string_bytes(value)?.len() as u64 + 1;
+            if string_length < length {
+                self.zero(address.to_owned(), length);
+            }
+            let name = self.string(value)?;
+            self.emit(format!(
+                "call void @llvm.memcpy.p0.p0.i64({}, ptr @{name}, i64 {}, i1 false)",
+                address.typed(),
+                string_length.min(length)
+            ));
+            self.intrinsic("declare void @llvm.memcpy.p0.p0.i64(ptr, ptr, i64, i1)");
+            return Ok(());
+        }
+
+        let Expression::DataStructureInitialization { values, .. } = initializer else {
+            let from = self.program.value_type(initializer)?;
+            let value = self.value(initializer)?;
+            let value = self.convert(value, &from, &resolved)?;
+            return self.store(address, value, &resolved);
+        };
+        let size = self.program.size_of(&resolved)?;
+        self.zero(address.to_owned(), size);
+        match &resolved {
+            DataType::Array { inside, .. } => {
+                let element_type = self.memory_type(inside)?;
+                for (index, value) in values.iter().enumerate() {
+                    let element = self.instruction(
+                        Type::Pointer,
+                        format!(
+                            "getelementptr inbounds {element_type}, {}, i64 {index}",
+                            address.typed()
+                        ),
+                    );
+                    self.initialize(element, inside, value)?;
+                }
+            }
+            DataType::Struct { .. } | DataType::Union { .. } => {
+                let layout = self.program.layout.layout(&resolved)?;
+                let take = match resolved {
+                    DataType::Union { .. } => 1,
+                    _ => layout.fields.len(),
+                };
+                for (field, value) in layout.fields.iter().take(take).zip(values) {
+                    let member = self.member(address.to_owned(), &resolved, &field.name)?;
+                    self.initialize(member, &field.data_type, value)?;
+                }
+            }
+            scalar => {
+                if let Some(value) = values.first() {
+                    self.initialize(address, scalar, value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// `&&` and `||` as an `int`, a phi picks 1 or 0 depending on where the branches went
+    fn logical_value(&mut self, expression: &'a Expression) -> Result<Operand> {
+        let then = self.new_block("logical.true");
+        let otherwise = self.new_block("logical.false");
+        let end = self.new_block("logical.end");
+        self.condition(expression, then, otherwise)?;
+        self.start(then);
+        self.jump(end);
+        self.start(otherwise);
+        self.jump(end);
+        self.start(end);
+        let (then, otherwise) = (self.label(then), self.label(otherwise));
+        let truth = self.instruction(
+            Type::I1,
+            format!("phi i1 [ true, %{then} ], [ false, %{otherwise} ]"),
+        );
+        Ok(self.resize(truth, Type::I32, false))
+    }
+
+    fn function_value(&mut self, name: &str) -> Result<Operand> {
+        Ok(Operand::Named(format!("@{name}"), Type::Pointer))
+    }
+
+    fn call(&mut self, left: &'a Expression, values: &'a [Expression]) -> Result<Option<Operand>> {
+        let Expression::Identifier(name, _) = left else {
+            bail!("calls through function pointers are not supported by the LLVM backend");
+        };
+        let function_type = match self.program.symbols.resolution(left) {
+            Some(symbol) if symbol.kind == SymbolKind::Function => symbol.data_type.to_owned(),
+            Some(_) => {
+                bail!("calls through function pointers are not supported by the LLVM backend")
+            }
+            None => {
+                libc_type(name).with_context(|| format!("call of an unknown function '{name}'"))?
+            }
+        };
+        self.declare_function(name, &function_type)?;
+        let DataType::Function {
+            output,
+            parameters,
+            variadic,
+        } = self.program.resolve(&function_type)
+        else {
+            bail!("'{name}' is not a function");
+        };
+
+        let output = self.program.resolve(&output);
+        let (llvm_output, hidden) = self.signature(&output)?;
+        let mut arguments = Vec::new();
+        let result = match hidden.first() {
+            Some(sret) => {
+                let address = self.allocate("result", &output)?;
+                arguments.push(format!("{sret} {address}"));
+                Some(Operand::Named(address, Type::Pointer))
+            }
+            None => None,
+        };
+        for (index, value) in values.iter().enumerate() {
+            let from = self.program.value_type(value)?;
+            let argument = self.value(value)?;
+            // extra arguments of variadic functions get the default promotions
+            let to = match parameters.get(index) {
+                Some(parameter) => self.program.resolve(parameter),
+                None => promote(&from),
+            };
+            if is_aggregate(&to) {
+                let parameter = self.parameter_type(&to)?;
+                arguments.push(format!("{parameter} {}", argument.text()));
+            } else {
+                let argument = self.convert(argument, &from, &to)?;
+                arguments.push(argument.typed());
+            }
+        }
+
+        // variadic callees need the whole function type
+        let callee = if variadic {
+            let mut types = hidden.to_owned();
+            for parameter in &parameters {
+                types.push(self.parameter_type(parameter)?);
+            }
+            types.push("...".to_owned());
+            format!("{llvm_output} ({})", types.join(", "))
+        } else {
+            llvm_output.to_owned()
+        };
+        let call = format!("call {callee} @{name}({})", arguments.join(", "));
+        if result.is_some() || is_void(&output) {
+            self.emit(call);
+            return Ok(result);
+        }
+        let data_type = self.program.scalar_type(&output)?;
+        Ok(Some(self.instruction(data_type, call)))
+    }
+}
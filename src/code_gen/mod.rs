@@ -1,131 +1,1203 @@
+pub mod c;
+pub mod dot;
 pub mod functions;
+pub mod llvm;
+pub mod rust;
+pub mod wasm;
+pub mod x86_64;
 
-use crate::parser::expression::Expression;
-use anyhow::{Context, Result};
+use std::collections::BTreeSet;
 
-pub fn generate_code(expressions: Vec<Expression>) -> Result<String> {
-    let mut output_code = String::new();
+use anyhow::{Context, Result, bail};
 
-    for expr in expressions {
-        output_code +=
-            &(expr_to_string(expr).with_context(|| format!("expr: {:?}", expr))? + "{};\n");
+use crate::{
+    code_gen::functions::{boolean, character, identifier, string, zig_type},
+    lexer::{character_value, string_bytes, token::TokenKind},
+    parser::{
+        expression::{DebugData, Expression},
+        types::DataType,
+    },
+    semantic::{
+        scope::{SymbolId, SymbolTable},
+        type_check::{
+            TypeTable, integer_rank, is_floating, is_integer, is_void, primitive, resolve,
+            usual_arithmetic_conversion,
+        },
+    },
+};
+
+const INDENT: &str = "    ";
+
+/// declarations of the libc functions that C programs here use without including anything
+const LIBC: [(&str, &str); 4] = [
+    (
+        "printf",
+        "extern \"c\" fn printf(format: [*c]const u8, ...) c_int;",
+    ),
+    (
+        "malloc",
+        "extern \"c\" fn malloc(size: c_ulong) ?*anyopaque;",
+    ),
+    ("free", "extern \"c\" fn free(pointer: ?*anyopaque) void;"),
+    (
+        "strlen",
+        "extern \"c\" fn strlen(string: [*c]const u8) c_ulong;",
+    ),
+];
+
+/// Types of the libc functions, the type checker only knows that they return `int`
+pub fn libc_type(name: &str) -> Option<DataType> {
+    let char_pointer = DataType::Pointer(Box::new(primitive("char", false)));
+    let void_pointer = DataType::Pointer(Box::new(primitive("void", false)));
+    let (output, parameters) = match name {
+        "printf" => (primitive("int", false), vec![char_pointer]),
+        "malloc" => (void_pointer, vec![primitive("long", true)]),
+        "free" => (primitive("void", false), vec![void_pointer]),
+        "strlen" => (primitive("long", true), vec![char_pointer]),
+        _ => return None,
+    };
+    Some(DataType::Function {
+        output: Box::new(output),
+        parameters,
+        variadic: name == "printf",
+    })
+}
+
+/// Translates the program to Zig. The types from the type checker decide where the implicit
+/// conversions of C (integers used as conditions, mixed integer types...) have to be written out.
+/// Expressions that the type checker couldn't type are translated as they are.
+pub fn generate_code(
+    expressions: &[Expression],
+    symbols: &SymbolTable,
+    types: &TypeTable,
+) -> Result<String> {
+    let mut generator = Generator {
+        program: expressions,
+        symbols,
+        types,
+        libc: BTreeSet::new(),
+        labels: 0,
+        output: None,
+    };
+    let mut declarations = String::new();
+    for expression in expressions {
+        declarations += &generator
+            .top_level(expression)
+            .with_context(|| format!("expression: {expression:?}"))?;
+    }
+
+    let mut output_code = String::from("const std = @import(\"std\");\n\n");
+    for (name, declaration) in LIBC {
+        if generator.libc.contains(name) {
+            output_code += declaration;
+            output_code.push('\n');
+        }
+    }
+    if !generator.libc.is_empty() {
+        output_code.push('\n');
+    }
+    output_code += &declarations;
+    Ok(output_code)
+}
+
+/// Translated expression
+struct Code {
+    text: String,
+    /// Zig `bool`, C would have an `int` here unless the type is `bool`
+    boolean: bool,
+    /// doesn't need parentheses before a postfix operator (`.*`, `[i]`, `.x`, a call)
+    atomic: bool,
+}
+
+impl Code {
+    fn new(text: String) -> Self {
+        Self {
+            text,
+            boolean: false,
+            atomic: false,
+        }
     }
+    fn atom(text: String) -> Self {
+        Self {
+            text,
+            boolean: false,
+            atomic: true,
+        }
+    }
+    fn boolean(text: String) -> Self {
+        Self {
+            text,
+            boolean: true,
+            atomic: false,
+        }
+    }
+
+    /// Text that can be followed by a postfix operator
+    fn postfix(&self) -> String {
+        if self.atomic {
+            self.text.to_owned()
+        } else {
+            format!("({})", self.text)
+        }
+    }
+}
+
+struct Generator<'a> {
+    program: &'a [Expression],
+    symbols: &'a SymbolTable,
+    types: &'a TypeTable,
+    /// libc functions that need a declaration
+    libc: BTreeSet<&'static str>,
+    /// numbers of labeled blocks, Zig doesn't allow a label to shadow another one
+    labels: usize,
+    /// output type of the function being translated
+    output: Option<DataType>,
+}
 
-    Ok(output_code);
+fn indent(level: usize) -> String {
+    INDENT.repeat(level)
 }
 
-pub fn vec_of_expr_to_string(value: Vec<Expression>) -> Result<String> {
-    let mut output = String::new();
-    for expr in value {
-        output += &(expr_to_string(expr).context("for_loop -> inside expressions ")? + ";\n");
+fn is_comparison(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Equals
+            | TokenKind::NotEquals
+            | TokenKind::Less
+            | TokenKind::LessEquals
+            | TokenKind::Greater
+            | TokenKind::GreaterEquals
+    )
+}
+
+fn is_literal(expression: &Expression) -> bool {
+    match expression {
+        Expression::Number(..) | Expression::Character(..) => true,
+        Expression::Grouping { value, .. } => is_literal(value),
+        Expression::Prefix { prefix, value, .. } => {
+            matches!(prefix.kind, TokenKind::Minus | TokenKind::Plus) && is_literal(value)
+        }
+        _ => false,
     }
-    output
 }
-pub fn expr_to_string(expr: Expression) -> Result<String> {
-    match expr {
-        Expression::Skip => todo!(),
-        Expression::DataStructureInitialization { values, debug_data } => todo!(),
-        Expression::TypeConversion {
-            value,
-            data_type,
-            debug_data,
-        } => todo!(),
-        Expression::Typedef {
-            data_type,
-            name,
-            debug_data,
-        } => todo!(),
-        Expression::Dereference { value, debug_data } => todo!(),
-        Expression::Boolean(_, debug_data) => todo!(),
-        Expression::Number(_, debug_data) => todo!(),
-        Expression::CompilerData(_, debug_data) => todo!(),
-        Expression::String(_, debug_data) => todo!(),
-        Expression::Identifier(_, debug_data) => todo!(),
-        Expression::Prefix {
-            prefix,
-            value,
-            debug_data,
-        } => todo!(),
-        Expression::Keyword(token_kind, debug_data) => todo!(),
-        Expression::Assignment {
-            target,
-            operator,
-            value,
-            debug_data,
-        } => todo!(),
-        Expression::DataTypeAccess {
-            data_type,
-            debug_data,
-        } => todo!(),
-        Expression::VariableDeclaration {
-            var_type,
-            name,
-            debug_data,
-        } => todo!(),
-        Expression::Grouping { value, debug_data } => todo!(),
-        Expression::Struct {
-            public,
+
+fn is_zero(expression: &Expression) -> bool {
+    match expression {
+        Expression::Number(0, ..) => true,
+        Expression::Grouping { value, .. } => is_zero(value),
+        _ => false,
+    }
+}
+
+/// Unsigned arithmetic wraps in C, Zig has the `+%` operators for it
+fn wrapping(data_type: Option<&DataType>) -> &'static str {
+    match data_type {
+        Some(data_type @ DataType::Data { unsigned: true, .. }) if is_integer(data_type) => "%",
+        _ => "",
+    }
+}
+
+fn member_name(expression: &Expression) -> Result<String> {
+    match expression {
+        Expression::Identifier(name, _) => Ok(identifier(name)),
+        _ => bail!("expected a member name, found: {expression:?}"),
+    }
+}
+
+impl Generator<'_> {
+    fn resolve(&self, data_type: &DataType) -> DataType {
+        resolve(self.symbols, data_type)
+    }
+
+    fn zig_type(&self, data_type: &DataType) -> String {
+        zig_type(self.symbols, data_type)
+    }
+
+    /// Signature of a libc function called without a declaration
+    fn libc(&self, expression: &Expression) -> Option<DataType> {
+        match expression {
+            Expression::Identifier(name, _) if self.symbols.resolution(expression).is_none() => {
+                libc_type(name)
+            }
+            _ => None,
+        }
+    }
+
+    /// Resolved type of the expression used as a value, arrays decay
+    fn type_of(&self, expression: &Expression) -> Option<DataType> {
+        if let Some(function) = self.libc(expression) {
+            return Some(function);
+        }
+        if let Expression::FunctionCall { left, .. } = expression
+            && let Some(DataType::Function { output, .. }) = self.libc(left)
+        {
+            return Some(*output);
+        }
+        Some(match self.resolve(self.types.type_of(expression)?) {
+            DataType::Array { inside, .. } => DataType::Pointer(inside),
+            data_type => data_type,
+        })
+    }
+
+    /// Zig doesn't allow shadowing, so variables that hide an outer name get their symbol id
+    /// appended to it
+    fn name(&self, id: SymbolId) -> String {
+        let symbol = self.symbols.symbol(id);
+        match self.symbols.scopes[symbol.scope].parent {
+            Some(parent) if self.symbols.lookup(parent, &symbol.name).is_some() => {
+                format!("{}_{id}", symbol.name)
+            }
+            _ => identifier(&symbol.name),
+        }
+    }
+
+    /// Name of the variable that a `VariableDeclaration` declares
+    fn declared_name(&self, declaration: &Expression, name: &str) -> String {
+        match self.symbols.declarations.get(&declaration.id()) {
+            Some(id) => self.name(*id),
+            None => identifier(name),
+        }
+    }
+
+    fn label(&mut self) -> String {
+        self.labels += 1;
+        format!("blk_{}", self.labels)
+    }
+
+    // -- declarations --
+
+    fn top_level(&mut self, expression: &Expression) -> Result<String> {
+        Ok(match expression {
+            Expression::Skip => String::new(),
+            Expression::CompilerData(..)
+            | Expression::Typedef { .. }
+            | Expression::FunctionDeclaration { .. } => self.statement(expression, 0)?,
+            Expression::Function { .. } | Expression::Struct { .. } => {
+                self.statement(expression, 0)? + "\n"
+            }
+            Expression::VariableDeclaration { .. }
+            | Expression::Assignment { .. }
+            | Expression::Static { .. } => self.global(expression)?,
+            // only declarations can be at the top level of a Zig file
+            expression => format!("comptime {{\n{}}}\n\n", self.statement(expression, 1)?),
+        })
+    }
+
+    /// Globals start zeroed in C
+    fn global(&mut self, expression: &Expression) -> Result<String> {
+        match expression {
+            Expression::Static { value, .. } => self.global(value),
+            Expression::VariableDeclaration { var_type, name, .. } => {
+                let data_type = self.zig_type(var_type);
+                Ok(format!(
+                    "var {}: {data_type} = std.mem.zeroes({data_type});\n",
+                    self.declared_name(expression, name)
+                ))
+            }
+            expression => self.statement(expression, 0),
+        }
+    }
+
+    /// `var name: T = value;`
+    fn declaration(
+        &mut self,
+        declaration: &Expression,
+        value: Option<&Expression>,
+        level: usize,
+    ) -> Result<String> {
+        let Expression::VariableDeclaration { var_type, name, .. } = declaration else {
+            bail!("expected a variable declaration, found: {declaration:?}");
+        };
+        // arrays decay to pointers that can be written through
+        let constant = value.is_some()
+            && !matches!(self.resolve(var_type), DataType::Array { .. })
+            && self
+                .symbols
+                .declarations
+                .get(&declaration.id())
+                .is_some_and(|id| !self.program.iter().any(|e| self.modifies(e, *id)));
+        let value = match value {
+            Some(value) => self.initializer(var_type, value)?,
+            None => "undefined".to_owned(),
+        };
+        Ok(format!(
+            "{}{} {}: {} = {value};\n",
+            indent(level),
+            if constant { "const" } else { "var" },
+            self.declared_name(declaration, name),
+            self.zig_type(var_type)
+        ))
+    }
+
+    /// Zig needs every element of an aggregate, the ones C leaves out are zeroed
+    fn initializer(&mut self, data_type: &DataType, value: &Expression) -> Result<String> {
+        let resolved = self.resolve(data_type);
+        if let (DataType::Array { length, inside }, Expression::String(string, _)) =
+            (&resolved, value)
+            && integer_rank(&self.resolve(inside)) == Some(2)
+        {
+            let mut bytes = string_bytes(string)?;
+            bytes.resize(*length as usize, 0);
+            let bytes = bytes.iter().map(|byte| character(*byte as i128));
+            return Ok(format!(".{{ {} }}", bytes.collect::<Vec<_>>().join(", ")));
+        }
+        let Expression::DataStructureInitialization { values, .. } = value else {
+            return self.coerce(value, &resolved);
+        };
+
+        let mut elements = Vec::new();
+        match &resolved {
+            DataType::Array { length, inside } => {
+                for i in 0..*length as usize {
+                    elements.push(match values.get(i) {
+                        Some(value) => self.initializer(inside, value)?,
+                        None => format!("std.mem.zeroes({})", self.zig_type(inside)),
+                    });
+                }
+            }
+            DataType::Struct { properties, .. } | DataType::Union { properties, .. } => {
+                let take = match resolved {
+                    DataType::Union { .. } => 1,
+                    _ => properties.len(),
+                };
+                for (i, property) in properties.iter().take(take).enumerate() {
+                    let value = match values.get(i) {
+                        Some(value) => self.initializer(&property.var_type, value)?,
+                        None => format!("std.mem.zeroes({})", self.zig_type(&property.var_type)),
+                    };
+                    elements.push(format!(".{} = {value}", identifier(&property.var_name)));
+                }
+            }
+            scalar => match values.first() {
+                Some(value) => return self.initializer(scalar, value),
+                None => return Ok(format!("std.mem.zeroes({})", self.zig_type(scalar))),
+            },
+        }
+        if elements.is_empty() {
+            return Ok(".{}".to_owned());
+        }
+        Ok(format!(".{{ {} }}", elements.join(", ")))
+    }
+
+    /// Variable that a change of the place changes, `a.x[1]` changes `a`
+    fn root<'e>(&self, place: &'e Expression) -> &'e Expression {
+        match place {
+            Expression::Grouping { value, .. } => self.root(value),
+            Expression::MemberExpr { left, .. } => self.root(left),
+            Expression::AccessArray { left, .. }
+                if matches!(
+                    self.types
+                        .type_of(left)
+                        .map(|data_type| self.resolve(data_type)),
+                    Some(DataType::Array { .. })
+                ) =>
+            {
+                self.root(left)
+            }
+            place => place,
+        }
+    }
+
+    /// Parameters are constants in Zig, the ones that the body changes are copied to a `var`.
+    /// Locals that nothing changes are declared `const`, which Zig requires.
+    fn modifies(&self, expression: &Expression, variable: SymbolId) -> bool {
+        let is_variable = |target: &Expression| {
+            self.symbols.resolutions.get(&self.root(target).id()) == Some(&variable)
+        };
+        let modified = match expression {
+            Expression::Assignment { target, .. }
+                if !matches!(target.as_ref(), Expression::VariableDeclaration { .. }) =>
+            {
+                is_variable(target)
+            }
+            Expression::Increment { target, .. }
+            | Expression::Decrement { target, .. }
+            | Expression::AccessReference { value: target, .. } => is_variable(target),
+            _ => false,
+        };
+        modified
+            || expression
+                .children()
+                .into_iter()
+                .any(|child| self.modifies(child, variable))
+    }
+
+    /// Zig doesn't need prototypes of functions that are defined in the file, the rest are
+    /// provided by a C library
+    fn prototype(&mut self, prototype: &Expression, level: usize) -> Result<String> {
+        let Expression::FunctionDeclaration {
             name,
             properties,
-            functions,
-            debug_data,
-        } => todo!(),
-        Expression::NewCodeBlock { inside, debug_data } => todo!(),
-        Expression::Binary {
-            left,
-            operator,
-            right,
-            debug_data,
-        } => todo!(),
-        Expression::Function {
+            output,
+            variadic,
+            ..
+        } = prototype
+        else {
+            bail!("expected a function prototype, found: {prototype:?}");
+        };
+        let id = self.symbols.declarations.get(&prototype.id());
+        let defined = self.program.iter().any(|expression| {
+            matches!(expression, Expression::Function { .. })
+                && self.symbols.declarations.get(&expression.id()) == id
+        });
+        if defined {
+            return Ok(String::new());
+        }
+        let mut parameters = properties
+            .iter()
+            .map(|property| match property.var_name.as_str() {
+                "" => self.zig_type(&property.var_type),
+                name => format!(
+                    "{}: {}",
+                    identifier(name),
+                    self.zig_type(&property.var_type)
+                ),
+            })
+            .collect::<Vec<_>>();
+        if *variadic {
+            parameters.push("...".to_owned());
+        }
+        Ok(format!(
+            "{}extern \"c\" fn {}({}) {};\n",
+            indent(level),
+            identifier(name),
+            parameters.join(", "),
+            self.zig_type(output)
+        ))
+    }
+
+    fn function(&mut self, function: &Expression, level: usize) -> Result<String> {
+        let Expression::Function {
             name,
             properties,
             output,
             inside,
-            debug_data,
-        } => todo!(),
-        Expression::MemberExpr {
-            left,
-            right,
-            debug_data,
-        } => todo!(),
-
-        Expression::AccessReference { value, debug_data } => todo!(),
-        Expression::Break { debug_data } => todo!(),
-        Expression::Return { value, debug_data } => todo!(),
-        Expression::If {
-            condition,
-            inside,
-            chained_elses,
-            debug_data,
-        } => todo!(),
-        Expression::Else {
-            condition,
-            inside,
-            debug_data,
-        } => todo!(),
-        Expression::Index {
-            left,
-            index,
-            debug_data,
-        } => todo!(),
-        Expression::While {
-            condition,
-            inside,
-            debug_data,
-        } => todo!(),
-        Expression::Static { value, debug_data } => todo!(),
-        Expression::For {
-            iterator_init,
-            condition,
-            incr,
-            inside,
-            debug_data,
-        } => todo!(),
-        Expression::FunctionCall {
-            left,
-            values,
-            debug_data,
-        } => todo!(),
+            ..
+        } = function
+        else {
+            bail!("expected a function, found: {function:?}");
+        };
+        let parameters = self
+            .symbols
+            .parameters
+            .get(&function.id())
+            .cloned()
+            .unwrap_or_default();
+
+        let mut signature = Vec::new();
+        let mut copies = String::new();
+        for (i, property) in properties.iter().enumerate() {
+            let data_type = self.zig_type(&property.var_type);
+            let name = match parameters.get(i) {
+                Some(id) => self.name(*id),
+                None => identifier(&property.var_name),
+            };
+            let modified = parameters.get(i).is_some_and(|id| {
+                inside
+                    .iter()
+                    .any(|expression| self.modifies(expression, *id))
+            });
+            if modified {
+                let argument = identifier(&format!("arg_{}", property.var_name));
+                signature.push(format!("{argument}: {data_type}"));
+                copies += &format!("{}var {name} = {argument};\n", indent(level + 1));
+            } else {
+                signature.push(format!("{name}: {data_type}"));
+            }
+        }
+
+        // `main` keeps the C signature, so the program still links against libc
+        let prefix = if name == "main" { "pub export " } else { "" };
+        let outer = self.output.replace(output.to_owned());
+        let body = self.statements(inside, level + 1);
+        self.output = outer;
+        Ok(format!(
+            "{}{prefix}fn {}({}) {} {{\n{copies}{}{}}}\n",
+            indent(level),
+            identifier(name),
+            signature.join(", "),
+            self.zig_type(output),
+            body?,
+            indent(level)
+        ))
+    }
+
+    // -- statements --
+
+    fn statements(&mut self, inside: &[Expression], level: usize) -> Result<String> {
+        let mut output = String::new();
+        for expression in inside {
+            output += &self.statement(expression, level)?;
+        }
+        Ok(output)
+    }
+
+    fn block(&mut self, inside: &[Expression], level: usize) -> Result<String> {
+        Ok(format!(
+            "{{\n{}{}}}",
+            self.statements(inside, level + 1)?,
+            indent(level)
+        ))
+    }
+
+    fn statement(&mut self, expression: &Expression, level: usize) -> Result<String> {
+        let tab = indent(level);
+        Ok(match expression {
+            Expression::Skip | Expression::Else { .. } => String::new(),
+            Expression::CompilerData(value, _) => format!("{tab}// #{}\n", value.trim()),
+            Expression::Typedef {
+                data_type, name, ..
+            } => {
+                let mut output = format!(
+                    "{tab}const {} = {};\n",
+                    identifier(name),
+                    self.zig_type(data_type)
+                );
+                if let DataType::Enum { fields } = data_type {
+                    for field in fields {
+                        output += &format!(
+                            "{tab}const {}: c_int = {};\n",
+                            identifier(&field.name),
+                            field.value
+                        );
+                    }
+                }
+                output
+            }
+            Expression::Struct {
+                public,
+                name,
+                properties,
+                functions,
+                ..
+            } => {
+                let mut output = format!(
+                    "{tab}{}const {} = struct {{\n",
+                    if *public { "pub " } else { "" },
+                    identifier(name)
+                );
+                for property in properties {
+                    output += &format!(
+                        "{tab}{INDENT}{}: {},\n",
+                        identifier(&property.var_name),
+                        self.zig_type(&property.var_type)
+                    );
+                }
+                for function in functions {
+                    output += &self.statement(function, level + 1)?;
+                }
+                output + &format!("{tab}}};\n")
+            }
+            Expression::Function { .. } => self.function(expression, level)?,
+            Expression::FunctionDeclaration { .. } => self.prototype(expression, level)?,
+            Expression::VariableDeclaration { .. } => self.declaration(expression, None, level)?,
+            Expression::Assignment { target, value, .. }
+                if matches!(target.as_ref(), Expression::VariableDeclaration { .. }) =>
+            {
+                self.declaration(target, Some(value), level)?
+            }
+            Expression::Static { value, .. } => {
+                format!("{tab}// static\n{}", self.statement(value, level)?)
+            }
+            Expression::NewCodeBlock { inside, .. } => {
+                format!("{tab}{}\n", self.block(inside, level)?)
+            }
+            Expression::Break { .. } => format!("{tab}break;\n"),
+            Expression::Return { value, .. } => match value.as_ref() {
+                Expression::Skip => format!("{tab}return;\n"),
+                value => {
+                    let value = match self.output.to_owned() {
+                        Some(output) => self.coerce(value, &self.resolve(&output))?,
+                        None => self.value(value)?,
+                    };
+                    format!("{tab}return {value};\n")
+                }
+            },
+            Expression::If {
+                condition,
+                inside,
+                chained_elses,
+                ..
+            } => {
+                let mut output = format!(
+                    "{tab}if ({}) {}",
+                    self.condition(condition)?,
+                    self.block(inside, level)?
+                );
+                for chained in chained_elses {
+                    let Expression::Else {
+                        condition, inside, ..
+                    } = chained
+                    else {
+                        bail!("expected an else, found: {chained:?}");
+                    };
+                    output += " else ";
+                    if let Some(condition) = condition {
+                        output += &format!("if ({}) ", self.condition(condition)?);
+                    }
+                    output += &self.block(inside, level)?;
+                }
+                output + "\n"
+            }
+            Expression::While {
+                condition, inside, ..
+            } => format!(
+                "{tab}while ({}) {}\n",
+                self.condition(condition)?,
+                self.block(inside, level)?
+            ),
+            // the initialization gets its own block, so that its variables don't leak out
+            Expression::For {
+                iterator_init,
+                condition,
+                incr,
+                inside,
+                ..
+            } => {
+                let continue_expression = match incr.as_ref() {
+                    Expression::Skip => String::new(),
+                    incr => format!(" : ({})", self.simple_statement(incr)?),
+                };
+                format!(
+                    "{tab}{{\n{}{tab}{INDENT}while ({}){continue_expression} {}\n{tab}}}\n",
+                    self.statement(iterator_init, level + 1)?,
+                    self.condition(condition)?,
+                    self.block(inside, level + 1)?
+                )
+            }
+            Expression::FunctionCall { left, values, .. } if matches!(left.as_ref(), Expression::Identifier(name, _) if name == "_Static_assert") =>
+            {
+                let Some(condition) = values.first() else {
+                    bail!("'_Static_assert' expects a condition");
+                };
+                let message = match values.get(1) {
+                    Some(Expression::String(message, _)) => string(&string_bytes(message)?),
+                    _ => "\"static assertion failed\"".to_owned(),
+                };
+                format!(
+                    "{tab}if (!({})) @compileError({message});\n",
+                    self.condition(condition)?
+                )
+            }
+            expression => format!("{tab}{};\n", self.simple_statement(expression)?),
+        })
+    }
+
+    /// Statement that is a single expression, like the continue expression of a loop
+    fn simple_statement(&mut self, expression: &Expression) -> Result<String> {
+        Ok(match expression {
+            Expression::Increment { target, .. } => format!(
+                "{} +{}= 1",
+                self.value(target)?,
+                wrapping(self.type_of(target).as_ref())
+            ),
+            Expression::Decrement { target, .. } => format!(
+                "{} -{}= 1",
+                self.value(target)?,
+                wrapping(self.type_of(target).as_ref())
+            ),
+            Expression::Assignment {
+                target,
+                operator,
+                value,
+                ..
+            } => self.assignment(target, operator.kind, value)?,
+            Expression::Grouping { value, .. } => self.simple_statement(value)?,
+            expression => {
+                let code = self.expression(expression)?;
+                match self.type_of(expression) {
+                    Some(data_type) if is_void(&data_type) => code.text,
+                    _ => format!("_ = {}", code.text),
+                }
+            }
+        })
+    }
+
+    fn assignment(
+        &mut self,
+        target: &Expression,
+        operator: TokenKind,
+        value: &Expression,
+    ) -> Result<String> {
+        let target_text = self.value(target)?;
+        let Some(target_type) = self.type_of(target) else {
+            return Ok(format!(
+                "{target_text} {} {}",
+                operator.to_str(),
+                self.value(value)?
+            ));
+        };
+        Ok(match operator {
+            TokenKind::Assignment => {
+                format!("{target_text} = {}", self.coerce(value, &target_type)?)
+            }
+            // pointer arithmetic needs a `usize`
+            TokenKind::PlusEquals | TokenKind::MinusEquals
+                if matches!(target_type, DataType::Pointer(_)) =>
+            {
+                format!(
+                    "{target_text} {} {}",
+                    operator.to_str(),
+                    self.offset(value)?
+                )
+            }
+            TokenKind::SlashEquals if !is_floating(&target_type) => format!(
+                "{target_text} = @divTrunc({target_text}, {})",
+                self.coerce(value, &target_type)?
+            ),
+            TokenKind::PercentEquals => format!(
+                "{target_text} = @rem({target_text}, {})",
+                self.coerce(value, &target_type)?
+            ),
+            // the shift amount is a `Log2Int` of the target
+            TokenKind::BitwiseShiftLeftEquals | TokenKind::BitwiseShiftRightEquals => {
+                let value_text = match is_literal(value) {
+                    true => self.value(value)?,
+                    false => format!("@intCast({})", self.value(value)?),
+                };
+                format!("{target_text} {} {value_text}", operator.to_str())
+            }
+            TokenKind::PlusEquals | TokenKind::MinusEquals | TokenKind::StarEquals => {
+                let operator = operator.to_str();
+                format!(
+                    "{target_text} {}{}= {}",
+                    &operator[..1],
+                    wrapping(Some(&target_type)),
+                    self.coerce(value, &target_type)?
+                )
+            }
+            operator => format!(
+                "{target_text} {} {}",
+                operator.to_str(),
+                self.coerce(value, &target_type)?
+            ),
+        })
+    }
+
+    // -- expressions --
+
+    /// Value used as a condition, Zig only accepts `bool`s
+    fn condition(&mut self, expression: &Expression) -> Result<String> {
+        if let Expression::Skip = expression {
+            return Ok(boolean(true));
+        }
+        let code = self.expression(expression)?;
+        if code.boolean {
+            return Ok(code.text);
+        }
+        Ok(match self.type_of(expression) {
+            Some(DataType::Pointer(_)) => format!("{} != null", code.text),
+            Some(DataType::Data { name, .. }) if name == "bool" => code.text,
+            // constant conditions like `while (1)`
+            _ if is_literal(expression) => {
+                let value = match expression {
+                    Expression::Character(value, _) => character_value(value)?,
+                    _ => self.value(expression)?.parse::<i128>().unwrap_or(1),
+                };
+                boolean(value != 0)
+            }
+            _ => format!("{} != 0", code.text),
+        })
+    }
+
+    /// Value used as a number, comparisons become integers again and arrays decay to pointers
+    fn value(&mut self, expression: &Expression) -> Result<String> {
+        let code = self.expression(expression)?;
+        if code.boolean {
+            return Ok(format!("@intFromBool({})", code.text));
+        }
+        if let Some(DataType::Array { inside, .. }) = self
+            .types
+            .type_of(expression)
+            .map(|data_type| self.resolve(data_type))
+            && !matches!(expression, Expression::String(..))
+        {
+            return Ok(format!(
+                "@as({}, &{})",
+                self.zig_type(&DataType::Pointer(inside)),
+                code.postfix()
+            ));
+        }
+        Ok(code.text)
+    }
+
+    /// Value converted to the type it is stored in, the conversions C does implicitly
+    fn coerce(&mut self, expression: &Expression, target: &DataType) -> Result<String> {
+        let target = self.resolve(target);
+        if matches!(&target, DataType::Data { name, .. } if name == "bool") {
+            return self.condition(expression);
+        }
+        if let DataType::Pointer(_) = target
+            && is_zero(expression)
+        {
+            return Ok("null".to_owned());
+        }
+        let text = self.value(expression)?;
+        let Some(source) = self.type_of(expression) else {
+            return Ok(text);
+        };
+        Ok(match (&source, &target) {
+            _ if is_literal(expression) => text,
+            (source, target) if is_integer(source) && is_integer(target) => {
+                if self.zig_type(source) == self.zig_type(target) {
+                    text
+                } else {
+                    format!("@as({}, @intCast({text}))", self.zig_type(target))
+                }
+            }
+            (source, target) if is_integer(source) && is_floating(target) => {
+                format!("@as({}, @floatFromInt({text}))", self.zig_type(target))
+            }
+            (source, target) if is_floating(source) && is_integer(target) => {
+                format!("@as({}, @intFromFloat({text}))", self.zig_type(target))
+            }
+            // string literals and adding `const` are implicit in Zig too
+            (DataType::Pointer(_), DataType::Pointer(_)) => {
+                let target_type = self.zig_type(&target);
+                let source_type = self.zig_type(&source);
+                if matches!(expression, Expression::String(..))
+                    || source_type == target_type
+                    || source_type == target_type.replace("[*c]const ", "[*c]")
+                {
+                    text
+                } else {
+                    format!(
+                        "@as({}, @ptrCast(@alignCast({text})))",
+                        self.zig_type(&target)
+                    )
+                }
+            }
+            _ => text,
+        })
+    }
+
+    /// Integer added to a pointer
+    fn offset(&mut self, expression: &Expression) -> Result<String> {
+        let text = self.value(expression)?;
+        if is_literal(expression) {
+            return Ok(text);
+        }
+        Ok(format!("@as(usize, @intCast({text}))"))
+    }
+
+    fn binary(
+        &mut self,
+        left: &Expression,
+        operator: TokenKind,
+        right: &Expression,
+    ) -> Result<Code> {
+        if let TokenKind::And | TokenKind::Or = operator {
+            let keyword = match operator {
+                TokenKind::And => "and",
+                _ => "or",
+            };
+            return Ok(Code::boolean(format!(
+                "{} {keyword} {}",
+                self.condition(left)?,
+                self.condition(right)?
+            )));
+        }
+
+        let (left_type, right_type) = (self.type_of(left), self.type_of(right));
+        let operator_text = operator.to_str();
+        match (&left_type, &right_type) {
+            (Some(DataType::Pointer(_)), Some(DataType::Pointer(inside)))
+                if operator == TokenKind::Minus =>
+            {
+                return Ok(Code::new(format!(
+                    "@as(c_long, @intCast(@divExact(@intFromPtr({}) - @intFromPtr({}), @sizeOf({}))))",
+                    self.value(left)?,
+                    self.value(right)?,
+                    self.zig_type(inside)
+                )));
+            }
+            (Some(DataType::Pointer(_)), _) if is_comparison(operator) && is_zero(right) => {
+                return Ok(Code::boolean(format!(
+                    "{} {operator_text} null",
+                    self.value(left)?
+                )));
+            }
+            (_, Some(DataType::Pointer(_))) if is_comparison(operator) && is_zero(left) => {
+                return Ok(Code::boolean(format!(
+                    "null {operator_text} {}",
+                    self.value(right)?
+                )));
+            }
+            (Some(DataType::Pointer(_)), Some(other)) if is_integer(other) => {
+                return Ok(Code::new(format!(
+                    "{} {operator_text} {}",
+                    self.value(left)?,
+                    self.offset(right)?
+                )));
+            }
+            (Some(other), Some(DataType::Pointer(_))) if is_integer(other) => {
+                return Ok(Code::new(format!(
+                    "{} + {}",
+                    self.value(right)?,
+                    self.offset(left)?
+                )));
+            }
+            _ => {}
+        }
+
+        // both operands are converted to their common type first
+        let common = match (&left_type, &right_type) {
+            (Some(left), Some(right)) if is_integer(left) || is_floating(left) => {
+                if is_integer(right) || is_floating(right) {
+                    Some(usual_arithmetic_conversion(left, right))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        let (left_text, right_text) = match (&common, operator) {
+            (Some(common), TokenKind::BitwiseShiftLeft | TokenKind::BitwiseShiftRight) => {
+                let right_text = match is_literal(right) {
+                    true => self.value(right)?,
+                    false => format!("@intCast({})", self.value(right)?),
+                };
+                (self.coerce(left, common)?, right_text)
+            }
+            (Some(common), _) => (self.coerce(left, common)?, self.coerce(right, common)?),
+            (None, _) => (self.value(left)?, self.value(right)?),
+        };
+        let floating = common.as_ref().is_some_and(is_floating);
+        Ok(match operator {
+            operator if is_comparison(operator) => {
+                Code::boolean(format!("{left_text} {operator_text} {right_text}"))
+            }
+            TokenKind::Slash if !floating => {
+                Code::atom(format!("@divTrunc({left_text}, {right_text})"))
+            }
+            TokenKind::Percent if !floating => {
+                Code::atom(format!("@rem({left_text}, {right_text})"))
+            }
+            TokenKind::Reference => Code::new(format!("{left_text} & {right_text}")),
+            TokenKind::Plus | TokenKind::Minus | TokenKind::Star => Code::new(format!(
+                "{left_text} {operator_text}{} {right_text}",
+                wrapping(common.as_ref())
+            )),
+            _ => Code::new(format!("{left_text} {operator_text} {right_text}")),
+        })
+    }
+
+    fn call(&mut self, left: &Expression, values: &[Expression]) -> Result<Code> {
+        if let Expression::Identifier(name, _) = left {
+            match name.as_str() {
+                "sizeof" | "_Alignof" => {
+                    let builtin = match name.as_str() {
+                        "sizeof" => "@sizeOf",
+                        _ => "@alignOf",
+                    };
+                    let [value] = values else {
+                        bail!("'{name}' takes 1 operand, found: {}", values.len());
+                    };
+                    let operand = match value {
+                        Expression::DataTypeAccess { data_type, .. }
+                            if self.symbols.resolution(value).is_none() =>
+                        {
+                            self.zig_type(data_type)
+                        }
+                        value => format!("@TypeOf({})", self.expression(value)?.text),
+                    };
+                    return Ok(Code::atom(format!("{builtin}({operand})")));
+                }
+                "_Static_assert" => {
+                    bail!("'_Static_assert' can only be used as a statement")
+                }
+                _ => {}
+            }
+            if self.symbols.resolution(left).is_none()
+                && let Some((name, _)) = LIBC.iter().find(|(libc, _)| libc == name)
+            {
+                self.libc.insert(name);
+            }
+        }
+
+        let parameters = match self.type_of(left) {
+            Some(DataType::Function { parameters, .. }) => parameters,
+            Some(DataType::Pointer(inside)) => match self.resolve(&inside) {
+                DataType::Function { parameters, .. } => parameters,
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        };
+        let mut arguments = Vec::new();
+        for (i, value) in values.iter().enumerate() {
+            arguments.push(match parameters.get(i) {
+                Some(parameter) => self.coerce(value, parameter)?,
+                // variadic arguments need a type with a size
+                None => match self.type_of(value) {
+                    Some(data_type) if is_literal(value) => {
+                        format!("@as({}, {})", self.zig_type(&data_type), self.value(value)?)
+                    }
+                    Some(DataType::Pointer(_)) if matches!(value, Expression::String(..)) => {
+                        format!("@as([*c]const u8, {})", self.value(value)?)
+                    }
+                    _ => self.value(value)?,
+                },
+            });
+        }
+        let callee = self.expression(left)?.postfix();
+        Ok(Code::atom(format!("{callee}({})", arguments.join(", "))))
+    }
+
+    /// Assignment or `x++` used as a value, turned into a block that produces it
+    fn block_expression(&mut self, statements: Vec<String>, result: String) -> Code {
+        let label = self.label();
+        Code::atom(format!(
+            "{label}: {{ {} break :{label} {result}; }}",
+            statements
+                .iter()
+                .map(|statement| format!("{statement};"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        ))
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Result<Code> {
+        Ok(match expression {
+            Expression::Skip => Code::atom("{}".to_owned()),
+            Expression::Boolean(value, _) => Code {
+                text: boolean(*value),
+                boolean: true,
+                atomic: true,
+            },
+            Expression::Number(value, ..) => Code::atom(value.to_string()),
+            Expression::Character(value, _) => Code::atom(character(character_value(value)?)),
+            Expression::String(value, _) => Code::atom(string(&string_bytes(value)?)),
+            Expression::Identifier(name, _) => match self.symbols.resolutions.get(&expression.id())
+            {
+                Some(id) => Code::atom(self.name(*id)),
+                None => Code::atom(identifier(name)),
+            },
+            Expression::DataTypeAccess { data_type, .. } => {
+                match self.symbols.resolution(expression) {
+                    Some(_) => Code::atom(self.name(self.symbols.resolutions[&expression.id()])),
+                    None => Code::atom(self.zig_type(data_type)),
+                }
+            }
+            Expression::Grouping { value, .. } => {
+                let code = self.expression(value)?;
+                Code {
+                    text: format!("({})", code.text),
+                    boolean: code.boolean,
+                    atomic: true,
+                }
+            }
+            Expression::Static { value, .. } => self.expression(value)?,
+            Expression::Prefix { prefix, value, .. } => match prefix.kind {
+                TokenKind::Not => Code::boolean(format!("!({})", self.condition(value)?)),
+                TokenKind::Minus => Code::new(format!("-{}", self.value(value)?)),
+                TokenKind::BitwiseNot => Code::new(format!("~{}", self.value(value)?)),
+                TokenKind::Plus => Code::new(self.value(value)?),
+                kind => bail!("unsupported prefix operator: '{}'", kind.to_str()),
+            },
+            Expression::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => self.binary(left, operator.kind, right)?,
+            Expression::TypeConversion {
+                value, data_type, ..
+            } => {
+                let target = self.resolve(data_type);
+                if is_void(&target) {
+                    let label = self.label();
+                    return Ok(Code::atom(format!(
+                        "{label}: {{ _ = {}; break :{label} {{}}; }}",
+                        self.value(value)?
+                    )));
+                }
+                let text = self.coerce(value, &target)?;
+                match self.type_of(value) {
+                    Some(DataType::Pointer(_)) if is_integer(&target) => Code::atom(format!(
+                        "@as({}, @intCast(@intFromPtr({})))",
+                        self.zig_type(&target),
+                        self.value(value)?
+                    )),
+                    Some(source)
+                        if is_integer(&source) && matches!(target, DataType::Pointer(_)) =>
+                    {
+                        Code::atom(format!(
+                            "@as({}, @ptrFromInt(@as(usize, @intCast({}))))",
+                            self.zig_type(&target),
+                            self.value(value)?
+                        ))
+                    }
+                    _ if text.starts_with("@as(") => Code::atom(text),
+                    _ => Code::atom(format!("@as({}, {text})", self.zig_type(&target))),
+                }
+            }
+            Expression::Dereference { value, .. } => {
+                Code::atom(format!("{}.*", self.expression(value)?.postfix()))
+            }
+            Expression::AccessReference { value, .. } => {
+                Code::new(format!("&{}", self.expression(value)?.postfix()))
+            }
+            Expression::MemberExpr { left, right, .. } => Code::atom(format!(
+                "{}.{}",
+                self.expression(left)?.postfix(),
+                member_name(right)?
+            )),
+            Expression::Arrow { left, right, .. } => Code::atom(format!(
+                "{}.*.{}",
+                self.expression(left)?.postfix(),
+                member_name(right)?
+            )),
+            Expression::AccessArray { left, index, .. } => {
+                let index = match is_literal(index) {
+                    true => self.value(index)?,
+                    false => format!("@intCast({})", self.value(index)?),
+                };
+                Code::atom(format!("{}[{index}]", self.expression(left)?.postfix()))
+            }
+            Expression::FunctionCall { left, values, .. } => self.call(left, values)?,
+            Expression::DataStructureInitialization { values, .. } => {
+                let mut elements = Vec::new();
+                for value in values {
+                    elements.push(self.value(value)?);
+                }
+                Code::atom(format!(".{{ {} }}", elements.join(", ")))
+            }
+            Expression::Assignment {
+                target,
+                operator,
+                value,
+                ..
+            } => {
+                let assignment = self.assignment(target, operator.kind, value)?;
+                let result = self.value(target)?;
+                self.block_expression(vec![assignment], result)
+            }
+            Expression::Increment { target, .. } | Expression::Decrement { target, .. } => {
+                let operator = match expression {
+                    Expression::Increment { .. } => "+",
+                    _ => "-",
+                };
+                let operator = format!("{operator}{}=", wrapping(self.type_of(target).as_ref()));
+                let target = self.value(target)?;
+                let old = format!("old_{}", self.labels + 1);
+                self.block_expression(
+                    vec![
+                        format!("const {old} = {target}"),
+                        format!("{target} {operator} 1"),
+                    ],
+                    old,
+                )
+            }
+            Expression::VariableDeclaration { name, .. } => {
+                bail!("declaration of '{name}' can't be used as a value")
+            }
+            Expression::Function { name, .. } => {
+                bail!("function '{name}' can't be defined inside of an expression")
+            }
+            expression => {
+                let line = expression.debug_data().map(|DebugData { line, .. }| *line);
+                bail!("statement at line: {line:?} can't be used as a value")
+            }
+        })
     }
 }
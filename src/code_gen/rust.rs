@@ -0,0 +1,626 @@
+use std::collections::{HashSet, VecDeque};
+
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+
+use crate::{
+    code_gen::functions::string,
+    layout::{LayoutEngine, target::Target},
+    lexer::{
+        string_bytes,
+        token::{Token, TokenKind},
+    },
+    parser::{
+        self,
+        expression::{Expression, Property},
+        types::DataType,
+    },
+    preprocessor::{PpToken, Preprocessor, is_white_space, macros::MacroKind},
+    semantic::{
+        constant::{Evaluator, resolve_typedefs},
+        scope::SymbolTable,
+        type_check::is_void,
+    },
+};
+
+const INDENT: &str = "    ";
+
+const RUST_KEYWORDS: [&str; 48] = [
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+/// Names of items to generate: a pattern is an exact name or a regex that has to match the
+/// whole name
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    allow: Vec<Regex>,
+    deny: Vec<Regex>,
+}
+
+impl Filter {
+    pub fn new(allow: &[String], deny: &[String]) -> Result<Self> {
+        let compile = |patterns: &[String]| -> Result<Vec<Regex>> {
+            patterns
+                .iter()
+                .map(|pattern| {
+                    Regex::new(&format!("^(?:{pattern})$"))
+                        .with_context(|| format!("invalid bindings pattern: '{pattern}'"))
+                })
+                .collect()
+        };
+        Ok(Self {
+            allow: compile(allow)?,
+            deny: compile(deny)?,
+        })
+    }
+
+    /// An empty allowlist allows everything, the denylist always wins
+    pub fn allows(&self, name: &str) -> bool {
+        (self.allow.is_empty() || self.allow.iter().any(|pattern| pattern.is_match(name)))
+            && !self.denies(name)
+    }
+
+    pub fn denies(&self, name: &str) -> bool {
+        self.deny.iter().any(|pattern| pattern.is_match(name))
+    }
+}
+
+/// C names that Rust reserves are written as raw identifiers, the ones that can't be raw get a
+/// `_` at the end
+pub fn identifier(value: &str) -> String {
+    if matches!(value, "self" | "Self" | "super" | "crate" | "_") {
+        format!("{value}_")
+    } else if RUST_KEYWORDS.contains(&value) {
+        format!("r#{value}")
+    } else {
+        value.to_owned()
+    }
+}
+
+fn primitive(name: &str, unsigned: bool) -> Option<&'static str> {
+    Some(match (name, unsigned) {
+        ("void", _) => "::core::ffi::c_void",
+        ("bool", _) => "bool",
+        ("char", false) => "::core::ffi::c_char",
+        ("char", true) => "::core::ffi::c_uchar",
+        ("short", false) => "::core::ffi::c_short",
+        ("short", true) => "::core::ffi::c_ushort",
+        ("int", false) => "::core::ffi::c_int",
+        ("int", true) => "::core::ffi::c_uint",
+        ("long", false) => "::core::ffi::c_long",
+        ("long", true) => "::core::ffi::c_ulong",
+        ("float", _) => "f32",
+        ("double", _) => "f64",
+        _ => return None,
+    })
+}
+
+/// Enums are `c_uint`s like with gcc, unless one of the constants is negative
+fn enum_type(data_type: &DataType) -> &'static str {
+    match data_type {
+        DataType::Enum { fields } if fields.iter().any(|field| field.value < 0) => {
+            "::core::ffi::c_int"
+        }
+        _ => "::core::ffi::c_uint",
+    }
+}
+
+/// Typedef names that a type refers to
+fn referenced(data_type: &DataType, names: &mut Vec<String>) {
+    match data_type {
+        DataType::Data { name, .. } => {
+            if primitive(name, false).is_none() {
+                names.push(name.to_owned());
+            }
+        }
+        DataType::Array { inside, .. } | DataType::Pointer(inside) => referenced(inside, names),
+        DataType::Struct { properties, .. } | DataType::Union { properties, .. } => {
+            for property in properties {
+                referenced(&property.var_type, names);
+            }
+        }
+        DataType::Enum { .. } => {}
+        DataType::Function {
+            output, parameters, ..
+        } => {
+            referenced(output, names);
+            for parameter in parameters {
+                referenced(parameter, names);
+            }
+        }
+    }
+}
+
+/// Item of the header that bindings can be generated for
+enum Item<'a> {
+    Typedef {
+        name: &'a str,
+        data_type: &'a DataType,
+    },
+    Function {
+        name: &'a str,
+        properties: &'a [Property],
+        output: &'a DataType,
+        variadic: bool,
+    },
+}
+
+impl Item<'_> {
+    fn name(&self) -> &str {
+        match self {
+            Item::Typedef { name, .. } | Item::Function { name, .. } => name,
+        }
+    }
+}
+
+/// Writes Rust `extern "C"` declarations and `#[repr(C)]` types for the typedefs, prototypes,
+/// non-static functions and constant object-like `#define`s of the program. Layout assertions
+/// are generated for every record, computed with the layout engine for the given target.
+pub fn generate_bindings(
+    expressions: &[Expression],
+    symbols: &SymbolTable,
+    preprocessor: &mut Preprocessor,
+    target: &Target,
+    filter: &Filter,
+    file: &str,
+) -> Result<String> {
+    let mut items = Vec::new();
+    for expression in expressions {
+        match expression {
+            Expression::Typedef {
+                data_type, name, ..
+            } => items.push(Item::Typedef { name, data_type }),
+            Expression::FunctionDeclaration {
+                name,
+                properties,
+                output,
+                variadic,
+                ..
+            } => items.push(Item::Function {
+                name,
+                properties,
+                output,
+                variadic: *variadic,
+            }),
+            Expression::Function {
+                name,
+                properties,
+                output,
+                ..
+            } => items.push(Item::Function {
+                name,
+                properties,
+                output,
+                variadic: false,
+            }),
+            _ => {}
+        }
+    }
+
+    let mut generator = Generator {
+        symbols,
+        engine: LayoutEngine::new(target, symbols),
+        items: Vec::new(),
+        records: Vec::new(),
+    };
+
+    // typedefs used by what is generated come along, the bindings wouldn't compile without them
+    let typedefs = items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Typedef { name, .. } => Some(*name),
+            Item::Function { .. } => None,
+        })
+        .collect::<HashSet<_>>();
+    let mut needed = HashSet::new();
+    let mut queue = items
+        .iter()
+        .filter(|item| filter.allows(item.name()))
+        .collect::<VecDeque<_>>();
+    let mut emitted = HashSet::new();
+    while let Some(item) = queue.pop_front() {
+        if !needed.insert(item.name()) {
+            continue;
+        }
+        let mut names = Vec::new();
+        match item {
+            Item::Typedef { data_type, .. } => referenced(data_type, &mut names),
+            Item::Function {
+                properties, output, ..
+            } => {
+                referenced(output, &mut names);
+                for property in *properties {
+                    referenced(&property.var_type, &mut names);
+                }
+            }
+        }
+        for name in names {
+            if !typedefs.contains(name.as_str()) {
+                continue;
+            }
+            if filter.denies(&name) {
+                bail!("'{}' uses '{name}', which is denied", item.name());
+            }
+            queue.extend(items.iter().filter(
+                |item| matches!(item, Item::Typedef { name: other, .. } if *other == name),
+            ));
+        }
+    }
+
+    let mut functions = Vec::new();
+    for item in &items {
+        if !needed.contains(item.name()) || !emitted.insert(item.name()) {
+            continue;
+        }
+        match item {
+            Item::Typedef { name, data_type } => generator.typedef(name, data_type),
+            Item::Function {
+                name,
+                properties,
+                output,
+                variadic,
+            } => functions.push(generator.function(name, properties, output, *variadic)),
+        }
+    }
+
+    let mut output = format!(
+        "// Rust bindings for: {file}\n// layouts were computed for the target: {}\n\
+         #![allow(non_camel_case_types, non_snake_case, non_upper_case_globals, dead_code)]\n",
+        target.name
+    );
+    let constants = generator.constants(preprocessor, target, filter);
+    if !constants.is_empty() {
+        output.push('\n');
+        output += &constants.join("");
+    }
+    for item in &generator.items {
+        output.push('\n');
+        output += item;
+    }
+    if !functions.is_empty() {
+        output += "\nunsafe extern \"C\" {\n";
+        output += &functions.join("");
+        output += "}\n";
+    }
+    output += &generator.layout_tests()?;
+    Ok(output)
+}
+
+struct Generator<'a> {
+    symbols: &'a SymbolTable,
+    engine: LayoutEngine<'a, SymbolTable>,
+    /// type definitions in the order they have to be written
+    items: Vec<String>,
+    /// records and their names, for the layout assertions
+    records: Vec<(String, DataType)>,
+}
+
+impl Generator<'_> {
+    fn typedef(&mut self, name: &str, data_type: &DataType) {
+        match data_type {
+            DataType::Struct { .. } | DataType::Union { .. } => self.record(name, data_type),
+            DataType::Enum { fields } => {
+                let mut output = format!(
+                    "pub type {} = {};\n",
+                    identifier(name),
+                    enum_type(data_type)
+                );
+                for field in fields {
+                    output += &format!(
+                        "pub const {}: {} = {};\n",
+                        identifier(&field.name),
+                        identifier(name),
+                        field.value
+                    );
+                }
+                self.items.push(output);
+            }
+            // `typedef struct Entry Entry;` would alias itself
+            DataType::Data { name: inner, .. } if inner == name => {}
+            _ => {
+                let rust_type = self.rust_type(data_type, name);
+                self.items
+                    .push(format!("pub type {} = {rust_type};\n", identifier(name)));
+            }
+        }
+    }
+
+    /// Rust type with the same layout. Anonymous records inside of other types get the name of
+    /// the place they were found at.
+    fn rust_type(&mut self, data_type: &DataType, place: &str) -> String {
+        match data_type {
+            DataType::Data { name, unsigned, .. } => match primitive(name, *unsigned) {
+                Some(primitive) => primitive.to_owned(),
+                None => identifier(name),
+            },
+            DataType::Pointer(inside) => {
+                if let DataType::Function { .. } = resolve_typedefs(self.symbols, inside) {
+                    // function pointers can be null, only `Option` of them can
+                    return format!("Option<{}>", self.rust_type(inside, place));
+                }
+                let mutability = match inside.as_ref() {
+                    DataType::Data { constant: true, .. } => "const",
+                    _ => "mut",
+                };
+                format!("*{mutability} {}", self.rust_type(inside, place))
+            }
+            DataType::Array { length, inside } => {
+                format!("[{}; {length}]", self.rust_type(inside, place))
+            }
+            DataType::Struct { .. } | DataType::Union { .. } => {
+                self.record(place, data_type);
+                identifier(place)
+            }
+            DataType::Enum { .. } => enum_type(data_type).to_owned(),
+            DataType::Function {
+                output,
+                parameters,
+                variadic,
+            } => {
+                let mut parameters = parameters
+                    .iter()
+                    .map(|parameter| self.parameter_type(parameter, place))
+                    .collect::<Vec<_>>();
+                if *variadic {
+                    parameters.push("...".to_owned());
+                }
+                format!(
+                    "unsafe extern \"C\" fn({}){}",
+                    parameters.join(", "),
+                    self.output_type(output, place)
+                )
+            }
+        }
+    }
+
+    /// Arrays can't be passed by value in C, they decay to a pointer to the first element
+    fn parameter_type(&mut self, data_type: &DataType, place: &str) -> String {
+        match data_type {
+            DataType::Array { inside, .. } => {
+                self.rust_type(&DataType::Pointer(inside.to_owned()), place)
+            }
+            _ => self.rust_type(data_type, place),
+        }
+    }
+
+    fn output_type(&mut self, output: &DataType, place: &str) -> String {
+        if is_void(output) {
+            String::new()
+        } else {
+            format!(" -> {}", self.rust_type(output, place))
+        }
+    }
+
+    /// `Debug` can't be derived for anything that has a union inside of it
+    fn debuggable(&self, data_type: &DataType) -> bool {
+        match resolve_typedefs(self.symbols, data_type) {
+            DataType::Union { .. } => false,
+            DataType::Struct { properties, .. } => properties
+                .iter()
+                .all(|property| self.debuggable(&property.var_type)),
+            DataType::Array { inside, .. } => self.debuggable(&inside),
+            _ => true,
+        }
+    }
+
+    /// `#[repr(C)]` struct or union. Members moved by `_Alignas` get explicit padding in front
+    /// of them and the record is aligned like in C.
+    fn record(&mut self, name: &str, data_type: &DataType) {
+        let (properties, packing, union) = match data_type {
+            DataType::Struct {
+                properties,
+                packing,
+            } => (properties, *packing, false),
+            DataType::Union {
+                properties,
+                packing,
+            } => (properties, *packing, true),
+            _ => return,
+        };
+        let layout = self.engine.layout(data_type).ok();
+
+        let mut fields = String::new();
+        let mut offset: u64 = 0;
+        let mut natural_alignment = 1;
+        for (index, property) in properties.iter().enumerate() {
+            let place = format!("{name}_{}", property.var_name);
+            let rust_type = self.rust_type(&property.var_type, &place);
+            if let Some(layout) = &layout
+                && let Ok(member) = self.engine.layout(&property.var_type)
+            {
+                let alignment = match packing {
+                    Some(packing) => member.alignment.min(packing),
+                    None => member.alignment,
+                };
+                natural_alignment = natural_alignment.max(alignment);
+                if !union {
+                    let natural = offset.next_multiple_of(alignment);
+                    let wanted = layout.fields[index].offset;
+                    if wanted > natural {
+                        fields += &format!(
+                            "{INDENT}pub __padding_{index}: [u8; {}],\n",
+                            wanted - natural
+                        );
+                    }
+                    offset = wanted + member.size;
+                }
+            }
+            fields += &format!(
+                "{INDENT}pub {}: {rust_type},\n",
+                identifier(&property.var_name)
+            );
+        }
+
+        let mut representation = String::from("C");
+        match (packing, &layout) {
+            (Some(packing), _) => representation += &format!(", packed({packing})"),
+            (None, Some(layout)) if layout.alignment > natural_alignment => {
+                representation += &format!(", align({})", layout.alignment)
+            }
+            _ => {}
+        }
+        let derive = if union {
+            "Copy, Clone"
+        } else if self.debuggable(data_type) {
+            "Debug, Copy, Clone"
+        } else {
+            "Copy, Clone"
+        };
+        let keyword = if union { "union" } else { "struct" };
+        self.items.push(format!(
+            "#[repr({representation})]\n#[derive({derive})]\npub {keyword} {} {{\n{fields}}}\n",
+            identifier(name)
+        ));
+        self.records.push((name.to_owned(), data_type.to_owned()));
+    }
+
+    fn function(
+        &mut self,
+        name: &str,
+        properties: &[Property],
+        output: &DataType,
+        variadic: bool,
+    ) -> String {
+        let mut parameters = properties
+            .iter()
+            .enumerate()
+            .map(|(index, property)| {
+                let place = format!("{name}_{}", property.var_name);
+                let parameter = match property.var_name.as_str() {
+                    "" => format!("arg{index}"),
+                    parameter => identifier(parameter),
+                };
+                format!(
+                    "{parameter}: {}",
+                    self.parameter_type(&property.var_type, &place)
+                )
+            })
+            .collect::<Vec<_>>();
+        if variadic {
+            parameters.push("...".to_owned());
+        }
+        let output = self.output_type(output, name);
+        format!(
+            "{INDENT}pub fn {}({}){output};\n",
+            identifier(name),
+            parameters.join(", ")
+        )
+    }
+
+    /// Object-like macros from the source whose body is a string literal or an integer constant
+    /// expression, anything else can't be a Rust constant and is left out
+    fn constants(
+        &mut self,
+        preprocessor: &mut Preprocessor,
+        target: &Target,
+        filter: &Filter,
+    ) -> Vec<String> {
+        let mut macros = preprocessor
+            .macros
+            .values()
+            .filter(|definition| {
+                definition.kind == MacroKind::Object
+                    && definition.line > 0
+                    && definition.file != "<built-in>"
+                    && filter.allows(&definition.name)
+            })
+            .map(|definition| {
+                (
+                    definition.file.to_owned(),
+                    definition.line,
+                    definition.name.to_owned(),
+                    definition.body.to_owned(),
+                )
+            })
+            .collect::<Vec<_>>();
+        macros.sort_by(|left, right| (&left.0, left.1).cmp(&(&right.0, right.1)));
+
+        let mut output = Vec::new();
+        for (file, line, name, body) in macros {
+            let input = body.into_iter().map(PpToken::new).collect();
+            let Ok(expanded) = preprocessor.expand(input, false) else {
+                continue;
+            };
+            let mut tokens = expanded
+                .into_iter()
+                .map(|token| token.token)
+                .filter(|token| !is_white_space(token.kind))
+                .collect::<Vec<_>>();
+            if tokens.is_empty() {
+                continue;
+            }
+            tokens.push(Token::new(String::new(), TokenKind::EndOfFile, line));
+            let Ok(expressions) = parser::parse(tokens, file, target) else {
+                continue;
+            };
+            let [expression] = expressions.as_slice() else {
+                continue;
+            };
+            if let Expression::String(value, _) = expression {
+                let Ok(mut bytes) = string_bytes(value) else {
+                    continue;
+                };
+                bytes.push(0);
+                output.push(format!(
+                    "pub const {}: &[u8; {}] = b{};\n",
+                    identifier(&name),
+                    bytes.len(),
+                    string(&bytes)
+                ));
+                continue;
+            }
+            let Ok(constant) = Evaluator::new(self.symbols, target).evaluate(expression) else {
+                continue;
+            };
+            output.push(format!(
+                "pub const {}: {} = {};\n",
+                identifier(&name),
+                self.rust_type(&constant.data_type, &name),
+                constant.value
+            ));
+        }
+        output
+    }
+
+    /// Test module that checks the sizes, alignments and member offsets of every record
+    /// against the ones of the C compiler
+    fn layout_tests(&self) -> Result<String> {
+        if self.records.is_empty() {
+            return Ok(String::new());
+        }
+        let mut output = String::from("\n#[cfg(test)]\nmod layout_tests {\n");
+        output += &format!("{INDENT}use super::*;\n");
+        for (name, data_type) in &self.records {
+            let layout = self
+                .engine
+                .layout(data_type)
+                .with_context(|| format!("layout of '{name}'"))?;
+            let rust_name = identifier(name);
+            output += &format!("\n{INDENT}#[test]\n{INDENT}fn layout_{name}() {{\n");
+            output += &format!(
+                "{INDENT}{INDENT}assert_eq!(::core::mem::size_of::<{rust_name}>(), {});\n",
+                layout.size
+            );
+            output += &format!(
+                "{INDENT}{INDENT}assert_eq!(::core::mem::align_of::<{rust_name}>(), {});\n",
+                layout.alignment
+            );
+            if let DataType::Struct { .. } = data_type {
+                for field in &layout.fields {
+                    output += &format!(
+                        "{INDENT}{INDENT}assert_eq!(::core::mem::offset_of!({rust_name}, {}), {});\n",
+                        identifier(&field.name),
+                        field.offset
+                    );
+                }
+            }
+            output += &format!("{INDENT}}}\n");
+        }
+        output += "}\n";
+        Ok(output)
+    }
+}
@@ -0,0 +1,689 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use anyhow::{Context, Result, bail};
+
+use crate::ir::dominance::Dominance;
+use crate::ir::{
+    BinaryOperator, BlockId, Condition, Conversion, Declaration, Function, Instruction, Module,
+    Register, Terminator, Type, UnaryOperator, Value,
+};
+
+/// the stack grows down from here, globals come right after it
+const STACK_TOP: u64 = 65536;
+const PAGE_SIZE: u64 = 65536;
+
+/// Writes the module as a WebAssembly text module. Pointers are `i32` addresses in one linear
+/// memory that holds the stack, the globals and then the heap, and take 8 bytes in memory like
+/// in the IR. Functions that aren't `static` are exported along with the memory and
+/// `__heap_base`, called functions without a body are imported from `env`, and the extra
+/// arguments of variadic ones are passed as a pointer to 8 byte slots after the fixed ones.
+/// The control flow graph is turned back into blocks and loops following "Beyond Relooper"
+/// by Norman Ramsey.
+pub fn generate(module: &Module) -> Result<String> {
+    let mut addresses = HashMap::new();
+    let mut end = STACK_TOP;
+    for global in &module.globals {
+        end = end.next_multiple_of(global.alignment.max(1));
+        addresses.insert(global.name.to_owned(), end);
+        end += global.bytes.len() as u64;
+    }
+    let heap_base = end.next_multiple_of(16);
+
+    let mut output = String::new();
+    writeln!(output, "(module").unwrap();
+    for declaration in &module.declarations {
+        let mut parameters = declaration
+            .parameters
+            .iter()
+            .map(|parameter| value_type(*parameter))
+            .collect::<Vec<_>>();
+        if declaration.variadic {
+            parameters.push("i32");
+        }
+        writeln!(
+            output,
+            "  (import \"env\" \"{0}\" (func ${0}{1}{2}))",
+            declaration.name,
+            signature_part("param", &parameters),
+            signature_part(
+                "result",
+                &declaration
+                    .output
+                    .map(value_type)
+                    .into_iter()
+                    .collect::<Vec<_>>()
+            ),
+        )
+        .unwrap();
+    }
+    writeln!(
+        output,
+        "  (memory (export \"memory\") {})",
+        heap_base.div_ceil(PAGE_SIZE) + 1
+    )
+    .unwrap();
+    writeln!(
+        output,
+        "  (global $__stack_pointer (mut i32) (i32.const {STACK_TOP}))"
+    )
+    .unwrap();
+    writeln!(
+        output,
+        "  (global (export \"__heap_base\") i32 (i32.const {heap_base}))"
+    )
+    .unwrap();
+
+    for global in &module.globals {
+        let mut bytes = global.bytes.to_owned();
+        for (offset, name) in &global.pointers {
+            let address = addresses
+                .get(name)
+                .with_context(|| format!("'{}' points to the function '{name}'", global.name))?;
+            let offset = *offset as usize;
+            bytes[offset..offset + 8].copy_from_slice(&address.to_le_bytes());
+        }
+        if bytes.iter().all(|byte| *byte == 0) {
+            continue;
+        }
+        let escaped = bytes
+            .iter()
+            .map(|byte| format!("\\{byte:02x}"))
+            .collect::<String>();
+        writeln!(
+            output,
+            "  (data (i32.const {}) \"{escaped}\") ;; {}",
+            addresses[&global.name], global.name
+        )
+        .unwrap();
+    }
+
+    for function in &module.functions {
+        let mut generator = Generator::new(module, function, &addresses)?;
+        generator
+            .write()
+            .with_context(|| format!("compiling '{}' to WebAssembly", function.name))?;
+        output.push_str(&generator.output);
+    }
+    writeln!(output, ")").unwrap();
+    Ok(output)
+}
+
+/// `i1`, `i8` and `i16` are kept sign extended in `i32` locals, pointers are `i32`
+fn value_type(data_type: Type) -> &'static str {
+    match data_type {
+        Type::I64 => "i64",
+        _ => "i32",
+    }
+}
+
+fn signature_part(kind: &str, types: &[&str]) -> String {
+    if types.is_empty() {
+        String::new()
+    } else {
+        format!(" ({kind} {})", types.join(" "))
+    }
+}
+
+struct Generator<'a> {
+    module: &'a Module,
+    function: &'a Function,
+    addresses: &'a HashMap<String, u64>,
+    dominance: Dominance,
+    /// position of every block in reverse postorder
+    order: Vec<usize>,
+    /// blocks with more than one forward edge coming in, they follow a `block` that the edges
+    /// leave with `br`
+    merges: Vec<bool>,
+    /// blocks that a back edge goes to, they start a `loop`
+    headers: Vec<bool>,
+    slots: Vec<u64>,
+    /// where the extra arguments of variadic calls are written
+    arguments: u64,
+    frame_size: u64,
+    output: String,
+    level: usize,
+}
+
+impl<'a> Generator<'a> {
+    fn new(
+        module: &'a Module,
+        function: &'a Function,
+        addresses: &'a HashMap<String, u64>,
+    ) -> Result<Self> {
+        let dominance = Dominance::new(function);
+        let mut order = vec![usize::MAX; function.blocks.len()];
+        for (index, block) in dominance.order.iter().enumerate() {
+            order[block.0 as usize] = index;
+        }
+        let mut forward = vec![0; function.blocks.len()];
+        let mut headers = vec![false; function.blocks.len()];
+        for source in &dominance.order {
+            for target in function.block(*source).terminator.successors() {
+                if order[target.0 as usize] > order[source.0 as usize] {
+                    forward[target.0 as usize] += 1;
+                } else if dominance.dominates(target, *source) {
+                    headers[target.0 as usize] = true;
+                } else {
+                    bail!(
+                        "the control flow of '{}' can't be expressed with blocks and loops",
+                        function.name
+                    );
+                }
+            }
+        }
+
+        let mut size: u64 = 0;
+        let mut slots = Vec::new();
+        for slot in &function.slots {
+            size = size.next_multiple_of(slot.alignment.max(1));
+            slots.push(size);
+            size += slot.size;
+        }
+        let variadic_arguments = function
+            .blocks
+            .iter()
+            .flat_map(|block| &block.instructions)
+            .filter_map(|instruction| match instruction {
+                Instruction::Call {
+                    function,
+                    arguments,
+                    ..
+                } => {
+                    let declaration = declaration(module, function)?;
+                    declaration
+                        .variadic
+                        .then(|| arguments.len() - declaration.parameters.len())
+                }
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0) as u64;
+        size = size.next_multiple_of(8);
+        let arguments = size;
+        size += 8 * variadic_arguments;
+
+        Ok(Generator {
+            module,
+            function,
+            addresses,
+            dominance,
+            order,
+            merges: forward.into_iter().map(|count| count > 1).collect(),
+            headers,
+            slots,
+            arguments,
+            frame_size: size.next_multiple_of(16),
+            output: String::new(),
+            level: 1,
+        })
+    }
+
+    fn line(&mut self, line: impl AsRef<str>) {
+        writeln!(self.output, "{}{}", "  ".repeat(self.level), line.as_ref()).unwrap();
+    }
+
+    fn open(&mut self, line: impl AsRef<str>) {
+        self.line(line);
+        self.level += 1;
+    }
+
+    fn close(&mut self) {
+        self.level -= 1;
+        self.line(")");
+    }
+
+    fn write(&mut self) -> Result<()> {
+        let function = self.function;
+        let parameters = function
+            .parameters
+            .iter()
+            .map(|parameter| {
+                format!(
+                    " (param {parameter_name} {})",
+                    value_type(function.register_type(*parameter)),
+                    parameter_name = local(*parameter)
+                )
+            })
+            .collect::<String>();
+        let export = if function.exported {
+            format!(" (export \"{}\")", function.name)
+        } else {
+            String::new()
+        };
+        let result = function
+            .output
+            .map(|output| format!(" (result {})", value_type(output)))
+            .unwrap_or_default();
+        self.open(format!(
+            "(func ${}{export}{parameters}{result}",
+            function.name
+        ));
+
+        // registers that optimizations left unused don't get a local
+        let mut assigned = vec![false; function.registers.len()];
+        let mut phis = Vec::new();
+        for instruction in function.blocks.iter().flat_map(|block| &block.instructions) {
+            if let Some(destination) = instruction.destination() {
+                assigned[destination.0 as usize] = true;
+            }
+            if let Instruction::Phi { destination, .. } = instruction {
+                phis.push(*destination);
+            }
+        }
+        for (index, assigned) in assigned.into_iter().enumerate() {
+            let register = Register(index as u32);
+            if assigned && !function.parameters.contains(&register) {
+                let data_type = value_type(function.register_type(register));
+                self.line(format!("(local {} {data_type})", local(register)));
+            }
+        }
+        for phi in phis {
+            let data_type = value_type(function.register_type(phi));
+            self.line(format!("(local {} {data_type})", incoming(phi)));
+        }
+        if self.frame_size > 0 {
+            self.line("(local $frame i32)");
+            self.line(format!(
+                "(local.set $frame (i32.sub (global.get $__stack_pointer) (i32.const {})))",
+                self.frame_size
+            ));
+            self.line("(global.set $__stack_pointer (local.get $frame))");
+        }
+
+        self.tree(BlockId(0))?;
+        if function.output.is_some() {
+            // every path returned already
+            self.line("(unreachable)");
+        }
+        self.close();
+        Ok(())
+    }
+
+    /// Code of the block and of the blocks it dominates
+    fn tree(&mut self, block: BlockId) -> Result<()> {
+        let mut merges = self.dominance.children[block.0 as usize]
+            .iter()
+            .copied()
+            .filter(|child| self.merges[child.0 as usize])
+            .collect::<Vec<_>>();
+        // the merge block that comes last in the order is outermost, so it is written last
+        merges.sort_by_key(|child| std::cmp::Reverse(self.order[child.0 as usize]));
+        if self.headers[block.0 as usize] {
+            self.open(format!("(loop $loop_{block}"));
+            self.within(block, &merges)?;
+            self.close();
+            Ok(())
+        } else {
+            self.within(block, &merges)
+        }
+    }
+
+    /// Code of the block inside of `block`s that the merge blocks it dominates follow
+    fn within(&mut self, block: BlockId, merges: &[BlockId]) -> Result<()> {
+        if let Some((merge, rest)) = merges.split_first() {
+            self.open(format!("(block ${merge}"));
+            self.within(block, rest)?;
+            self.close();
+            return self.tree(*merge);
+        }
+
+        for instruction in &self.function.block(block).instructions {
+            self.instruction(instruction)?;
+        }
+        match &self.function.block(block).terminator {
+            Terminator::Jump(target) => self.branch(block, *target)?,
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let condition = self.value(*condition);
+                self.open(format!("(if {condition}"));
+                self.open("(then");
+                self.branch(block, *then)?;
+                self.close();
+                self.open("(else");
+                self.branch(block, *otherwise)?;
+                self.close();
+                self.close();
+            }
+            Terminator::Return(value) => {
+                let value = value.map(|value| format!(" {}", self.value(value)));
+                if self.frame_size > 0 {
+                    self.line(format!(
+                        "(global.set $__stack_pointer (i32.add (local.get $frame) (i32.const {})))",
+                        self.frame_size
+                    ));
+                }
+                self.line(format!("(return{})", value.unwrap_or_default()));
+            }
+            Terminator::Unreachable => self.line("(unreachable)"),
+        }
+        Ok(())
+    }
+
+    fn branch(&mut self, source: BlockId, target: BlockId) -> Result<()> {
+        for instruction in &self.function.block(target).instructions {
+            let Instruction::Phi {
+                destination,
+                incoming: values,
+            } = instruction
+            else {
+                break;
+            };
+            if let Some((_, value)) = values.iter().find(|(block, _)| *block == source) {
+                let value = self.value(*value);
+                self.line(format!("(local.set {} {value})", incoming(*destination)));
+            }
+        }
+        if self.order[target.0 as usize] <= self.order[source.0 as usize] {
+            self.line(format!("(br $loop_{target})"));
+            Ok(())
+        } else if self.merges[target.0 as usize] {
+            self.line(format!("(br ${target})"));
+            Ok(())
+        } else {
+            self.tree(target)
+        }
+    }
+
+    fn value(&self, value: Value) -> String {
+        match value {
+            Value::Register(register) => format!("(local.get {})", local(register)),
+            Value::Constant(constant, Type::I64) => format!("(i64.const {constant})"),
+            Value::Constant(constant, _) => format!("(i32.const {})", constant as i32),
+        }
+    }
+
+    /// The value zero extended instead of sign extended
+    fn unsigned(&self, value: Value) -> String {
+        let mask = match self.function.type_of(value) {
+            Type::I8 => 0xff,
+            Type::I16 => 0xffff,
+            _ => return self.value(value),
+        };
+        match value {
+            Value::Constant(constant, _) => format!("(i32.const {})", constant & mask),
+            value => format!("(i32.and {} (i32.const {mask}))", self.value(value)),
+        }
+    }
+
+    /// Sets the register, sign extending results of narrow types
+    fn set(&mut self, destination: Register, expression: String) {
+        let expression = match self.function.register_type(destination) {
+            Type::I8 => format!("(i32.extend8_s {expression})"),
+            Type::I16 => format!("(i32.extend16_s {expression})"),
+            _ => expression,
+        };
+        self.line(format!("(local.set {} {expression})", local(destination)));
+    }
+
+    fn instruction(&mut self, instruction: &Instruction) -> Result<()> {
+        match instruction {
+            Instruction::Phi { destination, .. } => {
+                self.set(
+                    *destination,
+                    format!("(local.get {})", incoming(*destination)),
+                );
+            }
+            Instruction::Copy { destination, value } => {
+                let value = self.value(*value);
+                self.set(*destination, value);
+            }
+            Instruction::Unary {
+                destination,
+                operator,
+                value,
+            } => {
+                let data_type = self.function.register_type(*destination);
+                let wasm = value_type(data_type);
+                let value = self.value(*value);
+                let expression = match (operator, data_type) {
+                    (UnaryOperator::Negate, _) => format!("({wasm}.sub ({wasm}.const 0) {value})"),
+                    (UnaryOperator::Not, Type::I1) => format!("(i32.xor {value} (i32.const 1))"),
+                    (UnaryOperator::Not, _) => format!("({wasm}.xor {value} ({wasm}.const -1))"),
+                };
+                self.set(*destination, expression);
+            }
+            Instruction::Binary {
+                destination,
+                operator,
+                left,
+                right,
+            } => {
+                let expression = self.binary(*destination, *operator, *left, *right);
+                self.set(*destination, expression);
+            }
+            Instruction::Compare {
+                destination,
+                condition,
+                left,
+                right,
+            } => {
+                let wasm = value_type(self.function.type_of(*left));
+                let (name, unsigned) = match condition {
+                    Condition::Equal => ("eq", false),
+                    Condition::NotEqual => ("ne", false),
+                    Condition::SignedLess => ("lt_s", false),
+                    Condition::SignedLessEqual => ("le_s", false),
+                    Condition::SignedGreater => ("gt_s", false),
+                    Condition::SignedGreaterEqual => ("ge_s", false),
+                    Condition::UnsignedLess => ("lt_u", true),
+                    Condition::UnsignedLessEqual => ("le_u", true),
+                    Condition::UnsignedGreater => ("gt_u", true),
+                    Condition::UnsignedGreaterEqual => ("ge_u", true),
+                };
+                let (left, right) = if unsigned {
+                    (self.unsigned(*left), self.unsigned(*right))
+                } else {
+                    (self.value(*left), self.value(*right))
+                };
+                self.set(*destination, format!("({wasm}.{name} {left} {right})"));
+            }
+            Instruction::Convert {
+                destination,
+                conversion,
+                value,
+            } => {
+                let from = self.function.type_of(*value);
+                let to = self.function.register_type(*destination);
+                let expression = match (conversion, from, to) {
+                    (_, Type::I64, Type::I64) => self.value(*value),
+                    (_, Type::I64, _) => format!("(i32.wrap_i64 {})", self.value(*value)),
+                    (Conversion::SignExtend, Type::I1, Type::I64) => {
+                        format!(
+                            "(i64.sub (i64.const 0) (i64.extend_i32_u {}))",
+                            self.value(*value)
+                        )
+                    }
+                    (Conversion::SignExtend, Type::I1, _) => {
+                        format!("(i32.sub (i32.const 0) {})", self.value(*value))
+                    }
+                    (Conversion::SignExtend, _, Type::I64) => {
+                        format!("(i64.extend_i32_s {})", self.value(*value))
+                    }
+                    (_, _, Type::I64) => format!("(i64.extend_i32_u {})", self.unsigned(*value)),
+                    (Conversion::ZeroExtend, _, _) => self.unsigned(*value),
+                    // narrowing sign extends again when setting the register
+                    _ => self.value(*value),
+                };
+                self.set(*destination, expression);
+            }
+            Instruction::Load {
+                destination,
+                address,
+            } => {
+                let operation = match self.function.register_type(*destination) {
+                    Type::I1 => "i32.load8_u",
+                    Type::I8 => "i32.load8_s",
+                    Type::I16 => "i32.load16_s",
+                    Type::I32 | Type::Pointer => "i32.load",
+                    Type::I64 => "i64.load",
+                };
+                let address = self.value(*address);
+                self.set(*destination, format!("({operation} {address})"));
+            }
+            Instruction::Store { address, value } => {
+                let address = self.value(*address);
+                let (operation, value) = match self.function.type_of(*value) {
+                    Type::I1 | Type::I8 => ("i32.store8", self.value(*value)),
+                    Type::I16 => ("i32.store16", self.value(*value)),
+                    Type::I32 => ("i32.store", self.value(*value)),
+                    Type::I64 => ("i64.store", self.value(*value)),
+                    Type::Pointer => (
+                        "i64.store",
+                        format!("(i64.extend_i32_u {})", self.value(*value)),
+                    ),
+                };
+                self.line(format!("({operation} {address} {value})"));
+            }
+            Instruction::StackAddress { destination, slot } => {
+                let offset = self.slots[slot.0 as usize];
+                self.set(
+                    *destination,
+                    format!("(i32.add (local.get $frame) (i32.const {offset}))"),
+                );
+            }
+            Instruction::GlobalAddress { destination, name } => {
+                let address = self.addresses.get(name).with_context(|| {
+                    format!("functions can't be used as pointers, found '{name}'")
+                })?;
+                self.set(*destination, format!("(i32.const {address})"));
+            }
+            Instruction::Call {
+                destination,
+                function,
+                arguments,
+            } => self.call(*destination, function, arguments)?,
+            Instruction::CopyMemory {
+                destination,
+                source,
+                size,
+            } => {
+                let (destination, source) = (self.value(*destination), self.value(*source));
+                self.line(format!(
+                    "(memory.copy {destination} {source} (i32.const {size}))"
+                ));
+            }
+            Instruction::ZeroMemory { destination, size } => {
+                let destination = self.value(*destination);
+                self.line(format!(
+                    "(memory.fill {destination} (i32.const 0) (i32.const {size}))"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn binary(
+        &self,
+        destination: Register,
+        operator: BinaryOperator,
+        left: Value,
+        right: Value,
+    ) -> String {
+        let data_type = self.function.register_type(destination);
+        let wasm = value_type(data_type);
+        if data_type == Type::Pointer {
+            // the offset of `ptr + i64`
+            let right = match self.function.type_of(right) {
+                Type::I64 => format!("(i32.wrap_i64 {})", self.value(right)),
+                _ => self.value(right),
+            };
+            return format!("(i32.add {} {right})", self.value(left));
+        }
+        let (name, unsigned) = match operator {
+            BinaryOperator::Add => ("add", false),
+            BinaryOperator::Subtract => ("sub", false),
+            BinaryOperator::Multiply => ("mul", false),
+            BinaryOperator::SignedDivide => ("div_s", false),
+            BinaryOperator::UnsignedDivide => ("div_u", true),
+            BinaryOperator::SignedRemainder => ("rem_s", false),
+            BinaryOperator::UnsignedRemainder => ("rem_u", true),
+            BinaryOperator::And => ("and", false),
+            BinaryOperator::Or => ("or", false),
+            BinaryOperator::Xor => ("xor", false),
+            BinaryOperator::ShiftLeft => ("shl", false),
+            BinaryOperator::LogicalShiftRight => ("shr_u", true),
+            BinaryOperator::ArithmeticShiftRight => ("shr_s", false),
+        };
+        let (left, right) = if unsigned {
+            (self.unsigned(left), self.unsigned(right))
+        } else {
+            (self.value(left), self.value(right))
+        };
+        format!("({wasm}.{name} {left} {right})")
+    }
+
+    fn call(
+        &mut self,
+        destination: Option<Register>,
+        function: &str,
+        arguments: &[Value],
+    ) -> Result<()> {
+        let fixed = match self.module.function(function) {
+            Some(callee) => callee.parameters.len(),
+            None => declaration(self.module, function)
+                .with_context(|| format!("call of an unknown function '{function}'"))?
+                .parameters
+                .len(),
+        };
+        let variadic =
+            declaration(self.module, function).is_some_and(|declaration| declaration.variadic);
+        let mut values = arguments[..fixed]
+            .iter()
+            .map(|argument| self.value(*argument))
+            .collect::<Vec<_>>();
+        if variadic {
+            for (index, argument) in arguments[fixed..].iter().enumerate() {
+                let value = match self.function.type_of(*argument) {
+                    Type::I64 => self.value(*argument),
+                    Type::Pointer => format!("(i64.extend_i32_u {})", self.value(*argument)),
+                    _ => format!("(i64.extend_i32_s {})", self.value(*argument)),
+                };
+                self.line(format!(
+                    "(i64.store (i32.add (local.get $frame) (i32.const {})) {value})",
+                    self.arguments + 8 * index as u64
+                ));
+            }
+            values.push(format!(
+                "(i32.add (local.get $frame) (i32.const {}))",
+                self.arguments
+            ));
+        }
+        let call = format!("(call ${function} {})", values.join(" "));
+        match destination {
+            Some(destination) => self.set(destination, call),
+            None => {
+                let output = match self.module.function(function) {
+                    Some(callee) => callee.output,
+                    None => declaration(self.module, function)
+                        .and_then(|declaration| declaration.output),
+                };
+                if output.is_some() {
+                    self.line(format!("(drop {call})"));
+                } else {
+                    self.line(call);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn declaration<'a>(module: &'a Module, name: &str) -> Option<&'a Declaration> {
+    module
+        .declarations
+        .iter()
+        .find(|declaration| declaration.name == name)
+}
+
+fn local(register: Register) -> String {
+    format!("$r{}", register.0)
+}
+
+/// Local that the predecessors of the block of a phi write its value to
+fn incoming(register: Register) -> String {
+    format!("$phi{}", register.0)
+}
@@ -0,0 +1,545 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::ir::{
+    BinaryOperator, BlockId, Condition, Conversion, Function, Global, Instruction, Module,
+    Register, Terminator, Type, UnaryOperator, Value,
+};
+
+/// registers of the first six integer arguments in the SysV ABI
+const ARGUMENT_REGISTERS: [&str; 6] = ["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
+
+/// Writes the module as x86-64 assembly for the GNU assembler, in AT&T syntax, following the
+/// SysV calling convention for integers and pointers. Every register of the IR lives in a stack
+/// slot of its own and instructions go through `%rax` and `%rcx`. Structs are passed through
+/// pointers the way the IR does it, which only matches functions compiled by RIP.
+pub fn generate(module: &Module) -> String {
+    let mut output = String::new();
+    let defined = |name: &str| module.function(name).is_some() || module.global(name).is_some();
+
+    for global in &module.globals {
+        global_data(&mut output, global);
+    }
+    if !module.functions.is_empty() {
+        writeln!(output, "\t.text").unwrap();
+    }
+    let variadic = |name: &str| {
+        module
+            .declarations
+            .iter()
+            .any(|declaration| declaration.name == name && declaration.variadic)
+    };
+    for function in &module.functions {
+        let mut generator = Generator {
+            output: &mut output,
+            function,
+            frame: Frame::new(function),
+            defined: &defined,
+            variadic: &variadic,
+        };
+        generator.write();
+    }
+    writeln!(output, "\t.section .note.GNU-stack,\"\",@progbits").unwrap();
+    output
+}
+
+fn global_data(output: &mut String, global: &Global) {
+    let zeroed = global.pointers.is_empty() && global.bytes.iter().all(|byte| *byte == 0);
+    let section = if global.constant {
+        ".section .rodata"
+    } else if zeroed {
+        ".bss"
+    } else {
+        ".data"
+    };
+    writeln!(output, "\t{section}").unwrap();
+    if global.exported {
+        writeln!(output, "\t.globl {}", global.name).unwrap();
+    }
+    writeln!(output, "\t.p2align {}", global.alignment.trailing_zeros()).unwrap();
+    writeln!(output, "\t.type {}, @object", global.name).unwrap();
+    writeln!(output, "\t.size {}, {}", global.name, global.bytes.len()).unwrap();
+    writeln!(output, "{}:", global.name).unwrap();
+    if zeroed {
+        writeln!(output, "\t.zero {}", global.bytes.len().max(1)).unwrap();
+        return;
+    }
+    let mut offset = 0;
+    let mut pointers = global.pointers.to_owned();
+    pointers.sort();
+    for (pointer, name) in pointers {
+        bytes(output, &global.bytes[offset..pointer as usize]);
+        writeln!(output, "\t.quad {name}").unwrap();
+        offset = pointer as usize + 8;
+    }
+    bytes(output, &global.bytes[offset..]);
+}
+
+fn bytes(output: &mut String, bytes: &[u8]) {
+    for line in bytes.chunks(16) {
+        let line = line
+            .iter()
+            .map(|byte| byte.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(output, "\t.byte {line}").unwrap();
+    }
+}
+
+/// Where everything of a function is on the stack, as offsets from `%rbp`
+struct Frame {
+    registers: Vec<i64>,
+    /// where the values of the phis are written by the predecessors of their block, so that
+    /// phis that read each other see the values from before the jump
+    phis: HashMap<Register, i64>,
+    slots: Vec<i64>,
+    size: u64,
+}
+
+impl Frame {
+    fn new(function: &Function) -> Self {
+        let mut size = 0;
+        let mut allocate = |bytes: u64, alignment: u64| {
+            size = (size + bytes).next_multiple_of(alignment.max(1));
+            -(size as i64)
+        };
+        let registers = function.registers.iter().map(|_| allocate(8, 8)).collect();
+        let mut phis = HashMap::new();
+        for block in &function.blocks {
+            for instruction in &block.instructions {
+                if let Instruction::Phi { destination, .. } = instruction {
+                    phis.insert(*destination, allocate(8, 8));
+                }
+            }
+        }
+        let slots = function
+            .slots
+            .iter()
+            .map(|slot| allocate(slot.size, slot.alignment))
+            .collect();
+        Frame {
+            registers,
+            phis,
+            slots,
+            size: size.next_multiple_of(16),
+        }
+    }
+
+    fn register(&self, register: Register) -> i64 {
+        self.registers[register.0 as usize]
+    }
+}
+
+struct Generator<'a> {
+    output: &'a mut String,
+    function: &'a Function,
+    frame: Frame,
+    /// whether a symbol is in the module or has to go through the PLT and GOT
+    defined: &'a dyn Fn(&str) -> bool,
+    variadic: &'a dyn Fn(&str) -> bool,
+}
+
+/// Name of the part of a general purpose register used for a type
+fn register_name(register: &str, data_type: Type) -> String {
+    let numbered =
+        register.starts_with('r') && register[1..].starts_with(|c: char| c.is_ascii_digit());
+    if numbered {
+        return match data_type {
+            Type::I1 | Type::I8 => format!("{register}b"),
+            Type::I16 => format!("{register}w"),
+            Type::I32 => format!("{register}d"),
+            Type::I64 | Type::Pointer => register.to_owned(),
+        };
+    }
+    let base = &register[1..];
+    match data_type {
+        Type::I1 | Type::I8 => match base {
+            "ax" | "bx" | "cx" | "dx" => format!("{}l", &base[..1]),
+            _ => format!("{base}l"),
+        },
+        Type::I16 => base.to_owned(),
+        Type::I32 => format!("e{base}"),
+        Type::I64 | Type::Pointer => register.to_owned(),
+    }
+}
+
+fn suffix(data_type: Type) -> char {
+    match data_type {
+        Type::I1 | Type::I8 => 'b',
+        Type::I16 => 'w',
+        Type::I32 => 'l',
+        Type::I64 | Type::Pointer => 'q',
+    }
+}
+
+fn condition_code(condition: Condition) -> &'static str {
+    match condition {
+        Condition::Equal => "e",
+        Condition::NotEqual => "ne",
+        Condition::SignedLess => "l",
+        Condition::SignedLessEqual => "le",
+        Condition::SignedGreater => "g",
+        Condition::SignedGreaterEqual => "ge",
+        Condition::UnsignedLess => "b",
+        Condition::UnsignedLessEqual => "be",
+        Condition::UnsignedGreater => "a",
+        Condition::UnsignedGreaterEqual => "ae",
+    }
+}
+
+impl Generator<'_> {
+    fn emit(&mut self, line: impl AsRef<str>) {
+        writeln!(self.output, "\t{}", line.as_ref()).unwrap();
+    }
+
+    fn label(&self, block: BlockId) -> String {
+        format!(".L{}.{block}", self.function.name)
+    }
+
+    /// Loads the value into the 64 bit register, sign or zero extending it
+    fn load(&mut self, value: Value, register: &str, signed: bool) {
+        match value {
+            Value::Constant(constant, data_type) => {
+                let constant = if signed || data_type.bits() == 64 {
+                    constant
+                } else {
+                    ((constant as u64) & (u64::MAX >> (64 - data_type.bits()))) as i64
+                };
+                if i32::try_from(constant).is_ok() {
+                    self.emit(format!("movq ${constant}, %{register}"));
+                } else {
+                    self.emit(format!("movabsq ${constant}, %{register}"));
+                }
+            }
+            Value::Register(source) => {
+                let offset = self.frame.register(source);
+                self.load_memory(
+                    &format!("{offset}(%rbp)"),
+                    self.function.register_type(source),
+                    register,
+                    signed,
+                );
+            }
+        }
+    }
+
+    /// Loads a value of the type from memory into the 64 bit register
+    fn load_memory(&mut self, address: &str, data_type: Type, register: &str, signed: bool) {
+        let instruction = match (data_type, signed) {
+            (Type::I64 | Type::Pointer, _) => format!("movq {address}, %{register}"),
+            (Type::I32, true) => format!("movslq {address}, %{register}"),
+            // writing the 32 bit register clears the upper half
+            (Type::I32, false) => {
+                format!("movl {address}, %{}", register_name(register, Type::I32))
+            }
+            (Type::I1, _) => format!("movzbq {address}, %{register}"),
+            (data_type, signed) => format!(
+                "mov{}{}q {address}, %{register}",
+                if signed { 's' } else { 'z' },
+                suffix(data_type)
+            ),
+        };
+        self.emit(instruction);
+    }
+
+    /// Stores the low part of the 64 bit register in the home of the destination
+    fn store(&mut self, register: &str, destination: Register) {
+        let data_type = self.function.register_type(destination);
+        let offset = self.frame.register(destination);
+        self.store_memory(register, data_type, &format!("{offset}(%rbp)"));
+    }
+
+    fn store_memory(&mut self, register: &str, data_type: Type, address: &str) {
+        self.emit(format!(
+            "mov{} %{}, {address}",
+            suffix(data_type),
+            register_name(register, data_type)
+        ));
+    }
+
+    fn write(&mut self) {
+        let name = self.function.name.to_owned();
+        writeln!(self.output).unwrap();
+        if self.function.exported {
+            self.emit(format!(".globl {name}"));
+        }
+        self.emit(format!(".type {name}, @function"));
+        writeln!(self.output, "{name}:").unwrap();
+        self.emit("pushq %rbp");
+        self.emit("movq %rsp, %rbp");
+        if self.frame.size > 0 {
+            self.emit(format!("subq ${}, %rsp", self.frame.size));
+        }
+        for (index, parameter) in self.function.parameters.iter().copied().enumerate() {
+            match ARGUMENT_REGISTERS.get(index) {
+                Some(register) => self.store(register, parameter),
+                None => {
+                    // above the saved %rbp and the return address
+                    let offset = 16 + 8 * (index - ARGUMENT_REGISTERS.len());
+                    self.emit(format!("movq {offset}(%rbp), %rax"));
+                    self.store("rax", parameter);
+                }
+            }
+        }
+
+        for id in self.function.block_ids() {
+            if id.0 != 0 {
+                writeln!(self.output, "{}:", self.label(id)).unwrap();
+            }
+            let block = self.function.block(id);
+            for instruction in &block.instructions {
+                self.instruction(instruction);
+            }
+            self.phi_values(id);
+            self.terminator(id, &block.terminator);
+        }
+        self.emit(format!(".size {name}, .-{name}"));
+    }
+
+    /// Writes the values that the successors of the block get in their phis
+    fn phi_values(&mut self, id: BlockId) {
+        for successor in self.function.block(id).terminator.successors() {
+            for instruction in &self.function.block(successor).instructions {
+                let Instruction::Phi {
+                    destination,
+                    incoming,
+                } = instruction
+                else {
+                    break;
+                };
+                if let Some((_, value)) = incoming.iter().find(|(block, _)| *block == id) {
+                    self.load(*value, "rax", false);
+                    let offset = self.frame.phis[destination];
+                    self.emit(format!("movq %rax, {offset}(%rbp)"));
+                }
+            }
+        }
+    }
+
+    fn terminator(&mut self, id: BlockId, terminator: &Terminator) {
+        let next = BlockId(id.0 + 1);
+        match terminator {
+            Terminator::Jump(target) => {
+                if *target != next {
+                    self.emit(format!("jmp {}", self.label(*target)));
+                }
+            }
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => {
+                self.load(*condition, "rax", false);
+                self.emit("testb %al, %al");
+                self.emit(format!("jne {}", self.label(*then)));
+                if *otherwise != next {
+                    self.emit(format!("jmp {}", self.label(*otherwise)));
+                }
+            }
+            Terminator::Return(value) => {
+                if let Some(value) = value {
+                    self.load(*value, "rax", true);
+                }
+                self.emit("leave");
+                self.emit("ret");
+            }
+            Terminator::Unreachable => self.emit("ud2"),
+        }
+    }
+
+    fn instruction(&mut self, instruction: &Instruction) {
+        match instruction {
+            Instruction::Phi { destination, .. } => {
+                let offset = self.frame.phis[destination];
+                self.emit(format!("movq {offset}(%rbp), %rax"));
+                self.store("rax", *destination);
+            }
+            Instruction::Copy { destination, value } => {
+                self.load(*value, "rax", true);
+                self.store("rax", *destination);
+            }
+            Instruction::Unary {
+                destination,
+                operator,
+                value,
+            } => {
+                self.load(*value, "rax", true);
+                match (operator, self.function.register_type(*destination)) {
+                    (UnaryOperator::Not, Type::I1) => self.emit("xorq $1, %rax"),
+                    (UnaryOperator::Not, _) => self.emit("notq %rax"),
+                    (UnaryOperator::Negate, _) => self.emit("negq %rax"),
+                }
+                self.store("rax", *destination);
+            }
+            Instruction::Binary {
+                destination,
+                operator,
+                left,
+                right,
+            } => self.binary(*destination, *operator, *left, *right),
+            Instruction::Compare {
+                destination,
+                condition,
+                left,
+                right,
+            } => {
+                let signed = matches!(
+                    condition,
+                    Condition::SignedLess
+                        | Condition::SignedLessEqual
+                        | Condition::SignedGreater
+                        | Condition::SignedGreaterEqual
+                );
+                self.load(*left, "rax", signed);
+                self.load(*right, "rcx", signed);
+                self.emit("cmpq %rcx, %rax");
+                self.emit(format!("set{} %al", condition_code(*condition)));
+                self.store("rax", *destination);
+            }
+            Instruction::Convert {
+                destination,
+                conversion,
+                value,
+            } => {
+                let signed = *conversion == Conversion::SignExtend;
+                self.load(*value, "rax", signed);
+                self.store("rax", *destination);
+            }
+            Instruction::Load {
+                destination,
+                address,
+            } => {
+                self.load(*address, "rax", false);
+                let data_type = self.function.register_type(*destination);
+                self.load_memory("(%rax)", data_type, "rcx", false);
+                self.store("rcx", *destination);
+            }
+            Instruction::Store { address, value } => {
+                self.load(*address, "rax", false);
+                self.load(*value, "rcx", false);
+                let data_type = self.function.type_of(*value);
+                self.store_memory("rcx", data_type, "(%rax)");
+            }
+            Instruction::StackAddress { destination, slot } => {
+                let offset = self.frame.slots[slot.0 as usize];
+                self.emit(format!("leaq {offset}(%rbp), %rax"));
+                self.store("rax", *destination);
+            }
+            Instruction::GlobalAddress { destination, name } => {
+                if (self.defined)(name) {
+                    self.emit(format!("leaq {name}(%rip), %rax"));
+                } else {
+                    self.emit(format!("movq {name}@GOTPCREL(%rip), %rax"));
+                }
+                self.store("rax", *destination);
+            }
+            Instruction::Call {
+                destination,
+                function,
+                arguments,
+            } => self.call(*destination, function, arguments),
+            Instruction::CopyMemory {
+                destination,
+                source,
+                size,
+            } => {
+                self.load(*destination, "rdi", false);
+                self.load(*source, "rsi", false);
+                self.emit(format!("movq ${size}, %rcx"));
+                self.emit("rep movsb");
+            }
+            Instruction::ZeroMemory { destination, size } => {
+                self.load(*destination, "rdi", false);
+                self.emit("xorl %eax, %eax");
+                self.emit(format!("movq ${size}, %rcx"));
+                self.emit("rep stosb");
+            }
+        }
+    }
+
+    /// The operation is done on 64 bit registers holding the operands extended the way the
+    /// operator needs, the low part of the result is the result for the type
+    fn binary(
+        &mut self,
+        destination: Register,
+        operator: BinaryOperator,
+        left: Value,
+        right: Value,
+    ) {
+        let signed = !matches!(
+            operator,
+            BinaryOperator::UnsignedDivide
+                | BinaryOperator::UnsignedRemainder
+                | BinaryOperator::LogicalShiftRight
+        );
+        self.load(left, "rax", signed);
+        self.load(right, "rcx", signed);
+        let data_type = self.function.register_type(destination);
+        match operator {
+            BinaryOperator::Add => self.emit("addq %rcx, %rax"),
+            BinaryOperator::Subtract => self.emit("subq %rcx, %rax"),
+            BinaryOperator::Multiply => self.emit("imulq %rcx, %rax"),
+            BinaryOperator::And => self.emit("andq %rcx, %rax"),
+            BinaryOperator::Or => self.emit("orq %rcx, %rax"),
+            BinaryOperator::Xor => self.emit("xorq %rcx, %rax"),
+            BinaryOperator::ShiftLeft => self.emit("shlq %cl, %rax"),
+            BinaryOperator::LogicalShiftRight => self.emit("shrq %cl, %rax"),
+            BinaryOperator::ArithmeticShiftRight => self.emit("sarq %cl, %rax"),
+            BinaryOperator::SignedDivide | BinaryOperator::SignedRemainder => {
+                // 32 bit division so that INT_MIN / -1 traps like it does in C
+                if data_type == Type::I64 {
+                    self.emit("cqto");
+                    self.emit("idivq %rcx");
+                } else {
+                    self.emit("cltd");
+                    self.emit("idivl %ecx");
+                    self.emit("movslq %eax, %rax");
+                    self.emit("movslq %edx, %rdx");
+                }
+                if operator == BinaryOperator::SignedRemainder {
+                    self.emit("movq %rdx, %rax");
+                }
+            }
+            BinaryOperator::UnsignedDivide | BinaryOperator::UnsignedRemainder => {
+                self.emit("xorl %edx, %edx");
+                self.emit("divq %rcx");
+                if operator == BinaryOperator::UnsignedRemainder {
+                    self.emit("movq %rdx, %rax");
+                }
+            }
+        }
+        self.store("rax", destination);
+    }
+
+    fn call(&mut self, destination: Option<Register>, function: &str, arguments: &[Value]) {
+        let stack_arguments = arguments.len().saturating_sub(ARGUMENT_REGISTERS.len());
+        // %rsp has to be a multiple of 16 at the call
+        let padding = if stack_arguments % 2 == 1 { 8 } else { 0 };
+        if padding != 0 {
+            self.emit("subq $8, %rsp");
+        }
+        for argument in arguments.iter().skip(ARGUMENT_REGISTERS.len()).rev() {
+            self.load(*argument, "rax", true);
+            self.emit("pushq %rax");
+        }
+        for (argument, register) in arguments.iter().zip(ARGUMENT_REGISTERS) {
+            self.load(*argument, register, true);
+        }
+        if (self.variadic)(function) {
+            // no arguments in vector registers
+            self.emit("xorl %eax, %eax");
+        }
+        if (self.defined)(function) {
+            self.emit(format!("call {function}"));
+        } else {
+            self.emit(format!("call {function}@PLT"));
+        }
+        let popped = 8 * stack_arguments as i64 + padding;
+        if popped != 0 {
+            self.emit(format!("addq ${popped}, %rsp"));
+        }
+        if let Some(destination) = destination {
+            self.store("rax", destination);
+        }
+    }
+}
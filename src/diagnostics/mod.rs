@@ -0,0 +1,138 @@
+use std::fmt::Display;
+
+use log::{error, info, warn};
+
+use crate::parser::expression::DebugData;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Note,
+    Warning,
+    Error,
+}
+
+/// Problem found in the code by one of the passes that run after parsing. Unlike errors returned
+/// from the parser, these don't stop anything, so all of them can be reported at once.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub debug_data: DebugData,
+    /// text on the line that the diagnostic is about, like the name of a variable. The passes
+    /// only know lines, tools that know columns mark where it is first found on the line.
+    pub span: Option<String>,
+    /// how the code could be changed to fix it
+    pub suggestion: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(message: String, debug_data: &DebugData) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+            debug_data: debug_data.to_owned(),
+            span: None,
+            suggestion: None,
+        }
+    }
+    pub fn warning(message: String, debug_data: &DebugData) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message,
+            debug_data: debug_data.to_owned(),
+            span: None,
+            suggestion: None,
+        }
+    }
+    pub fn note(message: String, debug_data: &DebugData) -> Self {
+        Self {
+            severity: Severity::Note,
+            message,
+            debug_data: debug_data.to_owned(),
+            span: None,
+            suggestion: None,
+        }
+    }
+
+    pub fn with_span(mut self, span: &str) -> Self {
+        self.span = Some(span.to_owned());
+        self
+    }
+    pub fn with_suggestion(mut self, suggestion: String) -> Self {
+        self.suggestion = Some(suggestion);
+        self
+    }
+
+    /// Macros that the code came from, the innermost one first, with the line each was used on
+    pub fn expansion_notes(&self) -> Vec<(u16, String)> {
+        let mut notes = Vec::new();
+        let mut expansion = self.debug_data.expansion.as_deref();
+        while let Some(current) = expansion {
+            notes.push((
+                current.invocation_line,
+                format!(
+                    "in expansion of macro '{}' defined at {}:{}",
+                    current.macro_name, current.definition_file, current.definition_line
+                ),
+            ));
+            expansion = current.parent.as_deref();
+        }
+        notes
+    }
+}
+
+/// Context of an error that stopped the lexer or the parser, it prints like the message alone,
+/// but tools can downcast to it to find the line
+#[derive(Debug)]
+pub struct ErrorLine {
+    pub line: u16,
+    pub message: String,
+}
+
+impl Display for ErrorLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Note => write!(f, "note"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// `file:line: severity: message`, the suggestion follows on its own line as `help` and the
+/// macros that the code came from as `note`s
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let place = format!("{}:{}", self.debug_data.file, self.debug_data.line);
+        write!(f, "{place}: {}: {}", self.severity, self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "\n{place}: help: {suggestion}")?;
+        }
+        for (line, note) in self.expansion_notes() {
+            write!(f, "\n{}:{line}: note: {note}", self.debug_data.file)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn log_diagnostics(diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        match diagnostic.severity {
+            Severity::Note => info!("{diagnostic}"),
+            Severity::Warning => warn!("{diagnostic}"),
+            Severity::Error => error!("{diagnostic}"),
+        }
+    }
+}
+
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error)
+}
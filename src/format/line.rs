@@ -0,0 +1,403 @@
+use std::{collections::HashSet, ops::Range};
+
+use crate::{
+    format::{Config, Word},
+    lexer::token::TokenKind,
+    parser::types::PRIMITIVE_TYPE_NAMES,
+};
+
+/// What a token does in its statement, decides the spaces around it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Plain,
+    /// `(`, `[` or `{` of an initializer list, lines broken inside continue after it
+    Open,
+    Close,
+    /// `)` of a cast
+    CastEnd,
+    /// unary operator before its operand
+    Prefix,
+    /// `++` or `--` after its operand
+    Postfix,
+    Binary,
+    /// `*` of a pointer declaration
+    Pointer,
+}
+
+/// Place after a token where its line can be broken
+struct Candidate {
+    after: usize,
+    /// length in bytes of the line up to the break
+    length: usize,
+    /// columns of the line up to the break
+    width: usize,
+    /// lower is preferred, breaks in outer parentheses come before breaks in inner ones
+    score: usize,
+    /// column where the next line starts
+    continuation: usize,
+    open: Vec<usize>,
+}
+
+/// Tokens of one statement, or of the part of one up to an opening brace
+pub(super) struct Unit<'a> {
+    words: Vec<&'a Word>,
+    roles: Vec<Role>,
+    /// `unsigned int` or `Point`, also identifiers after `struct`, `union` and `enum`
+    types: Vec<bool>,
+}
+
+impl<'a> Unit<'a> {
+    pub(super) fn new(words: Vec<&'a Word>, typedefs: &HashSet<&str>) -> Self {
+        let mut types = Vec::with_capacity(words.len());
+        for (i, word) in words.iter().enumerate() {
+            let tagged = i > 0
+                && matches!(
+                    words[i - 1].kind,
+                    TokenKind::Struct | TokenKind::Union | TokenKind::Enum
+                );
+            types.push(
+                word.kind == TokenKind::Identifier
+                    && (tagged
+                        || PRIMITIVE_TYPE_NAMES.contains(&word.text.as_str())
+                        || ["signed", "unsigned"].contains(&word.text.as_str())
+                        || typedefs.contains(word.text.as_str())),
+            );
+        }
+        let mut unit = Self {
+            roles: vec![Role::Plain; words.len()],
+            words,
+            types,
+        };
+        unit.assign_roles();
+        unit
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub(super) fn has_comments(&self) -> bool {
+        self.words
+            .iter()
+            .any(|word| word.kind == TokenKind::Comment)
+    }
+
+    fn assign_roles(&mut self) {
+        let last = self.words.len() - 1;
+        let mut previous: Option<usize> = None;
+        for i in 0..self.words.len() {
+            let kind = self.words[i].kind;
+            if kind == TokenKind::Comment {
+                continue;
+            }
+            let operand_before = previous.is_some_and(|previous| self.is_operand(previous));
+            let role = match kind {
+                TokenKind::OpenParen | TokenKind::OpenBracket => Role::Open,
+                // braces of bodies end or start their unit, the others are initializer lists
+                TokenKind::OpenCurly if i != last => Role::Open,
+                TokenKind::CloseParen | TokenKind::CloseBracket => Role::Close,
+                TokenKind::CloseCurly if i != 0 => Role::Close,
+                TokenKind::PlusPlus | TokenKind::MinusMinus if operand_before => Role::Postfix,
+                TokenKind::PlusPlus
+                | TokenKind::MinusMinus
+                | TokenKind::Not
+                | TokenKind::BitwiseNot => Role::Prefix,
+                TokenKind::Star
+                    if previous.is_some_and(|previous| {
+                        self.types[previous]
+                            || self.words[previous].kind == TokenKind::Constant
+                            || self.roles[previous] == Role::Pointer
+                    }) =>
+                {
+                    Role::Pointer
+                }
+                TokenKind::Star | TokenKind::Minus | TokenKind::Plus | TokenKind::Reference => {
+                    if operand_before {
+                        Role::Binary
+                    } else {
+                        Role::Prefix
+                    }
+                }
+                TokenKind::Slash
+                | TokenKind::Percent
+                | TokenKind::PlusEquals
+                | TokenKind::MinusEquals
+                | TokenKind::StarEquals
+                | TokenKind::SlashEquals
+                | TokenKind::PercentEquals
+                | TokenKind::BitwiseShiftLeftEquals
+                | TokenKind::BitwiseShiftRightEquals
+                | TokenKind::BitwiseAndEquals
+                | TokenKind::BitwiseOrEquals
+                | TokenKind::BitwiseXorEquals
+                | TokenKind::Equals
+                | TokenKind::NotEquals
+                | TokenKind::Less
+                | TokenKind::LessEquals
+                | TokenKind::Greater
+                | TokenKind::GreaterEquals
+                | TokenKind::And
+                | TokenKind::Or
+                | TokenKind::BitwiseShiftLeft
+                | TokenKind::BitwiseShiftRight
+                | TokenKind::BitwiseOr
+                | TokenKind::BitwiseXor
+                | TokenKind::Assignment
+                | TokenKind::Question
+                | TokenKind::Colon => Role::Binary,
+                _ => Role::Plain,
+            };
+            if self.roles[i] != Role::CastEnd {
+                self.roles[i] = role;
+            }
+            if kind == TokenKind::OpenParen
+                && !previous.is_some_and(|previous| self.is_call(previous))
+                && let Some(end) = self.cast_end(i)
+            {
+                self.roles[end] = Role::CastEnd;
+            }
+            previous = Some(i);
+        }
+    }
+
+    /// An operator after the word would be binary
+    fn is_operand(&self, i: usize) -> bool {
+        matches!(self.roles[i], Role::Close | Role::Postfix)
+            || (!self.types[i]
+                && matches!(
+                    self.words[i].kind,
+                    TokenKind::Identifier
+                        | TokenKind::Number
+                        | TokenKind::String
+                        | TokenKind::Character
+                        | TokenKind::True
+                        | TokenKind::False
+                ))
+    }
+
+    /// A `(` after the word starts arguments or parameters
+    fn is_call(&self, i: usize) -> bool {
+        self.roles[i] == Role::Close
+            || (self.words[i].kind == TokenKind::Identifier && !self.types[i])
+    }
+
+    /// `)` of the parentheses opened at `i` when only a type is between them
+    fn cast_end(&self, i: usize) -> Option<usize> {
+        let type_word = |j: usize| {
+            self.types[j]
+                || matches!(
+                    self.words[j].kind,
+                    TokenKind::Constant | TokenKind::Struct | TokenKind::Union | TokenKind::Enum
+                )
+        };
+        if i + 1 == self.words.len() || !type_word(i + 1) {
+            return None;
+        }
+        let end = (i + 1..self.words.len())
+            .find(|&j| !type_word(j) && self.words[j].kind != TokenKind::Star)?;
+        (self.words[end].kind == TokenKind::CloseParen).then_some(end)
+    }
+
+    /// Whether there is a space between the words at `previous` and `next`
+    fn space(&self, previous: usize, next: usize) -> bool {
+        let (before, after) = (self.words[previous].kind, self.words[next].kind);
+        // `...` of variadic parameters
+        if after == TokenKind::Comment || (before == TokenKind::Comma && after == TokenKind::Dot) {
+            return true;
+        }
+        if matches!(
+            after,
+            TokenKind::CloseParen
+                | TokenKind::CloseBracket
+                | TokenKind::OpenBracket
+                | TokenKind::Comma
+                | TokenKind::SemiColon
+                | TokenKind::Dot
+                | TokenKind::Arrow
+        ) || matches!(
+            self.roles[next],
+            Role::Close | Role::CastEnd | Role::Postfix
+        ) {
+            return false;
+        }
+        if matches!(before, TokenKind::Dot | TokenKind::Arrow)
+            || matches!(
+                self.roles[previous],
+                Role::Open | Role::Prefix | Role::Pointer | Role::CastEnd
+            )
+        {
+            return false;
+        }
+        !(after == TokenKind::OpenParen && self.is_call(previous))
+    }
+
+    /// How good a line break after the word at `i` is, lower is preferred. Operators that bind
+    /// less tightly come first so that `a * b + c` isn't broken inside of `a * b`.
+    fn rank(&self, i: usize) -> Option<usize> {
+        if self.roles[i] != Role::Binary {
+            return matches!(self.words[i].kind, TokenKind::Comma | TokenKind::SemiColon)
+                .then_some(0);
+        }
+        Some(match self.words[i].kind {
+            TokenKind::Or => 1,
+            TokenKind::And => 2,
+            TokenKind::BitwiseOr | TokenKind::BitwiseXor | TokenKind::Reference => 3,
+            TokenKind::Equals | TokenKind::NotEquals => 4,
+            TokenKind::Less
+            | TokenKind::LessEquals
+            | TokenKind::Greater
+            | TokenKind::GreaterEquals => 5,
+            TokenKind::BitwiseShiftLeft | TokenKind::BitwiseShiftRight => 6,
+            TokenKind::Plus | TokenKind::Minus => 7,
+            TokenKind::Star | TokenKind::Slash | TokenKind::Percent => 8,
+            // assignments and `?:`, the value goes on the next line only when nothing else helps
+            _ => 9,
+        })
+    }
+
+    /// Words in the range on one line
+    pub(super) fn text(&self, range: Range<usize>) -> String {
+        let mut text = String::new();
+        for i in range.clone() {
+            if i > range.start && self.space(i - 1, i) {
+                text.push(' ');
+            }
+            text += &self.words[i].text;
+        }
+        text
+    }
+
+    /// The statement indented by `indent` levels. Lines that are too long are broken after
+    /// commas and binary operators, preferring the outermost ones and filling every line as
+    /// much as possible. Lines broken inside of parentheses continue after the opening one,
+    /// others one level deeper. Comments always end their line.
+    pub(super) fn layout(&self, indent: usize, config: &Config) -> Vec<String> {
+        let base = indent * config.indent_width;
+        let mut lines = Vec::new();
+        let mut line = " ".repeat(base);
+        let mut width = base;
+        let mut start = true;
+        let mut open: Vec<usize> = Vec::new();
+        let mut candidates: Vec<Candidate> = Vec::new();
+        let mut previous = None;
+        let mut i = 0;
+        while i < self.words.len() {
+            let word = self.words[i];
+            let continuation =
+                |open: &Vec<usize>| open.last().copied().unwrap_or(base + config.indent_width);
+            let space = !start && previous.is_some_and(|previous| self.space(previous, i));
+            let word_width = word.text.chars().count();
+            if word.kind == TokenKind::Comment {
+                if space {
+                    line.push(' ');
+                }
+                line += &word.text;
+                lines.push(std::mem::take(&mut line));
+                width = continuation(&open);
+                line = " ".repeat(width);
+                start = true;
+                candidates.clear();
+                i += 1;
+                continue;
+            }
+            if !start
+                && width + usize::from(space) + word_width > config.max_width
+                && let Some(candidate) = best(&candidates, config.max_width)
+            {
+                line.truncate(candidate.length);
+                lines.push(std::mem::take(&mut line));
+                width = candidate.continuation;
+                line = " ".repeat(width);
+                open = candidate.open.clone();
+                previous = Some(candidate.after);
+                i = candidate.after + 1;
+                start = true;
+                candidates.clear();
+                continue;
+            }
+            if space {
+                line.push(' ');
+                width += 1;
+            }
+            line += &word.text;
+            width += word_width;
+            start = false;
+            match self.roles[i] {
+                Role::Open => open.push(width),
+                Role::Close | Role::CastEnd => {
+                    open.pop();
+                }
+                _ => {}
+            }
+            if i + 1 < self.words.len()
+                && let Some(rank) = self.rank(i)
+            {
+                candidates.push(Candidate {
+                    after: i,
+                    length: line.len(),
+                    width,
+                    score: open.len() * 10 + rank,
+                    continuation: continuation(&open),
+                    open: open.clone(),
+                });
+            }
+            previous = Some(i);
+            i += 1;
+        }
+        if !start {
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// Where the name of a struct member starts: `int *next;` is split into `int` and `*next;`
+    pub(super) fn member_name(&self) -> Option<usize> {
+        if self.words.last()?.kind != TokenKind::SemiColon
+            || self.words.iter().any(|word| {
+                matches!(
+                    word.kind,
+                    TokenKind::OpenParen | TokenKind::OpenCurly | TokenKind::CloseCurly
+                )
+            })
+        {
+            return None;
+        }
+        let mut depth = 0usize;
+        let mut name = None;
+        for (i, word) in self.words.iter().enumerate() {
+            match word.kind {
+                TokenKind::OpenBracket => depth += 1,
+                TokenKind::CloseBracket => depth = depth.saturating_sub(1),
+                TokenKind::Identifier if depth == 0 => name = Some(i),
+                _ => {}
+            }
+        }
+        let mut start = name?;
+        while start > 0 && self.roles[start - 1] == Role::Pointer {
+            start -= 1;
+        }
+        (start > 0).then_some(start)
+    }
+
+    /// Where `= value` of an enum value starts, `Some(None)` for a value without one
+    pub(super) fn enum_value(&self) -> Option<Option<usize>> {
+        if self.words[0].kind != TokenKind::Identifier {
+            return None;
+        }
+        match self.words.get(1).map(|word| word.kind) {
+            Some(TokenKind::Assignment) => Some(Some(1)),
+            None | Some(TokenKind::Comma) => Some(None),
+            _ => None,
+        }
+    }
+}
+
+/// The preferred break among those that keep the line short enough, or the earliest one with
+/// the lowest score when none does
+fn best(candidates: &[Candidate], max_width: usize) -> Option<&Candidate> {
+    let fitting = candidates
+        .iter()
+        .filter(|candidate| candidate.width <= max_width)
+        .min_by_key(|candidate| (candidate.score, std::cmp::Reverse(candidate.after)));
+    fitting.or_else(|| candidates.iter().min_by_key(|candidate| candidate.score))
+}
@@ -0,0 +1,463 @@
+mod line;
+
+use std::collections::HashSet;
+
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    format::line::Unit,
+    lexer::{
+        self,
+        token::{Token, TokenKind},
+    },
+};
+
+pub const DEFAULT_INDENT_WIDTH: usize = 4;
+pub const DEFAULT_MAX_WIDTH: usize = 80;
+
+/// Where the opening brace of a body goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BraceStyle {
+    /// at the end of the line that opens the body: `int main() {`
+    #[default]
+    Attach,
+    /// on a line of its own
+    Allman,
+    /// on a line of its own for function bodies, attached everywhere else
+    Linux,
+}
+
+impl BraceStyle {
+    pub fn by_name(name: &str) -> Result<Self> {
+        Ok(match name {
+            "attach" => BraceStyle::Attach,
+            "allman" => BraceStyle::Allman,
+            "linux" => BraceStyle::Linux,
+            name => bail!("unknown brace style: '{name}', expected one of: attach, allman, linux"),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// spaces per level of nesting
+    pub indent_width: usize,
+    pub brace_style: BraceStyle,
+    /// statements longer than this are broken after commas and binary operators
+    pub max_width: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            indent_width: DEFAULT_INDENT_WIDTH,
+            brace_style: BraceStyle::default(),
+            max_width: DEFAULT_MAX_WIDTH,
+        }
+    }
+}
+
+/// Reformats C source. Works on the tokens of the lexer without parsing or preprocessing them,
+/// so comments and directives are kept and only whitespace changes. Formatting the result
+/// again gives back the same text.
+pub fn format(source: &str, config: &Config) -> Result<String> {
+    let tokens = lexer::tokenize(source.chars().collect()).context("tokenization")?;
+    let words = words(source, &tokens)?;
+    let mut formatter = Formatter {
+        config,
+        typedefs: typedefs(&words),
+        words: &words,
+        lines: Vec::new(),
+        braces: Vec::new(),
+        initializers: 0,
+        depth: 0,
+        unit: Vec::new(),
+    };
+    for i in 0..words.len() {
+        formatter.word(i);
+    }
+    formatter.flush();
+    let formatted = render(formatter.lines, config);
+
+    // only whitespace may change, anything else is a bug in the formatter
+    let reformatted = lexer::tokenize(formatted.chars().collect())
+        .context("tokenization of the formatted source")?;
+    if significant(&tokens) != significant(&reformatted) {
+        bail!("formatting would change the tokens of the program:\n{formatted}");
+    }
+    Ok(formatted)
+}
+
+/// Token with the text it is written as and the amount of line breaks before it
+#[derive(Debug)]
+struct Word {
+    kind: TokenKind,
+    text: String,
+    newlines: usize,
+}
+
+fn words(source: &str, tokens: &[Token]) -> Result<Vec<Word>> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut words = Vec::new();
+    let mut newlines = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        let text = match token.kind {
+            TokenKind::NextLine => {
+                newlines += 1;
+                continue;
+            }
+            TokenKind::WhiteSpace | TokenKind::Tab | TokenKind::EndOfFile => continue,
+            TokenKind::Slash
+                if tokens
+                    .get(i + 1)
+                    .is_some_and(|next| next.kind == TokenKind::Star) =>
+            {
+                bail!(
+                    "line {}: block comments aren't supported, only '//' comments",
+                    token.line
+                );
+            }
+            // continued lines of a directive are kept as they were written
+            TokenKind::CompilerData => {
+                let start = token.line as usize - 1;
+                let end = (start..lines.len())
+                    .find(|&line| !lines[line].trim_end().ends_with('\\'))
+                    .unwrap_or(lines.len() - 1);
+                lines[start..=end]
+                    .iter()
+                    .map(|line| line.trim_end())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+                    .trim_start()
+                    .to_owned()
+            }
+            _ => token.text().trim_end().to_owned(),
+        };
+        words.push(Word {
+            kind: token.kind,
+            text,
+            newlines,
+        });
+        newlines = 0;
+    }
+    Ok(words)
+}
+
+/// Names declared with `typedef`, a `*` after them declares a pointer instead of multiplying
+fn typedefs(words: &[Word]) -> HashSet<&str> {
+    let mut names = HashSet::new();
+    let mut typedef = false;
+    let mut depth = 0usize;
+    let mut last = None;
+    for word in words {
+        match word.kind {
+            TokenKind::Typedef if depth == 0 => typedef = true,
+            TokenKind::OpenCurly | TokenKind::OpenParen => depth += 1,
+            TokenKind::CloseCurly | TokenKind::CloseParen => depth = depth.saturating_sub(1),
+            TokenKind::Identifier if typedef && depth == 0 => last = Some(word.text.as_str()),
+            TokenKind::SemiColon if typedef && depth == 0 => {
+                names.extend(last.take());
+                typedef = false;
+            }
+            _ => {}
+        }
+    }
+    names
+}
+
+/// Tokens that formatting must keep
+fn significant(tokens: &[Token]) -> Vec<(TokenKind, String)> {
+    tokens
+        .iter()
+        .filter(|token| {
+            !matches!(
+                token.kind,
+                TokenKind::WhiteSpace | TokenKind::Tab | TokenKind::NextLine
+            )
+        })
+        .map(|token| (token.kind, token.value.trim_end().to_owned()))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Brace {
+    Function,
+    Block,
+    /// body of a struct or a union
+    Aggregate,
+    Enum,
+}
+
+#[derive(Debug)]
+struct Line {
+    /// there was an empty line before it, more of them are merged into one
+    blank_before: bool,
+    kind: LineKind,
+    /// comment at the end of the line
+    comment: Option<String>,
+}
+
+#[derive(Debug)]
+enum LineKind {
+    /// statement, already indented and broken into lines
+    Code(Vec<String>),
+    /// struct member or enum value, the names of neighbouring members and the values of
+    /// neighbouring enum values start in the same column
+    Aligned {
+        indent: usize,
+        /// type of a member or name of an enum value
+        head: String,
+        /// name of a member or `= value` of an enum value, names without a value aren't
+        /// aligned
+        tail: Option<String>,
+    },
+    Comment(String),
+    Directive(String),
+}
+
+struct Formatter<'a> {
+    config: &'a Config,
+    words: &'a [Word],
+    typedefs: HashSet<&'a str>,
+    lines: Vec<Line>,
+    /// bodies that are open, initializer lists don't count
+    braces: Vec<Brace>,
+    /// nesting of initializer lists, they stay inside of their statement
+    initializers: usize,
+    /// nesting of parentheses and brackets
+    depth: usize,
+    /// indices of the words of the statement that is being collected
+    unit: Vec<usize>,
+}
+
+impl Formatter<'_> {
+    fn word(&mut self, i: usize) {
+        let word = &self.words[i];
+        match word.kind {
+            TokenKind::CompilerData => {
+                self.flush();
+                self.push(word.newlines, LineKind::Directive(word.text.to_owned()));
+            }
+            TokenKind::Comment if word.newlines == 0 && i > 0 => {
+                if !self.unit.is_empty() {
+                    self.unit.push(i);
+                } else if let Some(line) =
+                    self.lines.last_mut().filter(|line| line.comment.is_none())
+                {
+                    line.comment = Some(word.text.to_owned());
+                } else {
+                    self.push(word.newlines, LineKind::Comment(word.text.to_owned()));
+                }
+            }
+            TokenKind::Comment => {
+                self.flush();
+                let indent = " ".repeat(self.braces.len() * self.config.indent_width);
+                self.push(
+                    word.newlines,
+                    LineKind::Comment(format!("{indent}{}", word.text)),
+                );
+            }
+            TokenKind::OpenCurly
+                if self.initializers > 0 || self.previous_is(i, TokenKind::Assignment) =>
+            {
+                self.initializers += 1;
+                self.unit.push(i);
+            }
+            TokenKind::OpenCurly => {
+                let brace = self.classify(i);
+                let own_line = match self.config.brace_style {
+                    BraceStyle::Attach => false,
+                    BraceStyle::Allman => true,
+                    BraceStyle::Linux => brace == Brace::Function,
+                };
+                if own_line {
+                    self.flush();
+                }
+                self.unit.push(i);
+                self.flush();
+                self.braces.push(brace);
+            }
+            TokenKind::CloseCurly if self.initializers > 0 => {
+                self.initializers -= 1;
+                self.unit.push(i);
+            }
+            TokenKind::CloseCurly => {
+                self.flush();
+                let brace = self.braces.pop();
+                self.unit.push(i);
+                // `} Name;` of a typedef, `};` and `} else` stay on the line of the brace
+                let next = self.words.get(i + 1).map(|word| word.kind);
+                let keep = matches!(brace, Some(Brace::Aggregate | Brace::Enum))
+                    || next == Some(TokenKind::SemiColon)
+                    || (next == Some(TokenKind::Else)
+                        && self.config.brace_style != BraceStyle::Allman);
+                if !keep {
+                    self.flush();
+                }
+            }
+            TokenKind::SemiColon => {
+                self.unit.push(i);
+                if self.depth == 0 && self.initializers == 0 {
+                    self.flush();
+                }
+            }
+            TokenKind::Comma => {
+                self.unit.push(i);
+                if self.depth == 0
+                    && self.initializers == 0
+                    && self.braces.last() == Some(&Brace::Enum)
+                {
+                    self.flush();
+                }
+            }
+            TokenKind::OpenParen | TokenKind::OpenBracket => {
+                self.depth += 1;
+                self.unit.push(i);
+            }
+            TokenKind::CloseParen | TokenKind::CloseBracket => {
+                self.depth = self.depth.saturating_sub(1);
+                self.unit.push(i);
+            }
+            _ => self.unit.push(i),
+        }
+    }
+
+    /// Kind of the previous word that isn't a comment
+    fn previous(&self, i: usize, back: usize) -> Option<TokenKind> {
+        self.words[..i]
+            .iter()
+            .rev()
+            .filter(|word| word.kind != TokenKind::Comment)
+            .nth(back)
+            .map(|word| word.kind)
+    }
+
+    fn previous_is(&self, i: usize, kind: TokenKind) -> bool {
+        self.previous(i, 0) == Some(kind)
+    }
+
+    /// What the body opened by the brace at `i` is
+    fn classify(&self, i: usize) -> Brace {
+        let tagged = |keyword: &[TokenKind]| {
+            self.previous(i, 0)
+                .is_some_and(|kind| keyword.contains(&kind))
+                || (self.previous_is(i, TokenKind::Identifier)
+                    && self
+                        .previous(i, 1)
+                        .is_some_and(|kind| keyword.contains(&kind)))
+        };
+        if tagged(&[TokenKind::Struct, TokenKind::Union]) {
+            Brace::Aggregate
+        } else if tagged(&[TokenKind::Enum]) {
+            Brace::Enum
+        } else if self.braces.is_empty() && self.previous_is(i, TokenKind::CloseParen) {
+            Brace::Function
+        } else {
+            Brace::Block
+        }
+    }
+
+    fn push(&mut self, newlines: usize, kind: LineKind) {
+        self.lines.push(Line {
+            blank_before: newlines > 1,
+            kind,
+            comment: None,
+        });
+    }
+
+    /// Ends the statement that is being collected
+    fn flush(&mut self) {
+        if self.unit.is_empty() {
+            return;
+        }
+        let indices = std::mem::take(&mut self.unit);
+        self.depth = 0;
+        self.initializers = 0;
+        let newlines = self.words[indices[0]].newlines;
+        let unit = Unit::new(
+            indices.iter().map(|&i| &self.words[i]).collect(),
+            &self.typedefs,
+        );
+        let indent = self.braces.len();
+        let lines = unit.layout(indent, self.config);
+        let split = match self.braces.last() {
+            _ if lines.len() > 1 || unit.has_comments() => None,
+            Some(Brace::Aggregate) => unit.member_name().map(Some),
+            Some(Brace::Enum) => unit.enum_value(),
+            _ => None,
+        };
+        let kind = match split {
+            Some(split) => LineKind::Aligned {
+                indent,
+                head: unit.text(0..split.unwrap_or(unit.len())),
+                tail: split.map(|split| unit.text(split..unit.len())),
+            },
+            None => LineKind::Code(lines),
+        };
+        self.push(newlines, kind);
+    }
+}
+
+/// `*`s before the name of a member, they are left of the column where the names start
+fn pointers(tail: &str) -> usize {
+    tail.len() - tail.trim_start_matches('*').len()
+}
+
+/// Aligns struct members and enum values and joins the lines
+fn render(mut lines: Vec<Line>, config: &Config) -> String {
+    let mut start = 0;
+    while start < lines.len() {
+        let mut end = start;
+        let mut width = 0;
+        while let Some(Line {
+            kind: LineKind::Aligned { head, tail, .. },
+            blank_before,
+            ..
+        }) = lines.get(end)
+        {
+            if end > start && *blank_before {
+                break;
+            }
+            if let Some(tail) = tail {
+                width = width.max(head.chars().count() + 1 + pointers(tail));
+            }
+            end += 1;
+        }
+        for line in &mut lines[start..end] {
+            if let LineKind::Aligned { indent, head, tail } = &line.kind {
+                let indent = " ".repeat(indent * config.indent_width);
+                let text = match tail {
+                    Some(tail) => {
+                        let padding = width - head.chars().count() - pointers(tail);
+                        format!("{indent}{head}{}{tail}", " ".repeat(padding))
+                    }
+                    None => format!("{indent}{head}"),
+                };
+                line.kind = LineKind::Code(vec![text]);
+            }
+        }
+        start = end.max(start + 1);
+    }
+
+    let mut output: Vec<String> = Vec::new();
+    for line in lines {
+        let mut texts = match line.kind {
+            LineKind::Code(texts) => texts,
+            LineKind::Comment(text) | LineKind::Directive(text) => vec![text],
+            LineKind::Aligned { .. } => unreachable!("aligned above"),
+        };
+        if let Some(comment) = line.comment
+            && let Some(last) = texts.last_mut()
+        {
+            *last = format!("{last} {comment}");
+        }
+        let after_open = output.last().is_none_or(|last| last.ends_with('{'));
+        if line.blank_before && !after_open && !texts[0].trim_start().starts_with('}') {
+            output.push(String::new());
+        }
+        output.extend(texts);
+    }
+    let mut text = output.join("\n");
+    text.push('\n');
+    text
+}
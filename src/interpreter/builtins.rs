@@ -0,0 +1,308 @@
+use std::iter::Peekable;
+
+use crate::{
+    diagnostics::Diagnostic,
+    interpreter::{
+        HEAP_LIMIT, Interpreter, Value,
+        memory::{AllocationKind, Byte},
+    },
+    parser::expression::Expression,
+    semantic::{constant::wrap, type_check::primitive},
+};
+
+/// functions of libc that the interpreter provides without a declaration
+pub const BUILTINS: [&str; 4] = ["printf", "malloc", "free", "strlen"];
+
+/// Flags, width and precision of a single `printf` conversion
+#[derive(Debug, Default)]
+pub(crate) struct Specification {
+    left: bool,
+    plus: bool,
+    space: bool,
+    zero: bool,
+    alternate: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Specification {
+    /// Reads the flags, width, precision and length modifier that follow a `%`
+    pub(crate) fn parse(bytes: &mut Peekable<impl Iterator<Item = u8>>) -> (Self, String) {
+        let mut specification = Specification::default();
+        while let Some(flag) = bytes.next_if(|byte| b"-+ 0#".contains(byte)) {
+            match flag {
+                b'-' => specification.left = true,
+                b'+' => specification.plus = true,
+                b' ' => specification.space = true,
+                b'0' => specification.zero = true,
+                _ => specification.alternate = true,
+            }
+        }
+        while let Some(digit) = bytes.next_if(u8::is_ascii_digit) {
+            specification.width = specification.width * 10 + (digit - b'0') as usize;
+        }
+        if bytes.next_if_eq(&b'.').is_some() {
+            let mut precision = 0;
+            while let Some(digit) = bytes.next_if(u8::is_ascii_digit) {
+                precision = precision * 10 + (digit - b'0') as usize;
+            }
+            specification.precision = Some(precision);
+        }
+        let mut length = String::new();
+        while let Some(modifier) = bytes.next_if(|byte| b"hlzjt".contains(byte)) {
+            length.push(modifier as char);
+        }
+        (specification, length)
+    }
+
+    /// `%d %i %u %x %X %o` of a value read as the type of the length modifier
+    pub(crate) fn integer(&self, conversion: u8, value: i128, length: &str) -> String {
+        if matches!(conversion, b'd' | b'i') {
+            let value = wrap(value, &primitive(length_type(length), false));
+            let sign = if value < 0 {
+                "-"
+            } else if self.plus {
+                "+"
+            } else if self.space {
+                " "
+            } else {
+                ""
+            };
+            let digits = with_precision(value.unsigned_abs().to_string(), self);
+            return self.pad(sign, &digits);
+        }
+        let value = wrap(value, &primitive(length_type(length), true));
+        let (digits, prefix) = match conversion {
+            b'u' => (value.to_string(), ""),
+            b'x' => (format!("{value:x}"), "0x"),
+            b'X' => (format!("{value:X}"), "0X"),
+            _ => (format!("{value:o}"), "0"),
+        };
+        let prefix = if self.alternate && value != 0 {
+            prefix
+        } else {
+            ""
+        };
+        self.pad(prefix, &with_precision(digits, self))
+    }
+
+    /// `%c` and `%s`, the bytes don't have to be UTF-8
+    pub(crate) fn bytes(&self, bytes: &[u8]) -> Vec<u8> {
+        let padding = self.pad("", &" ".repeat(bytes.len()));
+        let start = if self.left {
+            0
+        } else {
+            padding.len() - bytes.len()
+        };
+        let mut text = padding.into_bytes();
+        text[start..start + bytes.len()].copy_from_slice(bytes);
+        text
+    }
+
+    /// `%s` of a string cut to the precision
+    pub(crate) fn string(mut self, mut string: Vec<u8>) -> Vec<u8> {
+        if let Some(precision) = self.precision {
+            string.truncate(precision);
+        }
+        self.zero = false;
+        self.bytes(&string)
+    }
+
+    /// Pads a converted value to the width, `prefix` is the sign or `0x` that zeros go after
+    pub(crate) fn pad(&self, prefix: &str, digits: &str) -> String {
+        let length = prefix.len() + digits.len();
+        if length >= self.width {
+            return format!("{prefix}{digits}");
+        }
+        let padding = self.width - length;
+        if self.left {
+            format!("{prefix}{digits}{}", " ".repeat(padding))
+        } else if self.zero && self.precision.is_none() {
+            format!("{prefix}{}{digits}", "0".repeat(padding))
+        } else {
+            format!("{}{prefix}{digits}", " ".repeat(padding))
+        }
+    }
+}
+
+impl Interpreter<'_> {
+    /// Calls of functions that were never declared
+    pub(super) fn builtin(
+        &mut self,
+        name: &str,
+        values: &[Expression],
+        expression: &Expression,
+    ) -> Result<Value, Diagnostic> {
+        if !BUILTINS.contains(&name) {
+            return Err(self.error(
+                format!(
+                    "call to undeclared function '{name}', the interpreter only provides: {}",
+                    BUILTINS.join(", ")
+                ),
+                expression,
+            ));
+        }
+        let mut arguments = Vec::new();
+        for value in values {
+            arguments.push(self.value(value)?);
+        }
+        let expected = match name {
+            "printf" => 1..=usize::MAX,
+            _ => 1..=1,
+        };
+        if !expected.contains(&arguments.len()) {
+            return Err(self.error(
+                format!("wrong number of arguments to '{name}': {}", arguments.len()),
+                expression,
+            ));
+        }
+
+        let debug_data = self.debug_data(expression);
+        let argument = arguments[0].to_owned();
+        match (name, argument) {
+            ("printf", _) => self.printf(&arguments, expression),
+            ("malloc", Value::Integer(size)) => {
+                let size = wrap(size, &primitive("long", true)) as u64;
+                if self.memory.heap_size.saturating_add(size) > HEAP_LIMIT {
+                    return Ok(Value::Pointer(None));
+                }
+                let pointer = self.memory.allocate(
+                    AllocationKind::Heap,
+                    "heap memory",
+                    vec![Byte::Uninit; size as usize],
+                    &debug_data,
+                );
+                Ok(Value::Pointer(Some(pointer)))
+            }
+            ("free", Value::Pointer(pointer)) => {
+                if let Some(pointer) = pointer {
+                    self.memory.free(pointer, &debug_data)?;
+                }
+                Ok(Value::Void)
+            }
+            ("strlen", Value::Pointer(Some(pointer))) => {
+                let string = self.memory.read_string(pointer, &debug_data)?;
+                Ok(Value::Integer(string.len() as i128))
+            }
+            ("strlen", Value::Pointer(None)) => {
+                Err(self.error("strlen of a null pointer".to_owned(), expression))
+            }
+            (name, _) => Err(self.error(
+                format!("argument of '{name}' has the wrong type"),
+                expression,
+            )),
+        }
+    }
+
+    /// `%d %i %u %x %X %o %c %s %p %%` with flags, width, precision and length modifiers
+    fn printf(
+        &mut self,
+        arguments: &[Value],
+        expression: &Expression,
+    ) -> Result<Value, Diagnostic> {
+        let debug_data = self.debug_data(expression);
+        let Value::Pointer(Some(format)) = arguments[0] else {
+            return Err(self.error(
+                "format string of 'printf' is not a valid pointer".to_owned(),
+                expression,
+            ));
+        };
+        let format = self.memory.read_string(format, &debug_data)?;
+        let mut arguments = arguments[1..].iter();
+        let mut output: Vec<u8> = Vec::new();
+        let mut bytes = format.iter().copied().peekable();
+
+        while let Some(byte) = bytes.next() {
+            if byte != b'%' {
+                output.push(byte);
+                continue;
+            }
+            let (specification, length) = Specification::parse(&mut bytes);
+            let Some(conversion) = bytes.next() else {
+                return Err(self.error(
+                    "format string of 'printf' ends in the middle of a conversion".to_owned(),
+                    expression,
+                ));
+            };
+            if conversion == b'%' {
+                output.push(b'%');
+                continue;
+            }
+
+            let Some(argument) = arguments.next() else {
+                return Err(self.error(
+                    format!(
+                        "'%{}' in the format string of 'printf' has no argument",
+                        conversion as char
+                    ),
+                    expression,
+                ));
+            };
+            let text = match (conversion, argument) {
+                (b'd' | b'i' | b'u' | b'x' | b'X' | b'o', Value::Integer(value)) => {
+                    specification.integer(conversion, *value, &length)
+                }
+                (b'c', Value::Integer(value)) => {
+                    output.extend(specification.bytes(&[*value as u8]));
+                    continue;
+                }
+                (b's', Value::Pointer(Some(pointer))) => {
+                    let string = self.memory.read_string(*pointer, &debug_data)?;
+                    output.extend(specification.string(string));
+                    continue;
+                }
+                (b's', Value::Pointer(None)) => specification.pad("", "(null)"),
+                (b'p', Value::Pointer(pointer)) => match pointer {
+                    Some(pointer) => {
+                        specification.pad("0x", &format!("{:x}", self.memory.address(*pointer)))
+                    }
+                    None => specification.pad("", "(nil)"),
+                },
+                (b'd' | b'i' | b'u' | b'x' | b'X' | b'o' | b'c' | b's' | b'p', _) => {
+                    return Err(self.error(
+                        format!(
+                            "'%{}' in the format string of 'printf' was passed a value of the wrong type",
+                            conversion as char
+                        ),
+                        expression,
+                    ));
+                }
+                _ => {
+                    return Err(self.error(
+                        format!(
+                            "unsupported conversion '%{}' in the format string of 'printf'",
+                            conversion as char
+                        ),
+                        expression,
+                    ));
+                }
+            };
+            output.extend(text.into_bytes());
+        }
+
+        let written = output.len();
+        self.output.extend(output);
+        Ok(Value::Integer(written as i128))
+    }
+}
+
+/// Type that a length modifier reads the argument as
+fn length_type(length: &str) -> &'static str {
+    match length {
+        "hh" => "char",
+        "h" => "short",
+        "" => "int",
+        _ => "long",
+    }
+}
+
+/// Precision of an integer conversion is the minimum amount of digits
+fn with_precision(digits: String, specification: &Specification) -> String {
+    match specification.precision {
+        Some(0) if digits == "0" => String::new(),
+        Some(precision) if precision > digits.len() => {
+            format!("{}{digits}", "0".repeat(precision - digits.len()))
+        }
+        _ => digits,
+    }
+}
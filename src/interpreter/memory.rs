@@ -0,0 +1,268 @@
+use crate::{diagnostics::Diagnostic, parser::expression::DebugData};
+
+pub type AllocationId = usize;
+
+/// Address inside of an allocation. Pointers never leave the allocation they were made from,
+/// which is how out of bounds accesses are found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pointer {
+    pub allocation: AllocationId,
+    pub offset: i64,
+}
+
+impl Pointer {
+    pub fn offset_by(self, amount: i64) -> Self {
+        Self {
+            allocation: self.allocation,
+            offset: self.offset + amount,
+        }
+    }
+}
+
+/// Content of a single byte of simulated memory. Pointers are stored as their parts, so that a
+/// pointer can be copied byte by byte without losing the allocation it points into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Byte {
+    Uninit,
+    Data(u8),
+    Pointer { pointer: Pointer, index: u8 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocationKind {
+    Global,
+    /// local variables and parameters, they die at the end of their block
+    Stack,
+    /// `malloc`
+    Heap,
+    /// read only
+    StringLiteral,
+    /// zero sized, only exists so that functions have an address
+    Function,
+}
+
+#[derive(Debug, Clone)]
+pub struct Allocation {
+    pub kind: AllocationKind,
+    /// name of the variable, or what created the allocation
+    pub name: String,
+    pub bytes: Vec<Byte>,
+    /// false after `free` or after the variable went out of scope
+    pub live: bool,
+    pub debug_data: DebugData,
+}
+
+impl Allocation {
+    fn describe(&self) -> String {
+        match self.kind {
+            AllocationKind::Heap => format!(
+                "{} (allocated at line: {})",
+                self.name, self.debug_data.line
+            ),
+            AllocationKind::StringLiteral => "a string literal".to_owned(),
+            AllocationKind::Function => format!("function '{}'", self.name),
+            AllocationKind::Global | AllocationKind::Stack => format!("'{}'", self.name),
+        }
+    }
+}
+
+/// Every allocation the program made. Ids are never reused, so a dangling pointer always
+/// refers to the dead allocation instead of a new one at the same place.
+#[derive(Debug, Default)]
+pub struct Memory {
+    pub allocations: Vec<Allocation>,
+    /// bytes currently allocated by `malloc`
+    pub heap_size: u64,
+}
+
+/// fake addresses for printing pointers and casting them to integers
+const ADDRESS_BASE: i128 = 0x1000_0000;
+const ADDRESS_STRIDE: i128 = 0x10_0000;
+
+impl Memory {
+    pub fn allocate(
+        &mut self,
+        kind: AllocationKind,
+        name: &str,
+        bytes: Vec<Byte>,
+        debug_data: &DebugData,
+    ) -> Pointer {
+        if kind == AllocationKind::Heap {
+            self.heap_size += bytes.len() as u64;
+        }
+        self.allocations.push(Allocation {
+            kind,
+            name: name.to_owned(),
+            bytes,
+            live: true,
+            debug_data: debug_data.to_owned(),
+        });
+        Pointer {
+            allocation: self.allocations.len() - 1,
+            offset: 0,
+        }
+    }
+
+    pub fn allocation(&self, pointer: Pointer) -> &Allocation {
+        &self.allocations[pointer.allocation]
+    }
+
+    /// Ends the lifetime of a local variable
+    pub fn kill(&mut self, pointer: Pointer) {
+        self.allocations[pointer.allocation].live = false;
+    }
+
+    pub fn address(&self, pointer: Pointer) -> i128 {
+        ADDRESS_BASE + pointer.allocation as i128 * ADDRESS_STRIDE + pointer.offset as i128
+    }
+
+    /// Pointer arithmetic may only move inside of the allocation or one past its end
+    pub fn check_offset(&self, pointer: Pointer, debug_data: &DebugData) -> Result<(), Diagnostic> {
+        let allocation = self.allocation(pointer);
+        if pointer.offset < 0 || pointer.offset as usize > allocation.bytes.len() {
+            return Err(Diagnostic::error(
+                format!(
+                    "pointer arithmetic moved to offset {} of {}, which has {} bytes",
+                    pointer.offset,
+                    allocation.describe(),
+                    allocation.bytes.len()
+                ),
+                debug_data,
+            ));
+        }
+        Ok(())
+    }
+
+    fn access(
+        &self,
+        pointer: Pointer,
+        size: usize,
+        action: &str,
+        debug_data: &DebugData,
+    ) -> Result<std::ops::Range<usize>, Diagnostic> {
+        let allocation = self.allocation(pointer);
+        if !allocation.live {
+            let reason = match allocation.kind {
+                AllocationKind::Heap => "after it was freed",
+                _ => "after its lifetime ended",
+            };
+            return Err(Diagnostic::error(
+                format!("{action} of {} {reason}", allocation.describe()),
+                debug_data,
+            ));
+        }
+        if allocation.kind == AllocationKind::Function {
+            return Err(Diagnostic::error(
+                format!("{action} of the code of {}", allocation.describe()),
+                debug_data,
+            ));
+        }
+        let end = pointer.offset + size as i64;
+        if pointer.offset < 0 || end > allocation.bytes.len() as i64 {
+            return Err(Diagnostic::error(
+                format!(
+                    "out of bounds {action} of {size} bytes at offset {} of {}, which has {} bytes",
+                    pointer.offset,
+                    allocation.describe(),
+                    allocation.bytes.len()
+                ),
+                debug_data,
+            ));
+        }
+        Ok(pointer.offset as usize..end as usize)
+    }
+
+    pub fn read(
+        &self,
+        pointer: Pointer,
+        size: usize,
+        debug_data: &DebugData,
+    ) -> Result<Vec<Byte>, Diagnostic> {
+        let range = self.access(pointer, size, "read", debug_data)?;
+        Ok(self.allocation(pointer).bytes[range].to_vec())
+    }
+
+    pub fn write(
+        &mut self,
+        pointer: Pointer,
+        bytes: &[Byte],
+        debug_data: &DebugData,
+    ) -> Result<(), Diagnostic> {
+        let range = self.access(pointer, bytes.len(), "write", debug_data)?;
+        let allocation = &mut self.allocations[pointer.allocation];
+        if allocation.kind == AllocationKind::StringLiteral {
+            return Err(Diagnostic::error(
+                "write to a string literal".to_owned(),
+                debug_data,
+            ));
+        }
+        allocation.bytes[range].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Reads a zero terminated string, without the zero
+    pub fn read_string(
+        &self,
+        pointer: Pointer,
+        debug_data: &DebugData,
+    ) -> Result<Vec<u8>, Diagnostic> {
+        let mut output = Vec::new();
+        loop {
+            let byte = self.read(pointer.offset_by(output.len() as i64), 1, debug_data)?;
+            match byte[0] {
+                Byte::Data(0) => return Ok(output),
+                Byte::Data(byte) => output.push(byte),
+                Byte::Uninit => {
+                    return Err(Diagnostic::error(
+                        format!(
+                            "read of uninitialized memory at offset {} of {} while looking for the end of a string",
+                            pointer.offset + output.len() as i64,
+                            self.allocation(pointer).describe()
+                        ),
+                        debug_data,
+                    ));
+                }
+                Byte::Pointer { .. } => {
+                    return Err(Diagnostic::error(
+                        "a pointer was read as characters of a string".to_owned(),
+                        debug_data,
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Bytes that `malloc` gave out and nobody freed
+    pub fn leaks(&self) -> impl Iterator<Item = &Allocation> {
+        self.allocations
+            .iter()
+            .filter(|allocation| allocation.kind == AllocationKind::Heap && allocation.live)
+    }
+
+    pub fn free(&mut self, pointer: Pointer, debug_data: &DebugData) -> Result<(), Diagnostic> {
+        let allocation = &mut self.allocations[pointer.allocation];
+        let message = if allocation.kind != AllocationKind::Heap {
+            format!(
+                "free of {}, which wasn't allocated by malloc",
+                allocation.describe()
+            )
+        } else if !allocation.live {
+            format!("double free of {}", allocation.describe())
+        } else if pointer.offset != 0 {
+            format!(
+                "free of a pointer to offset {} of {}, instead of its start",
+                pointer.offset,
+                allocation.describe()
+            )
+        } else {
+            allocation.live = false;
+            self.heap_size -= allocation.bytes.len() as u64;
+            return Ok(());
+        };
+        Err(Diagnostic::error(message, debug_data))
+    }
+
+    pub fn describe(&self, pointer: Pointer) -> String {
+        self.allocation(pointer).describe()
+    }
+}
@@ -0,0 +1,1499 @@
+pub(crate) mod builtins;
+pub mod memory;
+
+use std::collections::HashMap;
+
+use crate::{
+    diagnostics::Diagnostic,
+    interpreter::memory::{AllocationId, AllocationKind, Byte, Memory, Pointer},
+    layout::{LayoutEngine, target::Target},
+    lexer::{character_value, string_bytes, token::TokenKind},
+    parser::{
+        expression::{DebugData, Expression, ExpressionId},
+        types::DataType,
+    },
+    semantic::{
+        constant::{ConstantContext, wrap},
+        scope::{SymbolId, SymbolKind, SymbolTable},
+        type_check::{
+            TypeTable, compound_operator, integer_rank, is_floating, is_integer, is_void,
+            primitive, promote, resolve, usual_arithmetic_conversion,
+        },
+    },
+};
+
+pub const DEFAULT_MAX_STEPS: u64 = 1_000_000;
+/// `malloc` returns NULL once the program holds this many bytes
+const HEAP_LIMIT: u64 = 64 * 1024 * 1024;
+/// deeper recursion is reported as a stack overflow
+const MAX_CALL_DEPTH: usize = 1000;
+/// every interpreted call takes a few native frames
+const NATIVE_STACK_SIZE: usize = 512 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Integer(i128),
+    /// `None` is the null pointer
+    Pointer(Option<Pointer>),
+    /// structs and unions are passed around as their bytes
+    Aggregate(Vec<Byte>),
+    Void,
+}
+
+/// What happened when the program ran
+#[derive(Debug)]
+pub struct Execution {
+    /// everything the program printed
+    pub output: String,
+    /// value returned from `main`, or the runtime error that stopped the program
+    pub result: Result<i128, Diagnostic>,
+    /// memory that was never freed
+    pub warnings: Vec<Diagnostic>,
+    pub steps: u64,
+}
+
+enum Flow {
+    Normal,
+    Break,
+    Return(Value),
+}
+
+#[derive(Default)]
+struct Frame {
+    variables: HashMap<SymbolId, Pointer>,
+    /// allocations made inside of every open block, they die when it closes
+    blocks: Vec<Vec<Pointer>>,
+}
+
+/// Executes the program by walking the tree. Every value lives in a simulated memory where each
+/// byte knows whether it was initialized and which allocation a pointer belongs to, so reads of
+/// uninitialized memory, out of bounds accesses, use after free and other undefined behavior
+/// stop the program with an error instead of doing something random.
+/// The program should have no errors from the earlier passes.
+pub fn run(
+    expressions: &[Expression],
+    symbols: &SymbolTable,
+    types: &TypeTable,
+    target: &Target,
+    max_steps: u64,
+) -> Execution {
+    std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .stack_size(NATIVE_STACK_SIZE)
+            .spawn_scoped(scope, || {
+                let mut interpreter = Interpreter::new(expressions, symbols, types, target);
+                interpreter.max_steps = max_steps;
+                let result = interpreter.program(expressions);
+                interpreter.finish(result)
+            })
+            .expect("failed to spawn the interpreter thread")
+            .join()
+            .expect("the interpreter thread panicked")
+    })
+}
+
+struct Interpreter<'a> {
+    symbols: &'a SymbolTable,
+    types: &'a TypeTable,
+    layout: LayoutEngine<'a, SymbolTable>,
+    memory: Memory,
+    functions: HashMap<SymbolId, &'a Expression>,
+    /// functions get an address the first time it is taken
+    function_addresses: HashMap<SymbolId, Pointer>,
+    function_allocations: HashMap<AllocationId, SymbolId>,
+    /// globals and static locals
+    globals: HashMap<SymbolId, Pointer>,
+    frames: Vec<Frame>,
+    /// every string literal is allocated once
+    strings: HashMap<ExpressionId, Pointer>,
+    output: Vec<u8>,
+    steps: u64,
+    max_steps: u64,
+    /// for errors about expressions without debug data
+    last_debug_data: DebugData,
+}
+
+impl<'a> Interpreter<'a> {
+    fn new(
+        expressions: &'a [Expression],
+        symbols: &'a SymbolTable,
+        types: &'a TypeTable,
+        target: &'a Target,
+    ) -> Self {
+        let functions = expressions
+            .iter()
+            .filter(|expression| matches!(expression, Expression::Function { .. }))
+            .filter_map(|function| Some((*symbols.declarations.get(&function.id())?, function)))
+            .collect();
+        let last_debug_data = expressions
+            .iter()
+            .find_map(Expression::debug_data)
+            .cloned()
+            .unwrap_or(DebugData {
+                line: 0,
+                file: String::new(),
+                expansion: None,
+            });
+        Self {
+            symbols,
+            types,
+            layout: LayoutEngine::new(target, symbols),
+            memory: Memory::default(),
+            functions,
+            function_addresses: HashMap::new(),
+            function_allocations: HashMap::new(),
+            globals: HashMap::new(),
+            frames: Vec::new(),
+            strings: HashMap::new(),
+            output: Vec::new(),
+            steps: 0,
+            max_steps: DEFAULT_MAX_STEPS,
+            last_debug_data,
+        }
+    }
+
+    fn finish(self, result: Result<i128, Diagnostic>) -> Execution {
+        let warnings = match result {
+            Ok(_) => self
+                .memory
+                .leaks()
+                .map(|allocation| {
+                    Diagnostic::warning(
+                        format!(
+                            "{} bytes allocated by malloc were never freed",
+                            allocation.bytes.len()
+                        ),
+                        &allocation.debug_data,
+                    )
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        Execution {
+            output: String::from_utf8_lossy(&self.output).into_owned(),
+            result,
+            warnings,
+            steps: self.steps,
+        }
+    }
+
+    /// Initializes the globals in order and calls `main`
+    fn program(&mut self, expressions: &'a [Expression]) -> Result<i128, Diagnostic> {
+        for expression in expressions {
+            match expression {
+                Expression::VariableDeclaration { .. }
+                | Expression::Assignment { .. }
+                | Expression::Static { .. } => {
+                    self.exec(expression)?;
+                }
+                _ => {}
+            }
+        }
+
+        let main = self
+            .functions
+            .iter()
+            .find(|(id, _)| self.symbols.symbol(**id).name == "main")
+            .map(|(_, function)| *function);
+        let Some(main) = main else {
+            return Err(Diagnostic::error(
+                "the program has no 'main' function".to_owned(),
+                &self.last_debug_data,
+            ));
+        };
+        match self.call(main, Vec::new())? {
+            Value::Integer(value) => Ok(value),
+            _ => Ok(0),
+        }
+    }
+
+    fn error(&self, message: String, expression: &Expression) -> Diagnostic {
+        let debug_data = expression.debug_data().unwrap_or(&self.last_debug_data);
+        Diagnostic::error(message, debug_data)
+    }
+
+    fn debug_data(&self, expression: &Expression) -> DebugData {
+        expression
+            .debug_data()
+            .unwrap_or(&self.last_debug_data)
+            .to_owned()
+    }
+
+    fn step(&mut self, expression: &Expression) -> Result<(), Diagnostic> {
+        if let Some(debug_data) = expression.debug_data() {
+            self.last_debug_data = debug_data.to_owned();
+        }
+        self.steps += 1;
+        if self.steps > self.max_steps {
+            return Err(self.error(
+                format!(
+                    "execution took more than {} steps, the program might be stuck in an infinite loop",
+                    self.max_steps
+                ),
+                expression,
+            ));
+        }
+        Ok(())
+    }
+
+    fn resolve(&self, data_type: &DataType) -> DataType {
+        resolve(self.symbols, data_type)
+    }
+
+    /// Type from the type checker, with typedefs resolved
+    fn type_of(&self, expression: &Expression) -> Result<DataType, Diagnostic> {
+        match self.types.type_of(expression) {
+            Some(data_type) => Ok(self.resolve(data_type)),
+            None => Err(self.error(
+                "the type of this expression is unknown".to_owned(),
+                expression,
+            )),
+        }
+    }
+
+    /// Type of the expression used as a value, arrays and functions decay to pointers
+    fn value_type(&self, expression: &Expression) -> Result<DataType, Diagnostic> {
+        Ok(match self.type_of(expression)? {
+            DataType::Array { inside, .. } => DataType::Pointer(inside),
+            function @ DataType::Function { .. } => DataType::Pointer(Box::new(function)),
+            data_type => data_type,
+        })
+    }
+
+    fn size_of(&self, data_type: &DataType, expression: &Expression) -> Result<u64, Diagnostic> {
+        self.layout
+            .size_of(data_type)
+            .map_err(|err| self.error(format!("{err:#}"), expression))
+    }
+
+    fn member_offset(
+        &self,
+        structure: &DataType,
+        member: &Expression,
+        expression: &Expression,
+    ) -> Result<i64, Diagnostic> {
+        let layout = self
+            .layout
+            .layout(structure)
+            .map_err(|err| self.error(format!("{err:#}"), expression))?;
+        let Expression::Identifier(name, _) = member else {
+            return Err(self.error("expected a member name".to_owned(), expression));
+        };
+        match layout.field(name) {
+            Some(field) => Ok(field.offset as i64),
+            None => Err(self.error(
+                format!("no member named '{name}' in '{structure}'"),
+                expression,
+            )),
+        }
+    }
+
+    // -- values in memory --
+
+    fn encode(
+        &self,
+        value: &Value,
+        data_type: &DataType,
+        expression: &Expression,
+    ) -> Result<Vec<Byte>, Diagnostic> {
+        let size = self.size_of(data_type, expression)? as usize;
+        Ok(match value {
+            Value::Integer(value) => (0..size)
+                .map(|i| Byte::Data((value >> (i * 8)) as u8))
+                .collect(),
+            Value::Pointer(Some(pointer)) => (0..size)
+                .map(|index| Byte::Pointer {
+                    pointer: *pointer,
+                    index: index as u8,
+                })
+                .collect(),
+            Value::Pointer(None) => vec![Byte::Data(0); size],
+            Value::Aggregate(bytes) => bytes.to_owned(),
+            Value::Void => {
+                return Err(self.error("a void value can't be stored".to_owned(), expression));
+            }
+        })
+    }
+
+    fn store(
+        &mut self,
+        pointer: Pointer,
+        data_type: &DataType,
+        value: &Value,
+        expression: &Expression,
+    ) -> Result<(), Diagnostic> {
+        let bytes = self.encode(value, data_type, expression)?;
+        let debug_data = self.debug_data(expression);
+        self.memory.write(pointer, &bytes, &debug_data)
+    }
+
+    fn load(
+        &self,
+        pointer: Pointer,
+        data_type: &DataType,
+        expression: &Expression,
+    ) -> Result<Value, Diagnostic> {
+        let data_type = self.resolve(data_type);
+        match &data_type {
+            DataType::Array { .. } => return Ok(Value::Pointer(Some(pointer))),
+            DataType::Function { .. } => {
+                return Err(self.error("functions can't be read as values".to_owned(), expression));
+            }
+            data_type if is_floating(data_type) => {
+                return Err(self.error(
+                    format!("'{data_type}' values are not supported by the interpreter"),
+                    expression,
+                ));
+            }
+            _ => {}
+        }
+        let size = self.size_of(&data_type, expression)? as usize;
+        let bytes = self
+            .memory
+            .read(pointer, size, &self.debug_data(expression))?;
+        if let DataType::Struct { .. } | DataType::Union { .. } = data_type {
+            return Ok(Value::Aggregate(bytes));
+        }
+
+        if let Some(uninit) = bytes.iter().position(|byte| *byte == Byte::Uninit) {
+            return Err(self.error(
+                format!(
+                    "read of uninitialized memory at offset {} of {}",
+                    pointer.offset + uninit as i64,
+                    self.memory.describe(pointer)
+                ),
+                expression,
+            ));
+        }
+        if let DataType::Pointer(_) = data_type {
+            if bytes.iter().all(|byte| *byte == Byte::Data(0)) {
+                return Ok(Value::Pointer(None));
+            }
+            if let Byte::Pointer { pointer, .. } = bytes[0]
+                && bytes.iter().enumerate().all(|(i, byte)| {
+                    *byte
+                        == Byte::Pointer {
+                            pointer,
+                            index: i as u8,
+                        }
+                })
+            {
+                return Ok(Value::Pointer(Some(pointer)));
+            }
+            return Err(self.error(
+                "read of a pointer that was only partially written".to_owned(),
+                expression,
+            ));
+        }
+
+        let mut value: i128 = 0;
+        for (i, byte) in bytes.iter().enumerate() {
+            let Byte::Data(byte) = byte else {
+                return Err(self.error("read of a pointer as an integer".to_owned(), expression));
+            };
+            value |= (*byte as i128) << (i * 8);
+        }
+        // sign extend from the stored size, then to the range of the type
+        let shift = 128 - size * 8;
+        Ok(Value::Integer(wrap((value << shift) >> shift, &data_type)))
+    }
+
+    fn integer(&self, value: Value, expression: &Expression) -> Result<i128, Diagnostic> {
+        match value {
+            Value::Integer(value) => Ok(value),
+            _ => Err(self.error("expected an integer value".to_owned(), expression)),
+        }
+    }
+
+    fn truthy(&self, value: &Value, expression: &Expression) -> Result<bool, Diagnostic> {
+        match value {
+            Value::Integer(value) => Ok(*value != 0),
+            Value::Pointer(pointer) => Ok(pointer.is_some()),
+            _ => Err(self.error("condition is not a scalar value".to_owned(), expression)),
+        }
+    }
+
+    /// The same conversion as assigning the value to something of the type
+    fn convert(
+        &self,
+        value: Value,
+        data_type: &DataType,
+        expression: &Expression,
+    ) -> Result<Value, Diagnostic> {
+        let data_type = self.resolve(data_type);
+        Ok(match (&data_type, value) {
+            (data_type, _) if is_void(data_type) => Value::Void,
+            (data_type, _) if is_floating(data_type) => {
+                return Err(self.error(
+                    format!("'{data_type}' values are not supported by the interpreter"),
+                    expression,
+                ));
+            }
+            (data_type, Value::Integer(value)) if is_integer(data_type) => {
+                Value::Integer(wrap(value, data_type))
+            }
+            (data_type, Value::Pointer(pointer)) if is_integer(data_type) => {
+                let address = match pointer {
+                    Some(pointer) => self.memory.address(pointer),
+                    None => 0,
+                };
+                Value::Integer(wrap(address, data_type))
+            }
+            (DataType::Pointer(_), Value::Integer(0)) => Value::Pointer(None),
+            (DataType::Pointer(_), Value::Integer(value)) => {
+                return Err(self.error(
+                    format!(
+                        "integer {value} converted to a pointer, only pointers to objects of the program can be used"
+                    ),
+                    expression,
+                ));
+            }
+            (DataType::Pointer(_), pointer @ Value::Pointer(_)) => pointer,
+            (DataType::Struct { .. } | DataType::Union { .. }, aggregate @ Value::Aggregate(_)) => {
+                aggregate
+            }
+            (_, Value::Void) => {
+                return Err(self.error(
+                    "void value used where a value is expected".to_owned(),
+                    expression,
+                ));
+            }
+            (data_type, _) => {
+                return Err(self.error(
+                    format!("value can't be converted to '{data_type}'"),
+                    expression,
+                ));
+            }
+        })
+    }
+
+    // -- variables --
+
+    fn variable(&self, id: SymbolId, expression: &Expression) -> Result<Pointer, Diagnostic> {
+        self.frames
+            .last()
+            .and_then(|frame| frame.variables.get(&id))
+            .or_else(|| self.globals.get(&id))
+            .copied()
+            .ok_or_else(|| {
+                self.error(
+                    format!(
+                        "'{}' was used before its declaration was executed",
+                        self.symbols.symbol(id).name
+                    ),
+                    expression,
+                )
+            })
+    }
+
+    fn function_pointer(&mut self, id: SymbolId) -> Value {
+        if let Some(pointer) = self.function_addresses.get(&id) {
+            return Value::Pointer(Some(*pointer));
+        }
+        let symbol = self.symbols.symbol(id);
+        let pointer = self.memory.allocate(
+            AllocationKind::Function,
+            &symbol.name,
+            Vec::new(),
+            &symbol.debug_data,
+        );
+        self.function_addresses.insert(id, pointer);
+        self.function_allocations.insert(pointer.allocation, id);
+        Value::Pointer(Some(pointer))
+    }
+
+    /// `int x;`, `int x = 5;`, `static int x;`. Globals and statics start zeroed, locals
+    /// uninitialized.
+    fn declare(
+        &mut self,
+        declaration: &Expression,
+        initializer: Option<&Expression>,
+        is_static: bool,
+    ) -> Result<(), Diagnostic> {
+        let Expression::VariableDeclaration { var_type, name, .. } = declaration else {
+            return Err(self.error("expected a variable declaration".to_owned(), declaration));
+        };
+        let Some(id) = self.symbols.declarations.get(&declaration.id()).copied() else {
+            return Err(self.error(
+                format!("declaration of '{name}' wasn't resolved"),
+                declaration,
+            ));
+        };
+        let is_static = is_static || self.frames.is_empty();
+        // static locals are only initialized the first time
+        if is_static && self.globals.contains_key(&id) {
+            return Ok(());
+        }
+
+        let size = self.size_of(var_type, declaration)? as usize;
+        let (kind, byte) = if is_static {
+            (AllocationKind::Global, Byte::Data(0))
+        } else {
+            (AllocationKind::Stack, Byte::Uninit)
+        };
+        let debug_data = self.debug_data(declaration);
+        let pointer = self
+            .memory
+            .allocate(kind, name, vec![byte; size], &debug_data);
+        match self.frames.last_mut() {
+            Some(frame) if !is_static => {
+                frame.variables.insert(id, pointer);
+                if let Some(block) = frame.blocks.last_mut() {
+                    block.push(pointer);
+                }
+            }
+            _ => {
+                self.globals.insert(id, pointer);
+            }
+        }
+
+        if let Some(initializer) = initializer {
+            self.initialize(pointer, var_type, initializer)?;
+        }
+        Ok(())
+    }
+
+    /// Stores the initializer of a declaration, members and elements that an initializer list
+    /// leaves out are zeroed
+    fn initialize(
+        &mut self,
+        pointer: Pointer,
+        data_type: &DataType,
+        initializer: &Expression,
+    ) -> Result<(), Diagnostic> {
+        self.step(initializer)?;
+        let resolved = self.resolve(data_type);
+        let debug_data = self.debug_data(initializer);
+
+        if let (DataType::Array { length, inside }, Expression::String(string, _)) =
+            (&resolved, initializer)
+            && integer_rank(&self.resolve(inside)) == Some(2)
+        {
+            let mut bytes =
+                string_bytes(string).map_err(|err| self.error(err.to_string(), initializer))?;
+            bytes.resize(*length as usize, 0);
+            let bytes = bytes.into_iter().map(Byte::Data).collect::<Vec<_>>();
+            return self.memory.write(pointer, &bytes, &debug_data);
+        }
+
+        let Expression::DataStructureInitialization { values, .. } = initializer else {
+            let value = self.value(initializer)?;
+            let value = self.convert(value, &resolved, initializer)?;
+            return self.store(pointer, &resolved, &value, initializer);
+        };
+
+        let size = self.size_of(&resolved, initializer)? as usize;
+        self.memory
+            .write(pointer, &vec![Byte::Data(0); size], &debug_data)?;
+        let elements: Vec<(i64, DataType)> = match &resolved {
+            DataType::Array { length, inside } => {
+                let element_size = self.size_of(inside, initializer)? as i64;
+                (0..*length as i64)
+                    .map(|i| (i * element_size, inside.as_ref().to_owned()))
+                    .collect()
+            }
+            DataType::Struct { .. } | DataType::Union { .. } => {
+                let layout = self
+                    .layout
+                    .layout(&resolved)
+                    .map_err(|err| self.error(format!("{err:#}"), initializer))?;
+                let take = match resolved {
+                    DataType::Union { .. } => 1,
+                    _ => layout.fields.len(),
+                };
+                layout
+                    .fields
+                    .into_iter()
+                    .take(take)
+                    .map(|field| (field.offset as i64, field.data_type))
+                    .collect()
+            }
+            scalar => vec![(0, scalar.to_owned())],
+        };
+        for ((offset, element_type), value) in elements.iter().zip(values) {
+            self.initialize(pointer.offset_by(*offset), element_type, value)?;
+        }
+        Ok(())
+    }
+
+    fn push_block(&mut self) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.blocks.push(Vec::new());
+        }
+    }
+
+    fn pop_block(&mut self) {
+        if let Some(frame) = self.frames.last_mut()
+            && let Some(block) = frame.blocks.pop()
+        {
+            for pointer in block {
+                self.memory.kill(pointer);
+            }
+        }
+    }
+
+    // -- statements --
+
+    fn block(&mut self, inside: &[Expression]) -> Result<Flow, Diagnostic> {
+        self.push_block();
+        let flow = self.statements(inside);
+        self.pop_block();
+        flow
+    }
+
+    fn statements(&mut self, inside: &[Expression]) -> Result<Flow, Diagnostic> {
+        for expression in inside {
+            match self.exec(expression)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn condition(&mut self, condition: &Expression) -> Result<bool, Diagnostic> {
+        if let Expression::Skip = condition {
+            return Ok(true);
+        }
+        let value = self.value(condition)?;
+        self.truthy(&value, condition)
+    }
+
+    fn exec(&mut self, expression: &Expression) -> Result<Flow, Diagnostic> {
+        self.step(expression)?;
+        match expression {
+            Expression::Skip
+            | Expression::CompilerData(..)
+            | Expression::Typedef { .. }
+            | Expression::Struct { .. }
+            | Expression::Function { .. }
+            | Expression::FunctionDeclaration { .. } => {}
+            Expression::VariableDeclaration { .. } => self.declare(expression, None, false)?,
+            Expression::Assignment { target, value, .. }
+                if matches!(target.as_ref(), Expression::VariableDeclaration { .. }) =>
+            {
+                self.declare(target, Some(value), false)?
+            }
+            Expression::Static { value, .. } => match value.as_ref() {
+                Expression::VariableDeclaration { .. } => self.declare(value, None, true)?,
+                Expression::Assignment { target, value, .. } => {
+                    self.declare(target, Some(value), true)?
+                }
+                _ => return self.exec(value),
+            },
+            Expression::NewCodeBlock { inside, .. } => return self.block(inside),
+            Expression::Break { .. } => return Ok(Flow::Break),
+            Expression::Return { value, .. } => {
+                let value = match value.as_ref() {
+                    Expression::Skip => Value::Void,
+                    value => self.value(value)?,
+                };
+                return Ok(Flow::Return(value));
+            }
+            Expression::If {
+                condition,
+                inside,
+                chained_elses,
+                ..
+            } => {
+                if self.condition(condition)? {
+                    return self.block(inside);
+                }
+                for chained in chained_elses {
+                    let Expression::Else {
+                        condition, inside, ..
+                    } = chained
+                    else {
+                        continue;
+                    };
+                    let taken = match condition {
+                        Some(condition) => self.condition(condition)?,
+                        None => true,
+                    };
+                    if taken {
+                        return self.block(inside);
+                    }
+                }
+            }
+            Expression::While {
+                condition, inside, ..
+            } => {
+                while self.condition(condition)? {
+                    match self.block(inside)? {
+                        Flow::Normal => {}
+                        Flow::Break => break,
+                        flow @ Flow::Return(_) => return Ok(flow),
+                    }
+                }
+            }
+            Expression::For {
+                iterator_init,
+                condition,
+                incr,
+                inside,
+                ..
+            } => {
+                self.push_block();
+                let flow = self.for_loop(iterator_init, condition, incr, inside);
+                self.pop_block();
+                return flow;
+            }
+            _ => {
+                self.value(expression)?;
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn for_loop(
+        &mut self,
+        iterator_init: &Expression,
+        condition: &Expression,
+        incr: &Expression,
+        inside: &[Expression],
+    ) -> Result<Flow, Diagnostic> {
+        self.exec(iterator_init)?;
+        while self.condition(condition)? {
+            match self.block(inside)? {
+                Flow::Normal => {}
+                Flow::Break => break,
+                flow @ Flow::Return(_) => return Ok(flow),
+            }
+            if !matches!(incr, Expression::Skip) {
+                self.value(incr)?;
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    // -- calls --
+
+    fn call(&mut self, function: &Expression, arguments: Vec<Value>) -> Result<Value, Diagnostic> {
+        let Expression::Function {
+            name,
+            output,
+            inside,
+            ..
+        } = function
+        else {
+            return Err(self.error("expected a function".to_owned(), function));
+        };
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Err(self.error(
+                format!("stack overflow, calls are nested more than {MAX_CALL_DEPTH} levels deep"),
+                function,
+            ));
+        }
+        let parameters = self
+            .symbols
+            .parameters
+            .get(&function.id())
+            .cloned()
+            .unwrap_or_default();
+        if parameters.len() != arguments.len() {
+            return Err(self.error(
+                format!(
+                    "'{name}' takes {} arguments, {} were passed",
+                    parameters.len(),
+                    arguments.len()
+                ),
+                function,
+            ));
+        }
+
+        let mut frame = Frame {
+            variables: HashMap::new(),
+            blocks: vec![Vec::new()],
+        };
+        for (id, argument) in parameters.into_iter().zip(arguments) {
+            let symbol = self.symbols.symbol(id);
+            let data_type = self.resolve(&symbol.data_type);
+            let value = self.convert(argument, &data_type, function)?;
+            let bytes = self.encode(&value, &data_type, function)?;
+            let pointer = self.memory.allocate(
+                AllocationKind::Stack,
+                &symbol.name,
+                bytes,
+                &symbol.debug_data,
+            );
+            frame.variables.insert(id, pointer);
+            frame.blocks[0].push(pointer);
+        }
+        self.frames.push(frame);
+        let flow = self.statements(inside);
+        while self
+            .frames
+            .last()
+            .is_some_and(|frame| !frame.blocks.is_empty())
+        {
+            self.pop_block();
+        }
+        self.frames.pop();
+
+        match flow? {
+            Flow::Return(value) => self.convert(value, output, function),
+            _ if is_void(&self.resolve(output)) => Ok(Value::Void),
+            // reaching the end of main returns 0
+            _ if name == "main" => Ok(Value::Integer(0)),
+            _ => Err(self.error(
+                format!("'{name}' reached its end without returning a value"),
+                function,
+            )),
+        }
+    }
+
+    fn function_call(
+        &mut self,
+        left: &Expression,
+        values: &[Expression],
+        expression: &Expression,
+    ) -> Result<Value, Diagnostic> {
+        if let Expression::Identifier(name, _) = left {
+            match name.as_str() {
+                "sizeof" | "_Alignof" => return self.size_operator(name, values, expression),
+                "_Static_assert" => return Ok(Value::Void),
+                _ => {}
+            }
+            // libc functions can be declared by a prototype, they still don't have a body
+            let has_body = self
+                .symbols
+                .resolutions
+                .get(&left.id())
+                .is_some_and(|id| self.functions.contains_key(id));
+            let prototype = self
+                .symbols
+                .resolution(left)
+                .is_some_and(|symbol| symbol.kind == SymbolKind::Function);
+            if self.symbols.resolution(left).is_none() || (prototype && !has_body) {
+                return self.builtin(name, values, expression);
+            }
+        }
+
+        let function = match self.value(left)? {
+            Value::Pointer(Some(pointer)) if pointer.offset == 0 => self
+                .function_allocations
+                .get(&pointer.allocation)
+                .and_then(|id| self.functions.get(id))
+                .copied(),
+            Value::Pointer(None) => {
+                return Err(self.error(
+                    "call through a null function pointer".to_owned(),
+                    expression,
+                ));
+            }
+            _ => None,
+        };
+        let Some(function) = function else {
+            return Err(self.error(
+                "called value is not a function with a body".to_owned(),
+                expression,
+            ));
+        };
+        let mut arguments = Vec::new();
+        for value in values {
+            arguments.push(self.value(value)?);
+        }
+        self.call(function, arguments)
+    }
+
+    /// `sizeof(Vec)` or `sizeof(value)`, the value isn't evaluated
+    fn size_operator(
+        &self,
+        operator: &str,
+        values: &[Expression],
+        expression: &Expression,
+    ) -> Result<Value, Diagnostic> {
+        let [value] = values else {
+            return Err(self.error(format!("'{operator}' takes 1 operand"), expression));
+        };
+        let data_type = match value {
+            Expression::DataTypeAccess { data_type, .. }
+                if self.symbols.resolution(value).is_none() =>
+            {
+                data_type.to_owned()
+            }
+            value => self.type_of(value)?,
+        };
+        let size = match operator {
+            "sizeof" => self.layout.size_of(&data_type),
+            _ => self.layout.align_of(&data_type),
+        }
+        .map_err(|err| self.error(format!("{err:#}"), expression))?;
+        Ok(Value::Integer(size as i128))
+    }
+
+    // -- expressions --
+
+    /// Address of something that can be assigned to, and its type
+    fn place(&mut self, expression: &Expression) -> Result<(Pointer, DataType), Diagnostic> {
+        self.step(expression)?;
+        match expression {
+            Expression::Identifier(..) | Expression::DataTypeAccess { .. } => {
+                let Some(id) = self.symbols.resolutions.get(&expression.id()).copied() else {
+                    return Err(self.error("undeclared identifier".to_owned(), expression));
+                };
+                let pointer = self.variable(id, expression)?;
+                Ok((pointer, self.resolve(&self.symbols.symbol(id).data_type)))
+            }
+            Expression::VariableDeclaration { var_type, .. } => {
+                self.declare(expression, None, false)?;
+                let id = self.symbols.declarations[&expression.id()];
+                Ok((self.variable(id, expression)?, self.resolve(var_type)))
+            }
+            Expression::Grouping { value, .. } => self.place(value),
+            Expression::String(..) => {
+                let pointer = self.string(expression)?;
+                Ok((pointer, self.type_of(expression)?))
+            }
+            Expression::Dereference { value, .. } => match self.value(value)? {
+                Value::Pointer(Some(pointer)) => Ok((pointer, self.type_of(expression)?)),
+                Value::Pointer(None) => {
+                    Err(self.error("null pointer dereference".to_owned(), expression))
+                }
+                _ => Err(self.error(
+                    "dereference of a value that is not a pointer".to_owned(),
+                    expression,
+                )),
+            },
+            Expression::AccessArray { left, index, .. } => {
+                let (left, index) = (self.value(left)?, self.value(index)?);
+                let (pointer, index) = match (left, index) {
+                    (Value::Pointer(pointer), Value::Integer(index))
+                    | (Value::Integer(index), Value::Pointer(pointer)) => (pointer, index),
+                    _ => {
+                        return Err(
+                            self.error("subscripted value is not a pointer".to_owned(), expression)
+                        );
+                    }
+                };
+                let Some(pointer) = pointer else {
+                    return Err(self.error("subscript of a null pointer".to_owned(), expression));
+                };
+                let data_type = self.type_of(expression)?;
+                let size = self.size_of(&data_type, expression)? as i128;
+                let pointer = pointer.offset_by((index * size) as i64);
+                let allocation = self.memory.allocation(pointer);
+                if pointer.offset < 0 || pointer.offset as usize >= allocation.bytes.len() {
+                    return Err(self.error(
+                        format!(
+                            "index {index} is out of bounds of {}, which has {} bytes",
+                            self.memory.describe(pointer),
+                            allocation.bytes.len()
+                        ),
+                        expression,
+                    ));
+                }
+                Ok((pointer, data_type))
+            }
+            Expression::MemberExpr { left, right, .. } => {
+                let (pointer, structure) = self.place(left)?;
+                let offset = self.member_offset(&structure, right, expression)?;
+                Ok((pointer.offset_by(offset), self.type_of(expression)?))
+            }
+            Expression::Arrow { left, right, .. } => {
+                let DataType::Pointer(structure) = self.value_type(left)? else {
+                    return Err(self.error(
+                        "'->' used on a value that is not a pointer".to_owned(),
+                        expression,
+                    ));
+                };
+                let Value::Pointer(pointer) = self.value(left)? else {
+                    return Err(self.error(
+                        "'->' used on a value that is not a pointer".to_owned(),
+                        expression,
+                    ));
+                };
+                let Some(pointer) = pointer else {
+                    return Err(self.error(
+                        "member access through a null pointer".to_owned(),
+                        expression,
+                    ));
+                };
+                let structure = self.resolve(&structure);
+                let offset = self.member_offset(&structure, right, expression)?;
+                Ok((pointer.offset_by(offset), self.type_of(expression)?))
+            }
+            _ => Err(self.error("expression is not an lvalue".to_owned(), expression)),
+        }
+    }
+
+    fn string(&mut self, expression: &Expression) -> Result<Pointer, Diagnostic> {
+        if let Some(pointer) = self.strings.get(&expression.id()) {
+            return Ok(*pointer);
+        }
+        let Expression::String(value, debug_data) = expression else {
+            return Err(self.error("expected a string literal".to_owned(), expression));
+        };
+        let mut bytes =
+            string_bytes(value).map_err(|err| self.error(err.to_string(), expression))?;
+        bytes.push(0);
+        let pointer = self.memory.allocate(
+            AllocationKind::StringLiteral,
+            "string literal",
+            bytes.into_iter().map(Byte::Data).collect(),
+            debug_data,
+        );
+        self.strings.insert(expression.id(), pointer);
+        Ok(pointer)
+    }
+
+    fn value(&mut self, expression: &Expression) -> Result<Value, Diagnostic> {
+        self.step(expression)?;
+        match expression {
+            Expression::Number(value, ..) => Ok(Value::Integer(*value as i128)),
+            Expression::Boolean(value, _) => Ok(Value::Integer(*value as i128)),
+            Expression::Character(value, _) => character_value(value)
+                .map(Value::Integer)
+                .map_err(|err| self.error(err.to_string(), expression)),
+            Expression::String(..) => Ok(Value::Pointer(Some(self.string(expression)?))),
+            Expression::Identifier(name, _)
+            | Expression::DataTypeAccess {
+                data_type: DataType::Data { name, .. },
+                ..
+            } => {
+                let Some(id) = self.symbols.resolutions.get(&expression.id()).copied() else {
+                    return Err(
+                        self.error(format!("use of undeclared identifier '{name}'"), expression)
+                    );
+                };
+                match self.symbols.symbol(id).kind {
+                    SymbolKind::EnumConstant => match self.symbols.enum_constant(name) {
+                        Some(value) => Ok(Value::Integer(value as i128)),
+                        None => {
+                            Err(self.error(format!("unknown enum constant '{name}'"), expression))
+                        }
+                    },
+                    SymbolKind::Function => Ok(self.function_pointer(id)),
+                    _ => self.load_place(expression),
+                }
+            }
+            Expression::Dereference { .. }
+            | Expression::AccessArray { .. }
+            | Expression::Arrow { .. }
+            | Expression::DataTypeAccess { .. } => self.load_place(expression),
+            Expression::MemberExpr { left, right, .. } => {
+                if is_place(left) {
+                    return self.load_place(expression);
+                }
+                // member of a struct returned from a call
+                let structure = self.type_of(left)?;
+                let Value::Aggregate(bytes) = self.value(left)? else {
+                    return Err(self.error(
+                        "member access on a value that is not a struct".to_owned(),
+                        expression,
+                    ));
+                };
+                let offset = self.member_offset(&structure, right, expression)? as usize;
+                let data_type = self.type_of(expression)?;
+                let size = self.size_of(&data_type, expression)? as usize;
+                let debug_data = self.debug_data(expression);
+                let pointer = self.memory.allocate(
+                    AllocationKind::Stack,
+                    "temporary",
+                    bytes[offset..offset + size].to_vec(),
+                    &debug_data,
+                );
+                let value = self.load(pointer, &data_type, expression);
+                self.memory.kill(pointer);
+                value
+            }
+            Expression::AccessReference { value, .. } => {
+                if let Some(symbol) = self.symbols.resolutions.get(&value.id())
+                    && self.symbols.symbol(*symbol).kind == SymbolKind::Function
+                {
+                    return Ok(self.function_pointer(*symbol));
+                }
+                let (pointer, _) = self.place(value)?;
+                Ok(Value::Pointer(Some(pointer)))
+            }
+            Expression::Grouping { value, .. } => self.value(value),
+            Expression::TypeConversion {
+                value, data_type, ..
+            } => {
+                let value = self.value(value)?;
+                self.convert(value, data_type, expression)
+            }
+            Expression::Prefix { prefix, value, .. } => {
+                let operand = self.value(value)?;
+                match prefix.kind {
+                    TokenKind::Not => {
+                        Ok(Value::Integer(!self.truthy(&operand, expression)? as i128))
+                    }
+                    TokenKind::Plus => Ok(operand),
+                    TokenKind::Minus => {
+                        let data_type = promote(&self.value_type(value)?);
+                        let operand = self.integer(operand, expression)?;
+                        self.checked(-operand, &data_type, expression)
+                    }
+                    TokenKind::BitwiseNot => {
+                        let data_type = promote(&self.value_type(value)?);
+                        let operand = self.integer(operand, expression)?;
+                        Ok(Value::Integer(wrap(!operand, &data_type)))
+                    }
+                    kind => Err(self.error(
+                        format!("unsupported unary operator '{}'", kind.to_str()),
+                        expression,
+                    )),
+                }
+            }
+            Expression::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => match operator.kind {
+                TokenKind::And | TokenKind::Or => {
+                    let left_value = self.value(left)?;
+                    let left_true = self.truthy(&left_value, left)?;
+                    if left_true == (operator.kind == TokenKind::Or) {
+                        return Ok(Value::Integer(left_true as i128));
+                    }
+                    let right_value = self.value(right)?;
+                    Ok(Value::Integer(self.truthy(&right_value, right)? as i128))
+                }
+                kind => {
+                    let left_value = self.value(left)?;
+                    let right_value = self.value(right)?;
+                    self.binary(kind, (left, left_value), (right, right_value), expression)
+                }
+            },
+            Expression::Assignment {
+                target,
+                operator,
+                value,
+                ..
+            } => {
+                if let Expression::VariableDeclaration { .. } = target.as_ref() {
+                    self.declare(target, Some(value), false)?;
+                    return Ok(Value::Void);
+                }
+                let (pointer, data_type) = self.place(target)?;
+                let mut new_value = self.value(value)?;
+                if let Some(kind) = compound_operator(operator.kind) {
+                    let old_value = self.load(pointer, &data_type, target)?;
+                    new_value =
+                        self.binary(kind, (target, old_value), (value, new_value), expression)?;
+                }
+                let new_value = self.convert(new_value, &data_type, expression)?;
+                self.store(pointer, &data_type, &new_value, expression)?;
+                Ok(new_value)
+            }
+            Expression::Increment { target, .. } | Expression::Decrement { target, .. } => {
+                let (pointer, data_type) = self.place(target)?;
+                let old_value = self.load(pointer, &data_type, target)?;
+                let amount = match expression {
+                    Expression::Increment { .. } => 1,
+                    _ => -1,
+                };
+                let new_value = match &old_value {
+                    Value::Pointer(Some(value)) => {
+                        let DataType::Pointer(inside) = &data_type else {
+                            unreachable!("pointer values are only loaded from pointer types")
+                        };
+                        let size = self.size_of(inside, expression)? as i64;
+                        let value = value.offset_by(amount * size);
+                        self.memory
+                            .check_offset(value, &self.debug_data(expression))?;
+                        Value::Pointer(Some(value))
+                    }
+                    Value::Pointer(None) => {
+                        return Err(
+                            self.error("arithmetic on a null pointer".to_owned(), expression)
+                        );
+                    }
+                    Value::Integer(value) => {
+                        if integer_rank(&data_type) == Some(1) {
+                            Value::Integer((value + amount as i128 != 0) as i128)
+                        } else {
+                            // `x++` is `x += 1`, it can only overflow in the promoted type
+                            let common =
+                                usual_arithmetic_conversion(&data_type, &primitive("int", false));
+                            let new_value =
+                                self.checked(value + amount as i128, &common, expression)?;
+                            self.convert(new_value, &data_type, expression)?
+                        }
+                    }
+                    _ => {
+                        return Err(self.error("value can't be incremented".to_owned(), expression));
+                    }
+                };
+                self.store(pointer, &data_type, &new_value, expression)?;
+                Ok(old_value)
+            }
+            Expression::FunctionCall { left, values, .. } => {
+                self.function_call(left, values, expression)
+            }
+            Expression::VariableDeclaration { .. } => {
+                self.declare(expression, None, false)?;
+                Ok(Value::Void)
+            }
+            Expression::DataStructureInitialization { .. } => Err(self.error(
+                "initializer list outside of a declaration".to_owned(),
+                expression,
+            )),
+            _ => match self.exec(expression)? {
+                Flow::Normal => Ok(Value::Void),
+                _ => Err(self.error(
+                    "control flow statement used as a value".to_owned(),
+                    expression,
+                )),
+            },
+        }
+    }
+
+    /// Value stored at an lvalue, arrays decay to a pointer to their first element
+    fn load_place(&mut self, expression: &Expression) -> Result<Value, Diagnostic> {
+        let (pointer, data_type) = self.place(expression)?;
+        self.load(pointer, &data_type, expression)
+    }
+
+    /// Signed overflow is undefined behavior, unsigned math wraps
+    fn checked(
+        &self,
+        value: i128,
+        data_type: &DataType,
+        expression: &Expression,
+    ) -> Result<Value, Diagnostic> {
+        let wrapped = wrap(value, data_type);
+        if wrapped != value && !matches!(data_type, DataType::Data { unsigned: true, .. }) {
+            return Err(self.error(
+                format!("signed integer overflow, {value} doesn't fit in '{data_type}'"),
+                expression,
+            ));
+        }
+        Ok(Value::Integer(wrapped))
+    }
+
+    fn binary(
+        &mut self,
+        operator: TokenKind,
+        (left_expression, left): (&Expression, Value),
+        (right_expression, right): (&Expression, Value),
+        expression: &Expression,
+    ) -> Result<Value, Diagnostic> {
+        let comparison = matches!(
+            operator,
+            TokenKind::Equals
+                | TokenKind::NotEquals
+                | TokenKind::Less
+                | TokenKind::LessEquals
+                | TokenKind::Greater
+                | TokenKind::GreaterEquals
+        );
+        match (left, right) {
+            (Value::Integer(left), Value::Integer(right)) => {
+                let left_type = self.value_type(left_expression)?;
+                let right_type = self.value_type(right_expression)?;
+                let data_type = match operator {
+                    TokenKind::BitwiseShiftLeft | TokenKind::BitwiseShiftRight => {
+                        promote(&left_type)
+                    }
+                    _ => usual_arithmetic_conversion(&left_type, &right_type),
+                };
+                let (left, right) = match operator {
+                    TokenKind::BitwiseShiftLeft | TokenKind::BitwiseShiftRight => {
+                        (wrap(left, &data_type), right)
+                    }
+                    _ => (wrap(left, &data_type), wrap(right, &data_type)),
+                };
+                self.integer_binary(operator, left, right, &data_type, expression)
+            }
+            (Value::Pointer(left), Value::Pointer(right)) if comparison => {
+                self.compare_pointers(operator, left, right, expression)
+            }
+            (Value::Pointer(pointer), Value::Integer(0))
+            | (Value::Integer(0), Value::Pointer(pointer))
+                if comparison =>
+            {
+                self.compare_pointers(operator, pointer, None, expression)
+            }
+            (Value::Pointer(left), Value::Pointer(right)) if operator == TokenKind::Minus => {
+                let (Some(left), Some(right)) = (left, right) else {
+                    return Err(self.error("subtraction of a null pointer".to_owned(), expression));
+                };
+                if left.allocation != right.allocation {
+                    return Err(self.error(
+                        format!(
+                            "subtraction of pointers into different objects, {} and {}",
+                            self.memory.describe(left),
+                            self.memory.describe(right)
+                        ),
+                        expression,
+                    ));
+                }
+                let DataType::Pointer(inside) = self.value_type(left_expression)? else {
+                    return Err(self.error("expected a pointer".to_owned(), expression));
+                };
+                let size = self.size_of(&inside, expression)?.max(1) as i64;
+                Ok(Value::Integer(
+                    ((left.offset - right.offset) / size) as i128,
+                ))
+            }
+            (Value::Pointer(pointer), Value::Integer(amount))
+                if matches!(operator, TokenKind::Plus | TokenKind::Minus) =>
+            {
+                let amount = match operator {
+                    TokenKind::Minus => -amount,
+                    _ => amount,
+                };
+                self.pointer_offset(pointer, left_expression, amount, expression)
+            }
+            (Value::Integer(amount), Value::Pointer(pointer)) if operator == TokenKind::Plus => {
+                self.pointer_offset(pointer, right_expression, amount, expression)
+            }
+            _ => Err(self.error(
+                format!("unsupported operands to '{}'", operator.to_str()),
+                expression,
+            )),
+        }
+    }
+
+    fn pointer_offset(
+        &self,
+        pointer: Option<Pointer>,
+        pointer_expression: &Expression,
+        amount: i128,
+        expression: &Expression,
+    ) -> Result<Value, Diagnostic> {
+        let Some(pointer) = pointer else {
+            if amount == 0 {
+                return Ok(Value::Pointer(None));
+            }
+            return Err(self.error("arithmetic on a null pointer".to_owned(), expression));
+        };
+        let DataType::Pointer(inside) = self.value_type(pointer_expression)? else {
+            return Err(self.error("expected a pointer".to_owned(), expression));
+        };
+        let size = self.size_of(&inside, expression)? as i128;
+        let pointer = pointer.offset_by((amount * size) as i64);
+        self.memory
+            .check_offset(pointer, &self.debug_data(expression))?;
+        Ok(Value::Pointer(Some(pointer)))
+    }
+
+    fn compare_pointers(
+        &self,
+        operator: TokenKind,
+        left: Option<Pointer>,
+        right: Option<Pointer>,
+        expression: &Expression,
+    ) -> Result<Value, Diagnostic> {
+        let result = match operator {
+            TokenKind::Equals => left == right,
+            TokenKind::NotEquals => left != right,
+            _ => {
+                let (Some(left), Some(right)) = (left, right) else {
+                    return Err(self.error(
+                        "relational comparison with a null pointer".to_owned(),
+                        expression,
+                    ));
+                };
+                if left.allocation != right.allocation {
+                    return Err(self.error(
+                        format!(
+                            "relational comparison of pointers into different objects, {} and {}",
+                            self.memory.describe(left),
+                            self.memory.describe(right)
+                        ),
+                        expression,
+                    ));
+                }
+                match operator {
+                    TokenKind::Less => left.offset < right.offset,
+                    TokenKind::LessEquals => left.offset <= right.offset,
+                    TokenKind::Greater => left.offset > right.offset,
+                    _ => left.offset >= right.offset,
+                }
+            }
+        };
+        Ok(Value::Integer(result as i128))
+    }
+
+    fn integer_binary(
+        &self,
+        operator: TokenKind,
+        left: i128,
+        right: i128,
+        data_type: &DataType,
+        expression: &Expression,
+    ) -> Result<Value, Diagnostic> {
+        let bits = self.size_of(data_type, expression)? as i128 * 8;
+        let value = match operator {
+            TokenKind::Plus => left + right,
+            TokenKind::Minus => left - right,
+            TokenKind::Star => left * right,
+            TokenKind::Slash | TokenKind::Percent => {
+                if right == 0 {
+                    return Err(self.error("division by zero".to_owned(), expression));
+                }
+                // both truncate towards zero, like C
+                match operator {
+                    TokenKind::Slash => left / right,
+                    _ => left % right,
+                }
+            }
+            TokenKind::Reference => left & right,
+            TokenKind::BitwiseOr => left | right,
+            TokenKind::BitwiseXor => left ^ right,
+            TokenKind::BitwiseShiftLeft | TokenKind::BitwiseShiftRight => {
+                if right < 0 || right >= bits {
+                    return Err(self.error(
+                        format!("shift by {right} is out of range for '{data_type}'"),
+                        expression,
+                    ));
+                }
+                if operator == TokenKind::BitwiseShiftRight {
+                    left >> right
+                } else if left < 0 {
+                    return Err(self.error(
+                        format!("left shift of the negative value {left}"),
+                        expression,
+                    ));
+                } else {
+                    left << right
+                }
+            }
+            TokenKind::Equals => (left == right) as i128,
+            TokenKind::NotEquals => (left != right) as i128,
+            TokenKind::Less => (left < right) as i128,
+            TokenKind::LessEquals => (left <= right) as i128,
+            TokenKind::Greater => (left > right) as i128,
+            TokenKind::GreaterEquals => (left >= right) as i128,
+            kind => {
+                return Err(self.error(
+                    format!("unsupported binary operator '{}'", kind.to_str()),
+                    expression,
+                ));
+            }
+        };
+        if matches!(
+            operator,
+            TokenKind::Equals
+                | TokenKind::NotEquals
+                | TokenKind::Less
+                | TokenKind::LessEquals
+                | TokenKind::Greater
+                | TokenKind::GreaterEquals
+        ) {
+            return Ok(Value::Integer(value));
+        }
+        self.checked(value, data_type, expression)
+    }
+}
+
+/// Whether the expression has an address, members of anything else are read from a copy
+fn is_place(expression: &Expression) -> bool {
+    match expression {
+        Expression::Identifier(..)
+        | Expression::DataTypeAccess { .. }
+        | Expression::Dereference { .. }
+        | Expression::AccessArray { .. }
+        | Expression::Arrow { .. }
+        | Expression::String(..)
+        | Expression::VariableDeclaration { .. } => true,
+        Expression::MemberExpr { left, .. } | Expression::Grouping { value: left, .. } => {
+            is_place(left)
+        }
+        _ => false,
+    }
+}
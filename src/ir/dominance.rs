@@ -0,0 +1,122 @@
+use super::{BlockId, Function};
+
+/// Dominator tree and dominance frontiers of the reachable blocks of a function, computed with
+/// the algorithm of Cooper, Harvey and Kennedy
+pub struct Dominance {
+    /// immediate dominator of every block, `None` for the entry and unreachable blocks
+    pub immediate: Vec<Option<BlockId>>,
+    /// blocks immediately dominated by every block
+    pub children: Vec<Vec<BlockId>>,
+    /// blocks where the dominance of every block ends
+    pub frontiers: Vec<Vec<BlockId>>,
+    /// reachable blocks in reverse postorder
+    pub order: Vec<BlockId>,
+}
+
+impl Dominance {
+    pub fn new(function: &Function) -> Self {
+        let order = function.reverse_postorder();
+        let predecessors = function.predecessors();
+        let mut position = vec![usize::MAX; function.blocks.len()];
+        for (index, block) in order.iter().enumerate() {
+            position[block.0 as usize] = index;
+        }
+
+        let mut immediate: Vec<Option<BlockId>> = vec![None; function.blocks.len()];
+        immediate[0] = Some(BlockId(0));
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in order.iter().skip(1) {
+                let mut dominator = None;
+                for predecessor in &predecessors[block.0 as usize] {
+                    if immediate[predecessor.0 as usize].is_none() {
+                        continue;
+                    }
+                    dominator = Some(match dominator {
+                        None => *predecessor,
+                        Some(dominator) => {
+                            intersect(&immediate, &position, *predecessor, dominator)
+                        }
+                    });
+                }
+                if dominator.is_some() && immediate[block.0 as usize] != dominator {
+                    immediate[block.0 as usize] = dominator;
+                    changed = true;
+                }
+            }
+        }
+        // the entry was its own dominator only to start the iteration
+        immediate[0] = None;
+
+        let mut children = vec![Vec::new(); function.blocks.len()];
+        for block in &order {
+            if let Some(dominator) = immediate[block.0 as usize] {
+                children[dominator.0 as usize].push(*block);
+            }
+        }
+
+        let mut frontiers: Vec<Vec<BlockId>> = vec![Vec::new(); function.blocks.len()];
+        for block in &order {
+            let reachable = predecessors[block.0 as usize]
+                .iter()
+                .filter(|predecessor| position[predecessor.0 as usize] != usize::MAX)
+                .collect::<Vec<_>>();
+            if reachable.len() < 2 {
+                continue;
+            }
+            for predecessor in reachable {
+                let mut runner = Some(*predecessor);
+                while let Some(current) = runner {
+                    if Some(current) == immediate[block.0 as usize] {
+                        break;
+                    }
+                    if !frontiers[current.0 as usize].contains(block) {
+                        frontiers[current.0 as usize].push(*block);
+                    }
+                    runner = immediate[current.0 as usize];
+                }
+            }
+        }
+
+        Dominance {
+            immediate,
+            children,
+            frontiers,
+            order,
+        }
+    }
+
+    /// Whether every path from the entry to `block` goes through `dominator`
+    pub fn dominates(&self, dominator: BlockId, block: BlockId) -> bool {
+        let mut current = Some(block);
+        while let Some(block) = current {
+            if block == dominator {
+                return true;
+            }
+            current = self.immediate[block.0 as usize];
+        }
+        false
+    }
+
+    pub fn is_reachable(&self, block: BlockId) -> bool {
+        block.0 == 0 || self.immediate[block.0 as usize].is_some()
+    }
+}
+
+fn intersect(
+    immediate: &[Option<BlockId>],
+    position: &[usize],
+    mut left: BlockId,
+    mut right: BlockId,
+) -> BlockId {
+    while left != right {
+        while position[left.0 as usize] > position[right.0 as usize] {
+            left = immediate[left.0 as usize].expect("processed blocks have a dominator");
+        }
+        while position[right.0 as usize] > position[left.0 as usize] {
+            right = immediate[right.0 as usize].expect("processed blocks have a dominator");
+        }
+    }
+    left
+}
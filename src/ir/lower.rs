@@ -0,0 +1,795 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    code_gen::libc_type,
+    ir::{
+        BinaryOperator, Block, BlockId, Condition, Conversion, Declaration, Function, Global,
+        Instruction, Module, Register, Slot, SlotId, Terminator, Type, UnaryOperator, Value,
+        walk::{Program, Walker, defined_functions, is_aggregate, line},
+    },
+    layout::target::Target,
+    lexer::string_bytes,
+    parser::{expression::Expression, types::DataType},
+    semantic::{
+        constant::Evaluator,
+        scope::{SymbolId, SymbolKind, SymbolTable},
+        type_check::{TypeTable, integer_rank, is_void, promote},
+    },
+};
+
+/// Where the storage of a local variable is
+#[derive(Debug, Clone, Copy)]
+enum Local {
+    Slot(SlotId),
+    /// struct parameters, the caller passes a pointer to its own copy
+    Address(Register),
+}
+
+/// Lowers every function of the program to three-address code. Locals live in stack slots and
+/// every use of them is a load or a store, mem2reg turns them into registers later. The program
+/// should have no errors from the earlier passes.
+pub fn lower(
+    expressions: &[Expression],
+    symbols: &SymbolTable,
+    types: &TypeTable,
+    target: &Target,
+) -> Result<Module> {
+    let mut lowering = Lowering {
+        program: Program::new(symbols, types, target, "the IR"),
+        module: Module::default(),
+        globals: HashMap::new(),
+        defined: defined_functions(expressions),
+        strings: 0,
+        function: None,
+    };
+    for expression in expressions {
+        lowering
+            .top_level(expression, true)
+            .with_context(|| format!("lowering to IR at line: {}", line(expression)))?;
+    }
+    Ok(lowering.module)
+}
+
+/// State of the function that is being lowered
+struct FunctionState {
+    function: Function,
+    /// block that instructions are added to, `None` after a jump or a return until the next
+    /// block starts
+    current: Option<BlockId>,
+    locals: HashMap<SymbolId, Local>,
+    /// where `break` jumps to, innermost loop last
+    breaks: Vec<BlockId>,
+    /// C type of the result
+    output: DataType,
+    /// pointer to write a returned struct to
+    result: Option<Register>,
+}
+
+struct Lowering<'a> {
+    program: Program<'a>,
+    module: Module,
+    /// names of globals and static locals
+    globals: HashMap<SymbolId, String>,
+    /// functions with a body somewhere in the program
+    defined: Vec<String>,
+    strings: usize,
+    function: Option<FunctionState>,
+}
+
+impl Lowering<'_> {
+    // -- building --
+
+    fn state(&mut self) -> &mut FunctionState {
+        self.function
+            .as_mut()
+            .expect("instructions are only added inside of a function")
+    }
+
+    fn current(&mut self) -> BlockId {
+        match self.state().current {
+            Some(block) => block,
+            // code after a jump or a return, it is unreachable
+            None => {
+                let block = self.new_block("dead");
+                self.state().current = Some(block);
+                block
+            }
+        }
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        let block = self.current();
+        self.state().function.block_mut(block).terminator = terminator;
+        self.state().current = None;
+    }
+
+    fn emit(&mut self, instruction: Instruction) {
+        let block = self.current();
+        self.state()
+            .function
+            .block_mut(block)
+            .instructions
+            .push(instruction);
+    }
+
+    fn register(&mut self, data_type: Type) -> Register {
+        self.state().function.new_register(data_type)
+    }
+
+    fn slot(&mut self, name: &str, data_type: &DataType) -> Result<SlotId> {
+        let layout = self.program.layout.layout(data_type)?;
+        let slots = &mut self.state().function.slots;
+        slots.push(Slot {
+            name: name.to_owned(),
+            size: layout.size,
+            alignment: layout.alignment,
+        });
+        Ok(SlotId(slots.len() as u32 - 1))
+    }
+
+    fn stack_address(&mut self, slot: SlotId) -> Value {
+        let destination = self.register(Type::Pointer);
+        self.emit(Instruction::StackAddress { destination, slot });
+        Value::Register(destination)
+    }
+
+    fn global_address(&mut self, name: &str) -> Value {
+        let destination = self.register(Type::Pointer);
+        self.emit(Instruction::GlobalAddress {
+            destination,
+            name: name.to_owned(),
+        });
+        Value::Register(destination)
+    }
+
+    fn offset(&mut self, address: Value, offset: i64) -> Value {
+        if offset == 0 {
+            return address;
+        }
+        self.binary_instruction(
+            BinaryOperator::Add,
+            address,
+            Value::Constant(offset, Type::I64),
+            Type::Pointer,
+        )
+    }
+
+    // -- globals --
+
+    fn constant_initializer(
+        &mut self,
+        global: &mut Global,
+        offset: u64,
+        data_type: &DataType,
+        initializer: &Expression,
+    ) -> Result<()> {
+        let resolved = self.program.resolve(data_type);
+        if let Expression::Grouping { value, .. } = initializer {
+            return self.constant_initializer(global, offset, data_type, value);
+        }
+        if let (DataType::Array { length, .. }, Expression::String(value, _)) =
+            (&resolved, initializer)
+        {
+            let mut bytes = string_bytes(value)?;
+            bytes.resize(*length as usize, 0);
+            global.bytes[offset as usize..offset as usize + bytes.len()].copy_from_slice(&bytes);
+            return Ok(());
+        }
+        if let Expression::DataStructureInitialization { values, .. } = initializer {
+            for ((element_offset, element_type), value) in
+                self.elements(&resolved)?.iter().zip(values)
+            {
+                self.constant_initializer(global, offset + element_offset, element_type, value)?;
+            }
+            return Ok(());
+        }
+        if let DataType::Pointer(_) = resolved {
+            let target = match initializer {
+                Expression::String(value, _) => Some(self.string(value)?),
+                Expression::AccessReference { value, .. } => Some(self.global_name(value)?),
+                Expression::Identifier(..)
+                    if matches!(
+                        self.program.object_type(initializer)?,
+                        DataType::Array { .. }
+                    ) =>
+                {
+                    Some(self.global_name(initializer)?)
+                }
+                _ => None,
+            };
+            if let Some(target) = target {
+                global.pointers.push((offset, target));
+                return Ok(());
+            }
+        }
+        let constant = Evaluator::new(self.program.symbols, self.program.target)
+            .evaluate(initializer)
+            .map_err(|diagnostic| anyhow::anyhow!("{diagnostic}"))?;
+        let size = self.program.size_of(&resolved)? as usize;
+        let bytes = constant.value.to_le_bytes();
+        global.bytes[offset as usize..offset as usize + size].copy_from_slice(&bytes[..size]);
+        Ok(())
+    }
+
+    /// Name of the global that the expression refers to
+    fn global_name(&self, expression: &Expression) -> Result<String> {
+        let symbols = self.program.symbols;
+        let id = symbols
+            .resolutions
+            .get(&expression.id())
+            .with_context(|| format!("'{expression:?}' is not a global variable"))?;
+        self.globals
+            .get(id)
+            .cloned()
+            .with_context(|| format!("'{}' is not a global variable", symbols.symbol(*id).name))
+    }
+
+    /// Offsets and types of what an initializer list initializes, in order
+    fn elements(&self, data_type: &DataType) -> Result<Vec<(u64, DataType)>> {
+        Ok(match data_type {
+            DataType::Array { length, inside } => {
+                let size = self.program.size_of(inside)?;
+                (0..*length as u64)
+                    .map(|index| (index * size, inside.as_ref().to_owned()))
+                    .collect()
+            }
+            DataType::Struct { .. } | DataType::Union { .. } => {
+                let layout = self.program.layout.layout(data_type)?;
+                let take = match data_type {
+                    DataType::Union { .. } => 1,
+                    _ => layout.fields.len(),
+                };
+                layout
+                    .fields
+                    .into_iter()
+                    .take(take)
+                    .map(|field| (field.offset, field.data_type))
+                    .collect()
+            }
+            scalar => vec![(0, scalar.to_owned())],
+        })
+    }
+
+    /// Constant global with the bytes of a string literal and its terminating zero
+    fn string(&mut self, value: &str) -> Result<String> {
+        let mut bytes = string_bytes(value)?;
+        bytes.push(0);
+        let name = format!(".str.{}", self.strings);
+        self.strings += 1;
+        self.module.globals.push(Global {
+            name: name.to_owned(),
+            exported: false,
+            constant: true,
+            alignment: 1,
+            bytes,
+            pointers: Vec::new(),
+        });
+        Ok(name)
+    }
+
+    // -- functions --
+
+    /// Adds a declaration for a function without a body the first time it is called
+    fn declare_function(&mut self, name: &str, data_type: &DataType) -> Result<()> {
+        if self.defined.iter().any(|defined| defined == name)
+            || self
+                .module
+                .declarations
+                .iter()
+                .any(|declaration| declaration.name == name)
+        {
+            return Ok(());
+        }
+        let DataType::Function {
+            output,
+            parameters,
+            variadic,
+        } = self.program.resolve(data_type)
+        else {
+            bail!("'{name}' is not a function");
+        };
+        let (mut types, output) = self.signature(&output)?;
+        for parameter in &parameters {
+            types.push(self.program.scalar_type(parameter)?);
+        }
+        self.module.declarations.push(Declaration {
+            name: name.to_owned(),
+            parameters: types,
+            output,
+            variadic,
+        });
+        Ok(())
+    }
+
+    /// IR result type, and the hidden pointer parameter that structs are returned through
+    fn signature(&self, output: &DataType) -> Result<(Vec<Type>, Option<Type>)> {
+        let output = self.program.resolve(output);
+        Ok(if is_void(&output) {
+            (Vec::new(), None)
+        } else if is_aggregate(&output) {
+            (vec![Type::Pointer], None)
+        } else {
+            (Vec::new(), Some(self.program.scalar_type(&output)?))
+        })
+    }
+}
+
+impl<'a> Walker<'a> for Lowering<'a> {
+    type Value = Value;
+    type Block = BlockId;
+
+    fn program(&self) -> &Program<'a> {
+        &self.program
+    }
+
+    // -- building --
+
+    fn new_block(&mut self, _: &str) -> BlockId {
+        let function = &mut self.state().function;
+        function.blocks.push(Block {
+            instructions: Vec::new(),
+            terminator: Terminator::Unreachable,
+        });
+        BlockId(function.blocks.len() as u32 - 1)
+    }
+
+    fn start(&mut self, block: BlockId) {
+        if self.state().current.is_some() {
+            self.terminate(Terminator::Jump(block));
+        }
+        self.state().current = Some(block);
+    }
+
+    fn jump(&mut self, target: BlockId) {
+        if self.state().current.is_some() {
+            self.terminate(Terminator::Jump(target));
+        }
+    }
+
+    fn branch(&mut self, condition: Value, then: BlockId, otherwise: BlockId) {
+        self.terminate(Terminator::Branch {
+            condition,
+            then,
+            otherwise,
+        });
+    }
+
+    fn return_value(&mut self, value: Option<Value>) {
+        self.terminate(Terminator::Return(value));
+    }
+
+    fn breaks(&mut self) -> &mut Vec<BlockId> {
+        &mut self.state().breaks
+    }
+
+    fn constant(&self, value: i64, data_type: Type) -> Value {
+        Value::Constant(value, data_type)
+    }
+
+    fn as_constant(&self, value: &Value) -> Option<i64> {
+        match value {
+            Value::Constant(constant, _) => Some(*constant),
+            Value::Register(_) => None,
+        }
+    }
+
+    fn type_of(&mut self, value: &Value) -> Type {
+        self.state().function.type_of(*value)
+    }
+
+    fn binary_instruction(
+        &mut self,
+        operator: BinaryOperator,
+        left: Value,
+        right: Value,
+        data_type: Type,
+    ) -> Value {
+        let destination = self.register(data_type);
+        self.emit(Instruction::Binary {
+            destination,
+            operator,
+            left,
+            right,
+        });
+        Value::Register(destination)
+    }
+
+    fn unary_instruction(&mut self, operator: UnaryOperator, value: Value) -> Value {
+        let data_type = self.state().function.type_of(value);
+        let destination = self.register(data_type);
+        self.emit(Instruction::Unary {
+            destination,
+            operator,
+            value,
+        });
+        Value::Register(destination)
+    }
+
+    fn compare(&mut self, condition: Condition, left: Value, right: Value) -> Value {
+        let destination = self.register(Type::I1);
+        self.emit(Instruction::Compare {
+            destination,
+            condition,
+            left,
+            right,
+        });
+        Value::Register(destination)
+    }
+
+    fn convert_instruction(&mut self, conversion: Conversion, value: Value, to: Type) -> Value {
+        let destination = self.register(to);
+        self.emit(Instruction::Convert {
+            destination,
+            conversion,
+            value,
+        });
+        Value::Register(destination)
+    }
+
+    fn load(&mut self, address: Value, data_type: &DataType) -> Result<Value> {
+        let data_type = self.program.resolve(data_type);
+        // arrays decay to their address and structs are passed around as it
+        if matches!(data_type, DataType::Array { .. }) || is_aggregate(&data_type) {
+            return Ok(address);
+        }
+        let destination = self.register(self.program.scalar_type(&data_type)?);
+        self.emit(Instruction::Load {
+            destination,
+            address,
+        });
+        Ok(Value::Register(destination))
+    }
+
+    fn store(&mut self, address: Value, value: Value, data_type: &DataType) -> Result<()> {
+        let data_type = self.program.resolve(data_type);
+        if is_aggregate(&data_type) {
+            let size = self.program.size_of(&data_type)?;
+            self.emit(Instruction::CopyMemory {
+                destination: address,
+                source: value,
+                size,
+            });
+        } else {
+            self.emit(Instruction::Store { address, value });
+        }
+        Ok(())
+    }
+
+    // -- storage --
+
+    fn output(&mut self) -> (DataType, Option<Value>) {
+        let state = self.state();
+        (state.output.to_owned(), state.result.map(Value::Register))
+    }
+
+    fn function_name(&mut self) -> String {
+        self.state().function.name.to_owned()
+    }
+
+    fn has_local(&mut self, id: SymbolId) -> bool {
+        self.state().locals.contains_key(&id)
+    }
+
+    /// Stack slot for a local variable
+    fn allocate_local(&mut self, id: SymbolId, name: &str, data_type: &DataType) -> Result<()> {
+        let slot = self.slot(name, data_type)?;
+        self.state().locals.insert(id, Local::Slot(slot));
+        Ok(())
+    }
+
+    fn variable_address(&mut self, id: SymbolId) -> Option<Value> {
+        if let Some(local) = self.state().locals.get(&id).copied() {
+            return Some(match local {
+                Local::Slot(slot) => self.stack_address(slot),
+                Local::Address(register) => Value::Register(register),
+            });
+        }
+        let global = self.globals.get(&id).cloned()?;
+        Some(self.global_address(&global))
+    }
+
+    fn string_address(&mut self, value: &str) -> Result<Value> {
+        let name = self.string(value)?;
+        Ok(self.global_address(&name))
+    }
+
+    fn member_address(
+        &mut self,
+        base: Value,
+        structure: &DataType,
+        member: &Expression,
+    ) -> Result<Value> {
+        let Expression::Identifier(name, _) = member else {
+            bail!("expected a member name, found: {member:?}");
+        };
+        let layout = self.program.layout.layout(structure)?;
+        match layout.field(name) {
+            Some(field) => Ok(self.offset(base, field.offset as i64)),
+            None => bail!("no member named '{name}' in '{structure}'"),
+        }
+    }
+
+    /// `pointer + index` scaled by the size of what it points to
+    fn pointer_offset(
+        &mut self,
+        pointer: Value,
+        index: Value,
+        index_type: &DataType,
+        pointee: &DataType,
+        negate: bool,
+    ) -> Result<Value> {
+        let size = self.program.size_of(pointee)?.max(1) as i64;
+        let index = self.resize(index, Type::I64, self.program.is_signed(index_type));
+        let bytes = match index {
+            Value::Constant(index, _) => Value::Constant(index.wrapping_mul(size), Type::I64),
+            index if size == 1 => index,
+            index => self.binary_instruction(
+                BinaryOperator::Multiply,
+                index,
+                Value::Constant(size, Type::I64),
+                Type::I64,
+            ),
+        };
+        let bytes = match (negate, bytes) {
+            (false, bytes) => bytes,
+            (true, Value::Constant(bytes, _)) => Value::Constant(bytes.wrapping_neg(), Type::I64),
+            (true, bytes) => self.unary_instruction(UnaryOperator::Negate, bytes),
+        };
+        if bytes == Value::Constant(0, Type::I64) {
+            return Ok(pointer);
+        }
+        Ok(self.binary_instruction(BinaryOperator::Add, pointer, bytes, Type::Pointer))
+    }
+
+    fn global(
+        &mut self,
+        declaration: &Expression,
+        initializer: Option<&Expression>,
+        name: String,
+        exported: bool,
+    ) -> Result<()> {
+        let Expression::VariableDeclaration { var_type, .. } = declaration else {
+            bail!("expected a variable declaration, found: {declaration:?}");
+        };
+        let id = self.program.declared(declaration, &name)?;
+        let layout = self.program.layout.layout(var_type)?;
+        let mut global = Global {
+            name: name.to_owned(),
+            exported,
+            constant: false,
+            alignment: layout.alignment,
+            bytes: vec![0; layout.size as usize],
+            pointers: Vec::new(),
+        };
+        if let Some(initializer) = initializer {
+            self.constant_initializer(&mut global, 0, var_type, initializer)
+                .with_context(|| format!("initializer of '{name}'"))?;
+        }
+        self.globals.insert(id, name);
+        self.module.globals.push(global);
+        Ok(())
+    }
+
+    fn function_definition(&mut self, expression: &'a Expression, exported: bool) -> Result<()> {
+        let Expression::Function {
+            name,
+            output,
+            inside,
+            ..
+        } = expression
+        else {
+            bail!("expected a function, found: {expression:?}");
+        };
+        let (hidden, ir_output) = self.signature(output)?;
+        self.function = Some(FunctionState {
+            function: Function {
+                name: name.to_owned(),
+                exported,
+                parameters: Vec::new(),
+                output: ir_output,
+                registers: Vec::new(),
+                slots: Vec::new(),
+                blocks: Vec::new(),
+            },
+            current: None,
+            locals: HashMap::new(),
+            breaks: Vec::new(),
+            output: self.program.resolve(output),
+            result: None,
+        });
+        let entry = self.new_block("entry");
+        self.start(entry);
+
+        if !hidden.is_empty() {
+            let result = self.register(Type::Pointer);
+            self.state().function.parameters.push(result);
+            self.state().result = Some(result);
+        }
+        let symbols = self.program.symbols;
+        let parameters = symbols
+            .parameters
+            .get(&expression.id())
+            .cloned()
+            .unwrap_or_default();
+        let mut registers = Vec::new();
+        for id in &parameters {
+            let data_type = self.program.resolve(&symbols.symbol(*id).data_type);
+            let register = self.register(self.program.scalar_type(&data_type)?);
+            self.state().function.parameters.push(register);
+            registers.push(register);
+        }
+        for (id, register) in parameters.into_iter().zip(registers) {
+            let symbol = symbols.symbol(id);
+            let data_type = self.program.resolve(&symbol.data_type);
+            if is_aggregate(&data_type) {
+                self.state().locals.insert(id, Local::Address(register));
+                continue;
+            }
+            let slot = self.slot(&symbol.name, &data_type)?;
+            self.state().locals.insert(id, Local::Slot(slot));
+            let address = self.stack_address(slot);
+            self.emit(Instruction::Store {
+                address,
+                value: Value::Register(register),
+            });
+        }
+
+        self.statements(inside)?;
+        if self.state().current.is_some() {
+            let terminator = if ir_output.is_none() {
+                Terminator::Return(None)
+            } else if name == "main" {
+                // reaching the end of main returns 0
+                Terminator::Return(Some(Value::Constant(0, ir_output.unwrap_or(Type::I32))))
+            } else {
+                Terminator::Unreachable
+            };
+            self.terminate(terminator);
+        }
+
+        let mut state = self
+            .function
+            .take()
+            .expect("the function was started above");
+        state.function.remove_unreachable_blocks();
+        self.module.functions.push(state.function);
+        Ok(())
+    }
+
+    fn initialize(
+        &mut self,
+        address: Value,
+        data_type: &DataType,
+        initializer: &'a Expression,
+    ) -> Result<()> {
+        let resolved = self.program.resolve(data_type);
+        if let (DataType::Array { length, inside }, Expression::String(value, _)) =
+            (&resolved, initializer)
+            && integer_rank(&self.program.resolve(inside)) == Some(2)
+        {
+            let length = *length as u64;
+            let name = self.string(value)?;
+            let string_length = self
+                .module
+                .global(&name)
+                .map_or(0, |global| global.bytes.len() as u64);
+            if string_length < length {
+                self.emit(Instruction::ZeroMemory {
+                    destination: address,
+                    size: length,
+                });
+            }
+            let source = self.global_address(&name);
+            self.emit(Instruction::CopyMemory {
+                destination: address,
+                source,
+                size: string_length.min(length),
+            });
+            return Ok(());
+        }
+
+        let Expression::DataStructureInitialization { values, .. } = initializer else {
+            let from = self.program.value_type(initializer)?;
+            let value = self.value(initializer)?;
+            let value = self.convert(value, &from, &resolved)?;
+            return self.store(address, value, &resolved);
+        };
+        let size = self.program.size_of(&resolved)?;
+        self.emit(Instruction::ZeroMemory {
+            destination: address,
+            size,
+        });
+        for ((offset, element_type), value) in self.elements(&resolved)?.iter().zip(values) {
+            let element = self.offset(address, *offset as i64);
+            self.initialize(element, element_type, value)?;
+        }
+        Ok(())
+    }
+
+    /// `&&` and `||` as an `int`, the register gets a value on both paths
+    fn logical_value(&mut self, expression: &'a Expression) -> Result<Value> {
+        let result = self.register(Type::I32);
+        let (then, otherwise, end) = (
+            self.new_block("logical.true"),
+            self.new_block("logical.false"),
+            self.new_block("logical.end"),
+        );
+        self.condition(expression, then, otherwise)?;
+        for (block, value) in [(then, 1), (otherwise, 0)] {
+            self.start(block);
+            self.emit(Instruction::Copy {
+                destination: result,
+                value: Value::Constant(value, Type::I32),
+            });
+            self.terminate(Terminator::Jump(end));
+        }
+        self.start(end);
+        Ok(Value::Register(result))
+    }
+
+    fn function_value(&mut self, name: &str) -> Result<Value> {
+        bail!("function pointers are not supported by the IR, '{name}' is one")
+    }
+
+    fn call(&mut self, left: &'a Expression, values: &'a [Expression]) -> Result<Option<Value>> {
+        let Expression::Identifier(name, _) = left else {
+            bail!("calls through function pointers are not supported by the IR");
+        };
+        let function_type = match self.program.symbols.resolution(left) {
+            Some(symbol) if symbol.kind == SymbolKind::Function => symbol.data_type.to_owned(),
+            Some(_) => bail!("calls through function pointers are not supported by the IR"),
+            None => {
+                libc_type(name).with_context(|| format!("call of an unknown function '{name}'"))?
+            }
+        };
+        self.declare_function(name, &function_type)?;
+        let DataType::Function {
+            output, parameters, ..
+        } = self.program.resolve(&function_type)
+        else {
+            bail!("'{name}' is not a function");
+        };
+
+        let output = self.program.resolve(&output);
+        let mut arguments = Vec::new();
+        let result = if is_aggregate(&output) {
+            let slot = self.slot("result", &output)?;
+            let address = self.stack_address(slot);
+            arguments.push(address);
+            Some(address)
+        } else {
+            None
+        };
+        for (index, value) in values.iter().enumerate() {
+            let from = self.program.value_type(value)?;
+            let argument = self.value(value)?;
+            // extra arguments of variadic functions get the default promotions
+            let to = match parameters.get(index) {
+                Some(parameter) => self.program.resolve(parameter),
+                None => promote(&from),
+            };
+            let argument = if is_aggregate(&to) {
+                let slot = self.slot("argument", &to)?;
+                let copy = self.stack_address(slot);
+                self.store(copy, argument, &to)?;
+                copy
+            } else {
+                self.convert(argument, &from, &to)?
+            };
+            arguments.push(argument);
+        }
+
+        let destination = match (&result, is_void(&output)) {
+            (None, false) => Some(self.register(self.program.scalar_type(&output)?)),
+            _ => None,
+        };
+        self.emit(Instruction::Call {
+            destination,
+            function: name.to_owned(),
+            arguments,
+        });
+        Ok(result.or(destination.map(Value::Register)))
+    }
+}
@@ -0,0 +1,966 @@
+pub mod dominance;
+pub mod lower;
+pub mod optimize;
+pub mod verify;
+pub(crate) mod walk;
+
+use std::fmt::{self, Display, Formatter};
+
+/// Type of a register or of a value in memory. Signedness isn't part of the type, it is decided
+/// by the instructions (`sdiv`/`udiv`, `slt`/`ult`, `sext`/`zext`...).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Type {
+    /// result of a comparison, only ever in registers
+    I1,
+    I8,
+    I16,
+    I32,
+    I64,
+    Pointer,
+}
+
+impl Type {
+    /// Integer type with this many bytes
+    pub fn integer(size: u64) -> Option<Self> {
+        Some(match size {
+            1 => Type::I8,
+            2 => Type::I16,
+            4 => Type::I32,
+            8 => Type::I64,
+            _ => return None,
+        })
+    }
+
+    pub fn bits(self) -> u32 {
+        match self {
+            Type::I1 => 1,
+            Type::I8 => 8,
+            Type::I16 => 16,
+            Type::I32 => 32,
+            Type::I64 | Type::Pointer => 64,
+        }
+    }
+
+    /// Size in memory, `i1` is stored as a byte
+    pub fn size(self) -> u64 {
+        self.bits().div_ceil(8) as u64
+    }
+
+    /// Wraps the value to the range of the type, keeping the sign of its highest bit
+    pub fn wrap(self, value: i64) -> i64 {
+        let shift = 64 - self.bits();
+        (value << shift) >> shift
+    }
+
+    /// The value as a constant of the type, `i1` constants are 0 or 1
+    pub fn normalize(self, value: i64) -> i64 {
+        match self {
+            Type::I1 => value & 1,
+            data_type => data_type.wrap(value),
+        }
+    }
+
+    /// The value as an unsigned number of the type
+    pub fn unsigned(self, value: i64) -> u64 {
+        (value as u64) & (u64::MAX >> (64 - self.bits()))
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Type::I1 => "i1",
+            Type::I8 => "i8",
+            Type::I16 => "i16",
+            Type::I32 => "i32",
+            Type::I64 => "i64",
+            Type::Pointer => "ptr",
+        })
+    }
+}
+
+/// Virtual register, there is no limit on how many a function has. Before SSA construction a
+/// register can be assigned in more than one place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Register(pub u32);
+
+impl Display for Register {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "%{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub u32);
+
+impl Display for BlockId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "bb{}", self.0)
+    }
+}
+
+/// Stack memory of a function, locals live in these
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SlotId(pub u32);
+
+impl Display for SlotId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "${}", self.0)
+    }
+}
+
+/// Operand of an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Value {
+    Register(Register),
+    /// already wrapped to the range of the type, `i1` constants are 0 or 1 and the null pointer
+    /// is a `ptr` constant 0
+    Constant(i64, Type),
+}
+
+impl Value {
+    pub fn constant(self) -> Option<i64> {
+        match self {
+            Value::Constant(value, _) => Some(value),
+            Value::Register(_) => None,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Register(register) => register.fmt(f),
+            Value::Constant(0, Type::Pointer) => f.write_str("null"),
+            Value::Constant(value, _) => value.fmt(f),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnaryOperator {
+    Negate,
+    /// bitwise
+    Not,
+}
+
+impl UnaryOperator {
+    pub fn evaluate(self, value: i64, data_type: Type) -> i64 {
+        data_type.normalize(match self {
+            UnaryOperator::Negate => value.wrapping_neg(),
+            UnaryOperator::Not => !value,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinaryOperator {
+    /// also `ptr + i64`, the offset is in bytes
+    Add,
+    Subtract,
+    Multiply,
+    SignedDivide,
+    UnsignedDivide,
+    SignedRemainder,
+    UnsignedRemainder,
+    And,
+    Or,
+    Xor,
+    ShiftLeft,
+    /// fills with zeros
+    LogicalShiftRight,
+    /// fills with the sign bit
+    ArithmeticShiftRight,
+}
+
+impl BinaryOperator {
+    pub fn name(self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "add",
+            BinaryOperator::Subtract => "sub",
+            BinaryOperator::Multiply => "mul",
+            BinaryOperator::SignedDivide => "sdiv",
+            BinaryOperator::UnsignedDivide => "udiv",
+            BinaryOperator::SignedRemainder => "srem",
+            BinaryOperator::UnsignedRemainder => "urem",
+            BinaryOperator::And => "and",
+            BinaryOperator::Or => "or",
+            BinaryOperator::Xor => "xor",
+            BinaryOperator::ShiftLeft => "shl",
+            BinaryOperator::LogicalShiftRight => "lshr",
+            BinaryOperator::ArithmeticShiftRight => "ashr",
+        }
+    }
+
+    /// Result for operands of the type, `None` for a division by zero and for shifts by more
+    /// bits than the type has
+    pub fn evaluate(self, left: i64, right: i64, data_type: Type) -> Option<i64> {
+        let bits = data_type.bits() as i64;
+        let value = match self {
+            BinaryOperator::Add => left.wrapping_add(right),
+            BinaryOperator::Subtract => left.wrapping_sub(right),
+            BinaryOperator::Multiply => left.wrapping_mul(right),
+            BinaryOperator::SignedDivide if right != 0 => left.wrapping_div(right),
+            BinaryOperator::SignedRemainder if right != 0 => left.wrapping_rem(right),
+            BinaryOperator::UnsignedDivide if right != 0 => {
+                (data_type.unsigned(left) / data_type.unsigned(right)) as i64
+            }
+            BinaryOperator::UnsignedRemainder if right != 0 => {
+                (data_type.unsigned(left) % data_type.unsigned(right)) as i64
+            }
+            BinaryOperator::And => left & right,
+            BinaryOperator::Or => left | right,
+            BinaryOperator::Xor => left ^ right,
+            BinaryOperator::ShiftLeft if (0..bits).contains(&right) => left << right,
+            BinaryOperator::LogicalShiftRight if (0..bits).contains(&right) => {
+                (data_type.unsigned(left) >> right) as i64
+            }
+            BinaryOperator::ArithmeticShiftRight if (0..bits).contains(&right) => left >> right,
+            _ => return None,
+        };
+        Some(data_type.normalize(value))
+    }
+
+    pub fn is_commutative(self) -> bool {
+        matches!(
+            self,
+            BinaryOperator::Add
+                | BinaryOperator::Multiply
+                | BinaryOperator::And
+                | BinaryOperator::Or
+                | BinaryOperator::Xor
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Condition {
+    Equal,
+    NotEqual,
+    SignedLess,
+    SignedLessEqual,
+    SignedGreater,
+    SignedGreaterEqual,
+    UnsignedLess,
+    UnsignedLessEqual,
+    UnsignedGreater,
+    UnsignedGreaterEqual,
+}
+
+impl Condition {
+    pub fn name(self) -> &'static str {
+        match self {
+            Condition::Equal => "eq",
+            Condition::NotEqual => "ne",
+            Condition::SignedLess => "slt",
+            Condition::SignedLessEqual => "sle",
+            Condition::SignedGreater => "sgt",
+            Condition::SignedGreaterEqual => "sge",
+            Condition::UnsignedLess => "ult",
+            Condition::UnsignedLessEqual => "ule",
+            Condition::UnsignedGreater => "ugt",
+            Condition::UnsignedGreaterEqual => "uge",
+        }
+    }
+
+    /// The condition that is true when this one is false
+    pub fn inverse(self) -> Self {
+        match self {
+            Condition::Equal => Condition::NotEqual,
+            Condition::NotEqual => Condition::Equal,
+            Condition::SignedLess => Condition::SignedGreaterEqual,
+            Condition::SignedLessEqual => Condition::SignedGreater,
+            Condition::SignedGreater => Condition::SignedLessEqual,
+            Condition::SignedGreaterEqual => Condition::SignedLess,
+            Condition::UnsignedLess => Condition::UnsignedGreaterEqual,
+            Condition::UnsignedLessEqual => Condition::UnsignedGreater,
+            Condition::UnsignedGreater => Condition::UnsignedLessEqual,
+            Condition::UnsignedGreaterEqual => Condition::UnsignedLess,
+        }
+    }
+
+    pub fn evaluate(self, left: i64, right: i64) -> bool {
+        let (unsigned_left, unsigned_right) = (left as u64, right as u64);
+        match self {
+            Condition::Equal => left == right,
+            Condition::NotEqual => left != right,
+            Condition::SignedLess => left < right,
+            Condition::SignedLessEqual => left <= right,
+            Condition::SignedGreater => left > right,
+            Condition::SignedGreaterEqual => left >= right,
+            Condition::UnsignedLess => unsigned_left < unsigned_right,
+            Condition::UnsignedLessEqual => unsigned_left <= unsigned_right,
+            Condition::UnsignedGreater => unsigned_left > unsigned_right,
+            Condition::UnsignedGreaterEqual => unsigned_left >= unsigned_right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Conversion {
+    Truncate,
+    ZeroExtend,
+    SignExtend,
+    PointerToInteger,
+    IntegerToPointer,
+}
+
+impl Conversion {
+    pub fn name(self) -> &'static str {
+        match self {
+            Conversion::Truncate => "trunc",
+            Conversion::ZeroExtend => "zext",
+            Conversion::SignExtend => "sext",
+            Conversion::PointerToInteger => "ptrtoint",
+            Conversion::IntegerToPointer => "inttoptr",
+        }
+    }
+
+    pub fn evaluate(self, value: i64, from: Type, to: Type) -> i64 {
+        to.normalize(match self {
+            Conversion::ZeroExtend => from.unsigned(value) as i64,
+            Conversion::SignExtend if from == Type::I1 => -value,
+            Conversion::Truncate
+            | Conversion::SignExtend
+            | Conversion::PointerToInteger
+            | Conversion::IntegerToPointer => value,
+        })
+    }
+}
+
+/// Three-address instruction, every operand is a register or a constant
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    /// only at the start of a block, with a value for every predecessor
+    Phi {
+        destination: Register,
+        incoming: Vec<(BlockId, Value)>,
+    },
+    Copy {
+        destination: Register,
+        value: Value,
+    },
+    Unary {
+        destination: Register,
+        operator: UnaryOperator,
+        value: Value,
+    },
+    /// both operands have the type of the destination, except for the offset of `ptr + i64`
+    Binary {
+        destination: Register,
+        operator: BinaryOperator,
+        left: Value,
+        right: Value,
+    },
+    /// the destination is an `i1`
+    Compare {
+        destination: Register,
+        condition: Condition,
+        left: Value,
+        right: Value,
+    },
+    Convert {
+        destination: Register,
+        conversion: Conversion,
+        value: Value,
+    },
+    /// reads a value of the type of the destination
+    Load {
+        destination: Register,
+        address: Value,
+    },
+    Store {
+        address: Value,
+        value: Value,
+    },
+    /// address of a stack slot
+    StackAddress {
+        destination: Register,
+        slot: SlotId,
+    },
+    GlobalAddress {
+        destination: Register,
+        name: String,
+    },
+    Call {
+        destination: Option<Register>,
+        function: String,
+        arguments: Vec<Value>,
+    },
+    /// copies `size` bytes, the two areas don't overlap
+    CopyMemory {
+        destination: Value,
+        source: Value,
+        size: u64,
+    },
+    ZeroMemory {
+        destination: Value,
+        size: u64,
+    },
+}
+
+impl Instruction {
+    /// Register that the instruction assigns
+    pub fn destination(&self) -> Option<Register> {
+        match self {
+            Instruction::Phi { destination, .. }
+            | Instruction::Copy { destination, .. }
+            | Instruction::Unary { destination, .. }
+            | Instruction::Binary { destination, .. }
+            | Instruction::Compare { destination, .. }
+            | Instruction::Convert { destination, .. }
+            | Instruction::Load { destination, .. }
+            | Instruction::StackAddress { destination, .. }
+            | Instruction::GlobalAddress { destination, .. } => Some(*destination),
+            Instruction::Call { destination, .. } => *destination,
+            Instruction::Store { .. }
+            | Instruction::CopyMemory { .. }
+            | Instruction::ZeroMemory { .. } => None,
+        }
+    }
+
+    /// Values that the instruction reads
+    pub fn operands(&self) -> Vec<Value> {
+        match self {
+            Instruction::Phi { incoming, .. } => incoming.iter().map(|(_, value)| *value).collect(),
+            Instruction::Copy { value, .. }
+            | Instruction::Unary { value, .. }
+            | Instruction::Convert { value, .. } => vec![*value],
+            Instruction::Binary { left, right, .. } | Instruction::Compare { left, right, .. } => {
+                vec![*left, *right]
+            }
+            Instruction::Load { address, .. } => vec![*address],
+            Instruction::Store { address, value } => vec![*address, *value],
+            Instruction::StackAddress { .. } | Instruction::GlobalAddress { .. } => Vec::new(),
+            Instruction::Call { arguments, .. } => arguments.to_owned(),
+            Instruction::CopyMemory {
+                destination,
+                source,
+                ..
+            } => vec![*destination, *source],
+            Instruction::ZeroMemory { destination, .. } => vec![*destination],
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Value> {
+        match self {
+            Instruction::Phi { incoming, .. } => {
+                incoming.iter_mut().map(|(_, value)| value).collect()
+            }
+            Instruction::Copy { value, .. }
+            | Instruction::Unary { value, .. }
+            | Instruction::Convert { value, .. } => vec![value],
+            Instruction::Binary { left, right, .. } | Instruction::Compare { left, right, .. } => {
+                vec![left, right]
+            }
+            Instruction::Load { address, .. } => vec![address],
+            Instruction::Store { address, value } => vec![address, value],
+            Instruction::StackAddress { .. } | Instruction::GlobalAddress { .. } => Vec::new(),
+            Instruction::Call { arguments, .. } => arguments.iter_mut().collect(),
+            Instruction::CopyMemory {
+                destination,
+                source,
+                ..
+            } => vec![destination, source],
+            Instruction::ZeroMemory { destination, .. } => vec![destination],
+        }
+    }
+
+    /// Whether removing the instruction changes nothing but its destination
+    pub fn is_pure(&self) -> bool {
+        !matches!(
+            self,
+            Instruction::Store { .. }
+                | Instruction::Call { .. }
+                | Instruction::CopyMemory { .. }
+                | Instruction::ZeroMemory { .. }
+        )
+    }
+}
+
+/// Last instruction of a block
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    Jump(BlockId),
+    /// the condition is an `i1`
+    Branch {
+        condition: Value,
+        then: BlockId,
+        otherwise: BlockId,
+    },
+    Return(Option<Value>),
+    /// the end of a function that has to return a value but doesn't
+    Unreachable,
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch {
+                then, otherwise, ..
+            } => vec![*then, *otherwise],
+            Terminator::Return(_) | Terminator::Unreachable => Vec::new(),
+        }
+    }
+
+    pub fn operands(&self) -> Vec<Value> {
+        match self {
+            Terminator::Branch { condition, .. } => vec![*condition],
+            Terminator::Return(Some(value)) => vec![*value],
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Value> {
+        match self {
+            Terminator::Branch { condition, .. } => vec![condition],
+            Terminator::Return(Some(value)) => vec![value],
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn successors_mut(&mut self) -> Vec<&mut BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![target],
+            Terminator::Branch {
+                then, otherwise, ..
+            } => vec![then, otherwise],
+            Terminator::Return(_) | Terminator::Unreachable => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub instructions: Vec<Instruction>,
+    pub terminator: Terminator,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slot {
+    /// variable the slot was made for, or what kind of temporary it is
+    pub name: String,
+    pub size: u64,
+    pub alignment: u64,
+}
+
+/// Function with a body. The first block is the entry. Structs and unions are passed and
+/// returned through pointers: a struct parameter is a pointer to a copy made by the caller, and
+/// a function returning a struct gets a pointer to write it to as its first parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    /// `static` functions aren't visible to other files
+    pub exported: bool,
+    pub parameters: Vec<Register>,
+    /// `None` for `void`
+    pub output: Option<Type>,
+    /// type of every register, indexed by its number
+    pub registers: Vec<Type>,
+    pub slots: Vec<Slot>,
+    pub blocks: Vec<Block>,
+}
+
+impl Function {
+    pub fn new_register(&mut self, data_type: Type) -> Register {
+        self.registers.push(data_type);
+        Register(self.registers.len() as u32 - 1)
+    }
+
+    pub fn register_type(&self, register: Register) -> Type {
+        self.registers[register.0 as usize]
+    }
+
+    pub fn type_of(&self, value: Value) -> Type {
+        match value {
+            Value::Register(register) => self.register_type(register),
+            Value::Constant(_, data_type) => data_type,
+        }
+    }
+
+    pub fn block(&self, id: BlockId) -> &Block {
+        &self.blocks[id.0 as usize]
+    }
+
+    pub fn block_mut(&mut self, id: BlockId) -> &mut Block {
+        &mut self.blocks[id.0 as usize]
+    }
+
+    pub fn block_ids(&self) -> impl Iterator<Item = BlockId> + use<> {
+        (0..self.blocks.len() as u32).map(BlockId)
+    }
+
+    pub fn instruction_count(&self) -> usize {
+        self.blocks
+            .iter()
+            .map(|block| block.instructions.len() + 1)
+            .sum()
+    }
+
+    /// Predecessors of every block, indexed by its number
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut predecessors = vec![Vec::new(); self.blocks.len()];
+        for id in self.block_ids() {
+            for successor in self.block(id).terminator.successors() {
+                if !predecessors[successor.0 as usize].contains(&id) {
+                    predecessors[successor.0 as usize].push(id);
+                }
+            }
+        }
+        predecessors
+    }
+
+    /// Blocks reachable from the entry, in reverse postorder
+    pub fn reverse_postorder(&self) -> Vec<BlockId> {
+        let mut visited = vec![false; self.blocks.len()];
+        let mut order = Vec::new();
+        // (block, whether its successors were pushed already)
+        let mut stack = vec![(BlockId(0), false)];
+        while let Some((block, expanded)) = stack.pop() {
+            if expanded {
+                order.push(block);
+                continue;
+            }
+            if visited[block.0 as usize] {
+                continue;
+            }
+            visited[block.0 as usize] = true;
+            stack.push((block, true));
+            // the last successor is visited first, so the first one comes right after the
+            // block in the order: the body of a loop before the code after it
+            for successor in self.block(block).terminator.successors() {
+                if !visited[successor.0 as usize] {
+                    stack.push((successor, false));
+                }
+            }
+        }
+        order.reverse();
+        order
+    }
+
+    /// Drops blocks that can't be reached from the entry and numbers the rest in reverse
+    /// postorder, so that the entry stays first and blocks come before the ones they jump to,
+    /// except for loops
+    pub fn remove_unreachable_blocks(&mut self) {
+        let reachable = self.reverse_postorder();
+        let mut numbers = vec![None; self.blocks.len()];
+        for (number, block) in reachable.iter().enumerate() {
+            numbers[block.0 as usize] = Some(BlockId(number as u32));
+        }
+        let mut blocks = Vec::new();
+        for id in reachable {
+            let mut block = self.block(id).to_owned();
+            for target in block.terminator.successors_mut() {
+                *target = numbers[target.0 as usize]
+                    .expect("successors of reachable blocks are reachable");
+            }
+            for instruction in &mut block.instructions {
+                if let Instruction::Phi { incoming, .. } = instruction {
+                    incoming.retain_mut(|(predecessor, _)| {
+                        numbers[predecessor.0 as usize]
+                            .map(|number| *predecessor = number)
+                            .is_some()
+                    });
+                }
+            }
+            blocks.push(block);
+        }
+        self.blocks = blocks;
+    }
+}
+
+/// Function that is called but defined somewhere else, like `printf`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration {
+    pub name: String,
+    pub parameters: Vec<Type>,
+    pub output: Option<Type>,
+    pub variadic: bool,
+}
+
+/// Global variable, static local or string literal
+#[derive(Debug, Clone, PartialEq)]
+pub struct Global {
+    pub name: String,
+    pub exported: bool,
+    /// string literals can't be written to
+    pub constant: bool,
+    pub alignment: u64,
+    /// initial value of every byte, the global is as big as this
+    pub bytes: Vec<u8>,
+    /// addresses of other globals stored at an offset of the bytes, as 8 byte pointers
+    pub pointers: Vec<(u64, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Module {
+    pub declarations: Vec<Declaration>,
+    pub globals: Vec<Global>,
+    pub functions: Vec<Function>,
+}
+
+impl Module {
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+
+    pub fn global(&self, name: &str) -> Option<&Global> {
+        self.globals.iter().find(|global| global.name == name)
+    }
+}
+
+fn output_type(output: Option<Type>) -> String {
+    match output {
+        Some(output) => output.to_string(),
+        None => "void".to_owned(),
+    }
+}
+
+impl Display for Module {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        let mut separate = |f: &mut Formatter<'_>| {
+            if !std::mem::replace(&mut first, false) {
+                writeln!(f)?;
+            }
+            Ok(())
+        };
+        if !self.declarations.is_empty() {
+            separate(f)?;
+            for declaration in &self.declarations {
+                writeln!(f, "{declaration}")?;
+            }
+        }
+        if !self.globals.is_empty() {
+            separate(f)?;
+            for global in &self.globals {
+                writeln!(f, "{global}")?;
+            }
+        }
+        for function in &self.functions {
+            separate(f)?;
+            write!(f, "{function}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Declaration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut parameters = self
+            .parameters
+            .iter()
+            .map(Type::to_string)
+            .collect::<Vec<_>>();
+        if self.variadic {
+            parameters.push("...".to_owned());
+        }
+        write!(
+            f,
+            "declare {} @{}({})",
+            output_type(self.output),
+            self.name,
+            parameters.join(", ")
+        )
+    }
+}
+
+impl Display for Global {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let keyword = if self.constant { "constant" } else { "global" };
+        let visibility = if self.exported { "" } else { "internal " };
+        write!(
+            f,
+            "{visibility}{keyword} @{}: size {}, align {} =",
+            self.name,
+            self.bytes.len(),
+            self.alignment
+        )?;
+        if self.constant {
+            write!(f, " \"{}\"", self.bytes.escape_ascii())?;
+        } else if self.bytes.iter().all(|byte| *byte == 0) {
+            write!(f, " zeroed")?;
+        } else {
+            for byte in &self.bytes {
+                write!(f, " {byte:02x}")?;
+            }
+        }
+        for (offset, name) in &self.pointers {
+            write!(f, ", @{name} at {offset}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let parameters = self
+            .parameters
+            .iter()
+            .map(|parameter| format!("{} {parameter}", self.register_type(*parameter)))
+            .collect::<Vec<_>>();
+        let visibility = if self.exported { "" } else { "internal " };
+        writeln!(
+            f,
+            "{visibility}function {} @{}({}) {{",
+            output_type(self.output),
+            self.name,
+            parameters.join(", ")
+        )?;
+        for (id, slot) in self.slots.iter().enumerate() {
+            writeln!(
+                f,
+                "  {}: {}, size {}, align {}",
+                SlotId(id as u32),
+                slot.name,
+                slot.size,
+                slot.alignment
+            )?;
+        }
+        for id in self.block_ids() {
+            let block = self.block(id);
+            writeln!(f, "{id}:")?;
+            for instruction in &block.instructions {
+                writeln!(f, "  {}", self.instruction(instruction))?;
+            }
+            writeln!(f, "  {}", self.terminator(&block.terminator))?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+impl Function {
+    /// Operand with its type in front of it
+    fn typed(&self, value: Value) -> String {
+        format!("{} {value}", self.type_of(value))
+    }
+
+    /// One line of the textual dump
+    pub fn instruction(&self, instruction: &Instruction) -> String {
+        match instruction {
+            Instruction::Phi {
+                destination,
+                incoming,
+            } => {
+                let incoming = incoming
+                    .iter()
+                    .map(|(block, value)| format!("[{block}: {value}]"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!(
+                    "{destination} = phi {} {incoming}",
+                    self.register_type(*destination)
+                )
+            }
+            Instruction::Copy { destination, value } => {
+                format!("{destination} = copy {}", self.typed(*value))
+            }
+            Instruction::Unary {
+                destination,
+                operator,
+                value,
+            } => {
+                let name = match operator {
+                    UnaryOperator::Negate => "neg",
+                    UnaryOperator::Not => "not",
+                };
+                format!("{destination} = {name} {}", self.typed(*value))
+            }
+            Instruction::Binary {
+                destination,
+                operator,
+                left,
+                right,
+            } => {
+                // only the offset of `ptr + i64` has a type of its own
+                let right = if self.type_of(*left) == self.type_of(*right) {
+                    right.to_string()
+                } else {
+                    self.typed(*right)
+                };
+                format!(
+                    "{destination} = {} {}, {right}",
+                    operator.name(),
+                    self.typed(*left)
+                )
+            }
+            Instruction::Compare {
+                destination,
+                condition,
+                left,
+                right,
+            } => format!(
+                "{destination} = cmp {} {}, {right}",
+                condition.name(),
+                self.typed(*left)
+            ),
+            Instruction::Convert {
+                destination,
+                conversion,
+                value,
+            } => format!(
+                "{destination} = {} {} to {}",
+                conversion.name(),
+                self.typed(*value),
+                self.register_type(*destination)
+            ),
+            Instruction::Load {
+                destination,
+                address,
+            } => format!(
+                "{destination} = load {}, {address}",
+                self.register_type(*destination)
+            ),
+            Instruction::Store { address, value } => {
+                format!("store {}, {address}", self.typed(*value))
+            }
+            Instruction::StackAddress { destination, slot } => {
+                format!("{destination} = stack {slot}")
+            }
+            Instruction::GlobalAddress { destination, name } => {
+                format!("{destination} = global @{name}")
+            }
+            Instruction::Call {
+                destination,
+                function,
+                arguments,
+            } => {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.typed(*argument))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                match destination {
+                    Some(destination) => format!(
+                        "{destination} = call {} @{function}({arguments})",
+                        self.register_type(*destination)
+                    ),
+                    None => format!("call void @{function}({arguments})"),
+                }
+            }
+            Instruction::CopyMemory {
+                destination,
+                source,
+                size,
+            } => format!("copymem {destination}, {source}, {size}"),
+            Instruction::ZeroMemory { destination, size } => {
+                format!("zeromem {destination}, {size}")
+            }
+        }
+    }
+
+    pub fn terminator(&self, terminator: &Terminator) -> String {
+        match terminator {
+            Terminator::Jump(target) => format!("jump {target}"),
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => format!("branch {condition}, {then}, {otherwise}"),
+            Terminator::Return(Some(value)) => format!("return {}", self.typed(*value)),
+            Terminator::Return(None) => "return".to_owned(),
+            Terminator::Unreachable => "unreachable".to_owned(),
+        }
+    }
+}
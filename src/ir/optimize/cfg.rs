@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use super::{jump, replace_uses};
+use crate::ir::{Block, Function, Instruction, Terminator, Value};
+
+/// Simplifies the control flow graph until nothing changes: branches to the same block or on a
+/// constant become jumps, blocks that only jump somewhere else are skipped, a block is merged
+/// with the one it jumps to when it is the only way there, and unreachable blocks are removed
+pub fn run(function: &mut Function) {
+    loop {
+        function.remove_unreachable_blocks();
+        let changed =
+            fold_branches(function) || skip_empty_blocks(function) || merge_blocks(function);
+        if !changed {
+            break;
+        }
+    }
+}
+
+fn fold_branches(function: &mut Function) -> bool {
+    let mut changed = false;
+    for id in function.block_ids() {
+        if let Terminator::Branch {
+            condition,
+            then,
+            otherwise,
+        } = function.block(id).terminator
+        {
+            let target = match condition {
+                _ if then == otherwise => then,
+                Value::Constant(0, _) => otherwise,
+                Value::Constant(..) => then,
+                Value::Register(_) => continue,
+            };
+            jump(function, id, target);
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Predecessors of a block without instructions that only jumps to another one go straight
+/// there instead. A predecessor that already goes to the target can't if the target has phis,
+/// because it would need two values in them.
+fn skip_empty_blocks(function: &mut Function) -> bool {
+    let predecessors = function.predecessors();
+    for id in function.block_ids().skip(1) {
+        let block = function.block(id);
+        let Terminator::Jump(target) = block.terminator else {
+            continue;
+        };
+        if !block.instructions.is_empty() || target == id {
+            continue;
+        }
+        let has_phis = matches!(
+            function.block(target).instructions.first(),
+            Some(Instruction::Phi { .. })
+        );
+        let mut changed = false;
+        for predecessor in &predecessors[id.0 as usize] {
+            if has_phis
+                && function
+                    .block(*predecessor)
+                    .terminator
+                    .successors()
+                    .contains(&target)
+            {
+                continue;
+            }
+            for successor in function.block_mut(*predecessor).terminator.successors_mut() {
+                if *successor == id {
+                    *successor = target;
+                }
+            }
+            for instruction in &mut function.block_mut(target).instructions {
+                if let Instruction::Phi { incoming, .. } = instruction
+                    && let Some(value) = incoming
+                        .iter()
+                        .find(|(block, _)| *block == id)
+                        .map(|(_, value)| *value)
+                {
+                    incoming.push((*predecessor, value));
+                }
+            }
+            changed = true;
+        }
+        // the entries of the skipped block in the phis go when it becomes unreachable
+        if changed {
+            return true;
+        }
+    }
+    false
+}
+
+/// A block that jumps to a block only it goes to is merged with it
+fn merge_blocks(function: &mut Function) -> bool {
+    let predecessors = function.predecessors();
+    for id in function.block_ids() {
+        let Terminator::Jump(target) = function.block(id).terminator else {
+            continue;
+        };
+        if target == id || target.0 == 0 || predecessors[target.0 as usize].len() != 1 {
+            continue;
+        }
+
+        let merged = std::mem::replace(
+            function.block_mut(target),
+            Block {
+                instructions: Vec::new(),
+                terminator: Terminator::Unreachable,
+            },
+        );
+        let mut replacements = HashMap::new();
+        let mut instructions = Vec::new();
+        for instruction in merged.instructions {
+            match instruction {
+                Instruction::Phi {
+                    destination,
+                    incoming,
+                } => {
+                    replacements.insert(destination, incoming[0].1);
+                }
+                instruction => instructions.push(instruction),
+            }
+        }
+        for successor in merged.terminator.successors() {
+            for instruction in &mut function.block_mut(successor).instructions {
+                if let Instruction::Phi { incoming, .. } = instruction {
+                    for (block, _) in incoming {
+                        if *block == target {
+                            *block = id;
+                        }
+                    }
+                }
+            }
+        }
+        let block = function.block_mut(id);
+        block.instructions.extend(instructions);
+        block.terminator = merged.terminator;
+        replace_uses(function, &replacements);
+        return true;
+    }
+    false
+}
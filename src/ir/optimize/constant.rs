@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use super::{definition_counts, jump, replace_uses};
+use crate::ir::{BinaryOperator, Function, Instruction, Terminator, Type, Value};
+
+/// Folds instructions until nothing changes: an instruction whose operands are constants is
+/// replaced by its result everywhere, and so is one that gives one of its operands back, like
+/// `x + 0`. Branches on constants become jumps.
+pub fn run(function: &mut Function) {
+    loop {
+        let definitions = definition_counts(function);
+        // a register assigned more than once can't stand in for another one
+        let available = |value: &Value| match value {
+            Value::Register(register) => definitions[register.0 as usize] == 1,
+            Value::Constant(..) => true,
+        };
+        let registers = &function.registers;
+        let mut replacements = HashMap::new();
+        for block in &mut function.blocks {
+            block.instructions.retain(|instruction| {
+                let Some(destination) = instruction.destination() else {
+                    return true;
+                };
+                if definitions[destination.0 as usize] != 1 {
+                    return true;
+                }
+                let folded = match instruction {
+                    Instruction::Phi { incoming, .. } => {
+                        let mut values = incoming.iter().map(|(_, value)| *value);
+                        values
+                            .next()
+                            .filter(|first| first.constant().is_some())
+                            .filter(|first| values.all(|value| value == *first))
+                    }
+                    instruction => fold(registers, instruction).filter(available),
+                };
+                match folded {
+                    Some(value) => {
+                        replacements.insert(destination, value);
+                        false
+                    }
+                    None => true,
+                }
+            });
+        }
+
+        let mut branches = Vec::new();
+        for id in function.block_ids() {
+            if let Terminator::Branch {
+                condition: Value::Constant(condition, _),
+                then,
+                otherwise,
+            } = function.block(id).terminator
+            {
+                branches.push((id, if condition != 0 { then } else { otherwise }));
+            }
+        }
+        if replacements.is_empty() && branches.is_empty() {
+            break;
+        }
+        replace_uses(function, &replacements);
+        for (block, target) in branches {
+            jump(function, block, target);
+        }
+    }
+}
+
+fn type_of(registers: &[Type], value: Value) -> Type {
+    match value {
+        Value::Register(register) => registers[register.0 as usize],
+        Value::Constant(_, data_type) => data_type,
+    }
+}
+
+/// Constant of the type, `i1` constants are 0 or 1
+fn constant(value: i64, data_type: Type) -> Value {
+    Value::Constant(data_type.normalize(value), data_type)
+}
+
+/// Result of the instruction if it can be known without running it
+fn fold(registers: &[Type], instruction: &Instruction) -> Option<Value> {
+    match instruction {
+        Instruction::Copy { value, .. } => value.constant().map(|_| *value),
+        Instruction::Unary {
+            destination,
+            operator,
+            value,
+        } => {
+            let data_type = registers[destination.0 as usize];
+            let value = value.constant()?;
+            Some(constant(operator.evaluate(value, data_type), data_type))
+        }
+        Instruction::Binary {
+            destination,
+            operator,
+            left,
+            right,
+        } => {
+            let data_type = registers[destination.0 as usize];
+            match (left.constant(), right.constant()) {
+                (Some(left), Some(right)) => {
+                    // division by zero is left for the program to trip over
+                    let value = operator.evaluate(left, right, data_type)?;
+                    Some(constant(value, data_type))
+                }
+                (_, Some(right)) => identity(*operator, *left, right, data_type),
+                (Some(left), _) if operator.is_commutative() => {
+                    identity(*operator, *right, left, data_type)
+                }
+                _ => None,
+            }
+        }
+        Instruction::Compare {
+            condition,
+            left,
+            right,
+            ..
+        } => {
+            let (left, right) = (left.constant()?, right.constant()?);
+            Some(constant(condition.evaluate(left, right) as i64, Type::I1))
+        }
+        Instruction::Convert {
+            destination,
+            conversion,
+            value,
+        } => {
+            let (from, to) = (
+                type_of(registers, *value),
+                registers[destination.0 as usize],
+            );
+            let value = value.constant()?;
+            Some(constant(conversion.evaluate(value, from, to), to))
+        }
+        _ => None,
+    }
+}
+
+/// `x + 0`, `x * 1`, `x * 0` and the like
+fn identity(
+    operator: BinaryOperator,
+    value: Value,
+    constant: i64,
+    data_type: Type,
+) -> Option<Value> {
+    let all_ones = match data_type {
+        Type::I1 => 1,
+        data_type => data_type.wrap(-1),
+    };
+    match (operator, constant) {
+        (
+            BinaryOperator::Add
+            | BinaryOperator::Subtract
+            | BinaryOperator::Or
+            | BinaryOperator::Xor
+            | BinaryOperator::ShiftLeft
+            | BinaryOperator::LogicalShiftRight
+            | BinaryOperator::ArithmeticShiftRight,
+            0,
+        )
+        | (
+            BinaryOperator::Multiply
+            | BinaryOperator::SignedDivide
+            | BinaryOperator::UnsignedDivide,
+            1,
+        ) => Some(value),
+        (BinaryOperator::Multiply | BinaryOperator::And, 0) => Some(Value::Constant(0, data_type)),
+        (BinaryOperator::And, constant) if constant == all_ones => Some(value),
+        _ => None,
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+
+use super::{definition_counts, replace_uses, replacement};
+use crate::ir::{Function, Instruction, Value};
+
+/// Replaces the destination of `copy` instructions by the copied value, and phis that merge
+/// the same value from every predecessor by that value
+pub fn run(function: &mut Function) {
+    loop {
+        let definitions = definition_counts(function);
+        let once = |value: Value| match value {
+            Value::Register(register) => definitions[register.0 as usize] == 1,
+            Value::Constant(..) => true,
+        };
+        let mut replacements = HashMap::new();
+        for block in &mut function.blocks {
+            block.instructions.retain(|instruction| {
+                let copied = match instruction {
+                    Instruction::Copy { destination, value } => Some((*destination, *value)),
+                    Instruction::Phi {
+                        destination,
+                        incoming,
+                    } => {
+                        // the phi itself comes in from the back edges of loops
+                        let mut values = incoming
+                            .iter()
+                            .map(|(_, value)| *value)
+                            .filter(|value| *value != Value::Register(*destination));
+                        values
+                            .next()
+                            .filter(|first| values.all(|value| value == *first))
+                            .map(|value| (*destination, value))
+                    }
+                    _ => None,
+                };
+                // phis of unreachable loops can end up copying each other
+                let copied = copied
+                    .map(|(destination, value)| (destination, replacement(&replacements, value)));
+                match copied {
+                    Some((destination, value))
+                        if once(Value::Register(destination))
+                            && once(value)
+                            && value != Value::Register(destination) =>
+                    {
+                        replacements.insert(destination, value);
+                        false
+                    }
+                    _ => true,
+                }
+            });
+        }
+        if replacements.is_empty() {
+            break;
+        }
+        replace_uses(function, &replacements);
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use super::{definition_counts, replace_uses, replacement};
+use crate::ir::dominance::Dominance;
+use crate::ir::{
+    BinaryOperator, BlockId, Condition, Conversion, Function, Instruction, Register, SlotId, Type,
+    UnaryOperator, Value,
+};
+
+/// What an instruction computes, two instructions with the same expression give the same value
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Expression {
+    Unary(UnaryOperator, Value, Type),
+    Binary(BinaryOperator, Value, Value, Type),
+    Compare(Condition, Value, Value),
+    Convert(Conversion, Value, Type),
+    StackAddress(SlotId),
+    GlobalAddress(String),
+}
+
+/// Walks the dominator tree keeping the expressions computed by the dominators of the current
+/// block, an instruction that computes one of them again is replaced by the earlier result.
+/// Loads aren't reused since a store could come in between.
+pub fn run(function: &mut Function) {
+    let dominance = Dominance::new(function);
+    let mut elimination = Elimination {
+        definitions: definition_counts(function),
+        available: HashMap::new(),
+        replacements: HashMap::new(),
+    };
+    elimination.visit(function, &dominance, BlockId(0));
+    replace_uses(function, &elimination.replacements);
+}
+
+struct Elimination {
+    definitions: Vec<usize>,
+    available: HashMap<Expression, Register>,
+    replacements: HashMap<Register, Value>,
+}
+
+impl Elimination {
+    fn visit(&mut self, function: &mut Function, dominance: &Dominance, id: BlockId) {
+        let mut added = Vec::new();
+        let mut instructions = std::mem::take(&mut function.block_mut(id).instructions);
+        instructions.retain_mut(|instruction| {
+            if !matches!(instruction, Instruction::Phi { .. }) {
+                for operand in instruction.operands_mut() {
+                    *operand = replacement(&self.replacements, *operand);
+                }
+            }
+            let Some(destination) = instruction.destination() else {
+                return true;
+            };
+            let Some(expression) = self.expression(function, instruction) else {
+                return true;
+            };
+            match self.available.get(&expression) {
+                Some(register) => {
+                    self.replacements
+                        .insert(destination, Value::Register(*register));
+                    false
+                }
+                None => {
+                    self.available.insert(expression.to_owned(), destination);
+                    added.push(expression);
+                    true
+                }
+            }
+        });
+        function.block_mut(id).instructions = instructions;
+
+        for child in dominance.children[id.0 as usize].iter().copied() {
+            self.visit(function, dominance, child);
+        }
+        for expression in added {
+            self.available.remove(&expression);
+        }
+    }
+
+    fn expression(&self, function: &Function, instruction: &Instruction) -> Option<Expression> {
+        let once = |register: Register| self.definitions[register.0 as usize] == 1;
+        if !instruction.destination().is_some_and(once)
+            || !instruction.operands().iter().all(|operand| match operand {
+                Value::Register(register) => once(*register),
+                Value::Constant(..) => true,
+            })
+        {
+            return None;
+        }
+        let data_type = function.register_type(instruction.destination()?);
+        Some(match instruction {
+            Instruction::Unary {
+                operator, value, ..
+            } => Expression::Unary(*operator, *value, data_type),
+            Instruction::Binary {
+                operator,
+                left,
+                right,
+                ..
+            } => {
+                let (mut left, mut right) = (*left, *right);
+                if operator.is_commutative() && order(left) > order(right) {
+                    (left, right) = (right, left);
+                }
+                Expression::Binary(*operator, left, right, data_type)
+            }
+            Instruction::Compare {
+                condition,
+                left,
+                right,
+                ..
+            } => Expression::Compare(*condition, *left, *right),
+            Instruction::Convert {
+                conversion, value, ..
+            } => Expression::Convert(*conversion, *value, data_type),
+            Instruction::StackAddress { slot, .. } => Expression::StackAddress(*slot),
+            Instruction::GlobalAddress { name, .. } => Expression::GlobalAddress(name.to_owned()),
+            _ => return None,
+        })
+    }
+}
+
+/// Registers first, then constants, so that `1 + %0` and `%0 + 1` are the same expression
+fn order(value: Value) -> (bool, i64) {
+    match value {
+        Value::Register(register) => (false, register.0 as i64),
+        Value::Constant(constant, _) => (true, constant),
+    }
+}
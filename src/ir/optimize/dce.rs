@@ -0,0 +1,52 @@
+use super::remove_unused_slots;
+use crate::ir::{Function, Value};
+
+/// Marks the registers that instructions with side effects and terminators need, directly or
+/// through other instructions, and removes the pure instructions that assign anything else.
+/// Loops of phis that only feed each other go away too. Slots that are no longer used are
+/// dropped afterwards.
+pub fn run(function: &mut Function) {
+    let mut definitions = vec![Vec::new(); function.registers.len()];
+    let mut live = vec![false; function.registers.len()];
+    let mut worklist = Vec::new();
+    let mark = |value: Value, live: &mut Vec<bool>, worklist: &mut Vec<_>| {
+        if let Value::Register(register) = value
+            && !std::mem::replace(&mut live[register.0 as usize], true)
+        {
+            worklist.push(register);
+        }
+    };
+
+    for block in &function.blocks {
+        for instruction in &block.instructions {
+            if let Some(destination) = instruction.destination() {
+                definitions[destination.0 as usize].push(instruction);
+            }
+            if !instruction.is_pure() {
+                for operand in instruction.operands() {
+                    mark(operand, &mut live, &mut worklist);
+                }
+            }
+        }
+        for operand in block.terminator.operands() {
+            mark(operand, &mut live, &mut worklist);
+        }
+    }
+    while let Some(register) = worklist.pop() {
+        for instruction in &definitions[register.0 as usize] {
+            for operand in instruction.operands() {
+                mark(operand, &mut live, &mut worklist);
+            }
+        }
+    }
+
+    for block in &mut function.blocks {
+        block.instructions.retain(|instruction| {
+            !instruction.is_pure()
+                || instruction
+                    .destination()
+                    .is_some_and(|destination| live[destination.0 as usize])
+        });
+    }
+    remove_unused_slots(function);
+}
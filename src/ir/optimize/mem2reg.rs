@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+
+use super::{definition_counts, remove_unused_slots, replace_uses, replacement};
+use crate::ir::dominance::Dominance;
+use crate::ir::{BlockId, Function, Instruction, Register, SlotId, Type, Value};
+
+/// Variables get a new register for every assignment. They are the slots that are only loaded
+/// and stored whole, and the registers assigned in more than one place.
+struct Promotion {
+    /// type of every variable
+    variables: Vec<Type>,
+    slots: HashMap<SlotId, usize>,
+    registers: HashMap<Register, usize>,
+    /// addresses of promoted slots
+    addresses: HashMap<Register, usize>,
+    /// phis that were inserted, by their destination
+    phis: HashMap<Register, usize>,
+    /// loads replaced by the value that was stored last
+    replacements: HashMap<Register, Value>,
+    /// values of every variable along the current path of the dominator tree
+    stacks: Vec<Vec<Value>>,
+}
+
+/// Builds SSA form: phis are inserted at the iterated dominance frontiers of the assignments of
+/// every variable, then the variables are renamed walking the dominator tree
+pub fn run(function: &mut Function) {
+    function.remove_unreachable_blocks();
+    let mut promotion = Promotion::new(function);
+    if promotion.variables.is_empty() {
+        return;
+    }
+    let dominance = Dominance::new(function);
+    promotion.insert_phis(function, &dominance);
+    for parameter in &function.parameters {
+        if let Some(variable) = promotion.registers.get(parameter) {
+            promotion.stacks[*variable].push(Value::Register(*parameter));
+        }
+    }
+    promotion.rename(function, &dominance, BlockId(0));
+    replace_uses(function, &promotion.replacements);
+    remove_unused_slots(function);
+}
+
+impl Promotion {
+    fn new(function: &Function) -> Self {
+        let mut promotion = Promotion {
+            variables: Vec::new(),
+            slots: HashMap::new(),
+            registers: HashMap::new(),
+            addresses: HashMap::new(),
+            phis: HashMap::new(),
+            replacements: HashMap::new(),
+            stacks: Vec::new(),
+        };
+
+        for (register, count) in definition_counts(function).into_iter().enumerate() {
+            if count > 1 {
+                let register = Register(register as u32);
+                promotion
+                    .registers
+                    .insert(register, promotion.variables.len());
+                promotion.variables.push(function.register_type(register));
+            }
+        }
+
+        // a slot can be promoted if every use of its address loads or stores the whole slot
+        // with the same type
+        let mut addresses = HashMap::new();
+        for block in &function.blocks {
+            for instruction in &block.instructions {
+                if let Instruction::StackAddress { destination, slot } = instruction {
+                    addresses.insert(*destination, *slot);
+                }
+            }
+        }
+        let mut types: HashMap<SlotId, Option<Type>> = HashMap::new();
+        let mut access = |slot: SlotId, data_type: Option<Type>| {
+            let data_type = data_type
+                .filter(|data_type| data_type.size() == function.slots[slot.0 as usize].size);
+            let entry = types.entry(slot).or_insert(data_type);
+            if *entry != data_type {
+                *entry = None;
+            }
+        };
+        let slot_of = |value: &Value| match value {
+            Value::Register(register) => addresses.get(register).copied(),
+            Value::Constant(..) => None,
+        };
+        for block in &function.blocks {
+            for instruction in &block.instructions {
+                match instruction {
+                    Instruction::Load {
+                        destination,
+                        address,
+                    } if slot_of(address).is_some() => {
+                        access(
+                            slot_of(address).unwrap(),
+                            Some(function.register_type(*destination)),
+                        );
+                    }
+                    Instruction::Store { address, value } if slot_of(address).is_some() => {
+                        access(slot_of(address).unwrap(), Some(function.type_of(*value)));
+                        if let Some(slot) = slot_of(value) {
+                            access(slot, None);
+                        }
+                    }
+                    instruction => {
+                        for operand in instruction.operands() {
+                            if let Some(slot) = slot_of(&operand) {
+                                access(slot, None);
+                            }
+                        }
+                    }
+                }
+            }
+            for operand in block.terminator.operands() {
+                if let Some(slot) = slot_of(&operand) {
+                    access(slot, None);
+                }
+            }
+        }
+
+        let mut promoted = types
+            .into_iter()
+            .filter_map(|(slot, data_type)| Some((slot, data_type?)))
+            .collect::<Vec<_>>();
+        promoted.sort_by_key(|(slot, _)| *slot);
+        for (slot, data_type) in promoted {
+            promotion.slots.insert(slot, promotion.variables.len());
+            promotion.variables.push(data_type);
+        }
+        for (address, slot) in addresses {
+            if let Some(variable) = promotion.slots.get(&slot) {
+                promotion.addresses.insert(address, *variable);
+            }
+        }
+        promotion.stacks = vec![Vec::new(); promotion.variables.len()];
+        promotion
+    }
+
+    /// The variable that the instruction assigns
+    fn assigned(&self, instruction: &Instruction) -> Option<usize> {
+        match instruction {
+            Instruction::Store {
+                address: Value::Register(address),
+                ..
+            } => self.addresses.get(address).copied(),
+            instruction => self.registers.get(&instruction.destination()?).copied(),
+        }
+    }
+
+    fn insert_phis(&mut self, function: &mut Function, dominance: &Dominance) {
+        let mut assignments = vec![Vec::new(); self.variables.len()];
+        for id in function.block_ids() {
+            for instruction in &function.block(id).instructions {
+                if let Some(variable) = self.assigned(instruction)
+                    && !assignments[variable].contains(&id)
+                {
+                    assignments[variable].push(id);
+                }
+            }
+        }
+
+        for (variable, mut worklist) in assignments.into_iter().enumerate() {
+            let mut has_phi = vec![false; function.blocks.len()];
+            while let Some(block) = worklist.pop() {
+                for frontier in &dominance.frontiers[block.0 as usize] {
+                    if has_phi[frontier.0 as usize] {
+                        continue;
+                    }
+                    has_phi[frontier.0 as usize] = true;
+                    let destination = function.new_register(self.variables[variable]);
+                    self.phis.insert(destination, variable);
+                    let instructions = &mut function.block_mut(*frontier).instructions;
+                    let position = instructions
+                        .iter()
+                        .take_while(|instruction| matches!(instruction, Instruction::Phi { .. }))
+                        .count();
+                    instructions.insert(
+                        position,
+                        Instruction::Phi {
+                            destination,
+                            incoming: Vec::new(),
+                        },
+                    );
+                    worklist.push(*frontier);
+                }
+            }
+        }
+    }
+
+    /// Current value of the variable, reading a variable that was never assigned gives 0
+    fn current(&self, variable: usize) -> Value {
+        match self.stacks[variable].last() {
+            Some(value) => *value,
+            None => Value::Constant(0, self.variables[variable]),
+        }
+    }
+
+    fn rename(&mut self, function: &mut Function, dominance: &Dominance, id: BlockId) {
+        let mut assigned = Vec::new();
+        let mut instructions = Vec::new();
+        for mut instruction in std::mem::take(&mut function.block_mut(id).instructions) {
+            if let Instruction::Phi { destination, .. } = &instruction
+                && let Some(variable) = self.phis.get(destination)
+            {
+                self.stacks[*variable].push(Value::Register(*destination));
+                assigned.push(*variable);
+                instructions.push(instruction);
+                continue;
+            }
+            if !matches!(instruction, Instruction::Phi { .. }) {
+                for operand in instruction.operands_mut() {
+                    *operand = self.renamed(*operand);
+                }
+            }
+            match &mut instruction {
+                Instruction::StackAddress { destination, .. }
+                    if self.addresses.contains_key(destination) => {}
+                Instruction::Load {
+                    destination,
+                    address: Value::Register(address),
+                } if self.addresses.contains_key(address) => {
+                    let value = self.current(self.addresses[address]);
+                    self.replacements.insert(*destination, value);
+                }
+                Instruction::Store {
+                    address: Value::Register(address),
+                    value,
+                } if self.addresses.contains_key(address) => {
+                    let variable = self.addresses[address];
+                    self.stacks[variable].push(*value);
+                    assigned.push(variable);
+                }
+                instruction => {
+                    if let Some(destination) = instruction.destination()
+                        && let Some(variable) = self.registers.get(&destination).copied()
+                    {
+                        let renamed = function.new_register(function.register_type(destination));
+                        set_destination(instruction, renamed);
+                        self.stacks[variable].push(Value::Register(renamed));
+                        assigned.push(variable);
+                    }
+                    instructions.push(instruction.to_owned());
+                }
+            }
+        }
+        let block = function.block_mut(id);
+        block.instructions = instructions;
+        for operand in block.terminator.operands_mut() {
+            *operand = self.renamed(*operand);
+        }
+
+        let mut successors = function.block(id).terminator.successors();
+        successors.dedup();
+        for successor in successors {
+            let mut values = Vec::new();
+            for instruction in &function.block(successor).instructions {
+                if let Instruction::Phi { destination, .. } = instruction
+                    && let Some(variable) = self.phis.get(destination)
+                {
+                    values.push(self.current(*variable));
+                }
+            }
+            let mut values = values.into_iter();
+            for instruction in &mut function.block_mut(successor).instructions {
+                if let Instruction::Phi {
+                    destination,
+                    incoming,
+                } = instruction
+                    && self.phis.contains_key(destination)
+                {
+                    incoming.push((id, values.next().unwrap()));
+                }
+            }
+        }
+
+        for child in dominance.children[id.0 as usize].iter().copied() {
+            self.rename(function, dominance, child);
+        }
+        for variable in assigned {
+            self.stacks[variable].pop();
+        }
+    }
+
+    /// Uses of registers that are assigned more than once read the last assignment
+    fn renamed(&self, value: Value) -> Value {
+        match replacement(&self.replacements, value) {
+            Value::Register(register) => match self.registers.get(&register) {
+                Some(variable) => self.current(*variable),
+                None => Value::Register(register),
+            },
+            value => value,
+        }
+    }
+}
+
+fn set_destination(instruction: &mut Instruction, register: Register) {
+    match instruction {
+        Instruction::Phi { destination, .. }
+        | Instruction::Copy { destination, .. }
+        | Instruction::Unary { destination, .. }
+        | Instruction::Binary { destination, .. }
+        | Instruction::Compare { destination, .. }
+        | Instruction::Convert { destination, .. }
+        | Instruction::Load { destination, .. }
+        | Instruction::StackAddress { destination, .. }
+        | Instruction::GlobalAddress { destination, .. } => *destination = register,
+        Instruction::Call { destination, .. } => *destination = Some(register),
+        Instruction::Store { .. }
+        | Instruction::CopyMemory { .. }
+        | Instruction::ZeroMemory { .. } => {}
+    }
+}
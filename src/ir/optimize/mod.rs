@@ -0,0 +1,230 @@
+mod cfg;
+mod constant;
+mod copy;
+mod cse;
+mod dce;
+mod mem2reg;
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+use anyhow::{Context, Result, bail};
+use log::info;
+
+use super::verify::verify;
+use super::{BlockId, Function, Instruction, Module, Register, SlotId, Terminator, Value};
+
+/// Transformation of the IR, selected with `--passes`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pass {
+    /// promotes locals from stack slots to registers and builds SSA form with phis
+    Mem2Reg,
+    /// folds instructions whose operands are constants, and branches on constants
+    ConstantPropagation,
+    /// removes instructions whose result is never used
+    DeadCodeElimination,
+    /// replaces registers that are copies of other values with those values
+    CopyPropagation,
+    /// reuses the result of an instruction computed on every path to another one
+    CommonSubexpressionElimination,
+    /// removes unreachable and empty blocks and merges blocks with their only predecessor
+    SimplifyCfg,
+}
+
+/// Passes run by `-O`, in order
+pub const DEFAULT_PASSES: &[Pass] = &[
+    Pass::Mem2Reg,
+    Pass::SimplifyCfg,
+    Pass::ConstantPropagation,
+    Pass::CopyPropagation,
+    Pass::CommonSubexpressionElimination,
+    Pass::DeadCodeElimination,
+    Pass::SimplifyCfg,
+];
+
+const PASSES: &[Pass] = &[
+    Pass::Mem2Reg,
+    Pass::ConstantPropagation,
+    Pass::DeadCodeElimination,
+    Pass::CopyPropagation,
+    Pass::CommonSubexpressionElimination,
+    Pass::SimplifyCfg,
+];
+
+impl Pass {
+    pub fn name(self) -> &'static str {
+        match self {
+            Pass::Mem2Reg => "mem2reg",
+            Pass::ConstantPropagation => "constprop",
+            Pass::DeadCodeElimination => "dce",
+            Pass::CopyPropagation => "copyprop",
+            Pass::CommonSubexpressionElimination => "cse",
+            Pass::SimplifyCfg => "simplifycfg",
+        }
+    }
+
+    pub fn by_name(name: &str) -> Result<Self> {
+        match PASSES.iter().find(|pass| pass.name() == name) {
+            Some(pass) => Ok(*pass),
+            None => {
+                let names = PASSES.iter().map(|pass| pass.name()).collect::<Vec<_>>();
+                bail!(
+                    "unknown pass: '{name}', expected one of: {}",
+                    names.join(", ")
+                )
+            }
+        }
+    }
+
+    fn run(self, function: &mut Function) {
+        match self {
+            Pass::Mem2Reg => mem2reg::run(function),
+            Pass::ConstantPropagation => constant::run(function),
+            Pass::DeadCodeElimination => dce::run(function),
+            Pass::CopyPropagation => copy::run(function),
+            Pass::CommonSubexpressionElimination => cse::run(function),
+            Pass::SimplifyCfg => cfg::run(function),
+        }
+    }
+}
+
+/// Instructions in the module before and after a pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PassReport {
+    pub pass: Pass,
+    pub before: usize,
+    pub after: usize,
+}
+
+impl Display for PassReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} -> {} instructions",
+            self.pass.name(),
+            self.before,
+            self.after
+        )
+    }
+}
+
+/// Runs the passes in order over every function, checking the module with the verifier before
+/// the first pass and after every one
+pub fn optimize(module: &mut Module, passes: &[Pass]) -> Result<Vec<PassReport>> {
+    verify(module).context("verifying the lowered IR")?;
+    let mut reports = Vec::new();
+    for pass in passes {
+        let before = instruction_count(module);
+        for function in &mut module.functions {
+            pass.run(function);
+        }
+        verify(module).with_context(|| format!("verifying the IR after {}", pass.name()))?;
+        let report = PassReport {
+            pass: *pass,
+            before,
+            after: instruction_count(module),
+        };
+        info!("{report}");
+        reports.push(report);
+    }
+    Ok(reports)
+}
+
+fn instruction_count(module: &Module) -> usize {
+    module
+        .functions
+        .iter()
+        .map(Function::instruction_count)
+        .sum()
+}
+
+/// How many times every register is assigned, parameters count as an assignment
+fn definition_counts(function: &Function) -> Vec<usize> {
+    let mut counts = vec![0; function.registers.len()];
+    for parameter in &function.parameters {
+        counts[parameter.0 as usize] += 1;
+    }
+    for block in &function.blocks {
+        for instruction in &block.instructions {
+            if let Some(destination) = instruction.destination() {
+                counts[destination.0 as usize] += 1;
+            }
+        }
+    }
+    counts
+}
+
+/// The value that replaces a register, following replacements of replacements
+fn replacement(replacements: &HashMap<Register, Value>, mut value: Value) -> Value {
+    while let Value::Register(register) = value {
+        match replacements.get(&register) {
+            Some(replacement) => value = *replacement,
+            None => break,
+        }
+    }
+    value
+}
+
+/// Replaces every use of the registers. A register can only be replaced if it is assigned
+/// once, by a value that is available wherever the register is.
+fn replace_uses(function: &mut Function, replacements: &HashMap<Register, Value>) {
+    if replacements.is_empty() {
+        return;
+    }
+    for block in &mut function.blocks {
+        for instruction in &mut block.instructions {
+            for operand in instruction.operands_mut() {
+                *operand = replacement(replacements, *operand);
+            }
+        }
+        for operand in block.terminator.operands_mut() {
+            *operand = replacement(replacements, *operand);
+        }
+    }
+}
+
+/// Replaces the terminator of `block` with a jump to `target`, the blocks it no longer goes to
+/// forget about it in their phis
+fn jump(function: &mut Function, block: BlockId, target: BlockId) {
+    let dropped = function.block(block).terminator.successors();
+    function.block_mut(block).terminator = Terminator::Jump(target);
+    for successor in dropped {
+        if successor != target {
+            for instruction in &mut function.block_mut(successor).instructions {
+                if let Instruction::Phi { incoming, .. } = instruction {
+                    incoming.retain(|(predecessor, _)| *predecessor != block);
+                }
+            }
+        }
+    }
+}
+
+/// Drops the slots whose address is never taken and numbers the rest again
+fn remove_unused_slots(function: &mut Function) {
+    let mut used = vec![false; function.slots.len()];
+    for block in &function.blocks {
+        for instruction in &block.instructions {
+            if let Instruction::StackAddress { slot, .. } = instruction {
+                used[slot.0 as usize] = true;
+            }
+        }
+    }
+    let mut numbers = Vec::new();
+    let mut count = 0;
+    for used in &used {
+        numbers.push(SlotId(count));
+        count += *used as u32;
+    }
+    for block in &mut function.blocks {
+        for instruction in &mut block.instructions {
+            if let Instruction::StackAddress { slot, .. } = instruction {
+                *slot = numbers[slot.0 as usize];
+            }
+        }
+    }
+    let mut index = 0;
+    function.slots.retain(|_| {
+        index += 1;
+        used[index - 1]
+    });
+}
@@ -0,0 +1,323 @@
+use anyhow::{Context, Result, bail};
+
+use super::dominance::Dominance;
+use super::{
+    BinaryOperator, BlockId, Conversion, Function, Instruction, Module, Register, Terminator, Type,
+    Value,
+};
+
+/// Checks that every function of the module is well formed: operands have the types their
+/// instructions expect, calls match the signature of their function, phis match the
+/// predecessors of their block, and registers that are assigned once are assigned before they
+/// are used on every path
+pub fn verify(module: &Module) -> Result<()> {
+    for function in &module.functions {
+        Verifier::new(module, function)
+            .verify()
+            .with_context(|| format!("invalid IR in function '{}'", function.name))?;
+    }
+    Ok(())
+}
+
+struct Verifier<'a> {
+    module: &'a Module,
+    function: &'a Function,
+    dominance: Dominance,
+    /// where every register is assigned, `None` for parameters
+    definitions: Vec<Vec<(BlockId, Option<usize>)>>,
+}
+
+impl<'a> Verifier<'a> {
+    fn new(module: &'a Module, function: &'a Function) -> Self {
+        Verifier {
+            module,
+            function,
+            dominance: Dominance::new(function),
+            definitions: vec![Vec::new(); function.registers.len()],
+        }
+    }
+
+    fn verify(&mut self) -> Result<()> {
+        if self.function.blocks.is_empty() {
+            bail!("the function has no blocks");
+        }
+        for parameter in &self.function.parameters {
+            self.register(*parameter)?;
+            self.definitions[parameter.0 as usize].push((BlockId(0), None));
+        }
+        for id in self.function.block_ids() {
+            for (index, instruction) in self.function.block(id).instructions.iter().enumerate() {
+                if let Some(destination) = instruction.destination() {
+                    self.register(destination)?;
+                    self.definitions[destination.0 as usize].push((id, Some(index)));
+                }
+            }
+        }
+
+        let predecessors = self.function.predecessors();
+        for id in self.function.block_ids() {
+            let block = self.function.block(id);
+            let mut phis = true;
+            for (index, instruction) in block.instructions.iter().enumerate() {
+                let text = self.function.instruction(instruction);
+                if let Instruction::Phi { incoming, .. } = instruction {
+                    if !phis {
+                        bail!("{id}: phi after other instructions: {text}");
+                    }
+                    if self.dominance.is_reachable(id) {
+                        let mut blocks =
+                            incoming.iter().map(|(block, _)| *block).collect::<Vec<_>>();
+                        blocks.sort();
+                        let mut expected = predecessors[id.0 as usize]
+                            .iter()
+                            .copied()
+                            .filter(|predecessor| self.dominance.is_reachable(*predecessor))
+                            .collect::<Vec<_>>();
+                        expected.sort();
+                        if blocks != expected {
+                            bail!(
+                                "{id}: the phi doesn't have one value for every predecessor: {text}"
+                            );
+                        }
+                    }
+                } else {
+                    phis = false;
+                }
+                self.instruction(instruction)
+                    .and_then(|()| {
+                        for (position, value) in instruction.operands().into_iter().enumerate() {
+                            let user = match instruction {
+                                Instruction::Phi { incoming, .. } => {
+                                    // the value has to be there at the end of the predecessor
+                                    (incoming[position].0, usize::MAX)
+                                }
+                                _ => (id, index),
+                            };
+                            self.used(value, user)?;
+                        }
+                        Ok(())
+                    })
+                    .with_context(|| format!("{id}: {text}"))?;
+            }
+
+            let text = self.function.terminator(&block.terminator);
+            self.terminator(&block.terminator)
+                .and_then(|()| {
+                    for value in block.terminator.operands() {
+                        self.used(value, (id, usize::MAX))?;
+                    }
+                    Ok(())
+                })
+                .with_context(|| format!("{id}: {text}"))?;
+        }
+        Ok(())
+    }
+
+    fn register(&self, register: Register) -> Result<()> {
+        if register.0 as usize >= self.function.registers.len() {
+            bail!("register {register} has no type");
+        }
+        Ok(())
+    }
+
+    fn type_of(&self, value: Value) -> Result<Type> {
+        if let Value::Register(register) = value {
+            self.register(register)?;
+        }
+        Ok(self.function.type_of(value))
+    }
+
+    fn expect(&self, value: Value, expected: Type) -> Result<()> {
+        let found = self.type_of(value)?;
+        if found != expected {
+            bail!("{value} is a {found}, expected a {expected}");
+        }
+        Ok(())
+    }
+
+    fn block(&self, block: BlockId) -> Result<()> {
+        if block.0 as usize >= self.function.blocks.len() {
+            bail!("there is no block {block}");
+        }
+        Ok(())
+    }
+
+    /// A register that is used at `(block, index)` must be assigned somewhere, and if it is
+    /// assigned only once the assignment has to come first on every path
+    fn used(&self, value: Value, (block, index): (BlockId, usize)) -> Result<()> {
+        let Value::Register(register) = value else {
+            return Ok(());
+        };
+        match self.definitions[register.0 as usize].as_slice() {
+            [] => bail!("{register} is never assigned"),
+            [(definition, position)] if self.dominance.is_reachable(block) => {
+                let before = match position {
+                    None => true,
+                    Some(position) if *definition == block => *position < index,
+                    Some(_) => self.dominance.dominates(*definition, block),
+                };
+                if !before {
+                    bail!("{register} is used before it is assigned");
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn instruction(&self, instruction: &Instruction) -> Result<()> {
+        let destination = instruction
+            .destination()
+            .map(|register| self.function.register_type(register));
+        match instruction {
+            Instruction::Phi {
+                destination,
+                incoming,
+            } => {
+                for (block, value) in incoming {
+                    self.block(*block)?;
+                    self.expect(*value, self.function.register_type(*destination))?;
+                }
+            }
+            Instruction::Copy { value, .. } | Instruction::Unary { value, .. } => {
+                self.expect(*value, destination.unwrap())?;
+            }
+            Instruction::Binary {
+                operator,
+                left,
+                right,
+                ..
+            } => {
+                let destination = destination.unwrap();
+                self.expect(*left, destination)?;
+                if destination == Type::Pointer && *operator == BinaryOperator::Add {
+                    self.expect(*right, Type::I64)?;
+                } else if destination == Type::Pointer {
+                    bail!("only 'add' works on pointers");
+                } else {
+                    self.expect(*right, destination)?;
+                }
+            }
+            Instruction::Compare { left, right, .. } => {
+                self.expect(*right, self.type_of(*left)?)?;
+                if destination != Some(Type::I1) {
+                    bail!("the result of a comparison has to be an i1");
+                }
+            }
+            Instruction::Convert {
+                conversion, value, ..
+            } => {
+                let (from, to) = (self.type_of(*value)?, destination.unwrap());
+                let valid = match conversion {
+                    Conversion::Truncate => {
+                        from != Type::Pointer && to != Type::Pointer && from.bits() > to.bits()
+                    }
+                    Conversion::ZeroExtend | Conversion::SignExtend => {
+                        from != Type::Pointer && to != Type::Pointer && from.bits() < to.bits()
+                    }
+                    Conversion::PointerToInteger => from == Type::Pointer && to != Type::Pointer,
+                    Conversion::IntegerToPointer => from != Type::Pointer && to == Type::Pointer,
+                };
+                if !valid {
+                    bail!("can't {} a {from} to a {to}", conversion.name());
+                }
+            }
+            Instruction::Load { address, .. } => self.expect(*address, Type::Pointer)?,
+            Instruction::Store { address, value } => {
+                self.expect(*address, Type::Pointer)?;
+                self.type_of(*value)?;
+            }
+            Instruction::StackAddress { slot, .. } => {
+                if slot.0 as usize >= self.function.slots.len() {
+                    bail!("there is no slot {slot}");
+                }
+            }
+            Instruction::GlobalAddress { name, .. } => {
+                if self.module.global(name).is_none() && self.module.function(name).is_none() {
+                    bail!("there is no global @{name}");
+                }
+            }
+            Instruction::Call {
+                function,
+                arguments,
+                ..
+            } => {
+                let (parameters, output, variadic) = match (
+                    self.module.function(function),
+                    self.module
+                        .declarations
+                        .iter()
+                        .find(|declaration| declaration.name == *function),
+                ) {
+                    (Some(callee), _) => (
+                        callee
+                            .parameters
+                            .iter()
+                            .map(|parameter| callee.register_type(*parameter))
+                            .collect(),
+                        callee.output,
+                        false,
+                    ),
+                    (None, Some(declaration)) => (
+                        declaration.parameters.to_owned(),
+                        declaration.output,
+                        declaration.variadic,
+                    ),
+                    (None, None) => bail!("@{function} is neither defined nor declared"),
+                };
+                if arguments.len() < parameters.len()
+                    || (!variadic && arguments.len() > parameters.len())
+                {
+                    bail!(
+                        "@{function} takes {} arguments, not {}",
+                        parameters.len(),
+                        arguments.len()
+                    );
+                }
+                for (argument, parameter) in arguments.iter().zip(parameters) {
+                    self.expect(*argument, parameter)?;
+                }
+                for argument in arguments {
+                    self.type_of(*argument)?;
+                }
+                if let Some(destination) = destination
+                    && Some(destination) != output
+                {
+                    bail!("@{function} doesn't return a {destination}");
+                }
+            }
+            Instruction::CopyMemory {
+                destination,
+                source,
+                ..
+            } => {
+                self.expect(*destination, Type::Pointer)?;
+                self.expect(*source, Type::Pointer)?;
+            }
+            Instruction::ZeroMemory { destination, .. } => {
+                self.expect(*destination, Type::Pointer)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn terminator(&self, terminator: &Terminator) -> Result<()> {
+        for successor in terminator.successors() {
+            self.block(successor)?;
+        }
+        if terminator.successors().contains(&BlockId(0)) {
+            bail!("the entry block can't be jumped to");
+        }
+        match terminator {
+            Terminator::Branch { condition, .. } => self.expect(*condition, Type::I1)?,
+            Terminator::Return(value) => match (value, self.function.output) {
+                (Some(value), Some(output)) => self.expect(*value, output)?,
+                (None, None) => {}
+                (Some(_), None) => bail!("the function returns void"),
+                (None, Some(output)) => bail!("the function returns a {output}"),
+            },
+            Terminator::Jump(_) | Terminator::Unreachable => {}
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1016 @@
+//! Walking the statements and expressions of a checked program, shared by the lowering to the IR
+//! and the LLVM backend. They only differ in the instructions that they build and in how locals,
+//! calls and initializers are stored.
+
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    code_gen::libc_type,
+    ir::{BinaryOperator, Condition, Conversion, Type, UnaryOperator},
+    layout::{LayoutEngine, target::Target},
+    lexer::{character_value, token::TokenKind},
+    parser::{
+        expression::{DebugData, Expression},
+        types::DataType,
+    },
+    semantic::{
+        constant::ConstantContext,
+        scope::{SymbolId, SymbolKind, SymbolTable},
+        type_check::{
+            TypeTable, compound_operator, integer_rank, is_floating, is_integer, is_void,
+            primitive, promote, resolve, usual_arithmetic_conversion,
+        },
+    },
+};
+
+pub(crate) fn line(expression: &Expression) -> String {
+    match expression.debug_data() {
+        Some(DebugData { line, .. }) => line.to_string(),
+        None => "?".to_owned(),
+    }
+}
+
+pub(crate) fn is_aggregate(data_type: &DataType) -> bool {
+    matches!(data_type, DataType::Struct { .. } | DataType::Union { .. })
+}
+
+pub(crate) fn is_comparison(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Equals
+            | TokenKind::NotEquals
+            | TokenKind::Less
+            | TokenKind::LessEquals
+            | TokenKind::Greater
+            | TokenKind::GreaterEquals
+    )
+}
+
+/// Functions with a body somewhere in the program
+pub(crate) fn defined_functions(expressions: &[Expression]) -> Vec<String> {
+    expressions
+        .iter()
+        .filter_map(|expression| match expression {
+            Expression::Function { name, .. } => Some(name.to_owned()),
+            Expression::Static { value, .. } => match value.as_ref() {
+                Expression::Function { name, .. } => Some(name.to_owned()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// What the earlier passes know about the program
+pub(crate) struct Program<'a> {
+    pub(crate) symbols: &'a SymbolTable,
+    pub(crate) types: &'a TypeTable,
+    pub(crate) target: &'a Target,
+    pub(crate) layout: LayoutEngine<'a, SymbolTable>,
+    /// what errors call the backend, like "the IR"
+    backend: &'static str,
+}
+
+impl<'a> Program<'a> {
+    pub(crate) fn new(
+        symbols: &'a SymbolTable,
+        types: &'a TypeTable,
+        target: &'a Target,
+        backend: &'static str,
+    ) -> Self {
+        Program {
+            symbols,
+            types,
+            target,
+            layout: LayoutEngine::new(target, symbols),
+            backend,
+        }
+    }
+
+    pub(crate) fn resolve(&self, data_type: &DataType) -> DataType {
+        resolve(self.symbols, data_type)
+    }
+
+    /// Type from the type checker with typedefs resolved, arrays and functions decay
+    pub(crate) fn value_type(&self, expression: &Expression) -> Result<DataType> {
+        Ok(match self.object_type(expression)? {
+            DataType::Array { inside, .. } => DataType::Pointer(inside),
+            function @ DataType::Function { .. } => DataType::Pointer(Box::new(function)),
+            data_type => data_type,
+        })
+    }
+
+    /// Type from the type checker with typedefs resolved
+    pub(crate) fn object_type(&self, expression: &Expression) -> Result<DataType> {
+        // the type checker only knows that libc functions return `int`
+        if let Expression::FunctionCall { left, .. } = expression
+            && let Expression::Identifier(name, _) = left.as_ref()
+            && self.symbols.resolution(left).is_none()
+            && let Some(DataType::Function { output, .. }) = libc_type(name)
+        {
+            return Ok(*output);
+        }
+        match self.types.type_of(expression) {
+            Some(data_type) => Ok(self.resolve(data_type)),
+            None => bail!(
+                "the type of the expression at line {} is unknown",
+                line(expression)
+            ),
+        }
+    }
+
+    pub(crate) fn size_of(&self, data_type: &DataType) -> Result<u64> {
+        self.layout.size_of(data_type)
+    }
+
+    /// Type of the value of an expression, structs are passed around as their address
+    pub(crate) fn scalar_type(&self, data_type: &DataType) -> Result<Type> {
+        let data_type = self.resolve(data_type);
+        match &data_type {
+            DataType::Pointer(_) | DataType::Array { .. } | DataType::Function { .. } => {
+                Ok(Type::Pointer)
+            }
+            data_type if is_floating(data_type) => {
+                bail!("'{data_type}' values are not supported by {}", self.backend)
+            }
+            data_type if is_integer(data_type) => {
+                let size = self.size_of(data_type)?;
+                Type::integer(size).with_context(|| format!("integers of {size} bytes"))
+            }
+            data_type if is_aggregate(data_type) => Ok(Type::Pointer),
+            data_type => bail!("'{data_type}' has no type in {}", self.backend),
+        }
+    }
+
+    pub(crate) fn is_signed(&self, data_type: &DataType) -> bool {
+        match self.resolve(data_type) {
+            DataType::Data { unsigned: true, .. } => false,
+            DataType::Data { name, .. } if name == "char" => self.target.char_signed,
+            DataType::Data { name, .. } => name != "bool",
+            DataType::Enum { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Symbol of a variable declaration
+    pub(crate) fn declared(&self, declaration: &Expression, name: &str) -> Result<SymbolId> {
+        self.symbols
+            .declarations
+            .get(&declaration.id())
+            .copied()
+            .with_context(|| format!("declaration of '{name}' wasn't resolved"))
+    }
+}
+
+/// Lowers statements and expressions with the instructions that a backend builds. Control flow
+/// is made of blocks that end in a jump, a branch or a return.
+pub(crate) trait Walker<'a> {
+    type Value: Clone;
+    type Block: Copy + PartialEq;
+
+    fn program(&self) -> &Program<'a>;
+
+    // -- building --
+
+    fn new_block(&mut self, name: &str) -> Self::Block;
+    /// Continues in the block, the previous one falls through to it
+    fn start(&mut self, block: Self::Block);
+    /// Jumps unless the block already ended
+    fn jump(&mut self, target: Self::Block);
+    fn branch(&mut self, condition: Self::Value, then: Self::Block, otherwise: Self::Block);
+    /// Returns from the function, the value already has the type of its result
+    fn return_value(&mut self, value: Option<Self::Value>);
+    /// where `break` jumps to, innermost loop last
+    fn breaks(&mut self) -> &mut Vec<Self::Block>;
+    fn constant(&self, value: i64, data_type: Type) -> Self::Value;
+    fn as_constant(&self, value: &Self::Value) -> Option<i64>;
+    fn type_of(&mut self, value: &Self::Value) -> Type;
+    fn binary_instruction(
+        &mut self,
+        operator: BinaryOperator,
+        left: Self::Value,
+        right: Self::Value,
+        data_type: Type,
+    ) -> Self::Value;
+    fn unary_instruction(&mut self, operator: UnaryOperator, value: Self::Value) -> Self::Value;
+    fn compare(
+        &mut self,
+        condition: Condition,
+        left: Self::Value,
+        right: Self::Value,
+    ) -> Self::Value;
+    fn convert_instruction(
+        &mut self,
+        conversion: Conversion,
+        value: Self::Value,
+        to: Type,
+    ) -> Self::Value;
+    fn load(&mut self, address: Self::Value, data_type: &DataType) -> Result<Self::Value>;
+    fn store(
+        &mut self,
+        address: Self::Value,
+        value: Self::Value,
+        data_type: &DataType,
+    ) -> Result<()>;
+
+    /// Number of elements between two pointers from the bytes between them, which always
+    /// divide evenly
+    fn element_count(&mut self, bytes: Self::Value, size: i64) -> Self::Value {
+        let size = self.constant(size, Type::I64);
+        self.binary_instruction(BinaryOperator::SignedDivide, bytes, size, Type::I64)
+    }
+
+    // -- storage --
+
+    /// C type of the result of the function, and the pointer that a returned struct is
+    /// written to
+    fn output(&mut self) -> (DataType, Option<Self::Value>);
+    fn function_name(&mut self) -> String;
+    fn has_local(&mut self, id: SymbolId) -> bool;
+    fn allocate_local(&mut self, id: SymbolId, name: &str, data_type: &DataType) -> Result<()>;
+    /// Address of a local or a global
+    fn variable_address(&mut self, id: SymbolId) -> Option<Self::Value>;
+    fn string_address(&mut self, value: &str) -> Result<Self::Value>;
+    /// Address of a member of the struct or union at `base`
+    fn member_address(
+        &mut self,
+        base: Self::Value,
+        structure: &DataType,
+        member: &Expression,
+    ) -> Result<Self::Value>;
+    /// `pointer + index` in elements of what it points to
+    fn pointer_offset(
+        &mut self,
+        pointer: Self::Value,
+        index: Self::Value,
+        index_type: &DataType,
+        pointee: &DataType,
+        negate: bool,
+    ) -> Result<Self::Value>;
+    /// Global or static local, its initializer has to be constant
+    fn global(
+        &mut self,
+        declaration: &Expression,
+        initializer: Option<&Expression>,
+        name: String,
+        exported: bool,
+    ) -> Result<()>;
+    fn function_definition(&mut self, expression: &'a Expression, exported: bool) -> Result<()>;
+    /// Stores the initializer of a local, what an initializer list leaves out is zeroed
+    fn initialize(
+        &mut self,
+        address: Self::Value,
+        data_type: &DataType,
+        initializer: &'a Expression,
+    ) -> Result<()>;
+    /// `&&` and `||` as an `int`
+    fn logical_value(&mut self, expression: &'a Expression) -> Result<Self::Value>;
+    /// A function used as a value
+    fn function_value(&mut self, name: &str) -> Result<Self::Value>;
+    fn call(
+        &mut self,
+        left: &'a Expression,
+        values: &'a [Expression],
+    ) -> Result<Option<Self::Value>>;
+
+    // -- conversions --
+
+    /// Integer value with the type and sign of `from` as an integer of type `to`
+    fn resize(&mut self, value: Self::Value, to: Type, signed: bool) -> Self::Value {
+        let from = self.type_of(&value);
+        if from == to {
+            return value;
+        }
+        if let Some(constant) = self.as_constant(&value) {
+            let constant = if signed || from == Type::I1 {
+                constant
+            } else {
+                constant & (u64::MAX >> (64 - from.bits())) as i64
+            };
+            return self.constant(to.wrap(constant), to);
+        }
+        let conversion = if to.bits() < from.bits() {
+            Conversion::Truncate
+        } else if signed && from != Type::I1 {
+            Conversion::SignExtend
+        } else {
+            Conversion::ZeroExtend
+        };
+        self.convert_instruction(conversion, value, to)
+    }
+
+    /// The same conversion as assigning a value of type `from` to something of type `to`
+    fn convert(
+        &mut self,
+        value: Self::Value,
+        from: &DataType,
+        to: &DataType,
+    ) -> Result<Self::Value> {
+        let (from, to) = (self.program().resolve(from), self.program().resolve(to));
+        if is_void(&to) || is_aggregate(&to) {
+            return Ok(value);
+        }
+        let target = self.program().scalar_type(&to)?;
+        if integer_rank(&to) == Some(1) && integer_rank(&from) != Some(1) {
+            let truth = self.truth(value);
+            return Ok(self.resize(truth, target, false));
+        }
+        let signed = self.program().is_signed(&from);
+        Ok(match (self.type_of(&value), target) {
+            (Type::Pointer, Type::Pointer) => value,
+            (Type::Pointer, _) => {
+                let integer =
+                    self.convert_instruction(Conversion::PointerToInteger, value, Type::I64);
+                self.resize(integer, target, false)
+            }
+            (_, Type::Pointer) => {
+                let integer = self.resize(value, Type::I64, signed);
+                match self.as_constant(&integer) {
+                    Some(constant) => self.constant(constant, Type::Pointer),
+                    None => self.convert_instruction(
+                        Conversion::IntegerToPointer,
+                        integer,
+                        Type::Pointer,
+                    ),
+                }
+            }
+            _ => self.resize(value, target, signed),
+        })
+    }
+
+    /// `i1` that is true when the scalar isn't zero
+    fn truth(&mut self, value: Self::Value) -> Self::Value {
+        match self.type_of(&value) {
+            Type::I1 => value,
+            data_type => {
+                let zero = self.constant(0, data_type);
+                self.compare(Condition::NotEqual, value, zero)
+            }
+        }
+    }
+
+    // -- top level --
+
+    fn top_level(&mut self, expression: &'a Expression, exported: bool) -> Result<()> {
+        match expression {
+            Expression::Function { .. } => self.function_definition(expression, exported),
+            Expression::Static { value, .. } => self.top_level(value, false),
+            Expression::VariableDeclaration { name, .. } => {
+                self.global(expression, None, name.to_owned(), exported)
+            }
+            Expression::Assignment { target, value, .. }
+                if matches!(target.as_ref(), Expression::VariableDeclaration { .. }) =>
+            {
+                let Expression::VariableDeclaration { name, .. } = target.as_ref() else {
+                    unreachable!("checked by the guard");
+                };
+                self.global(target, Some(value), name.to_owned(), exported)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // -- statements --
+
+    fn statements(&mut self, inside: &'a [Expression]) -> Result<()> {
+        for expression in inside {
+            self.statement(expression)
+                .with_context(|| format!("statement at line: {}", line(expression)))?;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, expression: &'a Expression) -> Result<()> {
+        match expression {
+            Expression::Skip
+            | Expression::CompilerData(..)
+            | Expression::Typedef { .. }
+            | Expression::Struct { .. }
+            | Expression::FunctionDeclaration { .. } => {}
+            Expression::Function { name, .. } => {
+                bail!("function '{name}' can't be defined inside of another function")
+            }
+            Expression::VariableDeclaration { .. } => {
+                self.local(expression)?;
+            }
+            Expression::Assignment { target, value, .. }
+                if matches!(target.as_ref(), Expression::VariableDeclaration { .. }) =>
+            {
+                let (address, data_type) = self.local_address(target)?;
+                self.initialize(address, &data_type, value)?;
+            }
+            Expression::Static { value, .. } => self.static_local(value)?,
+            Expression::NewCodeBlock { inside, .. } => self.statements(inside)?,
+            Expression::Break { .. } => {
+                let Some(target) = self.breaks().last().copied() else {
+                    bail!("'break' outside of a loop");
+                };
+                self.jump(target);
+            }
+            Expression::Return { value, .. } => self.return_statement(value)?,
+            Expression::If {
+                condition,
+                inside,
+                chained_elses,
+                ..
+            } => {
+                let then = self.new_block("if.then");
+                let end = self.new_block("if.end");
+                let mut otherwise = if chained_elses.is_empty() {
+                    end
+                } else {
+                    self.new_block("if.else")
+                };
+                self.condition(condition, then, otherwise)?;
+                self.start(then);
+                self.statements(inside)?;
+                self.jump(end);
+                for chained in chained_elses {
+                    let Expression::Else {
+                        condition, inside, ..
+                    } = chained
+                    else {
+                        bail!("expected an else, found: {chained:?}");
+                    };
+                    self.start(otherwise);
+                    match condition {
+                        Some(condition) => {
+                            let then = self.new_block("if.then");
+                            otherwise = self.new_block("if.else");
+                            self.condition(condition, then, otherwise)?;
+                            self.start(then);
+                        }
+                        None => otherwise = end,
+                    }
+                    self.statements(inside)?;
+                    self.jump(end);
+                }
+                if otherwise != end {
+                    self.start(otherwise);
+                }
+                self.start(end);
+            }
+            Expression::While {
+                condition, inside, ..
+            } => {
+                let check = self.new_block("while.cond");
+                let body = self.new_block("while.body");
+                let end = self.new_block("while.end");
+                self.start(check);
+                self.condition(condition, body, end)?;
+                self.start(body);
+                self.loop_body(inside, end)?;
+                self.jump(check);
+                self.start(end);
+            }
+            Expression::For {
+                iterator_init,
+                condition,
+                incr,
+                inside,
+                ..
+            } => {
+                self.statement(iterator_init)?;
+                let check = self.new_block("for.cond");
+                let body = self.new_block("for.body");
+                let step = self.new_block("for.inc");
+                let end = self.new_block("for.end");
+                self.start(check);
+                self.condition(condition, body, end)?;
+                self.start(body);
+                self.loop_body(inside, end)?;
+                self.start(step);
+                if !matches!(incr.as_ref(), Expression::Skip) {
+                    self.expression(incr)?;
+                }
+                self.jump(check);
+                self.start(end);
+            }
+            Expression::FunctionCall { left, .. } if matches!(left.as_ref(), Expression::Identifier(name, _) if name == "_Static_assert") =>
+                {}
+            _ => {
+                self.expression(expression)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn loop_body(&mut self, inside: &'a [Expression], end: Self::Block) -> Result<()> {
+        self.breaks().push(end);
+        let result = self.statements(inside);
+        self.breaks().pop();
+        result
+    }
+
+    fn return_statement(&mut self, value: &'a Expression) -> Result<()> {
+        if let Expression::Skip = value {
+            self.return_value(None);
+            return Ok(());
+        }
+        let (output, result) = self.output();
+        let from = self.program().value_type(value)?;
+        let value = self.value(value)?;
+        if let Some(destination) = result {
+            self.store(destination, value, &output)?;
+            self.return_value(None);
+            return Ok(());
+        }
+        if is_void(&output) {
+            self.return_value(None);
+            return Ok(());
+        }
+        let value = self.convert(value, &from, &output)?;
+        self.return_value(Some(value));
+        Ok(())
+    }
+
+    /// Storage of a local variable, a declaration inside of a loop is the same variable in
+    /// every iteration
+    fn local(&mut self, declaration: &Expression) -> Result<(SymbolId, DataType)> {
+        let Expression::VariableDeclaration { var_type, name, .. } = declaration else {
+            bail!("expected a variable declaration, found: {declaration:?}");
+        };
+        let id = self.program().declared(declaration, name)?;
+        let data_type = self.program().resolve(var_type);
+        if !self.has_local(id) {
+            self.allocate_local(id, name, &data_type)?;
+        }
+        Ok((id, data_type))
+    }
+
+    fn local_address(&mut self, declaration: &Expression) -> Result<(Self::Value, DataType)> {
+        let (id, data_type) = self.local(declaration)?;
+        let address = self
+            .variable_address(id)
+            .context("the local was just allocated")?;
+        Ok((address, data_type))
+    }
+
+    /// `static int calls = 0;` inside of a function is a global named after both
+    fn static_local(&mut self, value: &Expression) -> Result<()> {
+        let function = self.function_name();
+        match value {
+            Expression::VariableDeclaration { name, .. } => {
+                self.global(value, None, format!("{function}.{name}"), false)
+            }
+            Expression::Assignment { target, value, .. } => {
+                let Expression::VariableDeclaration { name, .. } = target.as_ref() else {
+                    bail!("expected a variable declaration, found: {target:?}");
+                };
+                self.global(target, Some(value), format!("{function}.{name}"), false)
+            }
+            value => bail!("unsupported static declaration: {value:?}"),
+        }
+    }
+
+    // -- conditions --
+
+    /// Branches to `then` if the expression is true and to `otherwise` if it isn't, `&&`,
+    /// `||` and `!` become branches instead of values
+    fn condition(
+        &mut self,
+        expression: &'a Expression,
+        then: Self::Block,
+        otherwise: Self::Block,
+    ) -> Result<()> {
+        match expression {
+            Expression::Skip => self.jump(then),
+            Expression::Grouping { value, .. } => self.condition(value, then, otherwise)?,
+            Expression::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } if matches!(operator.kind, TokenKind::And | TokenKind::Or) => {
+                let next = if operator.kind == TokenKind::And {
+                    let next = self.new_block("land.rhs");
+                    self.condition(left, next, otherwise)?;
+                    next
+                } else {
+                    let next = self.new_block("lor.rhs");
+                    self.condition(left, then, next)?;
+                    next
+                };
+                self.start(next);
+                self.condition(right, then, otherwise)?;
+            }
+            Expression::Prefix { prefix, value, .. } if prefix.kind == TokenKind::Not => {
+                self.condition(value, otherwise, then)?
+            }
+            expression => {
+                let value = self.scalar(expression)?;
+                let condition = self.truth(value);
+                self.branch(condition, then, otherwise);
+            }
+        }
+        Ok(())
+    }
+
+    // -- expressions --
+
+    /// Value of an expression that has to have one
+    fn value(&mut self, expression: &'a Expression) -> Result<Self::Value> {
+        self.expression(expression)?
+            .with_context(|| format!("void value used at line: {}", line(expression)))
+    }
+
+    /// Value of a scalar, `i1` for comparisons so that they can be branched on directly
+    fn scalar(&mut self, expression: &'a Expression) -> Result<Self::Value> {
+        match expression {
+            Expression::Grouping { value, .. } => self.scalar(value),
+            Expression::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } if is_comparison(operator.kind) => self.comparison(operator.kind, left, right),
+            expression => self.value(expression),
+        }
+    }
+
+    /// Address of something that can be assigned to, and its type
+    fn address(&mut self, expression: &'a Expression) -> Result<(Self::Value, DataType)> {
+        match expression {
+            Expression::Identifier(name, _)
+            | Expression::DataTypeAccess {
+                data_type: DataType::Data { name, .. },
+                ..
+            } => {
+                let program = self.program();
+                let id = *program
+                    .symbols
+                    .resolutions
+                    .get(&expression.id())
+                    .with_context(|| format!("use of undeclared identifier '{name}'"))?;
+                let data_type = program.resolve(&program.symbols.symbol(id).data_type);
+                match self.variable_address(id) {
+                    Some(address) => Ok((address, data_type)),
+                    None => bail!("'{name}' has no address"),
+                }
+            }
+            Expression::VariableDeclaration { .. } => self.local_address(expression),
+            Expression::Grouping { value, .. } => self.address(value),
+            Expression::String(value, _) => {
+                let address = self.string_address(value)?;
+                Ok((address, self.program().object_type(expression)?))
+            }
+            Expression::Dereference { value, .. } => {
+                let address = self.value(value)?;
+                Ok((address, self.program().object_type(expression)?))
+            }
+            Expression::AccessArray { left, index, .. } => {
+                let left_type = self.program().value_type(left)?;
+                let (pointer, index, index_type) = match left_type {
+                    DataType::Pointer(_) => (left, index, self.program().value_type(index)?),
+                    _ => (index, left, left_type),
+                };
+                let data_type = self.program().object_type(expression)?;
+                let base = self.value(pointer)?;
+                let index = self.value(index)?;
+                let address = self.pointer_offset(base, index, &index_type, &data_type, false)?;
+                Ok((address, data_type))
+            }
+            Expression::MemberExpr { left, right, .. } => {
+                let (base, structure) = self.address(left)?;
+                let structure = self.program().resolve(&structure);
+                let member = self.member_address(base, &structure, right)?;
+                Ok((member, self.program().object_type(expression)?))
+            }
+            Expression::Arrow { left, right, .. } => {
+                let DataType::Pointer(structure) = self.program().value_type(left)? else {
+                    bail!("'->' used on a value that is not a pointer");
+                };
+                let structure = self.program().resolve(&structure);
+                let base = self.value(left)?;
+                let member = self.member_address(base, &structure, right)?;
+                Ok((member, self.program().object_type(expression)?))
+            }
+            // a struct returned from a call lives in a temporary
+            Expression::FunctionCall { .. }
+                if is_aggregate(&self.program().object_type(expression)?) =>
+            {
+                let data_type = self.program().object_type(expression)?;
+                Ok((self.value(expression)?, data_type))
+            }
+            _ => bail!("expression at line {} is not an lvalue", line(expression)),
+        }
+    }
+
+    fn expression(&mut self, expression: &'a Expression) -> Result<Option<Self::Value>> {
+        Ok(Some(match expression {
+            Expression::Number(value, ..) => {
+                let program = self.program();
+                let data_type = program.scalar_type(&program.object_type(expression)?)?;
+                self.constant(data_type.wrap(*value as i64), data_type)
+            }
+            Expression::Boolean(value, _) => self.constant(*value as i64, Type::I8),
+            Expression::Character(value, _) => {
+                let program = self.program();
+                let data_type = program.scalar_type(&program.object_type(expression)?)?;
+                self.constant(data_type.wrap(character_value(value)? as i64), data_type)
+            }
+            Expression::String(..) => self.address(expression)?.0,
+            Expression::Identifier(name, _)
+            | Expression::DataTypeAccess {
+                data_type: DataType::Data { name, .. },
+                ..
+            } => {
+                let symbols = self.program().symbols;
+                let id = *symbols
+                    .resolutions
+                    .get(&expression.id())
+                    .with_context(|| format!("use of undeclared identifier '{name}'"))?;
+                match symbols.symbol(id).kind {
+                    SymbolKind::EnumConstant => {
+                        let value = symbols
+                            .enum_constant(name)
+                            .with_context(|| format!("unknown enum constant '{name}'"))?;
+                        self.constant(Type::I32.wrap(value), Type::I32)
+                    }
+                    SymbolKind::Function => self.function_value(name)?,
+                    _ => {
+                        let (address, data_type) = self.address(expression)?;
+                        self.load(address, &data_type)?
+                    }
+                }
+            }
+            Expression::Dereference { .. }
+            | Expression::AccessArray { .. }
+            | Expression::Arrow { .. }
+            | Expression::MemberExpr { .. }
+            | Expression::DataTypeAccess { .. } => {
+                let (address, data_type) = self.address(expression)?;
+                self.load(address, &data_type)?
+            }
+            Expression::AccessReference { value, .. } => self.address(value)?.0,
+            Expression::Grouping { value, .. } => return self.expression(value),
+            Expression::TypeConversion {
+                value, data_type, ..
+            } => {
+                let from = self.program().value_type(value)?;
+                let converted = self.value(value)?;
+                self.convert(converted, &from, data_type)?
+            }
+            Expression::Prefix { prefix, value, .. } => self.prefix(prefix.kind, value)?,
+            Expression::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => match operator.kind {
+                TokenKind::And | TokenKind::Or => self.logical_value(expression)?,
+                kind if is_comparison(kind) => {
+                    let truth = self.comparison(kind, left, right)?;
+                    self.resize(truth, Type::I32, false)
+                }
+                kind => {
+                    let left_value = (self.value(left)?, self.program().value_type(left)?);
+                    let right_value = (self.value(right)?, self.program().value_type(right)?);
+                    self.binary(kind, left_value, right_value)?.0
+                }
+            },
+            Expression::Assignment {
+                target,
+                operator,
+                value,
+                ..
+            } => {
+                if let Expression::VariableDeclaration { .. } = target.as_ref() {
+                    self.statement(expression)?;
+                    return Ok(None);
+                }
+                let (address, data_type) = self.address(target)?;
+                let value_type = self.program().value_type(value)?;
+                let new_value = self.value(value)?;
+                let (new_value, new_type) = match compound_operator(operator.kind) {
+                    Some(kind) => {
+                        let old_value = self.load(address.clone(), &data_type)?;
+                        self.binary(
+                            kind,
+                            (old_value, data_type.to_owned()),
+                            (new_value, value_type),
+                        )?
+                    }
+                    None => (new_value, value_type),
+                };
+                let new_value = self.convert(new_value, &new_type, &data_type)?;
+                self.store(address, new_value.clone(), &data_type)?;
+                new_value
+            }
+            Expression::Increment { target, .. } | Expression::Decrement { target, .. } => {
+                let (address, data_type) = self.address(target)?;
+                let old_value = self.load(address.clone(), &data_type)?;
+                let one = (self.constant(1, Type::I32), primitive("int", false));
+                let kind = match expression {
+                    Expression::Increment { .. } => TokenKind::Plus,
+                    _ => TokenKind::Minus,
+                };
+                let (new_value, new_type) =
+                    self.binary(kind, (old_value.clone(), data_type.to_owned()), one)?;
+                let new_value = self.convert(new_value, &new_type, &data_type)?;
+                self.store(address, new_value, &data_type)?;
+                old_value
+            }
+            Expression::FunctionCall { left, values, .. } => match left.as_ref() {
+                Expression::Identifier(name, _)
+                    if matches!(name.as_str(), "sizeof" | "_Alignof") =>
+                {
+                    self.size_operator(name, values, expression)?
+                }
+                _ => return self.call(left, values),
+            },
+            Expression::VariableDeclaration { .. } => {
+                self.local(expression)?;
+                return Ok(None);
+            }
+            Expression::DataStructureInitialization { .. } => {
+                bail!("initializer list outside of a declaration")
+            }
+            expression => {
+                self.statement(expression)?;
+                return Ok(None);
+            }
+        }))
+    }
+
+    /// `sizeof` and `_Alignof` of a type or of an expression
+    fn size_operator(
+        &mut self,
+        name: &str,
+        values: &[Expression],
+        expression: &Expression,
+    ) -> Result<Self::Value> {
+        let [value] = values else {
+            bail!("'{name}' takes 1 operand, found: {}", values.len());
+        };
+        let program = self.program();
+        let data_type = match value {
+            Expression::DataTypeAccess { data_type, .. }
+                if program.symbols.resolution(value).is_none() =>
+            {
+                data_type.to_owned()
+            }
+            value => program.object_type(value)?,
+        };
+        let size = match name {
+            "sizeof" => program.layout.size_of(&data_type)?,
+            _ => program.layout.align_of(&data_type)?,
+        };
+        let data_type = program.scalar_type(&program.object_type(expression)?)?;
+        Ok(self.constant(size as i64, data_type))
+    }
+
+    fn prefix(&mut self, kind: TokenKind, value: &'a Expression) -> Result<Self::Value> {
+        if kind == TokenKind::Not {
+            let operand = self.scalar(value)?;
+            let data_type = self.type_of(&operand);
+            let zero = self.constant(0, data_type);
+            let truth = self.compare(Condition::Equal, operand, zero);
+            return Ok(self.resize(truth, Type::I32, false));
+        }
+        let data_type = self.program().value_type(value)?;
+        let promoted = promote(&data_type);
+        let operand = self.value(value)?;
+        let operand = self.convert(operand, &data_type, &promoted)?;
+        let operator = match kind {
+            TokenKind::Plus => return Ok(operand),
+            TokenKind::Minus => UnaryOperator::Negate,
+            TokenKind::BitwiseNot => UnaryOperator::Not,
+            kind => bail!("unsupported prefix operator '{}'", kind.to_str()),
+        };
+        if let Some(constant) = self.as_constant(&operand) {
+            let folded = match operator {
+                UnaryOperator::Negate => constant.wrapping_neg(),
+                UnaryOperator::Not => !constant,
+            };
+            let data_type = self.type_of(&operand);
+            return Ok(self.constant(data_type.wrap(folded), data_type));
+        }
+        Ok(self.unary_instruction(operator, operand))
+    }
+
+    /// `i1` result of comparing two scalars
+    fn comparison(
+        &mut self,
+        kind: TokenKind,
+        left: &'a Expression,
+        right: &'a Expression,
+    ) -> Result<Self::Value> {
+        let (left_type, right_type) = (
+            self.program().value_type(left)?,
+            self.program().value_type(right)?,
+        );
+        let left = self.value(left)?;
+        let right = self.value(right)?;
+        let (left, right, signed) = if is_integer(&left_type) && is_integer(&right_type) {
+            let common = usual_arithmetic_conversion(&left_type, &right_type);
+            (
+                self.convert(left, &left_type, &common)?,
+                self.convert(right, &right_type, &common)?,
+                self.program().is_signed(&common),
+            )
+        } else {
+            // pointers are compared as addresses, a 0 on the other side is the null pointer
+            let pointer = DataType::Pointer(Box::new(primitive("void", false)));
+            (
+                self.convert(left, &left_type, &pointer)?,
+                self.convert(right, &right_type, &pointer)?,
+                false,
+            )
+        };
+        let condition = match (kind, signed) {
+            (TokenKind::Equals, _) => Condition::Equal,
+            (TokenKind::NotEquals, _) => Condition::NotEqual,
+            (TokenKind::Less, true) => Condition::SignedLess,
+            (TokenKind::LessEquals, true) => Condition::SignedLessEqual,
+            (TokenKind::Greater, true) => Condition::SignedGreater,
+            (TokenKind::GreaterEquals, true) => Condition::SignedGreaterEqual,
+            (TokenKind::Less, false) => Condition::UnsignedLess,
+            (TokenKind::LessEquals, false) => Condition::UnsignedLessEqual,
+            (TokenKind::Greater, false) => Condition::UnsignedGreater,
+            (_, false) => Condition::UnsignedGreaterEqual,
+            (_, true) => Condition::SignedGreaterEqual,
+        };
+        Ok(self.compare(condition, left, right))
+    }
+
+    /// Arithmetic and bitwise operators on integers and pointers, and the type of the result
+    fn binary(
+        &mut self,
+        kind: TokenKind,
+        (left, left_type): (Self::Value, DataType),
+        (right, right_type): (Self::Value, DataType),
+    ) -> Result<(Self::Value, DataType)> {
+        let (left_type, right_type) = (
+            self.program().resolve(&left_type),
+            self.program().resolve(&right_type),
+        );
+        match (&left_type, &right_type) {
+            (DataType::Pointer(inside), DataType::Pointer(_)) if kind == TokenKind::Minus => {
+                let difference_type = primitive("long", false);
+                let left = self.convert_instruction(Conversion::PointerToInteger, left, Type::I64);
+                let right =
+                    self.convert_instruction(Conversion::PointerToInteger, right, Type::I64);
+                let bytes =
+                    self.binary_instruction(BinaryOperator::Subtract, left, right, Type::I64);
+                let size = self.program().size_of(inside)?.max(1) as i64;
+                if size == 1 {
+                    return Ok((bytes, difference_type));
+                }
+                return Ok((self.element_count(bytes, size), difference_type));
+            }
+            (DataType::Pointer(inside), index_type)
+                if matches!(kind, TokenKind::Plus | TokenKind::Minus) =>
+            {
+                let negate = kind == TokenKind::Minus;
+                let pointer = self.pointer_offset(left, right, index_type, inside, negate)?;
+                return Ok((pointer, left_type.to_owned()));
+            }
+            (index_type, DataType::Pointer(inside)) if kind == TokenKind::Plus => {
+                let pointer = self.pointer_offset(right, left, index_type, inside, false)?;
+                return Ok((pointer, right_type.to_owned()));
+            }
+            _ => {}
+        }
+        if !is_integer(&left_type) || !is_integer(&right_type) {
+            bail!(
+                "unsupported operands to '{}': '{left_type}' and '{right_type}'",
+                kind.to_str()
+            );
+        }
+
+        let shift = matches!(
+            kind,
+            TokenKind::BitwiseShiftLeft | TokenKind::BitwiseShiftRight
+        );
+        let common = if shift {
+            promote(&left_type)
+        } else {
+            usual_arithmetic_conversion(&left_type, &right_type)
+        };
+        let signed = self.program().is_signed(&common);
+        let left = self.convert(left, &left_type, &common)?;
+        let right = self.convert(right, &right_type, &common)?;
+        let operator = match kind {
+            TokenKind::Plus => BinaryOperator::Add,
+            TokenKind::Minus => BinaryOperator::Subtract,
+            TokenKind::Star => BinaryOperator::Multiply,
+            TokenKind::Slash if signed => BinaryOperator::SignedDivide,
+            TokenKind::Slash => BinaryOperator::UnsignedDivide,
+            TokenKind::Percent if signed => BinaryOperator::SignedRemainder,
+            TokenKind::Percent => BinaryOperator::UnsignedRemainder,
+            TokenKind::Reference => BinaryOperator::And,
+            TokenKind::BitwiseOr => BinaryOperator::Or,
+            TokenKind::BitwiseXor => BinaryOperator::Xor,
+            TokenKind::BitwiseShiftLeft => BinaryOperator::ShiftLeft,
+            TokenKind::BitwiseShiftRight if signed => BinaryOperator::ArithmeticShiftRight,
+            TokenKind::BitwiseShiftRight => BinaryOperator::LogicalShiftRight,
+            kind => bail!("unsupported binary operator '{}'", kind.to_str()),
+        };
+        let data_type = self.program().scalar_type(&common)?;
+        Ok((
+            self.binary_instruction(operator, left, right, data_type),
+            common,
+        ))
+    }
+}
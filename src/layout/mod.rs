@@ -0,0 +1,241 @@
+pub mod target;
+
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    layout::target::{PrimitiveLayout, Target},
+    parser::{
+        expression::{AlignAs, Property},
+        types::DataType,
+    },
+    semantic::constant::{ConstantContext, resolve_typedefs},
+};
+
+/// Where a member of a struct or union ends up
+#[derive(Debug, Clone)]
+pub struct FieldLayout {
+    pub name: String,
+    pub data_type: DataType,
+    pub offset: u64,
+    pub size: u64,
+    pub alignment: u64,
+}
+
+/// Bytes inside of a struct or union that no member uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hole {
+    pub offset: u64,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Layout {
+    pub size: u64,
+    pub alignment: u64,
+    /// only structs and unions have fields and holes
+    pub fields: Vec<FieldLayout>,
+    pub holes: Vec<Hole>,
+}
+
+impl Layout {
+    fn scalar(layout: PrimitiveLayout) -> Self {
+        Self {
+            size: layout.size,
+            alignment: layout.alignment,
+            fields: Vec::new(),
+            holes: Vec::new(),
+        }
+    }
+
+    pub fn field(&self, name: &str) -> Option<&FieldLayout> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    pub fn padding(&self) -> u64 {
+        self.holes.iter().map(|hole| hole.size).sum()
+    }
+}
+
+fn bytes(amount: u64) -> String {
+    match amount {
+        1 => "1 byte".to_owned(),
+        amount => format!("{amount} bytes"),
+    }
+}
+
+/// Computes sizes, alignments and member offsets of types for a target ABI.
+/// Typedef names are looked up through the context.
+pub struct LayoutEngine<'a, C: ConstantContext> {
+    pub target: &'a Target,
+    context: &'a C,
+}
+
+impl<'a, C: ConstantContext> LayoutEngine<'a, C> {
+    pub fn new(target: &'a Target, context: &'a C) -> Self {
+        Self { target, context }
+    }
+
+    pub fn size_of(&self, data_type: &DataType) -> Result<u64> {
+        Ok(self.layout(data_type)?.size)
+    }
+
+    pub fn align_of(&self, data_type: &DataType) -> Result<u64> {
+        Ok(self.layout(data_type)?.alignment)
+    }
+
+    pub fn layout(&self, data_type: &DataType) -> Result<Layout> {
+        match resolve_typedefs(self.context, data_type) {
+            DataType::Data { name, .. } => match self.target.primitives.get(&name) {
+                Some(layout) => Ok(Layout::scalar(*layout)),
+                None if name == "void" => bail!("'void' is an incomplete type, it has no size"),
+                None => bail!("unknown type name '{name}'"),
+            },
+            DataType::Pointer(_) => Ok(Layout::scalar(self.target.pointer)),
+            DataType::Enum { .. } => Ok(Layout::scalar(self.target.enumeration)),
+            DataType::Array { length, inside } => {
+                let inside = self.layout(&inside)?;
+                Ok(Layout {
+                    size: inside.size * length as u64,
+                    alignment: inside.alignment,
+                    fields: Vec::new(),
+                    holes: Vec::new(),
+                })
+            }
+            DataType::Struct {
+                properties,
+                packing,
+            } => self.record(&properties, packing, false),
+            DataType::Union {
+                properties,
+                packing,
+            } => self.record(&properties, packing, true),
+            DataType::Function { .. } => bail!("function types have no size"),
+        }
+    }
+
+    /// Alignment of a member: its natural alignment capped by `#pragma pack`, then raised by
+    /// `_Alignas`, which `#pragma pack` can't lower
+    fn member_alignment(
+        &self,
+        property: &Property,
+        natural: u64,
+        packing: Option<u64>,
+    ) -> Result<u64> {
+        let mut alignment = match packing {
+            Some(packing) => natural.min(packing),
+            None => natural,
+        };
+        if let Some(alignas) = &property.alignas {
+            let requested = match alignas {
+                AlignAs::Bytes(bytes) => *bytes,
+                AlignAs::Type(data_type) => self.align_of(data_type)?,
+            };
+            alignment = alignment.max(requested);
+        }
+        Ok(alignment)
+    }
+
+    fn record(&self, properties: &[Property], packing: Option<u64>, union: bool) -> Result<Layout> {
+        let mut fields = Vec::new();
+        let mut holes = Vec::new();
+        let mut offset: u64 = 0;
+        let mut alignment = 1;
+
+        for property in properties {
+            let layout = self
+                .layout(&property.var_type)
+                .with_context(|| format!("member '{}'", property.var_name))?;
+            let member_alignment = self.member_alignment(property, layout.alignment, packing)?;
+            alignment = alignment.max(member_alignment);
+
+            let member_offset = if union {
+                0
+            } else {
+                let aligned = offset.next_multiple_of(member_alignment);
+                if aligned != offset {
+                    holes.push(Hole {
+                        offset,
+                        size: aligned - offset,
+                    });
+                }
+                aligned
+            };
+            offset = if union {
+                offset.max(layout.size)
+            } else {
+                member_offset + layout.size
+            };
+
+            fields.push(FieldLayout {
+                name: property.var_name.to_owned(),
+                data_type: property.var_type.to_owned(),
+                offset: member_offset,
+                size: layout.size,
+                alignment: member_alignment,
+            });
+        }
+
+        let size = offset.next_multiple_of(alignment);
+        if size != offset {
+            holes.push(Hole {
+                offset,
+                size: size - offset,
+            });
+        }
+        Ok(Layout {
+            size,
+            alignment,
+            fields,
+            holes,
+        })
+    }
+
+    /// Human readable layout of a type, with every member and padding hole of structs and unions
+    pub fn report(&self, name: &str, data_type: &DataType) -> Result<String> {
+        let layout = self
+            .layout(data_type)
+            .with_context(|| format!("layout of '{name}'"))?;
+        let resolved = resolve_typedefs(self.context, data_type);
+        let keyword = match resolved {
+            DataType::Struct { .. } => "struct",
+            DataType::Union { .. } => "union",
+            _ => {
+                return Ok(format!(
+                    "{} /* size: {}, alignment: {} */\n",
+                    data_type.declaration(name),
+                    layout.size,
+                    layout.alignment
+                ));
+            }
+        };
+
+        let declarations = layout
+            .fields
+            .iter()
+            .map(|field| format!("{};", field.data_type.declaration(&field.name)))
+            .collect::<Vec<_>>();
+        let width = declarations.iter().map(String::len).max().unwrap_or(0);
+
+        let mut output = format!("{keyword} {name} {{ /* target: {} */\n", self.target.name);
+        let mut holes = layout.holes.iter().peekable();
+        for (field, declaration) in layout.fields.iter().zip(&declarations) {
+            while let Some(hole) = holes.next_if(|hole| hole.offset <= field.offset) {
+                output += &format!("    /* {} of padding */\n", bytes(hole.size));
+            }
+            output += &format!(
+                "    {declaration:width$} /* offset: {}, size: {}, alignment: {} */\n",
+                field.offset, field.size, field.alignment
+            );
+        }
+        for hole in holes {
+            output += &format!("    /* {} of padding */\n", bytes(hole.size));
+        }
+        output += &format!(
+            "}}; /* size: {}, alignment: {}, padding: {} */\n",
+            layout.size,
+            layout.alignment,
+            bytes(layout.padding())
+        );
+        Ok(output)
+    }
+}
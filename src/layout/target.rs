@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result, bail};
+
+use crate::parser::types::PRIMITIVE_TYPE_NAMES;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrimitiveLayout {
+    pub size: u64,
+    pub alignment: u64,
+}
+
+impl PrimitiveLayout {
+    pub fn new(size: u64, alignment: u64) -> Self {
+        Self { size, alignment }
+    }
+}
+
+/// Sizes and alignments of the basic types of an ABI. Everything else is built out of them.
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub name: String,
+    /// every name from `PRIMITIVE_TYPE_NAMES` except for `void`
+    pub primitives: HashMap<String, PrimitiveLayout>,
+    pub pointer: PrimitiveLayout,
+    /// enums are stored as an `int` by every supported ABI
+    pub enumeration: PrimitiveLayout,
+    pub char_signed: bool,
+}
+
+/// names accepted by `Target::by_name`, the first one of each is the canonical one
+pub const TARGET_NAMES: [&[&str]; 4] = [
+    &["x86_64-sysv", "x86_64", "lp64"],
+    &["ilp32", "i386", "x86"],
+    &["llp64", "x86_64-windows", "windows"],
+    &["aarch64", "arm64"],
+];
+
+impl Default for Target {
+    fn default() -> Self {
+        Self::x86_64_sysv()
+    }
+}
+
+impl Target {
+    fn new(name: &str, long: u64, pointer: u64, double_alignment: u64, char_signed: bool) -> Self {
+        let primitives = [
+            ("bool", PrimitiveLayout::new(1, 1)),
+            ("char", PrimitiveLayout::new(1, 1)),
+            ("short", PrimitiveLayout::new(2, 2)),
+            ("int", PrimitiveLayout::new(4, 4)),
+            ("long", PrimitiveLayout::new(long, long)),
+            ("float", PrimitiveLayout::new(4, 4)),
+            ("double", PrimitiveLayout::new(8, double_alignment)),
+        ];
+        Self {
+            name: name.to_owned(),
+            primitives: primitives
+                .into_iter()
+                .map(|(name, layout)| (name.to_owned(), layout))
+                .collect(),
+            pointer: PrimitiveLayout::new(pointer, pointer),
+            enumeration: PrimitiveLayout::new(4, 4),
+            char_signed,
+        }
+    }
+
+    /// LP64, x86-64 System V (Linux, macOS, BSDs)
+    pub fn x86_64_sysv() -> Self {
+        Self::new("x86_64-sysv", 8, 8, 8, true)
+    }
+    /// ILP32, i386 System V, where `double` inside of a struct is only aligned to 4
+    pub fn ilp32() -> Self {
+        Self::new("ilp32", 4, 4, 4, true)
+    }
+    /// LLP64, 64 bit Windows, `long` stays 4 bytes
+    pub fn llp64() -> Self {
+        Self::new("llp64", 4, 8, 8, true)
+    }
+    /// LP64 AAPCS64, the same sizes as x86-64 but `char` is unsigned
+    pub fn aarch64() -> Self {
+        Self::new("aarch64", 8, 8, 8, false)
+    }
+
+    pub fn by_name(name: &str) -> Result<Self> {
+        let Some(names) = TARGET_NAMES.iter().find(|names| names.contains(&name)) else {
+            bail!(
+                "unknown target: '{name}', expected one of: {}",
+                TARGET_NAMES.map(|names| names.join(" | ")).join(", ")
+            )
+        };
+        Ok(match names[0] {
+            "x86_64-sysv" => Self::x86_64_sysv(),
+            "ilp32" => Self::ilp32(),
+            "llp64" => Self::llp64(),
+            _ => Self::aarch64(),
+        })
+    }
+
+    /// Applies `NAME=SIZE[:ALIGNMENT]`, the alignment defaults to the size.
+    /// `pointer` and `enum` can be changed the same way as primitive types.
+    pub fn override_size(&mut self, definition: &str) -> Result<()> {
+        let (name, value) = definition
+            .split_once('=')
+            .with_context(|| format!("expected 'NAME=SIZE[:ALIGNMENT]', found: '{definition}'"))?;
+        let (size, alignment) = value.split_once(':').unwrap_or((value, value));
+        let size = size
+            .parse::<u64>()
+            .with_context(|| format!("invalid size: '{size}'"))?;
+        let alignment = alignment
+            .parse::<u64>()
+            .with_context(|| format!("invalid alignment: '{alignment}'"))?;
+        if !alignment.is_power_of_two() {
+            bail!("alignment has to be a power of 2, found: {alignment}");
+        }
+        let layout = PrimitiveLayout::new(size, alignment);
+
+        match name {
+            "pointer" => self.pointer = layout,
+            "enum" => self.enumeration = layout,
+            name if name != "void" && PRIMITIVE_TYPE_NAMES.contains(&name) => {
+                self.primitives.insert(name.to_owned(), layout);
+            }
+            name => bail!("size of '{name}' can't be changed"),
+        }
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@ use std::collections::{HashMap, HashSet};
 use anyhow::{Context, Result, bail};
 use log::warn;
 
+use crate::diagnostics::ErrorLine;
 use crate::lexer::{
     patterns::{TokenPattern, setup_token_patters},
     token::{Token, TokenKind},
@@ -12,6 +13,8 @@ mod patterns;
 pub mod token;
 mod tokenization_functions;
 
+pub use tokenization_functions::{character_value, string_bytes};
+
 pub struct Lexer {
     contents: Vec<char>,
     pub i: usize,
@@ -33,7 +36,6 @@ impl Lexer {
             None => '\n',
         }
     }
-    #[must_use]
     pub fn expect(&mut self, expected: char) -> Result<char> {
         let current = self.advance();
         if current != expected {
@@ -41,6 +43,14 @@ impl Lexer {
         }
         Ok(current)
     }
+    /// true if there is only white space between the start of the line and the current char
+    pub fn at_line_start(&self) -> bool {
+        self.contents[..self.i.min(self.contents.len())]
+            .iter()
+            .rev()
+            .take_while(|char| **char != '\n')
+            .all(|char| *char == ' ' || *char == '\t')
+    }
     pub fn advance(&mut self) -> char {
         self.i += 1;
         match self.contents.get(self.i - 1) {
@@ -60,7 +70,9 @@ pub fn tokenize(text: Vec<char>) -> Result<Vec<Token>> {
     .into_iter()
     .collect();
     let valid_number_token_chars: HashSet<char> = [
-        '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'x', 'a', 'b', 'c', 'd', 'e', 'f',
+        '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'x', 'a', 'b', 'c', 'd', 'e', 'f', 'X',
+        'A', 'B', 'C', 'D', 'E', 'F', // integer suffixes
+        'u', 'U', 'l', 'L',
     ]
     .into_iter()
     .collect();
@@ -76,6 +88,7 @@ pub fn tokenize(text: Vec<char>) -> Result<Vec<Token>> {
         ("true", TokenKind::True),
         ("false", TokenKind::False),
         ("struct", TokenKind::Struct),
+        ("union", TokenKind::Union),
         ("for", TokenKind::For),
         ("typedef", TokenKind::Typedef),
     ]);
@@ -115,14 +128,21 @@ pub fn tokenize(text: Vec<char>) -> Result<Vec<Token>> {
                 }
                 // advance
                 lexer.i += 1 + use_second_char as usize;
-                Token {
-                    value: String::new(),
-                    kind,
+                Token::new(String::new(), kind, current_line)
+            }
+            TokenPattern::Long(function) => {
+                let start = lexer.i;
+                let token = function(current_line, &mut lexer).with_context(|| ErrorLine {
                     line: current_line,
-                }
+                    message: "while running a token pattern function".to_owned(),
+                })?;
+                // line continuations inside of compiler data
+                current_line += lexer.contents[start..lexer.i]
+                    .iter()
+                    .filter(|char| **char == '\n')
+                    .count() as u16;
+                token
             }
-            TokenPattern::Long(function) => function(current_line, &mut lexer)
-                .context("while running a token pattern function")?,
         });
     }
 
@@ -2,10 +2,11 @@ use crate::lexer::{
     Lexer,
     token::{Token, TokenKind},
     tokenization_functions::{
-        handle_comments, handle_compiler_data, handle_identifier, handle_number, handle_string,
+        handle_character, handle_comments, handle_compiler_data, handle_identifier, handle_number,
+        handle_shift, handle_string,
     },
 };
-use anyhow::{Context, Result, bail};
+use anyhow::{Result, bail};
 use std::collections::HashMap;
 
 type TokenizationFunc = fn(u16, &mut Lexer) -> Result<Token>;
@@ -39,8 +40,7 @@ pub fn setup_token_patters() -> Result<HashMap<(char, char), TokenPattern>> {
     let patterns = patterns();
     let mut hashmap = HashMap::new();
 
-    let mut i = 0;
-    for pat in patterns {
+    for (i, pat) in patterns.into_iter().enumerate() {
         for start_char in pat.start_chars {
             let key = (start_char, pat.second_char);
             if hashmap.contains_key(&key) {
@@ -53,7 +53,6 @@ pub fn setup_token_patters() -> Result<HashMap<(char, char), TokenPattern>> {
 
             hashmap.insert(key, pat.pattern);
         }
-        i += 1;
     }
 
     Ok(hashmap)
@@ -67,10 +66,7 @@ pub fn pattern_for_current_char(lexer: &mut Lexer) -> Option<TokenPattern> {
     match lexer.token_patterns.get(&(current, next)) {
         Some(val) => Some(*val),
         // if there is no pattern with next char, then there might be one without it
-        None => match lexer.token_patterns.get(&(current, ' ')) {
-            Some(val) => Some(*val),
-            None => None,
-        },
+        None => lexer.token_patterns.get(&(current, ' ')).copied(),
     }
 }
 
@@ -85,7 +81,16 @@ fn patterns() -> Vec<TokenPatternInitialization> {
     vec![
         TokenPatternInitialization::new(vec!['/'], '/', TokenPattern::Long(handle_comments)),
         TokenPatternInitialization::new(vec!['"'], ' ', TokenPattern::Long(handle_string)),
+        TokenPatternInitialization::new(vec!['\''], ' ', TokenPattern::Long(handle_character)),
         TokenPatternInitialization::new(vec!['#'], ' ', TokenPattern::Long(handle_compiler_data)),
+        TokenPatternInitialization::new(
+            vec!['#'],
+            '#',
+            TokenPattern::Fast {
+                kind: TokenKind::HashHash,
+                use_second_char: true,
+            },
+        ),
         TokenPatternInitialization::new(NUMBERS.to_vec(), ' ', TokenPattern::Long(handle_number)),
         TokenPatternInitialization::new(
             SYMBOL_CHARS.to_vec(),
@@ -300,6 +305,14 @@ fn patterns() -> Vec<TokenPatternInitialization> {
                 use_second_char: false,
             },
         ),
+        TokenPatternInitialization::new(
+            vec!['%'],
+            '=',
+            TokenPattern::Fast {
+                kind: TokenKind::PercentEquals,
+                use_second_char: true,
+            },
+        ),
         TokenPatternInitialization::new(
             vec!['%'],
             ' ',
@@ -332,14 +345,7 @@ fn patterns() -> Vec<TokenPatternInitialization> {
                 use_second_char: true,
             },
         ),
-        TokenPatternInitialization::new(
-            vec!['<'],
-            '<',
-            TokenPattern::Fast {
-                kind: TokenKind::BitwiseShiftLeft,
-                use_second_char: true,
-            },
-        ),
+        TokenPatternInitialization::new(vec!['<'], '<', TokenPattern::Long(handle_shift)),
         TokenPatternInitialization::new(
             vec!['<'],
             ' ',
@@ -356,14 +362,7 @@ fn patterns() -> Vec<TokenPatternInitialization> {
                 use_second_char: true,
             },
         ),
-        TokenPatternInitialization::new(
-            vec!['>'],
-            '>',
-            TokenPattern::Fast {
-                kind: TokenKind::BitwiseShiftRight,
-                use_second_char: true,
-            },
-        ),
+        TokenPatternInitialization::new(vec!['>'], '>', TokenPattern::Long(handle_shift)),
         TokenPatternInitialization::new(
             vec!['>'],
             ' ',
@@ -400,10 +399,58 @@ fn patterns() -> Vec<TokenPatternInitialization> {
             vec!['='],
             ' ',
             TokenPattern::Fast {
-                kind: TokenKind::Equals,
+                kind: TokenKind::Assignment,
                 use_second_char: false,
             },
         ),
+        TokenPatternInitialization::new(
+            vec!['|'],
+            '=',
+            TokenPattern::Fast {
+                kind: TokenKind::BitwiseOrEquals,
+                use_second_char: true,
+            },
+        ),
+        TokenPatternInitialization::new(
+            vec!['|'],
+            ' ',
+            TokenPattern::Fast {
+                kind: TokenKind::BitwiseOr,
+                use_second_char: false,
+            },
+        ),
+        TokenPatternInitialization::new(
+            vec!['^'],
+            '=',
+            TokenPattern::Fast {
+                kind: TokenKind::BitwiseXorEquals,
+                use_second_char: true,
+            },
+        ),
+        TokenPatternInitialization::new(
+            vec!['^'],
+            ' ',
+            TokenPattern::Fast {
+                kind: TokenKind::BitwiseXor,
+                use_second_char: false,
+            },
+        ),
+        TokenPatternInitialization::new(
+            vec!['~'],
+            ' ',
+            TokenPattern::Fast {
+                kind: TokenKind::BitwiseNot,
+                use_second_char: false,
+            },
+        ),
+        TokenPatternInitialization::new(
+            vec!['&'],
+            '=',
+            TokenPattern::Fast {
+                kind: TokenKind::BitwiseAndEquals,
+                use_second_char: true,
+            },
+        ),
         TokenPatternInitialization::new(
             vec!['&'],
             ' ',
@@ -1,15 +1,58 @@
-#[derive(Debug, Clone)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Token {
     pub value: String,
     pub kind: TokenKind,
     pub line: u16, // needed for debugging
+    /// set when the token was produced by expanding a macro
+    pub expansion: Option<Box<MacroExpansion>>,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+impl Token {
+    pub fn new(value: String, kind: TokenKind, line: u16) -> Self {
+        Self {
+            value,
+            kind,
+            line,
+            expansion: None,
+        }
+    }
+
+    /// Text of the token as it would appear in the source code.
+    /// Keywords and operators don't store a value, so it is taken from the kind.
+    pub fn text(&self) -> String {
+        match self.kind {
+            TokenKind::String => format!("\"{}\"", self.value),
+            TokenKind::Character => format!("'{}'", self.value),
+            TokenKind::Comment => format!("//{}", self.value),
+            TokenKind::CompilerData => format!("#{}", self.value),
+            TokenKind::Identifier | TokenKind::Number | TokenKind::Other => self.value.to_owned(),
+            kind => kind.to_str().to_owned(),
+        }
+    }
+}
+
+/// Where a token produced by a macro came from. `line` of the token itself points at the place
+/// where the macro was used, this points back into the `#define`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MacroExpansion {
+    pub macro_name: String,
+    pub definition_file: String,
+    pub definition_line: u16,
+    pub invocation_line: u16,
+    /// expansion that the invocation itself was a part of, for macros used inside of other macros
+    pub parent: Option<Box<MacroExpansion>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TokenKind {
     Tab,
     Comment,
     CompilerData,
+    /// `#` and `##` outside of the start of a line, only valid inside of a macro definition
+    Hash,
+    HashHash,
 
     WhiteSpace,
     EndOfFile,
@@ -37,6 +80,7 @@ pub enum TokenKind {
     MinusEquals,
     StarEquals,
     SlashEquals,
+    PercentEquals,
 
     PlusPlus,
     MinusMinus,
@@ -54,12 +98,23 @@ pub enum TokenKind {
 
     BitwiseShiftLeft,
     BitwiseShiftRight,
+    BitwiseOr,
+    BitwiseXor,
+    BitwiseNot,
+
+    BitwiseShiftLeftEquals,
+    BitwiseShiftRightEquals,
+    BitwiseAndEquals,
+    BitwiseOrEquals,
+    BitwiseXorEquals,
 
     Assignment,
     Reference,
 
     Number,
     String,
+    /// `'a'`, the value is what was between the quotes, escapes are not decoded
+    Character,
     True,
     False,
 
@@ -72,6 +127,7 @@ pub enum TokenKind {
     For,
     Enum,
     Struct,
+    Union,
     Break,
     Other,
     Constant,
@@ -79,26 +135,28 @@ pub enum TokenKind {
 }
 
 impl TokenKind {
-    pub fn to_str(&self) -> &str {
+    pub fn to_str(self) -> &'static str {
         match self {
-            TokenKind::Tab => todo!(),
+            TokenKind::Tab => "\t",
             TokenKind::Comment => todo!(),
             TokenKind::CompilerData => todo!(),
-            TokenKind::WhiteSpace => todo!(),
-            TokenKind::EndOfFile => todo!(),
-            TokenKind::NextLine => todo!(),
-            TokenKind::OpenParen => todo!(),
-            TokenKind::CloseParen => todo!(),
-            TokenKind::OpenBracket => todo!(),
-            TokenKind::CloseBracket => todo!(),
-            TokenKind::OpenCurly => todo!(),
-            TokenKind::CloseCurly => todo!(),
-            TokenKind::Comma => todo!(),
-            TokenKind::Dot => todo!(),
-            TokenKind::SemiColon => todo!(),
-            TokenKind::Colon => todo!(),
-            TokenKind::Arrow => todo!(),
-            TokenKind::Question => todo!(),
+            TokenKind::Hash => "#",
+            TokenKind::HashHash => "##",
+            TokenKind::WhiteSpace => " ",
+            TokenKind::EndOfFile => "",
+            TokenKind::NextLine => "\n",
+            TokenKind::OpenParen => "(",
+            TokenKind::CloseParen => ")",
+            TokenKind::OpenBracket => "[",
+            TokenKind::CloseBracket => "]",
+            TokenKind::OpenCurly => "{",
+            TokenKind::CloseCurly => "}",
+            TokenKind::Comma => ",",
+            TokenKind::Dot => ".",
+            TokenKind::SemiColon => ";",
+            TokenKind::Colon => ":",
+            TokenKind::Arrow => "->",
+            TokenKind::Question => "?",
             TokenKind::Plus => "+",
             TokenKind::Minus => "-",
             TokenKind::Star => "*",
@@ -107,7 +165,8 @@ impl TokenKind {
             TokenKind::PlusEquals => "+=",
             TokenKind::MinusEquals => "-=",
             TokenKind::StarEquals => "*=",
-            TokenKind::SlashEquals => "*=",
+            TokenKind::SlashEquals => "/=",
+            TokenKind::PercentEquals => "%=",
             TokenKind::PlusPlus => "++",
             TokenKind::MinusMinus => "--",
             TokenKind::Equals => "==",
@@ -121,25 +180,36 @@ impl TokenKind {
             TokenKind::Or => "||",
             TokenKind::BitwiseShiftLeft => "<<",
             TokenKind::BitwiseShiftRight => ">>",
+            TokenKind::BitwiseOr => "|",
+            TokenKind::BitwiseXor => "^",
+            TokenKind::BitwiseNot => "~",
+            TokenKind::BitwiseShiftLeftEquals => "<<=",
+            TokenKind::BitwiseShiftRightEquals => ">>=",
+            TokenKind::BitwiseAndEquals => "&=",
+            TokenKind::BitwiseOrEquals => "|=",
+            TokenKind::BitwiseXorEquals => "^=",
             TokenKind::Assignment => "=",
             TokenKind::Reference => "&",
             TokenKind::Number => todo!(),
             TokenKind::String => todo!(),
+            // the text is in the value of the token, see `Token::text`
+            TokenKind::Character => "character",
             TokenKind::True => "true",
             TokenKind::False => "false",
             TokenKind::Identifier => todo!(),
-            TokenKind::Static => todo!(),
-            TokenKind::Return => todo!(),
-            TokenKind::If => todo!(),
-            TokenKind::Else => todo!(),
-            TokenKind::While => todo!(),
-            TokenKind::For => todo!(),
-            TokenKind::Enum => todo!(),
-            TokenKind::Struct => todo!(),
-            TokenKind::Break => todo!(),
+            TokenKind::Static => "static",
+            TokenKind::Return => "return",
+            TokenKind::If => "if",
+            TokenKind::Else => "else",
+            TokenKind::While => "while",
+            TokenKind::For => "for",
+            TokenKind::Enum => "enum",
+            TokenKind::Struct => "struct",
+            TokenKind::Union => "union",
+            TokenKind::Break => "break",
             TokenKind::Other => todo!(),
-            TokenKind::Constant => todo!(),
-            TokenKind::Typedef => todo!(),
+            TokenKind::Constant => "const",
+            TokenKind::Typedef => "typedef",
         }
     }
 }
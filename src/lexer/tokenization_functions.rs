@@ -2,7 +2,7 @@ use crate::lexer::{
     Lexer,
     token::{Token, TokenKind},
 };
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 pub fn handle_comments(line: u16, lexer: &mut Lexer) -> Result<Token> {
     lexer.expect('/')?;
     lexer.expect('/')?;
@@ -12,11 +12,7 @@ pub fn handle_comments(line: u16, lexer: &mut Lexer) -> Result<Token> {
         value += &lexer.advance().to_string();
     }
 
-    Ok(Token {
-        value,
-        kind: TokenKind::Comment,
-        line,
-    })
+    Ok(Token::new(value, TokenKind::Comment, line))
 }
 
 pub fn handle_number(line: u16, lexer: &mut Lexer) -> Result<Token> {
@@ -25,11 +21,7 @@ pub fn handle_number(line: u16, lexer: &mut Lexer) -> Result<Token> {
         value += &lexer.advance().to_string();
     }
 
-    Ok(Token {
-        value,
-        kind: TokenKind::Number,
-        line,
-    })
+    Ok(Token::new(value, TokenKind::Number, line))
 }
 
 pub fn handle_identifier(line: u16, lexer: &mut Lexer) -> Result<Token> {
@@ -42,32 +34,56 @@ pub fn handle_identifier(line: u16, lexer: &mut Lexer) -> Result<Token> {
     }
 
     match lexer.keywords.get(value.as_str()) {
-        Some(token_kind) => Ok(Token {
-            value: String::new(),
-            kind: *token_kind,
-            line,
-        }),
-        None => Ok(Token {
-            value,
-            kind: TokenKind::Identifier,
-            line,
-        }),
+        Some(token_kind) => Ok(Token::new(String::new(), *token_kind, line)),
+        None => Ok(Token::new(value, TokenKind::Identifier, line)),
     }
 }
 
+/// `<<` and `>>`, and `<<=` and `>>=` which are the only tokens three chars long
+pub fn handle_shift(line: u16, lexer: &mut Lexer) -> Result<Token> {
+    let first = lexer.advance();
+    lexer.expect(first)?;
+    let assignment = lexer.current() == '=';
+    if assignment {
+        lexer.advance();
+    }
+
+    let kind = match (first, assignment) {
+        ('<', false) => TokenKind::BitwiseShiftLeft,
+        ('<', true) => TokenKind::BitwiseShiftLeftEquals,
+        ('>', false) => TokenKind::BitwiseShiftRight,
+        ('>', true) => TokenKind::BitwiseShiftRightEquals,
+        _ => bail!("'{first}' does not start a shift"),
+    };
+    Ok(Token::new(String::new(), kind, line))
+}
+
 pub fn handle_compiler_data(line: u16, lexer: &mut Lexer) -> Result<Token> {
+    if !lexer.at_line_start() {
+        // stringification operator inside of a macro definition
+        lexer.expect('#')?;
+        return Ok(Token::new(String::new(), TokenKind::Hash, line));
+    }
     lexer.expect('#')?;
 
     let mut value = String::new();
     while lexer.current() != '\n' {
+        if lexer.current() == '\\' && lexer.i + 1 == lexer.contents.len() {
+            // a continuation at the end of the file continues into nothing
+            lexer.advance();
+            break;
+        }
+        if lexer.current() == '\\' && lexer.next() == '\n' {
+            // line continuation
+            lexer.advance();
+            lexer.advance();
+            value += " ";
+            continue;
+        }
         value += &lexer.advance().to_string();
     }
 
-    Ok(Token {
-        value,
-        kind: TokenKind::CompilerData,
-        line,
-    })
+    Ok(Token::new(value, TokenKind::CompilerData, line))
 }
 
 pub fn handle_string(line: u16, lexer: &mut Lexer) -> Result<Token> {
@@ -75,13 +91,102 @@ pub fn handle_string(line: u16, lexer: &mut Lexer) -> Result<Token> {
 
     let mut value = String::new();
     while lexer.current() != '"' {
+        if lexer.i >= lexer.contents.len() {
+            bail!("string literal is missing its closing '\"': \"{value}")
+        }
+        if lexer.current() == '\\' {
+            // escaped char, it might be a '"'
+            value += &lexer.advance().to_string();
+        }
         value += &lexer.advance().to_string();
     }
     lexer.expect('"')?;
 
-    Ok(Token {
-        value,
-        kind: TokenKind::String,
-        line,
-    })
+    Ok(Token::new(value, TokenKind::String, line))
+}
+
+pub fn handle_character(line: u16, lexer: &mut Lexer) -> Result<Token> {
+    lexer.expect('\'')?;
+
+    let mut value = String::new();
+    while lexer.current() != '\'' {
+        if lexer.i >= lexer.contents.len() || lexer.current() == '\n' {
+            bail!("character constant is missing its closing \"'\": '{value}")
+        }
+        if lexer.current() == '\\' {
+            value += &lexer.advance().to_string();
+        }
+        value += &lexer.advance().to_string();
+    }
+    lexer.expect('\'')?;
+
+    Ok(Token::new(value, TokenKind::Character, line))
+}
+
+/// Value of a character constant from what was between its quotes: `a`, `\n`, `\x41`, `\101`.
+/// Chars are signed, so `'\xff'` is -1 like in cc.
+pub fn character_value(value: &str) -> Result<i128> {
+    let mut chars = value.chars();
+    let output = match (chars.next(), chars.next()) {
+        (Some('\\'), Some(escaped)) => match escaped {
+            'n' => '\n' as u32,
+            't' => '\t' as u32,
+            'r' => '\r' as u32,
+            'a' => 0x07,
+            'b' => 0x08,
+            'f' => 0x0c,
+            'v' => 0x0b,
+            '\\' | '\'' | '"' | '?' => escaped as u32,
+            'x' => u32::from_str_radix(chars.as_str(), 16)
+                .with_context(|| format!("invalid hex escape in character constant: '{value}'"))?,
+            '0'..='7' => {
+                let digits = format!("{escaped}{}", chars.as_str());
+                u32::from_str_radix(&digits, 8).with_context(|| {
+                    format!("invalid octal escape in character constant: '{value}'")
+                })?
+            }
+            other => bail!("unknown escape sequence in character constant: '\\{other}'"),
+        },
+        (Some(char), None) => char as u32,
+        (None, _) => bail!("empty character constant"),
+        (Some(_), Some(_)) => bail!("multi-character constants are not supported: '{value}'"),
+    };
+    if output > 0xff {
+        bail!("character constant is too large for its type: '{value}'");
+    }
+    Ok(output as u8 as i8 as i128)
+}
+
+/// Bytes of a string literal with escapes decoded, without the terminating zero
+pub fn string_bytes(value: &str) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    let mut chars = value.chars().peekable();
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            let mut buffer = [0; 4];
+            output.extend_from_slice(char.encode_utf8(&mut buffer).as_bytes());
+            continue;
+        }
+        let mut escape = String::from('\\');
+        match chars.next() {
+            Some('x') => {
+                escape.push('x');
+                while let Some(digit) = chars.next_if(char::is_ascii_hexdigit) {
+                    escape.push(digit);
+                }
+            }
+            Some(digit @ '0'..='7') => {
+                escape.push(digit);
+                for _ in 0..2 {
+                    if let Some(digit) = chars.next_if(|char| matches!(char, '0'..='7')) {
+                        escape.push(digit);
+                    }
+                }
+            }
+            Some(other) => escape.push(other),
+            None => bail!("string literal ends with a backslash: \"{value}\""),
+        }
+        output.push(character_value(&escape)? as u8);
+    }
+    Ok(output)
 }
@@ -0,0 +1,15 @@
+pub mod analysis;
+pub mod bytecode;
+pub mod cli;
+pub mod code_gen;
+pub mod diagnostics;
+pub mod format;
+pub mod interpreter;
+pub mod ir;
+pub mod layout;
+pub mod lexer;
+pub mod lint;
+pub mod lsp;
+pub mod parser;
+pub mod preprocessor;
+pub mod semantic;
@@ -0,0 +1,48 @@
+use crate::{
+    diagnostics::Diagnostic, lexer::token::TokenKind, lint::Context, parser::expression::Expression,
+};
+
+pub fn check(context: &Context) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut work: Vec<&Expression> = context.expressions.iter().collect();
+    while let Some(expression) = work.pop() {
+        let condition = match expression {
+            Expression::If { condition, .. }
+            | Expression::While { condition, .. }
+            | Expression::For { condition, .. }
+            | Expression::Else {
+                condition: Some(condition),
+                ..
+            } => Some(condition.as_ref()),
+            _ => None,
+        };
+        if let Some(condition) = condition {
+            assignments(condition, &mut diagnostics);
+        }
+        work.extend(expression.children());
+    }
+    diagnostics
+}
+
+/// `=` in the condition, also in `&&`, `||` and `!`, but not inside of parentheses
+fn assignments(expression: &Expression, diagnostics: &mut Vec<Diagnostic>) {
+    match expression {
+        Expression::Assignment {
+            operator,
+            debug_data,
+            ..
+        } if operator.kind == TokenKind::Assignment => diagnostics.push(
+            Diagnostic::warning("assignment used as a condition".to_owned(), debug_data)
+                .with_suggestion(
+                    "compare with `==`, or put the assignment in another pair of parentheses"
+                        .to_owned(),
+                ),
+        ),
+        Expression::Binary { left, right, .. } => {
+            assignments(left, diagnostics);
+            assignments(right, diagnostics);
+        }
+        Expression::Prefix { value, .. } => assignments(value, diagnostics),
+        _ => {}
+    }
+}
@@ -0,0 +1,65 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::{diagnostics::Severity, lint::Rule};
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Level {
+    Off,
+    Note,
+    Warning,
+    Error,
+}
+
+/// Layout of the TOML file:
+/// ```toml
+/// [rules]
+/// magic-number = "off"
+/// shadowed-variable = "error"
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct File {
+    #[serde(default)]
+    rules: BTreeMap<String, Level>,
+}
+
+/// Severity of every rule, `None` turns the rule off
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    severities: HashMap<Rule, Option<Severity>>,
+}
+
+impl Config {
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let file: File = toml::from_str(text)?;
+        let mut config = Config::default();
+        for (name, level) in file.rules {
+            let rule = Rule::by_name(&name)?;
+            let severity = match level {
+                Level::Off => None,
+                Level::Note => Some(Severity::Note),
+                Level::Warning => Some(Severity::Warning),
+                Level::Error => Some(Severity::Error),
+            };
+            config.severities.insert(rule, severity);
+        }
+        Ok(config)
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading a lint config at path: '{path}'"))?;
+        Config::from_toml(&text).with_context(|| format!("lint config: '{path}'"))
+    }
+
+    pub fn severity(&self, rule: Rule) -> Option<Severity> {
+        match self.severities.get(&rule) {
+            Some(severity) => *severity,
+            None => Some(rule.default_severity()),
+        }
+    }
+}
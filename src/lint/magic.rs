@@ -0,0 +1,57 @@
+use std::collections::HashSet;
+
+use crate::{
+    diagnostics::Diagnostic, lexer::token::TokenKind, lint::Context,
+    parser::expression::Expression, preprocessor::expression::parse_integer,
+};
+
+/// small enough to be obvious: counting, halving, comparing with zero
+const ALLOWED: [u64; 3] = [0, 1, 2];
+
+pub fn check(context: &Context) -> Vec<Diagnostic> {
+    // numbers that macros put into the code are named by the macro
+    let written: HashSet<(u16, u64)> = context
+        .tokens
+        .iter()
+        .filter(|token| token.kind == TokenKind::Number)
+        .filter_map(|token| Some((token.line, parse_integer(&token.value).ok()?.0)))
+        .collect();
+    let mut diagnostics = Vec::new();
+    for expression in context.expressions {
+        if let Expression::Function { inside, .. } = expression {
+            for statement in inside {
+                numbers(statement, &written, &mut diagnostics);
+            }
+        }
+    }
+    diagnostics
+}
+
+fn numbers(
+    expression: &Expression,
+    written: &HashSet<(u16, u64)>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    match expression {
+        Expression::Number(value, _, debug_data) => {
+            if !ALLOWED.contains(value) && written.contains(&(debug_data.line, *value)) {
+                diagnostics.push(
+                    Diagnostic::warning(format!("magic number {value}"), debug_data)
+                        .with_span(&value.to_string())
+                        .with_suggestion(
+                            "give it a name with a `#define`, an enum or a variable".to_owned(),
+                        ),
+                );
+            }
+        }
+        // the variable is the name: `int retries = 5;`
+        Expression::Assignment { target, .. }
+            if matches!(target.as_ref(), Expression::VariableDeclaration { .. }) => {}
+        Expression::Static { .. } => {}
+        expression => {
+            for child in expression.children() {
+                numbers(child, written, diagnostics);
+            }
+        }
+    }
+}
@@ -0,0 +1,160 @@
+mod condition;
+mod config;
+mod magic;
+mod shadow;
+mod type_name;
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Result, bail};
+
+pub use config::Config;
+
+use crate::{
+    diagnostics::{Diagnostic, Severity},
+    lexer::token::{Token, TokenKind},
+    parser::expression::{DebugData, Expression},
+    semantic::scope::SymbolTable,
+};
+
+/// Check for code that compiles but is easy to get wrong, selected in the config by its name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    /// a local variable with the name of a variable in a scope around it
+    ShadowedVariable,
+    /// `if (x = 1)`, extra parentheses mark it as meant: `if ((x = 1))`
+    AssignmentInCondition,
+    /// number literals in function bodies other than 0, 1 and 2, outside of initializers
+    MagicNumber,
+    /// `i32 i32 = 7;`
+    TypeNameVariable,
+}
+
+pub const RULES: &[Rule] = &[
+    Rule::ShadowedVariable,
+    Rule::AssignmentInCondition,
+    Rule::MagicNumber,
+    Rule::TypeNameVariable,
+];
+
+impl Rule {
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::ShadowedVariable => "shadowed-variable",
+            Rule::AssignmentInCondition => "assignment-in-condition",
+            Rule::MagicNumber => "magic-number",
+            Rule::TypeNameVariable => "type-name-variable",
+        }
+    }
+
+    pub fn by_name(name: &str) -> Result<Self> {
+        match RULES.iter().find(|rule| rule.name() == name) {
+            Some(rule) => Ok(*rule),
+            None => {
+                let names = RULES.iter().map(|rule| rule.name()).collect::<Vec<_>>();
+                bail!(
+                    "unknown lint rule: '{name}', expected one of: {}",
+                    names.join(", ")
+                )
+            }
+        }
+    }
+
+    /// Severity when the config doesn't set one
+    pub fn default_severity(self) -> Severity {
+        match self {
+            Rule::MagicNumber => Severity::Note,
+            _ => Severity::Warning,
+        }
+    }
+
+    fn run(self, context: &Context) -> Vec<Diagnostic> {
+        match self {
+            Rule::ShadowedVariable => shadow::check(context),
+            Rule::AssignmentInCondition => condition::check(context),
+            Rule::MagicNumber => magic::check(context),
+            Rule::TypeNameVariable => type_name::check(context),
+        }
+    }
+}
+
+/// What the rules look at, only findings in `file` itself are reported, not in what it includes
+pub struct Context<'a> {
+    pub file: &'a str,
+    pub expressions: &'a [Expression],
+    pub symbols: &'a SymbolTable,
+    /// tokens of the file before preprocessing, with comments and white space
+    pub tokens: &'a [Token],
+}
+
+/// Runs the rules that the config doesn't turn off. The messages end with the name of the rule,
+/// `// rip-allow(rule, ...)` turns rules off for the line of the comment, or for the next one
+/// when the comment is alone on its line.
+pub fn check(context: &Context, config: &Config) -> Vec<Diagnostic> {
+    let (allowed, mut diagnostics) = suppressions(context);
+    for &rule in RULES {
+        let Some(severity) = config.severity(rule) else {
+            continue;
+        };
+        for mut diagnostic in rule.run(context) {
+            let line = diagnostic.debug_data.line;
+            if diagnostic.debug_data.file != context.file
+                || allowed
+                    .get(&line)
+                    .is_some_and(|rules| rules.contains(&rule))
+            {
+                continue;
+            }
+            diagnostic.severity = severity;
+            diagnostic.message = format!("{} [{}]", diagnostic.message, rule.name());
+            diagnostics.push(diagnostic);
+        }
+    }
+    diagnostics.sort_by_key(|diagnostic| diagnostic.debug_data.line);
+    diagnostics
+}
+
+/// Rules allowed on each line, and warnings for names in the comments that aren't rules
+fn suppressions(context: &Context) -> (HashMap<u16, HashSet<Rule>>, Vec<Diagnostic>) {
+    let mut allowed: HashMap<u16, HashSet<Rule>> = HashMap::new();
+    let mut diagnostics = Vec::new();
+    let mut code_lines = HashSet::new();
+    for token in context.tokens {
+        if !matches!(
+            token.kind,
+            TokenKind::Comment | TokenKind::WhiteSpace | TokenKind::Tab | TokenKind::NextLine
+        ) {
+            code_lines.insert(token.line);
+            continue;
+        }
+        if token.kind != TokenKind::Comment {
+            continue;
+        }
+        let Some((_, names)) = token.value.split_once("rip-allow(") else {
+            continue;
+        };
+        let Some((names, _)) = names.split_once(')') else {
+            continue;
+        };
+        let line = match code_lines.contains(&token.line) {
+            true => token.line,
+            false => token.line + 1,
+        };
+        for name in names.split(',').map(str::trim) {
+            match Rule::by_name(name) {
+                Ok(rule) => {
+                    allowed.entry(line).or_default().insert(rule);
+                }
+                Err(err) => diagnostics.push(Diagnostic::warning(
+                    format!("rip-allow: {err}"),
+                    &DebugData {
+                        line: token.line,
+                        file: context.file.to_owned(),
+                        expansion: None,
+                    },
+                )),
+            }
+        }
+    }
+    (allowed, diagnostics)
+}
@@ -0,0 +1,44 @@
+use crate::{
+    diagnostics::Diagnostic,
+    lint::Context,
+    semantic::scope::{ScopeKind, SymbolKind},
+};
+
+pub fn check(context: &Context) -> Vec<Diagnostic> {
+    let symbols = context.symbols;
+    let mut diagnostics = Vec::new();
+    for symbol in &symbols.symbols {
+        if !matches!(symbol.kind, SymbolKind::Variable | SymbolKind::Parameter)
+            || symbols.scopes[symbol.scope].kind == ScopeKind::File
+        {
+            continue;
+        }
+        let Some(outer) = symbols.scopes[symbol.scope]
+            .parent
+            .and_then(|parent| symbols.lookup(parent, &symbol.name))
+            .map(|id| symbols.symbol(id))
+        else {
+            continue;
+        };
+        // a variable declared after the block isn't visible in it
+        let same_file = outer.debug_data.file == symbol.debug_data.file;
+        if !matches!(outer.kind, SymbolKind::Variable | SymbolKind::Parameter)
+            || same_file && outer.debug_data.line > symbol.debug_data.line
+        {
+            continue;
+        }
+        let place = match same_file {
+            true => format!("line {}", outer.debug_data.line),
+            false => format!("{}:{}", outer.debug_data.file, outer.debug_data.line),
+        };
+        diagnostics.push(
+            Diagnostic::warning(
+                format!("'{}' shadows the variable declared on {place}", symbol.name),
+                &symbol.debug_data,
+            )
+            .with_span(&symbol.name)
+            .with_suggestion("rename one of them".to_owned()),
+        );
+    }
+    diagnostics
+}
@@ -0,0 +1,22 @@
+use crate::{diagnostics::Diagnostic, lint::Context, semantic::scope::SymbolKind};
+
+pub fn check(context: &Context) -> Vec<Diagnostic> {
+    let symbols = context.symbols;
+    symbols
+        .symbols
+        .iter()
+        .filter(|symbol| matches!(symbol.kind, SymbolKind::Variable | SymbolKind::Parameter))
+        .filter(|symbol| symbols.typedef(&symbol.name).is_some())
+        .map(|symbol| {
+            Diagnostic::warning(
+                format!("variable '{}' has the name of a type", symbol.name),
+                &symbol.debug_data,
+            )
+            .with_span(&symbol.name)
+            .with_suggestion(format!(
+                "rename the variable, '{}' reads as the type",
+                symbol.name
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,556 @@
+use std::collections::{HashMap, HashSet};
+
+use serde_json::{Value, json};
+
+use crate::{
+    diagnostics::{Diagnostic, ErrorLine, Severity},
+    layout::target::Target,
+    lexer::{
+        self,
+        token::{Token, TokenKind},
+    },
+    parser::{self, types::DataType, types::PRIMITIVE_TYPE_NAMES},
+    preprocessor::Preprocessor,
+    semantic::{
+        scope::{BUILTIN_FUNCTIONS, SymbolId, SymbolKind, SymbolTable},
+        type_check,
+    },
+};
+
+/// Kinds of semantic tokens, the index in this list is what the client gets
+pub const TOKEN_TYPES: [&str; 11] = [
+    "keyword",
+    "type",
+    "function",
+    "variable",
+    "parameter",
+    "enumMember",
+    "number",
+    "string",
+    "comment",
+    "macro",
+    "operator",
+];
+
+/// Token of the source before preprocessing, positions are in UTF-16 code units from 0 like
+/// in the protocol
+#[derive(Debug)]
+struct Span {
+    line: u32,
+    start: u32,
+    length: u32,
+    kind: TokenKind,
+    value: String,
+}
+
+/// Open file, analyzed again whenever it changes
+pub struct Document {
+    path: String,
+    lines: Vec<String>,
+    spans: Vec<Span>,
+    /// symbol that each identifier in `spans` resolves to
+    resolved: HashMap<usize, SymbolId>,
+    symbols: Option<SymbolTable>,
+    pub diagnostics: Vec<Value>,
+}
+
+impl Document {
+    /// Runs the lexer, preprocessor, parser and semantic passes as far as they get
+    pub fn analyze(path: &str, text: &str) -> Self {
+        let mut document = Document {
+            path: path.to_owned(),
+            lines: text.lines().map(str::to_owned).collect(),
+            spans: Vec::new(),
+            resolved: HashMap::new(),
+            symbols: None,
+            diagnostics: Vec::new(),
+        };
+        let tokens = match lexer::tokenize(text.chars().collect()) {
+            Ok(tokens) => tokens,
+            Err(err) => {
+                document.error(&err);
+                return document;
+            }
+        };
+        document.spans = document.locate(&tokens);
+
+        let mut tokens = match Preprocessor::new(path.to_owned()).run(tokens) {
+            Ok(tokens) => tokens,
+            Err(err) => {
+                document.error(&err);
+                return document;
+            }
+        };
+        tokens.retain(|token| {
+            !matches!(
+                token.kind,
+                TokenKind::Tab | TokenKind::Comment | TokenKind::NextLine | TokenKind::WhiteSpace
+            )
+        });
+        tokens.push(Token::new(String::new(), TokenKind::EndOfFile, 0));
+        let target = Target::default();
+        let expressions = match parser::parse(tokens, path.to_owned(), &target) {
+            Ok(expressions) => expressions,
+            Err(err) => {
+                document.error(&err);
+                return document;
+            }
+        };
+
+        let symbols = crate::semantic::scope::resolve(&expressions);
+        let types = type_check::check(&expressions, &symbols, &target);
+        let mut flow = crate::analysis::flow::check(&expressions, &symbols);
+        flow.extend(crate::analysis::dataflow::check(&expressions, &symbols));
+        for diagnostic in symbols
+            .diagnostics
+            .iter()
+            .chain(&types.diagnostics)
+            .chain(&flow)
+        {
+            if diagnostic.debug_data.file == path {
+                document.diagnostic(diagnostic);
+            }
+        }
+        document.resolve(&symbols);
+        document.symbols = Some(symbols);
+        document
+    }
+
+    /// Error that stopped the analysis, on the line where it happened if it is known
+    fn error(&mut self, err: &anyhow::Error) {
+        let line = err
+            .downcast_ref::<ErrorLine>()
+            .map_or(1, |error_line| error_line.line);
+        let message = err
+            .chain()
+            .last()
+            .map_or_else(|| err.to_string(), |cause| cause.to_string());
+        self.diagnostics
+            .push(self.diagnostic_json(line, Severity::Error, &message));
+    }
+
+    fn diagnostic(&mut self, diagnostic: &Diagnostic) {
+        let mut message = match &diagnostic.suggestion {
+            Some(suggestion) => format!("{}\nhelp: {suggestion}", diagnostic.message),
+            None => diagnostic.message.to_owned(),
+        };
+        for (_, note) in diagnostic.expansion_notes() {
+            message += &format!("\nnote: {note}");
+        }
+        let mut json =
+            self.diagnostic_json(diagnostic.debug_data.line, diagnostic.severity, &message);
+        let line = diagnostic.debug_data.line.saturating_sub(1) as u32;
+        if let Some(text) = &diagnostic.span
+            && let Some(span) = self
+                .spans
+                .iter()
+                .find(|span| span.line == line && &span.value == text)
+        {
+            json["range"] = range(line, span.start, span.start + span.length);
+        }
+        self.diagnostics.push(json);
+    }
+
+    /// The whole line is marked unless the diagnostic has a span that is one of its tokens
+    fn diagnostic_json(&self, line: u16, severity: Severity, message: &str) -> Value {
+        let line = line.saturating_sub(1) as u32;
+        let end = self
+            .lines
+            .get(line as usize)
+            .map_or(0, |text| text.encode_utf16().count());
+        json!({
+            "range": range(line, 0, end as u32),
+            "severity": match severity {
+                Severity::Error => 1,
+                Severity::Warning => 2,
+                Severity::Note => 3,
+            },
+            "source": "rip",
+            "message": message,
+        })
+    }
+
+    /// Tokens only know their line, so every token is looked up in it after the one before it
+    fn locate(&self, tokens: &[Token]) -> Vec<Span> {
+        let mut spans = Vec::new();
+        let mut cursor = (0, 0);
+        for token in tokens {
+            let text = match token.kind {
+                TokenKind::Tab
+                | TokenKind::WhiteSpace
+                | TokenKind::NextLine
+                | TokenKind::EndOfFile
+                | TokenKind::Other => continue,
+                // a directive can go on in the next lines, only its first line is marked
+                TokenKind::CompilerData => format!("#{}", token.value)
+                    .split(' ')
+                    .next()
+                    .unwrap_or("#")
+                    .to_owned(),
+                _ => token.text(),
+            };
+            let line = token.line.saturating_sub(1) as usize;
+            let Some(source) = self.lines.get(line) else {
+                continue;
+            };
+            if cursor.0 != line {
+                cursor = (line, 0);
+            }
+            let Some(offset) = source[cursor.1..].find(&text) else {
+                continue;
+            };
+            let start = cursor.1 + offset;
+            let length = if token.kind == TokenKind::CompilerData {
+                source.len() - start
+            } else {
+                text.len()
+            };
+            cursor.1 = start + text.len();
+            spans.push(Span {
+                line: line as u32,
+                start: utf16(&source[..start]),
+                length: utf16(&source[start..start + length]),
+                kind: token.kind,
+                value: token.value.to_owned(),
+            });
+        }
+        spans
+    }
+
+    /// Links every identifier to a symbol. The passes know the line of each declaration and
+    /// use, the uses of one name on a line are taken in order, declarations first.
+    fn resolve(&mut self, symbols: &SymbolTable) {
+        let mut uses: HashMap<(u32, &str), Vec<SymbolId>> = HashMap::new();
+        let declarations = symbols
+            .symbols
+            .iter()
+            .enumerate()
+            .map(|(id, symbol)| (id, &symbol.name, &symbol.debug_data));
+        let references = symbols
+            .references
+            .iter()
+            .map(|reference| (reference.symbol, &reference.name, &reference.debug_data));
+        for (id, name, debug_data) in declarations.chain(references) {
+            if debug_data.file == self.path {
+                let line = debug_data.line.saturating_sub(1) as u32;
+                uses.entry((line, name)).or_default().push(id);
+            }
+        }
+
+        let mut seen: HashMap<(u32, &str), usize> = HashMap::new();
+        for (index, span) in self.spans.iter().enumerate() {
+            if span.kind != TokenKind::Identifier {
+                continue;
+            }
+            let key = (span.line, span.value.as_str());
+            let Some(ids) = uses.get(&key) else {
+                continue;
+            };
+            let count = seen.entry(key).or_default();
+            let id = ids.get(*count).or(ids.last());
+            *count += 1;
+            if let Some(id) = id {
+                self.resolved.insert(index, *id);
+            }
+        }
+    }
+
+    fn span_at(&self, line: u32, character: u32) -> Option<usize> {
+        self.spans.iter().position(|span| {
+            span.line == line
+                && span.start <= character
+                && character <= span.start + span.length
+                && span.kind == TokenKind::Identifier
+        })
+    }
+
+    fn symbol_at(&self, line: u32, character: u32) -> Option<SymbolId> {
+        self.span_at(line, character)
+            .and_then(|index| self.resolved.get(&index).copied())
+    }
+
+    /// Span of the name in the declaration of the symbol
+    fn declaration(&self, id: SymbolId) -> Option<usize> {
+        let symbol = self.symbols.as_ref()?.symbol(id);
+        let line = symbol.debug_data.line.saturating_sub(1) as u32;
+        self.spans
+            .iter()
+            .enumerate()
+            .filter(|(_, span)| span.line == line && span.value == symbol.name)
+            .find(|(index, _)| self.resolved.get(index) == Some(&id))
+            .map(|(index, _)| index)
+    }
+
+    fn span_range(&self, index: usize) -> Value {
+        let span = &self.spans[index];
+        range(span.line, span.start, span.start + span.length)
+    }
+
+    /// `textDocument/definition`, the uri is the one of the document
+    pub fn definition(&self, uri: &str, line: u32, character: u32) -> Value {
+        let Some(id) = self.symbol_at(line, character) else {
+            return Value::Null;
+        };
+        match self.declaration(id) {
+            Some(index) => json!({ "uri": uri, "range": self.span_range(index) }),
+            None => Value::Null,
+        }
+    }
+
+    /// `textDocument/references`
+    pub fn references(&self, uri: &str, line: u32, character: u32, declaration: bool) -> Value {
+        let Some(id) = self.symbol_at(line, character) else {
+            return Value::Null;
+        };
+        let skipped = if declaration {
+            None
+        } else {
+            self.declaration(id)
+        };
+        let locations = (0..self.spans.len())
+            .filter(|index| self.resolved.get(index) == Some(&id) && Some(*index) != skipped)
+            .map(|index| json!({ "uri": uri, "range": self.span_range(index) }))
+            .collect::<Vec<_>>();
+        Value::Array(locations)
+    }
+
+    /// `textDocument/hover` with the declaration of the symbol as C
+    pub fn hover(&self, line: u32, character: u32) -> Value {
+        let (Some(index), Some(symbols)) = (self.span_at(line, character), &self.symbols) else {
+            return Value::Null;
+        };
+        let Some(id) = self.resolved.get(&index) else {
+            return Value::Null;
+        };
+        let symbol = symbols.symbol(*id);
+        let declaration = match (&symbol.kind, &symbol.data_type) {
+            (
+                SymbolKind::Function,
+                DataType::Function {
+                    output,
+                    parameters,
+                    variadic,
+                },
+            ) => {
+                let mut parameters = parameters
+                    .iter()
+                    .map(DataType::to_string)
+                    .collect::<Vec<_>>();
+                if *variadic {
+                    parameters.push("...".to_owned());
+                }
+                format!(
+                    "{}({})",
+                    output.declaration(&symbol.name),
+                    parameters.join(", ")
+                )
+            }
+            (SymbolKind::Typedef, data_type) => {
+                format!("typedef {}", data_type.declaration(&symbol.name))
+            }
+            (SymbolKind::EnumConstant, DataType::Enum { fields }) => {
+                let value = fields
+                    .iter()
+                    .find(|field| field.name == symbol.name)
+                    .map_or(0, |field| field.value);
+                format!("{} = {value}", symbol.name)
+            }
+            (_, data_type) => data_type.declaration(&symbol.name),
+        };
+        let kind = match symbol.kind {
+            SymbolKind::Variable => "variable",
+            SymbolKind::Parameter => "parameter",
+            SymbolKind::Function => "function",
+            SymbolKind::EnumConstant => "enum constant",
+            SymbolKind::Typedef => "typedef",
+        };
+        json!({
+            "contents": {
+                "kind": "markdown",
+                "value": format!("```c\n{declaration}\n```\n{kind} declared at line {}", symbol.debug_data.line),
+            },
+            "range": self.span_range(index),
+        })
+    }
+
+    /// `textDocument/documentSymbol`: functions, typedefs of structs, unions and enums with
+    /// their members, other typedefs and global variables
+    pub fn document_symbols(&self) -> Value {
+        let Some(symbols) = &self.symbols else {
+            return json!([]);
+        };
+        let mut output = Vec::new();
+        for (id, symbol) in symbols.symbols.iter().enumerate() {
+            if symbol.scope != 0
+                || symbol.debug_data.file != self.path
+                || symbol.kind == SymbolKind::EnumConstant
+            {
+                continue;
+            }
+            let Some(index) = self.declaration(id) else {
+                continue;
+            };
+            // https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#symbolKind
+            let (kind, children) = match (&symbol.kind, &symbol.data_type) {
+                (SymbolKind::Function, _) => (12, Vec::new()),
+                (SymbolKind::Typedef, DataType::Struct { properties, .. })
+                | (SymbolKind::Typedef, DataType::Union { properties, .. }) => {
+                    let fields = properties
+                        .iter()
+                        .map(|property| {
+                            (property.var_name.as_str(), 8, property.var_type.to_string())
+                        })
+                        .collect();
+                    (23, fields)
+                }
+                (SymbolKind::Typedef, DataType::Enum { fields }) => {
+                    let constants = fields
+                        .iter()
+                        .map(|field| (field.name.as_str(), 22, field.value.to_string()))
+                        .collect();
+                    (10, constants)
+                }
+                (SymbolKind::Typedef, _) => (26, Vec::new()),
+                _ => (13, Vec::new()),
+            };
+            let range = self.span_range(index);
+            let children = children
+                .into_iter()
+                .map(|(name, kind, detail)| {
+                    json!({
+                        "name": name,
+                        "detail": detail,
+                        "kind": kind,
+                        "range": range,
+                        "selectionRange": range,
+                    })
+                })
+                .collect::<Vec<_>>();
+            output.push(json!({
+                "name": symbol.name,
+                "detail": symbol.data_type.to_string(),
+                "kind": kind,
+                "range": range,
+                "selectionRange": range,
+                "children": children,
+            }));
+        }
+        Value::Array(output)
+    }
+
+    /// `textDocument/semanticTokens/full`, encoded relative to the token before like the
+    /// protocol wants
+    pub fn semantic_tokens(&self) -> Value {
+        let builtins = HashSet::from(BUILTIN_FUNCTIONS);
+        let mut data = Vec::new();
+        let mut previous = (0, 0);
+        for (index, span) in self.spans.iter().enumerate() {
+            let token_type = match span.kind {
+                TokenKind::Comment => "comment",
+                TokenKind::CompilerData | TokenKind::Hash | TokenKind::HashHash => "macro",
+                TokenKind::Number => "number",
+                TokenKind::String | TokenKind::Character => "string",
+                TokenKind::True
+                | TokenKind::False
+                | TokenKind::Static
+                | TokenKind::Return
+                | TokenKind::If
+                | TokenKind::Else
+                | TokenKind::While
+                | TokenKind::For
+                | TokenKind::Enum
+                | TokenKind::Struct
+                | TokenKind::Union
+                | TokenKind::Break
+                | TokenKind::Constant
+                | TokenKind::Typedef => "keyword",
+                TokenKind::Identifier => {
+                    let kind = self
+                        .resolved
+                        .get(&index)
+                        .and_then(|id| Some(self.symbols.as_ref()?.symbol(*id).kind));
+                    match kind {
+                        Some(SymbolKind::Function) => "function",
+                        Some(SymbolKind::Variable) => "variable",
+                        Some(SymbolKind::Parameter) => "parameter",
+                        Some(SymbolKind::EnumConstant) => "enumMember",
+                        Some(SymbolKind::Typedef) => "type",
+                        None if PRIMITIVE_TYPE_NAMES.contains(&span.value.as_str())
+                            || span.value == "unsigned"
+                            || span.value == "signed" =>
+                        {
+                            "type"
+                        }
+                        None if builtins.contains(span.value.as_str()) => "keyword",
+                        None => continue,
+                    }
+                }
+                TokenKind::Plus
+                | TokenKind::Minus
+                | TokenKind::Star
+                | TokenKind::Slash
+                | TokenKind::Percent
+                | TokenKind::PlusEquals
+                | TokenKind::MinusEquals
+                | TokenKind::StarEquals
+                | TokenKind::SlashEquals
+                | TokenKind::PercentEquals
+                | TokenKind::BitwiseShiftLeftEquals
+                | TokenKind::BitwiseShiftRightEquals
+                | TokenKind::BitwiseAndEquals
+                | TokenKind::BitwiseOrEquals
+                | TokenKind::BitwiseXorEquals
+                | TokenKind::PlusPlus
+                | TokenKind::MinusMinus
+                | TokenKind::Equals
+                | TokenKind::NotEquals
+                | TokenKind::Less
+                | TokenKind::LessEquals
+                | TokenKind::Greater
+                | TokenKind::GreaterEquals
+                | TokenKind::Not
+                | TokenKind::And
+                | TokenKind::Or
+                | TokenKind::BitwiseShiftLeft
+                | TokenKind::BitwiseShiftRight
+                | TokenKind::BitwiseOr
+                | TokenKind::BitwiseXor
+                | TokenKind::BitwiseNot
+                | TokenKind::Assignment
+                | TokenKind::Reference
+                | TokenKind::Arrow
+                | TokenKind::Question => "operator",
+                _ => continue,
+            };
+            let token_type = TOKEN_TYPES
+                .iter()
+                .position(|name| *name == token_type)
+                .unwrap_or_default();
+            let start = if span.line == previous.0 {
+                span.start - previous.1
+            } else {
+                span.start
+            };
+            data.extend([
+                span.line - previous.0,
+                start,
+                span.length,
+                token_type as u32,
+                0,
+            ]);
+            previous = (span.line, span.start);
+        }
+        json!({ "data": data })
+    }
+}
+
+fn range(line: u32, start: u32, end: u32) -> Value {
+    json!({
+        "start": { "line": line, "character": start },
+        "end": { "line": line, "character": end },
+    })
+}
+
+fn utf16(text: &str) -> u32 {
+    text.encode_utf16().count() as u32
+}
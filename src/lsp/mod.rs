@@ -0,0 +1,226 @@
+mod document;
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, Read, Write},
+};
+
+use anyhow::Result;
+use serde_json::{Value, json};
+
+use crate::lsp::document::{Document, TOKEN_TYPES};
+
+/// JSON-RPC error code of requests that the server doesn't know
+const METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC error code of requests that came after `shutdown`
+const INVALID_REQUEST: i64 = -32600;
+/// JSON-RPC error code of messages that aren't JSON or have no `Content-Length`
+const PARSE_ERROR: i64 = -32700;
+
+/// Language server speaking JSON-RPC with `Content-Length` headers, like over stdio. Documents
+/// are synced in full and analyzed again on every change, which also publishes their
+/// diagnostics. Returns once the client sends `exit` or closes the input.
+pub fn serve(mut input: impl BufRead, mut output: impl Write) -> Result<()> {
+    let mut server = Server {
+        documents: HashMap::new(),
+        shut_down: false,
+    };
+    while let Some(message) = read_message(&mut input)? {
+        let message = match message {
+            Ok(message) => message,
+            // the id of a message that can't be read is unknown
+            Err(error) => {
+                let response = json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": PARSE_ERROR, "message": error },
+                });
+                write_message(&mut output, &response)?;
+                continue;
+            }
+        };
+        let method = message["method"].as_str().unwrap_or_default();
+        if method == "exit" {
+            return Ok(());
+        }
+        let params = &message["params"];
+        match message.get("id") {
+            Some(id) => {
+                let response = match server.request(method, params) {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err((code, message)) => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": code, "message": message },
+                    }),
+                };
+                write_message(&mut output, &response)?;
+            }
+            None => {
+                for notification in server.notification(method, params) {
+                    write_message(&mut output, &notification)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Next message, `None` at the end of the input. A message with broken headers or a body that
+/// isn't JSON is an `Err` with what is wrong with it, the next one is read after it.
+fn read_message(input: &mut impl BufRead) -> Result<Option<Result<Value, String>>> {
+    let mut length = None;
+    let mut invalid = None;
+    loop {
+        let mut header = Vec::new();
+        if input.read_until(b'\n', &mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = String::from_utf8_lossy(&header);
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            match value.trim().parse::<usize>() {
+                Ok(value) => length = Some(value),
+                Err(_) => invalid = Some(format!("invalid Content-Length: '{}'", value.trim())),
+            }
+        }
+    }
+    if let Some(invalid) = invalid {
+        return Ok(Some(Err(invalid)));
+    }
+    let Some(length) = length else {
+        return Ok(Some(Err(
+            "message without a Content-Length header".to_owned()
+        )));
+    };
+    let mut body = Vec::new();
+    input.take(length as u64).read_to_end(&mut body)?;
+    if body.len() < length {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_slice(&body).map_err(|error| {
+        format!("message that isn't valid JSON: {error}")
+    })))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()?;
+    Ok(())
+}
+
+struct Server {
+    /// by uri
+    documents: HashMap<String, Document>,
+    shut_down: bool,
+}
+
+impl Server {
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        if self.shut_down {
+            return Err((INVALID_REQUEST, "the server was shut down".to_owned()));
+        }
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let line = params["position"]["line"].as_u64().unwrap_or_default() as u32;
+        let character = params["position"]["character"].as_u64().unwrap_or_default() as u32;
+        let document = self.documents.get(uri);
+        Ok(match (method, document) {
+            ("initialize", _) => json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "referencesProvider": true,
+                    "hoverProvider": true,
+                    "documentSymbolProvider": true,
+                    "semanticTokensProvider": {
+                        "legend": { "tokenTypes": TOKEN_TYPES, "tokenModifiers": [] },
+                        "full": true,
+                    },
+                },
+                "serverInfo": { "name": "rip", "version": env!("CARGO_PKG_VERSION") },
+            }),
+            ("shutdown", _) => {
+                self.shut_down = true;
+                Value::Null
+            }
+            ("textDocument/definition", Some(document)) => {
+                document.definition(uri, line, character)
+            }
+            ("textDocument/references", Some(document)) => {
+                let declaration = params["context"]["includeDeclaration"]
+                    .as_bool()
+                    .unwrap_or_default();
+                document.references(uri, line, character, declaration)
+            }
+            ("textDocument/hover", Some(document)) => document.hover(line, character),
+            ("textDocument/documentSymbol", Some(document)) => document.document_symbols(),
+            ("textDocument/semanticTokens/full", Some(document)) => document.semantic_tokens(),
+            (
+                "textDocument/definition"
+                | "textDocument/references"
+                | "textDocument/hover"
+                | "textDocument/documentSymbol"
+                | "textDocument/semanticTokens/full",
+                None,
+            ) => Value::Null,
+            (method, _) => return Err((METHOD_NOT_FOUND, format!("unknown method: '{method}'"))),
+        })
+    }
+
+    /// Handles a notification, the result is what the server sends back
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or_default()
+            .to_owned();
+        let text = match method {
+            "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+            // only full syncs were asked for, the last change has the whole text
+            "textDocument/didChange" => params["contentChanges"]
+                .as_array()
+                .and_then(|changes| changes.last())
+                .and_then(|change| change["text"].as_str()),
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![publish(&uri, Vec::new())];
+            }
+            _ => None,
+        };
+        let Some(text) = text else {
+            return Vec::new();
+        };
+        let document = Document::analyze(&path(&uri), text);
+        let notification = publish(&uri, document.diagnostics.to_owned());
+        self.documents.insert(uri, document);
+        vec![notification]
+    }
+}
+
+fn publish(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+/// Path of a `file://` uri, includes are looked up next to it
+fn path(uri: &str) -> String {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    let mut bytes = Vec::new();
+    let mut input = path.bytes();
+    while let Some(byte) = input.next() {
+        let escaped = (byte == b'%')
+            .then(|| {
+                let digits = [input.next()?, input.next()?];
+                u8::from_str_radix(std::str::from_utf8(&digits).ok()?, 16).ok()
+            })
+            .flatten();
+        bytes.push(escaped.unwrap_or(byte));
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
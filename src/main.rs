@@ -1,33 +1,255 @@
-mod lexer;
-pub mod parser;
-//mod code_gen;
-//mod code_gen;
-
 use std::{
     collections::HashSet,
     fs::{self},
+    io::Write,
+    path::{Path, PathBuf},
 };
 
 //INFO: Default includes: use log::*;use anyhow::{Result,bail,Context};
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use log::*;
 
-use crate::lexer::token::{Token, TokenKind};
+use rip::{
+    analysis::{self, call_graph::CallGraph, cfg::Cfg, metrics::FileMetrics},
+    bytecode::{self, Program, vm::Limits},
+    cli::{
+        self, Command, CommandLineMacro, Dot, Emit, FormatOptions, MetricsFormat, MetricsOptions,
+        Options,
+    },
+    code_gen, diagnostics, format, interpreter, ir,
+    layout::LayoutEngine,
+    lexer,
+    lexer::token::{Token, TokenKind},
+    lint,
+    parser::{self, expression::Expression, json::Ast},
+    preprocessor,
+    semantic::{self, scope::SymbolKind},
+};
 
 fn main() {
     colog::init();
     info!("init colog");
-    if let Err(err) = parse() {
-        error!("{err:?}")
+    let command = match cli::parse_command(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(err) => {
+            error!("{err:?}");
+            std::process::exit(1);
+        }
+    };
+    match command {
+        Command::Compile(options) => {
+            if let Err(err) = parse(&options) {
+                error!("{err:?}");
+                std::process::exit(1);
+            }
+        }
+        Command::Format(options) => {
+            if let Err(err) = format_files(&options) {
+                error!("{err:?}");
+                std::process::exit(1);
+            }
+        }
+        Command::Metrics(options) => {
+            if let Err(err) = metrics(&options) {
+                error!("{err:?}");
+                std::process::exit(1);
+            }
+        }
     }
 }
 
-fn parse() -> Result<()> {
-    const FILE_PATH: &str = "test_files/test.c";
+/// `rip metrics`, prints the report and fails when a function is over one of the thresholds or
+/// a file can't be parsed
+fn metrics(metrics: &MetricsOptions) -> Result<()> {
+    let options = &metrics.options;
+    let files = c_files(&options.file)?;
+
+    let mut reports = Vec::new();
+    let mut failures = 0;
+    for file in &files {
+        let file = file.display().to_string();
+        let parsed = fs::read_to_string(&file)
+            .with_context(|| format!("reading a file at path: '{file}'"))
+            .and_then(|source| front_end(options, &file, &source));
+        match parsed {
+            Ok(expressions) => reports.push(FileMetrics::new(&file, &expressions)),
+            Err(err) => {
+                error!("metrics failed: {file}: {err:?}");
+                failures += 1;
+            }
+        }
+    }
+    let report = match metrics.format {
+        MetricsFormat::Table => analysis::metrics::table(&reports),
+        MetricsFormat::Json => analysis::metrics::to_json(&reports),
+        MetricsFormat::Csv => analysis::metrics::csv(&reports),
+    };
+    write_output(options, report.as_bytes())?;
+
+    let over: Vec<_> = reports
+        .iter()
+        .flat_map(|report| report.check(&metrics.thresholds))
+        .collect();
+    diagnostics::log_diagnostics(&over);
+    if failures > 0 {
+        bail!("{failures} of {} files couldn't be parsed", files.len());
+    }
+    if !over.is_empty() {
+        bail!(
+            "{} metrics of functions are over the thresholds",
+            over.len()
+        );
+    }
+    Ok(())
+}
+
+/// `rip fmt`, rewrites the files that aren't formatted, only lists them with `--check` or prints
+/// them with `--stdout`
+fn format_files(options: &FormatOptions) -> Result<()> {
+    let mut files = Vec::new();
+    for path in &options.files {
+        files.extend(c_files(path)?);
+    }
 
-    let mut tokens = tokenize_file(FILE_PATH)
-        .with_context(|| format!("tokenization of a file at path: '{FILE_PATH}'"))?;
+    let mut unformatted = 0;
+    let mut failures = 0;
+    for file in &files {
+        let file = file.display().to_string();
+        match format_file(options, &file) {
+            Ok(true) => {}
+            Ok(false) => unformatted += 1,
+            Err(err) => {
+                error!("formatting failed: {file}: {err:?}");
+                failures += 1;
+            }
+        }
+    }
+    if failures > 0 {
+        bail!("{failures} of {} files couldn't be formatted", files.len());
+    }
+    if options.check && unformatted > 0 {
+        bail!("{unformatted} of {} files aren't formatted", files.len());
+    }
+    Ok(())
+}
+
+/// Whether the file was already formatted
+fn format_file(options: &FormatOptions, file_path: &str) -> Result<bool> {
+    let source = fs::read_to_string(file_path)
+        .with_context(|| format!("reading a file at path: '{file_path}'"))?;
+    let formatted = format::format(&source, &options.config)?;
+    if options.stdout {
+        print!("{formatted}");
+    }
+    if formatted == source {
+        return Ok(true);
+    }
+    if options.check {
+        let line = source
+            .lines()
+            .zip(formatted.lines())
+            .position(|(source, formatted)| source != formatted)
+            .unwrap_or(source.lines().count().min(formatted.lines().count()));
+        error!(
+            "not formatted: {file_path}, first difference on line {}",
+            line + 1
+        );
+    } else if !options.stdout {
+        fs::write(file_path, formatted)
+            .with_context(|| format!("writing a file at path: '{file_path}'"))?;
+        info!("formatted: {file_path}");
+    }
+    Ok(false)
+}
 
+/// The file, or the `.c` files in the directory sorted by name
+fn c_files(path: &str) -> Result<Vec<PathBuf>> {
+    let directory = Path::new(path);
+    if !directory.is_dir() {
+        return Ok(vec![directory.to_path_buf()]);
+    }
+    let mut files = fs::read_dir(directory)
+        .with_context(|| format!("reading a directory at path: '{path}'"))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    files.retain(|file| file.extension().is_some_and(|extension| extension == "c"));
+    files.sort();
+    Ok(files)
+}
+
+fn parse(options: &Options) -> Result<()> {
+    if options.round_trip {
+        return round_trip(options);
+    }
+    let file_path = options.file.as_str();
+    if options.vm || options.disassemble {
+        let bytes = fs::read(file_path)
+            .with_context(|| format!("reading a file at path: '{file_path}'"))?;
+        if Program::is_bytecode(&bytes) {
+            let program = Program::from_bytes(&bytes)
+                .with_context(|| format!("loading bytecode from: '{file_path}'"))?;
+            return execute_bytecode(options, &program);
+        }
+    }
+
+    let source = fs::read_to_string(file_path)
+        .with_context(|| format!("reading a file at path: '{file_path}'"))?;
+    let (tokens, mut preprocessor) = preprocess(options, file_path, &source)?;
+
+    if options.preprocess_only {
+        print!(
+            "{}",
+            preprocessor::output::write_preprocessed(&tokens, file_path, options.keep_comments)
+        );
+        return Ok(());
+    }
+
+    let expressions = parse_tokens(options, file_path, tokens)?;
+    if options.print_c {
+        print!("{}", code_gen::c::print(&expressions));
+    }
+    if options.ast_json {
+        print!("{}", Ast::new(file_path, expressions.to_owned()).to_json());
+    }
+    if let Some(dot) = options.dot {
+        let graph = match dot {
+            Dot::Ast => code_gen::dot::ast(&expressions),
+            Dot::CallGraph => code_gen::dot::call_graph(&CallGraph::build(&expressions)),
+            Dot::Cfg => code_gen::dot::cfg(&Cfg::functions(&expressions)),
+        };
+        write_output(options, graph.as_bytes())?;
+    }
+    analyze(options, &source, &expressions, &mut preprocessor)
+}
+
+/// Preprocessed tokens, and the preprocessor with every macro still defined at the end
+fn preprocess(
+    options: &Options,
+    file_path: &str,
+    source: &str,
+) -> Result<(Vec<Token>, preprocessor::Preprocessor)> {
+    let tokens = lexer::tokenize(source.chars().collect())
+        .with_context(|| format!("tokenization of a file at path: '{file_path}'"))?;
+
+    let mut preprocessor = preprocessor::Preprocessor::new(file_path.to_owned());
+    for command_line_macro in &options.macros {
+        match command_line_macro {
+            CommandLineMacro::Define(definition) => preprocessor.define(definition),
+            CommandLineMacro::Undefine(name) => preprocessor.undefine(name),
+        }
+        .with_context(|| format!("command line macro: {command_line_macro:?}"))?;
+    }
+    let tokens = preprocessor
+        .run(tokens)
+        .with_context(|| format!("preprocessing of a file at path: '{file_path}'"))?;
+    Ok((tokens, preprocessor))
+}
+
+fn parse_tokens(
+    options: &Options,
+    file_path: &str,
+    mut tokens: Vec<Token>,
+) -> Result<Vec<Expression>> {
     black_list_filter_tokens_by_kind(
         &mut tokens,
         HashSet::from([
@@ -38,23 +260,225 @@ fn parse() -> Result<()> {
         ]),
     );
 
-    tokens.push(Token {
-        value: String::new(),
-        kind: TokenKind::EndOfFile,
-        line: 0,
-    });
+    tokens.push(Token::new(String::new(), TokenKind::EndOfFile, 0));
     info!("Tokens: {tokens:#?}");
 
-    let expressions = parser::parse(tokens, FILE_PATH.to_owned())?;
+    let expressions = parser::parse(tokens, file_path.to_owned(), &options.target)?;
 
     info!("Expressions: {expressions:#?}");
+    Ok(expressions)
+}
+
+fn front_end(options: &Options, file_path: &str, source: &str) -> Result<Vec<Expression>> {
+    let (tokens, _) = preprocess(options, file_path, source)?;
+    parse_tokens(options, file_path, tokens)
+}
+
+/// Prints every file as C and parses it again, the trees have to be the same. The tree also has
+/// to come back the same from JSON.
+fn round_trip(options: &Options) -> Result<()> {
+    let files = c_files(&options.file)?;
 
+    let mut failures = 0;
+    for file in &files {
+        let file = file.display().to_string();
+        match round_trip_file(options, &file) {
+            Ok(()) => info!("round trip ok: {file}"),
+            Err(err) => {
+                error!("round trip failed: {file}: {err:?}");
+                failures += 1;
+            }
+        }
+    }
+    if failures > 0 {
+        bail!("{failures} of {} files didn't round-trip", files.len());
+    }
     Ok(())
 }
 
-fn tokenize_file(path: &str) -> Result<Vec<Token>> {
-    let chars = fs::read_to_string(path)?.chars().collect::<Vec<char>>();
-    lexer::tokenize(chars)
+fn round_trip_file(options: &Options, file_path: &str) -> Result<()> {
+    let source = fs::read_to_string(file_path)
+        .with_context(|| format!("reading a file at path: '{file_path}'"))?;
+    let expressions = front_end(options, file_path, &source).context("parsing the source")?;
+    let printed = code_gen::c::print(&expressions);
+    let reparsed = front_end(options, file_path, &printed)
+        .with_context(|| format!("parsing the printed C:\n{printed}"))?;
+    if let Some(difference) = code_gen::c::difference(&expressions, &reparsed) {
+        bail!("{difference}, printed C:\n{printed}");
+    }
+    let ast = Ast::new(file_path, expressions);
+    if Ast::from_json(&ast.to_json()).context("reading back the AST as JSON")? != ast {
+        bail!("the AST read back from JSON isn't the same");
+    }
+    Ok(())
+}
+
+fn analyze(
+    options: &Options,
+    source: &str,
+    expressions: &[Expression],
+    preprocessor: &mut preprocessor::Preprocessor,
+) -> Result<()> {
+    let symbols = semantic::scope::resolve(expressions);
+    diagnostics::log_diagnostics(&symbols.diagnostics);
+    if options.print_symbols {
+        print!("{}", symbols.report());
+    }
+
+    let types = semantic::type_check::check(expressions, &symbols, &options.target);
+    diagnostics::log_diagnostics(&types.diagnostics);
+    diagnostics::log_diagnostics(&analysis::flow::check(expressions, &symbols));
+    diagnostics::log_diagnostics(&analysis::dataflow::check(expressions, &symbols));
+
+    if options.lint {
+        let config = match &options.lint_config {
+            Some(path) => lint::Config::load(path)?,
+            None => lint::Config::default(),
+        };
+        let tokens = lexer::tokenize(source.chars().collect())
+            .with_context(|| format!("tokenization of a file at path: '{}'", options.file))?;
+        let context = lint::Context {
+            file: &options.file,
+            expressions,
+            symbols: &symbols,
+            tokens: &tokens,
+        };
+        let lints = lint::check(&context, &config);
+        diagnostics::log_diagnostics(&lints);
+        if diagnostics::has_errors(&lints) {
+            bail!("lint rules that are set to 'error' found problems");
+        }
+    }
+
+    if options.print_layout {
+        let engine = LayoutEngine::new(&options.target, &symbols);
+        for symbol in &symbols.symbols {
+            if symbol.kind == SymbolKind::Typedef {
+                print!("{}", engine.report(&symbol.name, &symbol.data_type)?);
+            }
+        }
+    }
+
+    // nothing is translated, compiled or run from a program with errors
+    if diagnostics::has_errors(&symbols.diagnostics) || diagnostics::has_errors(&types.diagnostics)
+    {
+        bail!("the program has errors");
+    }
+
+    if let Some(path) = &options.zig_output {
+        let code =
+            code_gen::generate_code(expressions, &symbols, &types).context("translation to Zig")?;
+        fs::write(path, code).with_context(|| format!("writing Zig output to: '{path}'"))?;
+    }
+
+    if let Some(path) = &options.rust_output {
+        let filter = code_gen::rust::Filter::new(&options.bindings_allow, &options.bindings_deny)?;
+        let code = code_gen::rust::generate_bindings(
+            expressions,
+            &symbols,
+            preprocessor,
+            &options.target,
+            &filter,
+            &options.file,
+        )
+        .context("generating Rust bindings")?;
+        fs::write(path, code).with_context(|| format!("writing Rust bindings to: '{path}'"))?;
+    }
+
+    if let Some(emit) = options.emit {
+        let code = if emit == Emit::Llvm {
+            code_gen::llvm::generate(expressions, &symbols, &types, &options.target)
+                .context("generating LLVM IR")?
+                .into_bytes()
+        } else {
+            let module = lower(options, expressions, &symbols, &types)?;
+            match emit {
+                Emit::Ir => module.to_string().into_bytes(),
+                Emit::Asm => code_gen::x86_64::generate(&module).into_bytes(),
+                Emit::Wat => code_gen::wasm::generate(&module)
+                    .context("compiling to WebAssembly")?
+                    .into_bytes(),
+                Emit::Bytecode => bytecode::compile::compile(&module)?.to_bytes(),
+                Emit::Llvm => unreachable!("generated from the syntax tree above"),
+            }
+        };
+        write_output(options, &code)?;
+    }
+
+    if options.vm || options.disassemble {
+        let module = lower(options, expressions, &symbols, &types)?;
+        execute_bytecode(options, &bytecode::compile::compile(&module)?)?;
+    }
+
+    if options.run {
+        let execution = interpreter::run(
+            expressions,
+            &symbols,
+            &types,
+            &options.target,
+            options.max_steps,
+        );
+        print!("{}", execution.output);
+        diagnostics::log_diagnostics(&execution.warnings);
+        match execution.result {
+            Ok(exit_code) => info!(
+                "program exited with code: {exit_code} after {} steps",
+                execution.steps
+            ),
+            Err(diagnostic) => bail!("runtime error: {diagnostic}"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the output of `--emit` or `--dot` to `-o FILE` or stdout
+fn write_output(options: &Options, output: &[u8]) -> Result<()> {
+    match &options.output {
+        Some(path) => {
+            fs::write(path, output).with_context(|| format!("writing output to: '{path}'"))?
+        }
+        None => std::io::stdout().write_all(output)?,
+    }
+    Ok(())
+}
+
+/// The program in the three-address IR with the passes of `-O` or `--passes` applied
+fn lower(
+    options: &Options,
+    expressions: &[Expression],
+    symbols: &semantic::scope::SymbolTable,
+    types: &semantic::type_check::TypeTable,
+) -> Result<ir::Module> {
+    let mut module =
+        ir::lower::lower(expressions, symbols, types, &options.target).context("lowering to IR")?;
+    if !options.passes.is_empty() {
+        ir::optimize::optimize(&mut module, &options.passes)?;
+    }
+    Ok(module)
+}
+
+/// `--disassemble` and `--vm`
+fn execute_bytecode(options: &Options, program: &Program) -> Result<()> {
+    if options.disassemble {
+        print!("{program}");
+    }
+    if options.vm {
+        let limits = Limits {
+            instructions: options.max_steps,
+            memory: options.max_memory,
+        };
+        let execution = bytecode::vm::run(program, limits);
+        print!("{}", execution.output);
+        match execution.result {
+            Ok(exit_code) => info!(
+                "program exited with code: {exit_code} after {} instructions",
+                execution.instructions
+            ),
+            Err(err) => bail!("runtime error: {err:?}"),
+        }
+    }
+    Ok(())
 }
 
 fn black_list_filter_tokens_by_kind(tokens: &mut Vec<Token>, black_list: HashSet<TokenKind>) {
@@ -1,21 +1,66 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{
-    lexer::token::{Token, TokenKind},
+    lexer::token::{MacroExpansion, Token},
     parser::types::DataType,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DebugData {
     pub line: u16,
     pub file: String,
+    /// macro that the expression came from, diagnostics about it point into the `#define`
+    pub expansion: Option<Box<MacroExpansion>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Property {
     pub var_name: String,
     pub var_type: DataType,
+    /// `_Alignas(...)` in front of a struct member
+    pub alignas: Option<AlignAs>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data")]
+pub enum AlignAs {
+    /// `_Alignas(16)`
+    Bytes(u64),
+    /// `_Alignas(double)`
+    Type(DataType),
+}
+
+/// How an integer literal is written, C gives it a type from its value and these
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntegerLiteral {
+    /// `u` or `U` suffix
+    pub unsigned: bool,
+    /// `l`, `ll` or their upper case versions, `long long` is as wide as `long`
+    pub long: bool,
+    /// hex, octal and binary literals that don't fit in `int` are `unsigned int`, decimal
+    /// ones are `long`
+    pub decimal: bool,
 }
 
-#[derive(Debug, Clone)]
+impl IntegerLiteral {
+    /// C source of the literal with this value
+    pub fn text(self, value: u64) -> String {
+        let mut text = match self.decimal {
+            true => value.to_string(),
+            false => format!("{value:#x}"),
+        };
+        if self.unsigned {
+            text.push('u');
+        }
+        if self.long {
+            text.push('l');
+        }
+        text
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data")]
 pub enum Expression {
     Skip,
     Increment {
@@ -53,10 +98,12 @@ pub enum Expression {
     },
     Boolean(bool, DebugData),
 
-    Number(u32, DebugData),
+    Number(u64, IntegerLiteral, DebugData),
 
     CompilerData(String, DebugData),
     String(String, DebugData),
+    /// what was between the quotes, see `lexer::character_value`
+    Character(String, DebugData),
     Identifier(String, DebugData),
     Prefix {
         prefix: Token,
@@ -110,6 +157,15 @@ pub enum Expression {
         inside: Vec<Expression>,
         debug_data: DebugData,
     },
+    /// prototype without a body: `int printf(const char *format, ...);`
+    FunctionDeclaration {
+        name: String,
+        /// names of the parameters can be left out, they are empty then
+        properties: Vec<Property>,
+        output: DataType,
+        variadic: bool,
+        debug_data: DebugData,
+    },
 
     MemberExpr {
         left: Box<Expression>,
@@ -176,3 +232,123 @@ pub enum Expression {
         debug_data: DebugData,
     },
 }
+
+/// Identity of an expression node. Passes that compute something for every node (types,
+/// resolved symbols...) store it next to the tree with this as a key, instead of inside of it.
+/// It is only valid as long as the tree isn't moved or changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExpressionId(usize);
+
+impl Expression {
+    pub fn id(&self) -> ExpressionId {
+        ExpressionId(self as *const Expression as usize)
+    }
+
+    /// Expressions directly inside of this one, in the order they appear in the source
+    pub fn children(&self) -> Vec<&Expression> {
+        match self {
+            Expression::Skip
+            | Expression::Boolean(..)
+            | Expression::Number(..)
+            | Expression::CompilerData(..)
+            | Expression::String(..)
+            | Expression::Character(..)
+            | Expression::Identifier(..)
+            | Expression::Typedef { .. }
+            | Expression::DataTypeAccess { .. }
+            | Expression::VariableDeclaration { .. }
+            | Expression::FunctionDeclaration { .. }
+            | Expression::Break { .. } => Vec::new(),
+            Expression::Increment { target, .. } | Expression::Decrement { target, .. } => {
+                vec![target]
+            }
+            Expression::TypeConversion { value, .. }
+            | Expression::Dereference { value, .. }
+            | Expression::Prefix { value, .. }
+            | Expression::Grouping { value, .. }
+            | Expression::AccessReference { value, .. }
+            | Expression::Return { value, .. }
+            | Expression::Static { value, .. } => vec![value],
+            Expression::Arrow { left, right, .. }
+            | Expression::MemberExpr { left, right, .. }
+            | Expression::Binary { left, right, .. } => vec![left, right],
+            Expression::Assignment { target, value, .. } => vec![target, value],
+            Expression::AccessArray { left, index, .. } => vec![left, index],
+            Expression::DataStructureInitialization { values: inside, .. }
+            | Expression::NewCodeBlock { inside, .. }
+            | Expression::Function { inside, .. }
+            | Expression::Struct {
+                functions: inside, ..
+            } => inside.iter().collect(),
+            Expression::If {
+                condition,
+                inside,
+                chained_elses,
+                ..
+            } => std::iter::once(condition.as_ref())
+                .chain(inside)
+                .chain(chained_elses)
+                .collect(),
+            Expression::Else {
+                condition, inside, ..
+            } => condition.iter().map(AsRef::as_ref).chain(inside).collect(),
+            Expression::While {
+                condition, inside, ..
+            } => std::iter::once(condition.as_ref()).chain(inside).collect(),
+            Expression::For {
+                iterator_init,
+                condition,
+                incr,
+                inside,
+                ..
+            } => [iterator_init, condition, incr]
+                .into_iter()
+                .map(AsRef::as_ref)
+                .chain(inside)
+                .collect(),
+            Expression::FunctionCall { left, values, .. } => {
+                std::iter::once(left.as_ref()).chain(values).collect()
+            }
+        }
+    }
+
+    pub fn debug_data(&self) -> Option<&DebugData> {
+        match self {
+            Expression::Skip => None,
+            Expression::Boolean(_, debug_data)
+            | Expression::Number(_, _, debug_data)
+            | Expression::CompilerData(_, debug_data)
+            | Expression::String(_, debug_data)
+            | Expression::Character(_, debug_data)
+            | Expression::Identifier(_, debug_data)
+            | Expression::Increment { debug_data, .. }
+            | Expression::Decrement { debug_data, .. }
+            | Expression::DataStructureInitialization { debug_data, .. }
+            | Expression::TypeConversion { debug_data, .. }
+            | Expression::Typedef { debug_data, .. }
+            | Expression::Arrow { debug_data, .. }
+            | Expression::Dereference { debug_data, .. }
+            | Expression::Prefix { debug_data, .. }
+            | Expression::Assignment { debug_data, .. }
+            | Expression::DataTypeAccess { debug_data, .. }
+            | Expression::VariableDeclaration { debug_data, .. }
+            | Expression::Grouping { debug_data, .. }
+            | Expression::Struct { debug_data, .. }
+            | Expression::NewCodeBlock { debug_data, .. }
+            | Expression::Binary { debug_data, .. }
+            | Expression::Function { debug_data, .. }
+            | Expression::FunctionDeclaration { debug_data, .. }
+            | Expression::MemberExpr { debug_data, .. }
+            | Expression::AccessReference { debug_data, .. }
+            | Expression::Break { debug_data }
+            | Expression::Return { debug_data, .. }
+            | Expression::If { debug_data, .. }
+            | Expression::Else { debug_data, .. }
+            | Expression::AccessArray { debug_data, .. }
+            | Expression::While { debug_data, .. }
+            | Expression::Static { debug_data, .. }
+            | Expression::For { debug_data, .. }
+            | Expression::FunctionCall { debug_data, .. } => Some(debug_data),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::parser::expression::Expression;
+
+/// Version of the JSON form of the tree, it changes whenever something in `Expression`,
+/// `DataType` or `Token` changes what is written. `docs/ast-json.md` describes it.
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// Parsed program as `--ast-json` writes it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ast {
+    /// `SCHEMA_VERSION` of the build that wrote it
+    pub version: u32,
+    /// path of the source file, as it was given
+    pub file: String,
+    pub expressions: Vec<Expression>,
+}
+
+impl Ast {
+    pub fn new(file: &str, expressions: Vec<Expression>) -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            file: file.to_owned(),
+            expressions,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).expect("the tree has no maps");
+        json.push('\n');
+        json
+    }
+
+    /// Reads back what `to_json` wrote, the version has to be the one of this build
+    pub fn from_json(json: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(json).context("AST that isn't JSON")?;
+        match value["version"].as_u64() {
+            Some(version) if version == SCHEMA_VERSION as u64 => {}
+            Some(version) => bail!(
+                "AST written with schema version {version}, this build reads version {SCHEMA_VERSION}"
+            ),
+            None => bail!("AST without a schema version"),
+        }
+        serde_json::from_value(value).context("AST that doesn't match the schema")
+    }
+}
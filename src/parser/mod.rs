@@ -1,13 +1,18 @@
 pub mod expression;
+pub mod json;
 pub mod parsing_functions;
 mod token_stats;
 pub mod types;
 
+pub use token_stats::{PREFIX_BINDING_POWER, binding_powers};
+
 use std::collections::{HashMap, HashSet};
 
 use anyhow::{Context, Result, bail};
 
 use crate::{
+    diagnostics::ErrorLine,
+    layout::target::Target,
     lexer::token::{Token, TokenKind},
     parser::{
         expression::Expression,
@@ -17,8 +22,18 @@ use crate::{
 
 pub struct Parser {
     pub valid_data_type_names: HashSet<String>,
+    /// typedefs and enum constants seen so far, for constant expressions like `int buf[N * 2]`
+    pub typedefs: HashMap<String, types::DataType>,
+    pub enum_constants: HashMap<String, i64>,
+    /// current `#pragma pack` value and the values saved by `#pragma pack(push)`
+    pub packing: Option<u64>,
+    pub packing_stack: Vec<Option<u64>>,
+    /// `sizeof` inside of array lengths depends on it
+    pub target: Target,
     pub tokens: Vec<Token>,
     pub i: usize,
+    /// what the parser sees once it runs past the last token, a half-typed file ends anywhere
+    pub end_of_file: Token,
     pub token_stats: HashMap<TokenKind, TokenStats>,
     pub file: String,
 }
@@ -27,18 +42,22 @@ impl Parser {
         expression::DebugData {
             file: self.file.to_owned(),
             line: self.current().line,
+            expansion: self.current().expansion.clone(),
         }
     }
     pub fn advance(&mut self) -> &Token {
         self.i += 1;
-        &self.tokens[self.i - 1]
+        self.token(self.i - 1)
     }
 
     pub fn next(&self) -> &Token {
-        &self.tokens[self.i + 1]
+        self.token(self.i + 1)
     }
     pub fn current(&self) -> &Token {
-        &self.tokens[self.i]
+        self.token(self.i)
+    }
+    fn token(&self, i: usize) -> &Token {
+        self.tokens.get(i).unwrap_or(&self.end_of_file)
     }
 
     pub fn current_stats(&self) -> Result<&TokenStats> {
@@ -50,33 +69,31 @@ impl Parser {
         })
     }
 
-    #[must_use]
     pub fn expect(&mut self, expected: TokenKind) -> Result<Token> {
-        match self.advance().to_owned() {
-            val => {
-                if val.kind == expected {
-                    return Ok(val);
-                } else {
-                    bail!("expected to find token of kind: '{expected:?}', found: '{val:?}'");
-                }
-            }
+        let val = self.advance().to_owned();
+        if val.kind == expected {
+            Ok(val)
+        } else {
+            bail!("expected to find token of kind: '{expected:?}', found: '{val:?}'");
         }
     }
 }
 
-pub fn parse(tokens: Vec<Token>, file: String) -> Result<Vec<Expression>> {
+pub fn parse(tokens: Vec<Token>, file: String, target: &Target) -> Result<Vec<Expression>> {
+    let last_line = tokens.last().map_or(0, |token| token.line);
     let mut parser = Parser {
-        valid_data_type_names: HashSet::from([
-            "bool".to_string(),
-            "char".to_string(),
-            "short".to_string(),
-            "int".to_string(),
-            "long".to_string(),
-            "float".to_string(),
-            "double".to_string(),
-        ]),
+        valid_data_type_names: types::PRIMITIVE_TYPE_NAMES
+            .iter()
+            .map(|name| name.to_string())
+            .collect(),
+        typedefs: HashMap::new(),
+        enum_constants: HashMap::new(),
+        packing: None,
+        packing_stack: Vec::new(),
+        target: target.to_owned(),
         i: 0,
-        tokens: tokens,
+        tokens,
+        end_of_file: Token::new(String::new(), TokenKind::EndOfFile, last_line),
         token_stats: token_stats(),
         file,
     };
@@ -88,12 +105,13 @@ pub fn parse(tokens: Vec<Token>, file: String) -> Result<Vec<Expression>> {
             continue;
         }
         output.push(
-            parsing_functions::expression(&mut parser, 0).with_context(|| {
-                format!(
+            parsing_functions::expression(&mut parser, 0).with_context(|| ErrorLine {
+                line: parser.current().line,
+                message: format!(
                     "debug data: {:?},\n parsed expressions:{:#?}",
                     parser.debug_data(),
                     output
-                )
+                ),
             })?,
         );
     }
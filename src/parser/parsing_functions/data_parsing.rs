@@ -1,39 +1,37 @@
-use crate::parser::{Parser, expression::Expression};
+use crate::{
+    parser::{Parser, expression::Expression},
+    preprocessor::expression::parse_integer,
+};
 use anyhow::{Result, bail};
 
 pub fn string(parser: &mut Parser) -> Result<Expression> {
     let token = parser.advance();
 
-    return Ok(Expression::String(
+    Ok(Expression::String(
         token.value.to_owned(),
         parser.debug_data(),
-    ));
+    ))
 }
 
-pub fn number(parser: &mut Parser) -> Result<Expression> {
+pub fn character(parser: &mut Parser) -> Result<Expression> {
     let token = parser.advance();
 
-    return Ok(Expression::Number(
-        str_to_num(&token.value)?,
+    Ok(Expression::Character(
+        token.value.to_owned(),
         parser.debug_data(),
-    ));
+    ))
 }
-pub fn str_to_num(s: &str) -> Result<u32, std::num::ParseIntError> {
-    if let Some(hex) = s.strip_prefix("0x") {
-        u32::from_str_radix(hex, 16)
-    } else if let Some(bin) = s.strip_prefix("0b") {
-        u32::from_str_radix(bin, 2)
-    } else if let Some(oct) = s.strip_prefix("0o") {
-        u32::from_str_radix(oct, 8)
-    } else {
-        // default to decimal
-        s.parse::<u32>()
-    }
+
+pub fn number(parser: &mut Parser) -> Result<Expression> {
+    let token = parser.advance();
+    let (value, literal) = parse_integer(&token.value)?;
+
+    Ok(Expression::Number(value, literal, parser.debug_data()))
 }
 
 pub fn boolean(parser: &mut Parser) -> Result<Expression> {
     let token = parser.advance();
-    return Ok(Expression::Boolean(
+    Ok(Expression::Boolean(
         match token.kind {
             crate::lexer::token::TokenKind::True => true,
             crate::lexer::token::TokenKind::False => false,
@@ -45,5 +43,5 @@ pub fn boolean(parser: &mut Parser) -> Result<Expression> {
             }
         },
         parser.debug_data(),
-    ));
+    ))
 }
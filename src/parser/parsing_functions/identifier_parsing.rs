@@ -1,19 +1,18 @@
 use crate::{
     lexer::token::TokenKind,
-    parse,
     parser::{
         Parser,
-        expression::{Expression, Property},
+        expression::{DebugData, Expression, Property},
         parsing_functions::{self},
         types::{self, DataType},
     },
 };
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 pub fn identifier(parser: &mut Parser) -> Result<Expression> {
     let first = parser.current().to_owned();
 
-    if parser.valid_data_type_names.contains(&first.value) {
+    if parser.valid_data_type_names.contains(&first.value) || first.value == "unsigned" {
         handle_function_or_variable_declaration(parser)
             .with_context(|| format!("identifier - data type name: {}", first.value.as_str()))
     } else {
@@ -22,22 +21,24 @@ pub fn identifier(parser: &mut Parser) -> Result<Expression> {
     }
 }
 
-fn handle_function_or_variable_declaration(parser: &mut Parser) -> Result<Expression> {
+pub fn handle_function_or_variable_declaration(parser: &mut Parser) -> Result<Expression> {
     let mut data_type = types::parse(parser)
         .context("parse data type for: handle_function_or_variable_declaration")?;
     if parser.current().kind != TokenKind::Identifier {
         return Ok(Expression::DataTypeAccess {
-            data_type: data_type,
+            data_type,
             debug_data: parser.debug_data(),
         });
     }
 
+    // functions point at their name instead of the end of their body
+    let name_debug_data = parser.debug_data();
     let name = parser.advance().to_owned();
 
     data_type = types::wrap_data_type_in_an_array(data_type, parser)?;
 
     if parser.current().kind == TokenKind::OpenParen {
-        handle_function_declaration(data_type, name.value, parser)
+        handle_function_declaration(data_type, name.value, name_debug_data, parser)
             .context("handle_function_declaration")
     } else {
         Ok(Expression::VariableDeclaration {
@@ -50,19 +51,42 @@ fn handle_function_or_variable_declaration(parser: &mut Parser) -> Result<Expres
 fn handle_function_declaration(
     output_data_type: DataType,
     name: String,
+    debug_data: DebugData,
     parser: &mut Parser,
 ) -> Result<Expression> {
     parser.expect(TokenKind::OpenParen)?;
 
     let mut properties = Vec::new();
+    let mut variadic = false;
 
+    // `(void)` is an empty list of parameters
+    let is_void = parser.current().value == "void" && parser.next().kind == TokenKind::CloseParen;
+    if is_void {
+        parser.advance();
+    }
     if parser.current().kind != TokenKind::CloseParen {
         loop {
+            // `...` is lexed as three dots
+            if parser.current().kind == TokenKind::Dot {
+                for _ in 0..3 {
+                    parser
+                        .expect(TokenKind::Dot)
+                        .context("'...' of a variadic function")?;
+                }
+                variadic = true;
+                break;
+            }
             let data_type = types::parse(parser).context("parse function input data types")?;
-            let name = parser.expect(TokenKind::Identifier)?.value.to_owned();
+            // prototypes don't have to name their parameters
+            let name = match parser.current().kind {
+                TokenKind::Identifier => parser.advance().value.to_owned(),
+                _ => String::new(),
+            };
+            let data_type = types::wrap_data_type_in_an_array(data_type, parser)?;
             properties.push(Property {
                 var_name: name,
                 var_type: data_type,
+                alignas: None,
             });
             if parser.current().kind == TokenKind::CloseParen {
                 break;
@@ -73,6 +97,27 @@ fn handle_function_declaration(
         }
     }
     parser.expect(TokenKind::CloseParen)?;
+    if parser.current().kind == TokenKind::SemiColon {
+        return Ok(Expression::FunctionDeclaration {
+            name,
+            properties,
+            output: output_data_type,
+            variadic,
+            debug_data,
+        });
+    }
+    if variadic {
+        bail!("definitions of variadic functions are not supported: '{name}'");
+    }
+    if let Some(unnamed) = properties
+        .iter()
+        .position(|property| property.var_name.is_empty())
+    {
+        bail!(
+            "parameter {} of the definition of '{name}' has no name",
+            unnamed + 1
+        );
+    }
     parser.expect(TokenKind::OpenCurly)?;
 
     let mut inside = Vec::new();
@@ -88,7 +133,7 @@ fn handle_function_declaration(
         name,
         properties,
         output: output_data_type,
-        inside: inside,
-        debug_data: parser.debug_data(),
+        inside,
+        debug_data,
     })
 }
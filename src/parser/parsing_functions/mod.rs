@@ -1,17 +1,17 @@
 use crate::{
     lexer::token::TokenKind,
-    parser::{self, Parser, expression::Expression, types},
+    parser::{Parser, expression::Expression, token_stats::PREFIX_BINDING_POWER, types},
+    preprocessor::expression::parse_integer,
 };
 pub mod data_parsing;
 pub mod identifier_parsing;
 pub mod statement_parsing;
-use anyhow::{Context, Result};
-use log::info;
+use anyhow::{Context, Result, bail};
 
 pub fn function_call(parser: &mut Parser, left: Expression, _: i8) -> Result<Expression> {
     parser.expect(TokenKind::OpenParen)?;
     let mut properties = Vec::new();
-    loop {
+    while parser.current().kind != TokenKind::CloseParen {
         let current_token = parser.current().to_owned();
         properties.push(
             expression(parser, 0).with_context(|| {
@@ -87,7 +87,7 @@ pub fn expression(parser: &mut Parser, bp: i8) -> Result<Expression> {
             ))?;
     }
 
-    return Ok(current_expression);
+    Ok(current_expression)
 }
 
 pub fn binary(parser: &mut Parser, left: Expression, bp: i8) -> Result<Expression> {
@@ -97,47 +97,84 @@ pub fn binary(parser: &mut Parser, left: Expression, bp: i8) -> Result<Expressio
 
     Ok(Expression::Binary {
         left: Box::new(left),
-        operator: operator,
+        operator,
         right: Box::new(right),
         debug_data: parser.debug_data(),
     })
 }
 
 pub fn compiler_data(parser: &mut Parser) -> Result<Expression> {
-    Ok(Expression::CompilerData(
-        parser.advance().value.to_owned(),
-        parser.debug_data(),
-    ))
+    let value = parser.advance().value.to_owned();
+    if let Some(arguments) = value
+        .trim()
+        .strip_prefix("pragma")
+        .map(str::trim)
+        .and_then(|pragma| pragma.strip_prefix("pack"))
+    {
+        pragma_pack(parser, arguments).with_context(|| format!("#{value}"))?;
+    }
+    Ok(Expression::CompilerData(value, parser.debug_data()))
+}
+
+/// `#pragma pack(N)`, `pack()`, `pack(push[, N])` and `pack(pop)` change the packing of the
+/// structs declared after them
+fn pragma_pack(parser: &mut Parser, arguments: &str) -> Result<()> {
+    let arguments = arguments
+        .trim()
+        .strip_prefix('(')
+        .and_then(|arguments| arguments.strip_suffix(')'))
+        .context("expected the arguments of '#pragma pack' to be inside of parentheses")?;
+    let mut new_packing = parser.packing;
+    for argument in arguments.split(',').map(str::trim) {
+        match argument {
+            "" => new_packing = None,
+            "push" => parser.packing_stack.push(parser.packing),
+            "pop" => new_packing = parser.packing_stack.pop().flatten(),
+            value => {
+                let (value, _) =
+                    parse_integer(value).with_context(|| format!("invalid packing: '{value}'"))?;
+                if !value.is_power_of_two() {
+                    bail!("packing has to be a power of 2, found: {value}");
+                }
+                new_packing = Some(value);
+            }
+        }
+    }
+    parser.packing = new_packing;
+    Ok(())
 }
 
 pub fn prefix(parser: &mut Parser) -> Result<Expression> {
     let prefix = parser.advance().to_owned();
-    let value = expression(parser, 0).context("prefix")?;
+    let value = expression(parser, PREFIX_BINDING_POWER).context("prefix")?;
 
-    return Ok(Expression::Prefix {
+    Ok(Expression::Prefix {
         prefix,
         value: Box::new(value),
         debug_data: parser.debug_data(),
-    });
+    })
 }
 pub fn break_expr(parser: &mut Parser) -> Result<Expression> {
     parser.expect(TokenKind::Break)?;
-    return Ok(Expression::Break {
+    Ok(Expression::Break {
         debug_data: parser.debug_data(),
-    });
+    })
 }
 pub fn grouping(parser: &mut Parser) -> Result<Expression> {
     parser.expect(TokenKind::OpenParen)?;
 
     let current = parser.current();
-    if current.kind == TokenKind::Identifier
-        && parser
-            .valid_data_type_names
-            .contains(current.value.as_str())
-    {
+    let is_type = current.kind == TokenKind::Constant
+        || (current.kind == TokenKind::Identifier
+            && (parser
+                .valid_data_type_names
+                .contains(current.value.as_str())
+                || current.value == "unsigned"));
+    if is_type {
         let data_type = types::parse(parser).context("grouping -> TypeConversion -> data_type")?;
         parser.expect(TokenKind::CloseParen)?;
-        let value = expression(parser, 0).context("grouping -> TypeConversion -> value")?;
+        let value = expression(parser, PREFIX_BINDING_POWER)
+            .context("grouping -> TypeConversion -> value")?;
 
         Ok(Expression::TypeConversion {
             value: Box::new(value),
@@ -156,7 +193,11 @@ pub fn grouping(parser: &mut Parser) -> Result<Expression> {
 }
 pub fn member_expr(parser: &mut Parser, left: Expression, _: i8) -> Result<Expression> {
     parser.expect(TokenKind::Dot)?;
-    let right = expression(parser, 0).with_context(|| format!("member expr-> left:{:?}", left))?;
+    // only the name of the member, anything after it belongs to the outer expression
+    let right = parser
+        .expect(TokenKind::Identifier)
+        .with_context(|| format!("member expr-> left:{:?}", left))?;
+    let right = Expression::Identifier(right.value, parser.debug_data());
 
     Ok(Expression::MemberExpr {
         left: Box::new(left),
@@ -193,6 +234,9 @@ pub fn type_def(parser: &mut Parser) -> Result<Expression> {
         .value;
 
     parser.valid_data_type_names.insert(name.to_string());
+    parser
+        .typedefs
+        .insert(name.to_owned(), data_type.to_owned());
 
     Ok(Expression::Typedef {
         data_type,
@@ -203,7 +247,10 @@ pub fn type_def(parser: &mut Parser) -> Result<Expression> {
 
 pub fn arrow(parser: &mut Parser, left: Expression, _: i8) -> Result<Expression> {
     parser.expect(TokenKind::Arrow)?;
-    let right = expression(parser, 0).with_context(|| format!("arrow expr-> left:{:?}", left))?;
+    let right = parser
+        .expect(TokenKind::Identifier)
+        .with_context(|| format!("arrow expr-> left:{:?}", left))?;
+    let right = Expression::Identifier(right.value, parser.debug_data());
 
     Ok(Expression::Arrow {
         left: Box::new(left),
@@ -214,7 +261,7 @@ pub fn arrow(parser: &mut Parser, left: Expression, _: i8) -> Result<Expression>
 
 pub fn dereference(parser: &mut Parser) -> Result<Expression> {
     parser.expect(TokenKind::Star)?;
-    let value = expression(parser, 0)?;
+    let value = expression(parser, PREFIX_BINDING_POWER)?;
 
     Ok(Expression::Dereference {
         value: Box::new(value),
@@ -239,7 +286,7 @@ pub fn increment(parser: &mut Parser, left: Expression, _: i8) -> Result<Express
 
 pub fn access_reference(parser: &mut Parser) -> Result<Expression> {
     parser.expect(TokenKind::Reference)?;
-    let value = expression(parser, 0)?;
+    let value = expression(parser, PREFIX_BINDING_POWER)?;
 
     Ok(Expression::AccessReference {
         value: Box::new(value),
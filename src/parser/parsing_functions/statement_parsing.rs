@@ -1,7 +1,4 @@
-use std::collections::vec_deque;
-
 use anyhow::{Context, Result};
-use log::info;
 
 use crate::{
     lexer::token::TokenKind,
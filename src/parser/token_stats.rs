@@ -5,11 +5,16 @@ use crate::{
     parser::{
         Parser,
         expression::Expression,
-        parsing_functions::{self, grouping, identifier_parsing},
+        parsing_functions::{self, identifier_parsing},
     },
 };
 use anyhow::Result;
 
+/// Binding powers follow the C operator precedence, from the loosest:
+/// assignments 1, `||` 2, `&&` 3, `|` 4, `^` 5, `&` 6, `==` `!=` 7, `<` `<=` `>` `>=` 8,
+/// `<<` `>>` 9, `+` `-` 10, `*` `/` `%` 11, prefix operators 12 and postfix operators 13.
+pub const PREFIX_BINDING_POWER: i8 = 12;
+
 type NodFunction = fn(&mut Parser) -> Result<Expression>;
 type LedFunction = fn(&mut Parser, left: Expression, bp: i8) -> Result<Expression>;
 pub struct TokenStats {
@@ -17,6 +22,14 @@ pub struct TokenStats {
     pub nod_function: Option<NodFunction>,
     pub led_function: Option<LedFunction>,
 }
+/// Binding power of every token, for printers that have to put the parentheses back
+pub fn binding_powers() -> HashMap<TokenKind, i8> {
+    token_stats()
+        .into_iter()
+        .map(|(kind, stats)| (kind, stats.binding_power))
+        .collect()
+}
+
 pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
     HashMap::from([
         (
@@ -38,7 +51,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::BitwiseShiftLeft,
             TokenStats {
-                binding_power: 2,
+                binding_power: 9,
                 nod_function: None,
                 led_function: Some(parsing_functions::binary),
             },
@@ -46,7 +59,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::BitwiseShiftRight,
             TokenStats {
-                binding_power: 2,
+                binding_power: 9,
                 nod_function: None,
                 led_function: Some(parsing_functions::binary),
             },
@@ -54,7 +67,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::Plus,
             TokenStats {
-                binding_power: 2,
+                binding_power: 10,
                 nod_function: Some(parsing_functions::prefix),
                 led_function: Some(parsing_functions::binary),
             },
@@ -62,7 +75,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::Minus,
             TokenStats {
-                binding_power: 2,
+                binding_power: 10,
                 nod_function: Some(parsing_functions::prefix),
                 led_function: Some(parsing_functions::binary),
             },
@@ -70,7 +83,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::Star,
             TokenStats {
-                binding_power: 3,
+                binding_power: 11,
                 nod_function: Some(parsing_functions::dereference),
                 led_function: Some(parsing_functions::binary),
             },
@@ -78,7 +91,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::Slash,
             TokenStats {
-                binding_power: 3,
+                binding_power: 11,
                 nod_function: None,
                 led_function: Some(parsing_functions::binary),
             },
@@ -86,7 +99,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::Percent,
             TokenStats {
-                binding_power: 3,
+                binding_power: 11,
                 nod_function: None,
                 led_function: Some(parsing_functions::binary),
             },
@@ -94,7 +107,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::Equals,
             TokenStats {
-                binding_power: 4,
+                binding_power: 7,
                 nod_function: None,
                 led_function: Some(parsing_functions::binary),
             },
@@ -102,7 +115,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::NotEquals,
             TokenStats {
-                binding_power: 4,
+                binding_power: 7,
                 nod_function: None,
                 led_function: Some(parsing_functions::binary),
             },
@@ -110,7 +123,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::Less,
             TokenStats {
-                binding_power: 4,
+                binding_power: 8,
                 nod_function: None,
                 led_function: Some(parsing_functions::binary),
             },
@@ -118,7 +131,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::LessEquals,
             TokenStats {
-                binding_power: 4,
+                binding_power: 8,
                 nod_function: None,
                 led_function: Some(parsing_functions::binary),
             },
@@ -126,7 +139,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::Greater,
             TokenStats {
-                binding_power: 4,
+                binding_power: 8,
                 nod_function: None,
                 led_function: Some(parsing_functions::binary),
             },
@@ -134,7 +147,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::GreaterEquals,
             TokenStats {
-                binding_power: 4,
+                binding_power: 8,
                 nod_function: None,
                 led_function: Some(parsing_functions::binary),
             },
@@ -142,7 +155,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::Or,
             TokenStats {
-                binding_power: 1,
+                binding_power: 2,
                 nod_function: None,
                 led_function: Some(parsing_functions::binary),
             },
@@ -150,17 +163,41 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::And,
             TokenStats {
-                binding_power: 1,
+                binding_power: 3,
+                nod_function: None,
+                led_function: Some(parsing_functions::binary),
+            },
+        ),
+        (
+            TokenKind::BitwiseOr,
+            TokenStats {
+                binding_power: 4,
                 nod_function: None,
                 led_function: Some(parsing_functions::binary),
             },
         ),
+        (
+            TokenKind::BitwiseXor,
+            TokenStats {
+                binding_power: 5,
+                nod_function: None,
+                led_function: Some(parsing_functions::binary),
+            },
+        ),
+        (
+            TokenKind::BitwiseNot,
+            TokenStats {
+                binding_power: 0,
+                nod_function: Some(parsing_functions::prefix),
+                led_function: None,
+            },
+        ),
         (
             TokenKind::Not,
             TokenStats {
-                binding_power: 1,
+                binding_power: 0,
                 nod_function: Some(parsing_functions::prefix),
-                led_function: Some(parsing_functions::binary),
+                led_function: None,
             },
         ),
         (
@@ -179,6 +216,14 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
                 led_function: None,
             },
         ),
+        (
+            TokenKind::Character,
+            TokenStats {
+                binding_power: 0,
+                nod_function: Some(parsing_functions::data_parsing::character),
+                led_function: None,
+            },
+        ),
         (
             TokenKind::Identifier,
             TokenStats {
@@ -195,6 +240,14 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
                 led_function: None,
             },
         ),
+        (
+            TokenKind::Break,
+            TokenStats {
+                binding_power: 0,
+                nod_function: Some(parsing_functions::break_expr),
+                led_function: None,
+            },
+        ),
         (
             TokenKind::CloseParen,
             TokenStats {
@@ -206,7 +259,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::OpenParen,
             TokenStats {
-                binding_power: 5,
+                binding_power: 13,
                 nod_function: Some(parsing_functions::grouping),
                 led_function: Some(parsing_functions::function_call),
             },
@@ -254,7 +307,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::PlusEquals,
             TokenStats {
-                binding_power: 5,
+                binding_power: 1,
                 nod_function: None,
                 led_function: Some(parsing_functions::assignment),
             },
@@ -262,7 +315,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::MinusEquals,
             TokenStats {
-                binding_power: 5,
+                binding_power: 1,
                 nod_function: None,
                 led_function: Some(parsing_functions::assignment),
             },
@@ -270,7 +323,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::StarEquals,
             TokenStats {
-                binding_power: 5,
+                binding_power: 1,
                 nod_function: None,
                 led_function: Some(parsing_functions::assignment),
             },
@@ -278,7 +331,55 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::SlashEquals,
             TokenStats {
-                binding_power: 5,
+                binding_power: 1,
+                nod_function: None,
+                led_function: Some(parsing_functions::assignment),
+            },
+        ),
+        (
+            TokenKind::PercentEquals,
+            TokenStats {
+                binding_power: 1,
+                nod_function: None,
+                led_function: Some(parsing_functions::assignment),
+            },
+        ),
+        (
+            TokenKind::BitwiseShiftLeftEquals,
+            TokenStats {
+                binding_power: 1,
+                nod_function: None,
+                led_function: Some(parsing_functions::assignment),
+            },
+        ),
+        (
+            TokenKind::BitwiseShiftRightEquals,
+            TokenStats {
+                binding_power: 1,
+                nod_function: None,
+                led_function: Some(parsing_functions::assignment),
+            },
+        ),
+        (
+            TokenKind::BitwiseAndEquals,
+            TokenStats {
+                binding_power: 1,
+                nod_function: None,
+                led_function: Some(parsing_functions::assignment),
+            },
+        ),
+        (
+            TokenKind::BitwiseOrEquals,
+            TokenStats {
+                binding_power: 1,
+                nod_function: None,
+                led_function: Some(parsing_functions::assignment),
+            },
+        ),
+        (
+            TokenKind::BitwiseXorEquals,
+            TokenStats {
+                binding_power: 1,
                 nod_function: None,
                 led_function: Some(parsing_functions::assignment),
             },
@@ -286,7 +387,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::PlusPlus,
             TokenStats {
-                binding_power: 5,
+                binding_power: 13,
                 nod_function: None,
                 led_function: Some(parsing_functions::increment),
             },
@@ -294,15 +395,15 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::MinusMinus,
             TokenStats {
-                binding_power: 5,
+                binding_power: 13,
                 nod_function: None,
                 led_function: Some(parsing_functions::decrement),
             },
         ),
         (
-            TokenKind::Equals,
+            TokenKind::Assignment,
             TokenStats {
-                binding_power: 5,
+                binding_power: 1,
                 nod_function: None,
                 led_function: Some(parsing_functions::assignment),
             },
@@ -310,9 +411,9 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::Reference,
             TokenStats {
-                binding_power: 5,
+                binding_power: 6,
                 nod_function: Some(parsing_functions::access_reference),
-                led_function: None,
+                led_function: Some(parsing_functions::binary),
             },
         ),
         (
@@ -323,10 +424,18 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
                 led_function: None,
             },
         ),
+        (
+            TokenKind::Constant,
+            TokenStats {
+                binding_power: 0,
+                nod_function: Some(identifier_parsing::handle_function_or_variable_declaration),
+                led_function: None,
+            },
+        ),
         (
             TokenKind::OpenBracket,
             TokenStats {
-                binding_power: 5,
+                binding_power: 13,
                 nod_function: None,
                 led_function: Some(parsing_functions::access_array),
             },
@@ -382,7 +491,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::Arrow,
             TokenStats {
-                binding_power: 1,
+                binding_power: 13,
                 nod_function: None,
                 led_function: Some(parsing_functions::arrow),
             },
@@ -390,7 +499,7 @@ pub fn token_stats() -> HashMap<TokenKind, TokenStats> {
         (
             TokenKind::Dot,
             TokenStats {
-                binding_power: 1,
+                binding_power: 13,
                 nod_function: None,
                 led_function: Some(parsing_functions::member_expr),
             },
@@ -1,25 +1,142 @@
-use crate::lexer::token::Token;
-use crate::parser::expression::Property;
+use crate::lexer::token::{Token, TokenKind};
+use crate::parser::expression::{AlignAs, Expression, Property};
 use crate::parser::{Parser, parsing_functions};
-use crate::{lexer::token::TokenKind, parser::parsing_functions::data_parsing::str_to_num};
-use anyhow::{Context, Result, bail};
+use crate::semantic::constant::Evaluator;
+use anyhow::{Context, Result, anyhow, bail};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
 
-#[derive(Debug, Clone)]
+/// names of types that don't need to be declared with a typedef
+pub const PRIMITIVE_TYPE_NAMES: [&str; 8] = [
+    "void", "bool", "char", "short", "int", "long", "float", "double",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EnumField {
-    name: String,
-    value: u32,
+    pub name: String,
+    pub value: i64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data")]
 pub enum DataType {
-    Array { length: u32, inside: Box<DataType> },
-    Data { name: String, unsigned: bool },
-    Struct { properties: Vec<Property> },
-    Enum { fields: Vec<EnumField> },
+    Array {
+        length: u32,
+        inside: Box<DataType>,
+    },
+    Data {
+        name: String,
+        unsigned: bool,
+        /// `const`, only kept for what the type points to, to tell `*const` from `*mut`
+        constant: bool,
+    },
+    Struct {
+        properties: Vec<Property>,
+        /// set by `#pragma pack`, members are never aligned to more than this
+        packing: Option<u64>,
+    },
+    Union {
+        properties: Vec<Property>,
+        packing: Option<u64>,
+    },
+    Enum {
+        fields: Vec<EnumField>,
+    },
     Pointer(Box<DataType>),
+    /// type of a function itself, only created by the semantic passes
+    Function {
+        output: Box<DataType>,
+        parameters: Vec<DataType>,
+        /// `...` after the parameters
+        variadic: bool,
+    },
+}
+
+impl DataType {
+    /// C declaration of a variable of this type: `int *values[4]` instead of `int *[4] values`
+    pub fn declaration(&self, name: &str) -> String {
+        match self {
+            DataType::Array { length, inside } => inside.declaration(&format!("{name}[{length}]")),
+            DataType::Pointer(inside) if !matches!(inside.as_ref(), DataType::Array { .. }) => {
+                inside.declaration(&format!("*{name}"))
+            }
+            data_type => format!("{data_type} {name}"),
+        }
+    }
+}
+
+impl Display for DataType {
+    /// C syntax of the type, used in diagnostics
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataType::Data {
+                name,
+                unsigned,
+                constant,
+            } => {
+                if *constant {
+                    write!(f, "const ")?;
+                }
+                if *unsigned {
+                    write!(f, "unsigned ")?;
+                }
+                write!(f, "{name}")
+            }
+            DataType::Pointer(inside) if matches!(inside.as_ref(), DataType::Pointer(_)) => {
+                write!(f, "{inside}*")
+            }
+            DataType::Pointer(inside) => write!(f, "{inside} *"),
+            DataType::Array { length, inside } => write!(f, "{inside}[{length}]"),
+            DataType::Struct { properties, .. } | DataType::Union { properties, .. } => {
+                let keyword = match self {
+                    DataType::Union { .. } => "union",
+                    _ => "struct",
+                };
+                write!(f, "{keyword} {{ ")?;
+                for property in properties {
+                    write!(f, "{} {}; ", property.var_type, property.var_name)?;
+                }
+                write!(f, "}}")
+            }
+            DataType::Enum { fields } => {
+                let names = fields
+                    .iter()
+                    .map(|field| field.name.as_str())
+                    .collect::<Vec<_>>();
+                write!(f, "enum {{ {} }}", names.join(", "))
+            }
+            DataType::Function {
+                output,
+                parameters,
+                variadic,
+            } => {
+                let mut parameters = parameters
+                    .iter()
+                    .map(|parameter| parameter.to_string())
+                    .collect::<Vec<_>>();
+                if *variadic {
+                    parameters.push("...".to_owned());
+                }
+                write!(f, "{output} ({})", parameters.join(", "))
+            }
+        }
+    }
+}
+
+/// `const` can be written before or after the rest of the type: `const unsigned int`,
+/// `unsigned const int` and `int const` are the same
+fn qualifiers(parser: &mut Parser) -> bool {
+    let mut constant = false;
+    while parser.current().kind == TokenKind::Constant {
+        parser.advance();
+        constant = true;
+    }
+    constant
 }
 
 pub fn parse(parser: &mut Parser) -> Result<DataType> {
+    let mut constant = qualifiers(parser);
     let unsigned = {
         let current = parser.current();
 
@@ -29,17 +146,18 @@ pub fn parse(parser: &mut Parser) -> Result<DataType> {
         }
         unsigned
     };
+    constant |= qualifiers(parser);
 
     let current = parser.advance().to_owned();
     match current.kind {
-        TokenKind::Identifier => {
-            identifier_type(parser, unsigned, current).context("types::parse -> identifier")
-        }
+        TokenKind::Identifier => identifier_type(parser, unsigned, constant, current)
+            .context("types::parse -> identifier"),
         TokenKind::Enum => enum_type(parser).context("types::parse -> Enum"),
-        TokenKind::Struct => struct_type(parser).context("types::parse -> Struct"),
+        TokenKind::Struct => record_type(parser, false).context("types::parse -> Struct"),
+        TokenKind::Union => record_type(parser, true).context("types::parse -> Union"),
         other => {
             bail!(
-                "types::parse: expected to fine 'Identifier' || 'Enum' || 'Struct', found: {:?} -> parsing of this token kind as datatype is not supported",
+                "types::parse: expected to fine 'Identifier' || 'Enum' || 'Struct' || 'Union', found: {:?} -> parsing of this token kind as datatype is not supported",
                 other
             )
         }
@@ -51,29 +169,52 @@ pub fn wrap_data_type_in_an_array(
 ) -> Result<DataType> {
     while parser.current().kind == TokenKind::OpenBracket {
         parser.expect(TokenKind::OpenBracket)?;
-        let length =
-            parsing_functions::data_parsing::str_to_num(&parser.expect(TokenKind::Number)?.value)?;
+        let length_expression = parsing_functions::expression(parser, 0).context("array length")?;
+        let length = constant(parser, &length_expression).context("array length")?;
+        if length < 0 {
+            bail!("array can't have a negative length: {length}");
+        }
         parser.expect(TokenKind::CloseBracket)?;
         data_type = DataType::Array {
-            length,
+            length: u32::try_from(length).context("array length")?,
             inside: Box::new(data_type),
         };
     }
 
     Ok(data_type)
 }
-fn identifier_type(parser: &mut Parser, unsigned: bool, current: Token) -> Result<DataType> {
+fn identifier_type(
+    parser: &mut Parser,
+    unsigned: bool,
+    constant: bool,
+    current: Token,
+) -> Result<DataType> {
     let mut output = DataType::Data {
         name: current.value,
         unsigned,
+        constant: constant | qualifiers(parser),
     };
     while parser.current().kind == TokenKind::Star {
         output = DataType::Pointer(Box::new(output));
         parser.advance();
+        // `char *const` makes the pointer itself constant, which doesn't change how it is used
+        qualifiers(parser);
     }
     Ok(output)
 }
 
+/// Array lengths and enum values have to be known while parsing
+fn constant(parser: &Parser, expression: &Expression) -> Result<i128> {
+    let mut evaluator = Evaluator::new(parser, &parser.target);
+    let constant = evaluator
+        .evaluate(expression)
+        .map_err(|diagnostic| anyhow!("not an integer constant expression: {diagnostic}"))?;
+    for warning in evaluator.warnings {
+        warn!("{warning}");
+    }
+    Ok(constant.value)
+}
+
 fn enum_type(parser: &mut Parser) -> Result<DataType> {
     parser.expect(TokenKind::OpenCurly)?;
     let mut current_value = 0;
@@ -82,8 +223,12 @@ fn enum_type(parser: &mut Parser) -> Result<DataType> {
     while !end {
         let field_name = parser.expect(TokenKind::Identifier)?.value;
         match parser.advance().kind {
-            TokenKind::Equals => {
-                current_value = str_to_num(&parser.advance().value)?;
+            TokenKind::Assignment => {
+                let value = parsing_functions::expression(parser, 0)
+                    .with_context(|| format!("value of enum field: '{field_name}'"))?;
+                current_value = constant(parser, &value)
+                    .with_context(|| format!("value of enum field: '{field_name}'"))?
+                    as i64;
                 end = parser.advance().kind == TokenKind::CloseCurly;
             }
             TokenKind::Comma => {}
@@ -97,6 +242,9 @@ fn enum_type(parser: &mut Parser) -> Result<DataType> {
             }
         }
 
+        parser
+            .enum_constants
+            .insert(field_name.to_owned(), current_value);
         fields.push(EnumField {
             name: field_name,
             value: current_value,
@@ -107,12 +255,19 @@ fn enum_type(parser: &mut Parser) -> Result<DataType> {
     Ok(DataType::Enum { fields })
 }
 
-fn struct_type(parser: &mut Parser) -> Result<DataType> {
+/// `struct { ... }` and `union { ... }`, they only differ in layout
+fn record_type(parser: &mut Parser, union: bool) -> Result<DataType> {
     parser.expect(TokenKind::OpenCurly)?;
     let mut properties = Vec::new();
     while parser.current().kind != TokenKind::CloseCurly {
+        let alignas = if parser.current().value == "_Alignas" {
+            Some(alignas(parser).context("_Alignas")?)
+        } else {
+            None
+        };
         let data_type = parse(parser)?;
         let name = parser.expect(TokenKind::Identifier)?.value;
+        let data_type = wrap_data_type_in_an_array(data_type, parser)?;
         parser
             .expect(TokenKind::SemiColon)
             .context("expected to find a semicolon after a expression - struct contents")?;
@@ -120,9 +275,42 @@ fn struct_type(parser: &mut Parser) -> Result<DataType> {
         properties.push(Property {
             var_name: name,
             var_type: data_type,
+            alignas,
         });
     }
 
     parser.expect(TokenKind::CloseCurly)?;
-    Ok(DataType::Struct { properties })
+    let packing = parser.packing;
+    Ok(if union {
+        DataType::Union {
+            properties,
+            packing,
+        }
+    } else {
+        DataType::Struct {
+            properties,
+            packing,
+        }
+    })
+}
+
+/// `_Alignas(16)` or `_Alignas(double)`
+fn alignas(parser: &mut Parser) -> Result<AlignAs> {
+    parser.expect(TokenKind::Identifier)?;
+    parser.expect(TokenKind::OpenParen)?;
+    let current = parser.current();
+    let alignas = if current.kind == TokenKind::Identifier
+        && parser.valid_data_type_names.contains(&current.value)
+    {
+        AlignAs::Type(parse(parser)?)
+    } else {
+        let value = parsing_functions::expression(parser, 0)?;
+        let value = constant(parser, &value)?;
+        if value <= 0 || (value as u64).count_ones() != 1 {
+            bail!("requested alignment is not a positive power of 2: {value}");
+        }
+        AlignAs::Bytes(value as u64)
+    };
+    parser.expect(TokenKind::CloseParen)?;
+    Ok(alignas)
 }
@@ -0,0 +1,115 @@
+use std::collections::VecDeque;
+
+use anyhow::{Context, Result, bail};
+use log::warn;
+
+use crate::{
+    lexer::token::Token,
+    preprocessor::{Preprocessor, expect_macro_name, skip_white_space},
+};
+
+/// State of a single `#if` ... `#endif` group
+#[derive(Debug, Clone)]
+pub struct Conditional {
+    /// line of the `#if`, for error messages
+    pub line: u16,
+    /// tokens of the current branch are kept
+    pub active: bool,
+    /// one of the branches was already chosen, so the rest are skipped
+    pub taken: bool,
+    pub seen_else: bool,
+}
+
+pub fn is_conditional_directive(name: &str) -> bool {
+    matches!(name, "if" | "ifdef" | "ifndef" | "elif" | "else" | "endif")
+}
+
+impl Preprocessor {
+    /// false while inside of a branch that was not chosen
+    pub fn active(&self) -> bool {
+        self.conditionals
+            .last()
+            .is_none_or(|conditional| conditional.active)
+    }
+
+    pub fn conditional_directive(
+        &mut self,
+        name: &str,
+        mut tokens: VecDeque<Token>,
+        line: u16,
+    ) -> Result<()> {
+        match name {
+            "if" | "ifdef" | "ifndef" => {
+                // nested inside of a skipped group, the condition isn't even evaluated
+                if !self.active() {
+                    tokens.clear();
+                }
+                let value = self.active()
+                    && match name {
+                        "if" => self
+                            .evaluate_condition(std::mem::take(&mut tokens))
+                            .context("#if")?,
+                        "ifdef" => self
+                            .macros
+                            .contains_key(&expect_macro_name(&mut tokens).context("#ifdef")?),
+                        _ => !self
+                            .macros
+                            .contains_key(&expect_macro_name(&mut tokens).context("#ifndef")?),
+                    };
+                let parent_active = self.active();
+                self.conditionals.push(Conditional {
+                    line,
+                    active: value,
+                    taken: value || !parent_active,
+                    seen_else: false,
+                });
+            }
+            "elif" => {
+                let Some(conditional) = self.conditionals.last() else {
+                    bail!("#elif without #if")
+                };
+                if conditional.seen_else {
+                    bail!("#elif after #else, #if at line: {}", conditional.line)
+                }
+                let taken = conditional.taken;
+                let value = !taken
+                    && self
+                        .evaluate_condition(std::mem::take(&mut tokens))
+                        .context("#elif")?;
+                if let Some(conditional) = self.conditionals.last_mut() {
+                    conditional.active = value;
+                    conditional.taken |= value;
+                }
+            }
+            "else" => {
+                let Some(conditional) = self.conditionals.last_mut() else {
+                    bail!("#else without #if")
+                };
+                if conditional.seen_else {
+                    bail!("#else after #else, #if at line: {}", conditional.line)
+                }
+                conditional.seen_else = true;
+                conditional.active = !conditional.taken;
+                conditional.taken = true;
+            }
+            "endif" => {
+                if self.conditionals.pop().is_none() {
+                    bail!("#endif without #if")
+                }
+            }
+            _ => bail!("'{name}' is not a conditional directive"),
+        }
+
+        skip_white_space(&mut tokens);
+        if matches!(name, "ifdef" | "ifndef" | "else" | "endif")
+            && let Some(extra) = tokens.front()
+        {
+            warn!(
+                "{}:{line}: extra tokens at the end of #{name}: '{}'",
+                self.file,
+                extra.text()
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::VecDeque;
+
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    lexer::{
+        character_value,
+        token::{Token, TokenKind},
+    },
+    parser::expression::IntegerLiteral,
+    preprocessor::{PpToken, Preprocessor, is_white_space, skip_white_space},
+};
+
+/// Integer inside of `#if`, everything is computed as intmax_t or uintmax_t
+#[derive(Debug, Clone, Copy)]
+struct PpValue {
+    value: i128,
+    unsigned: bool,
+}
+
+impl PpValue {
+    fn new(value: i128, unsigned: bool) -> Self {
+        // wrap around to the range of a 64 bit integer
+        let value = if unsigned {
+            value.rem_euclid(1 << 64)
+        } else {
+            value as i64 as i128
+        };
+        Self { value, unsigned }
+    }
+    fn signed(value: i128) -> Self {
+        Self::new(value, false)
+    }
+    fn truth(&self) -> bool {
+        self.value != 0
+    }
+}
+
+impl Preprocessor {
+    /// Evaluates the condition of `#if` or `#elif`
+    pub fn evaluate_condition(&mut self, tokens: VecDeque<Token>) -> Result<bool> {
+        let tokens = replace_defined(tokens, |name| self.macros.contains_key(name))?;
+        let expanded = self
+            .expand(tokens.into_iter().map(PpToken::new).collect(), false)
+            .context("expanding macros inside of a condition")?;
+
+        let tokens: Vec<Token> = expanded
+            .into_iter()
+            .map(|pp| pp.token)
+            .filter(|token| !is_white_space(token.kind))
+            .collect();
+        if tokens.is_empty() {
+            bail!("condition is empty")
+        }
+        let text: String = tokens.iter().map(|token| token.text() + " ").collect();
+
+        let mut evaluator = ConditionEvaluator { tokens, i: 0 };
+        let value = evaluator
+            .conditional(true)
+            .with_context(|| format!("condition: '{}'", text.trim_end()))?;
+        if let Some(extra) = evaluator.tokens.get(evaluator.i) {
+            bail!(
+                "unexpected: '{}' in condition: '{}'",
+                extra.text(),
+                text.trim_end()
+            )
+        }
+        Ok(value.truth())
+    }
+}
+
+/// `defined NAME` and `defined(NAME)` have to be replaced before macros are expanded
+fn replace_defined(
+    mut tokens: VecDeque<Token>,
+    is_defined: impl Fn(&str) -> bool,
+) -> Result<VecDeque<Token>> {
+    let mut output = VecDeque::new();
+    while let Some(token) = tokens.pop_front() {
+        if token.kind != TokenKind::Identifier || token.value != "defined" {
+            output.push_back(token);
+            continue;
+        }
+
+        skip_white_space(&mut tokens);
+        let parenthesized = tokens
+            .front()
+            .is_some_and(|token| token.kind == TokenKind::OpenParen);
+        if parenthesized {
+            tokens.pop_front();
+            skip_white_space(&mut tokens);
+        }
+        let name = match tokens.pop_front() {
+            Some(name) if name.kind == TokenKind::Identifier => name.value,
+            _ => bail!("'defined' has to be followed by a macro name"),
+        };
+        if parenthesized {
+            skip_white_space(&mut tokens);
+            if tokens.pop_front().map(|token| token.kind) != Some(TokenKind::CloseParen) {
+                bail!("expected to find ')' after 'defined({name}'")
+            }
+        }
+
+        let value = if is_defined(&name) { "1" } else { "0" };
+        output.push_back(Token::new(value.to_owned(), TokenKind::Number, token.line));
+    }
+    Ok(output)
+}
+
+/// Precedence climbing over the tokens of a condition. `live` is false on the side of `&&`,
+/// `||` and `?:` that isn't evaluated, so division by zero there isn't an error.
+struct ConditionEvaluator {
+    tokens: Vec<Token>,
+    i: usize,
+}
+
+impl ConditionEvaluator {
+    fn current_kind(&self) -> Option<TokenKind> {
+        self.tokens.get(self.i).map(|token| token.kind)
+    }
+
+    fn expect(&mut self, expected: TokenKind) -> Result<()> {
+        match self.tokens.get(self.i) {
+            Some(token) if token.kind == expected => {
+                self.i += 1;
+                Ok(())
+            }
+            Some(token) => bail!(
+                "expected to find: '{}', found: '{}'",
+                expected.to_str(),
+                token.text()
+            ),
+            None => bail!("expected to find: '{}'", expected.to_str()),
+        }
+    }
+
+    fn conditional(&mut self, live: bool) -> Result<PpValue> {
+        let condition = self.binary(0, live)?;
+        if self.current_kind() != Some(TokenKind::Question) {
+            return Ok(condition);
+        }
+        self.i += 1;
+        let if_true = self.conditional(live && condition.truth())?;
+        self.expect(TokenKind::Colon)?;
+        let if_false = self.conditional(live && !condition.truth())?;
+
+        let unsigned = if_true.unsigned || if_false.unsigned;
+        let chosen = if condition.truth() { if_true } else { if_false };
+        Ok(PpValue::new(chosen.value, unsigned))
+    }
+
+    fn binary(&mut self, min_precedence: u8, live: bool) -> Result<PpValue> {
+        let mut left = self.unary(live)?;
+
+        while let Some(kind) = self.current_kind()
+            && let Some(precedence) = binary_precedence(kind)
+            && precedence >= min_precedence
+        {
+            self.i += 1;
+            let right_live = match kind {
+                TokenKind::And => live && left.truth(),
+                TokenKind::Or => live && !left.truth(),
+                _ => live,
+            };
+            let right = self.binary(precedence + 1, right_live)?;
+            left = apply_binary(kind, left, right, live)?;
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self, live: bool) -> Result<PpValue> {
+        let Some(token) = self.tokens.get(self.i).cloned() else {
+            bail!("expected to find a value at the end of a condition")
+        };
+        self.i += 1;
+
+        match token.kind {
+            TokenKind::Plus => self.unary(live),
+            TokenKind::Minus => {
+                let value = self.unary(live)?;
+                Ok(PpValue::new(-value.value, value.unsigned))
+            }
+            TokenKind::Not => Ok(PpValue::signed(!self.unary(live)?.truth() as i128)),
+            TokenKind::BitwiseNot => {
+                let value = self.unary(live)?;
+                Ok(PpValue::new(!value.value, value.unsigned))
+            }
+            TokenKind::OpenParen => {
+                let value = self.conditional(live)?;
+                self.expect(TokenKind::CloseParen)?;
+                Ok(value)
+            }
+            TokenKind::Number => {
+                let (value, literal) = parse_integer(&token.value)?;
+                // too big for intmax_t, so it has to be unsigned
+                let unsigned = literal.unsigned || value > i64::MAX as u64;
+                Ok(PpValue::new(value as i128, unsigned))
+            }
+            TokenKind::Character => Ok(PpValue::signed(character_value(&token.value)?)),
+            TokenKind::True => Ok(PpValue::signed(1)),
+            // identifiers that are left after expanding macros are 0, keywords too
+            TokenKind::Identifier | TokenKind::False => Ok(PpValue::signed(0)),
+            TokenKind::Static
+            | TokenKind::Return
+            | TokenKind::If
+            | TokenKind::Else
+            | TokenKind::While
+            | TokenKind::For
+            | TokenKind::Enum
+            | TokenKind::Struct
+            | TokenKind::Union
+            | TokenKind::Break
+            | TokenKind::Constant
+            | TokenKind::Typedef => Ok(PpValue::signed(0)),
+            _ => bail!("expected to find a value, found: '{}'", token.text()),
+        }
+    }
+}
+
+fn binary_precedence(kind: TokenKind) -> Option<u8> {
+    Some(match kind {
+        TokenKind::Or => 1,
+        TokenKind::And => 2,
+        TokenKind::BitwiseOr => 3,
+        TokenKind::BitwiseXor => 4,
+        TokenKind::Reference => 5,
+        TokenKind::Equals | TokenKind::NotEquals => 6,
+        TokenKind::Less | TokenKind::LessEquals | TokenKind::Greater | TokenKind::GreaterEquals => {
+            7
+        }
+        TokenKind::BitwiseShiftLeft | TokenKind::BitwiseShiftRight => 8,
+        TokenKind::Plus | TokenKind::Minus => 9,
+        TokenKind::Star | TokenKind::Slash | TokenKind::Percent => 10,
+        _ => return None,
+    })
+}
+
+fn apply_binary(kind: TokenKind, left: PpValue, right: PpValue, live: bool) -> Result<PpValue> {
+    // usual arithmetic conversions, if either side is unsigned both are
+    let unsigned = left.unsigned || right.unsigned;
+    let (l, r) = (
+        PpValue::new(left.value, unsigned).value,
+        PpValue::new(right.value, unsigned).value,
+    );
+    let boolean = |value: bool| Ok(PpValue::signed(value as i128));
+
+    match kind {
+        TokenKind::Or => boolean(left.truth() || right.truth()),
+        TokenKind::And => boolean(left.truth() && right.truth()),
+        TokenKind::Equals => boolean(l == r),
+        TokenKind::NotEquals => boolean(l != r),
+        TokenKind::Less => boolean(l < r),
+        TokenKind::LessEquals => boolean(l <= r),
+        TokenKind::Greater => boolean(l > r),
+        TokenKind::GreaterEquals => boolean(l >= r),
+        TokenKind::BitwiseOr => Ok(PpValue::new(l | r, unsigned)),
+        TokenKind::BitwiseXor => Ok(PpValue::new(l ^ r, unsigned)),
+        TokenKind::Reference => Ok(PpValue::new(l & r, unsigned)),
+        TokenKind::Plus => Ok(PpValue::new(l.wrapping_add(r), unsigned)),
+        TokenKind::Minus => Ok(PpValue::new(l.wrapping_sub(r), unsigned)),
+        TokenKind::Star => Ok(PpValue::new(l.wrapping_mul(r), unsigned)),
+        // shifts keep the type of the left side
+        TokenKind::BitwiseShiftLeft | TokenKind::BitwiseShiftRight => {
+            let amount = right.value.clamp(-64, 64) as i32;
+            let value = if (kind == TokenKind::BitwiseShiftLeft) == (amount >= 0) {
+                left.value.wrapping_shl(amount.unsigned_abs())
+            } else {
+                left.value >> amount.unsigned_abs()
+            };
+            Ok(PpValue::new(value, left.unsigned))
+        }
+        TokenKind::Slash | TokenKind::Percent => {
+            if r == 0 {
+                if live {
+                    bail!("division by zero")
+                }
+                return Ok(PpValue::new(0, unsigned));
+            }
+            let value = if kind == TokenKind::Slash {
+                l / r
+            } else {
+                l % r
+            };
+            Ok(PpValue::new(value, unsigned))
+        }
+        _ => bail!("'{}' is not a binary operator", kind.to_str()),
+    }
+}
+
+/// Integer literal with C suffixes: `u`, `l`, `ul`, `ll`..., hex, octal and binary
+/// Value of an integer literal and how it is written, the parser reads `Number` tokens with it too
+pub fn parse_integer(text: &str) -> Result<(u64, IntegerLiteral)> {
+    let digits = text.trim_end_matches(['u', 'U', 'l', 'L']);
+    let suffix = &text[digits.len()..];
+    if !["", "u", "l", "ul", "lu", "ll", "ull", "llu"].contains(&suffix.to_lowercase().as_str()) {
+        bail!("invalid suffix of an integer literal: '{text}'");
+    }
+
+    let lower = digits.to_lowercase();
+    let (radix, digits) = if let Some(hex) = lower.strip_prefix("0x") {
+        (16, hex)
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        (2, bin)
+    } else if lower.len() > 1 && lower.starts_with('0') {
+        (8, &lower[1..])
+    } else {
+        (10, lower.as_str())
+    };
+
+    let value = u64::from_str_radix(digits, radix)
+        .with_context(|| format!("invalid integer literal: '{text}'"))?;
+    let literal = IntegerLiteral {
+        unsigned: suffix.contains(['u', 'U']),
+        long: suffix.contains(['l', 'L']),
+        decimal: radix == 10,
+    };
+    Ok((value, literal))
+}
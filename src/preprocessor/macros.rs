@@ -0,0 +1,547 @@
+use std::collections::{HashSet, VecDeque};
+
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    lexer::{
+        self,
+        token::{MacroExpansion, Token, TokenKind},
+    },
+    preprocessor::{PpToken, Preprocessor, expect_macro_name, is_white_space, skip_white_space},
+};
+
+const VA_ARGS: &str = "__VA_ARGS__";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MacroKind {
+    Object,
+    Function {
+        parameters: Vec<String>,
+        variadic: bool,
+    },
+    /// `__FILE__`
+    File,
+    /// `__LINE__`
+    Line,
+}
+
+#[derive(Debug, Clone)]
+pub struct Macro {
+    pub name: String,
+    pub kind: MacroKind,
+    pub body: Vec<Token>,
+    pub file: String,
+    pub line: u16,
+}
+
+impl Macro {
+    /// Redefinition of a macro is allowed only if it doesn't change anything,
+    /// differences in the amount of white space don't count.
+    pub fn same_definition(&self, other: &Macro) -> bool {
+        self.kind == other.kind && normalized_body(&self.body) == normalized_body(&other.body)
+    }
+
+    /// names that are replaced with arguments, with `__VA_ARGS__` at the end for variadic macros
+    fn parameter_names(&self) -> Vec<String> {
+        match &self.kind {
+            MacroKind::Function {
+                parameters,
+                variadic,
+            } => {
+                let mut names = parameters.to_owned();
+                if *variadic {
+                    names.push(VA_ARGS.to_owned());
+                }
+                names
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn normalized_body(body: &[Token]) -> Vec<String> {
+    let mut output: Vec<String> = Vec::new();
+    for token in body {
+        if is_white_space(token.kind) {
+            if output.last().is_some_and(|last| last != " ") {
+                output.push(" ".to_owned());
+            }
+        } else {
+            output.push(token.text());
+        }
+    }
+    output
+}
+
+/// Parses everything after `#define`
+pub fn parse_definition(mut tokens: VecDeque<Token>, file: &str, line: u16) -> Result<Macro> {
+    let name = expect_macro_name(&mut tokens).context("#define")?;
+
+    // '(' has to be right after the name, otherwise it's the start of the body
+    let kind = if tokens
+        .front()
+        .is_some_and(|token| token.kind == TokenKind::OpenParen)
+    {
+        tokens.pop_front();
+        parse_parameters(&mut tokens).with_context(|| format!("parameters of a macro: '{name}'"))?
+    } else {
+        MacroKind::Object
+    };
+
+    skip_white_space(&mut tokens);
+    while tokens
+        .back()
+        .is_some_and(|token| is_white_space(token.kind))
+    {
+        tokens.pop_back();
+    }
+    let body: Vec<Token> = tokens.into_iter().collect();
+
+    let definition = Macro {
+        name,
+        kind,
+        body,
+        file: file.to_owned(),
+        line,
+    };
+    validate_body(&definition)
+        .with_context(|| format!("body of a macro: '{}'", definition.name))?;
+    Ok(definition)
+}
+
+fn parse_parameters(tokens: &mut VecDeque<Token>) -> Result<MacroKind> {
+    let mut parameters = Vec::new();
+    let mut variadic = false;
+    loop {
+        skip_white_space(tokens);
+        let Some(token) = tokens.pop_front() else {
+            bail!("expected to find ')' at the end of macro parameters")
+        };
+        match token.kind {
+            TokenKind::CloseParen if parameters.is_empty() => break,
+            TokenKind::Identifier => {
+                if parameters.contains(&token.value) {
+                    bail!("duplicate macro parameter: '{}'", token.value)
+                }
+                parameters.push(token.value);
+            }
+            TokenKind::Dot => {
+                // '...' is lexed as 3 dots
+                for _ in 0..2 {
+                    if tokens.pop_front().map(|token| token.kind) != Some(TokenKind::Dot) {
+                        bail!("expected to find '...'")
+                    }
+                }
+                variadic = true;
+            }
+            _ => bail!(
+                "expected to find parameter name or '...', found: '{}'",
+                token.text()
+            ),
+        }
+
+        skip_white_space(tokens);
+        match tokens.pop_front().map(|token| token.kind) {
+            Some(TokenKind::CloseParen) => break,
+            Some(TokenKind::Comma) if !variadic => {}
+            _ => bail!("expected to find ',' or ')' after a macro parameter"),
+        }
+    }
+    Ok(MacroKind::Function {
+        parameters,
+        variadic,
+    })
+}
+
+fn validate_body(definition: &Macro) -> Result<()> {
+    let body = &definition.body;
+    if body.first().map(|token| token.kind) == Some(TokenKind::HashHash)
+        || body.last().map(|token| token.kind) == Some(TokenKind::HashHash)
+    {
+        bail!("'##' can't be at either end of a macro body")
+    }
+
+    if let MacroKind::Function { .. } = definition.kind {
+        let names = definition.parameter_names();
+        for (i, token) in body.iter().enumerate() {
+            if token.kind != TokenKind::Hash {
+                continue;
+            }
+            let next = next_non_white_space(body, i + 1);
+            if body
+                .get(next)
+                .is_none_or(|token| parameter_index(&names, token).is_none())
+            {
+                bail!("'#' has to be followed by a macro parameter")
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Preprocessor {
+    /// Returns `None` when the token isn't a macro that can be expanded here, otherwise the
+    /// tokens that should replace it. Arguments of function like macros are taken from `input`.
+    pub fn expand_macro(
+        &mut self,
+        name_token: &PpToken,
+        input: &mut VecDeque<PpToken>,
+    ) -> Result<Option<Vec<PpToken>>> {
+        let name = &name_token.token.value;
+        if name_token.hide_set.contains(name) {
+            return Ok(None);
+        }
+        let Some(definition) = self.macros.get(name).cloned() else {
+            return Ok(None);
+        };
+
+        let line = name_token.token.line;
+        let expansion = MacroExpansion {
+            macro_name: name.to_owned(),
+            definition_file: definition.file.to_owned(),
+            definition_line: definition.line,
+            invocation_line: line,
+            parent: name_token.token.expansion.clone(),
+        };
+        // body tokens report the place where macro was used, and keep where they came from
+        let body: Vec<Token> = definition
+            .body
+            .iter()
+            .map(|token| {
+                let mut token = token.to_owned();
+                token.line = line;
+                token.expansion = Some(Box::new(expansion.clone()));
+                token
+            })
+            .collect();
+
+        let (replacement, mut hide_set) = match &definition.kind {
+            MacroKind::File => (
+                vec![PpToken::new(Token::new(
+                    self.file.to_owned(),
+                    TokenKind::String,
+                    line,
+                ))],
+                name_token.hide_set.to_owned(),
+            ),
+            MacroKind::Line => (
+                vec![PpToken::new(Token::new(
+                    line.to_string(),
+                    TokenKind::Number,
+                    line,
+                ))],
+                name_token.hide_set.to_owned(),
+            ),
+            MacroKind::Object => (
+                substitute(&body, &[], &[], &[], false)?,
+                name_token.hide_set.to_owned(),
+            ),
+            MacroKind::Function {
+                parameters,
+                variadic,
+            } => {
+                let Some((arguments, close_paren)) = collect_arguments(input)? else {
+                    // name of a function like macro without arguments is left alone
+                    return Ok(None);
+                };
+                let arguments = match_arguments(arguments, parameters.len(), *variadic)?;
+
+                let mut expanded_arguments = Vec::new();
+                for argument in &arguments {
+                    expanded_arguments.push(
+                        self.expand(argument.iter().cloned().collect(), false)
+                            .context("expanding macro argument")?,
+                    );
+                }
+
+                let hide_set: HashSet<String> = name_token
+                    .hide_set
+                    .intersection(&close_paren.hide_set)
+                    .cloned()
+                    .collect();
+                (
+                    substitute(
+                        &body,
+                        &definition.parameter_names(),
+                        &arguments,
+                        &expanded_arguments,
+                        true,
+                    )?,
+                    hide_set,
+                )
+            }
+        };
+
+        hide_set.insert(name.to_owned());
+        Ok(Some(
+            replacement
+                .into_iter()
+                .map(|mut token| {
+                    token.hide_set.extend(hide_set.iter().cloned());
+                    token
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// Returns `None` if the next non white space token isn't '(', in that case nothing is consumed.
+fn collect_arguments(
+    input: &mut VecDeque<PpToken>,
+) -> Result<Option<(Vec<Vec<PpToken>>, PpToken)>> {
+    let mut i = 0;
+    while input
+        .get(i)
+        .is_some_and(|token| is_white_space(token.token.kind))
+    {
+        i += 1;
+    }
+    if input.get(i).map(|token| token.token.kind) != Some(TokenKind::OpenParen) {
+        return Ok(None);
+    }
+    input.drain(..=i);
+
+    let mut arguments = vec![Vec::new()];
+    let mut depth = 0;
+    loop {
+        let Some(token) = input.pop_front() else {
+            bail!("unterminated argument list, expected to find ')'")
+        };
+        match token.token.kind {
+            TokenKind::CloseParen if depth == 0 => return Ok(Some((arguments, token))),
+            TokenKind::Comma if depth == 0 => {
+                arguments.push(Vec::new());
+                continue;
+            }
+            TokenKind::OpenParen => depth += 1,
+            TokenKind::CloseParen => depth -= 1,
+            _ => {}
+        }
+        if let Some(last) = arguments.last_mut() {
+            last.push(token);
+        }
+    }
+}
+
+/// Checks the argument count, trims white space and folds the variadic part into one argument,
+/// with a comma and the space that followed it between the arguments
+fn match_arguments(
+    arguments: Vec<Vec<PpToken>>,
+    parameter_count: usize,
+    variadic: bool,
+) -> Result<Vec<Vec<PpToken>>> {
+    // whether white space followed the comma in front of each argument
+    let spaced: Vec<bool> = arguments
+        .iter()
+        .map(|argument| {
+            argument
+                .first()
+                .is_some_and(|token| is_white_space(token.token.kind))
+        })
+        .collect();
+    let mut arguments: Vec<Vec<PpToken>> = arguments.into_iter().map(trim).collect();
+
+    // `f()` passes a single empty argument
+    if parameter_count == 0 && arguments.len() == 1 && arguments[0].is_empty() {
+        arguments.clear();
+    }
+
+    if variadic {
+        if arguments.len() < parameter_count {
+            bail!(
+                "macro requires at least: {parameter_count} arguments, but only: {} were given",
+                arguments.len()
+            )
+        }
+        let mut va_args: Vec<PpToken> = Vec::new();
+        for (i, argument) in arguments.drain(parameter_count..).enumerate() {
+            let line = argument.first().map_or(0, |token| token.token.line);
+            if i != 0 {
+                va_args.push(PpToken::new(Token::new(
+                    String::new(),
+                    TokenKind::Comma,
+                    line,
+                )));
+                // `LOG("%d %d", 1, 2)` keeps the space in `1, 2`
+                if spaced[parameter_count + i] {
+                    va_args.push(PpToken::new(Token::new(
+                        String::new(),
+                        TokenKind::WhiteSpace,
+                        line,
+                    )));
+                }
+            }
+            va_args.extend(argument);
+        }
+        arguments.push(va_args);
+    } else if arguments.len() != parameter_count {
+        bail!(
+            "macro requires: {parameter_count} arguments, but: {} were given",
+            arguments.len()
+        )
+    }
+    Ok(arguments)
+}
+
+fn trim(mut tokens: Vec<PpToken>) -> Vec<PpToken> {
+    while tokens
+        .last()
+        .is_some_and(|token| is_white_space(token.token.kind))
+    {
+        tokens.pop();
+    }
+    let start = tokens
+        .iter()
+        .position(|token| !is_white_space(token.token.kind))
+        .unwrap_or(tokens.len());
+    tokens.split_off(start)
+}
+
+fn parameter_index(names: &[String], token: &Token) -> Option<usize> {
+    if token.kind != TokenKind::Identifier {
+        return None;
+    }
+    names.iter().position(|name| *name == token.value)
+}
+
+fn next_non_white_space(body: &[Token], mut i: usize) -> usize {
+    while body.get(i).is_some_and(|token| is_white_space(token.kind)) {
+        i += 1;
+    }
+    i
+}
+
+/// Stands in for an empty argument used with '##', so that the other side isn't pasted with
+/// some unrelated token. Removed after substitution.
+fn placemarker() -> PpToken {
+    PpToken::new(Token::new(String::new(), TokenKind::Other, 0))
+}
+fn is_placemarker(token: &PpToken) -> bool {
+    token.token.kind == TokenKind::Other && token.token.value.is_empty()
+}
+
+/// Replaces parameters inside of the body with arguments and runs '#' and '##' operators.
+/// `raw_arguments` are used for operands of '#' and '##', every other use gets the fully
+/// expanded version.
+fn substitute(
+    body: &[Token],
+    names: &[String],
+    raw_arguments: &[Vec<PpToken>],
+    expanded_arguments: &[Vec<PpToken>],
+    function_like: bool,
+) -> Result<Vec<PpToken>> {
+    let raw_operand = |index: usize| {
+        if raw_arguments[index].is_empty() {
+            vec![placemarker()]
+        } else {
+            raw_arguments[index].to_owned()
+        }
+    };
+
+    let mut output: Vec<PpToken> = Vec::new();
+    let mut i = 0;
+    while i < body.len() {
+        let token = &body[i];
+        let next = next_non_white_space(body, i + 1);
+
+        if function_like && token.kind == TokenKind::Hash {
+            // checked while parsing the definition
+            let index = body
+                .get(next)
+                .and_then(|token| parameter_index(names, token))
+                .context("'#' has to be followed by a macro parameter")?;
+            output.push(PpToken::new(stringify(&raw_arguments[index], token)));
+            i = next + 1;
+        } else if token.kind == TokenKind::HashHash {
+            while output
+                .last()
+                .is_some_and(|token| is_white_space(token.token.kind))
+            {
+                output.pop();
+            }
+            let right_token = body.get(next).context("'##' is missing right operand")?;
+            let mut right = match parameter_index(names, right_token) {
+                Some(index) => raw_operand(index),
+                None => vec![PpToken::new(right_token.to_owned())],
+            };
+            let left = output.pop().unwrap_or_else(placemarker);
+
+            let first = right.remove(0);
+            output.push(if is_placemarker(&left) {
+                first
+            } else if is_placemarker(&first) {
+                left
+            } else {
+                PpToken::new(paste(&left.token, &first.token)?)
+            });
+            output.extend(right);
+            i = next + 1;
+        } else if let Some(index) = parameter_index(names, token) {
+            if body
+                .get(next)
+                .is_some_and(|token| token.kind == TokenKind::HashHash)
+            {
+                output.extend(raw_operand(index));
+            } else {
+                output.extend(expanded_arguments[index].iter().cloned());
+            }
+            i += 1;
+        } else {
+            output.push(PpToken::new(token.to_owned()));
+            i += 1;
+        }
+    }
+
+    output.retain(|token| !is_placemarker(token));
+    Ok(output)
+}
+
+/// `#argument`: white space between tokens becomes a single space, strings and chars get escaped
+fn stringify(argument: &[PpToken], hash: &Token) -> Token {
+    let mut value = String::new();
+    let mut space = false;
+    for token in argument {
+        if is_white_space(token.token.kind) {
+            space = !value.is_empty();
+            continue;
+        }
+        if space {
+            value += " ";
+            space = false;
+        }
+        match token.token.kind {
+            // quotes and backslashes of literals are escaped in the string
+            TokenKind::String | TokenKind::Character => {
+                value += &token
+                    .token
+                    .text()
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+            }
+            _ => value += &token.token.text(),
+        }
+    }
+
+    let mut output = Token::new(value, TokenKind::String, hash.line);
+    output.expansion = hash.expansion.clone();
+    output
+}
+
+/// `left ## right`: the text of both tokens joined has to form exactly one token
+fn paste(left: &Token, right: &Token) -> Result<Token> {
+    let text = left.text() + &right.text();
+    let mut tokens = lexer::tokenize(text.chars().collect())
+        .with_context(|| format!("tokenization of pasted tokens: '{text}'"))?;
+    if tokens.len() != 1 {
+        bail!(
+            "pasting: '{}' and '{}' does not give a valid token",
+            left.text(),
+            right.text()
+        )
+    }
+
+    let mut output = tokens.remove(0);
+    output.line = left.line;
+    output.expansion = left.expansion.clone();
+    Ok(output)
+}
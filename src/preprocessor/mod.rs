@@ -0,0 +1,261 @@
+pub mod conditional;
+pub mod expression;
+pub mod macros;
+pub mod output;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use anyhow::{Context, Result, bail};
+use log::warn;
+
+use crate::{
+    lexer::{
+        self,
+        token::{Token, TokenKind},
+    },
+    preprocessor::{
+        conditional::Conditional,
+        macros::{Macro, MacroKind},
+    },
+};
+
+/// Token together with the names of macros that can't be expanded from it anymore.
+/// This is what stops `#define foo foo` from expanding forever.
+#[derive(Debug, Clone)]
+pub struct PpToken {
+    pub token: Token,
+    pub hide_set: HashSet<String>,
+}
+impl PpToken {
+    pub fn new(token: Token) -> Self {
+        Self {
+            token,
+            hide_set: HashSet::new(),
+        }
+    }
+}
+
+pub struct Preprocessor {
+    pub file: String,
+    pub macros: HashMap<String, Macro>,
+    /// currently open `#if` groups, innermost last
+    conditionals: Vec<Conditional>,
+}
+
+impl Preprocessor {
+    pub fn new(file: String) -> Self {
+        let mut preprocessor = Self {
+            file,
+            macros: HashMap::new(),
+            conditionals: Vec::new(),
+        };
+        preprocessor.add_predefined_macros();
+        preprocessor
+    }
+
+    fn add_predefined_macros(&mut self) {
+        let predefined = [
+            ("__FILE__", MacroKind::File, vec![]),
+            ("__LINE__", MacroKind::Line, vec![]),
+            (
+                "__STDC__",
+                MacroKind::Object,
+                vec![Token::new("1".to_owned(), TokenKind::Number, 0)],
+            ),
+            (
+                "__STDC_VERSION__",
+                MacroKind::Object,
+                vec![Token::new("201710".to_owned(), TokenKind::Number, 0)],
+            ),
+        ];
+        for (name, kind, body) in predefined {
+            self.macros.insert(
+                name.to_owned(),
+                Macro {
+                    name: name.to_owned(),
+                    kind,
+                    body,
+                    file: "<built-in>".to_owned(),
+                    line: 0,
+                },
+            );
+        }
+    }
+
+    /// Runs all of the directives and expands macros. Expects the full token stream, with white
+    /// space and new lines, so that it knows where arguments of macros start and end.
+    pub fn run(&mut self, tokens: Vec<Token>) -> Result<Vec<Token>> {
+        let input = tokens.into_iter().map(PpToken::new).collect();
+        let output = self.expand(input, true)?;
+        if let Some(unterminated) = self.conditionals.last() {
+            bail!(
+                "#if at: {}:{} is missing its #endif",
+                self.file,
+                unterminated.line
+            )
+        }
+        Ok(output.into_iter().map(|pp| pp.token).collect())
+    }
+
+    /// `-D` from the command line: `NAME` defines it as 1, `NAME=VALUE` as the value
+    pub fn define(&mut self, definition: &str) -> Result<()> {
+        let definition = match definition.split_once('=') {
+            Some((name, value)) => format!("define {name} {value}"),
+            None => format!("define {definition} 1"),
+        };
+        self.directive(Token::new(definition, TokenKind::CompilerData, 0))?;
+        Ok(())
+    }
+
+    /// `-U` from the command line
+    pub fn undefine(&mut self, name: &str) -> Result<()> {
+        self.directive(Token::new(
+            format!("undef {name}"),
+            TokenKind::CompilerData,
+            0,
+        ))?;
+        Ok(())
+    }
+
+    /// Expands every macro inside of `input`. With `run_directives` `CompilerData` tokens are
+    /// treated as directives, this is off while pre-expanding arguments of function like macros.
+    pub fn expand(
+        &mut self,
+        mut input: VecDeque<PpToken>,
+        run_directives: bool,
+    ) -> Result<Vec<PpToken>> {
+        let mut output = Vec::new();
+        while let Some(current) = input.pop_front() {
+            // inside of a skipped group only directives matter, they can end it
+            if run_directives && !self.active() && current.token.kind != TokenKind::CompilerData {
+                continue;
+            }
+            match current.token.kind {
+                TokenKind::CompilerData if run_directives && current.token.expansion.is_none() => {
+                    let line = current.token.line;
+                    if let Some(passed_through) = self
+                        .directive(current.token)
+                        .with_context(|| format!("directive at: {}:{line}", self.file))?
+                    {
+                        output.push(PpToken::new(passed_through));
+                    }
+                }
+                TokenKind::Identifier => {
+                    let line = current.token.line;
+                    let name = current.token.value.to_owned();
+                    match self.expand_macro(&current, &mut input).with_context(|| {
+                        format!("expanding macro: '{name}' at: {}:{line}", self.file)
+                    })? {
+                        Some(expanded) => {
+                            // rescan the result together with the rest of the input
+                            for token in expanded.into_iter().rev() {
+                                input.push_front(token);
+                            }
+                        }
+                        None => output.push(current),
+                    }
+                }
+                _ => output.push(current),
+            }
+        }
+        Ok(output)
+    }
+
+    /// Returns the directive token back if it isn't handled by the preprocessor, so that it
+    /// can be passed to the parser as `CompilerData`.
+    fn directive(&mut self, token: Token) -> Result<Option<Token>> {
+        let mut tokens = directive_tokens(&token)?;
+        let Some(name) = tokens.pop_front() else {
+            // null directive: a line with only '#'
+            return Ok(None);
+        };
+
+        let name = name.text();
+        if conditional::is_conditional_directive(&name) {
+            self.conditional_directive(&name, tokens, token.line)?;
+            return Ok(None);
+        }
+        if !self.active() {
+            return Ok(None);
+        }
+
+        match name.as_str() {
+            "define" => {
+                let definition = macros::parse_definition(tokens, &self.file, token.line)?;
+                if let Some(previous) = self.macros.get(&definition.name)
+                    && !previous.same_definition(&definition)
+                {
+                    warn!(
+                        "macro: '{}' redefined at: {}:{}, previous definition at: {}:{}",
+                        definition.name, self.file, token.line, previous.file, previous.line
+                    );
+                }
+                self.macros.insert(definition.name.to_owned(), definition);
+                Ok(None)
+            }
+            "undef" => {
+                let name = expect_macro_name(&mut tokens).context("#undef")?;
+                self.macros.remove(&name);
+                Ok(None)
+            }
+            "error" => bail!("#error{}", message_text(tokens)),
+            "warning" => {
+                warn!(
+                    "{}:{}: #warning{}",
+                    self.file,
+                    token.line,
+                    message_text(tokens)
+                );
+                Ok(None)
+            }
+            _ => Ok(Some(token)),
+        }
+    }
+}
+
+/// `CompilerData` keeps the whole line as a single value, this splits it back into tokens.
+/// White space is kept, because function like macros need to know if there is a space between
+/// the name and the '('.
+pub fn directive_tokens(token: &Token) -> Result<VecDeque<Token>> {
+    let mut tokens: VecDeque<Token> = lexer::tokenize(token.value.chars().collect())
+        .with_context(|| format!("tokenization of a directive: '#{}'", token.value))?
+        .into_iter()
+        .filter(|token| token.kind != TokenKind::Comment)
+        .map(|mut directive_token| {
+            directive_token.line = token.line;
+            directive_token
+        })
+        .collect();
+    skip_white_space(&mut tokens);
+    Ok(tokens)
+}
+
+/// text of the rest of the directive, for #error and #warning
+fn message_text(tokens: VecDeque<Token>) -> String {
+    tokens.iter().map(|token| token.text()).collect()
+}
+
+pub fn is_white_space(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::WhiteSpace | TokenKind::Tab | TokenKind::NextLine | TokenKind::Comment
+    )
+}
+
+pub fn skip_white_space(tokens: &mut VecDeque<Token>) {
+    while tokens
+        .front()
+        .is_some_and(|token| is_white_space(token.kind))
+    {
+        tokens.pop_front();
+    }
+}
+
+pub fn expect_macro_name(tokens: &mut VecDeque<Token>) -> Result<String> {
+    skip_white_space(tokens);
+    match tokens.pop_front() {
+        Some(token) if token.kind == TokenKind::Identifier => Ok(token.value),
+        Some(token) => bail!("macro names must be identifiers, found: '{}'", token.text()),
+        None => bail!("expected to find a macro name"),
+    }
+}
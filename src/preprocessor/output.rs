@@ -0,0 +1,47 @@
+use crate::lexer::token::{Token, TokenKind};
+
+/// Gaps between lines bigger than this get a line marker instead of empty lines, same as in cc
+const MAX_EMPTY_LINES: u16 = 8;
+
+/// Writes the preprocessed token stream back out as C source, the same thing as `cc -E`.
+/// `# line "file"` markers keep line numbers in sync with the original file, where directives
+/// and skipped groups were removed.
+pub fn write_preprocessed(tokens: &[Token], file: &str, keep_comments: bool) -> String {
+    let mut output = line_marker(1, file);
+    let mut current_line: u16 = 1;
+    let mut at_line_start = true;
+
+    for token in tokens {
+        if token.kind == TokenKind::Comment && !keep_comments {
+            continue;
+        }
+        if token.kind == TokenKind::NextLine {
+            output += "\n";
+            current_line += 1;
+            at_line_start = true;
+            continue;
+        }
+
+        if at_line_start && token.line != current_line {
+            if token.line > current_line && token.line - current_line <= MAX_EMPTY_LINES {
+                for _ in current_line..token.line {
+                    output += "\n";
+                }
+            } else {
+                output += &line_marker(token.line, file);
+            }
+            current_line = token.line;
+        }
+        at_line_start = false;
+        output += &token.text();
+    }
+
+    if !output.ends_with('\n') {
+        output += "\n";
+    }
+    output
+}
+
+fn line_marker(line: u16, file: &str) -> String {
+    format!("# {line} \"{file}\"\n")
+}
@@ -0,0 +1,402 @@
+use crate::{
+    diagnostics::Diagnostic,
+    layout::{LayoutEngine, target::Target},
+    lexer::{character_value, token::TokenKind},
+    parser::{
+        Parser,
+        expression::{DebugData, Expression},
+        types::{DataType, PRIMITIVE_TYPE_NAMES},
+    },
+    semantic::{
+        scope::{SymbolKind, SymbolTable},
+        type_check::{
+            integer_rank, is_integer, literal_type, primitive, promote, usual_arithmetic_conversion,
+        },
+    },
+};
+
+/// Names that a constant expression can refer to. The parser answers while it reads array
+/// sizes and enum values, the symbol table answers after that.
+pub trait ConstantContext {
+    fn enum_constant(&self, name: &str) -> Option<i64>;
+    fn typedef(&self, name: &str) -> Option<DataType>;
+    /// type of a variable, only needed for `sizeof(variable)`
+    fn variable_type(&self, _: &Expression) -> Option<DataType> {
+        None
+    }
+}
+
+impl ConstantContext for Parser {
+    fn enum_constant(&self, name: &str) -> Option<i64> {
+        self.enum_constants.get(name).copied()
+    }
+    fn typedef(&self, name: &str) -> Option<DataType> {
+        self.typedefs.get(name).cloned()
+    }
+}
+
+impl ConstantContext for SymbolTable {
+    fn enum_constant(&self, name: &str) -> Option<i64> {
+        let symbol = self
+            .symbols
+            .iter()
+            .rev()
+            .find(|symbol| symbol.kind == SymbolKind::EnumConstant && symbol.name == name)?;
+        let DataType::Enum { fields } = &symbol.data_type else {
+            return None;
+        };
+        fields
+            .iter()
+            .find(|field| field.name == name)
+            .map(|field| field.value)
+    }
+    fn typedef(&self, name: &str) -> Option<DataType> {
+        SymbolTable::typedef(self, name).map(|symbol| symbol.data_type.to_owned())
+    }
+    fn variable_type(&self, expression: &Expression) -> Option<DataType> {
+        self.resolution(expression)
+            .filter(|symbol| matches!(symbol.kind, SymbolKind::Variable | SymbolKind::Parameter))
+            .map(|symbol| symbol.data_type.to_owned())
+    }
+}
+
+/// Looks through typedefs at the top level of the type
+pub fn resolve_typedefs(context: &impl ConstantContext, data_type: &DataType) -> DataType {
+    if let DataType::Data { name, .. } = data_type
+        && !PRIMITIVE_TYPE_NAMES.contains(&name.as_str())
+        && let Some(inner) = context.typedef(name)
+        // `typedef i32 i32;` would never end
+        && !matches!(&inner, DataType::Data { name: inner, .. } if inner == name)
+    {
+        return resolve_typedefs(context, &inner);
+    }
+    data_type.to_owned()
+}
+
+/// Value of an integer constant expression, already wrapped to the range of its type
+#[derive(Debug, Clone)]
+pub struct Constant {
+    pub value: i128,
+    pub data_type: DataType,
+}
+
+/// Folds integer constant expressions: literals, chars, enum constants, `sizeof`, `_Alignof` and
+/// casts,
+/// with the same overflow rules as C. Unsigned math wraps, signed overflow is reported as a
+/// warning and wraps too. Anything that isn't constant is an error pointing at the exact
+/// subexpression that caused it.
+pub struct Evaluator<'a, C: ConstantContext> {
+    context: &'a C,
+    layout: LayoutEngine<'a, C>,
+    pub warnings: Vec<Diagnostic>,
+}
+
+fn not_constant(expression: &Expression, message: String) -> Diagnostic {
+    let debug_data = expression.debug_data().cloned().unwrap_or(DebugData {
+        line: 0,
+        file: String::new(),
+        expansion: None,
+    });
+    Diagnostic::error(message, &debug_data)
+}
+
+/// Width of an integer type, the type has to be resolved
+fn bits(data_type: &DataType) -> u32 {
+    match integer_rank(data_type) {
+        Some(1) => 1,
+        Some(2) => 8,
+        Some(3) => 16,
+        Some(5) => 64,
+        _ => 32,
+    }
+}
+
+fn is_unsigned(data_type: &DataType) -> bool {
+    matches!(data_type, DataType::Data { unsigned: true, .. })
+}
+
+/// Converts the value to the range of the type, the same as storing it in a variable of it
+pub fn wrap(value: i128, data_type: &DataType) -> i128 {
+    if integer_rank(data_type) == Some(1) {
+        return (value != 0) as i128;
+    }
+    let modulus = 1i128 << bits(data_type);
+    let value = value.rem_euclid(modulus);
+    if !is_unsigned(data_type) && value >= modulus / 2 {
+        value - modulus
+    } else {
+        value
+    }
+}
+
+impl<'a, C: ConstantContext> Evaluator<'a, C> {
+    pub fn new(context: &'a C, target: &'a Target) -> Self {
+        Self {
+            context,
+            layout: LayoutEngine::new(target, context),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn resolve(&self, data_type: &DataType) -> DataType {
+        resolve_typedefs(self.context, data_type)
+    }
+
+    /// Result of a signed operation that doesn't fit its type wraps, but it is worth a warning
+    fn checked(
+        &mut self,
+        value: i128,
+        data_type: DataType,
+        expression: &Expression,
+    ) -> Result<Constant, Diagnostic> {
+        let wrapped = wrap(value, &data_type);
+        if wrapped != value
+            && !is_unsigned(&data_type)
+            && let Some(debug_data) = expression.debug_data()
+        {
+            self.warnings.push(Diagnostic::warning(
+                format!(
+                    "overflow in constant expression, result is {wrapped} with type '{data_type}'"
+                ),
+                debug_data,
+            ));
+        }
+        Ok(Constant {
+            value: wrapped,
+            data_type,
+        })
+    }
+
+    pub fn evaluate(&mut self, expression: &Expression) -> Result<Constant, Diagnostic> {
+        match expression {
+            Expression::Number(value, literal, _) => Ok(Constant {
+                value: *value as i128,
+                data_type: literal_type(*value, *literal),
+            }),
+            Expression::Character(value, _) => Ok(Constant {
+                value: character_value(value)
+                    .map_err(|err| not_constant(expression, err.to_string()))?,
+                data_type: primitive("int", false),
+            }),
+            Expression::Boolean(value, _) => Ok(Constant {
+                value: *value as i128,
+                data_type: primitive("bool", false),
+            }),
+            Expression::Identifier(name, _) => match self.context.enum_constant(name) {
+                Some(value) => Ok(Constant {
+                    value: value as i128,
+                    data_type: primitive("int", false),
+                }),
+                None => Err(not_constant(
+                    expression,
+                    format!("'{name}' is not a compile-time constant"),
+                )),
+            },
+            Expression::Grouping { value, .. } => self.evaluate(value),
+            Expression::TypeConversion {
+                value, data_type, ..
+            } => {
+                let constant = self.evaluate(value)?;
+                let resolved = self.resolve(data_type);
+                if !is_integer(&resolved) {
+                    return Err(not_constant(
+                        expression,
+                        format!(
+                            "cast to '{data_type}' is not allowed in an integer constant expression"
+                        ),
+                    ));
+                }
+                Ok(Constant {
+                    value: wrap(constant.value, &resolved),
+                    data_type: resolved,
+                })
+            }
+            Expression::Prefix { prefix, value, .. } => {
+                let constant = self.evaluate(value)?;
+                let data_type = promote(&self.resolve(&constant.data_type));
+                match prefix.kind {
+                    TokenKind::Plus => Ok(Constant {
+                        value: constant.value,
+                        data_type,
+                    }),
+                    TokenKind::Minus => self.checked(-constant.value, data_type, expression),
+                    TokenKind::BitwiseNot => Ok(Constant {
+                        value: wrap(!constant.value, &data_type),
+                        data_type,
+                    }),
+                    TokenKind::Not => Ok(Constant {
+                        value: (constant.value == 0) as i128,
+                        data_type: primitive("int", false),
+                    }),
+                    kind => Err(not_constant(
+                        expression,
+                        format!(
+                            "unary '{}' is not supported in a constant expression",
+                            kind.to_str()
+                        ),
+                    )),
+                }
+            }
+            Expression::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => self.binary(expression, operator.kind, left, right),
+            Expression::FunctionCall { left, values, .. } => match left.as_ref() {
+                Expression::Identifier(name, _) if name == "sizeof" || name == "_Alignof" => {
+                    let [value] = values.as_slice() else {
+                        return Err(not_constant(
+                            expression,
+                            format!("'{name}' takes 1 operand, found: {}", values.len()),
+                        ));
+                    };
+                    let data_type = match value {
+                        _ if let Some(data_type) = self.context.variable_type(value) => data_type,
+                        Expression::DataTypeAccess { data_type, .. } => data_type.to_owned(),
+                        Expression::String(value, _) => DataType::Array {
+                            length: value.len() as u32 + 1,
+                            inside: Box::new(primitive("char", false)),
+                        },
+                        _ => self.evaluate(value)?.data_type,
+                    };
+                    let layout = self
+                        .layout
+                        .layout(&data_type)
+                        .map_err(|err| not_constant(value, format!("{err:#}")))?;
+                    let value = match name.as_str() {
+                        "sizeof" => layout.size,
+                        _ => layout.alignment,
+                    };
+                    Ok(Constant {
+                        value: value as i128,
+                        data_type: primitive("long", true),
+                    })
+                }
+                Expression::Identifier(name, _) => Err(not_constant(
+                    expression,
+                    format!("call to '{name}' is not allowed in a constant expression"),
+                )),
+                _ => Err(not_constant(
+                    expression,
+                    "function call is not allowed in a constant expression".to_owned(),
+                )),
+            },
+            Expression::Assignment { .. } => Err(not_constant(
+                expression,
+                "assignment is not allowed in a constant expression".to_owned(),
+            )),
+            Expression::Increment { .. } | Expression::Decrement { .. } => Err(not_constant(
+                expression,
+                "increment and decrement are not allowed in a constant expression".to_owned(),
+            )),
+            Expression::String(value, _) => Err(not_constant(
+                expression,
+                format!("string literal \"{value}\" is not an integer constant"),
+            )),
+            Expression::DataTypeAccess { data_type, .. } => Err(not_constant(
+                expression,
+                format!("type name '{data_type}' is not a value"),
+            )),
+            Expression::Dereference { .. }
+            | Expression::AccessReference { .. }
+            | Expression::AccessArray { .. }
+            | Expression::MemberExpr { .. }
+            | Expression::Arrow { .. } => Err(not_constant(
+                expression,
+                "access to memory is not allowed in a constant expression".to_owned(),
+            )),
+            _ => Err(not_constant(
+                expression,
+                "expected an integer constant expression".to_owned(),
+            )),
+        }
+    }
+
+    fn binary(
+        &mut self,
+        expression: &Expression,
+        operator: TokenKind,
+        left: &Expression,
+        right: &Expression,
+    ) -> Result<Constant, Diagnostic> {
+        let left_constant = self.evaluate(left)?;
+        let int = primitive("int", false);
+        // the right side of `0 && x` is never evaluated
+        match operator {
+            TokenKind::And if left_constant.value == 0 => {
+                return Ok(Constant {
+                    value: 0,
+                    data_type: int,
+                });
+            }
+            TokenKind::Or if left_constant.value != 0 => {
+                return Ok(Constant {
+                    value: 1,
+                    data_type: int,
+                });
+            }
+            _ => {}
+        }
+        let right_constant = self.evaluate(right)?;
+        let left_type = self.resolve(&left_constant.data_type);
+        let right_type = self.resolve(&right_constant.data_type);
+
+        if let TokenKind::BitwiseShiftLeft | TokenKind::BitwiseShiftRight = operator {
+            let data_type = promote(&left_type);
+            let count = right_constant.value;
+            if count < 0 || count >= bits(&data_type) as i128 {
+                return Err(not_constant(
+                    right,
+                    format!("shift count {count} is negative or too big for '{data_type}'"),
+                ));
+            }
+            let value = if operator == TokenKind::BitwiseShiftLeft {
+                left_constant.value << count
+            } else {
+                left_constant.value >> count
+            };
+            return self.checked(value, data_type, expression);
+        }
+
+        let data_type = usual_arithmetic_conversion(&left_type, &right_type);
+        let (l, r) = (
+            wrap(left_constant.value, &data_type),
+            wrap(right_constant.value, &data_type),
+        );
+        let truth = |value: bool| {
+            Ok(Constant {
+                value: value as i128,
+                data_type: primitive("int", false),
+            })
+        };
+        match operator {
+            TokenKind::Plus => self.checked(l.wrapping_add(r), data_type, expression),
+            TokenKind::Minus => self.checked(l.wrapping_sub(r), data_type, expression),
+            TokenKind::Star => self.checked(l.wrapping_mul(r), data_type, expression),
+            TokenKind::Slash | TokenKind::Percent if r == 0 => Err(not_constant(
+                expression,
+                "division by zero is not a constant".to_owned(),
+            )),
+            TokenKind::Slash => self.checked(l / r, data_type, expression),
+            TokenKind::Percent => self.checked(l % r, data_type, expression),
+            TokenKind::Reference => self.checked(l & r, data_type, expression),
+            TokenKind::BitwiseOr => self.checked(l | r, data_type, expression),
+            TokenKind::BitwiseXor => self.checked(l ^ r, data_type, expression),
+            TokenKind::Equals => truth(l == r),
+            TokenKind::NotEquals => truth(l != r),
+            TokenKind::Less => truth(l < r),
+            TokenKind::LessEquals => truth(l <= r),
+            TokenKind::Greater => truth(l > r),
+            TokenKind::GreaterEquals => truth(l >= r),
+            TokenKind::And | TokenKind::Or => truth(r != 0),
+            kind => Err(not_constant(
+                expression,
+                format!(
+                    "binary '{}' is not supported in a constant expression",
+                    kind.to_str()
+                ),
+            )),
+        }
+    }
+}
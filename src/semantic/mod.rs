@@ -0,0 +1,3 @@
+pub mod constant;
+pub mod scope;
+pub mod type_check;
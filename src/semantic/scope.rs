@@ -0,0 +1,570 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    diagnostics::Diagnostic,
+    parser::{
+        expression::{AlignAs, DebugData, Expression, ExpressionId},
+        types::{DataType, PRIMITIVE_TYPE_NAMES},
+    },
+};
+
+pub type SymbolId = usize;
+pub type ScopeId = usize;
+
+/// identifiers that look like function calls, but are handled by the compiler itself
+pub const BUILTIN_FUNCTIONS: [&str; 3] = ["sizeof", "_Alignof", "_Static_assert"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    Variable,
+    Parameter,
+    Function,
+    EnumConstant,
+    Typedef,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    File,
+    /// parameters and the top level of the function body
+    Function,
+    Block,
+    /// variables declared inside of `for (...)`, the body gets its own block inside of it
+    ForInit,
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub data_type: DataType,
+    pub scope: ScopeId,
+    pub debug_data: DebugData,
+}
+
+#[derive(Debug, Clone)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    pub parent: Option<ScopeId>,
+    pub symbols: HashMap<String, SymbolId>,
+}
+
+/// A single use of a name that was resolved to its declaration
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub name: String,
+    pub symbol: SymbolId,
+    pub debug_data: DebugData,
+    /// `None` for names of types used inside of declarations
+    pub expression: Option<ExpressionId>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SymbolTable {
+    pub scopes: Vec<Scope>,
+    pub symbols: Vec<Symbol>,
+    pub references: Vec<Reference>,
+    /// `Identifier` (and `DataTypeAccess` shadowed by a variable) expressions -> declarations
+    pub resolutions: HashMap<ExpressionId, SymbolId>,
+    /// `VariableDeclaration` and `Function` expressions -> what they declared
+    pub declarations: HashMap<ExpressionId, SymbolId>,
+    /// `Function` expressions -> their parameters, in order
+    pub parameters: HashMap<ExpressionId, Vec<SymbolId>>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl SymbolTable {
+    pub fn symbol(&self, id: SymbolId) -> &Symbol {
+        &self.symbols[id]
+    }
+
+    /// declaration that the expression refers to
+    pub fn resolution(&self, expression: &Expression) -> Option<&Symbol> {
+        self.resolutions
+            .get(&expression.id())
+            .map(|id| self.symbol(*id))
+    }
+
+    /// Looks for the name starting from `scope` and going outwards
+    pub fn lookup(&self, mut scope: ScopeId, name: &str) -> Option<SymbolId> {
+        loop {
+            if let Some(id) = self.scopes[scope].symbols.get(name) {
+                return Some(*id);
+            }
+            scope = self.scopes[scope].parent?;
+        }
+    }
+
+    /// Typedefs are global for the parser, so they can be found by name alone
+    pub fn typedef(&self, name: &str) -> Option<&Symbol> {
+        self.symbols
+            .iter()
+            .rev()
+            .find(|symbol| symbol.kind == SymbolKind::Typedef && symbol.name == name)
+    }
+
+    pub fn references_to(&self, id: SymbolId) -> impl Iterator<Item = &Reference> {
+        self.references
+            .iter()
+            .filter(move |reference| reference.symbol == id)
+    }
+
+    /// Human readable list of declarations and what every use resolved to
+    pub fn report(&self) -> String {
+        let mut output = String::from("symbols:\n");
+        for (id, symbol) in self.symbols.iter().enumerate() {
+            output += &format!(
+                "  #{id} {:?} '{}' declared at line: {}, scope: #{} ({:?}), type: {:?}\n",
+                symbol.kind,
+                symbol.name,
+                symbol.debug_data.line,
+                symbol.scope,
+                self.scopes[symbol.scope].kind,
+                symbol.data_type
+            );
+        }
+        output += "references:\n";
+        for reference in &self.references {
+            let symbol = self.symbol(reference.symbol);
+            output += &format!(
+                "  line: {} '{}' -> #{} {:?} declared at line: {}\n",
+                reference.debug_data.line,
+                reference.name,
+                reference.symbol,
+                symbol.kind,
+                symbol.debug_data.line
+            );
+        }
+        output
+    }
+}
+
+/// Builds nested scopes for the output of `parser::parse` and links every use of a name to its
+/// declaration. Undeclared names end up in `diagnostics`.
+pub fn resolve(expressions: &[Expression]) -> SymbolTable {
+    let mut resolver = Resolver {
+        table: SymbolTable {
+            scopes: vec![Scope {
+                kind: ScopeKind::File,
+                parent: None,
+                symbols: HashMap::new(),
+            }],
+            symbols: Vec::new(),
+            references: Vec::new(),
+            resolutions: HashMap::new(),
+            declarations: HashMap::new(),
+            parameters: HashMap::new(),
+            diagnostics: Vec::new(),
+        },
+        current: 0,
+        implicit_functions: HashSet::new(),
+        prototypes: HashSet::new(),
+    };
+    for expression in expressions {
+        resolver.visit(expression);
+    }
+    resolver.table
+}
+
+struct Resolver {
+    table: SymbolTable,
+    current: ScopeId,
+    /// calls to undeclared functions are reported only once per name
+    implicit_functions: HashSet<String>,
+    /// functions that were declared by a prototype, but aren't defined yet
+    prototypes: HashSet<SymbolId>,
+}
+
+impl Resolver {
+    fn push_scope(&mut self, kind: ScopeKind) {
+        self.table.scopes.push(Scope {
+            kind,
+            parent: Some(self.current),
+            symbols: HashMap::new(),
+        });
+        self.current = self.table.scopes.len() - 1;
+    }
+    fn pop_scope(&mut self) {
+        self.current = self.table.scopes[self.current]
+            .parent
+            .expect("file scope is never popped");
+    }
+
+    fn declare(
+        &mut self,
+        name: &str,
+        kind: SymbolKind,
+        data_type: DataType,
+        debug_data: &DebugData,
+    ) -> SymbolId {
+        if let Some(previous) = self.table.scopes[self.current].symbols.get(name) {
+            let previous = self.table.symbol(*previous);
+            self.table.diagnostics.push(Diagnostic::error(
+                format!(
+                    "redeclaration of '{name}', previous declaration at line: {}",
+                    previous.debug_data.line
+                ),
+                debug_data,
+            ));
+        }
+
+        self.table.symbols.push(Symbol {
+            name: name.to_owned(),
+            kind,
+            data_type,
+            scope: self.current,
+            debug_data: debug_data.to_owned(),
+        });
+        let id = self.table.symbols.len() - 1;
+        self.table.scopes[self.current]
+            .symbols
+            .insert(name.to_owned(), id);
+        id
+    }
+
+    /// Function in the current scope that only has a prototype so far
+    fn prototype(&self, name: &str) -> Option<SymbolId> {
+        self.table.scopes[self.current]
+            .symbols
+            .get(name)
+            .copied()
+            .filter(|id| self.prototypes.contains(id))
+    }
+
+    /// enum constants are declared in the same scope as the enum itself
+    fn declare_enum_constants(&mut self, data_type: &DataType, debug_data: &DebugData) {
+        if let DataType::Enum { fields } = data_type {
+            for field in fields {
+                self.declare(
+                    &field.name,
+                    SymbolKind::EnumConstant,
+                    data_type.to_owned(),
+                    debug_data,
+                );
+            }
+        }
+    }
+
+    fn reference(
+        &mut self,
+        name: &str,
+        symbol: SymbolId,
+        debug_data: &DebugData,
+        expression: Option<&Expression>,
+    ) {
+        if let Some(expression) = expression {
+            self.table.resolutions.insert(expression.id(), symbol);
+        }
+        self.table.references.push(Reference {
+            name: name.to_owned(),
+            symbol,
+            debug_data: debug_data.to_owned(),
+            expression: expression.map(|expression| expression.id()),
+        });
+    }
+
+    fn use_name(
+        &mut self,
+        expression: &Expression,
+        name: &str,
+        debug_data: &DebugData,
+        call: bool,
+    ) {
+        match self.table.lookup(self.current, name) {
+            Some(symbol) => self.reference(name, symbol, debug_data, Some(expression)),
+            None if call => {
+                if self.implicit_functions.insert(name.to_owned()) {
+                    self.table.diagnostics.push(Diagnostic::warning(
+                        format!("implicit declaration of function '{name}'"),
+                        debug_data,
+                    ));
+                }
+            }
+            None => self.table.diagnostics.push(Diagnostic::error(
+                format!("use of undeclared identifier '{name}'"),
+                debug_data,
+            )),
+        }
+    }
+
+    /// names of typedefs used inside of a type
+    fn data_type(&mut self, data_type: &DataType, debug_data: &DebugData) {
+        match data_type {
+            DataType::Data { name, .. } => {
+                if PRIMITIVE_TYPE_NAMES.contains(&name.as_str()) {
+                    return;
+                }
+                match self.table.lookup(self.current, name) {
+                    Some(symbol) if self.table.symbol(symbol).kind == SymbolKind::Typedef => {
+                        self.reference(name, symbol, debug_data, None)
+                    }
+                    // `i32 i32 = 7;` hides the typedef for the rest of the scope
+                    Some(symbol) => {
+                        let symbol = self.table.symbol(symbol);
+                        self.table.diagnostics.push(Diagnostic::error(
+                            format!(
+                                "'{name}' is not a type name here, it is hidden by a {:?} declared at line: {}",
+                                symbol.kind, symbol.debug_data.line
+                            ),
+                            debug_data,
+                        ))
+                    }
+                    None => self.table.diagnostics.push(Diagnostic::error(
+                        format!("unknown type name '{name}'"),
+                        debug_data,
+                    )),
+                }
+            }
+            DataType::Array { inside, .. } | DataType::Pointer(inside) => {
+                self.data_type(inside, debug_data)
+            }
+            DataType::Struct { properties, .. } | DataType::Union { properties, .. } => {
+                for property in properties {
+                    self.data_type(&property.var_type, debug_data);
+                    if let Some(AlignAs::Type(data_type)) = &property.alignas {
+                        self.data_type(data_type, debug_data);
+                    }
+                }
+            }
+            DataType::Enum { .. } => {}
+            DataType::Function {
+                output, parameters, ..
+            } => {
+                self.data_type(output, debug_data);
+                for parameter in parameters {
+                    self.data_type(parameter, debug_data);
+                }
+            }
+        }
+    }
+
+    fn block(&mut self, kind: ScopeKind, inside: &[Expression]) {
+        self.push_scope(kind);
+        for expression in inside {
+            self.visit(expression);
+        }
+        self.pop_scope();
+    }
+
+    fn visit(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Skip
+            | Expression::Boolean(..)
+            | Expression::Number(..)
+            | Expression::CompilerData(..)
+            | Expression::String(..)
+            | Expression::Character(..)
+            | Expression::Break { .. } => {}
+            Expression::Identifier(name, debug_data) => {
+                self.use_name(expression, name, debug_data, false)
+            }
+            Expression::Increment { target, .. } | Expression::Decrement { target, .. } => {
+                self.visit(target)
+            }
+            Expression::DataStructureInitialization { values, .. } => {
+                for value in values {
+                    self.visit(value);
+                }
+            }
+            Expression::TypeConversion {
+                value,
+                data_type,
+                debug_data,
+            } => {
+                self.data_type(data_type, debug_data);
+                self.visit(value);
+            }
+            Expression::Typedef {
+                data_type,
+                name,
+                debug_data,
+            } => {
+                self.data_type(data_type, debug_data);
+                self.declare_enum_constants(data_type, debug_data);
+                self.declare(name, SymbolKind::Typedef, data_type.to_owned(), debug_data);
+            }
+            // the right side is a name of a member, the type checker looks at it
+            Expression::Arrow { left, .. } | Expression::MemberExpr { left, .. } => {
+                self.visit(left)
+            }
+            Expression::Dereference { value, .. }
+            | Expression::AccessReference { value, .. }
+            | Expression::Grouping { value, .. }
+            | Expression::Static { value, .. }
+            | Expression::Return { value, .. }
+            | Expression::Prefix { value, .. } => self.visit(value),
+            Expression::Assignment { target, value, .. } => {
+                // `int x = x;` already sees the new x, so the target goes first
+                self.visit(target);
+                self.visit(value);
+            }
+            Expression::DataTypeAccess {
+                data_type,
+                debug_data,
+            } => {
+                // a variable can have the same name as a type: `i32 i32 = 7; i32 + 1;`
+                if let DataType::Data { name, .. } = data_type
+                    && let Some(symbol) = self.table.lookup(self.current, name)
+                    && self.table.symbol(symbol).kind != SymbolKind::Typedef
+                {
+                    self.reference(name, symbol, debug_data, Some(expression));
+                } else {
+                    self.data_type(data_type, debug_data);
+                }
+            }
+            Expression::VariableDeclaration {
+                var_type,
+                name,
+                debug_data,
+            } => {
+                self.data_type(var_type, debug_data);
+                self.declare_enum_constants(var_type, debug_data);
+                let id = self.declare(name, SymbolKind::Variable, var_type.to_owned(), debug_data);
+                self.table.declarations.insert(expression.id(), id);
+            }
+            Expression::Struct { functions, .. } => {
+                for function in functions {
+                    self.visit(function);
+                }
+            }
+            Expression::NewCodeBlock { inside, .. } => self.block(ScopeKind::Block, inside),
+            Expression::Binary { left, right, .. } => {
+                self.visit(left);
+                self.visit(right);
+            }
+            Expression::Function {
+                name,
+                properties,
+                output,
+                inside,
+                debug_data,
+            } => {
+                self.data_type(output, debug_data);
+                let function_type = DataType::Function {
+                    output: Box::new(output.to_owned()),
+                    parameters: properties
+                        .iter()
+                        .map(|property| property.var_type.to_owned())
+                        .collect(),
+                    variadic: false,
+                };
+                // declared before the body, so that it can call itself
+                let id = match self.prototype(name) {
+                    Some(id) => {
+                        self.prototypes.remove(&id);
+                        id
+                    }
+                    None => self.declare(name, SymbolKind::Function, function_type, debug_data),
+                };
+                self.table.declarations.insert(expression.id(), id);
+
+                self.push_scope(ScopeKind::Function);
+                let mut parameters = Vec::new();
+                for property in properties {
+                    self.data_type(&property.var_type, debug_data);
+                    parameters.push(self.declare(
+                        &property.var_name,
+                        SymbolKind::Parameter,
+                        property.var_type.to_owned(),
+                        debug_data,
+                    ));
+                }
+                self.table.parameters.insert(expression.id(), parameters);
+                for expression in inside {
+                    self.visit(expression);
+                }
+                self.pop_scope();
+            }
+            Expression::FunctionDeclaration {
+                name,
+                properties,
+                output,
+                variadic,
+                debug_data,
+            } => {
+                self.data_type(output, debug_data);
+                for property in properties {
+                    self.data_type(&property.var_type, debug_data);
+                }
+                let function_type = DataType::Function {
+                    output: Box::new(output.to_owned()),
+                    parameters: properties
+                        .iter()
+                        .map(|property| property.var_type.to_owned())
+                        .collect(),
+                    variadic: *variadic,
+                };
+                // a function can be declared again, and defined once
+                let id = match self.table.scopes[self.current].symbols.get(name) {
+                    Some(id) if self.table.symbol(*id).kind == SymbolKind::Function => *id,
+                    _ => {
+                        let id =
+                            self.declare(name, SymbolKind::Function, function_type, debug_data);
+                        self.prototypes.insert(id);
+                        id
+                    }
+                };
+                self.table.declarations.insert(expression.id(), id);
+            }
+            Expression::If {
+                condition,
+                inside,
+                chained_elses,
+                ..
+            } => {
+                self.visit(condition);
+                self.block(ScopeKind::Block, inside);
+                for chained_else in chained_elses {
+                    self.visit(chained_else);
+                }
+            }
+            Expression::Else {
+                condition, inside, ..
+            } => {
+                if let Some(condition) = condition {
+                    self.visit(condition);
+                }
+                self.block(ScopeKind::Block, inside);
+            }
+            Expression::AccessArray { left, index, .. } => {
+                self.visit(left);
+                self.visit(index);
+            }
+            Expression::While {
+                condition, inside, ..
+            } => {
+                self.visit(condition);
+                self.block(ScopeKind::Block, inside);
+            }
+            Expression::For {
+                iterator_init,
+                condition,
+                incr,
+                inside,
+                ..
+            } => {
+                self.push_scope(ScopeKind::ForInit);
+                self.visit(iterator_init);
+                self.visit(condition);
+                self.visit(incr);
+                self.block(ScopeKind::Block, inside);
+                self.pop_scope();
+            }
+            Expression::FunctionCall {
+                left,
+                values,
+                debug_data,
+            } => {
+                match left.as_ref() {
+                    Expression::Identifier(name, _)
+                        if BUILTIN_FUNCTIONS.contains(&name.as_str()) => {}
+                    Expression::Identifier(name, _) => self.use_name(left, name, debug_data, true),
+                    _ => self.visit(left),
+                }
+                for value in values {
+                    self.visit(value);
+                }
+            }
+        }
+    }
+}
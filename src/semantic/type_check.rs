@@ -0,0 +1,1269 @@
+use std::collections::HashMap;
+
+use crate::{
+    diagnostics::Diagnostic,
+    layout::target::Target,
+    lexer::{character_value, token::TokenKind},
+    parser::{
+        expression::{DebugData, Expression, ExpressionId, IntegerLiteral, Property},
+        types::DataType,
+    },
+    semantic::{
+        constant::{Evaluator, resolve_typedefs},
+        scope::{SymbolKind, SymbolTable},
+    },
+};
+
+/// Type of every expression node, computed by `check`
+#[derive(Debug, Clone)]
+pub struct TypeTable {
+    /// types keep the names of typedefs, `resolve` looks through them
+    pub types: HashMap<ExpressionId, DataType>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl TypeTable {
+    pub fn type_of(&self, expression: &Expression) -> Option<&DataType> {
+        self.types.get(&expression.id())
+    }
+}
+
+/// Computes the type of every expression and reports everything that C wouldn't accept.
+/// Expressions whose type can't be known (undeclared names, earlier errors) are left out of
+/// the table, so one mistake doesn't turn into a chain of diagnostics.
+pub fn check(expressions: &[Expression], symbols: &SymbolTable, target: &Target) -> TypeTable {
+    let mut checker = Checker {
+        symbols,
+        target,
+        table: TypeTable {
+            types: HashMap::new(),
+            diagnostics: Vec::new(),
+        },
+        function: None,
+    };
+    for expression in expressions {
+        checker.expression(expression);
+    }
+    checker.table
+}
+
+pub fn primitive(name: &str, unsigned: bool) -> DataType {
+    DataType::Data {
+        name: name.to_owned(),
+        unsigned,
+        constant: false,
+    }
+}
+
+/// First type of the list for the suffix that holds the value (C11 6.4.4.1), decimal literals
+/// without `u` skip the unsigned ones. `long long` is `long`, and a decimal literal too big for
+/// `long` is `unsigned long` like gcc makes it.
+pub fn literal_type(value: u64, literal: IntegerLiteral) -> DataType {
+    let fits_int = value <= i32::MAX as u64;
+    let fits_unsigned_int = value <= u32::MAX as u64;
+    let fits_long = value <= i64::MAX as u64;
+    match (literal.unsigned, literal.long) {
+        (false, false) if fits_int => primitive("int", false),
+        (false, false) if fits_unsigned_int && !literal.decimal => primitive("int", true),
+        (false, _) if fits_long => primitive("long", false),
+        (true, false) if fits_unsigned_int => primitive("int", true),
+        _ => primitive("long", true),
+    }
+}
+
+/// Looks through typedefs at the top level of the type
+pub fn resolve(symbols: &SymbolTable, data_type: &DataType) -> DataType {
+    resolve_typedefs(symbols, data_type)
+}
+
+/// Conversion rank of an integer type, enums are compatible with `int`
+pub fn integer_rank(data_type: &DataType) -> Option<u8> {
+    match data_type {
+        DataType::Data { name, .. } => match name.as_str() {
+            "bool" => Some(1),
+            "char" => Some(2),
+            "short" => Some(3),
+            "int" => Some(4),
+            "long" => Some(5),
+            _ => None,
+        },
+        DataType::Enum { .. } => Some(4),
+        _ => None,
+    }
+}
+
+pub fn is_floating(data_type: &DataType) -> bool {
+    matches!(data_type, DataType::Data { name, .. } if name == "float" || name == "double")
+}
+
+pub fn is_integer(data_type: &DataType) -> bool {
+    integer_rank(data_type).is_some()
+}
+
+pub fn is_arithmetic(data_type: &DataType) -> bool {
+    is_integer(data_type) || is_floating(data_type)
+}
+
+pub fn is_scalar(data_type: &DataType) -> bool {
+    is_arithmetic(data_type) || matches!(data_type, DataType::Pointer(_))
+}
+
+pub fn is_void(data_type: &DataType) -> bool {
+    matches!(data_type, DataType::Data { name, .. } if name == "void")
+}
+
+fn is_unsigned(data_type: &DataType) -> bool {
+    matches!(data_type, DataType::Data { unsigned: true, .. })
+}
+
+/// Everything smaller than `int` is computed as an `int`
+pub fn promote(data_type: &DataType) -> DataType {
+    match integer_rank(data_type) {
+        Some(rank) if rank < 4 || matches!(data_type, DataType::Enum { .. }) => {
+            primitive("int", false)
+        }
+        _ => data_type.to_owned(),
+    }
+}
+
+/// Common type of both operands of an arithmetic operator. Both types have to be resolved.
+pub fn usual_arithmetic_conversion(left: &DataType, right: &DataType) -> DataType {
+    for floating in ["double", "float"] {
+        if [left, right]
+            .iter()
+            .any(|data_type| matches!(data_type, DataType::Data { name, .. } if name == floating))
+        {
+            return primitive(floating, false);
+        }
+    }
+
+    let (left, right) = (promote(left), promote(right));
+    let (left_rank, right_rank) = (integer_rank(&left), integer_rank(&right));
+    if is_unsigned(&left) == is_unsigned(&right) {
+        return if left_rank >= right_rank { left } else { right };
+    }
+    let (unsigned, signed) = if is_unsigned(&left) {
+        (left, right)
+    } else {
+        (right, left)
+    };
+    // in LP64 a bigger rank is also a bigger size, so the signed type can hold every value
+    if integer_rank(&unsigned) >= integer_rank(&signed) {
+        unsigned
+    } else {
+        signed
+    }
+}
+
+/// Number of bytes in the array created for a string literal, with the terminating zero
+fn string_length(value: &str) -> u32 {
+    let mut length = 1;
+    let mut chars = value.chars();
+    while let Some(char) = chars.next() {
+        if char == '\\' {
+            chars.next();
+        }
+        length += 1;
+    }
+    length
+}
+
+fn is_null_pointer_constant(expression: &Expression) -> bool {
+    match expression {
+        Expression::Number(0, ..) => true,
+        Expression::Grouping { value, .. } => is_null_pointer_constant(value),
+        Expression::TypeConversion {
+            value, data_type, ..
+        } => {
+            matches!(data_type, DataType::Pointer(inside) if is_void(inside))
+                && is_null_pointer_constant(value)
+        }
+        _ => false,
+    }
+}
+
+/// operator of `a += b` as if it was written `a = a + b`
+pub fn compound_operator(kind: TokenKind) -> Option<TokenKind> {
+    match kind {
+        TokenKind::PlusEquals => Some(TokenKind::Plus),
+        TokenKind::MinusEquals => Some(TokenKind::Minus),
+        TokenKind::StarEquals => Some(TokenKind::Star),
+        TokenKind::SlashEquals => Some(TokenKind::Slash),
+        TokenKind::PercentEquals => Some(TokenKind::Percent),
+        TokenKind::BitwiseShiftLeftEquals => Some(TokenKind::BitwiseShiftLeft),
+        TokenKind::BitwiseShiftRightEquals => Some(TokenKind::BitwiseShiftRight),
+        TokenKind::BitwiseAndEquals => Some(TokenKind::Reference),
+        TokenKind::BitwiseOrEquals => Some(TokenKind::BitwiseOr),
+        TokenKind::BitwiseXorEquals => Some(TokenKind::BitwiseXor),
+        _ => None,
+    }
+}
+
+struct Checker<'a> {
+    symbols: &'a SymbolTable,
+    /// for `sizeof` inside of `_Static_assert`
+    target: &'a Target,
+    table: TypeTable,
+    /// name and output type of the function whose body is being checked
+    function: Option<(String, DataType)>,
+}
+
+impl Checker<'_> {
+    fn error(&mut self, message: String, debug_data: &DebugData) {
+        self.table
+            .diagnostics
+            .push(Diagnostic::error(message, debug_data));
+    }
+    fn warning(&mut self, message: String, debug_data: &DebugData) {
+        self.table
+            .diagnostics
+            .push(Diagnostic::warning(message, debug_data));
+    }
+
+    fn resolve(&self, data_type: &DataType) -> DataType {
+        resolve(self.symbols, data_type)
+    }
+
+    /// Two types are the same after looking through typedefs at every level
+    fn compatible(&self, left: &DataType, right: &DataType) -> bool {
+        match (&self.resolve(left), &self.resolve(right)) {
+            (
+                DataType::Data {
+                    name: left_name,
+                    unsigned: left_unsigned,
+                    ..
+                },
+                DataType::Data {
+                    name: right_name,
+                    unsigned: right_unsigned,
+                    ..
+                },
+            ) => left_name == right_name && left_unsigned == right_unsigned,
+            (DataType::Pointer(left), DataType::Pointer(right)) => self.compatible(left, right),
+            (
+                DataType::Array {
+                    length: left_length,
+                    inside: left,
+                },
+                DataType::Array {
+                    length: right_length,
+                    inside: right,
+                },
+            ) => left_length == right_length && self.compatible(left, right),
+            (
+                DataType::Struct {
+                    properties: left, ..
+                },
+                DataType::Struct {
+                    properties: right, ..
+                },
+            )
+            | (
+                DataType::Union {
+                    properties: left, ..
+                },
+                DataType::Union {
+                    properties: right, ..
+                },
+            ) => {
+                left.len() == right.len()
+                    && left.iter().zip(right).all(|(left, right)| {
+                        left.var_name == right.var_name
+                            && self.compatible(&left.var_type, &right.var_type)
+                    })
+            }
+            (DataType::Enum { fields: left }, DataType::Enum { fields: right }) => {
+                left.len() == right.len()
+                    && left
+                        .iter()
+                        .zip(right)
+                        .all(|(left, right)| left.name == right.name)
+            }
+            (
+                DataType::Function {
+                    output: left_output,
+                    parameters: left,
+                    variadic: left_variadic,
+                },
+                DataType::Function {
+                    output: right_output,
+                    parameters: right,
+                    variadic: right_variadic,
+                },
+            ) => {
+                self.compatible(left_output, right_output)
+                    && left_variadic == right_variadic
+                    && left.len() == right.len()
+                    && left
+                        .iter()
+                        .zip(right)
+                        .all(|(left, right)| self.compatible(left, right))
+            }
+            _ => false,
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Option<DataType> {
+        let data_type = self.compute(expression);
+        if let Some(data_type) = &data_type {
+            self.table
+                .types
+                .insert(expression.id(), data_type.to_owned());
+        }
+        data_type
+    }
+
+    /// Type of the expression used as a value: arrays decay to pointers to their first element
+    /// and functions to function pointers
+    fn value(&mut self, expression: &Expression) -> Option<DataType> {
+        let data_type = self.expression(expression)?;
+        Some(match self.resolve(&data_type) {
+            DataType::Array { inside, .. } => DataType::Pointer(inside),
+            function @ DataType::Function { .. } => DataType::Pointer(Box::new(function)),
+            _ => data_type,
+        })
+    }
+
+    fn condition(&mut self, condition: &Expression) {
+        if let Some(data_type) = self.value(condition)
+            && !is_scalar(&self.resolve(&data_type))
+            && let Some(debug_data) = condition.debug_data()
+        {
+            self.error(
+                format!("statement requires expression of scalar type ('{data_type}' invalid)"),
+                debug_data,
+            );
+        }
+    }
+
+    fn block(&mut self, inside: &[Expression]) {
+        for expression in inside {
+            self.expression(expression);
+        }
+    }
+
+    fn is_lvalue(&self, expression: &Expression) -> bool {
+        match expression {
+            Expression::Identifier(..) | Expression::DataTypeAccess { .. } => matches!(
+                self.symbols
+                    .resolution(expression)
+                    .map(|symbol| symbol.kind),
+                Some(SymbolKind::Variable | SymbolKind::Parameter)
+            ),
+            Expression::VariableDeclaration { .. }
+            | Expression::Dereference { .. }
+            | Expression::AccessArray { .. }
+            | Expression::Arrow { .. }
+            | Expression::String(..) => true,
+            Expression::MemberExpr { left, .. } | Expression::Grouping { value: left, .. } => {
+                self.is_lvalue(left)
+            }
+            _ => false,
+        }
+    }
+
+    /// Targets of assignments, `++` and `--`. Returns false if an error was reported.
+    fn require_modifiable(&mut self, target: &Expression, data_type: &DataType) -> bool {
+        let Some(debug_data) = target.debug_data() else {
+            return true;
+        };
+        let resolved = self.resolve(data_type);
+        if !self.is_lvalue(target) || matches!(target, Expression::String(..)) {
+            self.error("expression is not assignable".to_owned(), debug_data);
+        } else if matches!(resolved, DataType::Array { .. }) {
+            self.error(
+                format!("array type '{data_type}' is not assignable"),
+                debug_data,
+            );
+        } else if matches!(resolved, DataType::Function { .. }) {
+            self.error(
+                format!("function type '{data_type}' is not assignable"),
+                debug_data,
+            );
+        } else {
+            return true;
+        }
+        false
+    }
+
+    /// Checks that a value can be stored in something of the target type, like in an
+    /// assignment. `action` describes where it happens: "assigning to", "passing", ...
+    fn assign(
+        &mut self,
+        target: &DataType,
+        value_expression: &Expression,
+        value: &DataType,
+        action: &str,
+        debug_data: &DebugData,
+    ) {
+        let (resolved_target, resolved_value) = (self.resolve(target), self.resolve(value));
+        match (&resolved_target, &resolved_value) {
+            (target, value) if is_arithmetic(target) && is_arithmetic(value) => {}
+            (DataType::Data { name, .. }, DataType::Pointer(_)) if name == "bool" => {}
+            (DataType::Pointer(target_inside), DataType::Pointer(value_inside)) => {
+                let target_inside = self.resolve(target_inside);
+                let value_inside = self.resolve(value_inside);
+                if !is_void(&target_inside)
+                    && !is_void(&value_inside)
+                    && !self.compatible(&target_inside, &value_inside)
+                {
+                    self.warning(
+                        format!("incompatible pointer types {action} '{target}' from '{value}'"),
+                        debug_data,
+                    );
+                }
+            }
+            (DataType::Pointer(_), value_type) if is_integer(value_type) => {
+                if !is_null_pointer_constant(value_expression) {
+                    self.warning(
+                        format!(
+                            "incompatible integer to pointer conversion {action} '{target}' from '{value}'"
+                        ),
+                        debug_data,
+                    );
+                }
+            }
+            (target_type, DataType::Pointer(_)) if is_integer(target_type) => self.warning(
+                format!(
+                    "incompatible pointer to integer conversion {action} '{target}' from '{value}'"
+                ),
+                debug_data,
+            ),
+            (DataType::Struct { .. }, DataType::Struct { .. })
+            | (DataType::Union { .. }, DataType::Union { .. })
+                if self.compatible(&resolved_target, &resolved_value) => {}
+            _ => self.error(
+                format!("incompatible types {action} '{target}' from '{value}'"),
+                debug_data,
+            ),
+        }
+    }
+
+    /// `int x = ...`, `Vec v = {1, 2}`
+    fn initializer(&mut self, target: &DataType, value: &Expression, debug_data: &DebugData) {
+        let Expression::DataStructureInitialization { values, .. } = value else {
+            let resolved = self.resolve(target);
+            // `char name[6] = "hello";` copies the literal instead of decaying it
+            if let (DataType::Array { length, inside }, Expression::String(string, _)) =
+                (&resolved, value)
+                && integer_rank(&self.resolve(inside)) == Some(2)
+            {
+                self.expression(value);
+                if string_length(string) > *length + 1 {
+                    self.warning(
+                        format!("initializer-string for char array '{target}' is too long"),
+                        debug_data,
+                    );
+                }
+                return;
+            }
+            if let DataType::Array { .. } = resolved {
+                self.value(value);
+                self.error(
+                    format!("array '{target}' has to be initialized with an initializer list"),
+                    debug_data,
+                );
+                return;
+            }
+            if let Some(value_type) = self.value(value) {
+                self.assign(target, value, &value_type, "initializing", debug_data);
+            }
+            return;
+        };
+        self.table.types.insert(value.id(), target.to_owned());
+
+        let element_types = match self.resolve(target) {
+            DataType::Array { length, inside } => vec![*inside; length as usize],
+            DataType::Struct { properties, .. } => properties
+                .into_iter()
+                .map(|property| property.var_type)
+                .collect(),
+            // only the first member of a union can be initialized without designators
+            DataType::Union { properties, .. } => properties
+                .into_iter()
+                .take(1)
+                .map(|property| property.var_type)
+                .collect(),
+            scalar => vec![scalar],
+        };
+        if values.len() > element_types.len() {
+            self.warning(
+                format!("excess elements in initializer of '{target}'"),
+                debug_data,
+            );
+        }
+        for (i, value) in values.iter().enumerate() {
+            match element_types.get(i) {
+                Some(element_type) => self.initializer(element_type, value, debug_data),
+                None => {
+                    self.value(value);
+                }
+            }
+        }
+    }
+
+    fn member(
+        &mut self,
+        structure: &DataType,
+        right: &Expression,
+        debug_data: &DebugData,
+    ) -> Option<DataType> {
+        let Expression::Identifier(name, _) = right else {
+            return None;
+        };
+        let (DataType::Struct { properties, .. } | DataType::Union { properties, .. }) =
+            self.resolve(structure)
+        else {
+            self.error(
+                format!("member reference base type '{structure}' is not a structure"),
+                debug_data,
+            );
+            return None;
+        };
+        match properties
+            .into_iter()
+            .find(|property| &property.var_name == name)
+        {
+            Some(property) => {
+                self.table
+                    .types
+                    .insert(right.id(), property.var_type.to_owned());
+                Some(property.var_type)
+            }
+            None => {
+                self.error(
+                    format!("no member named '{name}' in '{structure}'"),
+                    debug_data,
+                );
+                None
+            }
+        }
+    }
+
+    fn binary(
+        &mut self,
+        operator: TokenKind,
+        (left_expression, left): (&Expression, &DataType),
+        (right_expression, right): (&Expression, &DataType),
+        debug_data: &DebugData,
+    ) -> Option<DataType> {
+        let (resolved_left, resolved_right) = (self.resolve(left), self.resolve(right));
+        let int = primitive("int", false);
+        let output = match (operator, &resolved_left, &resolved_right) {
+            (TokenKind::Star | TokenKind::Slash, l, r) if is_arithmetic(l) && is_arithmetic(r) => {
+                Some(usual_arithmetic_conversion(l, r))
+            }
+            (
+                TokenKind::Percent
+                | TokenKind::Reference
+                | TokenKind::BitwiseOr
+                | TokenKind::BitwiseXor,
+                l,
+                r,
+            ) if is_integer(l) && is_integer(r) => Some(usual_arithmetic_conversion(l, r)),
+            (TokenKind::BitwiseShiftLeft | TokenKind::BitwiseShiftRight, l, r)
+                if is_integer(l) && is_integer(r) =>
+            {
+                Some(promote(l))
+            }
+            (TokenKind::Plus | TokenKind::Minus, l, r) if is_arithmetic(l) && is_arithmetic(r) => {
+                Some(usual_arithmetic_conversion(l, r))
+            }
+            (TokenKind::Plus | TokenKind::Minus, DataType::Pointer(inside), r) if is_integer(r) => {
+                self.pointer_arithmetic(inside, left, debug_data);
+                Some(left.to_owned())
+            }
+            (TokenKind::Plus, l, DataType::Pointer(inside)) if is_integer(l) => {
+                self.pointer_arithmetic(inside, right, debug_data);
+                Some(right.to_owned())
+            }
+            (TokenKind::Minus, DataType::Pointer(l), DataType::Pointer(r)) => {
+                if !self.compatible(l, r) {
+                    self.error(
+                        format!("'{left}' and '{right}' are not pointers to compatible types"),
+                        debug_data,
+                    );
+                }
+                self.pointer_arithmetic(l, left, debug_data);
+                // ptrdiff_t
+                Some(primitive("long", false))
+            }
+            (
+                TokenKind::Equals
+                | TokenKind::NotEquals
+                | TokenKind::Less
+                | TokenKind::LessEquals
+                | TokenKind::Greater
+                | TokenKind::GreaterEquals,
+                l,
+                r,
+            ) => {
+                match (l, r) {
+                    (l, r) if is_arithmetic(l) && is_arithmetic(r) => {}
+                    (DataType::Pointer(l), DataType::Pointer(r)) => {
+                        let (l, r) = (self.resolve(l), self.resolve(r));
+                        if !is_void(&l) && !is_void(&r) && !self.compatible(&l, &r) {
+                            self.warning(
+                                format!(
+                                    "comparison of distinct pointer types ('{left}' and '{right}')"
+                                ),
+                                debug_data,
+                            );
+                        }
+                    }
+                    (DataType::Pointer(_), _) if is_null_pointer_constant(right_expression) => {}
+                    (_, DataType::Pointer(_)) if is_null_pointer_constant(left_expression) => {}
+                    (DataType::Pointer(_), other) | (other, DataType::Pointer(_))
+                        if is_integer(other) =>
+                    {
+                        self.warning(
+                            format!(
+                                "comparison between pointer and integer ('{left}' and '{right}')"
+                            ),
+                            debug_data,
+                        );
+                    }
+                    _ => return self.invalid_operands(left, right, debug_data),
+                }
+                Some(int)
+            }
+            (TokenKind::And | TokenKind::Or, l, r) if is_scalar(l) && is_scalar(r) => Some(int),
+            _ => None,
+        };
+        if output.is_none() {
+            return self.invalid_operands(left, right, debug_data);
+        }
+        output
+    }
+
+    fn invalid_operands(
+        &mut self,
+        left: &DataType,
+        right: &DataType,
+        debug_data: &DebugData,
+    ) -> Option<DataType> {
+        self.error(
+            format!("invalid operands to binary expression ('{left}' and '{right}')"),
+            debug_data,
+        );
+        None
+    }
+
+    /// pointer arithmetic needs to know the size of the element
+    fn pointer_arithmetic(
+        &mut self,
+        inside: &DataType,
+        pointer: &DataType,
+        debug_data: &DebugData,
+    ) {
+        match self.resolve(inside) {
+            inside if is_void(&inside) => self.error(
+                format!("arithmetic on a pointer to void ('{pointer}')"),
+                debug_data,
+            ),
+            DataType::Function { .. } => self.error(
+                format!("arithmetic on a pointer to a function ('{pointer}')"),
+                debug_data,
+            ),
+            _ => {}
+        }
+    }
+
+    fn call(
+        &mut self,
+        left: &Expression,
+        values: &[Expression],
+        debug_data: &DebugData,
+    ) -> Option<DataType> {
+        if let Expression::Identifier(name, _) = left {
+            match name.as_str() {
+                "sizeof" | "_Alignof" => return self.size_of(name, values, debug_data),
+                "_Static_assert" => {
+                    for value in values {
+                        self.value(value);
+                    }
+                    self.static_assert(values, debug_data);
+                    return Some(primitive("void", false));
+                }
+                _ => {}
+            }
+        }
+
+        // the scope pass already warned about the implicit declaration, those return an int
+        if let Expression::Identifier(..) = left
+            && self.symbols.resolution(left).is_none()
+        {
+            for value in values {
+                self.value(value);
+            }
+            return Some(primitive("int", false));
+        }
+
+        let function = self.value(left)?;
+        let (output, parameters, variadic) = match self.resolve(&function) {
+            DataType::Pointer(inside) => match self.resolve(&inside) {
+                DataType::Function {
+                    output,
+                    parameters,
+                    variadic,
+                } => (output, parameters, variadic),
+                _ => {
+                    return self.not_callable(&function, values, debug_data);
+                }
+            },
+            _ => return self.not_callable(&function, values, debug_data),
+        };
+
+        // arguments that a `...` takes don't have a parameter to be checked against
+        if values.len() < parameters.len() || (values.len() > parameters.len() && !variadic) {
+            let amount = if values.len() > parameters.len() {
+                "many"
+            } else {
+                "few"
+            };
+            self.error(
+                format!(
+                    "too {amount} arguments to function call, expected {}, have {}",
+                    parameters.len(),
+                    values.len()
+                ),
+                debug_data,
+            );
+        }
+        for (i, value) in values.iter().enumerate() {
+            let Some(value_type) = self.value(value) else {
+                continue;
+            };
+            if let Some(parameter) = parameters.get(i) {
+                self.assign(
+                    parameter,
+                    value,
+                    &value_type,
+                    &format!("passing argument {} to parameter of type", i + 1),
+                    debug_data,
+                );
+            }
+        }
+        Some(*output)
+    }
+
+    /// `_Static_assert(condition, "message")`
+    fn static_assert(&mut self, values: &[Expression], debug_data: &DebugData) {
+        let Some(condition) = values.first() else {
+            self.error(
+                "'_Static_assert' expects a condition".to_owned(),
+                debug_data,
+            );
+            return;
+        };
+        let mut evaluator = Evaluator::new(self.symbols, self.target);
+        let result = evaluator.evaluate(condition);
+        self.table.diagnostics.extend(evaluator.warnings);
+        match result {
+            Ok(constant) if constant.value == 0 => {
+                let message = match values.get(1) {
+                    Some(Expression::String(message, _)) => format!(": \"{message}\""),
+                    _ => String::new(),
+                };
+                self.error(format!("static assertion failed{message}"), debug_data);
+            }
+            Ok(_) => {}
+            Err(mut diagnostic) => {
+                diagnostic.message = format!(
+                    "static assertion condition is not an integer constant expression: {}",
+                    diagnostic.message
+                );
+                self.table.diagnostics.push(diagnostic);
+            }
+        }
+    }
+
+    fn not_callable(
+        &mut self,
+        function: &DataType,
+        values: &[Expression],
+        debug_data: &DebugData,
+    ) -> Option<DataType> {
+        for value in values {
+            self.value(value);
+        }
+        self.error(
+            format!("called object type '{function}' is not a function or function pointer"),
+            debug_data,
+        );
+        None
+    }
+
+    /// `sizeof(Vec)` or `sizeof(value)`, the value isn't decayed. `_Alignof` works the same way.
+    fn size_of(
+        &mut self,
+        operator: &str,
+        values: &[Expression],
+        debug_data: &DebugData,
+    ) -> Option<DataType> {
+        let [value] = values else {
+            self.error(
+                format!("'{operator}' takes 1 operand, found: {}", values.len()),
+                debug_data,
+            );
+            return None;
+        };
+        let data_type = match value {
+            Expression::DataTypeAccess { data_type, .. }
+                if self.symbols.resolution(value).is_none() =>
+            {
+                self.table.types.insert(value.id(), data_type.to_owned());
+                Some(data_type.to_owned())
+            }
+            _ => self.expression(value),
+        };
+        if let Some(data_type) = data_type {
+            let resolved = self.resolve(&data_type);
+            if is_void(&resolved) || matches!(resolved, DataType::Function { .. }) {
+                self.error(
+                    format!("invalid application of '{operator}' to type '{data_type}'"),
+                    debug_data,
+                );
+            }
+        }
+        // size_t
+        Some(primitive("long", true))
+    }
+
+    fn parameters(&mut self, properties: &[Property], debug_data: &DebugData) {
+        for property in properties {
+            if is_void(&self.resolve(&property.var_type)) {
+                self.error(
+                    format!(
+                        "parameter '{}' has incomplete type '{}'",
+                        property.var_name, property.var_type
+                    ),
+                    debug_data,
+                );
+            }
+        }
+    }
+
+    fn compute(&mut self, expression: &Expression) -> Option<DataType> {
+        let void = primitive("void", false);
+        match expression {
+            Expression::Skip
+            | Expression::Break { .. }
+            | Expression::CompilerData(..)
+            | Expression::Typedef { .. } => Some(void),
+            Expression::Boolean(..) => Some(primitive("bool", false)),
+            // character constants are ints in C
+            Expression::Character(value, debug_data) => {
+                if let Err(err) = character_value(value) {
+                    self.error(err.to_string(), debug_data);
+                }
+                Some(primitive("int", false))
+            }
+            Expression::Number(value, literal, _) => Some(literal_type(*value, *literal)),
+            Expression::String(value, _) => Some(DataType::Array {
+                length: string_length(value),
+                inside: Box::new(primitive("char", false)),
+            }),
+            Expression::Identifier(..) => {
+                let symbol = self.symbols.resolution(expression)?;
+                Some(match symbol.kind {
+                    SymbolKind::EnumConstant => primitive("int", false),
+                    _ => symbol.data_type.to_owned(),
+                })
+            }
+            Expression::DataTypeAccess {
+                data_type,
+                debug_data,
+            } => match self.symbols.resolution(expression) {
+                // a variable named the same as a type
+                Some(symbol) => Some(symbol.data_type.to_owned()),
+                None => {
+                    self.error(
+                        format!("unexpected type name '{data_type}': expected expression"),
+                        debug_data,
+                    );
+                    None
+                }
+            },
+            Expression::VariableDeclaration {
+                var_type,
+                name,
+                debug_data,
+            } => {
+                if is_void(&self.resolve(var_type)) {
+                    self.error(
+                        format!("variable '{name}' has incomplete type '{var_type}'"),
+                        debug_data,
+                    );
+                }
+                Some(var_type.to_owned())
+            }
+            Expression::Increment { target, debug_data }
+            | Expression::Decrement { target, debug_data } => {
+                let data_type = self.expression(target)?;
+                if !self.require_modifiable(target, &data_type) {
+                    return Some(data_type);
+                }
+                match self.resolve(&data_type) {
+                    DataType::Pointer(inside) => {
+                        self.pointer_arithmetic(&inside, &data_type, debug_data)
+                    }
+                    resolved if is_arithmetic(&resolved) => {}
+                    _ => {
+                        let action = match expression {
+                            Expression::Increment { .. } => "increment",
+                            _ => "decrement",
+                        };
+                        self.error(
+                            format!("cannot {action} value of type '{data_type}'"),
+                            debug_data,
+                        );
+                    }
+                }
+                Some(data_type)
+            }
+            Expression::DataStructureInitialization { values, .. } => {
+                // only valid as an initializer, which doesn't go through here
+                for value in values {
+                    self.value(value);
+                }
+                None
+            }
+            Expression::TypeConversion {
+                value,
+                data_type,
+                debug_data,
+            } => {
+                let value_type = self.value(value)?;
+                let (resolved, resolved_value) =
+                    (self.resolve(data_type), self.resolve(&value_type));
+                if is_void(&resolved) {
+                } else if !is_scalar(&resolved) {
+                    self.error(
+                        format!(
+                            "used type '{data_type}' where arithmetic or pointer type is required"
+                        ),
+                        debug_data,
+                    );
+                } else if !is_scalar(&resolved_value) {
+                    self.error(
+                        format!(
+                            "operand of type '{value_type}' where arithmetic or pointer type is required"
+                        ),
+                        debug_data,
+                    );
+                } else if (is_floating(&resolved) && matches!(resolved_value, DataType::Pointer(_)))
+                    || (is_floating(&resolved_value) && matches!(resolved, DataType::Pointer(_)))
+                {
+                    self.error(
+                        format!("cannot cast '{value_type}' to '{data_type}'"),
+                        debug_data,
+                    );
+                }
+                Some(data_type.to_owned())
+            }
+            Expression::Arrow {
+                left,
+                right,
+                debug_data,
+            } => {
+                let left_type = self.value(left)?;
+                match self.resolve(&left_type) {
+                    DataType::Pointer(inside) => self.member(&inside, right, debug_data),
+                    DataType::Struct { .. } | DataType::Union { .. } => {
+                        self.error(
+                            format!(
+                                "member reference type '{left_type}' is not a pointer; did you mean to use '.'?"
+                            ),
+                            debug_data,
+                        );
+                        self.member(&left_type, right, debug_data)
+                    }
+                    _ => {
+                        self.error(
+                            format!(
+                                "member reference base type '{left_type}' is not a pointer to a structure"
+                            ),
+                            debug_data,
+                        );
+                        None
+                    }
+                }
+            }
+            Expression::MemberExpr {
+                left,
+                right,
+                debug_data,
+            } => {
+                let left_type = self.expression(left)?;
+                match self.resolve(&left_type) {
+                    DataType::Pointer(inside)
+                        if matches!(
+                            self.resolve(&inside),
+                            DataType::Struct { .. } | DataType::Union { .. }
+                        ) =>
+                    {
+                        self.error(
+                            format!(
+                                "member reference type '{left_type}' is a pointer; did you mean to use '->'?"
+                            ),
+                            debug_data,
+                        );
+                        self.member(&inside, right, debug_data)
+                    }
+                    _ => self.member(&left_type, right, debug_data),
+                }
+            }
+            Expression::Dereference { value, debug_data } => {
+                let value_type = self.value(value)?;
+                match self.resolve(&value_type) {
+                    DataType::Pointer(inside) => {
+                        if is_void(&self.resolve(&inside)) {
+                            self.error(
+                                format!("indirection of a pointer to void ('{value_type}')"),
+                                debug_data,
+                            );
+                            return None;
+                        }
+                        Some(*inside)
+                    }
+                    _ => {
+                        self.error(
+                            format!(
+                                "indirection requires pointer operand ('{value_type}' invalid)"
+                            ),
+                            debug_data,
+                        );
+                        None
+                    }
+                }
+            }
+            Expression::AccessReference { value, debug_data } => {
+                let value_type = self.expression(value)?;
+                if !self.is_lvalue(value)
+                    && !matches!(self.resolve(&value_type), DataType::Function { .. })
+                {
+                    self.error(
+                        format!("cannot take the address of an rvalue of type '{value_type}'"),
+                        debug_data,
+                    );
+                }
+                Some(DataType::Pointer(Box::new(value_type)))
+            }
+            Expression::Prefix {
+                prefix,
+                value,
+                debug_data,
+            } => {
+                let value_type = self.value(value)?;
+                let resolved = self.resolve(&value_type);
+                let output = match prefix.kind {
+                    TokenKind::Plus | TokenKind::Minus if is_arithmetic(&resolved) => {
+                        Some(promote(&resolved))
+                    }
+                    TokenKind::BitwiseNot if is_integer(&resolved) => Some(promote(&resolved)),
+                    TokenKind::Not if is_scalar(&resolved) => Some(primitive("int", false)),
+                    _ => None,
+                };
+                if output.is_none() {
+                    self.error(
+                        format!(
+                            "invalid argument type '{value_type}' to unary expression '{}'",
+                            prefix.kind.to_str()
+                        ),
+                        debug_data,
+                    );
+                }
+                output
+            }
+            Expression::Assignment {
+                target,
+                operator,
+                value,
+                debug_data,
+            } => {
+                if let Expression::VariableDeclaration { .. } = target.as_ref() {
+                    let target_type = self.expression(target)?;
+                    self.initializer(&target_type, value, debug_data);
+                    return Some(target_type);
+                }
+
+                let target_type = self.expression(target);
+                let value_type = self.value(value);
+                let (target_type, value_type) = (target_type?, value_type?);
+                if !self.require_modifiable(target, &target_type) {
+                    return Some(target_type);
+                }
+                let value_type = match compound_operator(operator.kind) {
+                    Some(operator) => self.binary(
+                        operator,
+                        (target, &target_type),
+                        (value, &value_type),
+                        debug_data,
+                    )?,
+                    None => value_type,
+                };
+                self.assign(&target_type, value, &value_type, "assigning to", debug_data);
+                Some(target_type)
+            }
+            Expression::Grouping { value, .. } | Expression::Static { value, .. } => {
+                self.expression(value)
+            }
+            Expression::Struct { functions, .. } => {
+                self.block(functions);
+                Some(void)
+            }
+            Expression::NewCodeBlock { inside, .. } => {
+                self.block(inside);
+                Some(void)
+            }
+            Expression::Binary {
+                left,
+                operator,
+                right,
+                debug_data,
+            } => {
+                let left_type = self.value(left);
+                let right_type = self.value(right);
+                self.binary(
+                    operator.kind,
+                    (left, &left_type?),
+                    (right, &right_type?),
+                    debug_data,
+                )
+            }
+            Expression::Function {
+                name,
+                properties,
+                output,
+                inside,
+                debug_data,
+            } => {
+                self.parameters(properties, debug_data);
+                let outer = self.function.replace((name.to_owned(), output.to_owned()));
+                self.block(inside);
+                self.function = outer;
+
+                Some(DataType::Function {
+                    output: Box::new(output.to_owned()),
+                    parameters: properties
+                        .iter()
+                        .map(|property| property.var_type.to_owned())
+                        .collect(),
+                    variadic: false,
+                })
+            }
+            Expression::FunctionDeclaration {
+                properties,
+                output,
+                variadic,
+                debug_data,
+                ..
+            } => {
+                self.parameters(properties, debug_data);
+                Some(DataType::Function {
+                    output: Box::new(output.to_owned()),
+                    parameters: properties
+                        .iter()
+                        .map(|property| property.var_type.to_owned())
+                        .collect(),
+                    variadic: *variadic,
+                })
+            }
+            Expression::Return { value, debug_data } => {
+                let value_type = self.value(value);
+                if let Some((name, output)) = self.function.to_owned()
+                    && let Some(value_type) = value_type
+                {
+                    if is_void(&self.resolve(&output)) {
+                        self.error(
+                            format!("void function '{name}' should not return a value"),
+                            debug_data,
+                        );
+                    } else {
+                        self.assign(&output, value, &value_type, "returning", debug_data);
+                    }
+                }
+                Some(void)
+            }
+            Expression::If {
+                condition,
+                inside,
+                chained_elses,
+                ..
+            } => {
+                self.condition(condition);
+                self.block(inside);
+                self.block(chained_elses);
+                Some(void)
+            }
+            Expression::Else {
+                condition, inside, ..
+            } => {
+                if let Some(condition) = condition {
+                    self.condition(condition);
+                }
+                self.block(inside);
+                Some(void)
+            }
+            Expression::While {
+                condition, inside, ..
+            } => {
+                self.condition(condition);
+                self.block(inside);
+                Some(void)
+            }
+            Expression::For {
+                iterator_init,
+                condition,
+                incr,
+                inside,
+                ..
+            } => {
+                self.expression(iterator_init);
+                self.condition(condition);
+                self.expression(incr);
+                self.block(inside);
+                Some(void)
+            }
+            Expression::AccessArray {
+                left,
+                index,
+                debug_data,
+            } => {
+                let left_type = self.value(left);
+                let index_type = self.value(index);
+                let (left_type, index_type) = (left_type?, index_type?);
+                let (resolved_left, resolved_index) =
+                    (self.resolve(&left_type), self.resolve(&index_type));
+                // `i[array]` is the same as `array[i]`
+                let (pointer, inside, index_type) = match (resolved_left, resolved_index) {
+                    (DataType::Pointer(inside), index) => (&left_type, inside, index),
+                    (index, DataType::Pointer(inside)) => (&index_type, inside, index),
+                    _ => {
+                        self.error(
+                            format!(
+                                "subscripted value is not an array or pointer ('{left_type}' invalid)"
+                            ),
+                            debug_data,
+                        );
+                        return None;
+                    }
+                };
+                if !is_integer(&index_type) {
+                    self.error(
+                        format!("array subscript is not an integer ('{index_type}' invalid)"),
+                        debug_data,
+                    );
+                }
+                self.pointer_arithmetic(&inside, pointer, debug_data);
+                Some(*inside)
+            }
+            Expression::FunctionCall {
+                left,
+                values,
+                debug_data,
+            } => self.call(left, values, debug_data),
+        }
+    }
+}
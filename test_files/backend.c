@@ -0,0 +1,93 @@
+int printf(const char *format, ...);
+
+int data[4] = {1, 2, 3, 4};
+const char *names[3] = {"zero", "one", "two"};
+int *second = data;
+long big = (long)123456 * 1000000;
+char letters[4] = "abc";
+short shorts[3] = {1, 300, 32767};
+unsigned char bytes[2] = {200, 255};
+
+typedef struct {
+    int x;
+    long y;
+    char tag;
+} Point;
+
+Point origin = {3, 4, 'o'};
+
+int many(int a, int b, int c, int d, int e, int f, int g, int h, int i) {
+    return a + 2 * b + 3 * c + 4 * d + 5 * e + 6 * f + 7 * g + 8 * h + 9 * i;
+}
+
+long seven(long a, long b, long c, long d, long e, long f, long g) {
+    return a - b + c - d + e - f + g * 1000;
+}
+
+Point shift(Point p, int by) {
+    p.x += by;
+    p.y -= by;
+    return p;
+}
+
+int counter(void) {
+    static int calls = 10;
+    calls++;
+    return calls;
+}
+
+int fibonacci(int n) {
+    int a = 0;
+    int b = 1;
+    while (n > 0) {
+        int next = a + b;
+        a = b;
+        b = next;
+        n--;
+    };
+    return a;
+}
+
+unsigned int swaps(unsigned int x, unsigned int y, int n) {
+    while (n > 0) {
+        unsigned int t = x;
+        x = y;
+        y = t;
+        n--;
+    };
+    return x * 10 + y;
+}
+
+int main(void) {
+    int i;
+    int total = 0;
+    unsigned int u = 0;
+    int negative = -17;
+    char c = 'A';
+    unsigned char uc = 250;
+    short s = -5;
+    long l = -1;
+    Point moved;
+    u = u - 294967296;
+    for (i = 0; i < 4; i++) {
+        total += data[i];
+    };
+    printf("%d %s %s %d %ld %s\n", total, names[1], names[2], *second, big, letters);
+    printf("%d %d %d %u %u\n", shorts[0], shorts[1], shorts[2], bytes[0], bytes[1]);
+    printf("%d %ld\n", many(1, 2, 3, 4, 5, 6, 7, 8, 9), seven(1, 2, 3, 4, 5, 6, 7));
+    printf("%u %u %u %d\n", u / 3, u % 7, u >> 3, negative >> 2);
+    printf("%d %d %d %d\n", negative / 5, negative % 5, negative * negative, -negative);
+    c = c + 2;
+    uc = uc + 10;
+    s = s * 3;
+    printf("%c %d %d %ld %lu\n", c, uc, s, -l << 40, (unsigned long)l >> 60);
+    moved = shift(origin, 5);
+    printf("%d %ld %c %d %ld\n", moved.x, moved.y, moved.tag, origin.x, origin.y);
+    i = counter();
+    i = i * 100 + counter();
+    printf("%d\n", i);
+    printf("%d %d %d %d\n", u > 5, negative < 0, uc < c, (unsigned int)negative > 5);
+    printf("%d %d\n", ~5, !0);
+    printf("%d %u %u\n", fibonacci(40), swaps(1, 2, 3), swaps(1, 2, 4));
+    return total;
+}
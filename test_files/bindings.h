@@ -0,0 +1,48 @@
+#define VERSION 3
+#define NAME "rip\n"
+#define MASK (1 << 4) - 1
+#define BIG 4000000000u
+#define LIMIT VERSION * 10
+#define EMPTY
+
+typedef enum { MODE_READ, MODE_WRITE = 4 } Mode;
+typedef enum { ERROR_NONE = 0, ERROR_BAD = -1 } Error;
+
+typedef unsigned long size;
+
+typedef struct {
+  char tag;
+  _Alignas(16) int aligned;
+  const char *name;
+  Mode mode;
+  unsigned char bytes[3];
+} Record;
+
+typedef union {
+  int word;
+  char bytes[4];
+} Word;
+
+typedef struct {
+  Word word;
+  Record *records;
+  size count;
+} Table;
+
+#pragma pack(push, 1)
+typedef struct {
+  char kind;
+  int length;
+} Packed;
+#pragma pack(pop)
+
+typedef struct {
+  int type;
+  double self;
+} Keywords;
+
+int printf(const char *format, ...);
+size table_count(const Table *table);
+void table_clear(Table *table, int values[4]);
+Error record_write(Record *, const char *, size);
+int version(void);
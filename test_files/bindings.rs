@@ -0,0 +1,114 @@
+// Rust bindings for: test_files/bindings.h
+// layouts were computed for the target: x86_64-sysv
+#![allow(non_camel_case_types, non_snake_case, non_upper_case_globals, dead_code)]
+
+pub const VERSION: ::core::ffi::c_int = 3;
+pub const NAME: &[u8; 5] = b"rip\n\x00";
+pub const MASK: ::core::ffi::c_int = 15;
+pub const BIG: ::core::ffi::c_uint = 4000000000;
+pub const LIMIT: ::core::ffi::c_int = 30;
+
+pub type Mode = ::core::ffi::c_uint;
+pub const MODE_READ: Mode = 0;
+pub const MODE_WRITE: Mode = 4;
+
+pub type Error = ::core::ffi::c_int;
+pub const ERROR_NONE: Error = 0;
+pub const ERROR_BAD: Error = -1;
+
+pub type size = ::core::ffi::c_ulong;
+
+#[repr(C, align(16))]
+#[derive(Debug, Copy, Clone)]
+pub struct Record {
+    pub tag: ::core::ffi::c_char,
+    pub __padding_1: [u8; 12],
+    pub aligned: ::core::ffi::c_int,
+    pub name: *const ::core::ffi::c_char,
+    pub mode: Mode,
+    pub bytes: [::core::ffi::c_uchar; 3],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub union Word {
+    pub word: ::core::ffi::c_int,
+    pub bytes: [::core::ffi::c_char; 4],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct Table {
+    pub word: Word,
+    pub records: *mut Record,
+    pub count: size,
+}
+
+#[repr(C, packed(1))]
+#[derive(Debug, Copy, Clone)]
+pub struct Packed {
+    pub kind: ::core::ffi::c_char,
+    pub length: ::core::ffi::c_int,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Keywords {
+    pub r#type: ::core::ffi::c_int,
+    pub self_: f64,
+}
+
+unsafe extern "C" {
+    pub fn printf(format: *const ::core::ffi::c_char, ...) -> ::core::ffi::c_int;
+    pub fn table_count(table: *const Table) -> size;
+    pub fn table_clear(table: *mut Table, values: *mut ::core::ffi::c_int);
+    pub fn record_write(arg0: *mut Record, arg1: *const ::core::ffi::c_char, arg2: size) -> Error;
+    pub fn version() -> ::core::ffi::c_int;
+}
+
+#[cfg(test)]
+mod layout_tests {
+    use super::*;
+
+    #[test]
+    fn layout_Record() {
+        assert_eq!(::core::mem::size_of::<Record>(), 48);
+        assert_eq!(::core::mem::align_of::<Record>(), 16);
+        assert_eq!(::core::mem::offset_of!(Record, tag), 0);
+        assert_eq!(::core::mem::offset_of!(Record, aligned), 16);
+        assert_eq!(::core::mem::offset_of!(Record, name), 24);
+        assert_eq!(::core::mem::offset_of!(Record, mode), 32);
+        assert_eq!(::core::mem::offset_of!(Record, bytes), 36);
+    }
+
+    #[test]
+    fn layout_Word() {
+        assert_eq!(::core::mem::size_of::<Word>(), 4);
+        assert_eq!(::core::mem::align_of::<Word>(), 4);
+    }
+
+    #[test]
+    fn layout_Table() {
+        assert_eq!(::core::mem::size_of::<Table>(), 24);
+        assert_eq!(::core::mem::align_of::<Table>(), 8);
+        assert_eq!(::core::mem::offset_of!(Table, word), 0);
+        assert_eq!(::core::mem::offset_of!(Table, records), 8);
+        assert_eq!(::core::mem::offset_of!(Table, count), 16);
+    }
+
+    #[test]
+    fn layout_Packed() {
+        assert_eq!(::core::mem::size_of::<Packed>(), 5);
+        assert_eq!(::core::mem::align_of::<Packed>(), 1);
+        assert_eq!(::core::mem::offset_of!(Packed, kind), 0);
+        assert_eq!(::core::mem::offset_of!(Packed, length), 1);
+    }
+
+    #[test]
+    fn layout_Keywords() {
+        assert_eq!(::core::mem::size_of::<Keywords>(), 16);
+        assert_eq!(::core::mem::align_of::<Keywords>(), 8);
+        assert_eq!(::core::mem::offset_of!(Keywords, r#type), 0);
+        assert_eq!(::core::mem::offset_of!(Keywords, self_), 8);
+    }
+}
@@ -0,0 +1,81 @@
+int printf(char *format, ...);
+void *malloc(unsigned long size);
+void free(void *pointer);
+unsigned long strlen(char *string);
+
+typedef struct {
+    int value;
+    void *next;
+} Node;
+
+typedef struct {
+    char tag;
+    long weight;
+    int scores[3];
+} Record;
+
+char *names[3] = {"zero", "one", "two"};
+int counter = 10;
+
+int gcd(int a, int b) {
+    while (b != 0) {
+        int t = a % b;
+        a = b;
+        b = t;
+    };
+    return a;
+}
+
+int fibonacci(int n) {
+    if (n < 2) {
+        return n;
+    };
+    return fibonacci(n - 1) + fibonacci(n - 2);
+}
+
+Node *push(Node *head, int value) {
+    Node *node = malloc(sizeof(Node));
+    node->value = value;
+    node->next = head;
+    return node;
+}
+
+Record heavier(Record record, long extra) {
+    record.weight = record.weight + extra;
+    record.scores[1] = record.scores[0] * 2;
+    return record;
+}
+
+int main() {
+    Node *list = 0;
+    int i;
+    for (i = 0; i < 5; i++) {
+        list = push(list, i * i);
+    };
+    int sum = 0;
+    while (list) {
+        Node *next = list->next;
+        sum = sum + list->value;
+        free(list);
+        list = next;
+    };
+    printf("sum %d gcd %d fib %d\n", sum, gcd(1071, 462), fibonacci(15));
+
+    Record record;
+    record.tag = 'r';
+    record.weight = (long)123456 * 1000000;
+    record.scores[0] = 21;
+    record.scores[2] = -7;
+    Record copy = heavier(record, 5);
+    printf("%c %ld %d %d %d\n", copy.tag, copy.weight, copy.scores[0], copy.scores[1], copy.scores[2]);
+
+    for (i = 0; i < 3; i++) {
+        printf("%-5s|%3lu|\n", names[i], strlen(names[i]));
+    };
+    unsigned int big = 4000000000u;
+    unsigned char small = 200;
+    int negative = -1;
+    printf("%u %d %x %d\n", big / 3, big > 5, (unsigned int)negative >> 28, (char)small);
+    counter += sum;
+    return counter % 256;
+}
@@ -0,0 +1,45 @@
+#define VERSION 3
+#define FEATURE_X
+
+#if defined(FEATURE_X) && VERSION >= 2
+int feature_x = 1;
+#elif VERSION == 1
+int feature_x = 2;
+#else
+int feature_x = 3;
+#endif
+
+#ifdef PLATFORM_WINDOWS
+int windows = 1;
+#ifdef NESTED
+#error this is never reached
+#endif
+#elif __STDC_VERSION__ >= 201112L
+int modern = 1;
+#endif
+
+#ifndef DEBUG
+int release = 1;
+#else
+int debug = 1;
+#endif
+
+#if (1 ? 2 : 1 / 0) == 2 && !0 && ~0 == -1 && (0xFFu | 1) == 255 && (1 << 4) == 16
+int arithmetic = 1;
+#endif
+
+#if -1 < 0u
+#error unsigned conversion is broken
+#endif
+
+#if UNDEFINED_NAME
+#warning this is skipped
+#else
+#warning undefined names are 0
+#endif
+
+#if 0xFFFFFFFFFFFFFFFFu * 0xFFFFFFFFFFFFFFFFu == 1 && 0xFFFFFFFFFFFFFFFFu + 1 == 0 && (0x8000000000000000u << 1) == 0
+int wrapped = 1;
+#else
+#error unsigned arithmetic does not wrap around
+#endif
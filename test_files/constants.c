@@ -0,0 +1,24 @@
+#define N 8
+
+typedef enum { FLAG_A = 1 << 0, FLAG_B = 1 << 3, FLAG_AB = FLAG_A | FLAG_B } Flags;
+
+typedef struct {
+  char tag;
+  int value;
+  long big;
+} Entry;
+
+int buffer[N * 2];
+char letters['z' - 'a' + 1];
+int entries[sizeof(Entry) / sizeof(int)];
+int masks[(unsigned char)300];
+int wrapped[((unsigned int)-1 >> 28) + FLAG_AB];
+
+int main() {
+  _Static_assert(sizeof(Entry) == 16, "Entry has padding");
+  _Static_assert(FLAG_AB == 9, "flags");
+  _Static_assert(sizeof(buffer) == 64, "buffer");
+  _Static_assert(2147483647 + 1 < 0, "signed overflow wraps");
+  _Static_assert(N > 100, "N is too small");
+  return 0;
+}
@@ -0,0 +1,61 @@
+int printf(const char *format, ...);
+int scanf(const char *format, ...);
+
+int pick(int flag) {
+  int value;
+  if (flag) {
+    value = 1;
+  };
+  return value;
+}
+
+int never(int flag) {
+  int total;
+  total += flag;
+  return total;
+}
+
+int stores(int x) {
+  int result = x * 2;
+  result = x + 1;
+  int unused;
+  int written = 3;
+  written = 4;
+  x++;
+  return result;
+}
+
+int loops(int n) {
+  int sum = 0;
+  for (int i = 0; i < n; i++) {
+    int step;
+    if (i == 0) {
+      step = 1;
+    };
+    sum = sum + step;
+  };
+  return sum;
+}
+
+int fine(int n) {
+  int read;
+  scanf("%d", &read);
+  static int calls;
+  calls++;
+  int size = sizeof(n);
+  int arrays[4];
+  arrays[0] = n;
+  return read + size + arrays[0];
+}
+
+int main() {
+  printf("%d", pick(1) + never(2) + stores(3) + loops(4) + fine(5));
+  return 0;
+}
+
+#define HALF(value) (value / divisor)
+
+int through_macro(int value) {
+  int divisor;
+  return HALF(value);
+}
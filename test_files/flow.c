@@ -0,0 +1,51 @@
+int printf(const char *format, ...);
+void exit(int status);
+
+int sign(int x) {
+  if (x < 0) {
+    return -1;
+  };
+  if (x > 0) {
+    return 1;
+  };
+}
+
+int first(int x) {
+  return x;
+  x = x + 1;
+  printf("%d", x);
+}
+
+void spin(int n) {
+  int i = 0;
+  while (i < n) {
+    printf("%d", i);
+  };
+  while (1) {
+    printf("forever");
+  };
+  printf("done");
+}
+
+void counted(int n) {
+  for (int i = 0; i < n; i++) {
+    printf("%d", i);
+  };
+  int j = 0;
+  while (j < n) {
+    if (j == 3) {
+      break;
+    };
+  };
+  while (n) {
+    exit(1);
+  };
+}
+
+int main() {
+  sign(1);
+  first(2);
+  spin(3);
+  counted(4);
+  return 0;
+}
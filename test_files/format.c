@@ -0,0 +1,46 @@
+#include <stdio.h>
+#define SQUARE(x) \
+    ((x) * (x))
+int printf(char*format,...);
+void*malloc(unsigned long size);
+
+// a node of the list
+typedef struct{
+int value; // payload
+void*next;
+  unsigned long weight;
+char name[16];
+}Node;
+typedef enum{RED,GREEN=4,DARK_BLUE=GREEN*2,BLUE}Color;
+
+
+
+typedef unsigned long Size;
+static int table[4]={1,2,3,4};
+
+int weighted_sum(int first_value,int second_value,int third_value,int fourth_value){
+return first_value*1+second_value*2+third_value*3+fourth_value*4+SQUARE(first_value)-table[0];
+}
+
+Node*make(int value){
+Node*node=(Node*)malloc(sizeof(Node));
+  node->value=-value;node->next=0;
+    node->weight=(unsigned long)value*1000;
+return node;}
+
+int main(){
+    int i=0;int total=0;
+        // count up to ten
+    while(i<10){total+=i;i++;
+    };
+    if(total>40&&total<100||total==0){printf("%d\n",total);}else if(!total){return 1;}else{
+        // unreachable with these numbers
+        total=~total;
+    };
+    Node*node=make(weighted_sum(total,i,table[1]+table[2],weighted_sum(1,2,3,4)));
+    printf("value %d weight %lu sum %d\n",node->value,node->weight,weighted_sum(1,2,3,4));
+    Color color=DARK_BLUE;
+    for(int j=0;j<3;j++){color--;}
+    int bits=1;bits<<=4;bits>>=1;bits%=5;bits&=7;bits|=8;bits^=2;
+    return color;
+}
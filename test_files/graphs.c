@@ -0,0 +1,39 @@
+int printf(char *format, ...);
+
+int square(int x) {
+    return x * x;
+}
+
+int classify(int n) {
+    if (n < 0) {
+        return -1;
+    } else if (n == 0) {
+        return 0;
+    } else {
+        n = square(n);
+    };
+    return n;
+}
+
+int sum_until(int limit) {
+    int total = 0;
+    int i;
+    for (i = 0; i < 100; i++) {
+        if (total > limit) {
+            break;
+        };
+        total = total + square(i);
+    };
+    while (1) {
+        total = total - 1;
+        if (total < limit) {
+            return total;
+        };
+    };
+}
+
+int main() {
+    int value = sum_until(50);
+    printf("%d %d\n", value, classify(value));
+    return 0;
+}
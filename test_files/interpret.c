@@ -0,0 +1,77 @@
+typedef struct {
+  int x;
+  int y;
+} Vec;
+
+typedef struct {
+  Vec *items;
+  int length;
+} List;
+
+static int counter = 0;
+static int primes[5] = {2, 3, 5, 7, 11};
+
+int fib(int n) {
+  if (n < 2) {
+    return n;
+  }
+  return fib(n - 1) + fib(n - 2);
+}
+
+int sum(int *values, int length) {
+  int total = 0;
+  for (int i = 0; i < length; i++) {
+    total += values[i];
+  }
+  return total;
+}
+
+Vec add(Vec a, Vec b) {
+  Vec result;
+  result.x = a.x + b.x;
+  result.y = a.y + b.y;
+  return result;
+}
+
+void count() { counter++; }
+
+int main() {
+  printf("fib(15) = %d\n", fib(15));
+  printf("sum of primes = %d\n", sum(primes, 5));
+
+  Vec a = {1, 2};
+  Vec b = {10, 20};
+  Vec c = add(a, b);
+  printf("c = {%d, %d}, add(a, a).y = %d\n", c.x, c.y, add(a, a).y);
+
+  List list;
+  list.length = 4;
+  list.items = (Vec *)malloc(list.length * sizeof(Vec));
+  for (int i = 0; i < list.length; i++) {
+    list.items[i].x = i;
+    (list.items + i)->y = i * i;
+  }
+  Vec *last = &list.items[list.length - 1];
+  printf("last = {%d, %d}\n", last->x, last->y);
+  free(list.items);
+
+  char name[8] = "rip";
+  printf("'%s' has %lu characters, first is '%c'\n", name, strlen(name), name[0]);
+  printf("[%5d] [%-5d] [%05d] [%x] [%#X] [%%]\n", 42, 42, 42, 255, 255);
+
+  int i = 0;
+  while (1) {
+    count();
+    i++;
+    if (i == 10) {
+      break;
+    };
+  }
+  printf("counter = %d\n", counter);
+
+  unsigned int big = 4294967295;
+  big++;
+  printf("unsigned wraps to %u\n", big);
+
+  return 0;
+}
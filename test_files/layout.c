@@ -0,0 +1,41 @@
+typedef struct {
+  char tag;
+  int value;
+  double ratio;
+  char flags[3];
+  long count;
+} Entry;
+
+typedef union {
+  char bytes[6];
+  int word;
+  short half;
+} Overlay;
+
+#pragma pack(push, 1)
+typedef struct {
+  char kind;
+  int length;
+  short crc;
+} PackedHeader;
+#pragma pack(pop)
+
+typedef struct {
+  char kind;
+  _Alignas(16) int aligned;
+  _Alignas(double) char wide;
+  Entry *next;
+} Aligned;
+
+typedef struct {
+  Entry entries[2];
+  Overlay overlay;
+} Nested;
+
+int checks[4];
+
+int main() {
+  _Static_assert(sizeof(PackedHeader) == 7, "packed");
+  _Static_assert(_Alignof(Aligned) == 16, "aligned");
+  return 0;
+}
@@ -0,0 +1,35 @@
+#define LIMIT 10
+
+typedef int count;
+
+int total = 0;
+
+int scale(int value) {
+  int factor = 3;
+  if (value = factor) {
+    return value * 4;
+  };
+  if ((value = factor * 2)) {
+    return value;
+  };
+  while (value < LIMIT && (total = value)) {
+    value++;
+  };
+  return value * 7; // rip-allow(magic-number)
+}
+
+int blocks(int total) {
+  int sum = 0;
+  for (int i = 0; i < total; i++) {
+    int sum = i;
+    // rip-allow(shadowed-variable, no-such-rule)
+    int total = sum;
+    sum = sum + total;
+  };
+  count count = sum;
+  return count;
+}
+
+int main() {
+  return scale(5) + blocks(6);
+}
@@ -0,0 +1,28 @@
+int printf(char *format, ...);
+
+// suffixes and the base of a literal decide its type, not only its value
+_Static_assert(0u - 1 > 0, "unsigned literals stay unsigned");
+_Static_assert(sizeof(0xFFFFFFFF) == 4, "hex literals can be unsigned int");
+_Static_assert(sizeof(4294967295) == 8, "decimal literals become long");
+_Static_assert(sizeof(1l) == 8 && sizeof(1ul) == 8, "l makes a long");
+_Static_assert(010 == 8 && 0X1F == 31, "a leading 0 is octal, the x can be upper case");
+_Static_assert(sizeof(0x100000000) == 8, "hex literals too big for unsigned int are long");
+_Static_assert(0xFFFFFFFFFFFFFFFFul > 0, "literals can use all 64 bits");
+
+int main() {
+  unsigned int top = 1u << 31;
+  // `++` adds in `int`, so the narrow types wrap instead of overflowing
+  short wide = 32767;
+  char narrow = 127;
+  wide++;
+  narrow++;
+  printf("%d\n", 0u - 1 > 0);
+  printf("%d\n", -1 < 0u);
+  printf("%d\n", 0xFFFFFFFF > 0);
+  printf("%u\n", top);
+  printf("%d\n", 010);
+  printf("%ld\n", 5000000000L);
+  printf("%lu\n", 0xFFFFFFFFFFFFFFFFul);
+  printf("%d %d\n", wide, narrow);
+  return 0;
+}
@@ -0,0 +1,67 @@
+int printf(char *format, ...);
+
+#pragma pack(1)
+typedef struct {
+    char tag;
+    int value;
+    short count;
+} Packed;
+#pragma pack()
+
+typedef union {
+    int number;
+    char bytes[6];
+} Overlay;
+
+typedef struct {
+    char kind;
+    Overlay overlay;
+    Packed packed[2];
+    long total;
+} Record;
+
+Packed first = {'a', 1, 2};
+Record global;
+Packed *second;
+
+Record build(Record record, int scale) {
+    int i;
+    record.total = record.total * scale;
+    for (i = 0; i < 2; i++) {
+        record.packed[i].value = record.packed[i].value * scale;
+    };
+    record.overlay.bytes[0] = 7;
+    return record;
+}
+
+int count(void) {
+    static int calls = 0;
+    calls++;
+    return calls;
+}
+
+int main() {
+    Record local;
+    global.packed[0] = first;
+    global.packed[1].tag = 'b';
+    global.packed[1].value = 3;
+    global.packed[1].count = 4;
+    global.total = 99;
+    global.kind = 'g';
+    second = global.packed;
+    global.overlay.number = 258;
+    local = build(global, 3);
+    Packed *last = &local.packed[1];
+    unsigned char byte = 250;
+    unsigned int big = 4000000000;
+    int shift = -64;
+    printf("%d %d %d %ld\n", local.kind, local.overlay.number, local.packed[0].value, local.total);
+    printf("%d %d %d\n", last->tag, (last - 1)->count, (int)(last - local.packed));
+    printf("%d %d %d\n", second->tag, global.overlay.bytes[1], (int)sizeof(Record));
+    byte = byte + 10;
+    printf("%d %u %d %d\n", byte, big / 3, shift >> 2, !last && count());
+    count();
+    shift = count();
+    printf("%d %d\n", shift, count() || 0);
+    return local.packed[1].value;
+}
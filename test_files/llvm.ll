@@ -0,0 +1,214 @@
+target triple = "x86_64-pc-linux-gnu"
+
+%Packed = type <{ i8, i32, i16 }>
+%Overlay = type { i32, [4 x i8] }
+%Record = type { i8, %Overlay, [2 x %Packed], i64 }
+
+@first = global %Packed <{ i8 97, i32 1, i16 2 }>, align 1
+@global = global %Record zeroinitializer, align 8
+@second = global ptr null, align 8
+@count.calls = internal global i32 0, align 4
+@.str.0 = private unnamed_addr constant [14 x i8] c"%d %d %d %ld\0A\00", align 1
+@.str.1 = private unnamed_addr constant [10 x i8] c"%d %d %d\0A\00", align 1
+@.str.2 = private unnamed_addr constant [10 x i8] c"%d %d %d\0A\00", align 1
+@.str.3 = private unnamed_addr constant [13 x i8] c"%d %u %d %d\0A\00", align 1
+@.str.4 = private unnamed_addr constant [7 x i8] c"%d %d\0A\00", align 1
+
+declare void @llvm.memcpy.p0.p0.i64(ptr, ptr, i64, i1)
+declare i32 @printf(ptr, ...)
+
+define void @build(ptr sret(%Record) %agg.result, ptr byval(%Record) %record, i32 %scale) {
+entry:
+  %scale.addr = alloca i32, align 4
+  %i.addr = alloca i32, align 4
+  store i32 %scale, ptr %scale.addr
+  %.1 = getelementptr inbounds %Record, ptr %record, i32 0, i32 3
+  %.2 = getelementptr inbounds %Record, ptr %record, i32 0, i32 3
+  %.3 = load i64, ptr %.2
+  %.4 = load i32, ptr %scale.addr
+  %.5 = sext i32 %.4 to i64
+  %.6 = mul i64 %.3, %.5
+  store i64 %.6, ptr %.1
+  store i32 0, ptr %i.addr
+  br label %for.cond1
+
+for.cond1:
+  %.7 = load i32, ptr %i.addr
+  %.8 = icmp slt i32 %.7, 2
+  br i1 %.8, label %for.body2, label %for.end4
+
+for.body2:
+  %.9 = getelementptr inbounds %Record, ptr %record, i32 0, i32 2
+  %.10 = load i32, ptr %i.addr
+  %.11 = sext i32 %.10 to i64
+  %.12 = getelementptr inbounds %Packed, ptr %.9, i64 %.11
+  %.13 = getelementptr inbounds %Packed, ptr %.12, i32 0, i32 1
+  %.14 = getelementptr inbounds %Record, ptr %record, i32 0, i32 2
+  %.15 = load i32, ptr %i.addr
+  %.16 = sext i32 %.15 to i64
+  %.17 = getelementptr inbounds %Packed, ptr %.14, i64 %.16
+  %.18 = getelementptr inbounds %Packed, ptr %.17, i32 0, i32 1
+  %.19 = load i32, ptr %.18
+  %.20 = load i32, ptr %scale.addr
+  %.21 = mul i32 %.19, %.20
+  store i32 %.21, ptr %.13
+  br label %for.inc3
+
+for.inc3:
+  %.22 = load i32, ptr %i.addr
+  %.23 = add i32 %.22, 1
+  store i32 %.23, ptr %i.addr
+  br label %for.cond1
+
+for.end4:
+  %.24 = getelementptr inbounds %Record, ptr %record, i32 0, i32 1
+  %.25 = getelementptr inbounds i8, ptr %.24, i64 0
+  store i8 7, ptr %.25
+  call void @llvm.memcpy.p0.p0.i64(ptr %agg.result, ptr %record, i64 40, i1 false)
+  ret void
+}
+
+define i32 @count() {
+entry:
+  %.1 = load i32, ptr @count.calls
+  %.2 = add i32 %.1, 1
+  store i32 %.2, ptr @count.calls
+  %.3 = load i32, ptr @count.calls
+  ret i32 %.3
+}
+
+define i32 @main() {
+entry:
+  %local.addr = alloca %Record, align 8
+  %result.addr = alloca %Record, align 8
+  %last.addr = alloca ptr, align 8
+  %byte.addr = alloca i8, align 1
+  %big.addr = alloca i32, align 4
+  %shift.addr = alloca i32, align 4
+  %.1 = getelementptr inbounds %Record, ptr @global, i32 0, i32 2
+  %.2 = getelementptr inbounds %Packed, ptr %.1, i64 0
+  call void @llvm.memcpy.p0.p0.i64(ptr %.2, ptr @first, i64 7, i1 false)
+  %.3 = getelementptr inbounds %Record, ptr @global, i32 0, i32 2
+  %.4 = getelementptr inbounds %Packed, ptr %.3, i64 1
+  %.5 = getelementptr inbounds %Packed, ptr %.4, i32 0, i32 0
+  store i8 98, ptr %.5
+  %.6 = getelementptr inbounds %Record, ptr @global, i32 0, i32 2
+  %.7 = getelementptr inbounds %Packed, ptr %.6, i64 1
+  %.8 = getelementptr inbounds %Packed, ptr %.7, i32 0, i32 1
+  store i32 3, ptr %.8
+  %.9 = getelementptr inbounds %Record, ptr @global, i32 0, i32 2
+  %.10 = getelementptr inbounds %Packed, ptr %.9, i64 1
+  %.11 = getelementptr inbounds %Packed, ptr %.10, i32 0, i32 2
+  store i16 4, ptr %.11
+  %.12 = getelementptr inbounds %Record, ptr @global, i32 0, i32 3
+  store i64 99, ptr %.12
+  %.13 = getelementptr inbounds %Record, ptr @global, i32 0, i32 0
+  store i8 103, ptr %.13
+  %.14 = getelementptr inbounds %Record, ptr @global, i32 0, i32 2
+  store ptr %.14, ptr @second
+  %.15 = getelementptr inbounds %Record, ptr @global, i32 0, i32 1
+  store i32 258, ptr %.15
+  call void @build(ptr sret(%Record) %result.addr, ptr byval(%Record) @global, i32 3)
+  call void @llvm.memcpy.p0.p0.i64(ptr %local.addr, ptr %result.addr, i64 40, i1 false)
+  %.16 = getelementptr inbounds %Record, ptr %local.addr, i32 0, i32 2
+  %.17 = getelementptr inbounds %Packed, ptr %.16, i64 1
+  store ptr %.17, ptr %last.addr
+  store i8 -6, ptr %byte.addr
+  store i32 -294967296, ptr %big.addr
+  store i32 -64, ptr %shift.addr
+  %.18 = getelementptr inbounds %Record, ptr %local.addr, i32 0, i32 0
+  %.19 = load i8, ptr %.18
+  %.20 = sext i8 %.19 to i32
+  %.21 = getelementptr inbounds %Record, ptr %local.addr, i32 0, i32 1
+  %.22 = load i32, ptr %.21
+  %.23 = getelementptr inbounds %Record, ptr %local.addr, i32 0, i32 2
+  %.24 = getelementptr inbounds %Packed, ptr %.23, i64 0
+  %.25 = getelementptr inbounds %Packed, ptr %.24, i32 0, i32 1
+  %.26 = load i32, ptr %.25
+  %.27 = getelementptr inbounds %Record, ptr %local.addr, i32 0, i32 3
+  %.28 = load i64, ptr %.27
+  %.29 = call i32 (ptr, ...) @printf(ptr @.str.0, i32 %.20, i32 %.22, i32 %.26, i64 %.28)
+  %.30 = load ptr, ptr %last.addr
+  %.31 = getelementptr inbounds %Packed, ptr %.30, i32 0, i32 0
+  %.32 = load i8, ptr %.31
+  %.33 = sext i8 %.32 to i32
+  %.34 = load ptr, ptr %last.addr
+  %.35 = getelementptr inbounds %Packed, ptr %.34, i64 -1
+  %.36 = getelementptr inbounds %Packed, ptr %.35, i32 0, i32 2
+  %.37 = load i16, ptr %.36
+  %.38 = sext i16 %.37 to i32
+  %.39 = load ptr, ptr %last.addr
+  %.40 = getelementptr inbounds %Record, ptr %local.addr, i32 0, i32 2
+  %.41 = ptrtoint ptr %.39 to i64
+  %.42 = ptrtoint ptr %.40 to i64
+  %.43 = sub i64 %.41, %.42
+  %.44 = sdiv exact i64 %.43, 7
+  %.45 = trunc i64 %.44 to i32
+  %.46 = call i32 (ptr, ...) @printf(ptr @.str.1, i32 %.33, i32 %.38, i32 %.45)
+  %.47 = load ptr, ptr @second
+  %.48 = getelementptr inbounds %Packed, ptr %.47, i32 0, i32 0
+  %.49 = load i8, ptr %.48
+  %.50 = sext i8 %.49 to i32
+  %.51 = getelementptr inbounds %Record, ptr @global, i32 0, i32 1
+  %.52 = getelementptr inbounds i8, ptr %.51, i64 1
+  %.53 = load i8, ptr %.52
+  %.54 = sext i8 %.53 to i32
+  %.55 = call i32 (ptr, ...) @printf(ptr @.str.2, i32 %.50, i32 %.54, i32 40)
+  %.56 = load i8, ptr %byte.addr
+  %.57 = zext i8 %.56 to i32
+  %.58 = add i32 %.57, 10
+  %.59 = trunc i32 %.58 to i8
+  store i8 %.59, ptr %byte.addr
+  %.60 = load i8, ptr %byte.addr
+  %.61 = zext i8 %.60 to i32
+  %.62 = load i32, ptr %big.addr
+  %.63 = udiv i32 %.62, 3
+  %.64 = load i32, ptr %shift.addr
+  %.65 = ashr i32 %.64, 2
+  %.66 = load ptr, ptr %last.addr
+  %.67 = icmp ne ptr %.66, null
+  br i1 %.67, label %logical.false2, label %land.rhs4
+
+logical.true1:
+  br label %logical.end3
+
+logical.false2:
+  br label %logical.end3
+
+logical.end3:
+  %.70 = phi i1 [ true, %logical.true1 ], [ false, %logical.false2 ]
+  %.71 = zext i1 %.70 to i32
+  %.72 = call i32 (ptr, ...) @printf(ptr @.str.3, i32 %.61, i32 %.63, i32 %.65, i32 %.71)
+  %.73 = call i32 @count()
+  %.74 = call i32 @count()
+  store i32 %.74, ptr %shift.addr
+  %.75 = load i32, ptr %shift.addr
+  %.76 = call i32 @count()
+  %.77 = icmp ne i32 %.76, 0
+  br i1 %.77, label %logical.true5, label %lor.rhs8
+
+land.rhs4:
+  %.68 = call i32 @count()
+  %.69 = icmp ne i32 %.68, 0
+  br i1 %.69, label %logical.true1, label %logical.false2
+
+logical.true5:
+  br label %logical.end7
+
+logical.false6:
+  br label %logical.end7
+
+logical.end7:
+  %.79 = phi i1 [ true, %logical.true5 ], [ false, %logical.false6 ]
+  %.80 = zext i1 %.79 to i32
+  %.81 = call i32 (ptr, ...) @printf(ptr @.str.4, i32 %.75, i32 %.80)
+  %.82 = getelementptr inbounds %Record, ptr %local.addr, i32 0, i32 2
+  %.83 = getelementptr inbounds %Packed, ptr %.82, i64 1
+  %.84 = getelementptr inbounds %Packed, ptr %.83, i32 0, i32 1
+  %.85 = load i32, ptr %.84
+  ret i32 %.85
+
+lor.rhs8:
+  %.78 = icmp ne i32 0, 0
+  br i1 %.78, label %logical.true5, label %logical.false6
+}
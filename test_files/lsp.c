@@ -0,0 +1,28 @@
+int printf(char *format, ...);
+
+typedef struct {
+    int x;
+    int y;
+} Point;
+
+typedef enum { RED, GREEN = 4, BLUE } Color;
+
+typedef unsigned long Size;
+
+Size count = 0;
+
+// sum of the coordinates
+int sum(Point point, int scale) {
+    count++;
+    return (point.x + point.y) * scale;
+}
+
+int main() {
+    Point point;
+    point.x = 3;
+    point.y = 4;
+    Color color = GREEN;
+    int total = sum(point, 2) + sum(point, color);
+    printf("%d %lu\n", total, count);
+    return undefined;
+}
@@ -0,0 +1,71 @@
+int printf(const char *format, ...);
+
+int collatz(int n) {
+    int steps = 0;
+    while (n != 1) {
+        if (n % 2 == 0) {
+            n = n / 2;
+        } else {
+            n = 3 * n + 1;
+        };
+        steps++;
+    };
+    return steps;
+}
+
+int logic(int a, int b) {
+    int x = a > 0 && b > 0;
+    int y = a > 0 || b > 0;
+    int z = !a;
+    return x * 100 + y * 10 + z;
+}
+
+int loops(int n) {
+    int total = 0;
+    int i;
+    for (i = 0; i < n; i++) {
+        int j = 0;
+        if (i == 3) {
+            j = i;
+        };
+        while (j < i) {
+            if (j == 5) {
+                break;
+            };
+            total += i * j;
+            j++;
+        };
+    };
+    return total;
+}
+
+int constants(void) {
+    int a = 6;
+    int b = a * 7;
+    int c = b - 40;
+    if (c == 2) {
+        return b + c;
+    };
+    return 0;
+}
+
+unsigned int hash(const char *s) {
+    unsigned int h = 5381;
+    while (*s) {
+        h = h * 33 + *s;
+        s++;
+    };
+    return h;
+}
+
+int main(void) {
+    int i;
+    int sum = 0;
+    for (i = 1; i < 10; i++) {
+        sum += collatz(i);
+    };
+    printf("%d %d %d %d\n", sum, logic(1, 0), logic(2, 3), logic(0, 0));
+    printf("%d %d\n", loops(10), constants());
+    printf("%u\n", hash("hello"));
+    return 0;
+}
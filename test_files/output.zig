@@ -1,4 +1,57 @@
+const std = @import("std");
 
+extern "c" fn printf(format: [*c]const u8, ...) c_int;
+const @"i32" = c_int;
+const Vec = extern struct { x: @"i32", y: @"i32" };
+const Mode = c_int;
+const MODE_A: c_int = 0;
+const MODE_B: c_int = 5;
+const MODE_C: c_int = 6;
+var global: @"i32" = 10;
+var data: [4]@"i32" = .{ 1, 2, 3, 4 };
+fn mul(a: @"i32", b: @"i32") @"i32" {
+    return a * b;
+}
 
+const weird_decl: @"i32" = @as(c_int, @intCast(@sizeOf(Vec) +% @sizeOf(Mode)));
+fn length(v: [*c]Vec) @"i32" {
+    return v.*.x * v.*.x + v.*.y * v.*.y;
+}
 
+pub export fn main() c_int {
+    var v: Vec = .{ .x = 3, .y = 4 };
+    const m: Mode = MODE_B;
+    const idx: @"i32" = v.x - 1;
+    const val: @"i32" = data[@intCast(idx)];
+    {
+        const val_20: c_int = mul(v.x, v.y);
+        global = val_20;
+    }
+    var a: c_int = 2;
+    const ptr: [*c]c_int = &a;
+    var b: c_int = ptr.* + 3;
+    var c: c_int = length(&v);
+    const len: c_int = 5;
+    {
+        var i_26: c_int = 0;
+        while (i_26 < len) : (i_26 += 1) {
+            _ = printf("%d\n", i_26);
+        }
+    }
+    var i: c_int = 0;
+    while (i < len) {
+        i += 2;
+    }
+    if (i < len) {
+        c += 1;
+    } else if (i > len * 2) {
+        b -= 1;
+        _ = printf("Some text\n");
+    } else {
+        c = c * 25;
+    }
+    c += a + b + val + global + m + weird_decl;
+    _ = printf("%d\n", c);
+    return 0;
+}
 
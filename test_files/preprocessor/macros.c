@@ -0,0 +1,34 @@
+#define N 16
+#define MAX(a, b) ((a) > (b) ? (a) : (b))
+#define STR(x) #x
+#define XSTR(x) STR(x)
+#define CAT(a, b) a##b
+#define LOG(fmt, ...) printf(fmt, __VA_ARGS__)
+#define SHOW(...) #__VA_ARGS__
+#define LONG_MACRO(x) \
+  (x + \
+   N)
+#define foo foo + 1
+#define f(x) x * g
+#define g f
+
+int data[N];
+
+int main() {
+  int CAT(my, var) = MAX(N, 2);
+  const char *s = STR(a + "b\n");
+  const char *n = XSTR(N);
+  const char *quote = STR('"');
+  const char *newline = STR('\n');
+  int line = __LINE__;
+  const char *file = __FILE__;
+  int version = __STDC_VERSION__;
+  LOG("%d %d", 1, 2);
+  const char *shown = SHOW(a,b,  c);
+  int l = LONG_MACRO(3);
+  int foo = 2;
+  int r = f(2)(9);
+#undef N
+  int n2 = N;
+  return 0;
+}
@@ -0,0 +1,35 @@
+# 1 "test_files/preprocessor/macros.c"
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+int data[16];
+
+int main() {
+  int myvar = ((16) > (2) ? (16) : (2));
+  const char *s = "a + \"b\\n\"";
+  const char *n = "16";
+  const char *quote = "'\"'";
+  const char *newline = "'\\n'";
+  int line = 23;
+  const char *file = "test_files/preprocessor/macros.c";
+  int version = 201710;
+  printf("%d %d", 1, 2);
+  const char *shown = "a,b, c";
+  int l = (3 +     16);
+  int foo + 1 = 2;
+  int r = 2 * f(9);
+
+  int n2 = N;
+  return 0;
+}
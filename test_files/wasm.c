@@ -0,0 +1,84 @@
+int printf(char *format, ...);
+
+typedef struct {
+    int x;
+    short y;
+    char name[8];
+} Point;
+
+Point origin = {0, 0, "origin"};
+Point *last = &origin;
+
+Point move(Point point, int dx, int dy) {
+    point.x = point.x + dx;
+    point.y = point.y + dy;
+    return point;
+}
+
+void swap(int *a, int *b) {
+    int t = *a;
+    *a = *b;
+    *b = t;
+}
+
+int find(int *values, int count, int wanted) {
+    int i;
+    for (i = 0; i < count; i++) {
+        if (values[i] == wanted) {
+            return i;
+        };
+    };
+    return -1;
+}
+
+int nested(int n) {
+    int total = 0;
+    int i;
+    int j;
+    for (i = 0; i < n; i++) {
+        for (j = 0; j < n; j++) {
+            if (j > i) {
+                break;
+            };
+            if (i % 3 == 0 && j % 2 == 1 || i == 7) {
+                total = total + i * j;
+            } else {
+                total = total - 1;
+            };
+        };
+        if (total > 500) {
+            break;
+        };
+    };
+    return total;
+}
+
+int collatz(long n) {
+    int steps = 0;
+    while (n != 1) {
+        if (n % 2 == 0) {
+            n = n / 2;
+        } else {
+            n = 3 * n + 1;
+        };
+        steps++;
+    };
+    return steps;
+}
+
+int main() {
+    int values[5] = {4, 8, 15, 16, 23};
+    int i;
+    Point point = move(origin, 3, -4);
+    point.name[0] = 'p';
+    last = &point;
+    printf("%d %d %s %s\n", last->x, last->y, last->name, origin.name);
+    swap(&values[0], &values[4]);
+    printf("%d %d %d\n", values[0], values[4], *(values + 2));
+    printf("%d %d\n", find(values, 5, 15), find(values, 5, 42));
+    for (i = 0; i < 12; i++) {
+        printf("%d ", nested(i));
+    };
+    printf("\n%d %d\n", collatz(27), collatz((long)123456 * 1000000));
+    return nested(5);
+}
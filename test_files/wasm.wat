@@ -0,0 +1,391 @@
+(module
+  (import "env" "printf" (func $printf (param i32 i32) (result i32)))
+  (memory (export "memory") 3)
+  (global $__stack_pointer (mut i32) (i32.const 65536))
+  (global (export "__heap_base") i32 (i32.const 65616))
+  (data (i32.const 65536) "\00\00\00\00\00\00\6f\72\69\67\69\6e\00\00\00\00") ;; origin
+  (data (i32.const 65552) "\00\00\01\00\00\00\00\00") ;; last
+  (data (i32.const 65560) "\25\64\20\25\64\20\25\73\20\25\73\0a\00") ;; .str.0
+  (data (i32.const 65573) "\25\64\20\25\64\20\25\64\0a\00") ;; .str.1
+  (data (i32.const 65583) "\25\64\20\25\64\0a\00") ;; .str.2
+  (data (i32.const 65590) "\25\64\20\00") ;; .str.3
+  (data (i32.const 65594) "\0a\25\64\20\25\64\0a\00") ;; .str.4
+  (func $move (export "move") (param $r0 i32) (param $r1 i32) (param $r2 i32) (param $r3 i32)
+    (local $r6 i32)
+    (local $r9 i32)
+    (local $r10 i32)
+    (local $r12 i32)
+    (local $r15 i32)
+    (local $r16 i32)
+    (local $r17 i32)
+    (local.set $r6 (i32.load (local.get $r1)))
+    (local.set $r9 (i32.add (local.get $r6) (local.get $r2)))
+    (i32.store (local.get $r1) (local.get $r9))
+    (local.set $r10 (i32.add (local.get $r1) (i32.wrap_i64 (i64.const 4))))
+    (local.set $r12 (i32.extend16_s (i32.load16_s (local.get $r10))))
+    (local.set $r15 (local.get $r12))
+    (local.set $r16 (i32.add (local.get $r15) (local.get $r3)))
+    (local.set $r17 (i32.extend16_s (local.get $r16)))
+    (i32.store16 (local.get $r10) (local.get $r17))
+    (memory.copy (local.get $r0) (local.get $r1) (i32.const 16))
+    (return)
+  )
+  (func $swap (export "swap") (param $r0 i32) (param $r1 i32)
+    (local $r7 i32)
+    (local $r12 i32)
+    (local.set $r7 (i32.load (local.get $r0)))
+    (local.set $r12 (i32.load (local.get $r1)))
+    (i32.store (local.get $r0) (local.get $r12))
+    (i32.store (local.get $r1) (local.get $r7))
+    (return)
+  )
+  (func $find (export "find") (param $r0 i32) (param $r1 i32) (param $r2 i32) (result i32)
+    (local $r11 i32)
+    (local $r16 i64)
+    (local $r17 i64)
+    (local $r18 i32)
+    (local $r19 i32)
+    (local $r22 i32)
+    (local $r27 i32)
+    (local $r28 i32)
+    (local $phi28 i32)
+    (local.set $phi28 (i32.const 0))
+    (loop $loop_bb1
+      (local.set $r28 (local.get $phi28))
+      (local.set $r11 (i32.lt_s (local.get $r28) (local.get $r1)))
+      (if (local.get $r11)
+        (then
+          (local.set $r16 (i64.extend_i32_s (local.get $r28)))
+          (local.set $r17 (i64.mul (local.get $r16) (i64.const 4)))
+          (local.set $r18 (i32.add (local.get $r0) (i32.wrap_i64 (local.get $r17))))
+          (local.set $r19 (i32.load (local.get $r18)))
+          (local.set $r22 (i32.eq (local.get $r19) (local.get $r2)))
+          (if (local.get $r22)
+            (then
+              (return (local.get $r28))
+            )
+            (else
+              (local.set $r27 (i32.add (local.get $r28) (i32.const 1)))
+              (local.set $phi28 (local.get $r27))
+              (br $loop_bb1)
+            )
+          )
+        )
+        (else
+          (return (i32.const -1))
+        )
+      )
+    )
+    (unreachable)
+  )
+  (func $nested (export "nested") (param $r0 i32) (result i32)
+    (local $r8 i32)
+    (local $r14 i32)
+    (local $r19 i32)
+    (local $r22 i32)
+    (local $r23 i32)
+    (local $r26 i32)
+    (local $r27 i32)
+    (local $r30 i32)
+    (local $r38 i32)
+    (local $r39 i32)
+    (local $r43 i32)
+    (local $r46 i32)
+    (local $r49 i32)
+    (local $r52 i32)
+    (local $r55 i32)
+    (local $r56 i32)
+    (local $r57 i32)
+    (local $r58 i32)
+    (local $r59 i32)
+    (local $r60 i32)
+    (local $phi57 i32)
+    (local $phi59 i32)
+    (local $phi56 i32)
+    (local $phi60 i32)
+    (local $phi55 i32)
+    (local $phi58 i32)
+    (local.set $phi57 (i32.const 0))
+    (local.set $phi59 (i32.const 0))
+    (loop $loop_bb1
+      (block $bb12
+        (local.set $r57 (local.get $phi57))
+        (local.set $r59 (local.get $phi59))
+        (local.set $r8 (i32.lt_s (local.get $r59) (local.get $r0)))
+        (if (local.get $r8)
+          (then
+            (local.set $phi56 (local.get $r57))
+            (local.set $phi60 (i32.const 0))
+            (loop $loop_bb2
+              (block $bb10
+                (local.set $r56 (local.get $phi56))
+                (local.set $r60 (local.get $phi60))
+                (local.set $r14 (i32.lt_s (local.get $r60) (local.get $r0)))
+                (if (local.get $r14)
+                  (then
+                    (local.set $r19 (i32.gt_s (local.get $r60) (local.get $r59)))
+                    (if (local.get $r19)
+                      (then
+                        (br $bb10)
+                      )
+                      (else
+                        (block $bb9
+                          (block $bb7
+                            (block $bb6
+                              (local.set $r22 (i32.rem_s (local.get $r59) (i32.const 3)))
+                              (local.set $r23 (i32.eq (local.get $r22) (i32.const 0)))
+                              (if (local.get $r23)
+                                (then
+                                  (local.set $r26 (i32.rem_s (local.get $r60) (i32.const 2)))
+                                  (local.set $r27 (i32.eq (local.get $r26) (i32.const 1)))
+                                  (if (local.get $r27)
+                                    (then
+                                      (br $bb7)
+                                    )
+                                    (else
+                                      (br $bb6)
+                                    )
+                                  )
+                                )
+                                (else
+                                  (br $bb6)
+                                )
+                              )
+                            )
+                            (local.set $r30 (i32.eq (local.get $r59) (i32.const 7)))
+                            (if (local.get $r30)
+                              (then
+                                (br $bb7)
+                              )
+                              (else
+                                (local.set $r43 (i32.sub (local.get $r56) (i32.const 1)))
+                                (local.set $phi55 (local.get $r43))
+                                (br $bb9)
+                              )
+                            )
+                          )
+                          (local.set $r38 (i32.mul (local.get $r59) (local.get $r60)))
+                          (local.set $r39 (i32.add (local.get $r56) (local.get $r38)))
+                          (local.set $phi55 (local.get $r39))
+                          (br $bb9)
+                        )
+                        (local.set $r55 (local.get $phi55))
+                        (local.set $r46 (i32.add (local.get $r60) (i32.const 1)))
+                        (local.set $phi56 (local.get $r55))
+                        (local.set $phi60 (local.get $r46))
+                        (br $loop_bb2)
+                      )
+                    )
+                  )
+                  (else
+                    (br $bb10)
+                  )
+                )
+              )
+              (local.set $r49 (i32.gt_s (local.get $r56) (i32.const 500)))
+              (if (local.get $r49)
+                (then
+                  (local.set $phi58 (local.get $r56))
+                  (br $bb12)
+                )
+                (else
+                  (local.set $r52 (i32.add (local.get $r59) (i32.const 1)))
+                  (local.set $phi57 (local.get $r56))
+                  (local.set $phi59 (local.get $r52))
+                  (br $loop_bb1)
+                )
+              )
+            )
+          )
+          (else
+            (local.set $phi58 (local.get $r57))
+            (br $bb12)
+          )
+        )
+      )
+      (local.set $r58 (local.get $phi58))
+      (return (local.get $r58))
+    )
+    (unreachable)
+  )
+  (func $collatz (export "collatz") (param $r0 i64) (result i32)
+    (local $r5 i32)
+    (local $r8 i64)
+    (local $r9 i32)
+    (local $r13 i64)
+    (local $r17 i64)
+    (local $r18 i64)
+    (local $r21 i32)
+    (local $r24 i64)
+    (local $r25 i64)
+    (local $r26 i32)
+    (local $phi25 i64)
+    (local $phi26 i32)
+    (local $phi24 i64)
+    (local.set $phi25 (local.get $r0))
+    (local.set $phi26 (i32.const 0))
+    (loop $loop_bb1
+      (local.set $r25 (local.get $phi25))
+      (local.set $r26 (local.get $phi26))
+      (local.set $r5 (i64.ne (local.get $r25) (i64.const 1)))
+      (if (local.get $r5)
+        (then
+          (block $bb5
+            (local.set $r8 (i64.rem_s (local.get $r25) (i64.const 2)))
+            (local.set $r9 (i64.eq (local.get $r8) (i64.const 0)))
+            (if (local.get $r9)
+              (then
+                (local.set $r13 (i64.div_s (local.get $r25) (i64.const 2)))
+                (local.set $phi24 (local.get $r13))
+                (br $bb5)
+              )
+              (else
+                (local.set $r17 (i64.mul (i64.const 3) (local.get $r25)))
+                (local.set $r18 (i64.add (local.get $r17) (i64.const 1)))
+                (local.set $phi24 (local.get $r18))
+                (br $bb5)
+              )
+            )
+          )
+          (local.set $r24 (local.get $phi24))
+          (local.set $r21 (i32.add (local.get $r26) (i32.const 1)))
+          (local.set $phi25 (local.get $r24))
+          (local.set $phi26 (local.get $r21))
+          (br $loop_bb1)
+        )
+        (else
+          (return (local.get $r26))
+        )
+      )
+    )
+    (unreachable)
+  )
+  (func $main (export "main") (result i32)
+    (local $r0 i32)
+    (local $r1 i32)
+    (local $r2 i32)
+    (local $r3 i32)
+    (local $r4 i32)
+    (local $r5 i32)
+    (local $r6 i32)
+    (local $r7 i32)
+    (local $r8 i32)
+    (local $r10 i32)
+    (local $r11 i32)
+    (local $r13 i32)
+    (local $r15 i32)
+    (local $r16 i32)
+    (local $r18 i32)
+    (local $r19 i32)
+    (local $r20 i32)
+    (local $r21 i32)
+    (local $r23 i32)
+    (local $r24 i32)
+    (local $r26 i32)
+    (local $r27 i32)
+    (local $r31 i32)
+    (local $r33 i32)
+    (local $r36 i32)
+    (local $r39 i32)
+    (local $r40 i32)
+    (local $r41 i32)
+    (local $r43 i32)
+    (local $r45 i32)
+    (local $r46 i32)
+    (local $r50 i32)
+    (local $r51 i32)
+    (local $r54 i32)
+    (local $r55 i32)
+    (local $r58 i32)
+    (local $r59 i32)
+    (local $r60 i32)
+    (local $r62 i32)
+    (local $r63 i32)
+    (local $r64 i32)
+    (local $r65 i32)
+    (local $phi65 i32)
+    (local $frame i32)
+    (local.set $frame (i32.sub (global.get $__stack_pointer) (i32.const 112)))
+    (global.set $__stack_pointer (local.get $frame))
+    (local.set $r0 (i32.add (local.get $frame) (i32.const 0)))
+    (memory.fill (local.get $r0) (i32.const 0) (i32.const 20))
+    (i32.store (local.get $r0) (i32.const 4))
+    (local.set $r1 (i32.add (local.get $r0) (i32.wrap_i64 (i64.const 4))))
+    (i32.store (local.get $r1) (i32.const 8))
+    (local.set $r2 (i32.add (local.get $r0) (i32.wrap_i64 (i64.const 8))))
+    (i32.store (local.get $r2) (i32.const 15))
+    (local.set $r3 (i32.add (local.get $r0) (i32.wrap_i64 (i64.const 12))))
+    (i32.store (local.get $r3) (i32.const 16))
+    (local.set $r4 (i32.add (local.get $r0) (i32.wrap_i64 (i64.const 16))))
+    (i32.store (local.get $r4) (i32.const 23))
+    (local.set $r5 (i32.add (local.get $frame) (i32.const 20)))
+    (local.set $r6 (i32.add (local.get $frame) (i32.const 36)))
+    (local.set $r7 (i32.const 65536))
+    (local.set $r8 (i32.add (local.get $frame) (i32.const 52)))
+    (memory.copy (local.get $r8) (local.get $r7) (i32.const 16))
+    (call $move (local.get $r6) (local.get $r8) (i32.const 3) (i32.const -4))
+    (memory.copy (local.get $r5) (local.get $r6) (i32.const 16))
+    (local.set $r10 (i32.add (local.get $r5) (i32.wrap_i64 (i64.const 6))))
+    (i32.store8 (local.get $r10) (i32.const 112))
+    (local.set $r11 (i32.const 65552))
+    (i64.store (local.get $r11) (i64.extend_i32_u (local.get $r5)))
+    (local.set $r13 (i32.const 65560))
+    (local.set $r15 (i32.load (local.get $r11)))
+    (local.set $r16 (i32.load (local.get $r15)))
+    (local.set $r18 (i32.load (local.get $r11)))
+    (local.set $r19 (i32.add (local.get $r18) (i32.wrap_i64 (i64.const 4))))
+    (local.set $r20 (i32.extend16_s (i32.load16_s (local.get $r19))))
+    (local.set $r21 (local.get $r20))
+    (local.set $r23 (i32.load (local.get $r11)))
+    (local.set $r24 (i32.add (local.get $r23) (i32.wrap_i64 (i64.const 6))))
+    (local.set $r26 (i32.add (local.get $r7) (i32.wrap_i64 (i64.const 6))))
+    (i64.store (i32.add (local.get $frame) (i32.const 72)) (i64.extend_i32_s (local.get $r16)))
+    (i64.store (i32.add (local.get $frame) (i32.const 80)) (i64.extend_i32_s (local.get $r21)))
+    (i64.store (i32.add (local.get $frame) (i32.const 88)) (i64.extend_i32_u (local.get $r24)))
+    (i64.store (i32.add (local.get $frame) (i32.const 96)) (i64.extend_i32_u (local.get $r26)))
+    (local.set $r27 (call $printf (local.get $r13) (i32.add (local.get $frame) (i32.const 72))))
+    (call $swap (local.get $r0) (local.get $r4))
+    (local.set $r31 (i32.const 65573))
+    (local.set $r33 (i32.load (local.get $r0)))
+    (local.set $r36 (i32.load (local.get $r4)))
+    (local.set $r39 (i32.load (local.get $r2)))
+    (i64.store (i32.add (local.get $frame) (i32.const 72)) (i64.extend_i32_s (local.get $r33)))
+    (i64.store (i32.add (local.get $frame) (i32.const 80)) (i64.extend_i32_s (local.get $r36)))
+    (i64.store (i32.add (local.get $frame) (i32.const 88)) (i64.extend_i32_s (local.get $r39)))
+    (local.set $r40 (call $printf (local.get $r31) (i32.add (local.get $frame) (i32.const 72))))
+    (local.set $r41 (i32.const 65583))
+    (local.set $r43 (call $find (local.get $r0) (i32.const 5) (i32.const 15)))
+    (local.set $r45 (call $find (local.get $r0) (i32.const 5) (i32.const 42)))
+    (i64.store (i32.add (local.get $frame) (i32.const 72)) (i64.extend_i32_s (local.get $r43)))
+    (i64.store (i32.add (local.get $frame) (i32.const 80)) (i64.extend_i32_s (local.get $r45)))
+    (local.set $r46 (call $printf (local.get $r41) (i32.add (local.get $frame) (i32.const 72))))
+    (local.set $phi65 (i32.const 0))
+    (loop $loop_bb1
+      (local.set $r65 (local.get $phi65))
+      (local.set $r50 (i32.lt_s (local.get $r65) (i32.const 12)))
+      (if (local.get $r50)
+        (then
+          (local.set $r51 (i32.const 65590))
+          (local.set $r54 (call $nested (local.get $r65)))
+          (i64.store (i32.add (local.get $frame) (i32.const 72)) (i64.extend_i32_s (local.get $r54)))
+          (local.set $r55 (call $printf (local.get $r51) (i32.add (local.get $frame) (i32.const 72))))
+          (local.set $r58 (i32.add (local.get $r65) (i32.const 1)))
+          (local.set $phi65 (local.get $r58))
+          (br $loop_bb1)
+        )
+        (else
+          (local.set $r59 (i32.const 65594))
+          (local.set $r60 (call $collatz (i64.const 27)))
+          (local.set $r62 (call $collatz (i64.const 123456000000)))
+          (i64.store (i32.add (local.get $frame) (i32.const 72)) (i64.extend_i32_s (local.get $r60)))
+          (i64.store (i32.add (local.get $frame) (i32.const 80)) (i64.extend_i32_s (local.get $r62)))
+          (local.set $r63 (call $printf (local.get $r59) (i32.add (local.get $frame) (i32.const 72))))
+          (local.set $r64 (call $nested (i32.const 5)))
+          (global.set $__stack_pointer (i32.add (local.get $frame) (i32.const 112)))
+          (return (local.get $r64))
+        )
+      )
+    )
+    (unreachable)
+  )
+)
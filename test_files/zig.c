@@ -0,0 +1,60 @@
+int printf(const char *format, ...);
+
+typedef int i32;
+
+typedef struct {
+  i32 x;
+  i32 y;
+} Vec;
+
+typedef enum { MODE_A, MODE_B = 5, MODE_C } Mode;
+
+static i32 global = 10;
+static i32 data[4] = {1, 2, 3, 4};
+
+i32 mul(i32 a, i32 b) { return a * b; }
+
+i32 weird_decl = sizeof(Vec) + sizeof(Mode);
+
+i32 length(Vec *v) { return v->x * v->x + v->y * v->y; }
+
+int main() {
+  Vec v = {3, 4};
+  Mode m = MODE_B;
+
+  i32 idx = v.x - 1;
+  i32 val = data[idx];
+
+  {
+    int val = mul(v.x, v.y);
+    global = val;
+  }
+
+  int a = 2;
+  int *ptr = &a;
+  int b = *ptr + 3;
+  int c = length(&v);
+
+  int len = 5;
+  for (int i = 0; i < len; i++) {
+    printf("%d\n", i);
+  }
+  int i = 0;
+  while (i < len) {
+    i += 2;
+  }
+
+  if (i < len) {
+    c++;
+  } else if (i > len * 2) {
+    b--;
+    printf("Some text\n");
+  } else {
+    c = c * 25;
+  }
+
+  c += a + b + val + global + m + weird_decl;
+  printf("%d\n", c);
+
+  return 0;
+}
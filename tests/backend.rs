@@ -0,0 +1,106 @@
+use std::{path::Path, process::Command};
+
+fn gcc(args: &[&str]) -> bool {
+    Command::new("gcc")
+        .args(args)
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// `--emit=asm` of `test_files/backend.c` assembled and linked by gcc prints the same and exits
+/// with the same code as gcc's own build of it. Skipped without gcc.
+#[test]
+fn asm_matches_gcc() {
+    if Command::new("gcc").arg("--version").output().is_err() {
+        eprintln!("gcc isn't installed, skipping");
+        return;
+    }
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let assembly = directory.join("backend.s");
+    let ours = directory.join("backend_rip");
+    let theirs = directory.join("backend_gcc");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_RIP"))
+        .args([
+            "--emit=asm",
+            "-o",
+            assembly.to_str().unwrap(),
+            "test_files/backend.c",
+        ])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(gcc(&[
+        assembly.to_str().unwrap(),
+        "-o",
+        ours.to_str().unwrap()
+    ]));
+    assert!(gcc(&[
+        "-w",
+        "test_files/backend.c",
+        "-o",
+        theirs.to_str().unwrap()
+    ]));
+
+    let ours = Command::new(&ours).output().unwrap();
+    let theirs = Command::new(&theirs).output().unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&ours.stdout),
+        String::from_utf8_lossy(&theirs.stdout)
+    );
+    assert_eq!(ours.status.code(), theirs.status.code());
+}
+
+/// `--rust` bindings of `test_files/bindings.h` compile with rustc and their layout tests pass.
+/// Skipped without rustc.
+#[test]
+fn bindings_compile() {
+    if Command::new("rustc").arg("--version").output().is_err() {
+        eprintln!("rustc isn't installed, skipping");
+        return;
+    }
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let bindings = directory.join("bindings_compile.rs");
+    let tests = directory.join("bindings_tests");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_RIP"))
+        .args([
+            "--rust",
+            bindings.to_str().unwrap(),
+            "test_files/bindings.h",
+        ])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let output = Command::new("rustc")
+        .args([
+            "--edition",
+            "2024",
+            "--test",
+            bindings.to_str().unwrap(),
+            "-o",
+            tests.to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = Command::new(&tests).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
@@ -0,0 +1,39 @@
+use rip::bytecode::{
+    Function, Op, Program,
+    vm::{self, DEFAULT_MEMORY_LIMIT, Limits},
+};
+
+/// `int main() { return 0; }` with the sizes of its frame
+fn program(locals: u32, frame_size: u32) -> Program {
+    Program {
+        constants: vec![0],
+        globals: Vec::new(),
+        functions: vec![Function {
+            name: "main".to_owned(),
+            parameters: Vec::new(),
+            locals,
+            frame_size,
+            returns: true,
+            code: vec![Op::Constant(0), Op::Return],
+        }],
+    }
+}
+
+/// A damaged file can't make the VM allocate more than the memory limit
+#[test]
+fn huge_frames() {
+    let limits = Limits {
+        instructions: 100,
+        memory: DEFAULT_MEMORY_LIMIT,
+    };
+    assert_eq!(vm::run(&program(1, 0), limits).result.unwrap(), 0);
+    for (locals, frame_size) in [(u32::MAX, 0), (0, u32::MAX), (1 << 20, 1 << 30)] {
+        let program = program(locals, frame_size);
+        let error = vm::run(&program, limits).result.unwrap_err();
+        assert!(error.to_string().contains("stack overflow"), "{error}");
+        assert_eq!(
+            Program::from_bytes(&program.to_bytes()).is_err(),
+            locals > 1 << 20 || frame_size > 1 << 30
+        );
+    }
+}
@@ -0,0 +1,46 @@
+use std::process::{Command, Output};
+
+fn rip(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_RIP"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Arguments that can't be parsed stop `rip` with a failure
+#[test]
+fn invalid_arguments() {
+    for args in [&["--bogus"][..], &["-D"], &["--rustfoo"], &["--emitir"]] {
+        let output = rip(args);
+        assert_eq!(output.status.code(), Some(1), "rip {args:?}");
+    }
+}
+
+/// A value follows its flag as the next argument or after a `=`, short flags take it glued too
+#[test]
+fn flag_values() {
+    for args in [
+        &["--emit", "ir", "test_files/interpret.c"][..],
+        &["--emit=ir", "test_files/interpret.c"],
+        &["-DLIMIT=3", "-E", "test_files/interpret.c"],
+        &["-D", "LIMIT=3", "-E", "test_files/interpret.c"],
+    ] {
+        let output = rip(args);
+        assert!(output.status.success(), "rip {args:?}");
+    }
+}
+
+/// Nothing is generated from a program with errors
+#[test]
+fn program_with_errors() {
+    let path = format!("{}/errors.zig", env!("CARGO_TARGET_TMPDIR"));
+    let _ = std::fs::remove_file(&path);
+    for file in ["test_files/lsp.c", "test_files/constants.c"] {
+        let output = rip(&[file, "--zig", &path]);
+        assert_eq!(output.status.code(), Some(1), "rip {file}");
+        assert!(
+            !std::path::Path::new(&path).exists(),
+            "{file} was translated"
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use rip::format::{BraceStyle, Config, format};
+
+/// C sources in `test_files` and the directories in it
+fn fixtures(directory: &str, files: &mut Vec<String>) {
+    for entry in std::fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            fixtures(path.to_str().unwrap(), files);
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "c" || extension == "h")
+        {
+            files.push(path.display().to_string());
+        }
+    }
+}
+
+/// Formatting what the formatter wrote changes nothing, with every brace style and with lines
+/// short enough to be broken
+#[test]
+fn idempotent() {
+    let mut files = Vec::new();
+    fixtures("test_files", &mut files);
+    files.sort();
+    for brace_style in [BraceStyle::Attach, BraceStyle::Allman, BraceStyle::Linux] {
+        for (indent_width, max_width) in [(4, 80), (2, 40)] {
+            let config = Config {
+                indent_width,
+                brace_style,
+                max_width,
+            };
+            for file in &files {
+                let source = std::fs::read_to_string(file).unwrap();
+                let once = format(&source, &config)
+                    .unwrap_or_else(|err| panic!("formatting {file}: {err:?}"));
+                let twice = format(&once, &config)
+                    .unwrap_or_else(|err| panic!("formatting {file} again: {err:?}"));
+                assert_eq!(
+                    once, twice,
+                    "{file} changed when formatted again with {config:?}"
+                );
+            }
+        }
+    }
+}
+
+/// Operators that are one token are never split, formatting already formatted code keeps them
+#[test]
+fn compound_assignments() {
+    let config = Config {
+        indent_width: 4,
+        brace_style: BraceStyle::Attach,
+        max_width: 80,
+    };
+    for operator in ["+=", "-=", "*=", "/=", "%=", "<<=", ">>=", "&=", "|=", "^="] {
+        let source =
+            format!("int main() {{\n    int a = 1;\n    a {operator} 1;\n    return a;\n}}\n");
+        let formatted = format(&source, &config)
+            .unwrap_or_else(|err| panic!("formatting '{operator}': {err:?}"));
+        assert_eq!(formatted, source, "'{operator}' was not kept as it was");
+        let packed = source.replace(&format!(" {operator} "), operator);
+        assert_eq!(format(&packed, &config).unwrap(), source);
+    }
+}
@@ -0,0 +1,279 @@
+//! The expected outputs in `test_files` that the README compares with `cmp`
+
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+fn run(binary: &str, args: &[&str], input: Option<&[u8]>) -> Output {
+    let mut child = Command::new(binary)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap_or_else(|err| panic!("running {binary}: {err}"));
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    stdin.write_all(input.unwrap_or_default()).unwrap();
+    drop(stdin);
+    child.wait_with_output().unwrap()
+}
+
+fn rip(args: &[&str]) -> Output {
+    run(env!("CARGO_BIN_EXE_RIP"), args, None)
+}
+
+fn compare(actual: &[u8], expected: &str) {
+    let expected_text = std::fs::read_to_string(expected).unwrap();
+    let actual = String::from_utf8_lossy(actual);
+    if actual != expected_text {
+        let line = actual
+            .lines()
+            .zip(expected_text.lines())
+            .position(|(actual, expected)| actual != expected)
+            .unwrap_or_else(|| actual.lines().count().min(expected_text.lines().count()));
+        panic!(
+            "output differs from {expected} at line {}:\n{actual}",
+            line + 1
+        );
+    }
+}
+
+/// stdout of `rip` with the arguments is the file
+fn stdout(args: &[&str], expected: &str) {
+    compare(&rip(args).stdout, expected);
+}
+
+/// Diagnostics that `rip` logs with the arguments, like `grep -E ': (warning|note|error|help):'`
+fn diagnostics(args: &[&str], expected: &str) {
+    let output = rip(args);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lines: String = stderr
+        .lines()
+        .filter(|line| {
+            [": warning:", ": note:", ": error:", ": help:"]
+                .iter()
+                .any(|severity| line.contains(severity))
+        })
+        .map(|line| format!("{line}\n"))
+        .collect();
+    compare(lines.as_bytes(), expected);
+}
+
+#[test]
+fn macros_i() {
+    stdout(
+        &["-E", "test_files/preprocessor/macros.c"],
+        "test_files/preprocessor/macros.i",
+    );
+}
+
+/// `-E` of a file that ends with a line continuation inside of a `#define`
+#[test]
+fn truncated_directive() {
+    let path = format!("{}/truncated_directive.c", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::write(&path, "#define SQUARE(x) \\").unwrap();
+    for args in [
+        &["-E", path.as_str()][..],
+        &["metrics", &path],
+        &["fmt", "--stdout", &path],
+    ] {
+        let output = rip(args);
+        assert!(
+            output.status.success(),
+            "rip {args:?}: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
+
+#[test]
+fn interpret_ir() {
+    stdout(
+        &["--emit=ir", "test_files/interpret.c"],
+        "test_files/interpret.ir",
+    );
+}
+
+#[test]
+fn optimize_ir() {
+    stdout(
+        &["--emit=ir", "-O", "test_files/optimize.c"],
+        "test_files/optimize.ir",
+    );
+}
+
+#[test]
+fn wasm_wat() {
+    stdout(
+        &["--emit=wat", "-O", "test_files/wasm.c"],
+        "test_files/wasm.wat",
+    );
+}
+
+#[test]
+fn llvm_ll() {
+    stdout(&["--emit=llvm", "test_files/llvm.c"], "test_files/llvm.ll");
+}
+
+#[test]
+fn layout_out() {
+    stdout(
+        &["--layout", "test_files/layout.c"],
+        "test_files/layout.out",
+    );
+    stdout(
+        &["--layout", "--target", "ilp32", "test_files/layout.c"],
+        "test_files/layout.ilp32.out",
+    );
+}
+
+#[test]
+fn output_zig() {
+    let path = format!("{}/output.zig", env!("CARGO_TARGET_TMPDIR"));
+    let output = rip(&["test_files/zig.c", "--zig", &path]);
+    assert!(output.status.success());
+    compare(&std::fs::read(&path).unwrap(), "test_files/output.zig");
+}
+
+#[test]
+fn bindings_rs() {
+    let path = format!("{}/bindings.rs", env!("CARGO_TARGET_TMPDIR"));
+    let output = rip(&["--rust", &path, "test_files/bindings.h"]);
+    assert!(output.status.success());
+    compare(&std::fs::read(&path).unwrap(), "test_files/bindings.rs");
+}
+
+/// A typedef that is denied can't be left out of the bindings of an allowed item using it
+#[test]
+fn bindings_denied_dependency() {
+    let path = format!("{}/bindings_denied.rs", env!("CARGO_TARGET_TMPDIR"));
+    let output = rip(&[
+        "--rust",
+        &path,
+        "--bindings-allow",
+        "Table",
+        "--bindings-deny",
+        "Word",
+        "test_files/bindings.h",
+    ]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("'Table' uses 'Word', which is denied")
+    );
+}
+
+#[test]
+fn bytecode_dis() {
+    stdout(
+        &["--disassemble", "-O", "test_files/bytecode.c"],
+        "test_files/bytecode.dis",
+    );
+}
+
+#[test]
+fn literals_out() {
+    stdout(
+        &["--run", "test_files/literals.c"],
+        "test_files/literals.out",
+    );
+    stdout(
+        &["--vm", "test_files/literals.c"],
+        "test_files/literals.out",
+    );
+}
+
+#[test]
+fn ast_json() {
+    stdout(
+        &["--ast-json", "test_files/lsp.c"],
+        "test_files/lsp.ast.json",
+    );
+}
+
+#[test]
+fn graphs() {
+    for kind in ["ast", "callgraph", "cfg"] {
+        stdout(
+            &[&format!("--dot={kind}"), "test_files/graphs.c"],
+            &format!("test_files/graphs.{kind}.dot"),
+        );
+    }
+    stdout(
+        &["metrics", "test_files/graphs.c"],
+        "test_files/graphs.metrics.txt",
+    );
+    for format in ["json", "csv"] {
+        stdout(
+            &["metrics", "--format", format, "test_files/graphs.c"],
+            &format!("test_files/graphs.metrics.{format}"),
+        );
+    }
+}
+
+#[test]
+fn flow_out() {
+    diagnostics(&["test_files/flow.c"], "test_files/flow.out");
+}
+
+#[test]
+fn dataflow_out() {
+    diagnostics(&["test_files/dataflow.c"], "test_files/dataflow.out");
+}
+
+#[test]
+fn lint_out() {
+    diagnostics(
+        &["test_files/lint.c", "--lint-config", "test_files/lint.toml"],
+        "test_files/lint.out",
+    );
+}
+
+#[test]
+fn format_out() {
+    stdout(
+        &["fmt", "--stdout", "test_files/format.c"],
+        "test_files/format.out",
+    );
+    stdout(
+        &[
+            "fmt",
+            "--stdout",
+            "--braces",
+            "allman",
+            "--indent",
+            "2",
+            "--max-width",
+            "60",
+            "test_files/format.c",
+        ],
+        "test_files/format.allman.out",
+    );
+    assert!(
+        rip(&["fmt", "--check", "test_files/format.out"])
+            .status
+            .success()
+    );
+    assert!(
+        rip(&[
+            "fmt",
+            "--check",
+            "--braces",
+            "allman",
+            "--indent",
+            "2",
+            "--max-width",
+            "60",
+            "test_files/format.allman.out",
+        ])
+        .status
+        .success()
+    );
+}
+
+#[test]
+fn lsp_out() {
+    let input = std::fs::read("test_files/lsp.in").unwrap();
+    let output = run(env!("CARGO_BIN_EXE_rip-lsp"), &[], Some(&input));
+    compare(&output.stdout, "test_files/lsp.out");
+}
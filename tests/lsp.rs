@@ -0,0 +1,186 @@
+use serde_json::{Value, json};
+
+/// Messages framed with `Content-Length` headers, like a client writes them
+fn frame(messages: &[Value]) -> Vec<u8> {
+    let mut input = Vec::new();
+    for message in messages {
+        let body = message.to_string();
+        input.extend(format!("Content-Length: {}\r\n\r\n{body}", body.len()).into_bytes());
+    }
+    input
+}
+
+/// Messages that the server writes back
+fn unframe(mut output: &str) -> Vec<Value> {
+    let mut messages = Vec::new();
+    while let Some((header, rest)) = output.split_once("\r\n\r\n") {
+        let length: usize = header
+            .trim_start_matches("Content-Length:")
+            .trim()
+            .parse()
+            .expect("the server writes a Content-Length header");
+        messages.push(serde_json::from_str(&rest[..length]).expect("the body is JSON"));
+        output = &rest[length..];
+    }
+    messages
+}
+
+/// Runs the server in process on the messages, until `exit` or the end of the input
+fn session(messages: &[Value]) -> Vec<Value> {
+    let mut output = Vec::new();
+    rip::lsp::serve(frame(messages).as_slice(), &mut output).expect("the session runs");
+    unframe(&String::from_utf8(output).expect("the server writes UTF-8"))
+}
+
+fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+fn position(uri: &str, line: u32, character: u32) -> Value {
+    json!({
+        "textDocument": { "uri": uri },
+        "position": { "line": line, "character": character },
+    })
+}
+
+fn open(uri: &str, text: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": {
+            "textDocument": { "uri": uri, "languageId": "c", "version": 1, "text": text },
+        },
+    })
+}
+
+/// Files cut off at every char get their diagnostics published instead of taking the server
+/// down, and the ones cut off right after an opening `(` or `{` outside of directives, where the
+/// parser runs out of tokens in the middle of an expression, get an error
+#[test]
+fn truncated_documents() {
+    for file in ["lsp.c", "flow.c", "dataflow.c", "graphs.c", "interpret.c"] {
+        let text = std::fs::read_to_string(format!("test_files/{file}")).unwrap();
+        let mut depth = 0;
+        for (end, c) in text.char_indices() {
+            let messages = session(&[open("file:///rip/truncated.c", &text[..end])]);
+            assert_eq!(
+                messages[0]["method"], "textDocument/publishDiagnostics",
+                "{file} cut off before byte {end}: {messages:#?}"
+            );
+            match c {
+                '(' | '{' => depth += 1,
+                ')' | '}' => depth -= 1,
+                _ => continue,
+            }
+            // an unbalanced macro body is fine as long as the macro isn't used
+            let line_start = text[..end].rfind('\n').map_or(0, |newline| newline + 1);
+            if depth == 0 || text[line_start..].starts_with('#') {
+                continue;
+            }
+            let prefix = &text[..end + c.len_utf8()];
+            let messages = session(&[open("file:///rip/truncated.c", prefix)]);
+            let diagnostics = &messages[0]["params"]["diagnostics"];
+            assert!(
+                diagnostics
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .any(|diagnostic| diagnostic["severity"] == 1),
+                "no error for {file} cut off at byte {end}: {diagnostics}"
+            );
+        }
+    }
+}
+
+/// Messages that can't be read get a parse error and the session goes on after them
+#[test]
+fn malformed_messages() {
+    let mut input = b"Content-Length: 9\r\n\r\n{\"jsonrpc\"".to_vec();
+    input.extend(b"Content-Type: application/json\r\n\r\n");
+    input.extend(b"Content-Length: many\r\n\r\n");
+    input.extend(frame(&[request(1, "shutdown", Value::Null)]));
+    let mut output = Vec::new();
+    rip::lsp::serve(input.as_slice(), &mut output).expect("the session runs");
+    let messages = unframe(&String::from_utf8(output).unwrap());
+    assert_eq!(messages.len(), 4, "{messages:#?}");
+    for message in &messages[..3] {
+        assert_eq!(message["id"], Value::Null);
+        assert_eq!(message["error"]["code"], -32700);
+    }
+    assert_eq!(
+        messages[3],
+        json!({ "jsonrpc": "2.0", "id": 1, "result": null })
+    );
+}
+
+/// A directive whose line continuation is the last char of the file ends there
+#[test]
+fn truncated_directive() {
+    for text in ["#define X \\", "#define SQUARE(x) \\", "int a;\n#if 1 \\"] {
+        let messages = session(&[open("file:///rip/directive.c", text)]);
+        assert_eq!(
+            messages[0]["method"], "textDocument/publishDiagnostics",
+            "{text:?}: {messages:#?}"
+        );
+    }
+}
+
+/// A client driving the server through a whole session without a process in between
+#[test]
+fn scripted_session() {
+    let uri = "file:///rip/session.c";
+    let text = "int add(int a, int b) {\n    return a + b;\n}\n\nint main() {\n    int total = add(1, 2);\n    return total;\n}\n";
+    let messages = session(&[
+        request(1, "initialize", json!({ "capabilities": {} })),
+        open(uri, text),
+        request(2, "textDocument/definition", position(uri, 5, 17)),
+        request(3, "textDocument/hover", position(uri, 6, 12)),
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didChange",
+            "params": {
+                "textDocument": { "uri": uri, "version": 2 },
+                "contentChanges": [{ "text": "int main() {\n    return missing;\n}\n" }],
+            },
+        }),
+        request(4, "shutdown", Value::Null),
+        request(5, "textDocument/hover", position(uri, 0, 0)),
+        json!({ "jsonrpc": "2.0", "method": "exit" }),
+        // nothing is read after `exit`
+        request(6, "shutdown", Value::Null),
+    ]);
+    assert_eq!(messages.len(), 7, "{messages:#?}");
+
+    assert_eq!(messages[0]["id"], 1);
+    assert_eq!(messages[0]["result"]["capabilities"]["hoverProvider"], true);
+    assert_eq!(messages[1]["method"], "textDocument/publishDiagnostics");
+    assert_eq!(messages[1]["params"]["diagnostics"], json!([]));
+
+    // `add` in `add(1, 2)` goes to its name in the definition
+    assert_eq!(
+        messages[2]["result"],
+        json!({
+            "uri": uri,
+            "range": {
+                "start": { "line": 0, "character": 4 },
+                "end": { "line": 0, "character": 7 },
+            },
+        })
+    );
+    let hover = messages[3]["result"]["contents"]["value"].as_str().unwrap();
+    assert!(hover.contains("int total"), "{hover}");
+
+    let diagnostics = &messages[4]["params"]["diagnostics"];
+    assert_eq!(diagnostics[0]["severity"], 1);
+    assert_eq!(
+        diagnostics[0]["message"],
+        "use of undeclared identifier 'missing'"
+    );
+
+    assert_eq!(
+        messages[5],
+        json!({ "jsonrpc": "2.0", "id": 4, "result": null })
+    );
+    assert_eq!(messages[6]["id"], 5);
+    assert_eq!(messages[6]["error"]["code"], -32600);
+}
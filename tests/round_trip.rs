@@ -0,0 +1,16 @@
+use std::process::Command;
+
+/// Every file in `test_files` prints as C that parses to the same tree, and its tree comes back
+/// the same from JSON
+#[test]
+fn corpus() {
+    let output = Command::new(env!("CARGO_BIN_EXE_RIP"))
+        .args(["--round-trip", "test_files"])
+        .output()
+        .expect("RIP runs");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}